@@ -0,0 +1,59 @@
+//! Benchmarks of solving a raster toolpath with and without a `CachedRobot`, run with
+//! `cargo bench --features criterion --bench cache`. Before benchmarking, how many poses of the
+//! path were warm-started is printed.
+
+use {
+    criterion::{criterion_group, criterion_main, Criterion},
+    ik_geo::{
+        cache::CachedRobot,
+        inverse_kinematics::hardcoded::setups::TwoParallelBot,
+        robot::{two_parallel_bot, IKSolver, Robot},
+    },
+    nalgebra::{Matrix3, Vector3},
+};
+
+/// Rows of 50 points 0.1 mm apart in a plane of the workspace, with the orientation of the start
+fn raster() -> Vec<(Matrix3<f64>, Vector3<f64>)> {
+    let (r, t) = two_parallel_bot().fk(&[0.3, -0.5, 0.7, 1.1, -0.4, 0.9]);
+    (0..4)
+        .flat_map(|row| (0..50).map(move |column| (row, column)))
+        .map(|(row, column)| (r, t + Vector3::new(column as f64, row as f64, 0.0) * 1e-4))
+        .collect()
+}
+
+fn cache(c: &mut Criterion) {
+    let path = raster();
+    let closed_form = two_parallel_bot();
+    let search = Robot::gen_six_dof(TwoParallelBot::get_kin());
+
+    let cached = CachedRobot::new(Robot::gen_six_dof(TwoParallelBot::get_kin()), 1024);
+    for (r, t) in &path {
+        cached.ik(*r, *t);
+    }
+    println!("gen_six_dof raster: {:?}", cached.stats());
+
+    for (name, robot) in [("two_parallel", closed_form), ("gen_six_dof", search)] {
+        let mut group = c.benchmark_group(name);
+        group.sample_size(10);
+        group.bench_function("raster", |b| {
+            b.iter(|| {
+                path.iter()
+                    .map(|(r, t)| robot.ik(*r, *t).len())
+                    .sum::<usize>()
+            })
+        });
+        let cached = CachedRobot::new(robot, 1024);
+        group.bench_function("raster_cached", |b| {
+            b.iter(|| {
+                cached.clear();
+                path.iter()
+                    .map(|(r, t)| cached.ik(*r, *t).len())
+                    .sum::<usize>()
+            })
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, cache);
+criterion_main!(benches);
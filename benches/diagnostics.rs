@@ -0,0 +1,19 @@
+//! Criterion benchmarks of every solver and hardcoded robot, run with
+//! `cargo bench --features criterion`. Each setup draws its poses from a generator seeded with 0,
+//! so the benchmarks of two releases solve the same poses.
+
+use {
+    criterion::{criterion_group, criterion_main, Criterion},
+    ik_geo::diagnostics::Diagnostics,
+};
+
+fn decompositions(c: &mut Criterion) {
+    Diagnostics::decompositions().bench(c);
+}
+
+fn hardcoded(c: &mut Criterion) {
+    Diagnostics::hardcoded().bench(c);
+}
+
+criterion_group!(benches, decompositions, hardcoded);
+criterion_main!(benches);
@@ -0,0 +1,89 @@
+//! Benchmarks of the search-based solvers with and without a reused `SolverScratch`, run with
+//! `cargo bench --features criterion --bench scratch`. Before benchmarking, the number of
+//! allocations per solve is printed, counted by the global allocator.
+
+use {
+    criterion::{criterion_group, criterion_main, Criterion},
+    ik_geo::{
+        inverse_kinematics::{auxiliary::SolverScratch, hardcoded::setups::RrcFixedQ6},
+        robot::{two_parallel_bot, IKSolver, SevenDofSweep, SweepStrategy},
+    },
+    nalgebra::{Matrix3, SVector, Vector3},
+    std::{
+        alloc::{GlobalAlloc, Layout, System},
+        sync::atomic::{AtomicUsize, Ordering},
+    },
+};
+
+/// The system allocator, counting its allocations
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// The allocations made by `f`
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    f();
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+fn pose_6dof() -> (Matrix3<f64>, Vector3<f64>) {
+    two_parallel_bot().fk(&[0.3, -0.5, 0.7, 1.1, -0.4, 0.9])
+}
+
+fn pose_7dof() -> (Matrix3<f64>, Vector3<f64>) {
+    let q = SVector::<f64, 7>::from([0.3, -0.5, 0.7, 1.1, -0.4, 0.9, 0.2]);
+    RrcFixedQ6::get_kin().forward_kinematics(&q)
+}
+
+fn scratch(c: &mut Criterion) {
+    let robot = two_parallel_bot();
+    let sweep = SevenDofSweep::rrc(SweepStrategy::Grid(36));
+    let (r, t) = pose_6dof();
+    let (r7, t7) = pose_7dof();
+
+    let mut scratch = SolverScratch::new();
+    robot.ik_with_scratch(r, t, &mut scratch);
+    sweep.solve_with_scratch(r7, t7, &mut scratch);
+    println!(
+        "two_parallel: {} allocations per ik, {} per ik_with_scratch",
+        allocations(|| drop(robot.ik(r, t))),
+        allocations(|| drop(robot.ik_with_scratch(r, t, &mut scratch))),
+    );
+    println!(
+        "rrc sweep: {} allocations per solve, {} per solve_with_scratch",
+        allocations(|| drop(sweep.solve(r7, t7))),
+        allocations(|| drop(sweep.solve_with_scratch(r7, t7, &mut scratch))),
+    );
+
+    c.bench_function("two_parallel/ik", |b| b.iter(|| robot.ik(r, t)));
+    c.bench_function("two_parallel/ik_with_scratch", |b| {
+        b.iter(|| robot.ik_with_scratch(r, t, &mut scratch))
+    });
+    c.bench_function("rrc_sweep/solve", |b| b.iter(|| sweep.solve(r7, t7)));
+    c.bench_function("rrc_sweep/solve_with_scratch", |b| {
+        b.iter(|| sweep.solve_with_scratch(r7, t7, &mut scratch))
+    });
+}
+
+criterion_group!(benches, scratch);
+criterion_main!(benches);
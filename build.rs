@@ -0,0 +1,15 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    // The gRPC service is generated from its schema with a pure Rust protobuf compiler, so no
+    // protoc is needed
+    #[cfg(feature = "service")]
+    {
+        println!("cargo:rerun-if-changed=proto/ik_geo.proto");
+        let files = protox::compile(["proto/ik_geo.proto"], ["proto"])
+            .expect("proto/ik_geo.proto is a valid schema");
+        tonic_build::configure()
+            .compile_fds(files)
+            .expect("the service code can be generated");
+    }
+}
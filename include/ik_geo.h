@@ -0,0 +1,89 @@
+/*
+ * C interface to the closed-form ik-geo solvers.
+ *
+ * Rotations are 9 doubles in row-major order and translations are 3 doubles.
+ * The IK functions write up to IK_GEO_MAX_SOLUTIONS joint vectors of 6 doubles each
+ * to out_q and their number to out_n. Least squares solutions are included: for each
+ * solution out_is_ls gets 1 if it is a least squares solution and 0 if it is exact,
+ * and out_error its residual.
+ * They return IK_GEO_TRUNCATED instead of IK_GEO_OK when a pose had more than
+ * IK_GEO_MAX_SOLUTIONS solutions and only the first of them were written.
+ * FANUC joint angles are in FANUC joint coordinates (J3 measured from the horizontal).
+ *
+ * The batch functions solve n poses, given as 4 * n doubles of quaternions with the
+ * scalar last (x, y, z, w) and 3 * n doubles of translations. They write
+ * IK_GEO_MAX_SOLUTIONS joint vectors per pose to out_q, and as many flags and
+ * residuals to out_is_ls and out_error, of which the first out_n[i] are the
+ * solutions of pose i. They return IK_GEO_INVALID_ARGUMENT when n poses are
+ * too many to address.
+ */
+
+#ifndef IK_GEO_H
+#define IK_GEO_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define IK_GEO_MAX_SOLUTIONS 8
+
+#define IK_GEO_OK 0
+#define IK_GEO_NULL_POINTER -1
+#define IK_GEO_INVALID_ARGUMENT -2
+#define IK_GEO_TRUNCATED 1
+
+int ik_geo_irb120(const double *R, const double *t, double *out_q, int *out_is_ls,
+    double *out_error, int *out_n);
+int ik_geo_irb120_fk(const double *q, double *out_R, double *out_t);
+int ik_geo_irb120_batch(size_t n, const double *quaternions, const double *translations,
+    double *out_q, int *out_is_ls, double *out_error, int *out_n);
+
+int ik_geo_irb1200(const double *R, const double *t, double *out_q, int *out_is_ls,
+    double *out_error, int *out_n);
+int ik_geo_irb1200_fk(const double *q, double *out_R, double *out_t);
+int ik_geo_irb1200_batch(size_t n, const double *quaternions, const double *translations,
+    double *out_q, int *out_is_ls, double *out_error, int *out_n);
+
+int ik_geo_irb2600(const double *R, const double *t, double *out_q, int *out_is_ls,
+    double *out_error, int *out_n);
+int ik_geo_irb2600_fk(const double *q, double *out_R, double *out_t);
+int ik_geo_irb2600_batch(size_t n, const double *quaternions, const double *translations,
+    double *out_q, int *out_is_ls, double *out_error, int *out_n);
+
+int ik_geo_irb4600(const double *R, const double *t, double *out_q, int *out_is_ls,
+    double *out_error, int *out_n);
+int ik_geo_irb4600_fk(const double *q, double *out_R, double *out_t);
+int ik_geo_irb4600_batch(size_t n, const double *quaternions, const double *translations,
+    double *out_q, int *out_is_ls, double *out_error, int *out_n);
+
+int ik_geo_irb6640(const double *R, const double *t, double *out_q, int *out_is_ls,
+    double *out_error, int *out_n);
+int ik_geo_irb6640_fk(const double *q, double *out_R, double *out_t);
+int ik_geo_irb6640_batch(size_t n, const double *quaternions, const double *translations,
+    double *out_q, int *out_is_ls, double *out_error, int *out_n);
+
+int ik_geo_fanuc_lr_mate_200id(const double *R, const double *t, double *out_q, int *out_is_ls,
+    double *out_error, int *out_n);
+int ik_geo_fanuc_lr_mate_200id_fk(const double *q, double *out_R, double *out_t);
+int ik_geo_fanuc_lr_mate_200id_batch(size_t n, const double *quaternions, const double *translations,
+    double *out_q, int *out_is_ls, double *out_error, int *out_n);
+
+int ik_geo_motoman_gp8(const double *R, const double *t, double *out_q, int *out_is_ls,
+    double *out_error, int *out_n);
+int ik_geo_motoman_gp8_fk(const double *q, double *out_R, double *out_t);
+int ik_geo_motoman_gp8_batch(size_t n, const double *quaternions, const double *translations,
+    double *out_q, int *out_is_ls, double *out_error, int *out_n);
+
+int ik_geo_ur5(const double *R, const double *t, double *out_q, int *out_is_ls,
+    double *out_error, int *out_n);
+int ik_geo_ur5_fk(const double *q, double *out_R, double *out_t);
+int ik_geo_ur5_batch(size_t n, const double *quaternions, const double *translations,
+    double *out_q, int *out_is_ls, double *out_error, int *out_n);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
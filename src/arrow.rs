@@ -0,0 +1,226 @@
+//! Reading poses from and writing solutions to Arrow record batches and Parquet files, for
+//! dataset tooling built on [Arrow](https://arrow.apache.org).
+//!
+//! A pose table has a `Float64` column for each component of the quaternions, `qw`, `qx`, `qy`
+//! and `qz`, and of the translations, `tx`, `ty` and `tz`, without nulls, and optionally a
+//! `UInt64` `pose_id` column. `pose_batch` reads the columns in place as a `PoseBatch`.
+//!
+//! A solution table has a row per solution: the `pose_id` of its pose, its `branch`, the joint
+//! values `q0` to `q5`, or `q6` for 7-DOF solutions, `is_ls` and the `residual`. Poses without a
+//! solution have no rows.
+
+use {
+    crate::{error::IkGeoError, pose::PoseBatch, robot::IKSolver, solutionset::IkSolutionSet},
+    arrow_array::{Array, ArrayRef, BooleanArray, Float64Array, RecordBatch, UInt64Array},
+    arrow_schema::{DataType, Field, Schema, SchemaRef},
+    nalgebra::UnitQuaternion,
+    parquet::arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ArrowWriter},
+    std::{fs::File, path::Path, sync::Arc},
+};
+
+/// The quaternion columns of a pose table, scalar first
+pub const QUATERNION_COLUMNS: [&str; 4] = ["qw", "qx", "qy", "qz"];
+
+/// The translation columns of a pose table
+pub const TRANSLATION_COLUMNS: [&str; 3] = ["tx", "ty", "tz"];
+
+fn arrow_error(e: impl ToString) -> IkGeoError {
+    IkGeoError::InvalidDescription(e.to_string())
+}
+
+/// The values of the column `name` of `batch`, which must be `Float64` without nulls
+fn float_column<'a>(batch: &'a RecordBatch, name: &str) -> Result<&'a [f64], IkGeoError> {
+    let column = batch
+        .column_by_name(name)
+        .ok_or_else(|| IkGeoError::InvalidDescription(format!("missing column \"{name}\"")))?;
+    let values = column
+        .as_any()
+        .downcast_ref::<Float64Array>()
+        .ok_or_else(|| {
+            IkGeoError::InvalidDescription(format!(
+                "column \"{name}\" is {}, not Float64",
+                column.data_type()
+            ))
+        })?;
+    if values.null_count() > 0 {
+        return Err(IkGeoError::InvalidDescription(format!(
+            "column \"{name}\" has nulls"
+        )));
+    }
+    Ok(values.values())
+}
+
+/// The schema of a pose table, with a `pose_id` column
+pub fn pose_schema() -> SchemaRef {
+    let fields = core::iter::once(Field::new("pose_id", DataType::UInt64, false)).chain(
+        QUATERNION_COLUMNS
+            .iter()
+            .chain(&TRANSLATION_COLUMNS)
+            .map(|name| Field::new(*name, DataType::Float64, false)),
+    );
+    Arc::new(Schema::new(fields.collect::<Vec<Field>>()))
+}
+
+/// The poses of a pose table, read in place
+pub fn pose_batch(batch: &RecordBatch) -> Result<PoseBatch<'_>, IkGeoError> {
+    let [qw, qx, qy, qz] = QUATERNION_COLUMNS;
+    let [tx, ty, tz] = TRANSLATION_COLUMNS;
+    PoseBatch::from_columns(
+        [
+            float_column(batch, qw)?,
+            float_column(batch, qx)?,
+            float_column(batch, qy)?,
+            float_column(batch, qz)?,
+        ],
+        [
+            float_column(batch, tx)?,
+            float_column(batch, ty)?,
+            float_column(batch, tz)?,
+        ],
+    )
+}
+
+/// The ids of the poses of a pose table, from its `pose_id` column, or the row numbers if it has
+/// none
+pub fn pose_ids(batch: &RecordBatch) -> Result<Vec<u64>, IkGeoError> {
+    let Some(column) = batch.column_by_name("pose_id") else {
+        return Ok((0..batch.num_rows() as u64).collect());
+    };
+    column
+        .as_any()
+        .downcast_ref::<UInt64Array>()
+        .filter(|ids| ids.null_count() == 0)
+        .map(|ids| ids.values().to_vec())
+        .ok_or_else(|| {
+            IkGeoError::InvalidDescription(String::from(
+                "column \"pose_id\" must be UInt64 without nulls",
+            ))
+        })
+}
+
+/// The pose table of the poses of `batch` with the ids `pose_ids`
+pub fn pose_table(batch: &PoseBatch, pose_ids: &[u64]) -> Result<RecordBatch, IkGeoError> {
+    let quaternions: Vec<_> = (0..batch.len()).map(|i| batch.quaternion(i)).collect();
+    let component = |f: fn(&UnitQuaternion<f64>) -> f64| -> ArrayRef {
+        Arc::new(Float64Array::from_iter_values(quaternions.iter().map(f)))
+    };
+    let translation = |k: usize| -> ArrayRef {
+        Arc::new(Float64Array::from_iter_values(
+            (0..batch.len()).map(|i| batch.translation(i)[k]),
+        ))
+    };
+    let columns = vec![
+        Arc::new(UInt64Array::from(pose_ids.to_vec())) as ArrayRef,
+        component(|q| q.w),
+        component(|q| q.i),
+        component(|q| q.j),
+        component(|q| q.k),
+        translation(0),
+        translation(1),
+        translation(2),
+    ];
+    RecordBatch::try_new(pose_schema(), columns).map_err(arrow_error)
+}
+
+/// The schema of a solution table of `N` joints
+pub fn solution_schema<const N: usize>() -> SchemaRef {
+    let mut fields = vec![
+        Field::new("pose_id", DataType::UInt64, false),
+        Field::new("branch", DataType::UInt64, false),
+    ];
+    fields.extend((0..N).map(|i| Field::new(format!("q{i}"), DataType::Float64, false)));
+    fields.push(Field::new("is_ls", DataType::Boolean, false));
+    fields.push(Field::new("residual", DataType::Float64, false));
+    Arc::new(Schema::new(fields))
+}
+
+/// The solution table of `solutions`, the solutions of the poses with the ids `pose_ids`
+pub fn solution_table<const N: usize>(
+    pose_ids: &[u64],
+    solutions: &[IkSolutionSet<N>],
+) -> Result<RecordBatch, IkGeoError> {
+    if pose_ids.len() != solutions.len() {
+        return Err(IkGeoError::DimensionMismatch {
+            expected: solutions.len(),
+            found: pose_ids.len(),
+        });
+    }
+    let rows = || {
+        pose_ids
+            .iter()
+            .zip(solutions)
+            .flat_map(|(&id, solutions)| solutions.iter().map(move |solution| (id, solution)))
+    };
+
+    let mut columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt64Array::from_iter_values(rows().map(|(id, _)| id))),
+        Arc::new(UInt64Array::from_iter_values(
+            rows().map(|(_, solution)| solution.branch as u64),
+        )),
+    ];
+    columns.extend((0..N).map(|i| -> ArrayRef {
+        Arc::new(Float64Array::from_iter_values(
+            rows().map(|(_, solution)| solution.q[i]),
+        ))
+    }));
+    columns.push(Arc::new(BooleanArray::from_iter(
+        rows().map(|(_, solution)| Some(solution.is_ls)),
+    )));
+    columns.push(Arc::new(Float64Array::from_iter_values(
+        rows().map(|(_, solution)| solution.error),
+    )));
+    RecordBatch::try_new(solution_schema::<N>(), columns).map_err(arrow_error)
+}
+
+/// Solve every pose of the pose table `batch` with `solver`, returning the solution table
+pub fn solve_record_batch<S: IKSolver + Sync>(
+    solver: &S,
+    batch: &RecordBatch,
+) -> Result<RecordBatch, IkGeoError> {
+    let poses = pose_batch(batch)?;
+    let solutions = solver.solve_pose_batch(&poses);
+    solution_table(&pose_ids(batch)?, &solutions)
+}
+
+/// The record batches of the Parquet file at `path`
+pub fn read_parquet(path: impl AsRef<Path>) -> Result<Vec<RecordBatch>, IkGeoError> {
+    let file = File::open(path).map_err(arrow_error)?;
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+        .and_then(|builder| builder.build())
+        .map_err(arrow_error)?;
+    let batches: Result<Vec<RecordBatch>, _> = reader.collect();
+    batches.map_err(arrow_error)
+}
+
+/// Write `batches`, which must share a schema, to a Parquet file at `path`
+pub fn write_parquet(path: impl AsRef<Path>, batches: &[RecordBatch]) -> Result<(), IkGeoError> {
+    let Some(first) = batches.first() else {
+        return Err(IkGeoError::InvalidDescription(String::from(
+            "a Parquet file needs at least one record batch",
+        )));
+    };
+    let file = File::create(path).map_err(arrow_error)?;
+    let mut writer = ArrowWriter::try_new(file, first.schema(), None).map_err(arrow_error)?;
+    for batch in batches {
+        writer.write(batch).map_err(arrow_error)?;
+    }
+    writer.close().map_err(arrow_error)?;
+    Ok(())
+}
+
+/// Solve every pose of the Parquet pose table at `input` with `solver`, writing the solution table
+/// to a Parquet file at `output`
+pub fn solve_parquet<S: IKSolver + Sync>(
+    solver: &S,
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+) -> Result<(), IkGeoError> {
+    let solutions = read_parquet(input)?
+        .iter()
+        .map(|batch| solve_record_batch(solver, batch))
+        .collect::<Result<Vec<_>, _>>()?;
+    if solutions.is_empty() {
+        return write_parquet(output, &[RecordBatch::new_empty(solution_schema::<6>())]);
+    }
+    write_parquet(output, &solutions)
+}
@@ -0,0 +1,189 @@
+//! Command line solver, enabled with the `cli` feature.
+//!
+//! Reads one pose per line, as comma separated values of the orientation followed by the
+//! translation, and writes one CSV row per solution with the index of its pose, the joint angles,
+//! the residual and whether it is a least squares solution.
+//!
+//! ```text
+//! ik-geo [--rotation <format>] [--robots <file>] <robot> [input] [output]
+//! ```
+//!
+//! The robot is any name `solver_by_name` accepts. With the `yaml` feature, `--robots` registers
+//! the robots of a YAML file, a list of descriptions with the layout of `schema::RobotData`, so
+//! they can be solved by their name.
+//!
+//! The orientation is given in one of the formats of `RotationFormat`, named `matrix`, `wxyz`,
+//! `xyzw`, `rpy`, `rpy_intrinsic` or `axis_angle`. The format is set with `--rotation` or by a
+//! header line `# rotation: <format>` in the input, which applies to the lines after it, and is
+//! otherwise detected from the number of values: 12 for a row-major rotation matrix as accepted by
+//! `hardcoded_setup_from_string`, and 6 for roll, pitch and yaw.
+//!
+//! The input and output default to stdin and stdout, which `-` also stands for. Blank lines and
+//! other lines starting with `#` are skipped.
+
+use {
+    ik_geo::{
+        pose::{Pose, RotationFormat},
+        registry,
+        solver::{solver_by_name, solver_names, PoseSolver},
+    },
+    std::{
+        env,
+        fs::File,
+        io::{self, BufRead, BufReader, BufWriter, Write},
+        process::ExitCode,
+    },
+};
+
+const USAGE: &str =
+    "usage: ik-geo [--rotation <format>] [--robots <file>] <robot> [input] [output]";
+
+fn open_input(path: Option<&str>) -> io::Result<Box<dyn BufRead>> {
+    match path {
+        None | Some("-") => Ok(Box::new(BufReader::new(io::stdin()))),
+        Some(path) => Ok(Box::new(BufReader::new(File::open(path)?))),
+    }
+}
+
+fn open_output(path: Option<&str>) -> io::Result<Box<dyn Write>> {
+    match path {
+        None | Some("-") => Ok(Box::new(BufWriter::new(io::stdout()))),
+        Some(path) => Ok(Box::new(BufWriter::new(File::create(path)?))),
+    }
+}
+
+/// Register every robot described in the YAML file at `path` in the global registry
+#[cfg(feature = "yaml")]
+fn register_robots(path: &str) -> Result<(), String> {
+    let yaml = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    let documents: Vec<serde_yaml::Value> =
+        serde_yaml::from_str(&yaml).map_err(|e| format!("{path}: {e}"))?;
+    let mut registry = registry::global()
+        .write()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    for document in documents {
+        let description = serde_yaml::to_string(&document).map_err(|e| e.to_string())?;
+        registry
+            .register_yaml(&description)
+            .map_err(|e| format!("{path}: {e}"))?;
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "yaml"))]
+fn register_robots(_path: &str) -> Result<(), String> {
+    Err(String::from("--robots needs the yaml feature"))
+}
+
+/// The names of the robots that can be solved, built-in and registered
+fn robot_names() -> String {
+    let registry = registry::global()
+        .read()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    let mut names: Vec<&str> = solver_names().collect();
+    names.extend(registry.names());
+    names.join(", ")
+}
+
+fn solve(
+    robot: &dyn PoseSolver,
+    mut format: Option<RotationFormat>,
+    input: impl BufRead,
+    mut output: impl Write,
+) -> Result<(), String> {
+    let joints = (1..=robot.dof())
+        .map(|i| format!("q{i}"))
+        .collect::<Vec<String>>()
+        .join(",");
+    writeln!(output, "pose,{joints},error,is_ls").map_err(|e| e.to_string())?;
+
+    let mut pose = 0;
+    for (line_number, line) in input.lines().enumerate() {
+        let line = line.map_err(|e| e.to_string())?;
+        let line = line.trim();
+        if let Some(header) = RotationFormat::from_header(line) {
+            format = Some(header.map_err(|e| format!("line {}: {e}", line_number + 1))?);
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let target =
+            Pose::parse(line, format).map_err(|e| format!("line {}: {e}", line_number + 1))?;
+
+        for solution in robot.solve(&target).iter() {
+            let q = solution
+                .q
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<String>>()
+                .join(",");
+            writeln!(output, "{pose},{q},{},{}", solution.error, solution.is_ls)
+                .map_err(|e| e.to_string())?;
+        }
+        pose += 1;
+    }
+
+    output.flush().map_err(|e| e.to_string())
+}
+
+fn main() -> ExitCode {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let mut format = None;
+    while args.first().is_some_and(|arg| arg.starts_with("--")) {
+        let Some(value) = args.get(1) else {
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        };
+        match args[0].as_str() {
+            "--rotation" => match value.parse() {
+                Ok(parsed) => format = Some(parsed),
+                Err(_) => {
+                    eprintln!(
+                        "unknown rotation format {value}, expected one of {}",
+                        RotationFormat::NAMES.join(", ")
+                    );
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--robots" => {
+                if let Err(e) = register_robots(value) {
+                    eprintln!("ik-geo: {e}");
+                    return ExitCode::FAILURE;
+                }
+            }
+            _ => {
+                eprintln!("{USAGE}");
+                return ExitCode::FAILURE;
+            }
+        }
+        args.drain(..2);
+    }
+    if args.is_empty() || args.len() > 3 {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    }
+
+    let Some(robot) = solver_by_name(&args[0]) else {
+        eprintln!(
+            "unknown robot {}, expected one of {}",
+            args[0],
+            robot_names()
+        );
+        return ExitCode::FAILURE;
+    };
+
+    let result = open_input(args.get(1).map(String::as_str))
+        .and_then(|input| Ok((input, open_output(args.get(2).map(String::as_str))?)))
+        .map_err(|e| e.to_string())
+        .and_then(|(input, output)| solve(robot.as_ref(), format, input, output));
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("ik-geo: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,297 @@
+//! Warm-starting the solvers from the solutions of nearby poses, for applications that solve
+//! millions of poses close to each other, such as raster toolpaths.
+//!
+//! A `SolutionCache` keeps the solutions of recently solved poses, keyed on the pose quantized to
+//! a grid of `translation_step` and `rotation_step`, and evicts the least recently used entry
+//! once it holds `capacity` poses. A `CachedRobot` looks every target up in its cache first. On a
+//! hit, each cached solution seeds `Kinematics::refine` toward the new target, and when every seed
+//! converges to an exact solution within the joint limits, those are returned without solving,
+//! keeping the branch and label of their seed. Otherwise the target is solved in full and the
+//! solutions are ordered like the cached ones, so the same branch keeps the same position from
+//! pose to pose.
+//!
+//! Seeds only find the branches the cached pose had, so the grid should be fine enough that the
+//! number of solutions does not change within a cell. The speedup is largest for the solvers that
+//! search, such as `gen_six_dof`, whose solve costs many times a few Gauss-Newton steps.
+
+use {
+    crate::{
+        inverse_kinematics::auxiliary::KinematicsDyn,
+        metrics::JointMetric,
+        pose::Pose,
+        robot::{IKSolver, IkSolution, IkSolutionSet, JointLimits, Robot},
+        solver::PoseSolver,
+    },
+    nalgebra::{Matrix3, Rotation3, Vector3, Vector6},
+    std::{
+        collections::{BTreeMap, HashMap},
+        sync::{Mutex, PoisonError},
+    },
+};
+
+/// A pose quantized to the grid of a `SolutionCache`: the translation and the rotation vector,
+/// each divided by its step and rounded
+pub type PoseKey = [i64; 6];
+
+/// The most Gauss-Newton steps taken from each cached solution toward the new target, whether or
+/// not `SolverConfig::refine` is set
+const WARM_START_ITERATIONS: usize = 5;
+
+/// How often a `CachedRobot` found and used the solutions of a nearby pose
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Targets solved by refining the cached solutions alone
+    pub warm_starts: usize,
+    /// Targets with cached solutions that were solved in full, ordered like the cached ones
+    pub hints: usize,
+    /// Targets without cached solutions
+    pub misses: usize,
+}
+
+/// The solutions of recently solved poses, see the `cache` module
+#[derive(Debug, Clone)]
+pub struct SolutionCache {
+    capacity: usize,
+    translation_step: f64,
+    rotation_step: f64,
+    entries: HashMap<PoseKey, (IkSolutionSet, u64)>,
+    /// The keys by the time they were last used, oldest first
+    recency: BTreeMap<u64, PoseKey>,
+    clock: u64,
+}
+
+impl SolutionCache {
+    /// A cache of up to `capacity` poses, on a grid of 1 mm and 1 mrad for kinematics in meters
+    pub fn new(capacity: usize) -> Self {
+        SolutionCache {
+            capacity,
+            translation_step: 1e-3,
+            rotation_step: 1e-3,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+
+    /// Quantize translations to `translation_step` and rotation vectors to `rotation_step`,
+    /// in radians
+    pub fn with_resolution(mut self, translation_step: f64, rotation_step: f64) -> Self {
+        self.translation_step = translation_step;
+        self.rotation_step = rotation_step;
+        self.clear();
+        self
+    }
+
+    /// The key of the pose `(r, t)`
+    pub fn key(&self, r: &Matrix3<f64>, t: &Vector3<f64>) -> PoseKey {
+        let w = Rotation3::from_matrix(r).scaled_axis();
+        let quantize = |x: f64, step: f64| (x / step).round() as i64;
+        [
+            quantize(t.x, self.translation_step),
+            quantize(t.y, self.translation_step),
+            quantize(t.z, self.translation_step),
+            quantize(w.x, self.rotation_step),
+            quantize(w.y, self.rotation_step),
+            quantize(w.z, self.rotation_step),
+        ]
+    }
+
+    /// The solutions cached under `key`, marking them as recently used
+    pub fn get(&mut self, key: &PoseKey) -> Option<&IkSolutionSet> {
+        self.clock += 1;
+        let (solutions, used) = self.entries.get_mut(key)?;
+        self.recency.remove(used);
+        *used = self.clock;
+        self.recency.insert(self.clock, *key);
+        Some(solutions)
+    }
+
+    /// Cache `solutions` under `key`, evicting the least recently used pose if the cache is full
+    pub fn insert(&mut self, key: PoseKey, solutions: IkSolutionSet) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.clock += 1;
+        if let Some((_, used)) = self.entries.insert(key, (solutions, self.clock)) {
+            self.recency.remove(&used);
+        } else if self.entries.len() > self.capacity {
+            if let Some((_, oldest)) = self.recency.pop_first() {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.recency.insert(self.clock, key);
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The number of cached poses
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+    }
+}
+
+/// A `Robot` warm-started from the solutions of nearby poses, see the `cache` module
+pub struct CachedRobot {
+    robot: Robot,
+    cache: Mutex<(SolutionCache, CacheStats)>,
+}
+
+impl CachedRobot {
+    /// Cache the solutions of up to `capacity` poses of `robot`
+    pub fn new(robot: Robot, capacity: usize) -> Self {
+        CachedRobot {
+            robot,
+            cache: Mutex::new((SolutionCache::new(capacity), CacheStats::default())),
+        }
+    }
+
+    /// Quantize poses to `translation_step` and `rotation_step`, see
+    /// `SolutionCache::with_resolution`
+    pub fn with_resolution(self, translation_step: f64, rotation_step: f64) -> Self {
+        let (cache, stats) = self
+            .cache
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        CachedRobot {
+            robot: self.robot,
+            cache: Mutex::new((
+                cache.with_resolution(translation_step, rotation_step),
+                stats,
+            )),
+        }
+    }
+
+    pub fn robot(&self) -> &Robot {
+        &self.robot
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.cache.lock().unwrap_or_else(PoisonError::into_inner).1
+    }
+
+    /// Forget every cached pose and reset the statistics
+    pub fn clear(&self) {
+        let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        cache.0.clear();
+        cache.1 = CacheStats::default();
+    }
+
+    /// The cached solutions refined toward `(rot, translation)`, if every one of them converges
+    /// to an exact solution within the joint limits
+    fn warm_start(
+        &self,
+        cached: &IkSolutionSet,
+        rot: &Matrix3<f64>,
+        translation: &Vector3<f64>,
+    ) -> Option<IkSolutionSet> {
+        let kin = self.robot.kinematics();
+        let config = self.robot.config();
+        if cached.is_empty() || cached.iter().any(|solution| solution.is_ls) {
+            return None;
+        }
+
+        cached
+            .iter()
+            .map(|seed| {
+                let q = kin.refine(&seed.q, rot, translation, WARM_START_ITERATIONS);
+                let (r_q, t_q) = kin.forward_kinematics(&q);
+                let error = config.pose_error(&r_q, &t_q, rot, translation);
+                let within_limits = self
+                    .robot
+                    .joint_limits()
+                    .is_none_or(|limits| limits.contains(&q));
+                (error <= config.ls_tolerance && within_limits).then(|| IkSolution {
+                    q,
+                    error,
+                    manipulability: config.annotate.then(|| kin.manipulability(&q)),
+                    raw: None,
+                    ..seed.clone()
+                })
+            })
+            .collect()
+    }
+}
+
+/// `solutions` ordered so that each takes the position of the cached solution nearest to it, as
+/// far as there are cached solutions, followed by the rest in their order
+fn order_like(solutions: IkSolutionSet, cached: &IkSolutionSet) -> IkSolutionSet {
+    let mut remaining: Vec<IkSolution> = solutions.into_iter().collect();
+    let mut ordered = Vec::with_capacity(remaining.len());
+    for hint in cached.iter() {
+        let nearest = remaining
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| {
+                (a.q - hint.q)
+                    .norm_squared()
+                    .total_cmp(&(b.q - hint.q).norm_squared())
+            })
+            .map(|(i, _)| i);
+        if let Some(i) = nearest {
+            ordered.push(remaining.remove(i));
+        }
+    }
+    ordered.extend(remaining);
+    ordered.into_iter().collect()
+}
+
+impl IKSolver for CachedRobot {
+    fn ik(&self, rot: Matrix3<f64>, translation: Vector3<f64>) -> IkSolutionSet {
+        let (key, cached) = {
+            let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+            let key = cache.0.key(&rot, &translation);
+            (key, cache.0.get(&key).cloned())
+        };
+
+        let warm = cached
+            .as_ref()
+            .and_then(|cached| self.warm_start(cached, &rot, &translation));
+        let (solutions, outcome) = match (warm, cached) {
+            (Some(solutions), _) => (solutions, 0),
+            (None, Some(cached)) => (order_like(self.robot.ik(rot, translation), &cached), 1),
+            (None, None) => (self.robot.ik(rot, translation), 2),
+        };
+
+        let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        match outcome {
+            0 => cache.1.warm_starts += 1,
+            1 => cache.1.hints += 1,
+            _ => cache.1.misses += 1,
+        }
+        cache.0.insert(key, solutions.clone());
+        solutions
+    }
+
+    fn joint_metric(&self) -> JointMetric {
+        self.robot.joint_metric()
+    }
+
+    fn manipulability(&self, q: &Vector6<f64>) -> Option<f64> {
+        self.robot.manipulability(q)
+    }
+
+    fn joint_limits(&self) -> Option<&JointLimits> {
+        self.robot.joint_limits()
+    }
+}
+
+impl PoseSolver for CachedRobot {
+    fn solve(&self, pose: &Pose) -> IkSolutionSet {
+        self.ik(pose.rotation, pose.translation)
+    }
+
+    fn kinematics(&self) -> &KinematicsDyn {
+        PoseSolver::kinematics(&self.robot)
+    }
+}
@@ -0,0 +1,64 @@
+//! Identified calibration errors of the joint axes and link offsets.
+//!
+//! A calibrated robot never exactly satisfies the parallel and intersecting axes its closed-form
+//! decomposition relies on, so `Robot::with_calibration` keeps solving the nominal kinematics in
+//! closed form and corrects each solution on the calibrated kinematics, see `Kinematics::refine`.
+
+use {
+    crate::inverse_kinematics::auxiliary::Kinematics,
+    nalgebra::{SMatrix, Vector3},
+};
+
+/// Corrections to the axes `h` and offsets `p` of a `Kinematics<C1, C2>`, e.g. as identified by a
+/// calibration procedure
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CalibrationSet<const C1: usize, const C2: usize> {
+    /// Added to each axis before it is normalized again
+    pub h: SMatrix<f64, 3, C1>,
+    /// Added to each offset
+    pub p: SMatrix<f64, 3, C2>,
+}
+
+impl<const C1: usize, const C2: usize> Default for CalibrationSet<C1, C2> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const C1: usize, const C2: usize> CalibrationSet<C1, C2> {
+    /// A calibration that changes nothing
+    pub fn new() -> Self {
+        CalibrationSet {
+            h: SMatrix::zeros(),
+            p: SMatrix::zeros(),
+        }
+    }
+
+    /// Correct the axis of `joint` by `delta`
+    pub fn with_axis_error(mut self, joint: usize, delta: Vector3<f64>) -> Self {
+        self.h.set_column(joint, &delta);
+        self
+    }
+
+    /// Correct the offset `p_i` before joint `i`, or the flange offset for `i = C1`, by `delta`
+    pub fn with_offset_error(mut self, i: usize, delta: Vector3<f64>) -> Self {
+        self.p.set_column(i, &delta);
+        self
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.h.iter().chain(self.p.iter()).all(|&x| x == 0.0)
+    }
+}
+
+impl<const C1: usize, const C2: usize> Kinematics<C1, C2> {
+    /// Perturb the axes and offsets by `calibration`, keeping every axis a unit vector
+    pub fn apply_calibration(&mut self, calibration: &CalibrationSet<C1, C2>) {
+        self.p += calibration.p;
+        for i in 0..C1 {
+            let axis = (self.h.column(i) + calibration.h.column(i)).normalize();
+            self.h.set_column(i, &axis);
+        }
+    }
+}
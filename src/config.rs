@@ -0,0 +1,210 @@
+use nalgebra::{convert, Matrix3, RealField, Rotation3, UnitQuaternion, Vector3};
+
+/// Tolerances used by the solvers in place of fixed constants.
+///
+/// The defaults suit kinematics given in meters. `ls_tolerance` and `subproblem2_ls_tolerance` are
+/// lengths, so they should be scaled along with the kinematics, e.g. by 1000 for millimeters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SolverConfig {
+    /// How far a subproblem may be from being solved exactly, in the length unit of the
+    /// kinematics, for its solution to still count as exact rather than least squares
+    pub ls_tolerance: f64,
+    /// `ls_tolerance` for subproblem 2, which has always been held to a stricter default than the
+    /// other subproblems, so that its solutions keep their least squares flags
+    #[cfg_attr(feature = "serde", serde(default = "default_subproblem2_ls_tolerance"))]
+    pub subproblem2_ls_tolerance: f64,
+    /// Singular values below this are treated as zero when computing pseudo-inverses
+    pub singular_tolerance: f64,
+    /// Solutions whose joint angles all differ by less than this, modulo 2π, are merged into one
+    pub dedup_tolerance: f64,
+    /// Samples per joint of the grid the 2D search starts from, used by `gen_six_dof_grid` and by
+    /// `gen_six_dof` without the `nlopt` feature. The cost of the search grows with its square.
+    #[cfg_attr(feature = "serde", serde(default = "default_search_resolution"))]
+    pub search_resolution: usize,
+    /// How the residual of a solution combines its rotation and translation errors, which also
+    /// decides the order of `IkSolutionSet::sort_by_error`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub error_metric: ErrorMetric,
+    /// The weight of the rotation error in the residual
+    #[cfg_attr(feature = "serde", serde(default = "default_weight"))]
+    pub rotation_weight: f64,
+    /// The weight of the translation error in the residual, e.g. the inverse of a length to make
+    /// it comparable to an angle
+    #[cfg_attr(feature = "serde", serde(default = "default_weight"))]
+    pub translation_weight: f64,
+    /// Whether `Robot` polishes every solution with Gauss-Newton steps after solving, see
+    /// `IkSolutionSet::refine`. This brings the residuals of exact solutions to machine precision
+    /// and can make least squares solutions exact, at the cost of a few Jacobians per solution.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub refine: bool,
+    /// The most Gauss-Newton steps taken per solution when `refine` is set
+    #[cfg_attr(feature = "serde", serde(default = "default_refine_iterations"))]
+    pub refine_iterations: usize,
+    /// What `Robot` returns for solutions at a singularity where a continuum of joint angles
+    /// reaches the pose
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub singular_policy: SingularBranchPolicy,
+    /// What `Robot` does with a target rotation that is not a rotation matrix within
+    /// `rotation_tolerance`, see `pose::validate_pose`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rotation_policy: RotationPolicy,
+    /// How far the rotation of a target may be from orthonormal with determinant 1, measured by
+    /// `pose::rotation_defect`
+    #[cfg_attr(feature = "serde", serde(default = "default_rotation_tolerance"))]
+    pub rotation_tolerance: f64,
+    /// The largest condition number of the linear systems that reduce a chain to one the
+    /// decompositions solve, such as moving offsets so two axes intersect in
+    /// `Kinematics::intersect_axes`. Reductions amplify rounding errors by their condition number,
+    /// so past this the chain is solved unreduced where possible.
+    #[cfg_attr(feature = "serde", serde(default = "default_max_condition"))]
+    pub max_condition: f64,
+    /// Whether the solvers fill in `IkSolution::manipulability` and `IkSolution::label` for every
+    /// solution. Off by default, as it costs a Jacobian per solution; without it both are `None`,
+    /// and `IkSolution::manipulability_on` and `BranchLabel::of` compute them for the solutions
+    /// that need them.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub annotate: bool,
+}
+
+/// How solutions at a singularity with a continuum of solutions are returned. Such a solution
+/// has two revolute joints whose axes lie on the same line, within `ls_tolerance`, so that
+/// turning one and turning the other back keeps the pose, see `Kinematics::coincident_axes`. The
+/// solvers return an arbitrary point of the continuum, and the branches that meet there are
+/// merged into one solution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum SingularBranchPolicy {
+    /// Return the solutions as the solver found them, with `IkSolution::degenerate` set
+    #[default]
+    FlagDegenerate,
+    /// Also return the point half a turn along the continuum, where the other branch meeting at
+    /// the singularity lies, e.g. the flipped wrist
+    ReturnBoth,
+    /// Return `samples` points evenly spaced along a full turn of the continuum in place of each
+    /// degenerate solution
+    ReturnContinuum { samples: usize },
+}
+
+/// How a target rotation that is not orthonormal with determinant 1 is handled. Rotations
+/// estimated by sensors or written with few digits are slightly off, and the solvers would
+/// otherwise solve for a pose that does not exist, returning least squares solutions or skewed
+/// angles without notice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum RotationPolicy {
+    /// Solve for the rotation as given
+    #[default]
+    Ignore,
+    /// Solve for the closest rotation matrix instead, found with an SVD
+    Project,
+    /// Fail with `IkGeoError::InvalidRotation`, or return no solutions from `IKSolver::ik`
+    Error,
+}
+
+/// The ways of measuring how far the pose a solution reaches is from the target pose
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ErrorMetric {
+    /// The Frobenius norm of the difference of the rotation matrices plus the distance between the
+    /// positions. Cheap, but the two terms have different units.
+    #[default]
+    Frobenius,
+    /// The angle of the rotation between the orientations, in radians, plus the distance between
+    /// the positions
+    QuaternionAngle,
+    /// The length of the twist between the poses: the square root of the sum of the squared
+    /// angle and the squared distance
+    SE3Distance,
+}
+
+#[cfg(feature = "serde")]
+fn default_subproblem2_ls_tolerance() -> f64 {
+    SolverConfig::default().subproblem2_ls_tolerance
+}
+
+#[cfg(feature = "serde")]
+fn default_search_resolution() -> usize {
+    SolverConfig::default().search_resolution
+}
+
+#[cfg(feature = "serde")]
+fn default_weight() -> f64 {
+    1.0
+}
+
+#[cfg(feature = "serde")]
+fn default_max_condition() -> f64 {
+    SolverConfig::default().max_condition
+}
+
+#[cfg(feature = "serde")]
+fn default_rotation_tolerance() -> f64 {
+    SolverConfig::default().rotation_tolerance
+}
+
+#[cfg(feature = "serde")]
+fn default_refine_iterations() -> usize {
+    SolverConfig::default().refine_iterations
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        SolverConfig {
+            ls_tolerance: 1e-6,
+            subproblem2_ls_tolerance: 1e-8,
+            singular_tolerance: 1e-12,
+            dedup_tolerance: 1e-9,
+            search_resolution: 64,
+            error_metric: ErrorMetric::Frobenius,
+            rotation_weight: 1.0,
+            translation_weight: 1.0,
+            refine: false,
+            refine_iterations: 5,
+            singular_policy: SingularBranchPolicy::FlagDegenerate,
+            rotation_policy: RotationPolicy::Ignore,
+            rotation_tolerance: 1e-6,
+            max_condition: 1e6,
+            annotate: false,
+        }
+    }
+}
+
+impl SolverConfig {
+    /// The residual between the pose `(r_q, t_q)` a solution reaches and the target `(r, t)`,
+    /// measured with `error_metric` and weighted by `rotation_weight` and `translation_weight`.
+    /// Generic over the scalar, so it can be differentiated with dual numbers, see
+    /// `derivatives::DifferentiableKinematics`.
+    pub fn pose_error<T: RealField + Copy>(
+        &self,
+        r_q: &Matrix3<T>,
+        t_q: &Vector3<T>,
+        r: &Matrix3<T>,
+        t: &Vector3<T>,
+    ) -> T {
+        let rotation_weight: T = convert(self.rotation_weight);
+        let distance = (t_q - t).norm() * convert(self.translation_weight);
+        let angle = || {
+            let difference = Rotation3::from_matrix_unchecked(r_q.transpose() * r);
+            UnitQuaternion::from_rotation_matrix(&difference).angle() * rotation_weight
+        };
+
+        match self.error_metric {
+            ErrorMetric::Frobenius => (r_q - r).norm() * rotation_weight + distance,
+            ErrorMetric::QuaternionAngle => angle() + distance,
+            ErrorMetric::SE3Distance => angle().hypot(distance),
+        }
+    }
+}
@@ -0,0 +1,222 @@
+//! Following one solution branch continuously along a densely sampled pose path.
+//!
+//! Unlike `trajectory::CartesianTracker`, which solves every waypoint on its own and searches all
+//! of the solutions for the least joint motion, `track` starts from a configuration and continues
+//! it from waypoint to waypoint. Each step predicts the next configuration from the last two, and
+//! takes the solution closest to the prediction, so the branch is followed straight through
+//! points where branches meet instead of being swapped for whichever solution is nearest.
+//! Along the way, other solutions coming close to the followed one are reported as bifurcations,
+//! sharp dips of the manipulability as singular crossings, and steps no solution can continue as
+//! lost branches. The manipulability is the absolute value of the determinant of the Jacobian,
+//! which changes sign linearly as the branch crosses a singularity, so a crossing shows as a `V`
+//! between waypoints where a smooth minimum of the manipulability is flat.
+
+use {
+    crate::{
+        inverse_kinematics::auxiliary::wrap_to_pi,
+        pose::Pose,
+        robot::{IKSolver, IkSolution},
+    },
+    alloc::vec::Vec,
+    nalgebra::Vector6,
+};
+
+/// Something that happened to the followed branch along the path
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ContinuationEvent {
+    /// No solution of the waypoint has a residual within the tolerance, and the branch continues
+    /// from the last reachable waypoint
+    Unreachable { waypoint: usize },
+    /// Another solution came within the bifurcation distance of the followed one, so the branches
+    /// meet here and the continuation was chosen by the prediction. Reported once for every
+    /// stretch of waypoints where the branches stay close, at the waypoint where they are closest.
+    Bifurcation { waypoint: usize, distance: f64 },
+    /// The manipulability of the followed branch has a sharp minimum at this waypoint, so the
+    /// branch crosses or touches a singularity near it
+    SingularCrossing {
+        waypoint: usize,
+        manipulability: f64,
+    },
+    /// No solution is within the largest joint step of the prediction, so the branch could not be
+    /// continued and the closest solution was taken instead
+    BranchLost { waypoint: usize, max_joint_step: f64 },
+}
+
+/// The followed branch, with one entry per waypoint
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BranchPath {
+    /// The solution of each waypoint on the branch, or `None` if it is unreachable. The joint
+    /// angles are unwrapped so that consecutive waypoints never differ by more than π in any joint.
+    pub waypoints: Vec<Option<IkSolution>>,
+    /// Events in the order of the waypoints
+    pub events: Vec<ContinuationEvent>,
+}
+
+impl BranchPath {
+    /// Whether the branch was followed through every waypoint without losing it
+    pub fn is_continuous(&self) -> bool {
+        !self.events.iter().any(|event| {
+            matches!(
+                event,
+                ContinuationEvent::Unreachable { .. } | ContinuationEvent::BranchLost { .. }
+            )
+        })
+    }
+
+    /// The joint angles of the reachable waypoints
+    pub fn q(&self) -> impl Iterator<Item = &Vector6<f64>> {
+        self.waypoints.iter().flatten().map(|solution| &solution.q)
+    }
+}
+
+/// The thresholds `track_with` follows a branch with
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ContinuationSettings {
+    /// The largest residual a solution may have for its waypoint to count as reachable
+    pub tolerance: f64,
+    /// The largest change of any joint from the prediction for a solution to continue the branch
+    pub max_joint_step: f64,
+    /// How close in any joint another solution has to come to be reported as a bifurcation
+    pub bifurcation_distance: f64,
+    /// The largest fraction of the manipulability at the larger of its neighbors the
+    /// manipulability at a local minimum may have to be reported as a singular crossing
+    pub singular_ratio: f64,
+}
+
+impl Default for ContinuationSettings {
+    fn default() -> Self {
+        ContinuationSettings {
+            tolerance: 1e-6,
+            max_joint_step: 0.5,
+            bifurcation_distance: 0.05,
+            singular_ratio: 0.5,
+        }
+    }
+}
+
+impl ContinuationSettings {
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn with_max_joint_step(mut self, max_joint_step: f64) -> Self {
+        self.max_joint_step = max_joint_step;
+        self
+    }
+
+    pub fn with_bifurcation_distance(mut self, bifurcation_distance: f64) -> Self {
+        self.bifurcation_distance = bifurcation_distance;
+        self
+    }
+
+    pub fn with_singular_ratio(mut self, singular_ratio: f64) -> Self {
+        self.singular_ratio = singular_ratio;
+        self
+    }
+}
+
+/// The largest change of any joint from `from` to `to`, taking the shorter way around
+fn max_step(from: &Vector6<f64>, to: &Vector6<f64>) -> f64 {
+    (to - from).map(wrap_to_pi).amax()
+}
+
+/// Follow the branch through `q0` along `pose_path` with the default settings
+pub fn track<S: IKSolver>(solver: &S, pose_path: &[Pose], q0: &Vector6<f64>) -> BranchPath {
+    track_with(solver, pose_path, q0, &ContinuationSettings::default())
+}
+
+/// Follow the branch through `q0` along `pose_path`. The path has to be sampled densely enough
+/// that the joints move much less than `settings.max_joint_step` between waypoints.
+pub fn track_with<S: IKSolver>(
+    solver: &S,
+    pose_path: &[Pose],
+    q0: &Vector6<f64>,
+    settings: &ContinuationSettings,
+) -> BranchPath {
+    let mut path = BranchPath::default();
+    let mut last = *q0;
+    let mut velocity = Vector6::zeros();
+
+    // The closest approach within the current stretch of bifurcation waypoints
+    let mut bifurcation: Option<(usize, f64)> = None;
+    // The manipulability of the last two reachable waypoints, the older first
+    let mut manipulability: [Option<(usize, f64)>; 2] = [None, None];
+
+    for (k, pose) in pose_path.iter().enumerate() {
+        let prediction = last + velocity;
+        let mut solutions = solver.ik_near(pose.rotation, pose.translation, &prediction);
+        solutions.retain(|solution| solution.error <= settings.tolerance);
+        let mut solutions: Vec<(f64, IkSolution)> = solutions
+            .into_iter()
+            .map(|solution| (max_step(&prediction, &solution.q), solution))
+            .collect();
+        solutions.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let Some((distance, solution)) = solutions.first().cloned() else {
+            path.events.push(ContinuationEvent::Unreachable { waypoint: k });
+            path.waypoints.push(None);
+            velocity = Vector6::zeros();
+            manipulability = [None, None];
+            continue;
+        };
+
+        if distance > settings.max_joint_step {
+            path.events.push(ContinuationEvent::BranchLost {
+                waypoint: k,
+                max_joint_step: max_step(&last, &solution.q),
+            });
+        }
+
+        let closest_other = solutions[1..]
+            .iter()
+            .map(|(_, other)| max_step(&solution.q, &other.q))
+            .min_by(f64::total_cmp)
+            .filter(|&d| d < settings.bifurcation_distance);
+        match (closest_other, bifurcation) {
+            (Some(d), Some((_, closest))) if d >= closest => {}
+            (Some(d), _) => bifurcation = Some((k, d)),
+            (None, Some((waypoint, distance))) => {
+                path.events
+                    .push(ContinuationEvent::Bifurcation { waypoint, distance });
+                bifurcation = None;
+            }
+            (None, None) => {}
+        }
+
+        // A plateau of equal values is reported at its first waypoint
+        let after = solver
+            .manipulability(&solution.q)
+            .or(solution.manipulability);
+        if let ([Some((_, before)), Some((waypoint, m))], Some(after)) = (manipulability, after) {
+            if m < before && m <= after && m < settings.singular_ratio * before.max(after) {
+                path.events.push(ContinuationEvent::SingularCrossing {
+                    waypoint,
+                    manipulability: m,
+                });
+            }
+        }
+        manipulability = [manipulability[1], after.map(|after| (k, after))];
+
+        let q = last + (solution.q - last).map(wrap_to_pi);
+        velocity = q - last;
+        last = q;
+        path.waypoints.push(Some(IkSolution { q, ..solution }));
+    }
+
+    if let Some((waypoint, distance)) = bifurcation {
+        path.events
+            .push(ContinuationEvent::Bifurcation { waypoint, distance });
+    }
+    path.events.sort_by_key(|event| match event {
+        ContinuationEvent::Unreachable { waypoint }
+        | ContinuationEvent::Bifurcation { waypoint, .. }
+        | ContinuationEvent::SingularCrossing { waypoint, .. }
+        | ContinuationEvent::BranchLost { waypoint, .. } => *waypoint,
+    });
+
+    path
+}
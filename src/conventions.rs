@@ -0,0 +1,414 @@
+//! Converting kinematics between the convention of the crate and the ones of papers and
+//! datasheets.
+//!
+//! The crate describes a chain by the axis `h[i]` of every joint and the offsets `p[i]` between
+//! the origins of consecutive joints, both in the base frame at the zero configuration, with the
+//! end of the chain unrotated at the zero configuration. Two other conventions are common:
+//!
+//! - The product of exponentials of Lynch and Park, `ProductOfExponentials`: the screw axis of
+//!   every joint at the zero configuration in the space frame, or in the tool frame for body
+//!   screws, and the home pose of the tool.
+//! - The modified Denavit-Hartenberg parameters of Craig, `DhChain`: a frame on every joint axis,
+//!   placed along the common normals of consecutive axes, and the four parameters between
+//!   consecutive frames.
+//!
+//! Converting to either convention and back gives the same forward kinematics, but not
+//! necessarily the same `p`, since a joint origin can be anywhere along its axis. The origins are
+//! placed at the intersections of consecutive axes where there are any, so the chains fall into
+//! the most specialized family they can.
+
+use {
+    crate::{
+        config::SolverConfig,
+        inverse_kinematics::auxiliary::{JointType, Kinematics},
+        screw::{self, adjoint},
+        subproblems::auxiliary::rot,
+    },
+    nalgebra::{
+        Isometry3, Matrix3, Matrix6, Rotation3, SVector, Translation3, UnitQuaternion, Vector3,
+        Vector6,
+    },
+};
+
+#[cfg(all(not(feature = "std"), target_os = "none"))]
+use num_traits::Float;
+
+/// The frame screw axes are expressed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ScrewFrame {
+    /// The fixed world frame, with `T(q) = exp(S_1 q_1) ... exp(S_n q_n) M`
+    Space,
+    /// The frame of the tool at its home pose, with `T(q) = M exp(B_1 q_1) ... exp(B_n q_n)`
+    Body,
+}
+
+/// A chain as a product of exponentials
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProductOfExponentials<const N: usize> {
+    pub frame: ScrewFrame,
+    /// The screw axis of every joint, angular part first: a unit axis `w` and `v = -w × q` for a
+    /// point `q` on the axis of a revolute joint, and `w = 0` and a unit `v` for a prismatic one
+    pub screws: [Vector6<f64>; N],
+    /// The pose of the tool at the zero configuration, `M`
+    pub home: Isometry3<f64>,
+}
+
+/// The modified Denavit-Hartenberg parameters of one joint, after Craig: the link from frame
+/// `i - 1` to frame `i` is `RotX(alpha) TransX(a) RotZ(theta) TransZ(d)`
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModifiedDh {
+    /// The length of the common normal from the previous axis to this one
+    pub a: f64,
+    /// The angle from the previous axis to this one about the common normal
+    pub alpha: f64,
+    /// The offset along this axis from the common normal to the frame of the joint
+    pub d: f64,
+    /// The angle about this axis from the previous common normal to the next one at the zero
+    /// configuration, which the joint angle adds to
+    pub theta: f64,
+}
+
+/// A chain in modified Denavit-Hartenberg parameters, with the world pose of frame 0 and the pose
+/// of the tool in the frame of the last joint
+#[derive(Debug, Clone, PartialEq)]
+pub struct DhChain<const N: usize> {
+    pub base: Isometry3<f64>,
+    pub joints: [ModifiedDh; N],
+    pub joint_types: [JointType; N],
+    pub tool: Isometry3<f64>,
+}
+
+/// Axes closer than this to parallel or to intersecting are taken as such
+fn tolerance() -> f64 {
+    SolverConfig::default().ls_tolerance
+}
+
+fn isometry(r: &Matrix3<f64>, t: &Vector3<f64>) -> Isometry3<f64> {
+    Isometry3::from_parts(
+        Translation3::from(*t),
+        UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix_unchecked(*r)),
+    )
+}
+
+fn rotation(isometry: &Isometry3<f64>) -> Matrix3<f64> {
+    isometry.rotation.to_rotation_matrix().into_inner()
+}
+
+/// The frame `RotX(alpha) TransX(a) RotZ(theta) TransZ(d)`
+fn dh_link(joint: &ModifiedDh, q: f64, joint_type: JointType) -> Isometry3<f64> {
+    let (theta, d) = match joint_type {
+        JointType::Revolute => (joint.theta + q, joint.d),
+        JointType::Prismatic => (joint.theta, joint.d + q),
+    };
+    let r = rot(&Vector3::x(), joint.alpha) * rot(&Vector3::z(), theta);
+    let t = Vector3::x() * joint.a + r * Vector3::z() * d;
+    isometry(&r, &t)
+}
+
+/// The signed angle from `from` to `to` about `axis`, all perpendicular to `axis`
+fn angle_about(from: &Vector3<f64>, to: &Vector3<f64>, axis: &Vector3<f64>) -> f64 {
+    from.cross(to).dot(axis).atan2(from.dot(to))
+}
+
+/// The points of the lines `(a, u)` and `(b, v)` closest to each other, or `None` if they are
+/// parallel
+fn closest_points(
+    a: &Vector3<f64>,
+    u: &Vector3<f64>,
+    b: &Vector3<f64>,
+    v: &Vector3<f64>,
+) -> Option<(Vector3<f64>, Vector3<f64>)> {
+    let n = u.cross(v);
+    if n.norm() < tolerance() {
+        return None;
+    }
+    let d = b - a;
+    let s = d.cross(v).dot(&n) / n.norm_squared();
+    let t = d.cross(u).dot(&n) / n.norm_squared();
+    Some((a + u * s, b + v * t))
+}
+
+/// The point of the line `(a, u)` closest to `x`
+fn project(x: &Vector3<f64>, a: &Vector3<f64>, u: &Vector3<f64>) -> Vector3<f64> {
+    a + u * (x - a).dot(u)
+}
+
+/// A unit vector perpendicular to the unit `axis`, as close as possible to `hint`
+fn perpendicular(hint: &Vector3<f64>, axis: &Vector3<f64>) -> Vector3<f64> {
+    [*hint, Vector3::x(), Vector3::y()]
+        .iter()
+        .find_map(|v| (v - axis * v.dot(axis)).try_normalize(tolerance()))
+        .unwrap()
+}
+
+impl<const N: usize> ProductOfExponentials<N> {
+    /// The pose of the tool at the joint values `q`
+    pub fn forward_kinematics(&self, q: &SVector<f64, N>) -> Isometry3<f64> {
+        let motion = self
+            .screws
+            .iter()
+            .zip(q.iter())
+            .fold(Isometry3::identity(), |t, (screw, &q)| {
+                t * Isometry3::from(screw::exp(screw, q))
+            });
+        match self.frame {
+            ScrewFrame::Space => motion * self.home,
+            ScrewFrame::Body => self.home * motion,
+        }
+    }
+
+    /// The same chain with its screws expressed in `frame`
+    pub fn to_frame(&self, frame: ScrewFrame) -> Self {
+        let adjoint = match (self.frame, frame) {
+            (ScrewFrame::Space, ScrewFrame::Body) => adjoint(&self.home.inverse().into()),
+            (ScrewFrame::Body, ScrewFrame::Space) => adjoint(&self.home.into()),
+            _ => Matrix6::identity(),
+        };
+        ProductOfExponentials {
+            frame,
+            screws: self.screws.map(|screw| adjoint * screw),
+            home: self.home,
+        }
+    }
+}
+
+impl<const N: usize> DhChain<N> {
+    /// The pose of the tool at the joint values `q`, added to `theta` for revolute joints and to
+    /// `d` for prismatic ones
+    pub fn forward_kinematics(&self, q: &SVector<f64, N>) -> Isometry3<f64> {
+        (0..N).fold(self.base, |t, i| {
+            t * dh_link(&self.joints[i], q[i], self.joint_types[i])
+        }) * self.tool
+    }
+}
+
+impl<const C1: usize, const C2: usize> Kinematics<C1, C2> {
+    /// The same chain with every axis scaled to a unit vector, as the solvers assume. The joint
+    /// values of prismatic joints are scaled by the length of their axis.
+    pub fn with_normalized_axes(mut self) -> Self {
+        for mut h in self.h.column_iter_mut() {
+            h.normalize_mut();
+        }
+        self
+    }
+
+    /// The origin of every joint at the zero configuration, in the world frame
+    fn world_origins(&self) -> [Vector3<f64>; C1] {
+        let mut origin: Vector3<f64> = self.p.column(0).into();
+        core::array::from_fn(|i| {
+            let joint = origin;
+            origin += self.p.column(i + 1);
+            self.base.transform_point(&joint.into()).coords
+        })
+    }
+
+    /// The axis of every joint at the zero configuration, in the world frame
+    fn world_axes(&self) -> [Vector3<f64>; C1] {
+        let r = rotation(&self.base);
+        core::array::from_fn(|i| r * self.h.column(i))
+    }
+
+    /// The pose of the tool at the zero configuration
+    fn home(&self) -> Isometry3<f64> {
+        let end: Vector3<f64> = self.p.column_sum();
+        self.base * isometry(&Matrix3::identity(), &end) * self.tool
+    }
+
+    /// The chain as a product of exponentials with screws in `frame`
+    pub fn to_poe(&self, frame: ScrewFrame) -> ProductOfExponentials<C1> {
+        let origins = self.world_origins();
+        let axes = self.world_axes();
+        let screws = core::array::from_fn(|i| match self.joint_types[i] {
+            JointType::Revolute => {
+                let v = origins[i].cross(&axes[i]);
+                Vector6::new(axes[i].x, axes[i].y, axes[i].z, v.x, v.y, v.z)
+            }
+            JointType::Prismatic => Vector6::new(0.0, 0.0, 0.0, axes[i].x, axes[i].y, axes[i].z),
+        });
+        ProductOfExponentials {
+            frame: ScrewFrame::Space,
+            screws,
+            home: self.home(),
+        }
+        .to_frame(frame)
+    }
+
+    /// The chain of the product of exponentials `poe`, in the world frame with no base. Screws
+    /// with a zero angular part are prismatic joints, and the pitch of the others is ignored.
+    pub fn from_poe(poe: &ProductOfExponentials<C1>) -> Self {
+        let poe = poe.to_frame(ScrewFrame::Space);
+        let mut joint_types = [JointType::Revolute; C1];
+        let lines: [(Vector3<f64>, Vector3<f64>); C1] = core::array::from_fn(|i| {
+            let w: Vector3<f64> = poe.screws[i].fixed_rows::<3>(0).into();
+            let v: Vector3<f64> = poe.screws[i].fixed_rows::<3>(3).into();
+            match w.try_normalize(tolerance()) {
+                Some(axis) => (w.cross(&v) / w.norm_squared(), axis),
+                None => {
+                    joint_types[i] = JointType::Prismatic;
+                    (Vector3::zeros(), v.normalize())
+                }
+            }
+        });
+
+        let home = (rotation(&poe.home), poe.home.translation.vector);
+        Self::from_lines(&lines, joint_types, &home)
+    }
+
+    /// The chain of joints on `lines`, each a point and a unit direction, that ends at `home`
+    /// at the zero configuration. The origin of every joint is placed where its axis meets the
+    /// next one, or else where it comes closest to the previous one. Prismatic joints have no
+    /// line, so their origins follow the previous joint.
+    fn from_lines(
+        lines: &[(Vector3<f64>, Vector3<f64>); C1],
+        joint_types: [JointType; C1],
+        home: &(Matrix3<f64>, Vector3<f64>),
+    ) -> Self {
+        let revolute = |i: usize| joint_types[i] == JointType::Revolute;
+        let mut origins = [Vector3::zeros(); C1];
+        for i in 0..C1 {
+            let (a, u) = lines[i];
+            let previous = if i == 0 {
+                Vector3::zeros()
+            } else {
+                origins[i - 1]
+            };
+            if !revolute(i) {
+                origins[i] = previous;
+                continue;
+            }
+
+            let next = (i + 1 < C1 && revolute(i + 1))
+                .then(|| closest_points(&a, &u, &lines[i + 1].0, &lines[i + 1].1))
+                .flatten()
+                .filter(|(p, q)| (p - q).norm() < tolerance());
+            let last = (i > 0 && revolute(i - 1))
+                .then(|| closest_points(&a, &u, &lines[i - 1].0, &lines[i - 1].1))
+                .flatten();
+            origins[i] = match (next, last) {
+                (Some((point, _)), _) | (None, Some((point, _))) => point,
+                (None, None) => project(&previous, &a, &u),
+            };
+        }
+
+        let mut kin = Self::new().with_joint_types(joint_types);
+        for i in 0..C1 {
+            kin.h.set_column(i, &lines[i].1);
+            let previous = if i == 0 {
+                Vector3::zeros()
+            } else {
+                origins[i - 1]
+            };
+            kin.p.set_column(i, &(origins[i] - previous));
+        }
+        let last = if C1 == 0 {
+            Vector3::zeros()
+        } else {
+            origins[C1 - 1]
+        };
+        kin.p.set_column(C1, &(home.1 - last));
+        kin.with_tool(isometry(&home.0, &Vector3::zeros()))
+    }
+
+    /// The chain in modified Denavit-Hartenberg parameters. Frame 0 is the frame of the first
+    /// joint at the zero configuration, so the parameters of the first joint are zero and its
+    /// pose is the `base` of the chain. The frames of parallel axes are placed along the previous
+    /// common normal, and the last frame has its x axis as close as possible to the previous one.
+    pub fn to_modified_dh(&self) -> DhChain<C1> {
+        let origins = self.world_origins();
+        let axes = self.world_axes().map(|h| h.normalize());
+
+        // The origin and x axis of every frame, and the length of its common normal
+        let mut frames = [(Vector3::zeros(), Vector3::x(), 0.0); C1];
+        for i in 0..C1 {
+            let z = axes[i];
+            // Where the previous common normal meets this axis
+            let start = if i == 0 {
+                project(&Vector3::zeros(), &origins[0], &z)
+            } else {
+                let (o, x, a) = frames[i - 1];
+                o + x * a
+            };
+            let hint = if i == 0 {
+                Vector3::x()
+            } else {
+                frames[i - 1].1
+            };
+
+            frames[i] = if i + 1 == C1 {
+                (start, perpendicular(&hint, &z), 0.0)
+            } else if let Some((o, o_next)) =
+                closest_points(&origins[i], &z, &origins[i + 1], &axes[i + 1])
+            {
+                let x = z.cross(&axes[i + 1]).normalize();
+                (o, x, (o_next - o).dot(&x))
+            } else {
+                let normal = origins[i + 1] - start;
+                let normal = normal - z * normal.dot(&z);
+                match normal.try_normalize(tolerance()) {
+                    Some(x) => (start, x, normal.norm()),
+                    None => (start, perpendicular(&hint, &z), 0.0),
+                }
+            };
+        }
+
+        let frame = |i: usize| {
+            let (o, x, _) = frames[i];
+            isometry(&Matrix3::from_columns(&[x, axes[i].cross(&x), axes[i]]), &o)
+        };
+        let joints = core::array::from_fn(|i| {
+            if i == 0 {
+                return ModifiedDh {
+                    a: 0.0,
+                    alpha: 0.0,
+                    d: 0.0,
+                    theta: 0.0,
+                };
+            }
+            let (o_last, x_last, a) = frames[i - 1];
+            let (o, x, _) = frames[i];
+            ModifiedDh {
+                a,
+                alpha: angle_about(&axes[i - 1], &axes[i], &x_last),
+                d: (o - (o_last + x_last * a)).dot(&axes[i]),
+                theta: angle_about(&x_last, &x, &axes[i]),
+            }
+        });
+
+        let base = if C1 == 0 {
+            Isometry3::identity()
+        } else {
+            frame(0)
+        };
+        let last = if C1 == 0 { base } else { frame(C1 - 1) };
+        DhChain {
+            base,
+            joints,
+            joint_types: self.joint_types,
+            tool: last.inverse() * self.home(),
+        }
+    }
+
+    /// The chain of the modified Denavit-Hartenberg parameters `dh`, in the world frame with no
+    /// base. Its zero configuration is the one where every joint is at its `theta` and `d`, so
+    /// the joint values are measured from them as in `DhChain::forward_kinematics`.
+    pub fn from_modified_dh(dh: &DhChain<C1>) -> Self {
+        let mut frame = dh.base;
+        let lines = core::array::from_fn(|i| {
+            frame *= dh_link(&dh.joints[i], 0.0, dh.joint_types[i]);
+            (frame.translation.vector, rotation(&frame) * Vector3::z())
+        });
+        let home = frame * dh.tool;
+
+        Self::from_lines(
+            &lines,
+            dh.joint_types,
+            &(rotation(&home), home.translation.vector),
+        )
+    }
+}
@@ -0,0 +1,147 @@
+//! Solving two arms that keep a fixed transform between their tools.
+//!
+//! When two arms carry one object, or one arm holds a part the other works on, the task fixes the
+//! pose of the slave tool relative to the master tool instead of the pose of each tool on its own.
+//! The master is solved for its target, and the slave for the target the relative transform puts
+//! in front of the master tool. Both arms must report poses in the same world frame, which
+//! `Kinematics::with_base` sets up for an arm that is not at the origin.
+
+use {
+    crate::{
+        robot::{IKSolver, IkSolution, IkSolutionSet},
+        subproblems::auxiliary::rot,
+    },
+    alloc::vec::Vec,
+    core::f64::consts::{PI, TAU},
+    nalgebra::{Matrix3, Vector3},
+};
+
+/// Configurations of the master and slave arm that together hold the relative pose of a task
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ArmPair<const M: usize = 6, const S: usize = 6> {
+    pub master: IkSolution<M>,
+    pub slave: IkSolution<S>,
+}
+
+impl<const M: usize, const S: usize> ArmPair<M, S> {
+    /// Whether neither solution is a least squares approximation
+    pub fn is_exact(&self) -> bool {
+        !self.master.is_ls && !self.slave.is_ls
+    }
+
+    /// The sum of the residuals of both solutions
+    pub fn error(&self) -> f64 {
+        self.master.error + self.slave.error
+    }
+}
+
+/// The pose of the slave tool, given the pose of the master tool and the pose of the slave tool
+/// relative to it
+pub fn relative_target(
+    master_rot: &Matrix3<f64>,
+    master_translation: &Vector3<f64>,
+    relative_rot: &Matrix3<f64>,
+    relative_translation: &Vector3<f64>,
+) -> (Matrix3<f64>, Vector3<f64>) {
+    (
+        master_rot * relative_rot,
+        master_translation + master_rot * relative_translation,
+    )
+}
+
+/// Every combination of a master and a slave solution, ordered by `sort_pairs`
+pub fn pair_solutions<const M: usize, const S: usize>(
+    master: &IkSolutionSet<M>,
+    slave: &IkSolutionSet<S>,
+) -> Vec<ArmPair<M, S>> {
+    let mut pairs: Vec<ArmPair<M, S>> = master
+        .iter()
+        .flat_map(|m| {
+            slave.iter().map(move |s| ArmPair {
+                master: m.clone(),
+                slave: s.clone(),
+            })
+        })
+        .collect();
+
+    sort_pairs(&mut pairs);
+    pairs
+}
+
+/// Order pairs with exact pairs first and then by the sum of their residuals
+pub fn sort_pairs<const M: usize, const S: usize>(pairs: &mut [ArmPair<M, S>]) {
+    pairs.sort_by(|a, b| {
+        b.is_exact()
+            .cmp(&a.is_exact())
+            .then(a.error().total_cmp(&b.error()))
+    });
+}
+
+/// Solves a master and a slave arm for a transform between their tools
+pub struct CoordinatedSolver<M: IKSolver, S: IKSolver> {
+    master: M,
+    slave: S,
+}
+
+impl<M: IKSolver, S: IKSolver> CoordinatedSolver<M, S> {
+    pub fn new(master: M, slave: S) -> Self {
+        CoordinatedSolver { master, slave }
+    }
+
+    pub fn master(&self) -> &M {
+        &self.master
+    }
+
+    pub fn slave(&self) -> &S {
+        &self.slave
+    }
+
+    /// The pairs of configurations with the master tool at `(master_rot, master_translation)` and
+    /// the slave tool at `(relative_rot, relative_translation)` in the frame of the master tool
+    pub fn solve(
+        &self,
+        master_rot: Matrix3<f64>,
+        master_translation: Vector3<f64>,
+        relative_rot: Matrix3<f64>,
+        relative_translation: Vector3<f64>,
+    ) -> Vec<ArmPair> {
+        let (slave_rot, slave_translation) = relative_target(
+            &master_rot,
+            &master_translation,
+            &relative_rot,
+            &relative_translation,
+        );
+        pair_solutions(
+            &self.master.ik(master_rot, master_translation),
+            &self.slave.ik(slave_rot, slave_translation),
+        )
+    }
+
+    /// Like `solve`, but with the master tool free to roll about its z axis, as when the master
+    /// grips a round part. `rolls` evenly spaced rolls over `[-π, π)` are searched and the slave
+    /// follows the master tool at each of them.
+    pub fn solve_with_free_roll(
+        &self,
+        master_rot: Matrix3<f64>,
+        master_translation: Vector3<f64>,
+        relative_rot: Matrix3<f64>,
+        relative_translation: Vector3<f64>,
+        rolls: usize,
+    ) -> Vec<ArmPair> {
+        let mut pairs: Vec<ArmPair> = (0..rolls)
+            .map(|i| master_rot * rot(&Vector3::z(), -PI + TAU * i as f64 / rolls as f64))
+            .flat_map(|master_rot| {
+                self.solve(
+                    master_rot,
+                    master_translation,
+                    relative_rot,
+                    relative_translation,
+                )
+            })
+            .collect();
+
+        sort_pairs(&mut pairs);
+        pairs
+    }
+}
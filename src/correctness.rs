@@ -1,27 +1,24 @@
-use crate::{
-    inverse_kinematics::{
-        hardcoded::setups::{
-            Irb6640, KukaR800FixedQ3, RrcFixedQ6, SphericalBot, ThreeParallelBot, TwoParallelBot,
-            Ur5, YumiFixedQ3,
+use {
+    crate::{
+        diagnostics::{Diagnostics, SetupStatic},
+        subproblems::setups::{
+            SetupDynamic, Subproblem1Setup, Subproblem2ExtendedSetup,
+            Subproblem2Setup, Subproblem3Setup, Subproblem4Setup, Subproblem5Setup,
+            Subproblem6Setup,
         },
-        setups::{
-            GenSixDofSetup, SetupIk, SphericalSetup, SphericalTwoIntersectingSetup,
-            SphericalTwoParallelSetup, ThreeParallelSetup, ThreeParallelTwoIntersectingSetup,
-            TwoIntersectingSetup, TwoParallelSetup,
-        },
-    },
-    subproblems::setups::{
-        SetupDynamic, SetupStatic, Subproblem1Setup, Subproblem2ExtendedSetup, Subproblem2Setup,
-        Subproblem3Setup, Subproblem4Setup, Subproblem5Setup, Subproblem6Setup,
     },
+    rand::{rngs::StdRng, SeedableRng},
 };
 
 const TEST_ITERATIONS: usize = 1000;
 
+/// Iterations per robot of the IK sweep run with the other tests. Some robots are solved by a
+/// search, so the full sweep of `TEST_ITERATIONS` is left to `run_tests_ik_full`.
+const IK_TEST_ITERATIONS: usize = 50;
+
 #[test]
 fn run_tests() {
-    // Seed fastrand
-    fastrand::seed(0);
+    let mut rng = StdRng::seed_from_u64(0);
     let setups: Vec<Box<dyn SetupDynamic>> = vec![
         Box::new(Subproblem6Setup::new()),
         Box::new(Subproblem5Setup::new()),
@@ -32,25 +29,6 @@ fn run_tests() {
         Box::new(Subproblem1Setup::new()),
     ];
 
-    let ik_setups: Vec<Box<dyn SetupIk>> = vec![
-        Box::new(SphericalTwoParallelSetup::new()),
-        Box::new(SphericalTwoIntersectingSetup::new()),
-        Box::new(SphericalSetup::new()),
-        Box::new(ThreeParallelTwoIntersectingSetup::new()),
-        Box::new(ThreeParallelSetup::new()),
-        Box::new(TwoParallelSetup::new()),
-        Box::new(TwoIntersectingSetup::new()),
-        Box::new(GenSixDofSetup::new()),
-        Box::new(Irb6640::new()),
-        Box::new(KukaR800FixedQ3::new()),
-        Box::new(RrcFixedQ6::new()),
-        Box::new(Ur5::new()),
-        Box::new(ThreeParallelBot::new()),
-        Box::new(TwoParallelBot::new()),
-        Box::new(SphericalBot::new()),
-        Box::new(YumiFixedQ3::new()),
-    ];
-
     for mut setup in setups {
         let mut total_error = 0.0;
         let mut nan_count = 0;
@@ -58,7 +36,7 @@ fn run_tests() {
         let mut num_iterations = 0;
 
         for _ in 0..TEST_ITERATIONS {
-            setup.setup();
+            setup.setup_with_rng(&mut rng);
             let is_ls = setup.run_report_info();
 
             if is_ls {
@@ -85,53 +63,26 @@ fn run_tests() {
         println!("\t% NaN:\t{nan_percent:.2}");
         println!("\t% LS:\t{ls_percent:.2}");
     }
+}
 
-    for mut setup in ik_setups {
-        let mut total_error = 0.0;
-        let mut num_q_ls = 0;
-        let mut num_all_ls = 0;
-        let mut total_q_count = 0;
-        let mut nan_count = 0;
-        let mut num_iterations = 0;
-
-        for _ in 0..TEST_ITERATIONS {
-            setup.setup();
-            setup.run();
-
-            let error = setup.error();
-            let n_ls = setup.ls_count();
-            let n_sol = setup.solution_count();
-
-            if error.is_nan() {
-                nan_count += 1;
-            } else {
-                // assert!(error <= ERROR_THRESHOLD, "{} error was at: {:.2e}", setup.name(), error);
-                total_error += error;
-                num_iterations += 1;
-
-                num_q_ls += n_ls;
-                total_q_count += n_sol;
-                if n_ls == n_sol {
-                    num_all_ls += 1
-                }
-            }
-        }
-
-        let avg_err = total_error / (num_iterations - num_all_ls) as f64;
-        let nan_percent = nan_count as f64 * 100.0 / TEST_ITERATIONS as f64;
-        let ls_percent = num_q_ls as f64 * 100.0 / total_q_count as f64;
-        let all_ls_percent = num_all_ls as f64 * 100.0 / TEST_ITERATIONS as f64;
+#[test]
+fn run_tests_ik() {
+    for stats in Diagnostics::builtin().run(IK_TEST_ITERATIONS) {
+        println!("{stats}");
+    }
+}
 
-        println!("{}", setup.name());
-        println!("\tAvg min Error:\t{avg_err:.2e}");
-        println!("\t% NaN:\t{nan_percent:.2}");
-        println!("\t% LS:\t{ls_percent:.2}");
-        println!("\t% All LS:\t{all_ls_percent:.2}");
+#[test]
+#[ignore = "takes minutes, run with `cargo test --release -- --ignored`"]
+fn run_tests_ik_full() {
+    for stats in Diagnostics::builtin().run(TEST_ITERATIONS) {
+        println!("{stats}");
     }
 }
 
 #[test]
 fn run_tests_ls() {
+    let mut rng = StdRng::seed_from_u64(0);
     let setups: Vec<Box<dyn SetupDynamic>> = vec![
         Box::new(Subproblem1Setup::new()),
         Box::new(Subproblem2Setup::new()),
@@ -141,7 +92,7 @@ fn run_tests_ls() {
 
     for mut setup in setups {
         for _ in 0..TEST_ITERATIONS {
-            setup.setup_ls();
+            setup.setup_ls_with_rng(&mut rng);
             setup.run();
 
             assert!(
@@ -152,3 +103,19 @@ fn run_tests_ls() {
         }
     }
 }
+
+#[test]
+fn run_tests_from_str() {
+    use core::f64::consts::FRAC_PI_2;
+
+    // A quarter turn about z takes x to y
+    let mut setup = Subproblem1Setup::new();
+    setup.setup_from_str("1,0,0,0,0,1,0,1,0").unwrap();
+    setup.run();
+    let theta: f64 = setup.write_output().parse().unwrap();
+    assert!((theta - FRAC_PI_2).abs() < 1e-12);
+    assert!(setup.error() < 1e-12);
+
+    assert!(setup.setup_from_str("1,0,0").is_err());
+    assert!(Subproblem4Setup::new().setup_from_str("1,0,0,0,0,1,0,1,0,x").is_err());
+}
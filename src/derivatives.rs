@@ -0,0 +1,87 @@
+//! Derivatives of the solutions of a robot with respect to the pose they reach, for sensitivity
+//! analysis and for using the solvers inside differentiable pipelines.
+//!
+//! Near an exact solution `q` of the pose `(R, t)` that is not singular, each branch of a
+//! closed-form solver is a smooth function of the pose. Differentiating
+//! `forward_kinematics(q(R, t)) = (R, t)` gives `J(q) dq = twist`, so the derivative of the
+//! branch is the inverse of the Jacobian, without differentiating through the subproblems or
+//! taking finite differences. The twist follows the convention of `Kinematics::jacobian`: the
+//! angular velocity `w` with `dR = [w]x R` in the first three entries and the velocity of the tool
+//! center point `dt` in the last three, both in the world frame.
+//!
+//! `DifferentiableKinematics` goes the other way, exposing the forward kinematics and the pose
+//! error generic over the scalar, so an autodiff scalar such as the dual numbers of
+//! [num-dual](https://docs.rs/num-dual) can backpropagate through them in an optimization.
+
+use {
+    crate::{
+        config::SolverConfig,
+        inverse_kinematics::auxiliary::Kinematics,
+        robot::{IKSolver, IkSolution, Robot},
+    },
+    alloc::vec::Vec,
+    nalgebra::{Matrix3, Matrix6, RealField, SVector, Vector3, Vector6},
+};
+
+/// A solution with the derivative of its joint angles with respect to the pose
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolutionDerivative {
+    pub solution: IkSolution,
+    /// `dq / d(twist)`, or `None` for least squares solutions and at singularities, where the
+    /// joint angles do not depend smoothly on the pose
+    pub dq_dpose: Option<Matrix6<f64>>,
+}
+
+impl SolutionDerivative {
+    /// The joint velocity that moves the tool with `twist`, to first order
+    pub fn joint_velocity(&self, twist: &Vector6<f64>) -> Option<Vector6<f64>> {
+        self.dq_dpose.map(|dq_dpose| dq_dpose * twist)
+    }
+}
+
+impl Robot {
+    /// The solutions of `ik` with the derivative of each with respect to the pose
+    pub fn solve_with_derivatives(
+        &self,
+        rot: Matrix3<f64>,
+        translation: Vector3<f64>,
+    ) -> Vec<SolutionDerivative> {
+        self.ik(rot, translation)
+            .into_iter()
+            .map(|solution| {
+                let dq_dpose = if solution.is_ls {
+                    None
+                } else {
+                    self.kinematics().jacobian(&solution.q).try_inverse()
+                };
+                SolutionDerivative { solution, dq_dpose }
+            })
+            .collect()
+    }
+}
+
+/// Forward kinematics and pose error for any scalar, e.g. a dual number type
+pub trait DifferentiableKinematics<const N: usize> {
+    /// The pose of the tool in the world frame at the joint values `q`
+    fn fk<T: RealField + Copy>(&self, q: &SVector<T, N>) -> (Matrix3<T>, Vector3<T>);
+
+    /// `SolverConfig::pose_error` between the pose reached at `q` and the target `(r, t)`. The
+    /// norms of the error metrics are not differentiable where they vanish, so at an exact
+    /// solution the derivative is not defined.
+    fn pose_error<T: RealField + Copy>(
+        &self,
+        q: &SVector<T, N>,
+        r: &Matrix3<f64>,
+        t: &Vector3<f64>,
+        config: &SolverConfig,
+    ) -> T {
+        let (r_q, t_q) = self.fk(q);
+        config.pose_error(&r_q, &t_q, &r.cast(), &t.cast())
+    }
+}
+
+impl<const C1: usize, const C2: usize> DifferentiableKinematics<C1> for Kinematics<C1, C2> {
+    fn fk<T: RealField + Copy>(&self, q: &SVector<T, C1>) -> (Matrix3<T>, Vector3<T>) {
+        self.cast::<T>().forward_kinematics(q)
+    }
+}
@@ -0,0 +1,905 @@
+//! Accuracy and timing statistics of the solvers, for the hardcoded robots and for your own.
+//!
+//! Each solver is wrapped in a `SetupIk`, which generates a random reachable pose, solves it and
+//! measures the residual of its solutions. `measure` runs a setup for a number of iterations and
+//! collects the same `Stats` the correctness tests of the crate report. The poses are drawn from
+//! an explicit `rand` generator, so the statistics of a seed are reproducible. `RobotSetup` wraps any
+//! `Robot`, so a user-defined chain can be registered in a `Diagnostics` next to the built-in
+//! setups:
+//!
+//! ```ignore
+//! let mut diagnostics = Diagnostics::builtin();
+//! diagnostics.register(RobotSetup::new("my_robot", robot));
+//! for stats in diagnostics.run(1000) {
+//!     println!("{stats}");
+//! }
+//! ```
+//!
+//! A robot that is not a `Robot`, e.g. one with a solver of your own, joins the same statistics by
+//! implementing `SetupIk`: drawing a target, solving it and describing the solutions. Besides the
+//! mean residual, `Stats` count the least squares solutions and keep a histogram of the residuals
+//! by decade in an `ErrorHistogram`.
+//!
+//! `Diagnostics::registered` measures every robot of a `RobotRegistry` the same way.
+//!
+//! `Diagnostics::time` collects the distribution of the solve times of each setup as `Timing`s
+//! instead, which serialize with the `serde` feature so the timings of one release can be kept
+//! and compared with those of the next by `regressions`. With the `criterion` feature,
+//! `bench_setup` and `Diagnostics::bench` time the setups with criterion.
+//!
+//! `accuracy_sweep` measures the accuracy of any `PoseSolver` on random reachable poses as an
+//! `AccuracyReport`: percentiles of the residual, the fraction of poses solved exactly, the mean
+//! number of branches and the worst poses, kept to solve again.
+//!
+//! `fk_probe` moves every joint of a chain on its own and reports how the tool moves, to compare
+//! with the physical robot when a model solves but moves wrong.
+//!
+//! `report` describes a single solve in Markdown or HTML, e.g. to attach to a support ticket or a
+//! failing test: the target pose and every branch with its joint angles, residual, distance from
+//! singularity, label and joint limit violations.
+
+pub use crate::inverse_kinematics::setups::{SetupIk, SetupStatic};
+
+use {
+    crate::{
+        error::IkGeoError,
+        inverse_kinematics::{
+            auxiliary::{JointType, Kinematics},
+            hardcoded::setups::{
+                hardcoded_setup_from_string, FanucLrMate200id, Irb120, Irb1200, Irb2600, Irb4600,
+                Irb6640, KinovaGen3FixedQ3, KukaR800FixedQ3, MotomanGp8, PandaFixedQ4, RrcFixedQ6,
+                SawyerFixedQ3, SphericalBot, ThreeParallelBot, TwoParallelBot, Ur5, YumiFixedQ3,
+            },
+            setups::{
+                ik_write_output, GenSixDofSetup, SphericalSetup, SphericalTwoIntersectingSetup,
+                SphericalTwoParallelSetup, ThreeParallelSetup, ThreeParallelTwoIntersectingSetup,
+                TwoIntersectingAtBaseSetup, TwoIntersectingSetup, TwoParallelSetup,
+            },
+        },
+        labels::BranchLabel,
+        pose::Pose,
+        registry::RobotRegistry,
+        robot::{IKSolver, IkSolutionSet, OutputFormat, Robot},
+        solver::PoseSolver,
+        subproblems::auxiliary::random_angle,
+    },
+    core::{f64::consts::TAU, fmt, fmt::Write},
+    nalgebra::{DVector, Matrix3, Rotation3, SVector, Vector3, Vector6},
+    rand::{rngs::StdRng, Rng, RngCore, SeedableRng},
+    std::time::{Duration, Instant},
+};
+
+/// The number of solves of each setup before `time_setup` starts timing
+pub const WARM_UP: usize = 10;
+
+/// The number of bins of an `ErrorHistogram`, one per decade from 1e-16 to 1
+pub const ERROR_BINS: usize = 16;
+
+/// How the smallest residuals of a setup are spread over the decades, see `Stats::error_histogram`.
+/// Displays the counts of the bins holding any residual, by the lower edges of the bins.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ErrorHistogram {
+    /// The number of residuals in each bin. Bin `i` holds the residuals from `10^(i - 16)` up to
+    /// `10^(i - 15)`, except that the first also holds smaller residuals and the last larger ones.
+    pub counts: [usize; ERROR_BINS],
+}
+
+impl ErrorHistogram {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The bin of `error`, which must not be NaN
+    pub fn bin(error: f64) -> usize {
+        let decade = error.log10().floor() + ERROR_BINS as f64;
+        decade.clamp(0.0, (ERROR_BINS - 1) as f64) as usize
+    }
+
+    /// The smallest residual of bin `i`, apart from the first bin, which starts at 0
+    pub fn lower_edge(i: usize) -> f64 {
+        10f64.powi(i as i32 - ERROR_BINS as i32)
+    }
+
+    pub fn record(&mut self, error: f64) {
+        self.counts[Self::bin(error)] += 1;
+    }
+
+    /// The number of residuals recorded
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// The number of residuals below `10^(i - 16)`, the lower edge of bin `i`
+    pub fn count_below(&self, i: usize) -> usize {
+        self.counts[..i.min(ERROR_BINS)].iter().sum()
+    }
+}
+
+impl fmt::Display for ErrorHistogram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bins: Vec<String> = (0..ERROR_BINS)
+            .filter(|&i| self.counts[i] > 0)
+            .map(|i| format!("{:.0e}: {}", Self::lower_edge(i), self.counts[i]))
+            .collect();
+        write!(f, "{}", bins.join(", "))
+    }
+}
+
+/// Statistics of a setup over a number of random poses
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    pub name: &'static str,
+    pub iterations: usize,
+    /// The mean over the poses with an exact solution of the smallest residual of their solutions
+    pub avg_error: f64,
+    /// The percentage of poses without any solution
+    pub nan_percent: f64,
+    /// The percentage of solutions that are least squares
+    pub ls_percent: f64,
+    /// The percentage of poses whose solutions are all least squares
+    pub all_ls_percent: f64,
+    /// The number of solutions over the poses with any solution
+    pub solutions: usize,
+    /// The number of those solutions that are least squares
+    pub ls_solutions: usize,
+    /// The smallest residual of the solutions of every pose with any solution, least squares or
+    /// not
+    pub error_histogram: ErrorHistogram,
+    /// The mean time of a solve, not counting the setup of the pose
+    pub avg_time: Duration,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.name)?;
+        writeln!(f, "\tAvg min Error:\t{:.2e}", self.avg_error)?;
+        writeln!(f, "\t% NaN:\t{:.2}", self.nan_percent)?;
+        writeln!(f, "\t% LS:\t{:.2}", self.ls_percent)?;
+        writeln!(f, "\t% All LS:\t{:.2}", self.all_ls_percent)?;
+        writeln!(
+            f,
+            "\tLS solutions:\t{}/{}",
+            self.ls_solutions, self.solutions
+        )?;
+        writeln!(f, "\tMin Errors:\t{}", self.error_histogram)?;
+        write!(f, "\tAvg Time:\t{:.2?}", self.avg_time)
+    }
+}
+
+/// The distribution of the solve times of a setup over a number of random poses
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Timing {
+    pub name: String,
+    pub iterations: usize,
+    pub mean: Duration,
+    pub min: Duration,
+    pub median: Duration,
+    /// The time 95% of the solves take at most
+    pub p95: Duration,
+    pub max: Duration,
+}
+
+impl Timing {
+    /// The statistics of the solve times `samples`
+    pub fn from_samples(name: &str, samples: &[Duration]) -> Self {
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        let quantile = |p: f64| quantile(&sorted, p);
+
+        Timing {
+            name: name.to_string(),
+            iterations: samples.len(),
+            mean: samples.iter().sum::<Duration>() / samples.len().max(1) as u32,
+            min: quantile(0.0),
+            median: quantile(0.5),
+            p95: quantile(0.95),
+            max: quantile(1.0),
+        }
+    }
+
+    /// The median time relative to that of `baseline`, above 1 when `self` is slower
+    pub fn ratio_to(&self, baseline: &Timing) -> f64 {
+        self.median.as_secs_f64() / baseline.median.as_secs_f64()
+    }
+}
+
+impl fmt::Display for Timing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: mean {:.2?}, min {:.2?}, median {:.2?}, p95 {:.2?}, max {:.2?} ({} solves)",
+            self.name, self.mean, self.min, self.median, self.p95, self.max, self.iterations
+        )
+    }
+}
+
+/// The value a fraction `p` of the way through `sorted`, or the default when it is empty
+fn quantile<T: Copy + Default>(sorted: &[T], p: f64) -> T {
+    let i = (p * sorted.len().saturating_sub(1) as f64).round() as usize;
+    sorted.get(i).copied().unwrap_or_default()
+}
+
+/// The timings of `current` whose median is more than `1 + tolerance` times that of the timing
+/// with the same name in `baseline`, with their ratios. Timings missing from `baseline` are
+/// skipped.
+pub fn regressions<'a>(
+    current: &'a [Timing],
+    baseline: &[Timing],
+    tolerance: f64,
+) -> Vec<(&'a Timing, f64)> {
+    current
+        .iter()
+        .filter_map(|timing| {
+            let before = baseline.iter().find(|before| before.name == timing.name)?;
+            let ratio = timing.ratio_to(before);
+            (ratio > 1.0 + tolerance).then_some((timing, ratio))
+        })
+        .collect()
+}
+
+/// Time `setup` on `iterations` random poses drawn from `rng`, after `WARM_UP` untimed solves
+pub fn time_setup(setup: &mut dyn SetupIk, iterations: usize, rng: &mut dyn RngCore) -> Timing {
+    for _ in 0..WARM_UP {
+        setup.setup_with_rng(rng);
+        setup.run();
+    }
+
+    let samples: Vec<Duration> = (0..iterations)
+        .map(|_| {
+            setup.setup_with_rng(rng);
+            let start = Instant::now();
+            setup.run();
+            start.elapsed()
+        })
+        .collect();
+
+    Timing::from_samples(setup.name(), &samples)
+}
+
+/// Run `setup` on `iterations` random poses drawn from `rng` and collect its statistics
+pub fn measure(setup: &mut dyn SetupIk, iterations: usize, rng: &mut dyn RngCore) -> Stats {
+    let mut total_error = 0.0;
+    let mut num_q_ls = 0;
+    let mut num_all_ls = 0;
+    let mut total_q_count = 0;
+    let mut nan_count = 0;
+    let mut num_iterations = 0;
+    let mut total_time = Duration::ZERO;
+    let mut error_histogram = ErrorHistogram::new();
+
+    for _ in 0..iterations {
+        setup.setup_with_rng(rng);
+        let start = Instant::now();
+        setup.run();
+        total_time += start.elapsed();
+
+        let error = setup.error();
+        let n_ls = setup.ls_count();
+        let n_sol = setup.solution_count();
+
+        if error.is_nan() {
+            nan_count += 1;
+        } else {
+            error_histogram.record(error);
+            num_iterations += 1;
+            num_q_ls += n_ls;
+            total_q_count += n_sol;
+            if n_ls == n_sol {
+                num_all_ls += 1
+            } else {
+                total_error += error;
+            }
+        }
+    }
+
+    Stats {
+        name: setup.name(),
+        iterations,
+        avg_error: total_error / (num_iterations - num_all_ls) as f64,
+        nan_percent: nan_count as f64 * 100.0 / iterations as f64,
+        ls_percent: num_q_ls as f64 * 100.0 / total_q_count as f64,
+        all_ls_percent: num_all_ls as f64 * 100.0 / iterations as f64,
+        solutions: total_q_count,
+        ls_solutions: num_q_ls,
+        error_histogram,
+        avg_time: total_time / iterations.max(1) as u32,
+    }
+}
+
+/// The number of poses with the largest residuals `accuracy_sweep` keeps
+pub const WORST_CASES: usize = 5;
+
+/// A pose of an `accuracy_sweep` with one of the largest residuals, to solve again when
+/// investigating it
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WorstCase {
+    /// The joint values the pose was reached at
+    pub q: Vec<f64>,
+    pub pose: Pose,
+    /// The smallest residual of the solutions of the pose, infinite when there were none
+    pub residual: f64,
+}
+
+/// The accuracy of a solver over random reachable poses, see `accuracy_sweep`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AccuracyReport {
+    pub poses: usize,
+    /// The percentiles of the smallest residual of the solutions of each pose, infinite for the
+    /// poses without any solution
+    pub min: f64,
+    pub median: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub max: f64,
+    /// The fraction of poses with at least one solution that is not least squares
+    pub exact_fraction: f64,
+    /// The mean number of solutions of a pose
+    pub avg_branches: f64,
+    /// The poses with the largest residuals, largest first
+    pub worst: Vec<WorstCase>,
+}
+
+impl fmt::Display for AccuracyReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} poses", self.poses)?;
+        writeln!(
+            f,
+            "\tResidual:\tmin {:.2e}, median {:.2e}, p95 {:.2e}, p99 {:.2e}, max {:.2e}",
+            self.min, self.median, self.p95, self.p99, self.max
+        )?;
+        writeln!(f, "\t% Exact:\t{:.2}", self.exact_fraction * 100.0)?;
+        write!(f, "\tAvg Branches:\t{:.2}", self.avg_branches)?;
+        for case in &self.worst {
+            write!(f, "\n\tWorst:\t{:.2e} at q = {:?}", case.residual, case.q)?;
+        }
+        Ok(())
+    }
+}
+
+/// Solve `n` poses reached at random joint values drawn from `rng` with `solver`, and measure
+/// the residuals of the solutions with the forward kinematics of `PoseSolver::kinematics`. Revolute
+/// joints are drawn from (-π, π) and prismatic joints from (-1, 1). The `WORST_CASES` poses with
+/// the largest residuals are kept with their joint values, so a failure found with one seed can be
+/// solved again on its own.
+pub fn accuracy_sweep(solver: &dyn PoseSolver, n: usize, rng: &mut dyn RngCore) -> AccuracyReport {
+    let kin = solver.kinematics();
+    let mut residuals = Vec::with_capacity(n);
+    let mut exact = 0;
+    let mut branches = 0;
+    let mut worst: Vec<WorstCase> = Vec::with_capacity(WORST_CASES + 1);
+
+    for _ in 0..n {
+        let q = DVector::from_iterator(
+            kin.num_joints(),
+            kin.joint_types().iter().map(|joint_type| match joint_type {
+                JointType::Revolute => random_angle(rng),
+                JointType::Prismatic => rng.random_range(-1.0..1.0),
+            }),
+        );
+        let pose = Pose::from(
+            kin.forward_kinematics(&q)
+                .expect("the joint values match the kinematics"),
+        );
+
+        let solutions = solver.solve(&pose);
+        branches += solutions.len();
+        if solutions.iter().any(|solution| !solution.is_ls) {
+            exact += 1;
+        }
+        let residual = solutions
+            .iter()
+            .filter_map(|solution| {
+                let q = DVector::from_column_slice(solution.q.as_slice());
+                let (r, t) = kin.forward_kinematics(&q).ok()?;
+                Some((r - pose.rotation).norm() + (t - pose.translation).norm())
+            })
+            .reduce(f64::min)
+            .unwrap_or(f64::INFINITY);
+        residuals.push(residual);
+
+        if worst.len() < WORST_CASES || residual > worst[WORST_CASES - 1].residual {
+            let i = worst.partition_point(|case| case.residual >= residual);
+            worst.insert(
+                i,
+                WorstCase {
+                    q: q.iter().copied().collect(),
+                    pose,
+                    residual,
+                },
+            );
+            worst.truncate(WORST_CASES);
+        }
+    }
+
+    residuals.sort_by(f64::total_cmp);
+    AccuracyReport {
+        poses: n,
+        min: quantile(&residuals, 0.0),
+        median: quantile(&residuals, 0.5),
+        p95: quantile(&residuals, 0.95),
+        p99: quantile(&residuals, 0.99),
+        max: quantile(&residuals, 1.0),
+        exact_fraction: exact as f64 / n.max(1) as f64,
+        avg_branches: branches as f64 / n.max(1) as f64,
+        worst,
+    }
+}
+
+/// How far `fk_probe` moves each joint: 0.1 rad for a revolute joint, and 0.1 in the length unit of
+/// the kinematics for a prismatic one
+pub const PROBE_STEP: f64 = 0.1;
+
+/// How the tool moves when a single joint moves from zero, see `fk_probe`
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct JointProbe {
+    /// The index of the joint, from 0
+    pub joint: usize,
+    pub joint_type: JointType,
+    /// The displacement of the tool in the world frame when the joint moves by `PROBE_STEP`
+    pub displacement: Vector3<f64>,
+    /// The rotation of the tool in the world frame when the joint moves by `PROBE_STEP`, as a
+    /// rotation vector
+    pub rotation: Vector3<f64>,
+}
+
+impl JointProbe {
+    /// The direction the tool moves in, if it moves at all
+    pub fn direction(&self) -> Option<Vector3<f64>> {
+        self.displacement.try_normalize(PROBE_STEP * 1e-9)
+    }
+
+    /// The axis the tool turns about, if it turns at all
+    pub fn rotation_axis(&self) -> Option<Vector3<f64>> {
+        self.rotation.try_normalize(PROBE_STEP * 1e-9)
+    }
+}
+
+/// The world axis closest to `direction`, e.g. `+x` or `-z`
+fn nearest_axis(direction: &Vector3<f64>) -> String {
+    let i = direction.iamax();
+    let sign = if direction[i] < 0.0 { '-' } else { '+' };
+    format!("{sign}{}", ['x', 'y', 'z'][i])
+}
+
+impl fmt::Display for JointProbe {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.joint_type {
+            JointType::Revolute => "revolute",
+            JointType::Prismatic => "prismatic",
+        };
+        write!(f, "q{} ({kind}) +{PROBE_STEP}:", self.joint + 1)?;
+        match self.direction() {
+            Some(d) => write!(
+                f,
+                "\tmoves {:.4} along ({:+.3}, {:+.3}, {:+.3}), mostly {}",
+                self.displacement.norm(),
+                d.x,
+                d.y,
+                d.z,
+                nearest_axis(&d)
+            )?,
+            None => write!(f, "\tdoes not move")?,
+        }
+        match self.rotation_axis() {
+            Some(w) => write!(
+                f,
+                ",\tturns {:.4} rad about ({:+.3}, {:+.3}, {:+.3}), mostly {}",
+                self.rotation.norm(),
+                w.x,
+                w.y,
+                w.z,
+                nearest_axis(&w)
+            ),
+            None => write!(f, ",\tdoes not turn"),
+        }
+    }
+}
+
+/// Move each joint of `kin` by `PROBE_STEP` from the zero configuration on its own, and report how
+/// the tool moves and turns in the world frame. Moving the joints of the physical robot the same
+/// way, e.g. jogging them from zero on the teach pendant, and comparing the directions catches
+/// flipped axes, swapped joints and offsets in the wrong frame, which leave a model that solves
+/// its own poses but drives the robot elsewhere.
+pub fn fk_probe<const C1: usize, const C2: usize>(kin: &Kinematics<C1, C2>) -> Vec<JointProbe> {
+    let zero = SVector::<f64, C1>::zeros();
+    let (r_zero, t_zero) = kin.forward_kinematics(&zero);
+    (0..C1)
+        .map(|joint| {
+            let mut q = zero;
+            q[joint] = PROBE_STEP;
+            let (r, t) = kin.forward_kinematics(&q);
+            JointProbe {
+                joint,
+                joint_type: kin.joint_types[joint],
+                displacement: t - t_zero,
+                rotation: Rotation3::from_matrix(&(r * r_zero.transpose())).scaled_axis(),
+            }
+        })
+        .collect()
+}
+
+/// A `SetupIk` for any `Robot`, solving poses reached at random joint angles. The angles are drawn
+/// from the joint limits of the robot when it has them.
+pub struct RobotSetup {
+    name: &'static str,
+    robot: Robot,
+    r: Matrix3<f64>,
+    t: Vector3<f64>,
+
+    solutions: IkSolutionSet,
+}
+
+impl RobotSetup {
+    pub fn new(name: &'static str, robot: Robot) -> Self {
+        RobotSetup {
+            name,
+            robot,
+            r: Matrix3::zeros(),
+            t: Vector3::zeros(),
+            solutions: IkSolutionSet::new(),
+        }
+    }
+}
+
+impl SetupIk for RobotSetup {
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
+        let q = match self.robot.joint_limits() {
+            Some(limits) => {
+                Vector6::from_fn(|i, _| rng.random_range(limits.lower[i]..=limits.upper[i]))
+            }
+            None => Vector6::zeros().map(|_: f64| random_angle(rng)),
+        };
+        (self.r, self.t) = self.robot.fk(&q.into());
+    }
+
+    fn setup_from_str(&mut self, raw: &str) -> Result<(), IkGeoError> {
+        (self.r, self.t) = hardcoded_setup_from_string(raw)?;
+        Ok(())
+    }
+
+    fn write_output(&self, format: OutputFormat) -> Vec<u8> {
+        ik_write_output(&self.solutions, format)
+    }
+
+    fn run(&mut self) {
+        self.solutions = self.robot.ik(self.r, self.t);
+    }
+
+    fn error(&self) -> f64 {
+        self.solutions
+            .iter()
+            .map(|solution| {
+                let (r_q, t_q) = self.robot.fk(&solution.q.into());
+                (r_q - self.r).norm() + (t_q - self.t).norm()
+            })
+            .reduce(f64::min)
+            .unwrap_or(f64::NAN)
+    }
+
+    fn ls_count(&self) -> usize {
+        self.solutions.iter().filter(|s| s.is_ls).count()
+    }
+
+    fn solution_count(&self) -> usize {
+        self.solutions.len()
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn debug(&self, i: usize) {
+        println!("{i}{}{}", self.r, self.t);
+    }
+}
+
+/// A set of setups to measure together. Each setup draws its poses from its own generator seeded
+/// with `seed`, so its statistics do not depend on the other setups.
+#[derive(Default)]
+pub struct Diagnostics {
+    setups: Vec<Box<dyn SetupIk>>,
+    seed: u64,
+}
+
+impl Diagnostics {
+    /// No setups, with a seed of 0
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// The setups the crate measures itself: every solver on random chains of its family, and the
+    /// hardcoded robots
+    pub fn builtin() -> Self {
+        let mut diagnostics = Self::decompositions();
+        diagnostics.setups.extend(Self::hardcoded().setups);
+        diagnostics
+    }
+
+    /// Every solver on random chains of its family
+    pub fn decompositions() -> Self {
+        Diagnostics {
+            setups: vec![
+                Box::new(SphericalTwoParallelSetup::new()),
+                Box::new(SphericalTwoIntersectingSetup::new()),
+                Box::new(SphericalSetup::new()),
+                Box::new(ThreeParallelTwoIntersectingSetup::new()),
+                Box::new(ThreeParallelSetup::new()),
+                Box::new(TwoParallelSetup::new()),
+                Box::new(TwoIntersectingSetup::new()),
+                Box::new(TwoIntersectingAtBaseSetup::new()),
+                Box::new(GenSixDofSetup::new()),
+            ],
+            seed: 0,
+        }
+    }
+
+    /// The hardcoded robots
+    pub fn hardcoded() -> Self {
+        Diagnostics {
+            setups: vec![
+                Box::new(Irb120::new()),
+                Box::new(Irb1200::new()),
+                Box::new(Irb2600::new()),
+                Box::new(Irb4600::new()),
+                Box::new(Irb6640::new()),
+                Box::new(FanucLrMate200id::new()),
+                Box::new(MotomanGp8::new()),
+                Box::new(KukaR800FixedQ3::new()),
+                Box::new(RrcFixedQ6::new()),
+                Box::new(Ur5::new()),
+                Box::new(ThreeParallelBot::new()),
+                Box::new(TwoParallelBot::new()),
+                Box::new(SphericalBot::new()),
+                Box::new(YumiFixedQ3::new()),
+                Box::new(PandaFixedQ4::new()),
+                Box::new(KinovaGen3FixedQ3::new()),
+                Box::new(SawyerFixedQ3::new()),
+            ],
+            seed: 0,
+        }
+    }
+
+    /// A `RobotSetup` of every robot in `registry`. `SetupIk::name` is `'static`, so the name of
+    /// each robot is leaked, which is fine for the few registries a program measures.
+    pub fn registered(registry: &RobotRegistry) -> Self {
+        let mut diagnostics = Self::new();
+        for (name, definition) in registry.iter() {
+            let name: &'static str = String::from(name).leak();
+            diagnostics.register(RobotSetup::new(name, definition.robot()));
+        }
+        diagnostics
+    }
+
+    /// Add a setup, e.g. a `RobotSetup` of your own robot
+    pub fn register(&mut self, setup: impl SetupIk + 'static) -> &mut Self {
+        self.setups.push(Box::new(setup));
+        self
+    }
+
+    /// The names of the registered setups
+    pub fn names(&self) -> Vec<&'static str> {
+        self.setups.iter().map(|setup| setup.name()).collect()
+    }
+
+    /// Measure every setup on `iterations` random poses
+    pub fn run(&mut self, iterations: usize) -> Vec<Stats> {
+        let seed = self.seed;
+        self.setups
+            .iter_mut()
+            .map(|setup| measure(setup.as_mut(), iterations, &mut StdRng::seed_from_u64(seed)))
+            .collect()
+    }
+
+    /// Time every setup on `iterations` random poses, see `time_setup`
+    pub fn time(&mut self, iterations: usize) -> Vec<Timing> {
+        let seed = self.seed;
+        self.setups
+            .iter_mut()
+            .map(|setup| time_setup(setup.as_mut(), iterations, &mut StdRng::seed_from_u64(seed)))
+            .collect()
+    }
+
+    /// Benchmark every setup with criterion
+    #[cfg(feature = "criterion")]
+    pub fn bench(&mut self, c: &mut criterion::Criterion) {
+        for setup in &mut self.setups {
+            bench_setup(c, setup.as_mut(), &mut StdRng::seed_from_u64(self.seed));
+        }
+    }
+}
+
+/// Benchmark the solver of `setup` with criterion, on a new random pose from `rng` for every
+/// iteration. Only the solve is timed.
+#[cfg(feature = "criterion")]
+pub fn bench_setup(c: &mut criterion::Criterion, setup: &mut dyn SetupIk, rng: &mut dyn RngCore) {
+    c.bench_function(setup.name(), |b| {
+        b.iter_custom(|iterations| {
+            let mut total = Duration::ZERO;
+            for _ in 0..iterations {
+                setup.setup_with_rng(rng);
+                let start = Instant::now();
+                setup.run();
+                total += start.elapsed();
+            }
+            total
+        })
+    });
+}
+
+/// The markup `report` writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReportFormat {
+    #[default]
+    Markdown,
+    /// A standalone HTML document
+    Html,
+}
+
+/// The joints of `q` that no whole number of turns brings within the limits of `robot`, each with
+/// the signed distance to the nearest limit
+fn limit_violations(robot: &Robot, q: &Vector6<f64>) -> Vec<(usize, f64)> {
+    let Some(limits) = robot.joint_limits() else {
+        return Vec::new();
+    };
+
+    (0..6)
+        .filter_map(|i| {
+            let above = q[i] - TAU * ((q[i] - limits.upper[i]) / TAU).ceil();
+            if above >= limits.lower[i] {
+                return None;
+            }
+            let below = above + TAU;
+            let over = below - limits.upper[i];
+            let under = above - limits.lower[i];
+            Some((i, if over < -under { over } else { under }))
+        })
+        .collect()
+}
+
+/// Write a table in `format`, with its cells already escaped
+fn write_table(out: &mut String, format: ReportFormat, header: &[&str], rows: &[Vec<String>]) {
+    match format {
+        ReportFormat::Markdown => {
+            let _ = writeln!(out, "| {} |", header.join(" | "));
+            let _ = writeln!(out, "|{}", "---|".repeat(header.len()));
+            for row in rows {
+                let _ = writeln!(out, "| {} |", row.join(" | "));
+            }
+        }
+        ReportFormat::Html => {
+            let _ = writeln!(
+                out,
+                "<table>\n<tr>{}</tr>",
+                header
+                    .iter()
+                    .map(|cell| format!("<th>{cell}</th>"))
+                    .collect::<String>()
+            );
+            for row in rows {
+                let _ = writeln!(
+                    out,
+                    "<tr>{}</tr>",
+                    row.iter()
+                        .map(|cell| format!("<td>{cell}</td>"))
+                        .collect::<String>()
+                );
+            }
+            let _ = writeln!(out, "</table>");
+        }
+    }
+}
+
+fn write_heading(out: &mut String, format: ReportFormat, level: usize, text: &str) {
+    let _ = match format {
+        ReportFormat::Markdown => writeln!(out, "{} {text}\n", "#".repeat(level)),
+        ReportFormat::Html => writeln!(out, "<h{level}>{text}</h{level}>"),
+    };
+}
+
+/// Describe solving `(rot, translation)` with `robot`: the target pose, then every branch with its
+/// joint angles, residual and whether it is least squares, its manipulability and the condition
+/// number of its Jacobian to show how close it is to a singularity, its shoulder, elbow and wrist
+/// label, and the joints outside of the joint limits of the robot with their distance to the
+/// nearest limit. Branches outside of the limits, which `ik` drops, are included.
+pub fn report(
+    robot: &Robot,
+    rot: Matrix3<f64>,
+    translation: Vector3<f64>,
+    format: ReportFormat,
+) -> String {
+    let kin = robot.kinematics();
+    let solutions = robot.ik_unlimited(rot, translation);
+
+    let mut out = String::new();
+    if format == ReportFormat::Html {
+        out.push_str("<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>IK report</title></head>\n<body>\n");
+    }
+    write_heading(&mut out, format, 1, "IK report");
+
+    write_heading(&mut out, format, 2, "Target");
+    let rows: Vec<Vec<String>> = (0..3)
+        .map(|i| {
+            let mut row: Vec<String> = (0..3).map(|j| format!("{:.6}", rot[(i, j)])).collect();
+            row.push(format!("{:.6}", translation[i]));
+            row
+        })
+        .collect();
+    write_table(&mut out, format, &["R", "", "", "t"], &rows);
+
+    write_heading(&mut out, format, 2, "Solutions");
+    let rows: Vec<Vec<String>> = solutions
+        .iter()
+        .map(|solution| {
+            let mut row = vec![solution.branch.to_string()];
+            row.extend(solution.q.iter().map(|q| format!("{q:.6}")));
+            row.push(format!("{:.3e}", solution.error));
+            row.push(if solution.is_ls { "yes" } else { "no" }.to_string());
+            row.push(format!("{:.3e}", solution.manipulability_on(kin)));
+            row.push(format!("{:.3e}", kin.condition_number(&solution.q)));
+            row.push(
+                BranchLabel::of(kin, &solution.q)
+                    .map_or_else(|| "-".to_string(), |label| label.to_string()),
+            );
+            let violations = limit_violations(robot, &solution.q);
+            row.push(if violations.is_empty() {
+                "ok".to_string()
+            } else {
+                violations
+                    .iter()
+                    .map(|(i, distance)| format!("q{} by {distance:+.4}", i + 1))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            });
+            row
+        })
+        .collect();
+    write_table(
+        &mut out,
+        format,
+        &[
+            "branch",
+            "q1",
+            "q2",
+            "q3",
+            "q4",
+            "q5",
+            "q6",
+            "residual",
+            "LS",
+            "manipulability",
+            "condition",
+            "label",
+            "limits",
+        ],
+        &rows,
+    );
+
+    let exact = solutions.iter().filter(|solution| !solution.is_ls).count();
+    let summary = format!(
+        "{} solutions, {exact} exact, {} within the joint limits",
+        solutions.len(),
+        solutions
+            .iter()
+            .filter(|solution| limit_violations(robot, &solution.q).is_empty())
+            .count()
+    );
+    match format {
+        ReportFormat::Markdown => {
+            let _ = writeln!(out, "\n{summary}");
+        }
+        ReportFormat::Html => {
+            let _ = writeln!(out, "<p>{summary}</p>\n</body>\n</html>");
+        }
+    }
+
+    out
+}
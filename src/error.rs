@@ -0,0 +1,112 @@
+use {
+    alloc::string::String,
+    core::fmt::{self, Display, Formatter},
+};
+
+#[cfg(feature = "std")]
+use alloc::{string::ToString, vec::Vec};
+
+/// Errors that can be produced while parsing setups or constructing kinematics
+#[derive(Debug, Clone, PartialEq)]
+pub enum IkGeoError {
+    /// A value in the input could not be parsed as a number
+    MalformedInput(String),
+    /// The input did not contain the expected number of values
+    DimensionMismatch { expected: usize, found: usize },
+    /// A pseudo-inverse required to build the kinematics could not be computed
+    PseudoInverse(&'static str),
+    /// A joint index was outside of the kinematic chain
+    InvalidJoint { joint: usize, num_joints: usize },
+    /// A robot description could not be read or parsed
+    InvalidDescription(String),
+    /// More solutions were found than fit in the fixed capacity storage they were written to
+    CapacityExceeded { capacity: usize },
+    /// The solver of a robot cannot solve without allocating, for the given reason
+    AllocationRequired(&'static str),
+    /// The format of the orientation in a pose could not be determined from a name or a line
+    UnknownRotationFormat(String),
+    /// The rotation of a target is not a rotation matrix, with the Frobenius norm of `R^T R - I`
+    /// and the determinant of `R`
+    InvalidRotation { defect: f64, determinant: f64 },
+    /// A coupling between motor and joint coordinates cannot be inverted
+    SingularCoupling,
+    /// A robot could not be registered under a name another robot already has
+    DuplicateRobot(String),
+    /// Reducing a chain would solve a linear system whose condition number is above
+    /// `SolverConfig::max_condition`
+    IllConditioned { condition: f64 },
+    /// The axes of joints `axis` and `axis + 1` do not intersect, missing each other by `distance`
+    SkewAxes { axis: usize, distance: f64 },
+    /// A joint state has no position for the joint with this name
+    MissingJoint(String),
+}
+
+impl Display for IkGeoError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::MalformedInput(value) => write!(f, "could not parse \"{value}\" as a number"),
+            Self::DimensionMismatch { expected, found } => {
+                write!(f, "expected {expected} values but found {found}")
+            }
+            Self::PseudoInverse(reason) => write!(f, "pseudo-inverse failed: {reason}"),
+            Self::InvalidJoint { joint, num_joints } => {
+                write!(f, "joint {joint} does not exist in a {num_joints} joint chain")
+            }
+            Self::InvalidDescription(reason) => write!(f, "invalid robot description: {reason}"),
+            Self::CapacityExceeded { capacity } => {
+                write!(f, "more than {capacity} solutions were found")
+            }
+            Self::AllocationRequired(reason) => {
+                write!(f, "the solver has to allocate: {reason}")
+            }
+            Self::UnknownRotationFormat(value) => {
+                write!(f, "could not determine the rotation format of \"{value}\"")
+            }
+            Self::InvalidRotation {
+                defect,
+                determinant,
+            } => write!(
+                f,
+                "not a rotation matrix: |R^T R - I| is {defect} and det(R) is {determinant}"
+            ),
+            Self::SingularCoupling => write!(f, "the joint coupling matrix is singular"),
+            Self::DuplicateRobot(name) => write!(f, "a robot called \"{name}\" already exists"),
+            Self::IllConditioned { condition } => {
+                write!(f, "the reduction is ill-conditioned, with condition number {condition}")
+            }
+            Self::SkewAxes { axis, distance } => {
+                write!(f, "the axes of joints {axis} and {} are {distance} apart", axis + 1)
+            }
+            Self::MissingJoint(name) => write!(f, "no position for the joint \"{name}\""),
+        }
+    }
+}
+
+impl core::error::Error for IkGeoError {}
+
+/// Parses a comma separated list of numbers
+#[cfg(feature = "std")]
+pub(crate) fn parse_list(raw: &str) -> Result<Vec<f64>, IkGeoError> {
+    raw.split(',')
+        .map(|s| {
+            s.trim()
+                .parse()
+                .map_err(|_| IkGeoError::MalformedInput(s.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Parses a comma separated list of exactly `expected` numbers
+#[cfg(feature = "std")]
+pub(crate) fn parse_values(raw: &str, expected: usize) -> Result<Vec<f64>, IkGeoError> {
+    let data = parse_list(raw)?;
+
+    if data.len() != expected {
+        return Err(IkGeoError::DimensionMismatch {
+            expected,
+            found: data.len(),
+        });
+    }
+
+    Ok(data)
+}
@@ -0,0 +1,262 @@
+//! Robots carried by an external axis, such as a linear rail or a turntable.
+//!
+//! Welding and machining cells often mount the arm on a rail or a rotary table, adding a joint in
+//! front of the chain that moves the whole arm. The external axis is redundant with the arm, so
+//! `ExternalAxisSolver` samples it over its range and solves the arm in closed form at each
+//! sample, or searches it for the value that minimizes a cost of the arm solutions.
+
+use {
+    crate::{
+        inverse_kinematics::auxiliary::Kinematics,
+        robot::{golden_section_interval, IKSolver, IkSolution, IkSolutionSet, SevenDofSweep},
+    },
+    alloc::vec::Vec,
+    nalgebra::{
+        ArrayStorage, Const, Isometry3, Matrix, Matrix3, Translation3, Unit, UnitQuaternion,
+        Vector3, U1,
+    },
+};
+
+/// How the external axis moves the arm
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ExternalAxisKind {
+    /// A rail, moving the arm along the axis by the value of the external axis
+    Prismatic,
+    /// A turntable, rotating the arm about the axis through the world origin by the value of the
+    /// external axis
+    Revolute,
+}
+
+/// An axis in the world frame that carries the base of an arm
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExternalAxis {
+    pub axis: Vector3<f64>,
+    pub kind: ExternalAxisKind,
+}
+
+impl ExternalAxis {
+    pub fn new(axis: Vector3<f64>, kind: ExternalAxisKind) -> Self {
+        ExternalAxis {
+            axis: axis.normalize(),
+            kind,
+        }
+    }
+
+    /// The motion of everything the axis carries when it is at `value`
+    pub fn transform(&self, value: f64) -> Isometry3<f64> {
+        match self.kind {
+            ExternalAxisKind::Prismatic => Isometry3::from_parts(
+                Translation3::from(self.axis * value),
+                UnitQuaternion::identity(),
+            ),
+            ExternalAxisKind::Revolute => Isometry3::from_parts(
+                Translation3::identity(),
+                UnitQuaternion::from_axis_angle(&Unit::new_normalize(self.axis), value),
+            ),
+        }
+    }
+
+    /// The pose the arm has to reach in its own world frame for the tool to reach `(rot,
+    /// translation)` with the external axis at `value`
+    pub fn arm_target(
+        &self,
+        value: f64,
+        rot: &Matrix3<f64>,
+        translation: &Vector3<f64>,
+    ) -> (Matrix3<f64>, Vector3<f64>) {
+        let inverse = self.transform(value).inverse();
+        (
+            inverse.rotation.to_rotation_matrix().into_inner() * rot,
+            inverse * translation + inverse.translation.vector,
+        )
+    }
+}
+
+/// The kinematics of an arm carried by an external axis
+#[derive(Debug, Clone)]
+pub struct ExternalAxisKinematics<const C1: usize, const C2: usize> {
+    pub arm: Kinematics<C1, C2>,
+    pub external: ExternalAxis,
+}
+
+impl<const C1: usize, const C2: usize> ExternalAxisKinematics<C1, C2> {
+    /// The pose of the tool with the external axis at `value` and the arm at `theta`
+    pub fn forward_kinematics(
+        &self,
+        value: f64,
+        theta: &Matrix<f64, Const<C1>, U1, ArrayStorage<f64, C1, 1>>,
+    ) -> (Matrix3<f64>, Vector3<f64>) {
+        let (r, p) = self.arm.forward_kinematics(theta);
+        let motion = self.external.transform(value);
+        (
+            motion.rotation.to_rotation_matrix().into_inner() * r,
+            motion * p + motion.translation.vector,
+        )
+    }
+}
+
+impl<const C1: usize, const C2: usize> Kinematics<C1, C2> {
+    /// Mount the arm on an external axis in front of its first joint, which carries the base of
+    /// the arm along `axis` or about `axis` through the world origin
+    pub fn with_external_axis(
+        self,
+        axis: Vector3<f64>,
+        kind: ExternalAxisKind,
+    ) -> ExternalAxisKinematics<C1, C2> {
+        ExternalAxisKinematics {
+            arm: self,
+            external: ExternalAxis::new(axis, kind),
+        }
+    }
+}
+
+/// An arm solver that an external axis can carry, returning configurations of `N` joints
+pub trait ArmSolver<const N: usize> {
+    fn solve_arm(&self, rot: Matrix3<f64>, translation: Vector3<f64>) -> IkSolutionSet<N>;
+}
+
+impl<S: IKSolver> ArmSolver<6> for S {
+    fn solve_arm(&self, rot: Matrix3<f64>, translation: Vector3<f64>) -> IkSolutionSet<6> {
+        self.ik(rot, translation)
+    }
+}
+
+/// The solutions over every sample of the locked joint of the sweep
+impl ArmSolver<7> for SevenDofSweep {
+    fn solve_arm(&self, rot: Matrix3<f64>, translation: Vector3<f64>) -> IkSolutionSet<7> {
+        self.solve(rot, translation)
+            .into_iter()
+            .flat_map(|sample| sample.solutions)
+            .collect()
+    }
+}
+
+/// The arm solutions with the external axis at `value`
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExternalAxisSample<const N: usize = 6> {
+    pub value: f64,
+    pub solutions: IkSolutionSet<N>,
+}
+
+/// Solves an arm carried by an external axis by sampling the external axis within its range
+pub struct ExternalAxisSolver<S> {
+    solver: S,
+    external: ExternalAxis,
+    range: (f64, f64),
+    samples: usize,
+}
+
+impl<S> ExternalAxisSolver<S> {
+    /// Solve the arm of `solver`, carried by `external`, with the external axis between `min` and
+    /// `max` sampled 32 times
+    pub fn new(solver: S, external: ExternalAxis, min: f64, max: f64) -> Self {
+        ExternalAxisSolver {
+            solver,
+            external,
+            range: (min, max),
+            samples: 32,
+        }
+    }
+
+    /// Set the number of evenly spaced samples of the external axis, including both ends of its
+    /// range
+    pub fn with_samples(mut self, samples: usize) -> Self {
+        self.samples = samples.max(2);
+        self
+    }
+
+    pub fn solver(&self) -> &S {
+        &self.solver
+    }
+
+    pub fn external(&self) -> &ExternalAxis {
+        &self.external
+    }
+
+    /// Solve the arm with the external axis at `value`
+    pub fn solve_at<const N: usize>(
+        &self,
+        rot: &Matrix3<f64>,
+        translation: &Vector3<f64>,
+        value: f64,
+    ) -> ExternalAxisSample<N>
+    where
+        S: ArmSolver<N>,
+    {
+        let (rot, translation) = self.external.arm_target(value, rot, translation);
+        ExternalAxisSample {
+            value,
+            solutions: self.solver.solve_arm(rot, translation),
+        }
+    }
+
+    /// Solve the arm at every sample of the external axis, in order of the external axis value
+    pub fn solve<const N: usize>(
+        &self,
+        rot: &Matrix3<f64>,
+        translation: &Vector3<f64>,
+    ) -> Vec<ExternalAxisSample<N>>
+    where
+        S: ArmSolver<N>,
+    {
+        (0..self.samples)
+            .map(|i| self.solve_at(rot, translation, self.sample(i)))
+            .collect()
+    }
+
+    /// The external axis value and arm solution with the least `cost`, found by refining the best
+    /// sample with a golden-section search down to `tolerance`. Only exact solutions are
+    /// considered, and `None` is returned if no sample has one.
+    pub fn optimize<const N: usize, F: Fn(&IkSolution<N>) -> f64>(
+        &self,
+        rot: &Matrix3<f64>,
+        translation: &Vector3<f64>,
+        cost: F,
+        tolerance: f64,
+    ) -> Option<(f64, IkSolution<N>)>
+    where
+        S: ArmSolver<N>,
+    {
+        let best = |sample: ExternalAxisSample<N>| {
+            sample
+                .solutions
+                .into_iter()
+                .filter(|solution| !solution.is_ls)
+                .min_by(|a, b| cost(a).total_cmp(&cost(b)))
+        };
+        let cost_at = |value: f64| {
+            best(self.solve_at(rot, translation, value)).map_or(f64::INFINITY, |s| cost(&s))
+        };
+
+        let (i, _) = (0..self.samples)
+            .map(|i| (i, cost_at(self.sample(i))))
+            .filter(|(_, c)| c.is_finite())
+            .min_by(|a, b| a.1.total_cmp(&b.1))?;
+
+        let (min, max) = self.range;
+        let step = (max - min) / (self.samples - 1) as f64;
+        let center = self.sample(i);
+        let value = golden_section_interval(
+            cost_at,
+            (center - step).max(min),
+            (center + step).min(max),
+            tolerance,
+        );
+
+        // The refinement can end up worse than the sample on a cost that is not unimodal
+        [value, center]
+            .into_iter()
+            .filter_map(|value| {
+                best(self.solve_at(rot, translation, value)).map(|solution| (value, solution))
+            })
+            .min_by(|a, b| cost(&a.1).total_cmp(&cost(&b.1)))
+    }
+
+    fn sample(&self, i: usize) -> f64 {
+        let (min, max) = self.range;
+        min + (max - min) * i as f64 / (self.samples - 1) as f64
+    }
+}
@@ -0,0 +1,317 @@
+//! C interface to the closed-form solvers of the hardcoded robots, declared in `include/ik_geo.h`,
+//! with the `ffi` feature.
+//!
+//! Rotations are passed as 9 doubles in row-major order and translations as 3 doubles. Every
+//! solver writes up to `IK_GEO_MAX_SOLUTIONS` joint vectors of 6 doubles each to `out_q`, one
+//! after the other, and their number to `out_n`. Least squares solutions are included, so for
+//! each solution `out_is_ls` gets 1 if it is a least squares solution and 0 if it is exact, and
+//! `out_error` its residual.
+//! The functions return `IK_GEO_OK` on success, `IK_GEO_TRUNCATED` if a pose had more than
+//! `IK_GEO_MAX_SOLUTIONS` solutions and only the first were written, `IK_GEO_NULL_POINTER` if any
+//! pointer is null and `IK_GEO_INVALID_ARGUMENT` if the arrays of `n` poses would not fit in memory.
+//!
+//! The batch solvers take `n` poses as arrays of `4 * n` doubles for the quaternions, each with the
+//! scalar last as in ROS and scipy, and `3 * n` doubles for the translations, as numpy arrays of
+//! shape `(n, 4)` and `(n, 3)`. They write `IK_GEO_MAX_SOLUTIONS` joint vectors per pose to
+//! `out_q`, and as many flags and residuals to `out_is_ls` and `out_error`, of which the first
+//! `out_n[i]` are the solutions of pose `i`, see `PoseBatch`.
+
+use {
+    crate::{
+        pose::{PoseBatch, RotationFormat},
+        robot::{
+            irb120, irb1200, irb2600, irb4600, irb6640, motoman_gp8, ur5, FanucLrMate200id,
+            IKSolver, IkSolutionSet,
+        },
+    },
+    core::{
+        ffi::{c_double, c_int},
+        mem::size_of,
+        slice,
+    },
+    nalgebra::{Matrix3, Vector3},
+};
+
+/// The most solutions any of the closed-form solvers can return
+pub const IK_GEO_MAX_SOLUTIONS: usize = 8;
+
+pub const IK_GEO_OK: c_int = 0;
+pub const IK_GEO_NULL_POINTER: c_int = -1;
+pub const IK_GEO_INVALID_ARGUMENT: c_int = -2;
+/// The solutions were written, but some pose had more than `IK_GEO_MAX_SOLUTIONS` of them
+pub const IK_GEO_TRUNCATED: c_int = 1;
+
+/// The number of values in an array of `n` items of `per_item` values of `T`, if the array can be
+/// addressed at all
+fn array_len<T>(n: usize, per_item: usize) -> Option<usize> {
+    let len = n.checked_mul(per_item)?;
+    (len.checked_mul(size_of::<T>())? <= isize::MAX as usize).then_some(len)
+}
+
+/// The arrays the IK functions write the solutions to, as passed by the caller
+struct OutputPointers {
+    q: *mut c_double,
+    is_ls: *mut c_int,
+    error: *mut c_double,
+    n: *mut c_int,
+}
+
+impl OutputPointers {
+    fn is_null(&self) -> bool {
+        self.q.is_null() || self.is_ls.is_null() || self.error.is_null() || self.n.is_null()
+    }
+}
+
+/// The arrays the solutions of one pose are written to
+struct Output<'a> {
+    q: &'a mut [f64],
+    is_ls: &'a mut [c_int],
+    error: &'a mut [f64],
+    n: &'a mut c_int,
+}
+
+/// Write the first `IK_GEO_MAX_SOLUTIONS` solutions to `out` and their number to `out.n`,
+/// returning whether any were left out
+fn write_solutions(solutions: &IkSolutionSet, out: Output) -> bool {
+    let n = solutions.len().min(IK_GEO_MAX_SOLUTIONS);
+    for (i, solution) in solutions.iter().take(n).enumerate() {
+        out.q[6 * i..6 * (i + 1)].copy_from_slice(solution.q.as_slice());
+        out.is_ls[i] = c_int::from(solution.is_ls);
+        out.error[i] = solution.error;
+    }
+    *out.n = n as c_int;
+    solutions.len() > n
+}
+
+/// # Safety
+///
+/// `r` and `t` must point to 9 and 3 readable doubles, `out.q` must have room for
+/// `6 * IK_GEO_MAX_SOLUTIONS` doubles, `out.is_ls` and `out.error` for `IK_GEO_MAX_SOLUTIONS` ints
+/// and doubles, and `out.n` must point to a writable int.
+unsafe fn solve<S: IKSolver>(
+    solver: &S,
+    r: *const c_double,
+    t: *const c_double,
+    out: OutputPointers,
+) -> c_int {
+    if r.is_null() || t.is_null() || out.is_null() {
+        return IK_GEO_NULL_POINTER;
+    }
+
+    let r = Matrix3::from_row_slice(slice::from_raw_parts(r, 9));
+    let t = Vector3::from_column_slice(slice::from_raw_parts(t, 3));
+    let out = Output {
+        q: slice::from_raw_parts_mut(out.q, 6 * IK_GEO_MAX_SOLUTIONS),
+        is_ls: slice::from_raw_parts_mut(out.is_ls, IK_GEO_MAX_SOLUTIONS),
+        error: slice::from_raw_parts_mut(out.error, IK_GEO_MAX_SOLUTIONS),
+        n: &mut *out.n,
+    };
+
+    let solutions = solver.ik(r, t);
+    if write_solutions(&solutions, out) {
+        IK_GEO_TRUNCATED
+    } else {
+        IK_GEO_OK
+    }
+}
+
+/// # Safety
+///
+/// `quaternions` and `translations` must point to `4 * n` and `3 * n` readable doubles, `out.q`
+/// must have room for `6 * IK_GEO_MAX_SOLUTIONS * n` doubles, `out.is_ls` and `out.error` for
+/// `IK_GEO_MAX_SOLUTIONS * n` ints and doubles, and `out.n` for `n` ints.
+unsafe fn solve_batch<S: IKSolver + Sync>(
+    solver: &S,
+    n: usize,
+    quaternions: *const c_double,
+    translations: *const c_double,
+    out: OutputPointers,
+) -> c_int {
+    if quaternions.is_null() || translations.is_null() || out.is_null() {
+        return IK_GEO_NULL_POINTER;
+    }
+
+    let stride = 6 * IK_GEO_MAX_SOLUTIONS;
+    let lengths = (
+        array_len::<c_double>(n, 4),
+        array_len::<c_double>(n, 3),
+        array_len::<c_double>(n, stride),
+        array_len::<c_double>(n, IK_GEO_MAX_SOLUTIONS),
+    );
+    let (Some(quaternions_len), Some(translations_len), Some(out_q_len), Some(out_error_len)) =
+        lengths
+    else {
+        return IK_GEO_INVALID_ARGUMENT;
+    };
+
+    let quaternions = slice::from_raw_parts(quaternions, quaternions_len);
+    let translations = slice::from_raw_parts(translations, translations_len);
+    // An int is no larger than a double, so `out.is_ls` fits whenever `out.error` does
+    let out_q = slice::from_raw_parts_mut(out.q, out_q_len);
+    let out_is_ls = slice::from_raw_parts_mut(out.is_ls, out_error_len);
+    let out_error = slice::from_raw_parts_mut(out.error, out_error_len);
+    let out_n = slice::from_raw_parts_mut(out.n, n);
+
+    let Ok(batch) =
+        PoseBatch::from_packed(quaternions, translations, RotationFormat::QuaternionXyzw)
+    else {
+        return IK_GEO_INVALID_ARGUMENT;
+    };
+    let solutions = solver.solve_pose_batch(&batch);
+
+    let outputs = out_q
+        .chunks_mut(stride)
+        .zip(out_is_ls.chunks_mut(IK_GEO_MAX_SOLUTIONS))
+        .zip(out_error.chunks_mut(IK_GEO_MAX_SOLUTIONS))
+        .zip(out_n)
+        .map(|(((q, is_ls), error), n)| Output { q, is_ls, error, n });
+
+    let mut truncated = false;
+    for (solutions, out) in solutions.iter().zip(outputs) {
+        truncated |= write_solutions(solutions, out);
+    }
+
+    if truncated {
+        IK_GEO_TRUNCATED
+    } else {
+        IK_GEO_OK
+    }
+}
+
+/// # Safety
+///
+/// `q` must point to 6 readable doubles, `out_r` and `out_t` to 9 and 3 writable doubles.
+unsafe fn forward<F: Fn(&[f64; 6]) -> (Matrix3<f64>, Vector3<f64>)>(
+    fk: F,
+    q: *const c_double,
+    out_r: *mut c_double,
+    out_t: *mut c_double,
+) -> c_int {
+    if q.is_null() || out_r.is_null() || out_t.is_null() {
+        return IK_GEO_NULL_POINTER;
+    }
+
+    let mut q_array = [0.0; 6];
+    q_array.copy_from_slice(slice::from_raw_parts(q, 6));
+    let (r, t) = fk(&q_array);
+
+    slice::from_raw_parts_mut(out_r, 9).copy_from_slice(r.transpose().as_slice());
+    slice::from_raw_parts_mut(out_t, 3).copy_from_slice(t.as_slice());
+
+    IK_GEO_OK
+}
+
+macro_rules! ffi_robot {
+    ($ik:ident, $fk:ident, $batch:ident, $robot:expr) => {
+        /// Inverse kinematics, see the module documentation for the layout of the arguments
+        ///
+        /// # Safety
+        ///
+        /// `r` and `t` must point to 9 and 3 readable doubles, `out_q` must have room for
+        /// `6 * IK_GEO_MAX_SOLUTIONS` doubles, `out_is_ls` and `out_error` for
+        /// `IK_GEO_MAX_SOLUTIONS` ints and doubles, and `out_n` must point to a writable int.
+        #[no_mangle]
+        pub unsafe extern "C" fn $ik(
+            r: *const c_double,
+            t: *const c_double,
+            out_q: *mut c_double,
+            out_is_ls: *mut c_int,
+            out_error: *mut c_double,
+            out_n: *mut c_int,
+        ) -> c_int {
+            let out = OutputPointers {
+                q: out_q,
+                is_ls: out_is_ls,
+                error: out_error,
+                n: out_n,
+            };
+            solve(&$robot, r, t, out)
+        }
+
+        /// Inverse kinematics of `n` poses, see the module documentation for the layout of the
+        /// arguments
+        ///
+        /// # Safety
+        ///
+        /// `quaternions` and `translations` must point to `4 * n` and `3 * n` readable doubles,
+        /// `out_q` must have room for `6 * IK_GEO_MAX_SOLUTIONS * n` doubles, `out_is_ls` and
+        /// `out_error` for `IK_GEO_MAX_SOLUTIONS * n` ints and doubles, and `out_n` for `n` ints.
+        #[no_mangle]
+        pub unsafe extern "C" fn $batch(
+            n: usize,
+            quaternions: *const c_double,
+            translations: *const c_double,
+            out_q: *mut c_double,
+            out_is_ls: *mut c_int,
+            out_error: *mut c_double,
+            out_n: *mut c_int,
+        ) -> c_int {
+            let out = OutputPointers {
+                q: out_q,
+                is_ls: out_is_ls,
+                error: out_error,
+                n: out_n,
+            };
+            solve_batch(&$robot, n, quaternions, translations, out)
+        }
+
+        /// Forward kinematics, writing the rotation in row-major order
+        ///
+        /// # Safety
+        ///
+        /// `q` must point to 6 readable doubles, `out_r` and `out_t` to 9 and 3 writable doubles.
+        #[no_mangle]
+        pub unsafe extern "C" fn $fk(
+            q: *const c_double,
+            out_r: *mut c_double,
+            out_t: *mut c_double,
+        ) -> c_int {
+            let robot = $robot;
+            forward(|q| robot.fk(q), q, out_r, out_t)
+        }
+    };
+}
+
+ffi_robot!(
+    ik_geo_irb120,
+    ik_geo_irb120_fk,
+    ik_geo_irb120_batch,
+    irb120()
+);
+ffi_robot!(
+    ik_geo_irb1200,
+    ik_geo_irb1200_fk,
+    ik_geo_irb1200_batch,
+    irb1200()
+);
+ffi_robot!(
+    ik_geo_irb2600,
+    ik_geo_irb2600_fk,
+    ik_geo_irb2600_batch,
+    irb2600()
+);
+ffi_robot!(
+    ik_geo_irb4600,
+    ik_geo_irb4600_fk,
+    ik_geo_irb4600_batch,
+    irb4600()
+);
+ffi_robot!(
+    ik_geo_irb6640,
+    ik_geo_irb6640_fk,
+    ik_geo_irb6640_batch,
+    irb6640()
+);
+ffi_robot!(
+    ik_geo_fanuc_lr_mate_200id,
+    ik_geo_fanuc_lr_mate_200id_fk,
+    ik_geo_fanuc_lr_mate_200id_batch,
+    FanucLrMate200id::new()
+);
+ffi_robot!(
+    ik_geo_motoman_gp8,
+    ik_geo_motoman_gp8_fk,
+    ik_geo_motoman_gp8_batch,
+    motoman_gp8()
+);
+ffi_robot!(ik_geo_ur5, ik_geo_ur5_fk, ik_geo_ur5_batch, ur5());
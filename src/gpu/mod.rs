@@ -0,0 +1,263 @@
+//! An experimental GPU backend solving very large batches of poses, e.g. to generate data for
+//! reinforcement learning or to fill reachability maps.
+//!
+//! `GpuBatchSolver` solves robots with a spherical wrist and parallel second and third axes,
+//! the family of `Robot::spherical_two_parallel`, with a [wgpu](https://wgpu.rs) compute shader
+//! that runs the closed-form solver of `inverse_kinematics::batch` in `f32`, one pose per
+//! invocation. The branches are then deduplicated, annotated and checked against the joint
+//! limits on the CPU as `Robot::solve_into` does, so `solve_batch` returns the same solution sets
+//! as `IKSolver::solve_batch`, up to the precision of `f32`.
+//!
+//! Without a GPU adapter, or when the robot is configured in a way the shader does not handle,
+//! such as refining its solutions, `solve_batch` falls back to `IKSolver::solve_batch` on the
+//! CPU, so the same code runs on machines with and without a GPU.
+
+use {
+    crate::{
+        config::SolverConfig,
+        inverse_kinematics::auxiliary::Kinematics,
+        robot::{IKSolver, IkSolutionSet, JointLimits, Robot, SolutionBuffer},
+    },
+    nalgebra::{Matrix3, Vector3, Vector6},
+    wgpu::util::DeviceExt,
+};
+
+/// The compute shader, solving one pose per invocation
+pub(crate) const SHADER: &str = include_str!("spherical_two_parallel.wgsl");
+
+/// The poses solved by one dispatch, which keeps the buffers within the default binding limits
+const CHUNK: usize = 1 << 18;
+
+/// The invocations of a workgroup of the shader
+const WORKGROUP_SIZE: usize = 64;
+
+/// The values the shader writes for each branch, the joint angles and the least squares flag
+const BRANCH_VALUES: usize = 7;
+
+/// A device with the compute pipeline of the shader
+struct GpuContext {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+}
+
+impl GpuContext {
+    /// The default adapter of the platform, if there is one
+    fn new() -> Option<Self> {
+        pollster::block_on(async {
+            let instance = wgpu::Instance::default();
+            let adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions::default())
+                .await?;
+            let (device, queue) = adapter
+                .request_device(&wgpu::DeviceDescriptor::default(), None)
+                .await
+                .ok()?;
+
+            let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("spherical_two_parallel"),
+                source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+            });
+            let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("spherical_two_parallel"),
+                layout: None,
+                module: &module,
+                entry_point: Some("main"),
+                compilation_options: Default::default(),
+                cache: None,
+            });
+
+            Some(GpuContext {
+                device,
+                queue,
+                pipeline,
+            })
+        })
+    }
+
+    /// The eight branches of every pose, given in the chain frame, as the shader writes them
+    fn solve(&self, poses: &[(Matrix3<f32>, Vector3<f32>)], params: &[u8]) -> Vec<f32> {
+        let pose_values: Vec<f32> = poses
+            .iter()
+            .flat_map(|(r, t)| r.iter().chain(t.iter()).copied())
+            .collect();
+
+        let mut params = params.to_vec();
+        params[212..216].copy_from_slice(&(poses.len() as u32).to_le_bytes());
+
+        let params = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("params"),
+                contents: &params,
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+        let input = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("poses"),
+                contents: &to_bytes(&pose_values),
+                usage: wgpu::BufferUsages::STORAGE,
+            });
+        let size = (poses.len() * 8 * BRANCH_VALUES * 4) as wgpu::BufferAddress;
+        let output = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("branches"),
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("staging"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: input.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: output.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(poses.len().div_ceil(WORKGROUP_SIZE) as u32, 1, 1);
+        }
+        encoder.copy_buffer_to_buffer(&output, 0, &staging, 0, size);
+        self.queue.submit([encoder.finish()]);
+
+        let slice = staging.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| {});
+        self.device.poll(wgpu::Maintain::Wait);
+        let values = slice
+            .get_mapped_range()
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect();
+        staging.unmap();
+        values
+    }
+}
+
+fn to_bytes(values: &[f32]) -> Vec<u8> {
+    values.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+/// The uniforms of the shader for the chain `kin`, with the pose count left to fill in
+fn params(kin: &Kinematics<6, 7>, ls_tolerance: f32) -> Vec<u8> {
+    let column = |v: Vector3<f64>| [v.x as f32, v.y as f32, v.z as f32, 0.0];
+    let mut values: Vec<f32> = (0..6).flat_map(|i| column(kin.h.column(i).into())).collect();
+    values.extend((0..7).flat_map(|i| column(kin.p.column(i).into())));
+    values.extend([ls_tolerance, 0.0, 0.0, 0.0]);
+    to_bytes(&values)
+}
+
+/// Solves batches of poses of a robot with a spherical wrist and parallel second and third axes
+/// on the GPU, falling back to the CPU
+pub struct GpuBatchSolver {
+    robot: Robot,
+    context: Option<GpuContext>,
+    ls_tolerance: f32,
+}
+
+impl GpuBatchSolver {
+    /// The solver of `Robot::spherical_two_parallel(kinematics)` on the default GPU adapter, or
+    /// on the CPU if there is none
+    pub fn new(kinematics: Kinematics<6, 7>) -> Self {
+        GpuBatchSolver {
+            robot: Robot::spherical_two_parallel(kinematics),
+            context: GpuContext::new(),
+            ls_tolerance: 1e-3,
+        }
+    }
+
+    /// The same solver always running on the CPU, e.g. to compare against the GPU
+    pub fn cpu(kinematics: Kinematics<6, 7>) -> Self {
+        GpuBatchSolver {
+            robot: Robot::spherical_two_parallel(kinematics),
+            context: None,
+            ls_tolerance: 1e-3,
+        }
+    }
+
+    pub fn with_config(mut self, config: SolverConfig) -> Self {
+        self.robot = self.robot.with_config(config);
+        self
+    }
+
+    pub fn with_joint_limits(mut self, joint_limits: JointLimits) -> Self {
+        self.robot = self.robot.with_joint_limits(joint_limits);
+        self
+    }
+
+    /// The tolerance of the shader for flagging least squares solutions, `1e-3` by default. The
+    /// `ls_tolerance` of the config is meant for `f64` and too tight for the `f32` of the shader.
+    pub fn with_ls_tolerance(mut self, ls_tolerance: f32) -> Self {
+        self.ls_tolerance = ls_tolerance;
+        self
+    }
+
+    /// The robot the CPU fallback solves with
+    pub fn robot(&self) -> &Robot {
+        &self.robot
+    }
+
+    /// Whether `solve_batch` runs on the GPU
+    pub fn is_gpu(&self) -> bool {
+        self.context.is_some() && self.robot.buffered().is_ok()
+    }
+
+    /// Solve IK for every pose in `poses`, returning the solution sets in the same order, like
+    /// `IKSolver::solve_batch`
+    pub fn solve_batch(&self, poses: &[(Matrix3<f64>, Vector3<f64>)]) -> Vec<IkSolutionSet> {
+        let Some(context) = self.context.as_ref().filter(|_| self.is_gpu()) else {
+            return self.robot.solve_batch(poses);
+        };
+
+        let kin = self.robot.kinematics();
+        let params = params(kin, self.ls_tolerance);
+        let mut solutions = SolutionBuffer::new();
+        let mut sets = Vec::with_capacity(poses.len());
+        for chunk in poses.chunks(CHUNK) {
+            let chain: Vec<(Matrix3<f32>, Vector3<f32>)> = chunk
+                .iter()
+                .map(|(r, t)| {
+                    let (r, t) = kin.to_chain_frame(r, t);
+                    (r.cast(), t.cast())
+                })
+                .collect();
+            let values = context.solve(&chain, &params);
+
+            for ((r, t), pose) in chunk.iter().zip(values.chunks_exact(8 * BRANCH_VALUES)) {
+                let branches = pose.chunks_exact(BRANCH_VALUES).map(|branch| {
+                    let q = Vector6::from_fn(|i, _| branch[i] as f64);
+                    (q, branch[6] != 0.0)
+                });
+                // More solutions than a buffer holds only happen with wide joint limits
+                sets.push(match self.robot.branches_into(*r, *t, branches, &mut solutions) {
+                    Ok(()) => solutions.iter().cloned().collect(),
+                    Err(_) => self.robot.ik(*r, *t),
+                });
+            }
+        }
+        sets
+    }
+}
@@ -0,0 +1,150 @@
+// The solver for a spherical wrist with parallel second and third axes, one pose per invocation.
+// It follows `inverse_kinematics::batch::spherical_two_parallel_x8`: every pose gets all eight
+// branches, in the order of the two choices of `q1`, then of `q3`, then of `q5`, and branches
+// where a subproblem has no exact solution repeat the least squares solution.
+
+struct Params {
+    // The axes and offsets of the chain, in the frame the poses are given in
+    h: array<vec4<f32>, 6>,
+    p: array<vec4<f32>, 7>,
+    ls_tolerance: f32,
+    count: u32,
+}
+
+// The columns of the rotation and the translation of each pose, 12 values per pose
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> poses: array<f32>;
+// The six joint angles and the least squares flag of each branch, 7 values per branch
+@group(0) @binding(2) var<storage, read_write> branches: array<f32>;
+
+struct Angle {
+    theta: f32,
+    is_ls: bool,
+}
+
+struct Angles {
+    theta: vec2<f32>,
+    is_ls: bool,
+}
+
+fn rotated(k: vec3<f32>, theta: f32, v: vec3<f32>) -> vec3<f32> {
+    let s = sin(theta);
+    let c = cos(theta);
+    return v * c + cross(k, v) * s + k * (dot(k, v) * (1.0 - c));
+}
+
+// `theta` where `rot(k, theta) * p1 = p2`
+fn subproblem1(p1: vec3<f32>, p2: vec3<f32>, k: vec3<f32>, tolerance: f32) -> Angle {
+    let kxp = cross(k, p1);
+    let theta = atan2(dot(kxp, p2), dot(-cross(k, kxp), p2));
+    let is_ls = abs(length(p1) - length(p2)) > tolerance
+        || abs(dot(k, p1) - dot(k, p2)) > tolerance;
+    return Angle(theta, is_ls);
+}
+
+// `theta` where `|| rot(k, theta) * p1 - p2 || = d`
+fn subproblem3(p1: vec3<f32>, p2: vec3<f32>, k: vec3<f32>, d: f32) -> Angles {
+    let kxp = cross(k, p1);
+    let a_col = cross(kxp, k);
+    let a = vec2<f32>(-2.0 * dot(p2, kxp), -2.0 * dot(p2, a_col));
+    let norm_a_sq = dot(a, a);
+
+    let p2_perp = p2 - k * dot(k, p1);
+    let b = d * d - dot(p2_perp, p2_perp) - dot(kxp, kxp);
+
+    let x_ls = vec2<f32>(dot(p2, kxp), dot(p2, a_col)) * (-2.0 * b / norm_a_sq);
+    let is_ls = dot(x_ls, x_ls) > 1.0;
+
+    let xi = sqrt(max(1.0 - b * b / norm_a_sq, 0.0)) / sqrt(norm_a_sq);
+    let a_perp = vec2<f32>(a.y, -a.x) * xi;
+    let x1 = x_ls + a_perp;
+    let x2 = x_ls - a_perp;
+    return Angles(vec2<f32>(atan2(x1.x, x1.y), atan2(x2.x, x2.y)), is_ls);
+}
+
+// `theta` where `h' * rot(k, theta) * p = d`
+fn subproblem4(h: vec3<f32>, p: vec3<f32>, k: vec3<f32>, d: f32) -> Angles {
+    let a_11 = cross(k, p);
+    let a_12 = cross(a_11, k);
+    let a = vec2<f32>(dot(h, a_11), dot(h, a_12));
+
+    let b = d - dot(h, k) * dot(k, p);
+    let norm_a_2 = dot(a, a);
+
+    let x_ls = a * b;
+    let is_ls = norm_a_2 <= b * b;
+
+    let xi = sqrt(max(norm_a_2 - b * b, 0.0));
+    let a_perp = vec2<f32>(a.y, -a.x) * xi;
+    let x1 = x_ls + a_perp;
+    let x2 = x_ls - a_perp;
+    return Angles(vec2<f32>(atan2(x1.x, x1.y), atan2(x2.x, x2.y)), is_ls);
+}
+
+fn h(i: u32) -> vec3<f32> {
+    return params.h[i].xyz;
+}
+
+fn p(i: u32) -> vec3<f32> {
+    return params.p[i].xyz;
+}
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let pose = id.x;
+    if pose >= params.count {
+        return;
+    }
+
+    let base = pose * 12u;
+    let c0 = vec3<f32>(poses[base], poses[base + 1u], poses[base + 2u]);
+    let c1 = vec3<f32>(poses[base + 3u], poses[base + 4u], poses[base + 5u]);
+    let c2 = vec3<f32>(poses[base + 6u], poses[base + 7u], poses[base + 8u]);
+    let p_0t = vec3<f32>(poses[base + 9u], poses[base + 10u], poses[base + 11u]);
+    let r_0t = mat3x3<f32>(c0, c1, c2);
+
+    let v = p_0t - r_0t * p(6u) - p(0u);
+    let r_h6 = r_0t * h(5u);
+
+    let t1 = subproblem4(h(1u), v, -h(0u), dot(h(1u), p(1u) + p(2u) + p(3u)));
+
+    var branch = 0u;
+    for (var i = 0u; i < 2u; i++) {
+        let q1 = t1.theta[i];
+        let w = rotated(-h(0u), q1, -v) + p(1u);
+        let t3 = subproblem3(-p(3u), p(2u), h(2u), length(w));
+
+        for (var j = 0u; j < 2u; j++) {
+            let q3 = t3.theta[j];
+            let q2 = subproblem1(-p(2u) - rotated(h(2u), q3, p(3u)), w, h(1u), params.ls_tolerance);
+
+            let r_36_h6 = rotated(-h(2u), q3, rotated(-h(1u), q2.theta, rotated(-h(0u), q1, r_h6)));
+            let r_36_t_h4 = transpose(r_0t)
+                * rotated(h(0u), q1, rotated(h(1u), q2.theta, rotated(h(2u), q3, h(3u))));
+
+            let t5 = subproblem4(h(3u), h(5u), h(4u), dot(h(3u), r_36_h6));
+
+            for (var k = 0u; k < 2u; k++) {
+                let q5 = t5.theta[k];
+                let q4 = subproblem1(rotated(h(4u), q5, h(5u)), r_36_h6, h(3u), params.ls_tolerance);
+                let q6 = subproblem1(
+                    rotated(-h(4u), q5, h(3u)),
+                    r_36_t_h4,
+                    -h(5u),
+                    params.ls_tolerance,
+                );
+
+                let is_ls = t1.is_ls || q2.is_ls || t3.is_ls || q4.is_ls || t5.is_ls || q6.is_ls;
+                let out = (pose * 8u + branch) * 7u;
+                branches[out] = q1;
+                branches[out + 1u] = q2.theta;
+                branches[out + 2u] = q3;
+                branches[out + 3u] = q4.theta;
+                branches[out + 4u] = q5;
+                branches[out + 5u] = q6.theta;
+                branches[out + 6u] = select(0.0, 1.0, is_ls);
+                branch += 1u;
+            }
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! Writing reachability maps and redundancy landscapes to HDF5 files, for maps too large for CSV.
+//!
+//! Every map is written to a group of its own, so a file can hold the maps of several robots or
+//! poses side by side. Grids are stored as datasets shaped like the grid, with the slowest varying
+//! axis first as numpy and h5py expect:
+//!
+//! - A `ReachabilityMap` has the `u32` datasets `exact`, `ls` and `reachable_orientations` of shape
+//!   `(nz, ny, nx)`, and the attributes `min` and `max`, the corners of the box, and `resolution`.
+//! - The `RedundancyOptimum`s of a sequence of poses have the values of the free parameter `psi`,
+//!   the costs `cost` of shape `(poses, samples)`, infinite where a sample has no exact solution,
+//!   and the optimum of every pose in `optimum_psi`, `optimum_cost` and `optimum_q`, which are NaN
+//!   for poses without one. The `parameter` attribute is `locked_joint` or `arm_angle`.
+//!
+//! The `hdf5` feature links the HDF5 C library, which has to be installed.
+
+use {
+    crate::{
+        error::IkGeoError,
+        redundancy::{RedundancyOptimum, RedundancyParameter},
+        workspace::{ReachabilityMap, VoxelCount, WorkspaceGrid},
+    },
+    ::hdf5::{types::VarLenUnicode, Group, H5Type},
+    nalgebra::Vector3,
+};
+
+fn hdf5_error(e: impl ToString) -> IkGeoError {
+    IkGeoError::InvalidDescription(e.to_string())
+}
+
+/// Write `data` to a new dataset `name` of `group` with the given shape
+fn write_dataset<T: H5Type>(
+    group: &Group,
+    name: &str,
+    shape: &[usize],
+    data: &[T],
+) -> Result<(), IkGeoError> {
+    group
+        .new_dataset::<T>()
+        .shape(shape)
+        .create(name)
+        .and_then(|dataset| dataset.write_raw(data))
+        .map_err(hdf5_error)
+}
+
+/// Write `data` to a new one-dimensional attribute `name` of `group`
+fn write_attribute<T: H5Type>(group: &Group, name: &str, data: &[T]) -> Result<(), IkGeoError> {
+    group
+        .new_attr::<T>()
+        .shape([data.len()])
+        .create(name)
+        .and_then(|attribute| attribute.write_raw(data))
+        .map_err(hdf5_error)
+}
+
+/// The values of the attribute `name` of `group`, which must hold `N` of them
+fn read_attribute<T: H5Type, const N: usize>(
+    group: &Group,
+    name: &str,
+) -> Result<[T; N], IkGeoError> {
+    let values = group
+        .attr(name)
+        .and_then(|attribute| attribute.read_raw::<T>())
+        .map_err(hdf5_error)?;
+    let found = values.len();
+    values
+        .try_into()
+        .map_err(|_| IkGeoError::DimensionMismatch { expected: N, found })
+}
+
+/// Write `map` to a new group `name` of `group`, e.g. of an `hdf5::File`
+pub fn write_reachability(
+    group: &Group,
+    name: &str,
+    map: &ReachabilityMap,
+) -> Result<(), IkGeoError> {
+    let group = group.create_group(name).map_err(hdf5_error)?;
+    let grid = &map.grid;
+    let [nx, ny, nz] = grid.resolution;
+    let shape = [nz, ny, nx];
+
+    let column = |f: fn(&VoxelCount) -> u32| map.voxels.iter().map(f).collect::<Vec<u32>>();
+    write_dataset(&group, "exact", &shape, &column(|count| count.exact))?;
+    write_dataset(&group, "ls", &shape, &column(|count| count.ls))?;
+    write_dataset(
+        &group,
+        "reachable_orientations",
+        &shape,
+        &column(|count| count.reachable_orientations),
+    )?;
+
+    write_attribute(&group, "min", grid.min.as_slice())?;
+    write_attribute(&group, "max", grid.max.as_slice())?;
+    write_attribute(&group, "resolution", &grid.resolution.map(|n| n as u64))
+}
+
+/// Read the map written by `write_reachability` to the group `name` of `group`
+pub fn read_reachability(group: &Group, name: &str) -> Result<ReachabilityMap, IkGeoError> {
+    let group = group.group(name).map_err(hdf5_error)?;
+    let grid = WorkspaceGrid::new(
+        Vector3::from(read_attribute::<f64, 3>(&group, "min")?),
+        Vector3::from(read_attribute::<f64, 3>(&group, "max")?),
+        read_attribute::<u64, 3>(&group, "resolution")?.map(|n| n as usize),
+    );
+
+    let column = |name: &str| -> Result<Vec<u32>, IkGeoError> {
+        let values = group
+            .dataset(name)
+            .and_then(|dataset| dataset.read_raw::<u32>())
+            .map_err(hdf5_error)?;
+        if values.len() != grid.len() {
+            return Err(IkGeoError::DimensionMismatch {
+                expected: grid.len(),
+                found: values.len(),
+            });
+        }
+        Ok(values)
+    };
+    let (exact, ls) = (column("exact")?, column("ls")?);
+    let reachable_orientations = column("reachable_orientations")?;
+
+    let voxels = (0..grid.len())
+        .map(|i| VoxelCount {
+            exact: exact[i],
+            ls: ls[i],
+            reachable_orientations: reachable_orientations[i],
+        })
+        .collect();
+    Ok(ReachabilityMap { grid, voxels })
+}
+
+/// Write the landscapes and optima of `optima`, found by `SevenDofSweep::optimize_redundancy`
+/// over `parameter` for a sequence of poses, to a new group `name` of `group`. Every landscape
+/// must sample the same values, as those of one sweep do.
+pub fn write_redundancy(
+    group: &Group,
+    name: &str,
+    parameter: RedundancyParameter,
+    optima: &[Option<RedundancyOptimum>],
+) -> Result<(), IkGeoError> {
+    let psi: Vec<f64> = optima
+        .iter()
+        .flatten()
+        .next()
+        .map(|optimum| {
+            optimum
+                .landscape
+                .iter()
+                .map(|sample| sample.value)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut cost = Vec::with_capacity(optima.len() * psi.len());
+    let mut optimum_psi = Vec::with_capacity(optima.len());
+    let mut optimum_cost = Vec::with_capacity(optima.len());
+    let mut optimum_q = Vec::with_capacity(7 * optima.len());
+    for optimum in optima {
+        match optimum {
+            Some(optimum) => {
+                if optimum.landscape.len() != psi.len() {
+                    return Err(IkGeoError::DimensionMismatch {
+                        expected: psi.len(),
+                        found: optimum.landscape.len(),
+                    });
+                }
+                cost.extend(optimum.landscape.iter().map(|sample| sample.cost));
+                optimum_psi.push(optimum.value);
+                optimum_cost.push(optimum.cost);
+                optimum_q.extend(optimum.solution.q.iter());
+            }
+            None => {
+                cost.extend(psi.iter().map(|_| f64::INFINITY));
+                optimum_psi.push(f64::NAN);
+                optimum_cost.push(f64::NAN);
+                optimum_q.extend([f64::NAN; 7]);
+            }
+        }
+    }
+
+    let group = group.create_group(name).map_err(hdf5_error)?;
+    write_dataset(&group, "psi", &[psi.len()], &psi)?;
+    write_dataset(&group, "cost", &[optima.len(), psi.len()], &cost)?;
+    write_dataset(&group, "optimum_psi", &[optima.len()], &optimum_psi)?;
+    write_dataset(&group, "optimum_cost", &[optima.len()], &optimum_cost)?;
+    write_dataset(&group, "optimum_q", &[optima.len(), 7], &optimum_q)?;
+
+    let parameter: VarLenUnicode = match parameter {
+        RedundancyParameter::LockedJoint => "locked_joint",
+        RedundancyParameter::ArmAngle => "arm_angle",
+    }
+    .parse()
+    .map_err(hdf5_error)?;
+    group
+        .new_attr::<VarLenUnicode>()
+        .create("parameter")
+        .and_then(|attribute| attribute.write_scalar(&parameter))
+        .map_err(hdf5_error)
+}
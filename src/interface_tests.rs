@@ -1,8 +1,11 @@
-use crate::{inverse_kinematics::hardcoded::setups::{Irb6640, KukaR800FixedQ3, SphericalBot, ThreeParallelBot, TwoParallelBot, Ur5}, robot::{
-    irb6640, spherical, spherical_bot, spherical_two_intersecting, spherical_two_parallel, three_parallel, three_parallel_bot, three_parallel_two_intersecting, two_parallel, two_parallel_bot, ur5, IKSolver, Robot
+use crate::{error::IkGeoError, inverse_kinematics::{auxiliary::{wrap_to_pi, Kinematics, KinematicsDyn}, hardcoded::setups::{hardcoded_setup_from_string, Irb6640, KukaR800FixedQ3, RrcFixedQ6, SphericalBot, ThreeParallelBot, TwoParallelBot, Ur5, UrModel}}, robot::{
+    irb120, irb1200, irb2600, irb4600, irb6640, motoman_gp8, preset, spherical, spherical_bot, spherical_two_intersecting, spherical_two_parallel, three_parallel, three_parallel_bot, three_parallel_two_intersecting, two_parallel, two_parallel_bot, ur5, FanucLrMate200id, IKSolver, IkSolution, Robot, PRESETS, SevenDofSweep, SweepStrategy, UniversalRobots
 }};
 
-use std::f64::consts::PI;
+use crate::pose::Pose;
+use crate::subproblems::{rot, subproblem1, subproblem3};
+use nalgebra::{DVector, Isometry3, Matrix3, SVector, Vector3, Vector6};
+use std::f64::consts::{PI, TAU};
 use rand::prelude::*;
 use rand_pcg::Pcg64;
 use rand_seeder::Seeder;
@@ -22,7 +25,7 @@ fn test_robot(robot: Robot) {
         let solutions = robot.ik(rot, translation);
 
         let mut found = false;
-        for (q, _) in solutions {
+        for IkSolution { q, .. } in solutions {
             let q = [q[0], q[1], q[2], q[3], q[4], q[5]];
             let (rot_test, translation_test) = robot.fk(&q);
 
@@ -40,11 +43,124 @@ fn test_robot(robot: Robot) {
     }
 }
 
+#[test]
+fn test_irb120() {
+    test_robot(irb120());
+}
+
+#[test]
+fn test_irb1200() {
+    test_robot(irb1200());
+}
+
+#[test]
+fn test_irb2600() {
+    test_robot(irb2600());
+}
+
+#[test]
+fn test_irb4600() {
+    test_robot(irb4600());
+}
+
 #[test]
 fn test_irb6640() {
     test_robot(irb6640());
 }
 
+#[test]
+fn test_motoman_gp8() {
+    test_robot(motoman_gp8());
+}
+
+#[test]
+fn test_fanuc_lr_mate_200id() {
+    let robot = FanucLrMate200id::new();
+    let mut rng: Pcg64 = Seeder::from("fanuc").into_rng();
+
+    // J3 is measured from the horizontal, so moving J2 alone keeps the forearm orientation
+    let (rot_a, _) = robot.fk(&[0.0, -0.4, 0.3, 0.0, 0.0, 0.0]);
+    let (rot_b, _) = robot.fk(&[0.0, 0.5, 0.3, 0.0, 0.0, 0.0]);
+    assert!((rot_a - rot_b).norm() < 1e-12);
+
+    for _ in 0..TEST_ITERATIONS {
+        let q = rng.random::<[f64; 6]>().map(|x| x * 2.0 * PI - PI);
+        let (rot, translation) = robot.fk(&q);
+
+        let found = robot.ik(rot, translation).iter().any(|solution| {
+            let q = solution.q;
+            let (rot_test, translation_test) = robot.fk(&[q[0], q[1], q[2], q[3], q[4], q[5]]);
+            (rot - rot_test).norm() < TOLERANCE
+                && (translation - translation_test).norm() < TOLERANCE
+        });
+
+        assert!(found, "Could not find a solution for q: {:?}", q);
+    }
+
+    // The manipulability and metric are those of the kinematics, at the joint angles of the FANUC
+    // joint coordinates
+    let m = Vector6::new(0.1, 0.3, -0.2, 0.4, 0.8, 0.2);
+    let q = robot.robot().mapping().to_joint(&m);
+    let manipulability = robot.manipulability(&m).unwrap();
+    assert!(manipulability > 1e-6);
+    assert_eq!(manipulability, robot.robot().robot().kinematics().manipulability(&q));
+    assert_eq!(robot.joint_metric(), robot.robot().robot().joint_metric());
+    assert_ne!(robot.joint_metric(), crate::metrics::JointMetric::default());
+}
+
+/// Checks that `robot` solves the poses `kin` reaches with `joint` locked at `value` for the
+/// configurations they were reached at. The fixed joint robots search for their solutions, so
+/// fewer configurations are tried than for the closed form robots.
+fn test_fixed_joint_robot(
+    robot: &impl IKSolver,
+    kin: &Kinematics<7, 8>,
+    (joint, value): (usize, f64),
+    seed: &str,
+) {
+    let mut rng: Pcg64 = Seeder::from(seed).into_rng();
+    for _ in 0..100 {
+        let mut q = SVector::<f64, 7>::from(rng.random::<[f64; 7]>().map(|x| x * 2.0 * PI - PI));
+        q[joint] = value;
+        let (rot, translation) = kin.forward_kinematics(&q);
+        let q_partial = q.remove_row(joint);
+
+        let found = robot.ik(rot, translation).iter().any(|solution| {
+            !solution.is_ls && (solution.q - q_partial).map(wrap_to_pi).amax() < TOLERANCE
+        });
+
+        assert!(found, "Could not find a solution for q: {:?}", q);
+    }
+}
+
+#[test]
+fn test_panda_fixed_q4() {
+    use crate::{inverse_kinematics::hardcoded::setups::PandaFixedQ4, robot::PandaFixedQ4 as Panda};
+
+    let kin = PandaFixedQ4::get_kin();
+    test_fixed_joint_robot(&Panda::new(), &kin, (3, PandaFixedQ4::Q4), "panda");
+}
+
+#[test]
+fn test_kinova_gen3_fixed_q3() {
+    use crate::{
+        inverse_kinematics::hardcoded::setups::KinovaGen3FixedQ3, robot::KinovaGen3FixedQ3 as Kinova,
+    };
+
+    let kin = KinovaGen3FixedQ3::get_kin();
+    test_fixed_joint_robot(&Kinova::new(), &kin, (2, KinovaGen3FixedQ3::Q3), "kinova");
+}
+
+#[test]
+fn test_sawyer_fixed_q3() {
+    use crate::{inverse_kinematics::hardcoded::setups::SawyerFixedQ3, robot::SawyerFixedQ3 as Sawyer};
+
+    // The tool of the kinematics tilts the flange, which the solver takes from `get_r_7t`
+    let kin = SawyerFixedQ3::get_kin();
+    let r_7t = kin.tool.rotation.to_rotation_matrix().into_inner();
+    assert!((r_7t - SawyerFixedQ3::get_r_7t()).norm() < 1e-12);
+    test_fixed_joint_robot(&Sawyer::new(), &kin, (2, SawyerFixedQ3::Q3), "sawyer");
+}
+
 #[test]
 fn test_spherical_bot() {
     test_robot(spherical_bot());
@@ -65,6 +181,29 @@ fn test_ur5() {
     test_robot(ur5());
 }
 
+#[test]
+fn test_universal_robots() {
+    let mut rng: Pcg64 = Seeder::from("universal robots").into_rng();
+
+    for model in UrModel::ALL {
+        let robot = UniversalRobots::new(model);
+
+        for _ in 0..100 {
+            let q = rng.random::<[f64; 6]>().map(|x| x * 2.0 * PI - PI);
+            let (rot, translation) = robot.fk(&q);
+
+            let found = robot.ik(rot, translation).iter().any(|solution| {
+                let q = solution.q;
+                let (rot_test, translation_test) = robot.fk(&[q[0], q[1], q[2], q[3], q[4], q[5]]);
+                (rot - rot_test).norm() < TOLERANCE
+                    && (translation - translation_test).norm() < TOLERANCE
+            });
+
+            assert!(found, "Could not find a solution for {:?} at q: {:?}", model, q);
+        }
+    }
+}
+
 #[test]
 fn test_spherical() {
     let kin = SphericalBot::get_kin();
@@ -100,3 +239,4704 @@ fn test_two_parallel() {
     let kin = TwoParallelBot::get_kin();
     test_robot(two_parallel(kin.h, kin.p));
 }
+
+#[test]
+fn test_solve_batch() {
+    let robot = ur5();
+    let mut rng: Pcg64 = Seeder::from("batch").into_rng();
+
+    let poses: Vec<_> = (0..100)
+        .map(|_| robot.fk(&rng.random::<[f64; 6]>().map(|x| x * 2.0 * PI - PI)))
+        .collect();
+
+    let batch = robot.solve_batch(&poses);
+    assert_eq!(batch.len(), poses.len());
+
+    for ((rot, translation), solutions) in poses.iter().zip(batch) {
+        let expected = robot.ik(*rot, *translation);
+        assert_eq!(solutions.len(), expected.len());
+        for (solution, expected) in solutions.iter().zip(expected.iter()) {
+            assert_eq!(solution.is_ls, expected.is_ls);
+            assert_eq!(solution.branch, expected.branch);
+            assert!((solution.q - expected.q).norm() < 1e-12);
+        }
+    }
+}
+
+#[test]
+fn test_setup_from_string() {
+    let (rot, translation) =
+        hardcoded_setup_from_string("1,0,0,0,1,0,0,0,1, 0.5, -0.25, 2").unwrap();
+    assert_eq!(rot, Matrix3::identity());
+    assert_eq!(translation, Vector3::new(0.5, -0.25, 2.0));
+
+    assert_eq!(
+        hardcoded_setup_from_string("1,0,0,0,1,0,0,0,1,0.5,x,2"),
+        Err(IkGeoError::MalformedInput("x".to_string()))
+    );
+    assert_eq!(
+        hardcoded_setup_from_string("1,0,0,0,1,0,0,0,1"),
+        Err(IkGeoError::DimensionMismatch {
+            expected: 12,
+            found: 9
+        })
+    );
+}
+
+#[test]
+fn test_seven_dof_sweep() {
+    let kin = KukaR800FixedQ3::get_kin();
+    let mut rng: Pcg64 = Seeder::from("sweep").into_rng();
+
+    for strategy in [
+        SweepStrategy::Grid(12),
+        SweepStrategy::GoldenSection(12, 1e-6),
+    ] {
+        let sweep = SevenDofSweep::kuka_r800(strategy);
+
+        for _ in 0..20 {
+            let q = SVector::<f64, 7>::from(rng.random::<[f64; 7]>().map(|x| x * 2.0 * PI - PI));
+            let (rot, translation) = kin.forward_kinematics(&q);
+
+            let samples = sweep.solve(rot, translation);
+            assert!(samples.windows(2).all(|w| w[0].q_free <= w[1].q_free));
+
+            let mut found = false;
+            for sample in samples {
+                for IkSolution { q, is_ls, .. } in sample.solutions {
+                    assert_eq!(q[2], sample.q_free);
+
+                    let (rot_test, translation_test) = kin.forward_kinematics(&q);
+                    let error = (rot - rot_test).norm() + (translation - translation_test).norm();
+                    if !is_ls {
+                        assert!(error < TOLERANCE, "Exact solution had error {error:.2e}");
+                        found = true;
+                    }
+                }
+            }
+
+            assert!(found, "No exact solutions found for q: {:?}", q);
+        }
+    }
+}
+
+#[test]
+fn test_general_seven_dof() {
+    let mut rng: Pcg64 = Seeder::from("general seven dof").into_rng();
+
+    for _ in 0..5 {
+        let mut kin = Kinematics::<7, 8>::new();
+        kin.h = kin.h.map(|_| rng.random::<f64>() * 2.0 - 1.0);
+        for mut h in kin.h.column_iter_mut() {
+            h.normalize_mut();
+        }
+        kin.p = kin.p.map(|_| rng.random::<f64>() * 2.0 - 1.0);
+
+        // Lock the fourth joint on a sample of the sweep
+        let mut q = SVector::<f64, 7>::from(rng.random::<[f64; 7]>().map(|x| x * 2.0 * PI - PI));
+        q[3] = -PI + TAU * 3.0 / 8.0;
+        let (rot, translation) = kin.forward_kinematics(&q);
+
+        let sweep = SevenDofSweep::general(kin, 3, SweepStrategy::Grid(8));
+        let samples = sweep.solve(rot, translation);
+        assert_eq!(samples.len(), 8);
+
+        let sample = &samples[3];
+        assert_eq!(sample.q_free, q[3]);
+        assert!(
+            sample
+                .solutions
+                .iter()
+                .any(|solution| solution.error < TOLERANCE
+                    && (solution.q - q).map(wrap_to_pi).amax() < 1e-3),
+            "q: {:?} not found among {:?}",
+            q,
+            sample.solutions
+        );
+    }
+}
+
+#[test]
+fn test_arm_angle() {
+    let kin = KukaR800FixedQ3::get_kin();
+    let sweep = SevenDofSweep::kuka_r800(SweepStrategy::Grid(24));
+    let mut rng: Pcg64 = Seeder::from("arm angle").into_rng();
+
+    for _ in 0..20 {
+        let q = SVector::<f64, 7>::from(rng.random::<[f64; 7]>().map(|x| x * 2.0 * PI - PI));
+        let (rot, translation) = kin.forward_kinematics(&q);
+        let psi = sweep.arm_angle_of(&q);
+
+        let solutions = sweep.solve_with_arm_angle(rot, translation, psi);
+        assert!(
+            solutions.iter().any(|solution| !solution.is_ls),
+            "No exact solutions found for q: {:?}",
+            q
+        );
+
+        for IkSolution { q, is_ls, .. } in solutions {
+            let (rot_test, translation_test) = kin.forward_kinematics(&q);
+            let error = (rot - rot_test).norm() + (translation - translation_test).norm();
+            if !is_ls {
+                assert!(error < TOLERANCE, "Exact solution had error {error:.2e}");
+            }
+            assert!((sweep.arm_angle_of(&q) - psi).abs() < 1e-6);
+        }
+    }
+}
+
+
+#[test]
+fn test_kinematics_dyn() {
+    let kin = KukaR800FixedQ3::get_kin();
+    let kin_dyn = KinematicsDyn::from(&kin);
+    let mut rng: Pcg64 = Seeder::from("kinematics dyn").into_rng();
+
+    assert_eq!(kin_dyn.num_joints(), 7);
+    assert_eq!(
+        Kinematics::<6, 7>::try_from(&kin_dyn).err(),
+        Some(IkGeoError::DimensionMismatch {
+            expected: 6,
+            found: 7
+        })
+    );
+    assert_eq!(
+        Kinematics::<7, 7>::try_from(&kin_dyn).err(),
+        Some(IkGeoError::DimensionMismatch {
+            expected: 7,
+            found: 8
+        })
+    );
+    let kin_back: Kinematics<7, 8> = (&kin_dyn).try_into().unwrap();
+    assert_eq!(kin_back.h, kin.h);
+    assert_eq!(kin_back.p, kin.p);
+
+    assert_eq!(
+        kin_dyn.forward_kinematics(&DVector::zeros(6)),
+        Err(IkGeoError::DimensionMismatch {
+            expected: 7,
+            found: 6
+        })
+    );
+
+    for _ in 0..100 {
+        let q = SVector::<f64, 7>::from(rng.random::<[f64; 7]>().map(|x| x * 2.0 * PI - PI));
+        let q_dyn = DVector::from_column_slice(q.as_slice());
+
+        let (rot, translation) = kin.forward_kinematics(&q);
+        let (rot_dyn, translation_dyn) = kin_dyn.forward_kinematics(&q_dyn).unwrap();
+        assert!((rot - rot_dyn).norm() + (translation - translation_dyn).norm() < 1e-12);
+
+        let (kin_partial, r_6t) = kin.forward_kinematics_partial(q[2], 2, &Matrix3::identity());
+        let (kin_dyn_partial, r_6t_dyn) = kin_dyn
+            .forward_kinematics_partial(q[2], 2, &Matrix3::identity())
+            .unwrap();
+        assert_eq!(KinematicsDyn::from(&kin_partial), kin_dyn_partial);
+        assert_eq!(r_6t, r_6t_dyn);
+
+        // Compare the Jacobian against central finite differences
+        let jacobian = kin_dyn.jacobian(&q_dyn).unwrap();
+        let step = 1e-6;
+        for i in 0..7 {
+            let mut q_plus = q_dyn.clone();
+            let mut q_minus = q_dyn.clone();
+            q_plus[i] += step;
+            q_minus[i] -= step;
+
+            let (rot_plus, translation_plus) = kin_dyn.forward_kinematics(&q_plus).unwrap();
+            let (rot_minus, translation_minus) = kin_dyn.forward_kinematics(&q_minus).unwrap();
+
+            let omega = (rot_plus - rot_minus) / (2.0 * step) * rot.transpose();
+            let velocity = (translation_plus - translation_minus) / (2.0 * step);
+            let expected = Vector6::new(
+                omega[(2, 1)],
+                omega[(0, 2)],
+                omega[(1, 0)],
+                velocity[0],
+                velocity[1],
+                velocity[2],
+            );
+
+            assert!((jacobian.column(i) - expected).norm() < 1e-6);
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde() {
+    use crate::robot::IkSolutionSet;
+
+    let kin = KukaR800FixedQ3::get_kin();
+    let json = serde_json::to_string(&kin).unwrap();
+    assert!(json.starts_with(r#"{"h":[[0.0,0.0,1.0],[0.0,1.0,0.0]"#));
+
+    let kin_back: Kinematics<7, 8> = serde_json::from_str(&json).unwrap();
+    assert_eq!(kin_back.h, kin.h);
+    assert_eq!(kin_back.p, kin.p);
+    assert!(serde_json::from_str::<Kinematics<6, 7>>(&json).is_err());
+
+    let kin_dyn: KinematicsDyn = serde_json::from_str(&json).unwrap();
+    assert_eq!(kin_dyn, KinematicsDyn::from(&kin));
+    assert!(!json.contains("tool"));
+
+    let kin_tool = kin
+        .clone()
+        .with_tool(Pose::new(rot(&Vector3::x(), 0.5), Vector3::z()).into());
+    let json = serde_json::to_string(&kin_tool).unwrap();
+    let kin_back: Kinematics<7, 8> = serde_json::from_str(&json).unwrap();
+    assert!((kin_back.tool.to_homogeneous() - kin_tool.tool.to_homogeneous()).norm() < 1e-12);
+
+    let pose = Pose::from(kin.forward_kinematics(&SVector::<f64, 7>::from([0.1; 7])));
+    let json = serde_json::to_string(&pose).unwrap();
+    let pose_back: Pose = serde_json::from_str(&json).unwrap();
+    assert!((pose_back.rotation - pose.rotation).norm() < 1e-12);
+    assert!((pose_back.translation - pose.translation).norm() < 1e-12);
+
+    let value: serde_json::Value = serde_json::to_value(Pose::identity()).unwrap();
+    assert_eq!(value["rotation"][0], serde_json::json!([1.0, 0.0, 0.0]));
+
+    let robot = ur5();
+    let solutions = robot.ik(pose.rotation, pose.translation);
+    let json = serde_json::to_string(&solutions).unwrap();
+    let solutions_back: IkSolutionSet = serde_json::from_str(&json).unwrap();
+    assert_eq!(solutions_back.len(), solutions.len());
+    for (solution_back, solution) in solutions_back.iter().zip(solutions.iter()) {
+        assert!((solution_back.q - solution.q).norm() < 1e-12);
+        assert_eq!(solution_back.is_ls, solution.is_ls);
+        assert_eq!(solution_back.branch, solution.branch);
+    }
+}
+
+#[test]
+fn test_solution_set() {
+    let robot = ur5();
+    let mut rng: Pcg64 = Seeder::from("solution set").into_rng();
+
+    for _ in 0..100 {
+        let q = rng.random::<[f64; 6]>().map(|x| x * 2.0 * PI - PI);
+        let (rot, translation) = robot.fk(&q);
+        let mut solutions = robot.ik(rot, translation);
+        assert!(!solutions.is_empty());
+
+        for (i, solution) in solutions.iter().enumerate() {
+            assert_eq!(solution.branch, i);
+
+            let q = solution.q;
+            let (rot_test, translation_test) = robot.fk(&[q[0], q[1], q[2], q[3], q[4], q[5]]);
+            let error = (rot - rot_test).norm() + (translation - translation_test).norm();
+            assert!((solution.error - error).abs() < 1e-9);
+        }
+
+        let best = solutions.best().unwrap().clone();
+        solutions.sort_by_error();
+        let errors: Vec<f64> = solutions.iter().map(|solution| solution.error).collect();
+        assert!(errors.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(solutions[0], best);
+
+        let exact = solutions.clone().exact();
+        assert!(exact.iter().all(|solution| !solution.is_ls));
+        assert_eq!(
+            exact.len(),
+            solutions.iter().filter(|solution| !solution.is_ls).count()
+        );
+        let within = solutions.within(TOLERANCE);
+        assert!(within.iter().all(|solution| solution.error <= TOLERANCE));
+    }
+}
+
+#[test]
+fn test_subproblem_least_squares() {
+    let k: Vector3<f64> = Vector3::z();
+    let p1 = Vector3::new(1.0, 0.0, 0.5);
+
+    let (theta, is_ls) = subproblem1(&p1, &(rot(&k, 0.3) * p1), &k);
+    assert!(!is_ls);
+    assert!((theta - 0.3).abs() < 1e-12);
+
+    // `p2` is out of reach, so the closest angle is returned
+    let (theta, is_ls) = subproblem1(&p1, &Vector3::new(0.0, 2.0, 0.5), &k);
+    assert!(is_ls);
+    assert!((theta - PI / 2.0).abs() < 1e-12);
+
+    let p2 = Vector3::new(1.0, 0.0, 0.5);
+    let (thetas, is_ls) = subproblem3(&p1, &p2, &k, 1.0);
+    assert!(!is_ls);
+    assert_eq!(thetas.size(), 2);
+    for theta in thetas.get_all() {
+        assert!(((rot(&k, theta) * p1 - p2).norm() - 1.0).abs() < 1e-12);
+    }
+
+    let (thetas, is_ls) = subproblem3(&p1, &Vector3::new(3.0, 0.0, 0.5), &k, 1.0);
+    assert!(is_ls);
+    assert_eq!(thetas.size(), 1);
+    assert!(thetas.get_first().abs() < 1e-12);
+}
+
+#[test]
+fn test_solver_config() {
+    use crate::{
+        config::SolverConfig,
+        robot::IkSolutionSet,
+        subproblems::{subproblem1_with_tolerance, subproblem2, subproblem2_with_tolerance},
+    };
+
+    // `p2` is 1e-5 too long, which only counts as exact with a looser tolerance
+    let k: Vector3<f64> = Vector3::z();
+    let p1 = Vector3::new(1.0, 0.0, 0.5);
+    let p2 = rot(&k, 0.3) * p1 * (1.0 + 1e-5);
+    assert!(subproblem1(&p1, &p2, &k).1);
+    assert!(!subproblem1_with_tolerance(&p1, &p2, &k, 1e-3).1);
+
+    // Subproblem 2 keeps its stricter default, so a length 1e-7 off stays least squares in the
+    // solvers as it is for `subproblem2`
+    let config = SolverConfig::default();
+    assert_eq!(config.subproblem2_ls_tolerance, 1e-8);
+    let k2 = Vector3::x();
+    let p2 = rot(&k2, -0.4) * rot(&k, 0.3) * p1 * (1.0 + 1e-7);
+    assert!(subproblem2(&p1, &p2, &k, &k2).1);
+    assert!(subproblem2_with_tolerance(&p1, &p2, &k, &k2, config.subproblem2_ls_tolerance).1);
+    assert!(!subproblem2_with_tolerance(&p1, &p2, &k, &k2, config.ls_tolerance).1);
+
+    // The same robot in millimeters is solved with a tolerance scaled to match
+    let mut kin = Ur5::get_kin();
+    kin.p *= 1000.0;
+    let config = SolverConfig {
+        ls_tolerance: 1e-3,
+        ..SolverConfig::default()
+    };
+    let robot = Robot::three_parallel_two_intersecting(kin).with_config(config);
+    assert_eq!(robot.config(), &config);
+
+    let (r, t) = robot.fk(&[0.3, -1.2, 1.5, 0.4, 1.0, -0.7]);
+    let solutions = robot.ik(r, t);
+    assert!(!solutions.is_empty());
+    assert!(solutions.iter().all(|solution| !solution.is_ls));
+
+    let mut duplicated: IkSolutionSet = solutions.iter().chain(&solutions).cloned().collect();
+    duplicated.dedup(config.dedup_tolerance);
+    assert_eq!(duplicated.len(), solutions.len());
+    assert!(duplicated
+        .iter()
+        .zip(&solutions)
+        .all(|(merged, solution)| merged.multiplicity == 2 * solution.multiplicity));
+
+    let invalid = SolverConfig {
+        singular_tolerance: -1.0,
+        ..SolverConfig::default()
+    };
+    assert!(crate::robot::RrcFixedQ6::with_config(invalid).is_err());
+    assert!(crate::robot::RrcFixedQ6::with_config(SolverConfig::default()).is_ok());
+    let (r, t) = (Matrix3::identity(), Vector3::new(0.5, 0.0, 0.5));
+    assert!(crate::inverse_kinematics::hardcoded::rrc_fixed_q6(&r, &t, &invalid).is_err());
+}
+
+#[test]
+fn test_dedup() {
+    use crate::robot::IkSolutionSet;
+
+    let robot = irb6640();
+    let q = Vector6::new(0.3, 0.2, -0.1, 0.4, 0.5, 0.2);
+    let (r, t) = robot.fk(&q.into());
+    let solutions = robot.ik(r, t);
+
+    for (i, a) in solutions.iter().enumerate() {
+        for b in solutions.iter().skip(i + 1) {
+            assert!((a.q - b.q).map(wrap_to_pi).amax() > 1e-6);
+        }
+    }
+
+    // Solutions a full turn apart are the same
+    let shifted = solutions.iter().map(|solution| IkSolution {
+        q: solution.q.add_scalar(TAU),
+        error: 0.0,
+        ..solution.clone()
+    });
+    let mut wrapped: IkSolutionSet = solutions.iter().cloned().chain(shifted).collect();
+    wrapped.dedup(1e-6);
+    assert_eq!(wrapped.len(), solutions.len());
+    for (merged, solution) in wrapped.iter().zip(&solutions) {
+        assert_eq!(merged.error, 0.0);
+        assert_eq!(merged.multiplicity, 2 * solution.multiplicity);
+    }
+}
+
+#[test]
+fn test_residuals() {
+    let kin = Irb6640::get_kin();
+    let robot = irb6640();
+    let mut rng: Pcg64 = Seeder::from("residuals").into_rng();
+
+    for _ in 0..100 {
+        let q = rng.random::<[f64; 6]>().map(|x| x * 2.0 * PI - PI);
+        let (r, t) = kin.forward_kinematics(&Vector6::from(q));
+
+        // The residuals reported by the solver are those of a full FK pass
+        let mut solutions = robot.ik(r, t);
+        for solution in solutions.iter() {
+            assert!((solution.residual(&kin, &r, &t) - solution.error).abs() < 1e-12);
+        }
+
+        // Against a target 1 cm away no solution is exact
+        let offset = t + Vector3::new(0.01, 0.0, 0.0);
+        solutions.compute_errors(&kin, &r, &offset);
+        assert!(solutions.iter().all(|solution| solution.error > 1e-3));
+    }
+}
+
+#[test]
+fn test_chains() {
+    use crate::{
+        config::SolverConfig,
+        inverse_kinematics::chains::{planar_2r, planar_3r, spherical_wrist_3r},
+    };
+    use nalgebra::{Matrix3x2, Matrix3x4, Vector2};
+
+    let config = SolverConfig::default();
+    let mut rng: Pcg64 = Seeder::from("chains").into_rng();
+    let random_vector =
+        |rng: &mut Pcg64| Vector3::from(rng.random::<[f64; 3]>().map(|x| x * 2.0 - 1.0));
+
+    for _ in 0..100 {
+        let h = random_vector(&mut rng).normalize();
+        let angles = random_vector(&mut rng) * PI;
+
+        let mut kin_2r: Kinematics<2, 3> = Kinematics::new();
+        kin_2r.h = Matrix3x2::from_columns(&[h, h]);
+        kin_2r.p = Matrix3::from_columns(&[
+            random_vector(&mut rng),
+            random_vector(&mut rng),
+            random_vector(&mut rng),
+        ]);
+        let q = Vector2::new(angles[0], angles[1]);
+        let (_, p) = kin_2r.forward_kinematics(&q);
+        let solutions = planar_2r(&p, &kin_2r, &config);
+        assert!(solutions
+            .iter()
+            .any(|solution| !solution.is_ls && solution.error < TOLERANCE));
+
+        let mut kin_3r: Kinematics<3, 4> = Kinematics::new();
+        kin_3r.h = Matrix3::from_columns(&[h, h, -h]);
+        kin_3r.p = Matrix3x4::from_fn(|_, _| 0.0);
+        for i in 0..4 {
+            kin_3r.p.set_column(i, &random_vector(&mut rng));
+        }
+        let (r, p) = kin_3r.forward_kinematics(&angles);
+        let solutions = planar_3r(&r, &p, &kin_3r, &config);
+        assert!(solutions
+            .iter()
+            .any(|solution| !solution.is_ls && solution.error < TOLERANCE));
+
+        // Out of plane rotations can only be approximated
+        let tilted = rot(&h.cross(&Vector3::new(h.y, h.z, h.x)).normalize(), 0.1) * r;
+        assert!(planar_3r(&tilted, &p, &kin_3r, &config)
+            .iter()
+            .all(|solution| solution.is_ls));
+
+        let mut kin_wrist: Kinematics<3, 4> = Kinematics::new();
+        kin_wrist.h = Matrix3::from_columns(&[
+            random_vector(&mut rng).normalize(),
+            random_vector(&mut rng).normalize(),
+            random_vector(&mut rng).normalize(),
+        ]);
+        let (r, _) = kin_wrist.forward_kinematics(&angles);
+        let solutions = spherical_wrist_3r(&r, &kin_wrist, &config);
+        assert!(solutions
+            .iter()
+            .any(|solution| (solution.q - angles).map(wrap_to_pi).amax() < 1e-6));
+    }
+}
+
+#[test]
+fn test_scara_palletizer() {
+    use crate::{
+        config::SolverConfig,
+        inverse_kinematics::{
+            auxiliary::JointType,
+            chains::{palletizer, palletizer_joints, scara},
+            hardcoded::{epson_ls6, setups::EpsonLs6},
+        },
+    };
+    use nalgebra::{Matrix3x5, Matrix3x6, Vector4};
+
+    let config = SolverConfig::default();
+    let mut rng: Pcg64 = Seeder::from("scara").into_rng();
+    let ex = Vector3::x();
+    let ey = Vector3::y();
+    let ez = Vector3::z();
+
+    let scara_kin = EpsonLs6::get_kin();
+    assert_eq!(scara_kin.joint_types[2], JointType::Prismatic);
+
+    let mut palletizer_kin: Kinematics<5, 6> = Kinematics::new();
+    palletizer_kin.h = Matrix3x5::from_columns(&[ez, ey, ey, ey, ez]);
+    palletizer_kin.p = Matrix3x6::from_columns(&[
+        0.5 * ez,
+        0.2 * ex + 0.05 * ey,
+        0.9 * ez,
+        1.1 * ex,
+        0.25 * ex - 0.1 * ez,
+        -0.2 * ez,
+    ]);
+
+    for _ in 0..100 {
+        let [a, b, c, d] = rng.random::<[f64; 4]>();
+        let q = Vector4::new(a * TAU - PI, b * TAU - PI, c * 0.2, d * TAU - PI);
+        let (r, p) = scara_kin.forward_kinematics(&q);
+        let solutions = epson_ls6(&r, &p, &config);
+        assert!(solutions
+            .iter()
+            .any(|solution| !solution.is_ls && (solution.q - q).map(wrap_to_pi).amax() < 1e-6));
+        assert_eq!(scara(&r, &p, &scara_kin, &config).len(), solutions.len());
+
+        // The tool can only turn about the vertical axis
+        let tilted = rot(&ex, 0.1) * r;
+        assert!(epson_ls6(&tilted, &p, &config)
+            .iter()
+            .all(|solution| solution.is_ls));
+
+        let q = Vector4::new(a * TAU - PI, b * 1.5 - 0.75, c * 1.5 - 0.75, d * TAU - PI);
+        let (r, p) = palletizer_kin.forward_kinematics(&palletizer_joints(&q));
+        let (level, _) = palletizer_kin.forward_kinematics(&palletizer_joints(&Vector4::new(
+            q[0], 0.0, 0.0, q[3],
+        )));
+        assert!((r - level).norm() < 1e-9);
+
+        let solutions = palletizer(&r, &p, &palletizer_kin, &config);
+        assert!(solutions
+            .iter()
+            .any(|solution| !solution.is_ls && (solution.q - q).map(wrap_to_pi).amax() < 1e-6));
+        assert!(solutions
+            .iter()
+            .all(|solution| solution.is_ls || solution.error < TOLERANCE));
+    }
+}
+
+#[test]
+fn test_delta() {
+    use crate::parallel::{sphere_intersection, Delta};
+
+    let mut rng: Pcg64 = Seeder::from("delta").into_rng();
+    let delta = Delta::new(0.2, 0.35, 0.8, 0.05);
+
+    for _ in 0..1000 {
+        let q = Vector3::from(rng.random::<[f64; 3]>().map(|x| x * 1.5 - 0.5));
+        let (p, is_ls) = delta.forward_kinematics(&q);
+        assert!(!is_ls);
+        assert!(delta
+            .elbows(&q)
+            .iter()
+            .all(|elbow| elbow.z > p.z));
+
+        let (q_ik, is_ls) = delta.inverse_kinematics(&p);
+        assert!(!is_ls);
+        assert!((q_ik - q).norm() < 1e-9);
+    }
+
+    // Far out of reach, every arm stretches toward the target
+    let (q, is_ls) = delta.inverse_kinematics(&Vector3::new(0.0, 0.0, -2.0));
+    assert!(is_ls);
+    assert!(q.iter().all(|&q_i| (q_i - q[0]).abs() < 1e-9));
+
+    let centers = [Vector3::x(), Vector3::y(), Vector3::zeros()];
+    let (points, is_ls) = sphere_intersection(&centers, &[1.0, 1.0, 1.0]);
+    assert!(!is_ls);
+    for point in points.get_all() {
+        assert!(centers
+            .iter()
+            .all(|center| ((point - center).norm() - 1.0).abs() < 1e-12));
+    }
+    assert!(sphere_intersection(&centers, &[0.1, 0.1, 0.1]).1);
+}
+
+#[test]
+fn test_stewart_platform() {
+    use crate::parallel::StewartPlatform;
+    use nalgebra::{Isometry3, Translation3, UnitQuaternion};
+
+    let mut rng: Pcg64 = Seeder::from("stewart").into_rng();
+    let platform = StewartPlatform::symmetric(1.0, 0.6, 0.3, 0.3);
+    let home = Isometry3::translation(0.0, 0.0, 1.0);
+    let random_vector =
+        |rng: &mut Pcg64| Vector3::from(rng.random::<[f64; 3]>().map(|x| x * 2.0 - 1.0));
+
+    for _ in 0..100 {
+        let pose = Isometry3::from_parts(
+            Translation3::from(home.translation.vector + random_vector(&mut rng) * 0.1),
+            UnitQuaternion::from_scaled_axis(random_vector(&mut rng) * 0.2),
+        );
+        let lengths = platform.inverse_kinematics(&pose);
+
+        let (found, is_ls) = platform.forward_kinematics(&lengths, &home, 1e-12, 20);
+        assert!(!is_ls);
+        assert!((found.to_homogeneous() - pose.to_homogeneous()).norm() < 1e-9);
+
+        // Compare the Jacobian to finite differences of the leg lengths
+        let jacobian = platform.jacobian(&pose);
+        let step = 1e-6;
+        for axis in 0..3 {
+            let rotated = Isometry3::from_parts(
+                pose.translation,
+                UnitQuaternion::from_scaled_axis(Vector3::ith(axis, step)) * pose.rotation,
+            );
+            let moved = Isometry3::from_parts(
+                Translation3::from(pose.translation.vector + Vector3::ith(axis, step)),
+                pose.rotation,
+            );
+            let d_rotated = (platform.inverse_kinematics(&rotated) - lengths) / step;
+            let d_moved = (platform.inverse_kinematics(&moved) - lengths) / step;
+            assert!((jacobian.column(axis) - d_rotated).norm() < 1e-5);
+            assert!((jacobian.column(axis + 3) - d_moved).norm() < 1e-5);
+        }
+    }
+
+    // Legs too short for any pose of the platform
+    let (_, is_ls) = platform.forward_kinematics(&SVector::<f64, 6>::repeat(0.1), &home, 1e-12, 20);
+    assert!(is_ls);
+}
+
+#[test]
+fn test_calibration() {
+    use crate::{calibration::CalibrationSet, config::SolverConfig, robot::KinematicFamily};
+
+    let mut rng: Pcg64 = Seeder::from("calibration").into_rng();
+    let random_vector =
+        |rng: &mut Pcg64| Vector3::from(rng.random::<[f64; 3]>().map(|x| x * 2.0 - 1.0));
+
+    let mut calibration = CalibrationSet::new();
+    assert!(calibration.is_zero());
+    for i in 0..6 {
+        calibration = calibration.with_axis_error(i, random_vector(&mut rng) * 1e-3);
+    }
+    for i in 0..7 {
+        calibration = calibration.with_offset_error(i, random_vector(&mut rng) * 1e-3);
+    }
+
+    let robot = irb6640().with_calibration(&calibration);
+    let calibrated = robot.kinematics().clone();
+    assert!(calibrated.h.column_iter().all(|h| (h.norm() - 1.0).abs() < 1e-12));
+    assert!((robot.nominal_kinematics().p - irb6640().kinematics().p).norm() < 1e-12);
+    assert_eq!(
+        KinematicFamily::classify(&calibrated, SolverConfig::default().ls_tolerance),
+        KinematicFamily::GenSixDof
+    );
+
+    // Calibrating again replaces the calibration rather than adding to it
+    let recalibrated = robot.with_calibration(&calibration);
+    assert!((recalibrated.kinematics().h - calibrated.h).norm() < 1e-12);
+
+    for _ in 0..100 {
+        let q = Vector6::from(rng.random::<[f64; 6]>().map(|x| x * 2.0 * PI - PI));
+        let (r, t) = calibrated.forward_kinematics(&q);
+        let solutions = recalibrated.ik(r, t);
+        assert!(solutions
+            .iter()
+            .any(|solution| !solution.is_ls && (solution.q - q).map(wrap_to_pi).amax() < 1e-6));
+        assert!(solutions
+            .iter()
+            .all(|solution| solution.is_ls || solution.error < 1e-9));
+
+        // The nominal solution is close, and refining it reaches the calibrated pose
+        let nominal = irb6640().ik(r, t);
+        let closest = nominal
+            .iter()
+            .min_by(|a, b| {
+                let distance = |q_a: &Vector6<f64>| (q_a - q).map(wrap_to_pi).norm();
+                distance(&a.q).total_cmp(&distance(&b.q))
+            })
+            .unwrap();
+        let refined = calibrated.refine(&closest.q, &r, &t, 10);
+        let (r_q, t_q) = calibrated.forward_kinematics(&refined);
+        assert!((r_q - r).norm() + (t_q - t).norm() < 1e-9);
+    }
+}
+
+#[test]
+fn test_refine() {
+    use crate::config::SolverConfig;
+
+    let mut rng: Pcg64 = Seeder::from("refine").into_rng();
+    let config = SolverConfig {
+        refine: true,
+        ..SolverConfig::default()
+    };
+    let kin = ur5().kinematics().clone();
+    let robot = ur5().with_config(config);
+
+    for _ in 0..20 {
+        let q = Vector6::from(rng.random::<[f64; 6]>().map(|x| x * 2.0 * PI - PI));
+        let (r, t) = kin.forward_kinematics(&q);
+        let solutions = robot.ik(r, t);
+        assert!(solutions
+            .iter()
+            .any(|solution| !solution.is_ls && (solution.q - q).map(wrap_to_pi).amax() < 1e-6));
+
+        for solution in &solutions {
+            let raw = solution.raw.expect("refined solutions keep the raw solution");
+            assert!(solution.error <= raw.error);
+            assert!(!raw.is_ls || solution.is_ls || solution.error < config.ls_tolerance);
+            if !solution.is_ls {
+                assert!(solution.error < 1e-12);
+            }
+        }
+    }
+
+    // Out of reach, the refined solutions stay least squares but get no worse
+    let solutions = robot.ik(Matrix3::identity(), Vector3::new(10.0, 0.0, 0.0));
+    assert!(solutions.iter().all(|solution| {
+        solution.is_ls && solution.error <= solution.raw.unwrap().error
+    }));
+    assert!(ur5()
+        .ik(Matrix3::identity(), Vector3::new(10.0, 0.0, 0.0))
+        .iter()
+        .all(|solution| solution.raw.is_none()));
+}
+
+#[cfg(feature = "interval")]
+#[test]
+fn test_interval_subproblems() {
+    use crate::subproblems::{
+        interval::{
+            interval_subproblem1, interval_subproblem2, interval_subproblem3,
+            interval_subproblem4, Interval, Reachability,
+        },
+        subproblem2, subproblem4,
+    };
+
+    let mut rng: Pcg64 = Seeder::from("interval").into_rng();
+    let random_vector =
+        |rng: &mut Pcg64| Vector3::from(rng.random::<[f64; 3]>().map(|x| x * 2.0 - 1.0));
+    // Angles may be enclosed a turn away from where the float solver puts them
+    let encloses = |interval: &Interval, theta: f64| {
+        [-TAU, 0.0, TAU]
+            .iter()
+            .any(|turn| interval.contains(theta + turn))
+    };
+
+    let a = Interval::new(-1.0, 2.0);
+    let b = Interval::point(3.0);
+    assert!((a * b).contains(-3.0) && (a * b).contains(6.0));
+    assert_eq!(a.sqr().lo, 0.0);
+    assert!((Interval::point(1.0) / Interval::point(3.0)).contains(1.0 / 3.0));
+    assert!((b / a).lo.is_infinite());
+
+    for _ in 0..1000 {
+        let k = random_vector(&mut rng).normalize();
+        let k2 = random_vector(&mut rng).normalize();
+        let p1 = random_vector(&mut rng);
+        let h = random_vector(&mut rng).normalize();
+        let theta = rng.random::<f64>() * TAU - PI;
+
+        let theta_1 = interval_subproblem1(&p1, &(rot(&k, theta) * p1), &k);
+        assert!(encloses(&theta_1, theta));
+        assert!(theta_1.width() < 1e-9);
+
+        let p2 = random_vector(&mut rng);
+        let d = (rot(&k, theta) * p1 - p2).norm();
+        let (theta_3, reachability) = interval_subproblem3(&p1, &p2, &k, d);
+        assert_ne!(reachability, Reachability::Unreachable);
+        assert!(theta_3.get_all().any(|interval| encloses(&interval, theta)));
+        let (_, reachability) = interval_subproblem3(&p1, &p2, &k, d + 10.0);
+        assert_eq!(reachability, Reachability::Unreachable);
+
+        let d = h.dot(&(rot(&k, theta) * p1));
+        let (theta_4, reachability) = interval_subproblem4(&h, &p1, &k, d);
+        assert!(theta_4.get_all().any(|interval| encloses(&interval, theta)));
+        let (float, is_ls) = subproblem4(&h, &p1, &k, d);
+        if reachability == Reachability::Reachable {
+            assert!(!is_ls);
+            for (interval, theta) in theta_4.get_all().zip(float.get_all()) {
+                assert!(encloses(&interval, theta));
+            }
+        }
+
+        let theta_2 = rng.random::<f64>() * TAU - PI;
+        let p2 = rot(&k2, -theta_2) * rot(&k, theta) * p1;
+        let (pairs, _) = interval_subproblem2(&p1, &p2, &k, &k2);
+        let (float_pairs, _) = subproblem2(&p1, &p2, &k, &k2);
+        assert!(pairs
+            .get_all()
+            .any(|(i_1, i_2)| encloses(&i_1, theta) && encloses(&i_2, theta_2)));
+        for ((i_1, i_2), (t_1, t_2)) in pairs.get_all().zip(float_pairs.get_all()) {
+            assert!(encloses(&i_1, t_1) && encloses(&i_2, t_2));
+        }
+    }
+
+    // A distance exactly at the edge of the reachable range cannot be decided
+    let p1 = Vector3::x();
+    let p2 = Vector3::new(2.0, 0.0, 0.0);
+    let (theta, reachability) = interval_subproblem3(&p1, &p2, &Vector3::z(), 1.0);
+    assert_eq!(reachability, Reachability::Undecided);
+    assert!(theta.get_all().all(|interval| encloses(&interval, 0.0)));
+}
+
+#[test]
+fn test_singular_policy() {
+    use crate::config::{SingularBranchPolicy, SolverConfig};
+
+    let kin = irb6640().kinematics().clone();
+    let q = Vector6::new(0.3, 0.2, -0.4, 0.5, 0.0, -0.7);
+    let (r, t) = kin.forward_kinematics(&q);
+    assert_eq!(kin.coincident_axes(&q, 1e-9), vec![(3, 5, 1.0)]);
+
+    // Points along the continuum reach the same pose, so they keep the residual
+    let keeps_error = |solution: &IkSolution| {
+        let (r_q, t_q) = kin.forward_kinematics(&solution.q);
+        (SolverConfig::default().pose_error(&r_q, &t_q, &r, &t) - solution.error).abs() < 1e-9
+    };
+    let solve = |singular_policy| {
+        irb6640()
+            .with_config(SolverConfig {
+                singular_policy,
+                ..SolverConfig::default()
+            })
+            .ik(r, t)
+    };
+
+    let flagged = solve(SingularBranchPolicy::FlagDegenerate);
+    let degenerate = flagged.iter().filter(|solution| solution.degenerate).count();
+    assert!(degenerate > 0);
+
+    let both = solve(SingularBranchPolicy::ReturnBoth);
+    assert_eq!(both.len(), flagged.len() + degenerate);
+    assert!(both.iter().filter(|solution| solution.degenerate).all(keeps_error));
+
+    let continuum = solve(SingularBranchPolicy::ReturnContinuum { samples: 8 });
+    assert_eq!(continuum.len(), flagged.len() + 7 * degenerate);
+    assert!(continuum.iter().filter(|solution| solution.degenerate).all(keeps_error));
+    // The continuum trades the fourth joint against the sixth
+    assert!(continuum
+        .iter()
+        .filter(|solution| solution.degenerate)
+        .all(|solution| solution.q[4].abs() < 1e-6));
+
+    let (r, t) = kin.forward_kinematics(&Vector6::new(0.3, 0.2, -0.4, 0.5, 0.6, -0.7));
+    assert!(irb6640().ik(r, t).iter().all(|solution| !solution.degenerate));
+}
+
+#[test]
+fn test_search_1d() {
+    use crate::inverse_kinematics::auxiliary::search_1d;
+    use nalgebra::Vector4;
+
+    let mut zeros = search_1d(
+        |x| {
+            Vector4::new(
+                // Two zeros closer together than the samples
+                (x - 0.5) * (x - 0.5005),
+                // A zero the error touches without changing sign
+                (x + 1.0) * (x + 1.0),
+                // A sign change that is not a zero
+                if x < 0.3 { -1.0 } else { 1.0 },
+                // A branch without solutions
+                f64::INFINITY,
+            )
+        },
+        -PI,
+        PI,
+    );
+
+    let expected = [(0.5, 0), (0.5005, 0), (-1.0, 1)];
+    zeros.sort_by(|a, b| a.1.cmp(&b.1).then(a.0.total_cmp(&b.0)));
+    assert_eq!(zeros.len(), expected.len(), "{:?}", zeros);
+    for ((x, i), (expected_x, expected_i)) in zeros.into_iter().zip(expected) {
+        assert_eq!(i, expected_i);
+        assert!((x - expected_x).abs() < 1e-6, "{x} != {expected_x}");
+    }
+}
+
+#[test]
+fn test_kinematic_family() {
+    use crate::{inverse_kinematics::auxiliary::Matrix3x7, robot::KinematicFamily};
+    use nalgebra::Matrix3x6;
+
+    let tolerance = 1e-9;
+    for (kin, family) in [
+        (
+            Ur5::get_kin(),
+            KinematicFamily::ThreeParallelTwoIntersecting,
+        ),
+        (Irb6640::get_kin(), KinematicFamily::SphericalTwoParallel),
+        (SphericalBot::get_kin(), KinematicFamily::Spherical),
+        (ThreeParallelBot::get_kin(), KinematicFamily::ThreeParallel),
+        (TwoParallelBot::get_kin(), KinematicFamily::TwoParallel),
+    ] {
+        assert_eq!(KinematicFamily::classify(&kin, tolerance), family);
+    }
+    for family in KinematicFamily::ALL {
+        assert_eq!(KinematicFamily::from_name(family.name()), Some(family));
+    }
+
+    // A random chain whose only structure is that its first two axes intersect
+    let mut rng: Pcg64 = Seeder::from("two intersecting at base").into_rng();
+    let mut kin = Kinematics::new();
+    kin.h = Matrix3x6::from_fn(|_, _| rng.random::<f64>() * 2.0 - 1.0);
+    for mut column in kin.h.column_iter_mut() {
+        column.normalize_mut();
+    }
+    kin.p = Matrix3x7::from_fn(|_, _| rng.random::<f64>() * 2.0 - 1.0);
+    kin.p.set_column(1, &Vector3::zeros());
+    assert_eq!(
+        KinematicFamily::classify(&kin, tolerance),
+        KinematicFamily::TwoIntersectingAtBase
+    );
+
+    // The solver searches over one joint, which can miss solutions at some poses
+    let robot = Robot::from_kinematics(kin);
+    let found = (0..100)
+        .filter(|_| {
+            let q = rng.random::<[f64; 6]>().map(|x| x * 2.0 * PI - PI);
+            let (r, t) = robot.fk(&q);
+            robot
+                .ik(r, t)
+                .iter()
+                .any(|solution| solution.error < TOLERANCE)
+        })
+        .count();
+    assert!(found >= 90, "Only found solutions for {found} of 100 poses");
+}
+
+#[test]
+fn test_classification() {
+    use crate::{
+        inverse_kinematics::{auxiliary::Matrix3x7, family::classify},
+        robot::{Classification, KinematicFamily},
+    };
+    use nalgebra::Matrix3x6;
+
+    let ur5 = classify(&Ur5::get_kin());
+    assert_eq!(ur5, KinematicFamily::ThreeParallelTwoIntersecting);
+    assert!(ur5.is_closed_form());
+
+    let irb6640 = Classification::from_kinematics(&Irb6640::get_kin(), 1e-9);
+    assert_eq!(irb6640.family, KinematicFamily::SphericalTwoParallel);
+    assert_eq!(irb6640.parallel, [(1, 2)]);
+    assert_eq!(irb6640.intersecting, [(3, 4), (4, 5)]);
+
+    // A random chain has no structure to exploit
+    let mut rng: Pcg64 = Seeder::from("classification").into_rng();
+    let mut kin = Kinematics::new();
+    kin.h = Matrix3x6::from_fn(|_, _| rng.random::<f64>() * 2.0 - 1.0);
+    for mut column in kin.h.column_iter_mut() {
+        column.normalize_mut();
+    }
+    kin.p = Matrix3x7::from_fn(|_, _| rng.random::<f64>() * 2.0 - 1.0);
+    let general = Classification::from_kinematics(&kin, 1e-9);
+    assert_eq!(general.family, KinematicFamily::GenSixDof);
+    assert!(general.parallel.is_empty() && general.intersecting.is_empty());
+    assert_eq!(general.family.search_dimensions(), 2);
+
+    // Moving the offset of the last joint along its axis keeps the axes intersecting, even though
+    // the solvers no longer recognize them as such
+    let mut kin = Irb6640::get_kin();
+    let shift = 0.1 * kin.h.column(5);
+    kin.p.set_column(5, &shift);
+    kin.p.set_column(6, &(kin.p.column(6) - shift));
+    let moved = Classification::from_kinematics(&kin, 1e-9);
+    assert_eq!(moved.intersecting, irb6640.intersecting);
+    assert_eq!(moved.family, KinematicFamily::TwoParallel);
+}
+
+#[test]
+fn test_tool_and_base() {
+    use nalgebra::{Isometry3, Translation3, UnitQuaternion};
+
+    let base = Isometry3::from_parts(
+        Translation3::new(0.5, -0.2, 0.8),
+        UnitQuaternion::from_euler_angles(PI, 0.0, 0.3),
+    );
+    let tool = Isometry3::from_parts(
+        Translation3::new(0.0, 0.05, 0.15),
+        UnitQuaternion::from_euler_angles(0.0, PI / 2.0, 0.0),
+    );
+
+    let kin = Ur5::get_kin();
+    let kin_frames = Ur5::get_kin().with_base(base).with_tool(tool);
+    let robot = Robot::three_parallel_two_intersecting(kin_frames.clone());
+    let mut rng: Pcg64 = Seeder::from("tool and base").into_rng();
+
+    for _ in 0..100 {
+        let q = rng.random::<[f64; 6]>().map(|x| x * 2.0 * PI - PI);
+
+        let (rot_raw, translation_raw) = kin.forward_kinematics(&Vector6::from(q));
+        let expected = base * Isometry3::from(Pose::new(rot_raw, translation_raw)) * tool;
+        let (rot, translation) = robot.fk(&q);
+        let pose = Pose::from(expected);
+        assert!((rot - pose.rotation).norm() + (translation - pose.translation).norm() < 1e-9);
+
+        let found = robot.ik(rot, translation).iter().any(|solution| {
+            let q = solution.q;
+            let (rot_test, translation_test) = robot.fk(&[q[0], q[1], q[2], q[3], q[4], q[5]]);
+            (rot - rot_test).norm() < TOLERANCE
+                && (translation - translation_test).norm() < TOLERANCE
+        });
+        assert!(found, "Could not find a solution for q: {:?}", q);
+    }
+}
+
+#[test]
+fn test_link_frames() {
+    use nalgebra::{Isometry3, Point3, Translation3, UnitQuaternion};
+
+    let base = Isometry3::from_parts(
+        Translation3::new(0.1, 0.2, 0.3),
+        UnitQuaternion::from_euler_angles(0.0, 0.0, PI / 3.0),
+    );
+    let tool = Isometry3::translation(0.0, 0.0, 0.1);
+    let kin = Ur5::get_kin();
+    let kin_frames = Ur5::get_kin().with_base(base).with_tool(tool);
+    let mut rng: Pcg64 = Seeder::from("link frames").into_rng();
+
+    for _ in 0..100 {
+        let q = Vector6::from(rng.random::<[f64; 6]>().map(|x| x * 2.0 * PI - PI));
+        let frames: Vec<_> = kin_frames.link_frames(&q).collect();
+        assert_eq!(frames.len(), 7);
+
+        let origins = kin.joint_origins(&q);
+        for (i, (rot, translation)) in frames.iter().take(6).enumerate() {
+            let expected = base * Point3::from(Vector3::from(origins.column(i)));
+            assert!((translation - expected.coords).norm() < 1e-9);
+            assert!((rot.transpose() * rot - Matrix3::identity()).norm() < 1e-9);
+        }
+
+        let (rot, translation) = kin_frames.forward_kinematics(&q);
+        let (rot_last, translation_last) = frames[6];
+        assert!((rot - rot_last).norm() + (translation - translation_last).norm() < 1e-9);
+    }
+}
+
+#[test]
+fn test_generic_scalar() {
+    use crate::subproblems::subproblem2;
+
+    let kin = Ur5::get_kin();
+    let kin_f32: Kinematics<6, 7, f32> = kin.cast();
+    let mut rng: Pcg64 = Seeder::from("generic scalar").into_rng();
+
+    for _ in 0..100 {
+        let q = Vector6::from(rng.random::<[f64; 6]>().map(|x| x * 2.0 * PI - PI));
+        let (rot, translation) = kin.forward_kinematics(&q);
+        let (rot_f32, translation_f32) = kin_f32.forward_kinematics(&q.cast());
+
+        assert!((rot.cast() - rot_f32).norm() < 1e-5);
+        assert!((translation.cast() - translation_f32).norm() < 1e-5);
+    }
+
+    let (k1, k2) = (Vector3::<f32>::z(), Vector3::<f32>::x());
+    let p1 = Vector3::new(0.3, -0.2, 0.9).normalize();
+    let p2 = rot(&k2, -0.4) * rot(&k1, 1.1) * p1;
+
+    let (thetas, is_ls) = subproblem2(&p1, &p2, &k1, &k2);
+    assert!(!is_ls);
+    assert!(thetas
+        .get_all()
+        .any(|(t1, t2)| (rot(&k1, t1) * p1 - rot(&k2, t2) * p2).norm() < 1e-5));
+}
+
+#[test]
+fn test_manipulability() {
+    use crate::config::SolverConfig;
+
+    let kin = Ur5::get_kin();
+    let robot = ur5().with_config(SolverConfig {
+        annotate: true,
+        ..SolverConfig::default()
+    });
+    let mut rng: Pcg64 = Seeder::from("manipulability").into_rng();
+    let delta = 1e-7;
+
+    for _ in 0..100 {
+        let q = Vector6::from(rng.random::<[f64; 6]>().map(|x| x * 2.0 * PI - PI));
+        let jacobian = kin.jacobian(&q);
+        let (rot_q, translation_q) = kin.forward_kinematics(&q);
+
+        for i in 0..6 {
+            let mut q_delta = q;
+            q_delta[i] += delta;
+            let (rot_delta, translation_delta) = kin.forward_kinematics(&q_delta);
+
+            let omega = (rot_delta * rot_q.transpose() - Matrix3::identity()) / delta;
+            let omega = Vector3::new(omega[(2, 1)], omega[(0, 2)], omega[(1, 0)]);
+            let velocity = (translation_delta - translation_q) / delta;
+
+            assert!((jacobian.fixed_view::<3, 1>(0, i) - omega).norm() < 1e-5);
+            assert!((jacobian.fixed_view::<3, 1>(3, i) - velocity).norm() < 1e-5);
+        }
+
+        let (r, t) = robot.fk(&q.into());
+        for solution in &robot.ik(r, t) {
+            let manipulability = solution.manipulability.unwrap();
+            assert!((manipulability - kin.manipulability(&solution.q)).abs() < 1e-12);
+        }
+
+        // Without annotation the manipulability is left to the caller
+        for solution in &ur5().ik(r, t) {
+            assert_eq!(solution.manipulability, None);
+            assert_eq!(solution.manipulability_on(&kin), kin.manipulability(&solution.q));
+        }
+    }
+
+    // With the fifth joint at zero the fourth and sixth axes line up
+    let singular = Vector6::new(0.3, -1.2, 1.5, 0.4, 0.0, -0.7);
+    assert!(kin.manipulability(&singular).abs() < 1e-9);
+    assert!(kin.is_near_singular(&singular, 1e-6));
+    assert!(kin.condition_number(&singular) > 1e6);
+
+    let regular = Vector6::new(0.3, -1.2, 1.5, 0.4, 1.0, -0.7);
+    assert!(kin.manipulability(&regular) > 1e-3);
+    assert!(!kin.is_near_singular(&regular, 1e-3));
+}
+
+#[test]
+fn test_solve_velocity() {
+    let kin = Ur5::get_kin();
+    let kin_redundant = KukaR800FixedQ3::get_kin();
+    let mut rng: Pcg64 = Seeder::from("solve velocity").into_rng();
+
+    for _ in 0..100 {
+        let twist = Vector6::from(rng.random::<[f64; 6]>().map(|x| x * 2.0 - 1.0));
+
+        let q = Vector6::from(rng.random::<[f64; 6]>().map(|x| x * 2.0 * PI - PI));
+        if kin.is_near_singular(&q, 1e-3) {
+            continue;
+        }
+        let q_dot = kin.solve_velocity(&q, &twist, 0.0);
+        assert!((kin.jacobian(&q) * q_dot - twist).norm() < 1e-8);
+
+        // Damping trades tracking for smaller joint velocities
+        let q_dot_damped = kin.solve_velocity(&q, &twist, 0.1);
+        assert!(q_dot_damped.norm() <= q_dot.norm());
+
+        let q = SVector::<f64, 7>::from(rng.random::<[f64; 7]>().map(|x| x * 2.0 * PI - PI));
+        if kin_redundant.is_near_singular(&q, 1e-3) {
+            continue;
+        }
+        let jacobian = kin_redundant.jacobian(&q);
+        let secondary = SVector::<f64, 7>::from(rng.random::<[f64; 7]>());
+        let q_dot = kin_redundant.solve_velocity(&q, &twist, 0.0);
+        let q_dot_null = kin_redundant.solve_velocity_with_nullspace(&q, &twist, 0.0, &secondary);
+
+        assert!((jacobian * q_dot - twist).norm() < 1e-8);
+        assert!((jacobian * q_dot_null - twist).norm() < 1e-8);
+        assert!((q_dot_null - q_dot).norm() > 1e-6);
+    }
+}
+
+#[test]
+fn test_self_motion() {
+    let mut rng: Pcg64 = Seeder::from("self motion").into_rng();
+
+    for (sweep, kin, free, complete) in [
+        (
+            SevenDofSweep::kuka_r800(SweepStrategy::Grid(72)),
+            KukaR800FixedQ3::get_kin(),
+            2,
+            true,
+        ),
+        // The RRC solver does not find every branch, so the configuration may not be traced
+        (
+            SevenDofSweep::rrc(SweepStrategy::Grid(72)),
+            RrcFixedQ6::get_kin(),
+            5,
+            false,
+        ),
+    ] {
+        for _ in 0..5 {
+            // Put the free joint on a sample so the configuration itself is on a curve
+            let mut q =
+                SVector::<f64, 7>::from(rng.random::<[f64; 7]>().map(|x| x * 2.0 * PI - PI));
+            q[free] = -PI + TAU * rng.random_range(0..72) as f64 / 72.0;
+            let (rot, translation) = kin.forward_kinematics(&q);
+
+            let curves = sweep.trace_self_motion(rot, translation);
+            assert!(!curves.is_empty(), "No self-motion found for q: {:?}", q);
+
+            let mut nearest = f64::INFINITY;
+            for curve in &curves {
+                for q_curve in &curve.q {
+                    let (rot_test, translation_test) = kin.forward_kinematics(q_curve);
+                    let error = (rot - rot_test).norm() + (translation - translation_test).norm();
+                    assert!(error < TOLERANCE, "Self-motion had error {error:.2e}");
+
+                    let distance = (q_curve - q).map(wrap_to_pi).amax();
+                    nearest = nearest.min(distance);
+                }
+                for pair in curve.q.windows(2) {
+                    assert!((pair[1] - pair[0]).map(wrap_to_pi).amax() < 0.5);
+                }
+            }
+            if complete {
+                assert!(nearest < 1e-6, "q: {:?} is not on its self-motion", q);
+                assert!(curves.iter().all(|curve| curve.closed));
+            }
+        }
+    }
+
+    // A strategy without samples traces nothing
+    let sweep = SevenDofSweep::kuka_r800(SweepStrategy::Grid(0));
+    let (rot, translation) = KukaR800FixedQ3::get_kin().forward_kinematics(&SVector::from([0.3; 7]));
+    assert!(sweep.trace_self_motion(rot, translation).is_empty());
+}
+
+#[test]
+fn test_cartesian_tracker() {
+    use crate::{
+        robot::JointLimits,
+        trajectory::{CartesianTracker, TrackingEvent},
+    };
+
+    let robot = irb6640();
+    let start = Vector6::new(0.2, 0.3, -0.4, 0.5, 0.8, -0.3);
+    let end = Vector6::new(1.1, -0.2, 0.3, -0.6, 1.2, 0.9);
+    let path: Vec<Vector6<f64>> = (0..50).map(|k| start.lerp(&end, k as f64 / 49.0)).collect();
+    let mut poses: Vec<Pose> = path.iter().map(|q| robot.fk(&(*q).into()).into()).collect();
+
+    let tracker = CartesianTracker::new(irb6640());
+    let trajectory = tracker.track(&poses, Some(&start));
+
+    // Robots measure joint motion weighted by link length, proximal joints first
+    let metric = crate::metrics::JointMetric::from_kinematics(
+        robot.kinematics(),
+        crate::metrics::Distance::default(),
+    );
+    assert_eq!(robot.joint_metric(), metric);
+    assert!(metric.weights[0] > metric.weights[5]);
+
+    assert!(trajectory.is_continuous());
+    for (q, expected) in trajectory.q().zip(&path) {
+        assert!((q - expected).norm() < 1e-6);
+    }
+
+    poses[20].translation = Vector3::new(100.0, 0.0, 0.0);
+    let trajectory = tracker.track(&poses, Some(&start));
+
+    assert_eq!(
+        trajectory.events,
+        vec![TrackingEvent::Unreachable { waypoint: 20 }]
+    );
+    assert!(trajectory.waypoints[20].is_none());
+    assert_eq!(trajectory.q().count(), path.len() - 1);
+
+    let trajectory = CartesianTracker::new(irb6640())
+        .with_max_joint_step(1e-3)
+        .track(&poses, None);
+    assert!(trajectory
+        .events
+        .iter()
+        .any(|event| matches!(event, TrackingEvent::BranchChange { waypoint: 1, .. })));
+
+    // A path turning the base through ±π is unwrapped past π, unless the limits of the robot stop
+    // it there, where it has to turn back the long way around
+    let start = Vector6::new(2.9, 0.3, -0.4, 0.5, 0.8, -0.3);
+    let end = Vector6::new(3.4, 0.3, -0.4, 0.5, 0.8, -0.3);
+    let poses: Vec<Pose> = (0..20)
+        .map(|k| robot.fk(&start.lerp(&end, k as f64 / 19.0).into()).into())
+        .collect();
+    let unlimited = CartesianTracker::new(irb6640()).track(&poses, Some(&start));
+    assert!(unlimited.is_continuous());
+    assert!(unlimited.q().any(|q| q[0] > PI));
+
+    let limits = JointLimits::from_pairs(&[[-PI, PI]; 6]);
+    let limited =
+        CartesianTracker::new(irb6640().with_joint_limits(limits)).track(&poses, Some(&start));
+    assert_eq!(limited.q().count(), poses.len());
+    assert!(limited.q().all(|q| limits.contains(q)));
+    assert!(limited.events.iter().any(|event| matches!(
+        event,
+        TrackingEvent::BranchChange { max_joint_step, .. } if *max_joint_step > PI
+    )));
+}
+
+#[test]
+fn test_workspace() {
+    use crate::workspace::{sphere_orientations, ReachabilityMap, WorkspaceGrid};
+
+    let orientations = sphere_orientations(20, 3);
+    assert_eq!(orientations.len(), 60);
+    for r in &orientations {
+        assert!((r.transpose() * r - Matrix3::identity()).norm() < 1e-12);
+        assert!((r.determinant() - 1.0).abs() < 1e-12);
+    }
+
+    let robot = irb6640();
+    let (r, t) = robot.fk(&[0.3, 0.2, -0.1, 0.4, 0.9, 0.2]);
+
+    // A row of voxels from a reachable position to far outside of the workspace
+    let grid = WorkspaceGrid::new(
+        t - Vector3::new(0.5, 0.5, 0.5),
+        t + Vector3::new(100.5, 0.5, 0.5),
+        [101, 1, 1],
+    );
+    assert!((grid.center(0) - t).norm() < 1e-12);
+
+    let map = grid.map(&robot, &[r]);
+    assert_eq!(map.voxels.len(), 101);
+    assert!(map.voxels[0].exact > 0);
+    assert_eq!(map.voxels[0].reachable_orientations, 1);
+    assert_eq!(map.voxels[100].exact, 0);
+    assert!(map.reachable_fraction() < 0.5);
+
+    assert_eq!(map.as_csv().lines().count(), 102);
+    assert_eq!(
+        ReachabilityMap::from_bytes(&map.to_bytes()),
+        Ok(map.clone())
+    );
+    assert!(ReachabilityMap::from_bytes(&map.to_bytes()[..70]).is_err());
+    let mut huge = map.to_bytes();
+    huge[4..16].fill(0xff);
+    assert!(matches!(ReachabilityMap::from_bytes(&huge), Err(IkGeoError::MalformedInput(_))));
+}
+
+#[test]
+#[cfg(feature = "ffi")]
+fn test_ffi() {
+    use crate::ffi::{
+        ik_geo_irb6640, ik_geo_irb6640_fk, IK_GEO_MAX_SOLUTIONS, IK_GEO_NULL_POINTER, IK_GEO_OK,
+    };
+
+    let robot = irb6640();
+    let mut rng: Pcg64 = Seeder::from("ffi").into_rng();
+
+    for _ in 0..100 {
+        let q = rng.random::<[f64; 6]>().map(|x| x * 2.0 * PI - PI);
+        let (rot, translation) = robot.fk(&q);
+
+        let mut r = [0.0; 9];
+        let mut t = [0.0; 3];
+        let status = unsafe { ik_geo_irb6640_fk(q.as_ptr(), r.as_mut_ptr(), t.as_mut_ptr()) };
+        assert_eq!(status, IK_GEO_OK);
+        assert!((Matrix3::from_row_slice(&r) - rot).norm() < 1e-12);
+        assert!((Vector3::from(t) - translation).norm() < 1e-12);
+
+        let mut out_q = [0.0; 6 * IK_GEO_MAX_SOLUTIONS];
+        let mut out_is_ls = [0; IK_GEO_MAX_SOLUTIONS];
+        let mut out_error = [0.0; IK_GEO_MAX_SOLUTIONS];
+        let mut out_n = 0;
+        let status = unsafe {
+            ik_geo_irb6640(
+                r.as_ptr(),
+                t.as_ptr(),
+                out_q.as_mut_ptr(),
+                out_is_ls.as_mut_ptr(),
+                out_error.as_mut_ptr(),
+                &mut out_n,
+            )
+        };
+        assert_eq!(status, IK_GEO_OK);
+
+        let expected = robot.ik(rot, translation);
+        assert_eq!(out_n as usize, expected.len());
+        for (i, solution) in expected.iter().enumerate() {
+            let q_out = Vector6::from_column_slice(&out_q[6 * i..6 * (i + 1)]);
+            assert!((q_out - solution.q).norm() < 1e-12);
+            assert_eq!(out_is_ls[i] != 0, solution.is_ls);
+            assert_eq!(out_error[i], solution.error);
+        }
+    }
+
+    // An unreachable pose has only least squares solutions, flagged with their residual
+    let mut out_q = [0.0; 6 * IK_GEO_MAX_SOLUTIONS];
+    let mut out_is_ls = [0; IK_GEO_MAX_SOLUTIONS];
+    let mut out_error = [0.0; IK_GEO_MAX_SOLUTIONS];
+    let mut out_n = 0;
+    let r = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+    let t = [100.0, 0.0, 0.0];
+    let status = unsafe {
+        ik_geo_irb6640(
+            r.as_ptr(),
+            t.as_ptr(),
+            out_q.as_mut_ptr(),
+            out_is_ls.as_mut_ptr(),
+            out_error.as_mut_ptr(),
+            &mut out_n,
+        )
+    };
+    assert_eq!(status, IK_GEO_OK);
+    assert!(out_n > 0);
+    for i in 0..out_n as usize {
+        assert_eq!(out_is_ls[i], 1);
+        assert!(out_error[i] > 1.0);
+    }
+
+    let status = unsafe {
+        ik_geo_irb6640(
+            std::ptr::null(),
+            std::ptr::null(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    assert_eq!(status, IK_GEO_NULL_POINTER);
+}
+
+#[test]
+fn test_joint_limits() {
+    use crate::robot::JointLimits;
+
+    let q = [0.3, -1.2, 1.5, 0.4, 1.0, -0.7];
+    let (r, t) = ur5().fk(&q);
+    let all = ur5().ik(r, t);
+
+    // Only the solutions with the shoulder and elbow on the side of `q` remain
+    let limits = JointLimits::from_pairs(&[
+        [-PI, PI],
+        [-PI, 0.0],
+        [0.0, PI],
+        [-PI, PI],
+        [-PI, PI],
+        [-PI, PI],
+    ]);
+    let limited = ur5().with_joint_limits(limits).ik(r, t);
+    assert!(limited.len() < all.len());
+    assert!(limited.iter().all(|solution| limits.contains(&solution.q)));
+    assert!(limited
+        .iter()
+        .any(|solution| (solution.q - Vector6::from(q)).amax() < 1e-6));
+}
+
+#[cfg(feature = "yaml")]
+#[test]
+fn test_robot_yaml() {
+    let mut rng: Pcg64 = Seeder::from("yaml").into_rng();
+    for (name, preset) in [
+        ("irb120", irb120()),
+        ("irb1200", irb1200()),
+        ("irb2600", irb2600()),
+        ("irb4600", irb4600()),
+        ("irb6640", irb6640()),
+        ("motoman_gp8", motoman_gp8()),
+        ("ur5", ur5()),
+        ("three_parallel_bot", three_parallel_bot()),
+        ("two_parallel_bot", two_parallel_bot()),
+        ("spherical_bot", spherical_bot()),
+    ] {
+        let robot = Robot::named(name).unwrap();
+        for _ in 0..10 {
+            let q = rng.random::<[f64; 6]>().map(|x| x * 2.0 * PI - PI);
+            let (r, t) = robot.fk(&q);
+            let (r_preset, t_preset) = preset.fk(&q);
+            assert!((r - r_preset).norm() + (t - t_preset).norm() < 1e-12, "{name}");
+            assert!(robot
+                .ik(r, t)
+                .iter()
+                .any(|solution| (solution.q - Vector6::from(q)).map(wrap_to_pi).amax() < 1e-6));
+        }
+    }
+    assert!(Robot::named("irb9000").is_none());
+
+    // The family is detected when left out, and the limits and tool are applied
+    let yaml = "
+h: [[0, 0, 1], [0, 1, 0], [0, 1, 0], [1, 0, 0], [0, 1, 0], [1, 0, 0]]
+p: [[0, 0, 0], [0.32, 0, 0.78], [0, 0, 1.075], [1.1425, 0, 0.2], [0, 0, 0], [0, 0, 0], [0.2, 0, 0]]
+tool: { rotation: [[1, 0, 0], [0, 1, 0], [0, 0, 1]], translation: [0, 0, 0.1] }
+limits: [[-3, 3], [-1, 1.5], [-3, 1.2], [-5, 5], [-2, 2], [-6, 6]]
+";
+    let robot = Robot::from_yaml_str(yaml).unwrap();
+    let limits = robot.joint_limits().copied().unwrap();
+    assert_eq!(limits.lower[1], -1.0);
+
+    let q = [0.3, 0.2, -0.1, 0.4, 0.5, 0.2];
+    let (r, t) = robot.fk(&q);
+    assert!((t - irb6640().fk(&q).1 - r * Vector3::new(0.0, 0.0, 0.1)).norm() < 1e-12);
+    let solutions = robot.ik(r, t);
+    assert!(solutions.iter().all(|solution| limits.contains(&solution.q)));
+    assert!(solutions
+        .iter()
+        .any(|solution| (solution.q - Vector6::from(q)).amax() < 1e-6));
+
+    let missing_limit = yaml.replace("[-6, 6]]", "]");
+    assert_eq!(
+        Robot::from_yaml_str(&missing_limit).err(),
+        Some(IkGeoError::DimensionMismatch {
+            expected: 6,
+            found: 5
+        })
+    );
+    assert!(matches!(
+        Robot::from_yaml("robots/missing.yaml"),
+        Err(IkGeoError::InvalidDescription(_))
+    ));
+}
+
+#[test]
+fn test_presets() {
+    let q = [0.3, 0.2, -0.1, 0.4, 0.5, 0.2];
+    for name in PRESETS {
+        let robot = preset(name).unwrap();
+        let (r, t) = robot.fk(&q);
+        let line = r
+            .transpose()
+            .iter()
+            .chain(t.iter())
+            .map(|x| x.to_string())
+            .collect::<Vec<String>>()
+            .join(",");
+        assert_eq!(hardcoded_setup_from_string(&line).unwrap(), (r, t));
+        assert!(robot
+            .ik(r, t)
+            .iter()
+            .any(|solution| (robot.fk(&solution.q.into()).1 - t).norm() < 1e-6));
+    }
+    assert!(preset("missing").is_none());
+
+    // The preset is the UR5 of `Ur5::get_kin`, not the DH model of `UrModel::Ur5`
+    let ur5_preset = preset("ur5").unwrap();
+    assert_eq!(ur5_preset.kinematics().h, Ur5::get_kin().h);
+    assert_eq!(ur5_preset.kinematics().p, Ur5::get_kin().p);
+}
+
+#[test]
+fn test_diagnostics() {
+    use crate::{
+        diagnostics::{Diagnostics, RobotSetup, Stats},
+        robot::JointLimits,
+    };
+
+    let mut diagnostics = Diagnostics::new().with_seed(7);
+    diagnostics
+        .register(RobotSetup::new("irb6640", irb6640()))
+        .register(RobotSetup::new(
+            "limited_ur5",
+            ur5().with_joint_limits(JointLimits::from_pairs(&[[-1.0, 1.0]; 6])),
+        ));
+    assert_eq!(diagnostics.names(), ["irb6640", "limited_ur5"]);
+
+    let first = diagnostics.run(50);
+    for stats in &first {
+        assert_eq!(stats.iterations, 50);
+        assert!(stats.avg_error < 1e-9, "{stats}");
+        assert_eq!(stats.nan_percent, 0.0);
+        assert_eq!(stats.all_ls_percent, 0.0);
+    }
+
+    // The same seed draws the same poses
+    let errors = |stats: &[Stats]| stats.iter().map(|s| s.avg_error).collect::<Vec<f64>>();
+    assert_eq!(errors(&diagnostics.run(50)), errors(&first));
+
+    let builtin = Diagnostics::builtin();
+    assert!(builtin.names().contains(&"IRB 6640"));
+}
+
+#[test]
+fn test_seeded_setups() {
+    use crate::{
+        inverse_kinematics::setups::{SetupIk, SetupStatic},
+        solutionset::OutputFormat,
+        subproblems::setups::{SetupDynamic, Subproblem5Setup},
+    };
+
+    // The same seed draws the same targets and so finds the same solutions, and another does not
+    let ik_outputs = |seed: u64| {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut setup = Irb6640::new();
+        (0..20)
+            .map(|_| {
+                setup.setup_with_rng(&mut rng);
+                setup.run();
+                setup.write_output(OutputFormat::Csv)
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(ik_outputs(1), ik_outputs(1));
+    assert_ne!(ik_outputs(1), ik_outputs(2));
+
+    let subproblem_outputs = |seed: u64| {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut setup = Subproblem5Setup::new();
+        (0..20)
+            .map(|_| {
+                setup.setup_with_rng(&mut rng);
+                setup.run();
+                setup.write_output()
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(subproblem_outputs(1), subproblem_outputs(1));
+    assert_ne!(subproblem_outputs(1), subproblem_outputs(2));
+}
+
+#[cfg(feature = "proptest")]
+mod strategies {
+    use {
+        crate::{
+            pose::Pose,
+            robot::{irb6640, IKSolver, JointLimits},
+            strategies::{near_joint_limits, near_singularity, outside_workspace, reachable_pose},
+        },
+        proptest::prelude::*,
+    };
+
+    fn solves_exactly(pose: &Pose) -> bool {
+        let robot = irb6640();
+        robot
+            .ik(pose.rotation, pose.translation)
+            .iter()
+            .any(|solution| {
+                let (r, t) = robot.fk(&solution.q.into());
+                !solution.is_ls && (r - pose.rotation).norm() + (t - pose.translation).norm() < 1e-6
+            })
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn test_reachable_pose((_, pose) in reachable_pose(irb6640().kinematics().clone())) {
+            prop_assert!(solves_exactly(&pose));
+        }
+
+        #[test]
+        fn test_near_joint_limits(
+            (_, pose) in near_joint_limits(
+                irb6640().kinematics().clone(),
+                JointLimits::from_pairs(&[[-2.9, 2.9], [-1.1, 1.4], [-3.1, 1.2], [-5.2, 5.2], [-2.0, 2.0], [-6.2, 6.2]]),
+                1e-3,
+            )
+        ) {
+            prop_assert!(solves_exactly(&pose));
+        }
+
+        #[test]
+        fn test_near_singularity((_, pose) in near_singularity(irb6640().kinematics().clone(), 1e-6)) {
+            prop_assert!(solves_exactly(&pose));
+        }
+
+        #[test]
+        fn test_outside_workspace(pose in outside_workspace(irb6640().kinematics().clone(), 0.01)) {
+            prop_assert!(irb6640()
+                .ik(pose.rotation, pose.translation)
+                .iter()
+                .all(|solution| solution.is_ls));
+        }
+    }
+}
+
+#[test]
+fn test_validation() {
+    use crate::{
+        config::SolverConfig,
+        robot::KinematicFamily,
+        validation::{parse_corpus, validate_family, validate_robot, Layout, Tolerances},
+    };
+
+    let robot = irb6640();
+    let kin = Irb6640::get_kin();
+    let join = |values: &[f64]| {
+        values
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<String>>()
+            .join(",")
+    };
+
+    let mut poses = String::from("R11,R12,R13,R21,R22,R23,R31,R32,R33,T1,T2,T3,Q\n");
+    let mut setups = String::new();
+    let mut branch_count = 0;
+    for q in [
+        [0.3, 0.2, -0.1, 0.4, 0.5, 0.2],
+        [-1.0, 0.4, 0.3, -2.0, 1.2, 2.5],
+    ] {
+        let (r, t) = robot.fk(&q);
+        let pose = join(&[r.transpose().as_slice(), t.as_slice()].concat());
+        let solutions = robot.ik(r, t);
+        branch_count += solutions.len();
+        let branches = join(
+            &solutions
+                .iter()
+                .flat_map(|solution| solution.q.iter().copied())
+                .collect::<Vec<f64>>(),
+        );
+        poses += &format!("{pose},{branches},NaN,NaN,NaN,NaN,NaN,NaN\n");
+        setups += &format!(
+            "{},{},{pose},{branches}\n",
+            join(kin.h.as_slice()),
+            join(kin.p.as_slice())
+        );
+    }
+
+    let cases = parse_corpus(&poses, Layout::Pose).unwrap();
+    assert_eq!(cases.len(), 2);
+    let report = validate_robot(&robot, &cases, &Tolerances::default());
+    assert!(report.is_parity(), "{report}");
+    assert_eq!(report.branches, branch_count);
+
+    let cases = parse_corpus(&setups, Layout::KinematicsAndPose).unwrap();
+    let report = validate_family(
+        KinematicFamily::SphericalTwoParallel,
+        &cases,
+        &Tolerances::default(),
+        &SolverConfig::default(),
+    );
+    assert!(report.is_parity(), "{report}");
+
+    // A branch the solver does not find is a mismatch, and the solution it replaced is extra
+    let mut cases = parse_corpus(&poses, Layout::Pose).unwrap();
+    cases[1].expected[3][5] += 0.1;
+    let report = validate_robot(&robot, &cases, &Tolerances::default());
+    assert_eq!(report.mismatches.len(), 1);
+    assert_eq!(
+        (report.mismatches[0].case, report.mismatches[0].branch),
+        (1, 3)
+    );
+    assert!((report.mismatches[0].distance - 0.1).abs() < 1e-9);
+    assert_eq!(report.extra.len(), 1);
+
+    assert_eq!(
+        parse_corpus("1,2,3", Layout::Pose).err(),
+        Some(IkGeoError::DimensionMismatch {
+            expected: 12,
+            found: 3
+        })
+    );
+}
+
+#[test]
+fn test_output_format() {
+    use crate::{
+        diagnostics::{SetupIk, SetupStatic},
+        robot::OutputFormat,
+    };
+
+    let robot = irb6640();
+    let (r, t) = robot.fk(&[0.3, 0.2, -0.1, 0.4, 0.5, 0.2]);
+    let solutions = robot.ik(r, t);
+    let text = |format| String::from_utf8(solutions.write(format)).unwrap();
+
+    let plain = text(OutputFormat::PlainText);
+    assert_eq!(plain.split(',').count(), 6 * solutions.len());
+
+    let csv = text(OutputFormat::Csv);
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next(),
+        Some("q1,q2,q3,q4,q5,q6,error,is_ls,manipulability,branch,multiplicity")
+    );
+    assert_eq!(lines.count(), solutions.len());
+
+    let json: serde_json::Value = serde_json::from_str(&text(OutputFormat::Json)).unwrap();
+    let objects = json.as_array().unwrap();
+    assert_eq!(objects.len(), solutions.len());
+    for (object, solution) in objects.iter().zip(&solutions) {
+        assert_eq!(object["q"][5].as_f64(), Some(solution.q[5]));
+        assert_eq!(object["is_ls"].as_bool(), Some(solution.is_ls));
+        assert_eq!(object["branch"].as_u64(), Some(solution.branch as u64));
+    }
+
+    let bytes = solutions.write(OutputFormat::Bincode);
+    assert_eq!(bytes.len(), 8 + solutions.len() * (7 * 8 + 1));
+    assert_eq!(bytes[..8], (solutions.len() as u64).to_le_bytes());
+    let error = f64::from_le_bytes(bytes[8 + 6 * 8..8 + 7 * 8].try_into().unwrap());
+    assert_eq!(error, solutions[0].error);
+
+    let pose = r
+        .transpose()
+        .iter()
+        .chain(t.iter())
+        .map(|x| x.to_string())
+        .collect::<Vec<String>>()
+        .join(",");
+    let mut setup = Irb6640::new();
+    setup.setup_from_str(&pose).unwrap();
+    setup.run();
+    assert_eq!(
+        setup.write_output(OutputFormat::PlainText),
+        plain.into_bytes()
+    );
+}
+
+#[test]
+fn test_error_metric() {
+    use crate::robot::{ErrorMetric, SolverConfig};
+
+    let r_q = rot(&Vector3::z(), 0.3);
+    let t_q = Vector3::new(0.4, 0.0, 0.0);
+    let (r, t) = (Matrix3::identity(), Vector3::zeros());
+    let error = |error_metric, translation_weight| {
+        SolverConfig {
+            error_metric,
+            translation_weight,
+            ..SolverConfig::default()
+        }
+        .pose_error(&r_q, &t_q, &r, &t)
+    };
+
+    let frobenius = 2.0 * 2.0_f64.sqrt() * 0.15_f64.sin();
+    assert!((error(ErrorMetric::Frobenius, 1.0) - frobenius - 0.4).abs() < 1e-12);
+    assert!((error(ErrorMetric::QuaternionAngle, 1.0) - 0.7).abs() < 1e-12);
+    assert!((error(ErrorMetric::QuaternionAngle, 0.5) - 0.5).abs() < 1e-12);
+    assert!((error(ErrorMetric::SE3Distance, 1.0) - 0.5).abs() < 1e-12);
+
+    // The residuals of the solvers follow the metric of their config
+    let config = SolverConfig {
+        error_metric: ErrorMetric::SE3Distance,
+        ..SolverConfig::default()
+    };
+    let robot = irb6640().with_config(config);
+    let (r, t) = robot.fk(&[0.3, 0.2, -0.1, 0.4, 0.5, 0.2]);
+    let t_far = t + Vector3::new(10.0, 0.0, 0.0);
+    let solutions = robot.ik(r, t_far);
+    assert!(!solutions.is_empty());
+    let kin = robot.kinematics();
+    for solution in &solutions {
+        let (r_q, t_q) = robot.fk(&solution.q.into());
+        assert!((solution.error - config.pose_error(&r_q, &t_q, &r, &t_far)).abs() < 1e-9);
+        assert!((solution.error - solution.residual_with(kin, &r, &t_far, &config)).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn test_joint_metrics() {
+    use crate::metrics::{Distance, JointMetric};
+
+    let a = Vector6::new(3.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+    let b = Vector6::new(-3.0, 0.0, 0.0, 0.0, 0.5, 0.0);
+    let distance = |distance| JointMetric::new(distance).distance(&a, &b);
+    assert!((distance(Distance::WeightedL2) - 6.0_f64.hypot(0.5)).abs() < 1e-12);
+    assert!((distance(Distance::MaxAbs) - 6.0).abs() < 1e-12);
+    assert!((distance(Distance::WrappedAngular) - (TAU - 6.0).hypot(0.5)).abs() < 1e-12);
+
+    let weights = Vector6::new(0.0, 1.0, 1.0, 1.0, 2.0, 1.0);
+    let weighted = JointMetric::new(Distance::MaxAbs).with_weights(weights);
+    assert!((weighted.distance(&a, &b) - 1.0).abs() < 1e-12);
+
+    // Proximal joints move more of the chain than the wrist
+    let robot = irb6640();
+    let metric = JointMetric::from_kinematics(robot.kinematics(), Distance::WrappedAngular);
+    assert_eq!(metric.weights[0], 1.0);
+    for i in 1..6 {
+        assert!(metric.weights[i] <= metric.weights[i - 1]);
+        assert!(metric.weights[i] > 0.0);
+    }
+
+    let q = Vector6::new(0.3, 0.2, -0.1, 0.4, 0.5, 0.2);
+    let (r, t) = robot.fk(&q.into());
+    let solutions = robot.ik(r, t);
+    let seed = q.add_scalar(0.01);
+    let nearest = solutions.nearest(&seed, &metric).unwrap();
+    assert!((nearest.q - q).map(wrap_to_pi).amax() < 1e-6);
+
+    let mut sorted = solutions.clone();
+    sorted.sort_by_distance(&seed, &metric);
+    assert_eq!(sorted.iter().next().unwrap().q, nearest.q);
+}
+
+#[test]
+fn test_partial_pose() {
+    use crate::partial_pose::PartialPoseSolver;
+
+    let robot = irb6640();
+    let (r, t) = robot.fk(&[0.3, 0.2, -0.1, 0.4, 0.9, 0.2]);
+    let axis: Vector3<f64> = r.column(2).into();
+    let solver = PartialPoseSolver::new(irb6640()).with_rolls(12);
+
+    let samples = solver.solve_axis(&axis, &t);
+    assert_eq!(samples.len(), 12);
+    let mut exact = 0;
+    for sample in &samples {
+        assert!((sample.rotation.column(2) - axis).norm() < 1e-12);
+        for solution in sample.solutions.iter().filter(|s| !s.is_ls) {
+            let (r_q, t_q) = robot.fk(&solution.q.into());
+            assert!((r_q.column(2) - axis).norm() < 1e-6);
+            assert!((t_q - t).norm() < 1e-6);
+            exact += 1;
+        }
+    }
+    // Fixing the roll would only keep the solutions of one sample
+    assert!(exact > robot.ik(r, t).exact().len());
+
+    let samples = solver.with_rolls(2).with_directions(10).solve_position(&t);
+    assert_eq!(samples.len(), 20);
+    let reached = samples
+        .iter()
+        .filter(|sample| sample.solutions.iter().any(|s| !s.is_ls))
+        .count();
+    assert!(reached > 1);
+    for sample in &samples {
+        for solution in sample.solutions.iter().filter(|s| !s.is_ls) {
+            assert!((robot.fk(&solution.q.into()).1 - t).norm() < 1e-6);
+        }
+    }
+}
+
+#[test]
+fn test_free_tool_roll() {
+    let robot = irb6640();
+    let (r, t) = robot.fk(&[0.3, 0.2, -0.1, 0.4, 0.9, 0.2]);
+    let axis = Vector3::new(0.0, 0.0, 1.0);
+
+    // The given roll is sampled, so the exact solutions of the pose are kept and the least
+    // squares ones can only improve
+    let solutions = robot.solve_with_free_tool_roll(r, t, axis);
+    for fixed in &robot.ik(r, t) {
+        let free = solutions
+            .iter()
+            .find(|solution| solution.branch == fixed.branch)
+            .unwrap();
+        if fixed.is_ls {
+            assert!(free.error <= fixed.error);
+        } else {
+            assert!((free.q - fixed.q).norm() < 1e-12);
+        }
+    }
+
+    // Rolling the target about the tool axis still reaches it, with the axis where it was
+    let rolled = r * rot(&axis, 0.7);
+    let solutions = robot.solve_with_free_tool_roll(rolled, t, axis);
+    assert!(solutions.iter().any(|solution| !solution.is_ls));
+    for solution in solutions.iter().filter(|solution| !solution.is_ls) {
+        let (r_q, t_q) = robot.fk(&solution.q.into());
+        assert!((r_q * axis - r * axis).norm() < 1e-6);
+        assert!((t_q - t).norm() < 1e-6);
+    }
+
+    let mut branches: Vec<usize> = solutions.iter().map(|solution| solution.branch).collect();
+    branches.dedup();
+    assert_eq!(branches.len(), solutions.len());
+}
+
+#[test]
+fn test_reach_envelope() {
+    use crate::workspace::{is_pose_reachable_conservative, ReachEnvelope};
+    use nalgebra::{Isometry3, Translation3, UnitQuaternion};
+
+    let frames = |kin: &Kinematics<6, 7>| {
+        kin.clone()
+            .with_base(Isometry3::from_parts(
+                Translation3::new(1.0, -2.0, 0.5),
+                UnitQuaternion::from_euler_angles(0.1, 0.2, 0.3),
+            ))
+            .with_tool(Isometry3::from_parts(
+                Translation3::new(0.0, 0.05, 0.2),
+                UnitQuaternion::from_euler_angles(0.3, 0.0, -0.2),
+            ))
+    };
+    let spherical = frames(irb6640().kinematics());
+    let general = frames(ur5().kinematics());
+
+    let envelope = ReachEnvelope::new(&spherical);
+    assert_eq!(Some(envelope), ReachEnvelope::spherical_wrist(&spherical));
+    assert!(ReachEnvelope::spherical_wrist(&general).is_none());
+    let sampled = ReachEnvelope::sampled(&general, 4);
+    let bounds = ReachEnvelope::link_lengths(&general);
+    assert!(bounds.min_radius <= sampled.min_radius && sampled.max_radius <= bounds.max_radius);
+
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..200 {
+        let q = Vector6::from_fn(|_, _| rng.random_range(-PI..PI));
+        let (r, t) = spherical.forward_kinematics(&q);
+        assert!(envelope.contains(&r, &t));
+        assert!(is_pose_reachable_conservative(&spherical, &r, &t));
+
+        let (r, t) = general.forward_kinematics(&q);
+        assert!(sampled.contains(&r, &t));
+        assert!(is_pose_reachable_conservative(&general, &r, &t));
+    }
+
+    let r = Matrix3::identity();
+    let far = envelope.center + Vector3::x() * (envelope.max_radius + 10.0);
+    assert!(!is_pose_reachable_conservative(&spherical, &r, &far));
+    assert!(!is_pose_reachable_conservative(&general, &r, &far));
+}
+
+#[test]
+fn test_branch_labels() {
+    use crate::{
+        config::SolverConfig,
+        labels::{BranchLabel, Elbow, Shoulder, Wrist},
+    };
+    use std::collections::HashSet;
+
+    let robot = irb6640();
+    let q = Vector6::new(0.3, 0.0, 0.5, 0.4, 0.5, 0.2);
+    assert_eq!(
+        BranchLabel::of(robot.kinematics(), &q),
+        Some(BranchLabel::new(Shoulder::Right, Elbow::Up, Wrist::NoFlip))
+    );
+
+    // The eight solutions of a pose inside the workspace each have their own label
+    let robot = robot.with_config(SolverConfig {
+        annotate: true,
+        ..SolverConfig::default()
+    });
+    let (r, t) = robot.fk(&q.into());
+    let solutions = robot.ik(r, t).exact();
+    assert_eq!(solutions.len(), 8);
+    let labels: HashSet<BranchLabel> = solutions.iter().map(|s| s.label.unwrap()).collect();
+    assert_eq!(labels.len(), 8);
+
+    let found = solutions
+        .labeled(BranchLabel::new(Shoulder::Right, Elbow::Up, Wrist::NoFlip))
+        .unwrap();
+    assert!((found.q - q).map(wrap_to_pi).amax() < 1e-6);
+
+    // The flipped wrist of the same arm configuration
+    let flipped = solutions
+        .labeled(BranchLabel::new(Shoulder::Right, Elbow::Up, Wrist::Flip))
+        .unwrap();
+    assert!((flipped.q.fixed_rows::<3>(0) - q.fixed_rows::<3>(0)).amax() < 1e-6);
+    assert_eq!(flipped.label.unwrap().to_string(), "right/up/flip");
+
+    assert!(BranchLabel::of(ur5().kinematics(), &q).is_none());
+}
+
+#[test]
+fn test_unwrap_near() {
+    use crate::limits::{unwrap_near, JointLimits};
+
+    let q = Vector6::new(0.3, 0.0, 0.5, 0.4, 0.5, 3.0);
+    let current = Vector6::new(0.2, 0.1, 0.4, 0.5, 0.6, -3.0 + 2.0 * TAU);
+    let near = unwrap_near(&q, &current);
+    assert!((near - Vector6::new(0.3, 0.0, 0.5, 0.4, 0.5, 3.0 + TAU)).amax() < 1e-12);
+
+    // A wrist of ±2 turns keeps the nearest revolution, a single turn moves back into its range
+    let mut pairs = [[-PI, PI]; 6];
+    pairs[5] = [-2.0 * TAU, 2.0 * TAU];
+    let unwrap = |pairs: &[[f64; 2]; 6]| JointLimits::from_pairs(pairs).unwrap_near(&q, &current);
+    assert!((unwrap(&pairs).unwrap() - near).amax() < 1e-12);
+    pairs[5] = [-PI, PI];
+    assert!((unwrap(&pairs).unwrap() - q).amax() < 1e-12);
+    pairs[5] = [-2.0, 2.0];
+    assert!(unwrap(&pairs).is_none());
+
+    // The solvers return the same solutions in the revolutions closest to the current state
+    let robot = irb6640();
+    let (r, t) = robot.fk(&q.into());
+    let solutions = robot.ik(r, t);
+    let unwrapped = robot.ik_near(r, t, &current);
+    assert_eq!(unwrapped.len(), solutions.len());
+    for (a, b) in unwrapped.iter().zip(&solutions) {
+        assert!((a.q - b.q).map(wrap_to_pi).amax() < 1e-9);
+        assert!((a.q - current).amax() <= PI + 1e-9);
+    }
+
+    // Limits of a turn on either side of zero let the wrist use its whole range
+    pairs[5] = [0.0, TAU];
+    let robot = irb6640().with_joint_limits(JointLimits::from_pairs(&pairs));
+    let q = Vector6::new(0.3, 0.0, 0.5, 0.4, 0.5, -1.0);
+    let (r, t) = robot.fk(&q.into());
+    let fixed = robot.ik(r, t);
+    let unwrapped = robot.ik_near(r, t, &q.add_scalar(TAU));
+    assert_eq!(unwrapped.len(), fixed.len());
+    assert!(unwrapped
+        .iter()
+        .all(|solution| (0.0..=TAU).contains(&solution.q[5])));
+    assert!(unwrapped
+        .iter()
+        .any(|solution| (solution.q[5] - (TAU - 1.0)).abs() < 1e-9));
+}
+
+#[test]
+fn test_multi_turn_limits() {
+    use crate::robot::JointLimits;
+
+    // A wrist of ±370° reaches angles within 10° of zero on three revolutions, others on two
+    let mut pairs = [[-PI, PI]; 6];
+    pairs[5] = [-370.0_f64.to_radians(), 370.0_f64.to_radians()];
+    let limits = JointLimits::from_pairs(&pairs);
+    let q = Vector6::new(0.3, 0.0, 0.5, 0.4, 0.5, 3.0);
+    let revolutions = limits.revolutions(&q);
+    assert_eq!(revolutions.len(), 2);
+    assert!((revolutions[0][5] - (3.0 - TAU)).abs() < 1e-12);
+    assert!((revolutions[1][5] - 3.0).abs() < 1e-12);
+    let mut q_0 = q;
+    q_0[5] = 0.0;
+    assert_eq!(limits.revolutions(&q_0).len(), 3);
+
+    // Limits of several turns multiply across joints
+    pairs[3] = [-2.0 * TAU, 2.0 * TAU];
+    assert_eq!(JointLimits::from_pairs(&pairs).revolutions(&q).len(), 8);
+    pairs[3] = [1.0, 2.0];
+    assert!(JointLimits::from_pairs(&pairs).revolutions(&q).is_empty());
+
+    // An unlimited joint keeps the revolution of `q` rather than enumerating endless turns, and a
+    // NaN bound admits none
+    pairs[3] = [f64::NEG_INFINITY, f64::INFINITY];
+    let unlimited = JointLimits::from_pairs(&pairs);
+    assert_eq!(unlimited.revolutions(&q).len(), 2);
+    assert!(unlimited.revolutions(&q).iter().all(|q_k| q_k[3] == q[3]));
+    pairs[3] = [-1.0, f64::INFINITY];
+    assert_eq!(JointLimits::from_pairs(&pairs).revolutions(&q).len(), 2);
+    pairs[3] = [f64::NAN, 1.0];
+    assert!(JointLimits::from_pairs(&pairs).revolutions(&q).is_empty());
+    let (r, t) = irb6640().fk(&q.into());
+    assert!(!irb6640().with_joint_limits(unlimited).ik(r, t).is_empty());
+
+    // The robot returns every revolution of its solutions within the limits
+    let robot = irb6640();
+    let (r, t) = robot.fk(&q.into());
+    let all = robot.ik(r, t);
+    let expanded = irb6640().with_joint_limits(limits).ik(r, t);
+    let expected: usize = all.iter().map(|s| limits.revolutions(&s.q).len()).sum();
+    assert_eq!(expanded.len(), expected);
+    assert!(expanded.len() > all.len());
+    for solution in &expanded {
+        assert!(limits.contains(&solution.q));
+        assert!(solution.error < 1e-6 || solution.is_ls);
+    }
+}
+
+#[test]
+fn test_ikfast_export() {
+    use crate::robot::{IkSolutionSet, OutputFormat};
+
+    let robot = irb6640();
+    let q = Vector6::new(0.3, 0.0, 0.5, 0.4, 0.5, 0.2);
+    let (r, t) = robot.fk(&q.into());
+    let solutions = robot.ik(r, t);
+    let ikfast = solutions.to_ikfast();
+    assert_eq!(ikfast.len(), 8);
+    assert!((ikfast[0] - q).amax() < 1e-9);
+    for q in &ikfast {
+        assert!(q.iter().all(|&x| -PI < x && x <= PI));
+    }
+
+    let text = String::from_utf8(solutions.write(OutputFormat::IkFast)).unwrap();
+    let mut lines = text.lines();
+    assert_eq!(lines.next(), Some("Found 8 ik solutions:"));
+    for (i, (line, q)) in lines.zip(&ikfast).enumerate() {
+        let values = line.strip_prefix(&format!("sol{i} (free=0): ")).unwrap();
+        let parsed: Vec<f64> = values
+            .split(", ")
+            .filter(|value| !value.is_empty())
+            .map(|value| value.parse().unwrap())
+            .collect();
+        assert_eq!(parsed.len(), 6);
+        assert!((Vector6::from_vec(parsed) - q).amax() < 1e-14);
+    }
+
+    // Least squares solutions are not solutions to ikfast
+    let (r, t) = robot.fk(&[0.3, 0.2, -0.1, 0.4, 0.9, 0.2]);
+    let solutions = robot.ik(r, t);
+    assert!(solutions.iter().any(|solution| solution.is_ls));
+    assert_eq!(solutions.to_ikfast().len(), solutions.exact().len());
+
+    let none = IkSolutionSet::<6>::new().write(OutputFormat::IkFast);
+    assert_eq!(none, b"Found 0 ik solutions:\n");
+}
+
+#[test]
+fn test_opw_parameters() {
+    use crate::inverse_kinematics::opw::OpwParameters;
+
+    // The IRB 6640 as OPW parameters reaches the same flange positions, with the flange z axis
+    // along the sixth axis instead of the tool x axis
+    let offsets = [0.0, 0.0, -PI / 2.0, 0.0, 0.0, 0.0];
+    let params =
+        OpwParameters::new(0.32, -0.2, 0.0, 0.78, 1.075, 1.1425, 0.2).with_offsets(offsets);
+    let robot = Robot::from_opw(params);
+    let reference = irb6640();
+    let flange = rot(&Vector3::y(), PI / 2.0);
+
+    let mut rng: Pcg64 = Seeder::from("opw").into_rng();
+    for _ in 0..100 {
+        let q = rng.random::<[f64; 6]>().map(|x| x * TAU - PI);
+        let (r, t) = robot.fk(&q);
+        let (r_ref, t_ref) = reference.fk(&q);
+        assert!((t - t_ref).norm() < 1e-12);
+        assert!((r - r_ref * flange).norm() < 1e-12);
+
+        let solutions = robot.ik(r, t);
+        assert!(solutions
+            .iter()
+            .any(|solution| (solution.q - Vector6::from(q)).map(wrap_to_pi).amax() < TOLERANCE));
+    }
+
+    // A sign correction turns the joint the other way
+    let flipped = Robot::from_opw(params.with_sign_corrections([-1, 1, 1, 1, 1, -1]));
+    let q = [0.3, 0.2, -0.1, 0.4, 0.9, 0.2];
+    let (r, t) = flipped.fk(&q);
+    let (r_ref, t_ref) = robot.fk(&[-0.3, 0.2, -0.1, 0.4, 0.9, -0.2]);
+    assert!((t - t_ref).norm() < 1e-12);
+    assert!((r - r_ref).norm() < 1e-12);
+}
+
+#[test]
+fn test_ros2_messages() {
+    use crate::ros2::{message_from_pose, pose_from_message, JointNames};
+
+    let robot = irb6640();
+    let q = Vector6::new(0.3, 0.2, -0.1, 0.4, 0.5, 0.2);
+    let pose = Pose::from(robot.fk(&q.into()));
+
+    // Poses go through the message fields and back, with the quaternion normalized when read
+    let (position, orientation) = message_from_pose(&pose);
+    assert_eq!(position, <[f64; 3]>::from(pose.translation));
+    let norm = orientation.iter().map(|x| x * x).sum::<f64>().sqrt();
+    assert!((norm - 1.0).abs() < 1e-12);
+    let back = pose_from_message(position, orientation);
+    assert!((back.rotation - pose.rotation).norm() < 1e-12);
+    assert_eq!(back.translation, pose.translation);
+    let scaled = pose_from_message(position, orientation.map(|x| 2.0 * x));
+    assert!((scaled.rotation - pose.rotation).norm() < 1e-12);
+
+    // A quarter turn about z is the quaternion (0, 0, sin(π/4), cos(π/4))
+    let quarter = pose_from_message([0.0; 3], [0.0, 0.0, (PI / 4.0).sin(), (PI / 4.0).cos()]);
+    assert!((quarter.rotation - rot(&Vector3::z(), PI / 2.0)).norm() < 1e-12);
+
+    // Joint values are read by name, whatever their order
+    let names = JointNames::new(["joint_1", "joint_2", "joint_3", "joint_4", "joint_5", "joint_6"]);
+    let mut state_names = names.0.to_vec();
+    let mut values = q.as_slice().to_vec();
+    state_names.reverse();
+    values.reverse();
+    assert_eq!(names.select(&state_names, &values), Ok(q));
+    assert_eq!(
+        names.select(&state_names[1..], &values[1..]),
+        Err(IkGeoError::MissingJoint(String::from("joint_6")))
+    );
+}
+
+#[cfg(feature = "ros2")]
+#[test]
+fn test_ros2() {
+    use crate::ros2::{ik_handler, JointNames};
+    use r2r::{geometry_msgs::msg as geometry_msgs, sensor_msgs::msg::JointState};
+
+    let robot = irb6640();
+    let q = Vector6::new(0.3, 0.2, -0.1, 0.4, 0.5, 0.2);
+    let pose = Pose::from(robot.fk(&q.into()));
+
+    // Poses go through the message and back, with the quaternion normalized when read
+    let message = geometry_msgs::Pose::from(&pose);
+    let back = Pose::from(&message);
+    assert!((back.rotation - pose.rotation).norm() < 1e-12);
+    assert_eq!(back.translation, pose.translation);
+    let mut scaled = message.clone();
+    scaled.orientation.x *= 2.0;
+    scaled.orientation.y *= 2.0;
+    scaled.orientation.z *= 2.0;
+    scaled.orientation.w *= 2.0;
+    assert!((Pose::from(scaled).rotation - pose.rotation).norm() < 1e-12);
+
+    // Joint states are read by name, whatever their order
+    let names = JointNames::new(["joint_1", "joint_2", "joint_3", "joint_4", "joint_5", "joint_6"]);
+    let mut state = names.joint_state(&q);
+    assert_eq!(state.position, q.as_slice());
+    state.name.reverse();
+    state.position.reverse();
+    assert_eq!(names.positions(&state), Ok(q));
+    let partial = JointState {
+        name: state.name[1..].to_vec(),
+        position: state.position[1..].to_vec(),
+        ..state
+    };
+    assert_eq!(
+        names.positions(&partial),
+        Err(IkGeoError::MissingJoint(String::from("joint_6")))
+    );
+
+    // The handler returns the exact solutions as joint states
+    let handler = ik_handler(irb6640(), names.clone());
+    let states = handler(&message);
+    assert_eq!(states.len(), robot.ik(pose.rotation, pose.translation).exact().len());
+    assert!(states.iter().all(|state| state.name == names.0));
+    assert!(states.iter().any(|state| {
+        (names.positions(state).unwrap() - q).map(wrap_to_pi).amax() < 1e-6
+    }));
+}
+
+#[cfg(feature = "service")]
+#[test]
+fn test_service() {
+    use crate::{
+        registry::{RobotDefinition, RobotRegistry},
+        service::{
+            proto::{self, ik_geo_client::IkGeoClient},
+            IkGeoService,
+        },
+    };
+    use tonic::{
+        transport::{server::TcpIncoming, Server},
+        Code,
+    };
+
+    let mut registry = RobotRegistry::new();
+    registry
+        .register("my_irb6640", RobotDefinition::new(Irb6640::get_kin()))
+        .unwrap();
+    let service = IkGeoService::with_registry(registry).into_server();
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(async {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let incoming = TcpIncoming::from_listener(listener, true, None).unwrap();
+        tokio::spawn(Server::builder().add_service(service).serve_with_incoming(incoming));
+        let mut client = IkGeoClient::connect(format!("http://{address}")).await.unwrap();
+
+        let robots = client
+            .list_robots(proto::ListRobotsRequest {})
+            .await
+            .unwrap()
+            .into_inner()
+            .robots;
+        assert!(robots.iter().any(|robot| robot == "irb6640"));
+        assert!(robots.iter().any(|robot| robot == "my_irb6640"));
+
+        // A pose from the forward kinematics is solved back to the configuration, also for the
+        // FANUC robot
+        let q = Vector6::new(0.3, 0.2, -0.1, 0.4, 0.5, 0.2);
+        for robot in ["irb6640", "my_irb6640", "fanuc_lr_mate_200id"] {
+            let request = proto::ForwardKinematicsRequest {
+                robot: String::from(robot),
+                q: q.as_slice().to_vec(),
+            };
+            let pose = client.forward_kinematics(request).await.unwrap().into_inner().pose;
+            let request = proto::SolveIkRequest {
+                robot: String::from(robot),
+                pose,
+            };
+            let solutions = client.solve_ik(request).await.unwrap().into_inner().solutions;
+            assert!(solutions.iter().any(|solution| {
+                let found = Vector6::from_column_slice(&solution.q);
+                !solution.is_ls && (found - q).map(wrap_to_pi).amax() < 1e-6
+            }));
+        }
+
+        let pose = proto::Pose {
+            rotation: Matrix3::identity().as_slice().to_vec(),
+            translation: vec![1.0, 0.0, 1.0],
+        };
+        let request = proto::SolveIkRequest {
+            robot: String::from("no_such_robot"),
+            pose: Some(pose.clone()),
+        };
+        assert_eq!(client.solve_ik(request).await.unwrap_err().code(), Code::NotFound);
+        let request = proto::SolveIkRequest {
+            robot: String::from("irb6640"),
+            pose: Some(proto::Pose {
+                translation: vec![1.0, 0.0],
+                ..pose
+            }),
+        };
+        assert_eq!(client.solve_ik(request).await.unwrap_err().code(), Code::InvalidArgument);
+        let request = proto::ForwardKinematicsRequest {
+            robot: String::from("irb6640"),
+            q: vec![0.0; 5],
+        };
+        let status = client.forward_kinematics(request).await.unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+    });
+}
+
+#[test]
+fn test_yumi_dual_arm() {
+    use crate::inverse_kinematics::auxiliary::mirror_pose;
+    use crate::robot::YumiDualArm;
+
+    let yumi = YumiDualArm::new();
+    let (left, right) = (yumi.left_kinematics(), yumi.right_kinematics());
+    assert!((right.p.column(0) - Vector3::new(0.0536, -0.0725, 0.4149)).norm() < 1e-12);
+
+    // The right arm mirrors the left arm at the same joint angles
+    let q_left = SVector::<f64, 7>::from([0.4, -0.8, PI / 6.0, 0.3, 0.6, -0.5, 0.2]);
+    let (r, t) = yumi.fk_left(&q_left);
+    let (r_mirrored, t_mirrored) = mirror_pose(&r, &t);
+    let (r_right, t_right) = yumi.fk_right(&q_left);
+    assert!((r_right - r_mirrored).norm() < 1e-12);
+    assert!((t_right - t_mirrored).norm() < 1e-12);
+    assert!((right.mirrored().h - left.h).norm() < 1e-12);
+
+    // Both arms holding an object, with the right tool given relative to the left tool
+    let q_right = SVector::<f64, 7>::from([-0.2, -0.6, PI / 6.0, 0.4, -0.3, 0.7, -0.1]);
+    let (r_r, t_r) = yumi.fk_right(&q_right);
+    let relative_rot = r.transpose() * r_r;
+    let relative_translation = r.transpose() * (t_r - t);
+    let solutions = yumi.ik_relative(r, t, relative_rot, relative_translation);
+
+    let found = |set: &crate::robot::IkSolutionSet<7>, q: &SVector<f64, 7>| {
+        set.iter()
+            .any(|solution| (solution.q - q).map(wrap_to_pi).amax() < TOLERANCE)
+    };
+    assert!(found(&solutions.left, &q_left));
+    assert!(found(&solutions.right, &q_right));
+}
+
+#[test]
+fn test_coordinated_arms() {
+    use crate::coordinated::CoordinatedSolver;
+    use crate::robot::YumiDualArm;
+    use nalgebra::{Isometry3, Translation3, UnitQuaternion};
+
+    // Two IRB 6640s facing each other across a cell
+    let base = Isometry3::from_parts(
+        Translation3::new(3.0, 0.0, 0.0),
+        UnitQuaternion::from_axis_angle(&Vector3::z_axis(), PI),
+    );
+    let master = irb6640();
+    let slave = Robot::spherical_two_parallel(Irb6640::get_kin().with_base(base));
+    let solver = CoordinatedSolver::new(master, slave);
+
+    let q_master = [0.3, 0.2, -0.1, 0.4, 0.9, 0.2];
+    let q_slave = [-0.2, 0.4, 0.1, -0.3, 0.7, 0.5];
+    let (r_m, t_m) = solver.master().fk(&q_master);
+    let (r_s, t_s) = solver.slave().fk(&q_slave);
+    let relative_rot = r_m.transpose() * r_s;
+    let relative_translation = r_m.transpose() * (t_s - t_m);
+
+    let near =
+        |a: Vector6<f64>, b: [f64; 6]| (a - Vector6::from(b)).map(wrap_to_pi).amax() < TOLERANCE;
+    let pairs = solver.solve(r_m, t_m, relative_rot, relative_translation);
+    assert!(pairs
+        .iter()
+        .any(|pair| near(pair.master.q, q_master) && near(pair.slave.q, q_slave)));
+    for pair in pairs.iter().filter(|pair| pair.is_exact()) {
+        let (r_m, t_m) = solver.master().fk(&pair.master.q.into());
+        let (r_s, t_s) = solver.slave().fk(&pair.slave.q.into());
+        assert!((r_m.transpose() * r_s - relative_rot).norm() < 1e-6);
+        assert!((r_m.transpose() * (t_s - t_m) - relative_translation).norm() < 1e-6);
+    }
+    assert!(pairs.windows(2).all(|w| w[0].is_exact() >= w[1].is_exact()));
+
+    // Letting the master roll about its tool axis still keeps the transform between the tools
+    let rolled = solver.solve_with_free_roll(r_m, t_m, relative_rot, relative_translation, 8);
+    assert!(rolled.len() > pairs.len());
+    for pair in rolled.iter().filter(|pair| pair.is_exact()).take(20) {
+        let (r_m, t_m) = solver.master().fk(&pair.master.q.into());
+        let (r_s, t_s) = solver.slave().fk(&pair.slave.q.into());
+        assert!((r_m.transpose() * r_s - relative_rot).norm() < 1e-6);
+        assert!((r_m.transpose() * (t_s - t_m) - relative_translation).norm() < 1e-6);
+    }
+
+    // The left arm of a Yumi searched over its third joint
+    let yumi = YumiDualArm::new();
+    let q_left = SVector::<f64, 7>::from([0.4, -0.8, PI / 2.0, 0.3, 0.6, -0.5, 0.2]);
+    let q_right = SVector::<f64, 7>::from([-0.2, -0.6, PI / 6.0, 0.4, -0.3, 0.7, -0.1]);
+    let (r_l, t_l) = yumi.fk_left(&q_left);
+    let (r_r, t_r) = yumi.fk_right(&q_right);
+    let (relative_rot, relative_translation) =
+        (r_l.transpose() * r_r, r_l.transpose() * (t_r - t_l));
+    let pairs = yumi.ik_pairs(r_l, t_l, relative_rot, relative_translation, 4);
+    assert!(pairs.iter().any(|pair| {
+        (pair.master.q - q_left).map(wrap_to_pi).amax() < TOLERANCE
+            && (pair.slave.q - q_right).map(wrap_to_pi).amax() < TOLERANCE
+    }));
+}
+
+#[test]
+fn test_external_axis() {
+    use crate::external_axis::{ExternalAxisKind, ExternalAxisSolver};
+    use crate::robot::{SevenDofSweep, SweepStrategy};
+
+    // An IRB 6640 on a rail along the y axis
+    let rail = Irb6640::get_kin().with_external_axis(Vector3::y(), ExternalAxisKind::Prismatic);
+    let solver = ExternalAxisSolver::new(irb6640(), rail.external, -2.0, 2.0).with_samples(9);
+    let q = Vector6::new(0.3, 0.2, -0.1, 0.4, 0.9, 0.2);
+    let (r, t) = rail.forward_kinematics(0.7, &q);
+    let (r_arm, t_arm) = Irb6640::get_kin().forward_kinematics(&q);
+    assert!((r - r_arm).norm() < 1e-12);
+    assert!((t - t_arm - Vector3::new(0.0, 0.7, 0.0)).norm() < 1e-12);
+
+    let at = solver.solve_at(&r, &t, 0.7);
+    assert!(at
+        .solutions
+        .iter()
+        .any(|solution| (solution.q - q).map(wrap_to_pi).amax() < TOLERANCE));
+
+    let samples = solver.solve(&r, &t);
+    assert_eq!(samples.len(), 9);
+    assert_eq!((samples[0].value, samples[8].value), (-2.0, 2.0));
+    for sample in &samples {
+        for solution in sample.solutions.iter().filter(|solution| !solution.is_ls) {
+            let (r_s, t_s) = rail.forward_kinematics(sample.value, &solution.q);
+            assert!((r_s - r).norm() + (t_s - t).norm() < 1e-6);
+        }
+    }
+
+    // The rail position that keeps the arm furthest from singularities
+    let arm = Irb6640::get_kin();
+    let (value, best) = solver
+        .optimize(&r, &t, |solution| -solution.manipulability_on(&arm), 1e-6)
+        .unwrap();
+    assert!((-2.0..=2.0).contains(&value));
+    let (r_s, t_s) = rail.forward_kinematics(value, &best.q);
+    assert!((r_s - r).norm() + (t_s - t).norm() < 1e-6);
+    for sample in &samples {
+        for solution in sample.solutions.iter().filter(|solution| !solution.is_ls) {
+            assert!(best.manipulability_on(&arm) >= solution.manipulability_on(&arm) - 1e-9);
+        }
+    }
+
+    // A KUKA iiwa on a turntable about the z axis
+    let turntable =
+        KukaR800FixedQ3::get_kin().with_external_axis(Vector3::z(), ExternalAxisKind::Revolute);
+    let sweep = SevenDofSweep::kuka_r800(SweepStrategy::Grid(8));
+    let solver = ExternalAxisSolver::new(sweep, turntable.external, -PI, PI);
+    let q = SVector::<f64, 7>::from([0.3, 0.4, -PI / 2.0, -0.8, 0.5, 0.6, -0.2]);
+    let (r, t) = turntable.forward_kinematics(1.2, &q);
+    let (r_arm, t_arm) = KukaR800FixedQ3::get_kin().forward_kinematics(&q);
+    let turn = rot(&Vector3::z(), 1.2);
+    assert!((r - turn * r_arm).norm() < 1e-12);
+    assert!((t - turn * t_arm).norm() < 1e-12);
+
+    let at = solver.solve_at(&r, &t, 1.2);
+    assert!(at
+        .solutions
+        .iter()
+        .any(|solution| (solution.q - q).map(wrap_to_pi).amax() < TOLERANCE));
+}
+
+#[test]
+fn test_prismatic_joints() {
+    use crate::inverse_kinematics::auxiliary::JointType;
+    use crate::subproblems::{
+        prismatic_subproblem1, prismatic_subproblem2, prismatic_subproblem3,
+        prismatic_subproblem4,
+    };
+
+    // A gantry: two rails along x and y carrying a revolute wrist about z and a vertical slide
+    let mut kin = Kinematics::<4, 5>::new().with_joint_types([
+        JointType::Prismatic,
+        JointType::Prismatic,
+        JointType::Revolute,
+        JointType::Prismatic,
+    ]);
+    kin.h = nalgebra::Matrix3x4::from_columns(&[
+        Vector3::x(),
+        Vector3::y(),
+        Vector3::z(),
+        -Vector3::z(),
+    ]);
+    kin.p = nalgebra::Matrix3x5::from_columns(&[
+        Vector3::new(0.0, 0.0, 1.0),
+        Vector3::zeros(),
+        Vector3::zeros(),
+        Vector3::new(0.2, 0.0, 0.0),
+        Vector3::new(0.0, 0.0, -0.1),
+    ]);
+    assert!(!kin.is_revolute());
+
+    let q = nalgebra::Vector4::new(0.5, -0.3, PI / 2.0, 0.4);
+    let (r, t) = kin.forward_kinematics(&q);
+    assert!((r - rot(&Vector3::z(), PI / 2.0)).norm() < 1e-12);
+    assert!((t - Vector3::new(0.5, -0.1, 0.5)).norm() < 1e-12);
+
+    // The Jacobian matches finite differences of the forward kinematics
+    let jacobian = kin.jacobian(&q);
+    for i in 0..4 {
+        let mut q_step = q;
+        q_step[i] += 1e-7;
+        let (r_step, t_step) = kin.forward_kinematics(&q_step);
+        let omega = (r_step - r) * r.transpose() / 1e-7;
+        let expected = Vector6::new(
+            omega[(2, 1)],
+            omega[(0, 2)],
+            omega[(1, 0)],
+            (t_step[0] - t[0]) / 1e-7,
+            (t_step[1] - t[1]) / 1e-7,
+            (t_step[2] - t[2]) / 1e-7,
+        );
+        assert!((jacobian.column(i) - expected).norm() < 1e-5);
+    }
+    let kin_dyn = KinematicsDyn::from(&kin);
+    let q_dyn = DVector::from_column_slice(q.as_slice());
+    assert_eq!(kin_dyn.forward_kinematics(&q_dyn).unwrap(), (r, t));
+    assert!((kin_dyn.jacobian(&q_dyn).unwrap() - jacobian).norm() < 1e-12);
+
+    // The prismatic axes keep their direction in the mirror image
+    let (r_m, t_m) = kin.mirrored().forward_kinematics(&q);
+    let (r_e, t_e) = crate::inverse_kinematics::auxiliary::mirror_pose(&r, &t);
+    assert!((r_m - r_e).norm() + (t_m - t_e).norm() < 1e-12);
+
+    #[cfg(feature = "serde")]
+    {
+        let json = serde_json::to_string(&kin).unwrap();
+        assert!(json.contains(r#""joint_types":["prismatic","prismatic","revolute","prismatic"]"#));
+        let kin_back: Kinematics<4, 5> = serde_json::from_str(&json).unwrap();
+        assert_eq!(kin_back.joint_types, kin.joint_types);
+        assert!(!serde_json::to_string(&Ur5::get_kin()).unwrap().contains("joint_types"));
+    }
+
+    let (r_rev, t_rev) = kin
+        .reversed()
+        .chain_forward_kinematics(&nalgebra::Vector4::new(q[3], q[2], q[1], q[0]));
+    assert!((r_rev - r.transpose()).norm() + (t_rev + r.transpose() * t).norm() < 1e-12);
+
+    let p1 = Vector3::<f64>::new(0.3, -0.2, 0.5);
+    let k = Vector3::new(1.0, 2.0, -1.0).normalize();
+    let (d, is_ls) = prismatic_subproblem1(&p1, &(p1 + k * 0.7), &k);
+    assert!(!is_ls && (d - 0.7).abs() < 1e-12);
+    assert!(prismatic_subproblem1(&p1, &(p1 + Vector3::x()), &Vector3::y()).1);
+
+    let k2 = Vector3::new(0.0, 1.0, 1.0).normalize();
+    let p2 = p1 + k * 0.4 - k2 * 1.1;
+    let ((d1, d2), is_ls) = prismatic_subproblem2(&p1, &p2, &k, &k2);
+    assert!(!is_ls && (d1 - 0.4).abs() < 1e-12 && (d2 - 1.1).abs() < 1e-12);
+
+    let (d, is_ls) = prismatic_subproblem3(&p1, &Vector3::zeros(), &k, 2.0);
+    assert!(!is_ls);
+    for d in d.get_all() {
+        assert!(((p1 + k * d).norm() - 2.0).abs() < 1e-12);
+    }
+    assert!(prismatic_subproblem3(&p1, &Vector3::zeros(), &Vector3::x(), 0.1).1);
+
+    let (d, is_ls) = prismatic_subproblem4(&k2, &p1, &k, 0.3);
+    assert!(!is_ls && (k2.dot(&(p1 + k * d)) - 0.3).abs() < 1e-12);
+}
+
+#[test]
+fn test_continuation() {
+    use crate::continuation::{track, ContinuationEvent};
+
+    // The fifth joint passes through zero, where the two wrist branches are singular
+    let robot = irb6640();
+    let start = Vector6::new(0.2, 0.3, -0.4, 0.5, 0.5, -0.3);
+    let end = Vector6::new(0.6, 0.1, -0.2, 0.9, -0.6, 0.4);
+    let path: Vec<Vector6<f64>> = (0..50).map(|k| start.lerp(&end, k as f64 / 49.0)).collect();
+    let mut poses: Vec<Pose> = path.iter().map(|q| robot.fk(&(*q).into()).into()).collect();
+
+    let branch = track(&robot, &poses, &start);
+    assert!(branch.is_continuous());
+    for (q, expected) in branch.q().zip(&path) {
+        assert!((q - expected).norm() < 1e-6, "{q:?} left the branch");
+    }
+    let crossings: Vec<usize> = branch
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            ContinuationEvent::SingularCrossing { waypoint, .. } => Some(*waypoint),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(crossings, vec![22]);
+
+    poses[30].translation = Vector3::new(100.0, 0.0, 0.0);
+    let branch = track(&robot, &poses, &start);
+    assert!(!branch.is_continuous());
+    assert!(branch
+        .events
+        .contains(&ContinuationEvent::Unreachable { waypoint: 30 }));
+    assert!((branch.waypoints[49].as_ref().unwrap().q - end).norm() < 1e-6);
+}
+
+#[test]
+fn test_se3() {
+    use crate::se3::{exp, interpolate, linear_path, log, screw_interpolate, screw_path};
+
+    let robot = irb6640();
+    let from: Pose = robot.fk(&[0.1, 0.2, -0.3, 0.4, 0.5, 0.6]).into();
+    let to: Pose = robot.fk(&[0.9, -0.2, 0.3, -0.8, 1.1, -0.4]).into();
+    let close = |a: &Pose, b: &Pose| {
+        (a.rotation - b.rotation).norm() < 1e-9 && (a.translation - b.translation).norm() < 1e-9
+    };
+
+    for pose in [from, to, Pose::identity()] {
+        assert!(close(&exp(&log(&pose)), &pose));
+    }
+    let small = Vector6::new(1e-8, -2e-8, 0.0, 0.1, 0.2, 0.3);
+    assert!((log(&exp(&small)) - small).norm() < 1e-12);
+
+    for interpolate in [interpolate, screw_interpolate] {
+        assert!(close(&interpolate(&from, &to, 0.0), &from));
+        assert!(close(&interpolate(&from, &to, 1.0), &to));
+    }
+
+    // Halfway along the straight line, and halfway through the turn
+    let halfway = interpolate(&from, &to, 0.5);
+    assert!((halfway.translation - (from.translation + to.translation) / 2.0).norm() < 1e-12);
+    let first = halfway.rotation.transpose() * from.rotation;
+    let second = to.rotation.transpose() * halfway.rotation;
+    assert!((first - second).norm() < 1e-9);
+
+    // A turn about a vertical line through (1, 0, 0) stays on the circle about it
+    let start = Pose::new(Matrix3::identity(), Vector3::new(2.0, 0.0, 0.5));
+    let turn = rot(&Vector3::z(), 2.0);
+    let end = Pose::new(
+        turn,
+        turn * Vector3::new(1.0, 0.0, 0.0) + Vector3::new(1.0, 0.0, 0.5),
+    );
+    let path = screw_path(&start, &end, 20);
+    assert_eq!(path.len(), 20);
+    assert!(close(&path[19], &end));
+    for pose in &path {
+        let offset = pose.translation - Vector3::new(1.0, 0.0, 0.5);
+        assert!((offset.norm() - 1.0).abs() < 1e-9 && offset.z.abs() < 1e-9);
+    }
+    let path = linear_path(&start, &end, 20);
+    assert!(path.iter().any(|pose| {
+        ((pose.translation - Vector3::new(1.0, 0.0, 0.5)).norm() - 1.0).abs() > 1e-3
+    }));
+}
+
+#[test]
+fn test_retiming() {
+    use crate::retiming::{retime, DynamicLimits};
+    use crate::trajectory::CartesianTracker;
+
+    let limits = DynamicLimits::new(Vector6::repeat(1.0), Vector6::repeat(2.0));
+
+    // Half a second to reach full speed over a quarter radian at each end, and half a second
+    // cruising in between
+    let line: Vec<Vector6<f64>> = (0..11)
+        .map(|k| Vector6::new(k as f64 / 10.0, 0.0, 0.0, 0.0, 0.0, 0.0))
+        .collect();
+    let timed = retime(&line, &limits);
+    assert_eq!(timed.waypoints.len(), 11);
+    assert!((timed.duration() - 1.5).abs() < 1e-9);
+    assert!((timed.waypoints[5].velocity[0] - 1.0).abs() < 1e-9);
+    assert_eq!(timed.waypoints[10].velocity, Vector6::zeros());
+
+    let robot = irb6640();
+    let start = Vector6::new(0.2, 0.3, -0.4, 0.5, 0.8, -0.3);
+    let end = Vector6::new(1.1, -0.2, 0.3, -0.6, 1.2, 0.9);
+    let poses: Vec<Pose> = (0..50)
+        .map(|k| robot.fk(&start.lerp(&end, k as f64 / 49.0).into()).into())
+        .collect();
+    let trajectory = CartesianTracker::new(irb6640()).track(&poses, Some(&start));
+    let timed = trajectory.retime(&limits);
+
+    // The joints move together, so the slowest one sets the pace
+    let slowest = (end - start).amax();
+    assert!(timed.duration() > slowest);
+    for pair in timed.waypoints.windows(2) {
+        let dt = pair[1].time - pair[0].time;
+        assert!(dt > 0.0);
+        assert!((pair[1].q - pair[0].q).amax() <= dt * (1.0 + 1e-9));
+        assert!(pair[1].velocity.amax() <= 1.0 + 1e-9);
+    }
+}
+
+#[test]
+fn test_solve_filtered() {
+    use crate::trajectory::{CartesianTracker, TrackingEvent};
+    use std::cell::Cell;
+
+    let robot = irb6640();
+    let q = Vector6::new(0.3, 0.2, -0.1, 0.4, 0.9, 0.2);
+    let (r, t) = robot.fk(&q.into());
+
+    // Pretend every solution with the wrist unflipped collides
+    let checks = Cell::new(0);
+    let solution = robot
+        .solve_filtered(r, t, |candidate| {
+            checks.set(checks.get() + 1);
+            candidate[4] < 0.0
+        })
+        .unwrap();
+    assert!(solution.q[4] < 0.0);
+    assert!(solution.error < 1e-9);
+    assert!(checks.get() < robot.ik(r, t).len());
+
+    assert!(robot.solve_filtered(r, t, |_| false).is_none());
+    let first = robot.solve_filtered(r, t, |_| true).unwrap();
+    assert!(!first.is_ls);
+
+    let poses: Vec<Pose> = (0..10)
+        .map(|k| robot.fk(&(q * (1.0 + k as f64 / 100.0)).into()).into())
+        .collect();
+    let trajectory = CartesianTracker::new(irb6640())
+        .with_filter(|q| q[4] < 0.0)
+        .track(&poses, Some(&q));
+    assert!(trajectory.q().all(|q| q[4] < 0.0));
+
+    let trajectory = CartesianTracker::new(irb6640())
+        .with_filter(|_| false)
+        .track(&poses, None);
+    assert_eq!(trajectory.events[0], TrackingEvent::Unreachable { waypoint: 0 });
+}
+
+// The wrapper is plain Rust apart from its errors, which are JavaScript objects and so are only
+// created on `wasm32` targets
+#[cfg(feature = "wasm")]
+#[test]
+fn test_wasm() {
+    use crate::wasm::WasmRobot;
+
+    let robot = irb6640();
+    let kin = robot.kinematics();
+    let wasm_robot = WasmRobot::new("irb6640").unwrap();
+    let wasm_kinematics =
+        WasmRobot::from_kinematics("auto", kin.h.as_slice(), kin.p.as_slice()).unwrap();
+    let mut rng: Pcg64 = Seeder::from("wasm").into_rng();
+
+    for _ in 0..100 {
+        let q = rng.random::<[f64; 6]>().map(|x| x * 2.0 * PI - PI);
+        let (rot, translation) = robot.fk(&q);
+
+        let pose = wasm_robot.fk(&q).unwrap();
+        assert_eq!(pose.len(), 12);
+        assert!((Matrix3::from_row_slice(&pose[..9]) - rot).norm() < 1e-12);
+        assert!((Vector3::from_column_slice(&pose[9..]) - translation).norm() < 1e-12);
+
+        let expected = robot.ik(rot, translation);
+        for wasm_robot in [&wasm_robot, &wasm_kinematics] {
+            let solutions = wasm_robot.ik(&pose[..9], &pose[9..]).unwrap();
+            assert_eq!(solutions.length(), expected.len());
+
+            let (q_out, is_ls) = (solutions.q(), solutions.is_ls());
+            let (error, manipulability) = (solutions.error(), solutions.manipulability());
+            assert_eq!(q_out.len(), 6 * expected.len());
+            for (i, solution) in expected.iter().enumerate() {
+                let q_solution = Vector6::from_column_slice(&q_out[6 * i..6 * (i + 1)]);
+                assert!((q_solution - solution.q).norm() < 1e-9);
+                assert_eq!(is_ls[i] == 1, solution.is_ls);
+                assert!((error[i] - solution.error).abs() < 1e-9);
+                assert!((manipulability[i] - kin.manipulability(&solution.q)).abs() < 1e-9);
+
+                if !solution.is_ls {
+                    let pose_solution = wasm_robot.fk(q_solution.as_slice()).unwrap();
+                    let translation_solution = Vector3::from_column_slice(&pose_solution[9..]);
+                    assert!((translation_solution - translation).norm() < 1e-9);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "viz")]
+#[test]
+fn test_viz() {
+    use crate::viz::{log_candidates, log_configuration, MeshcatCommands, MeshcatServer};
+    use rmpv::Value;
+
+    fn field<'a>(command: &'a Value, key: &str) -> &'a Value {
+        let entries = command.as_map().unwrap();
+        &entries.iter().find(|(k, _)| k.as_str() == Some(key)).unwrap().1
+    }
+
+    fn read_commands(mut bytes: &[u8]) -> Vec<Value> {
+        let mut commands = Vec::new();
+        while !bytes.is_empty() {
+            commands.push(rmpv::decode::read_value(&mut bytes).unwrap());
+        }
+        commands
+    }
+
+    let robot = irb6640();
+    let q = [0.3, 0.2, -0.1, 0.4, 0.9, 0.2];
+    let (r, t) = robot.fk(&q);
+    let solutions = robot.ik(r, t);
+
+    let mut sink = MeshcatCommands::new(Vec::new());
+    log_candidates(&mut sink, "irb6640", robot.kinematics(), &solutions, |solution| {
+        solution.q[4] > 0.0
+    })
+    .unwrap();
+    let commands = read_commands(&sink.into_inner());
+
+    // Each frame is added as a triad and then moved
+    assert_eq!(commands.len(), 2 * 7 * solutions.len());
+    for (solution, frames) in solutions.iter().zip(commands.chunks(14)) {
+        let group = if solution.q[4] > 0.0 { "accepted" } else { "rejected" };
+        let path = format!("/meshcat/irb6640/{group}/branch_{}/tool", solution.branch);
+
+        let object = &frames[12];
+        assert_eq!(field(object, "type").as_str(), Some("set_object"));
+        assert_eq!(field(object, "path").as_str(), Some(path.as_str()));
+        let geometry = &field(field(object, "object"), "geometries").as_array().unwrap()[0];
+        let position = field(field(field(geometry, "data"), "attributes"), "position");
+        assert!(matches!(field(position, "array"), Value::Ext(0x17, data) if data.len() == 72));
+
+        let tool = &frames[13];
+        assert_eq!(field(tool, "type").as_str(), Some("set_transform"));
+        assert_eq!(field(tool, "path").as_str(), Some(path.as_str()));
+        let matrix: Vec<f64> = field(tool, "matrix")
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|x| x.as_f64().unwrap())
+            .collect();
+        let (r_solution, t_solution) = robot.fk(&solution.q.into());
+        if !solution.is_ls {
+            assert!((Vector3::new(matrix[12], matrix[13], matrix[14]) - t_solution).norm() < 1e-9);
+            assert!((matrix[0] - r_solution[(0, 0)]).abs() < 1e-9);
+            assert!((matrix[4] - r_solution[(0, 1)]).abs() < 1e-9);
+        }
+    }
+
+    // A viewer connecting to the server is sent the frames shown so far, then every change
+    let mut server = MeshcatServer::bind("127.0.0.1:0").unwrap();
+    let kin = robot.kinematics();
+    log_configuration(&mut server, "irb6640", kin, &Vector6::from(q)).unwrap();
+    let (mut viewer, _) = tungstenite::connect(format!("ws://{}", server.address())).unwrap();
+    let mut receive = || match viewer.read().unwrap() {
+        tungstenite::Message::Binary(bytes) => read_commands(&bytes),
+        message => panic!("unexpected message {message:?}"),
+    };
+
+    let replayed: Vec<Value> = (0..14).flat_map(|_| receive()).collect();
+    assert_eq!(replayed.len(), 14);
+    assert!(replayed
+        .iter()
+        .any(|command| field(command, "path").as_str() == Some("/meshcat/irb6640/tool")));
+
+    log_configuration(&mut server, "irb6640", kin, &Vector6::zeros()).unwrap();
+    assert_eq!(server.viewers(), 1);
+    for _ in 0..7 {
+        let command = receive();
+        assert_eq!(field(&command[0], "type").as_str(), Some("set_transform"));
+    }
+}
+
+#[test]
+fn test_report() {
+    use crate::diagnostics::{report, ReportFormat};
+    use crate::limits::JointLimits;
+
+    let mut pairs = [[-PI, PI]; 6];
+    pairs[1] = [-1.0, 1.0];
+    let robot = irb6640().with_joint_limits(JointLimits::from_pairs(&pairs));
+    let (r, t) = robot.fk(&[0.3, 0.2, -0.1, 0.4, 0.9, 0.2]);
+    let within = robot.ik(r, t).len();
+
+    let markdown = report(&robot, r, t, ReportFormat::Markdown);
+    assert!(markdown.starts_with("# IK report"));
+    assert!(markdown.contains("| branch | q1 |"));
+    assert!(markdown.contains("right/up/no_flip"));
+    assert!(markdown.contains(&format!("6 solutions, 4 exact, {within} within")));
+    let rows = markdown
+        .lines()
+        .filter(|line| line.starts_with("| ") && !line.starts_with("| branch"))
+        .count();
+    assert_eq!(rows, 1 + 3 + 6);
+    let violating = markdown.lines().filter(|line| line.contains("q2 by")).count();
+    assert!(violating > 0);
+    assert_eq!(violating, 6 - within);
+
+    let html = report(&robot, r, t, ReportFormat::Html);
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.trim_end().ends_with("</html>"));
+    assert_eq!(html.matches("<tr>").count(), 1 + 3 + 1 + 6);
+}
+
+#[cfg(feature = "trace")]
+#[test]
+fn test_trace() {
+    use std::sync::{Arc, Mutex};
+    use tracing::{
+        field::{Field, Visit},
+        span, Event, Metadata, Subscriber,
+    };
+
+    /// Records the names of the spans entered and the `subproblem` fields of the events
+    #[derive(Default)]
+    struct Recorder {
+        spans: Mutex<Vec<&'static str>>,
+        subproblems: Mutex<Vec<u64>>,
+    }
+
+    struct SubproblemField(Option<u64>);
+
+    impl Visit for SubproblemField {
+        fn record_u64(&mut self, field: &Field, value: u64) {
+            if field.name() == "subproblem" {
+                self.0 = Some(value);
+            }
+        }
+
+        fn record_i64(&mut self, field: &Field, value: i64) {
+            self.record_u64(field, value as u64);
+        }
+
+        fn record_debug(&mut self, _: &Field, _: &dyn std::fmt::Debug) {}
+    }
+
+    struct Handle(Arc<Recorder>);
+
+    impl Subscriber for Handle {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+            let mut spans = self.0.spans.lock().unwrap();
+            spans.push(span.metadata().name());
+            span::Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut field = SubproblemField(None);
+            event.record(&mut field);
+            if let Some(subproblem) = field.0 {
+                self.0.subproblems.lock().unwrap().push(subproblem);
+            }
+        }
+
+        fn enter(&self, _: &span::Id) {}
+
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    let recorder = Arc::new(Recorder::default());
+    tracing::subscriber::with_default(Handle(recorder.clone()), || {
+        let robot = irb6640();
+        let (r, t) = robot.fk(&[0.3, 0.2, -0.1, 0.4, 0.9, 0.2]);
+        robot.ik(r, t);
+
+        // Out of reach, so the subproblem finding the elbow falls back to least squares
+        subproblem3(&Vector3::x(), &Vector3::zeros(), &Vector3::z(), 2.0);
+    });
+
+    assert_eq!(
+        *recorder.spans.lock().unwrap(),
+        vec!["spherical_two_parallel"]
+    );
+    assert!(recorder.subproblems.lock().unwrap().contains(&3));
+}
+
+#[cfg(feature = "simd")]
+#[test]
+fn test_simd_batch() {
+    use crate::inverse_kinematics::batch::spherical_two_parallel_batch;
+    use crate::subproblems::batch::{mask_to_bools, subproblem3_x8, Vector3x8};
+
+    let mut rng: Pcg64 = Seeder::from("simd").into_rng();
+    let robot = irb6640();
+    let kin = Irb6640::get_kin().cast::<f32>();
+
+    let configurations: Vec<[f64; 6]> = (0..21)
+        .map(|_| core::array::from_fn(|_| rng.random_range(-PI..PI)))
+        .collect();
+    let poses: Vec<(Matrix3<f32>, Vector3<f32>)> = configurations
+        .iter()
+        .map(|q| {
+            let (r, t) = robot.fk(q);
+            (r.cast(), t.cast())
+        })
+        .collect();
+
+    let solved = spherical_two_parallel_batch(&poses, &kin, 1e-3);
+    assert_eq!(solved.len(), poses.len());
+
+    for (q, branches) in configurations.iter().zip(&solved) {
+        let q = Vector6::from_row_slice(q);
+        let found = branches.iter().any(|(branch, is_ls)| {
+            !is_ls && (branch.cast::<f64>() - q).map(wrap_to_pi).amax() < 1e-2
+        });
+        assert!(found, "{q:?} is not among {branches:?}");
+
+        let (r, t) = robot.fk(&q.into());
+        for (branch, is_ls) in branches {
+            if !is_ls {
+                let (r_q, t_q) = robot.fk(&branch.cast::<f64>().into());
+                assert!((r_q - r).norm() < 1e-3 && (t_q - t).norm() < 1e-2);
+            }
+        }
+    }
+
+    // The lanes out of reach return the least squares solution twice
+    let p1 = Vector3x8::splat(&Vector3::x());
+    let p2 = Vector3x8::splat(&Vector3::new(2.0, 0.0, 0.0));
+    let k = Vector3x8::splat(&Vector3::z());
+    let d = wide::f32x8::new([2.0, 0.5, 2.0, 0.5, 2.0, 0.5, 2.0, 0.5]);
+    let ([theta_1, theta_2], is_ls) = subproblem3_x8(&p1, &p2, &k, d);
+    assert_eq!(
+        mask_to_bools(is_ls),
+        [false, true, false, true, false, true, false, true]
+    );
+    for (lane, is_ls) in mask_to_bools(is_ls).into_iter().enumerate() {
+        let d = [2.0, 0.5][lane % 2];
+        let expected = subproblem3(&Vector3::x(), &Vector3::new(2.0, 0.0, 0.0), &Vector3::z(), d);
+        assert_eq!(expected.1, is_ls);
+        let (a, b) = (theta_1.to_array()[lane] as f64, theta_2.to_array()[lane] as f64);
+        assert!(expected.0.get_all().all(|t| (t - a).abs() < 1e-5 || (t - b).abs() < 1e-5));
+    }
+}
+
+#[test]
+fn test_solve_into() {
+    use crate::{limits::JointLimits, solutionset::{SolutionBuffer, MAX_SOLUTIONS}, config::SolverConfig};
+
+    let mut rng: Pcg64 = Seeder::from("solve_into").into_rng();
+    let mut lower = Vector6::from_element(-PI);
+    let mut upper = Vector6::from_element(PI);
+    lower[5] = -TAU;
+    upper[5] = TAU;
+    let limits = JointLimits::new(lower, upper);
+
+    let mut solutions = SolutionBuffer::new();
+    let annotated = SolverConfig { annotate: true, ..SolverConfig::default() };
+    for robot in [irb6640(), spherical_bot(), three_parallel_bot(), ur5(), irb6640().with_joint_limits(limits)] {
+        let robot = robot.with_config(annotated);
+        for _ in 0..100 {
+            let q: [f64; 6] = core::array::from_fn(|_| rng.random_range(-PI..PI));
+            let (r, t) = robot.fk(&q);
+
+            robot.solve_into(r, t, &mut solutions).unwrap();
+            let expected = robot.ik(r, t);
+            assert_eq!(solutions.len(), expected.len());
+            for (solution, expected) in solutions.iter().zip(expected.iter()) {
+                assert!((solution.q - expected.q).norm() < 1e-12);
+                assert_eq!(solution.branch, expected.branch);
+                assert_eq!(solution.is_ls, expected.is_ls);
+                assert!((solution.error - expected.error).abs() < 1e-12);
+                let manipulability = solution.manipulability.unwrap();
+                assert!((manipulability - expected.manipulability.unwrap()).abs() < 1e-9);
+            }
+        }
+    }
+
+    let (r, t) = irb6640().fk(&[0.1, 0.2, 0.3, 0.4, 0.5, 0.6]);
+    assert!(matches!(two_parallel_bot().solve_into(r, t, &mut solutions), Err(IkGeoError::AllocationRequired(_))));
+    let refined = irb6640().with_config(SolverConfig { refine: true, ..SolverConfig::default() });
+    assert!(matches!(refined.solve_into(r, t, &mut solutions), Err(IkGeoError::AllocationRequired(_))));
+
+    let wide = JointLimits::new(Vector6::from_element(-TAU), Vector6::from_element(TAU));
+    assert_eq!(
+        irb6640().with_joint_limits(wide).solve_into(r, t, &mut solutions),
+        Err(IkGeoError::CapacityExceeded { capacity: MAX_SOLUTIONS })
+    );
+}
+
+#[test]
+fn test_ik_with_scratch() {
+    use crate::inverse_kinematics::auxiliary::SolverScratch;
+
+    let mut rng: Pcg64 = Seeder::from("scratch").into_rng();
+    let mut scratch = SolverScratch::new();
+    for (robot, iterations) in [
+        (two_parallel_bot(), 20),
+        (Robot::gen_six_dof(Irb6640::get_kin()), 2),
+    ] {
+        for _ in 0..iterations {
+            let q: [f64; 6] = core::array::from_fn(|_| rng.random_range(-PI..PI));
+            let (r, t) = robot.fk(&q);
+
+            let expected = robot.ik(r, t);
+            let solutions = robot.ik_with_scratch(r, t, &mut scratch);
+            assert_eq!(solutions.len(), expected.len());
+            for (solution, expected) in solutions.iter().zip(expected.iter()) {
+                assert_eq!(solution.q, expected.q);
+                assert_eq!(solution.is_ls, expected.is_ls);
+            }
+        }
+    }
+
+    let sweep = SevenDofSweep::rrc(SweepStrategy::GoldenSection(12, 1e-6));
+    let kin = RrcFixedQ6::get_kin();
+    for _ in 0..3 {
+        let q = SVector::<f64, 7>::from(rng.random::<[f64; 7]>().map(|x| x * 2.0 * PI - PI));
+        let (r, t) = kin.forward_kinematics(&q);
+
+        let expected = sweep.solve(r, t);
+        let samples = sweep.solve_with_scratch(r, t, &mut scratch);
+        assert_eq!(samples.len(), expected.len());
+        for (sample, expected) in samples.iter().zip(expected.iter()) {
+            assert_eq!(sample.q_free, expected.q_free);
+            assert_eq!(sample.solutions.len(), expected.solutions.len());
+        }
+    }
+}
+
+#[test]
+fn test_diagnostics_timing() {
+    use crate::diagnostics::{regressions, Diagnostics, RobotSetup, Timing};
+    use std::time::Duration;
+
+    let mut diagnostics = Diagnostics::new().with_seed(3);
+    diagnostics.register(RobotSetup::new("irb6640", irb6640()));
+    let timings = diagnostics.time(20);
+    assert_eq!(timings.len(), 1);
+    let timing = &timings[0];
+    assert_eq!(timing.name, "irb6640");
+    assert_eq!(timing.iterations, 20);
+    assert!(timing.min <= timing.median && timing.median <= timing.p95 && timing.p95 <= timing.max);
+    assert!(timing.min <= timing.mean && timing.mean <= timing.max);
+
+    let samples: Vec<Duration> = (1..=100).map(Duration::from_micros).collect();
+    let baseline = Timing::from_samples("solver", &samples);
+    assert_eq!(baseline.median, Duration::from_micros(51));
+    assert_eq!(baseline.p95, Duration::from_micros(95));
+
+    let doubled: Vec<Duration> = samples.iter().map(|t| *t * 2).collect();
+    let slower = [Timing::from_samples("solver", &doubled), Timing::from_samples("new", &doubled)];
+    let found = regressions(&slower, core::slice::from_ref(&baseline), 0.1);
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].0.name, "solver");
+    assert!((found[0].1 - 2.0).abs() < 1e-12);
+    let unchanged = [baseline.clone()];
+    assert!(regressions(&unchanged, &unchanged, 0.1).is_empty());
+
+    let builtin = Diagnostics::builtin().names();
+    let mut split = Diagnostics::decompositions().names();
+    split.extend(Diagnostics::hardcoded().names());
+    assert_eq!(builtin, split);
+}
+
+#[cfg(feature = "gpu")]
+#[test]
+fn test_gpu_batch() {
+    use crate::gpu::{GpuBatchSolver, SHADER};
+    use wgpu::naga::{front::wgsl, valid::{Capabilities, ValidationFlags, Validator}};
+
+    let module = wgsl::parse_str(SHADER).unwrap();
+    Validator::new(ValidationFlags::all(), Capabilities::empty()).validate(&module).unwrap();
+
+    let mut rng: Pcg64 = Seeder::from("gpu").into_rng();
+    let robot = irb6640();
+    let poses: Vec<_> = (0..100)
+        .map(|_| robot.fk(&core::array::from_fn(|_| rng.random_range(-PI..PI))))
+        .collect();
+    let expected = robot.solve_batch(&poses);
+
+    let cpu = GpuBatchSolver::cpu(Irb6640::get_kin());
+    assert!(!cpu.is_gpu());
+    assert_eq!(cpu.solve_batch(&poses), expected);
+
+    // Without an adapter this falls back to the CPU as well
+    let gpu = GpuBatchSolver::new(Irb6640::get_kin());
+    for (solutions, expected) in gpu.solve_batch(&poses).iter().zip(&expected) {
+        assert_eq!(solutions.len(), expected.len());
+        for solution in solutions.iter() {
+            assert!(expected.iter().any(|e| (solution.q - e.q).map(wrap_to_pi).norm() < 1e-2));
+        }
+    }
+}
+
+#[test]
+fn test_solve_with_derivatives() {
+    use crate::robot::IkSolutionSet;
+
+    let mut rng: Pcg64 = Seeder::from("derivatives").into_rng();
+    let epsilon = 1e-6;
+
+    for robot in [irb6640(), ur5(), spherical_bot()] {
+        for _ in 0..20 {
+            let q: [f64; 6] = core::array::from_fn(|_| rng.random_range(-PI..PI));
+            let (r, t) = robot.fk(&q);
+            let twist = Vector6::from_fn(|_, _| rng.random_range(-1.0..1.0));
+
+            // Move the pose both ways along the twist and solve again
+            let omega = Vector3::new(twist[0], twist[1], twist[2]);
+            let v = Vector3::new(twist[3], twist[4], twist[5]);
+            let moved = |step: f64| {
+                robot.ik(rot(&omega.normalize(), omega.norm() * step) * r, t + v * step)
+            };
+            let (forward, backward) = (moved(epsilon), moved(-epsilon));
+
+            for derivative in robot.solve_with_derivatives(r, t) {
+                let Some(dq) = derivative.joint_velocity(&twist) else {
+                    continue;
+                };
+                let branch = |solutions: &IkSolutionSet| {
+                    solutions
+                        .iter()
+                        .filter(|solution| solution.branch == derivative.solution.branch)
+                        .map(|solution| solution.q)
+                        .min_by(|a, b| {
+                            let distance = |q: &Vector6<f64>| {
+                                (q - derivative.solution.q).map(wrap_to_pi).norm()
+                            };
+                            distance(a).total_cmp(&distance(b))
+                        })
+                        .unwrap()
+                };
+                let expected =
+                    (branch(&forward) - branch(&backward)).map(wrap_to_pi) / (2.0 * epsilon);
+                let scale = 1.0 + dq.norm();
+                assert!((expected - dq).norm() < 1e-3 * scale, "{expected} != {dq}");
+            }
+        }
+    }
+}
+
+#[test]
+fn test_differentiable_kinematics() {
+    use crate::{
+        config::{ErrorMetric, SolverConfig},
+        derivatives::DifferentiableKinematics,
+    };
+    use num_dual::{Dual64, DualNum};
+
+    let mut rng: Pcg64 = Seeder::from("autodiff").into_rng();
+    let kin = Ur5::get_kin();
+    let epsilon = 1e-6;
+
+    for _ in 0..20 {
+        let q = Vector6::from_fn(|_, _| rng.random_range(-PI..PI));
+        let (r, t) = kin.forward_kinematics(&Vector6::from_fn(|_, _| rng.random_range(-PI..PI)));
+        // Seed joint `i` with a unit derivative
+        let seeded = |i: usize| {
+            q.map(Dual64::from)
+                .map_with_location(|j, _, x| if j == i { x.derivative() } else { x })
+        };
+
+        // The derivatives of the position are the linear rows of the Jacobian
+        let jacobian = kin.jacobian(&q);
+        for i in 0..6 {
+            let (_, t_q) = kin.fk(&seeded(i));
+            let dt = t_q.map(|x| x.eps);
+            assert!((dt - jacobian.fixed_view::<3, 1>(3, i)).norm() < 1e-9);
+        }
+
+        for error_metric in [
+            ErrorMetric::Frobenius,
+            ErrorMetric::QuaternionAngle,
+            ErrorMetric::SE3Distance,
+        ] {
+            let config = SolverConfig {
+                error_metric,
+                rotation_weight: 0.5,
+                ..SolverConfig::default()
+            };
+            for i in 0..6 {
+                let gradient = kin.pose_error(&seeded(i), &r, &t, &config).eps;
+
+                let mut step = Vector6::zeros();
+                step[i] = epsilon;
+                let expected = (kin.pose_error(&(q + step), &r, &t, &config)
+                    - kin.pose_error(&(q - step), &r, &t, &config))
+                    / (2.0 * epsilon);
+                assert!((gradient - expected).abs() < 1e-6, "{gradient} != {expected}");
+            }
+
+            let value = kin.pose_error(&q.map(Dual64::from), &r, &t, &config).re();
+            assert!((value - kin.pose_error(&q, &r, &t, &config)).abs() < 1e-12);
+        }
+    }
+}
+
+#[test]
+fn test_optimize_redundancy() {
+    use crate::{
+        limits::JointLimits,
+        redundancy::{joint_limit_cost, RedundancyParameter},
+    };
+
+    let kin = KukaR800FixedQ3::get_kin();
+    let sweep = SevenDofSweep::kuka_r800(SweepStrategy::GoldenSection(24, 1e-6));
+    let limits = JointLimits::from_pairs(&[
+        [-2.9, 2.9],
+        [-2.0, 2.0],
+        [-2.9, 2.9],
+        [-2.0, 2.0],
+        [-2.9, 2.9],
+        [-2.0, 2.0],
+        [-3.0, 3.0],
+    ]);
+    let mut rng: Pcg64 = Seeder::from("redundancy").into_rng();
+
+    for _ in 0..3 {
+        let q = SVector::<f64, 7>::from(rng.random::<[f64; 7]>().map(|x| x * 2.0 - 1.0));
+        let (rot, translation) = kin.forward_kinematics(&q);
+
+        for parameter in [RedundancyParameter::LockedJoint, RedundancyParameter::ArmAngle] {
+            let costs: [&dyn Fn(&IkSolution<7>) -> f64; 3] = [
+                &|solution| joint_limit_cost(&limits, &solution.q),
+                &|solution| sweep.manipulability_cost(&solution.q),
+                &|solution| sweep.elbow_height_cost(&solution.q),
+            ];
+            for cost in costs {
+                let optimum = sweep.optimize_redundancy(rot, translation, parameter, cost).unwrap();
+                assert_eq!(optimum.landscape.len(), 24);
+                assert!(optimum.landscape.iter().all(|s| optimum.cost <= s.cost + 1e-9));
+                assert_eq!(optimum.cost, cost(&optimum.solution));
+
+                let (rot_q, translation_q) = kin.forward_kinematics(&optimum.solution.q);
+                assert!((rot_q - rot).norm() + (translation_q - translation).norm() < TOLERANCE);
+                let value = match parameter {
+                    RedundancyParameter::LockedJoint => optimum.solution.q[2],
+                    RedundancyParameter::ArmAngle => sweep.arm_angle_of(&optimum.solution.q),
+                };
+                assert!(wrap_to_pi(value - optimum.value).abs() < 1e-6);
+            }
+        }
+    }
+
+    // A search with a tolerance of zero stops after a bounded number of steps
+    let exhaustive = SevenDofSweep::kuka_r800(SweepStrategy::GoldenSection(24, 0.0));
+    let (rot, translation) = kin.forward_kinematics(&SVector::from([0.3; 7]));
+    let parameter = RedundancyParameter::LockedJoint;
+    let cost = |solution: &IkSolution<7>| exhaustive.manipulability_cost(&solution.q);
+    let optimum = exhaustive.optimize_redundancy(rot, translation, parameter, cost);
+    assert!(optimum.is_some());
+}
+
+#[test]
+fn test_solve_with_clearance() {
+    use crate::obstacles::Obstacle;
+
+    let (p, q) = (Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+    let sphere = Obstacle::Sphere { center: Vector3::new(0.5, 2.0, 0.0), radius: 0.5 };
+    assert!((sphere.distance_to_segment(&p, &q) - 1.5).abs() < 1e-12);
+    let sphere = Obstacle::Sphere { center: Vector3::new(3.0, 0.0, 0.0), radius: 0.5 };
+    assert!((sphere.distance_to_segment(&p, &q) - 1.5).abs() < 1e-12);
+    let parallel = Obstacle::Capsule {
+        a: Vector3::new(0.5, 1.0, 0.0),
+        b: Vector3::new(2.0, 1.0, 0.0),
+        radius: 0.25,
+    };
+    assert!((parallel.distance_to_segment(&p, &q) - 0.75).abs() < 1e-12);
+    let crossing = Obstacle::Capsule {
+        a: Vector3::new(0.5, -1.0, 1.0),
+        b: Vector3::new(0.5, 1.0, 1.0),
+        radius: 0.25,
+    };
+    assert!((crossing.distance_to_segment(&p, &q) - 0.75).abs() < 1e-12);
+
+    let kin = KukaR800FixedQ3::get_kin();
+    let sweep = SevenDofSweep::kuka_r800(SweepStrategy::Grid(36));
+    let mut rng: Pcg64 = Seeder::from("clearance").into_rng();
+
+    for _ in 0..5 {
+        let q = SVector::<f64, 7>::from(rng.random::<[f64; 7]>().map(|x| x * 2.0 - 1.0));
+        let (rot, translation) = kin.forward_kinematics(&q);
+
+        // An obstacle around the elbow of `q`
+        let elbow = kin.joint_origins(&q).column(3).into();
+        let obstacles = [Obstacle::Sphere { center: elbow, radius: 0.05 }];
+        let blocked = sweep.arm_clearance(&q, &obstacles);
+        assert!(blocked < 0.0);
+
+        let result = sweep.solve_with_clearance(rot, translation, &obstacles);
+        assert!(!result.candidates.is_empty());
+        let best = result.best.unwrap();
+        let clearance = sweep.arm_clearance(&best.solution.q, &obstacles);
+        assert_eq!(-best.cost, clearance);
+        assert!(clearance > blocked);
+        assert!(result.candidates.iter().all(|c| c.clearance <= clearance + 1e-9));
+
+        let (rot_q, translation_q) = kin.forward_kinematics(&best.solution.q);
+        assert!((rot_q - rot).norm() + (translation_q - translation).norm() < TOLERANCE);
+    }
+}
+
+#[test]
+fn test_solve_toleranced() {
+    use crate::toleranced::Tolerance;
+
+    let robot = irb6640();
+    let mut rng: Pcg64 = Seeder::from("toleranced").into_rng();
+    let step = 0.01;
+    let tolerance = Tolerance::new(Vector3::repeat(2.0 * step), Vector3::repeat(0.02));
+
+    for _ in 0..5 {
+        let q: [f64; 6] = core::array::from_fn(|_| rng.random_range(-PI..PI));
+        let (r, t) = robot.fk(&q);
+
+        // A reachable target keeps its exact solutions, which deviate from it the least
+        let solutions = robot.solve_toleranced(r, t, tolerance);
+        assert!(!solutions.is_empty());
+        assert!(!solutions[0].solution.is_ls);
+        assert!(solutions[0].deviation.norm() < 1e-6);
+
+        // Move the target away from the base until it is just out of reach
+        let outward = Vector3::new(t.x, t.y, 0.0).normalize();
+        let mut target = t;
+        while robot.ik(r, target).iter().any(|solution| !solution.is_ls) {
+            target += outward * step;
+        }
+
+        let solutions = robot.solve_toleranced(r, target, tolerance);
+        assert!(!solutions.is_empty());
+        for solution in &solutions {
+            assert!(tolerance.normalized(&solution.deviation) <= 1.0);
+            assert!(solution.solution.error > 0.0);
+        }
+        assert!(!solutions[0].solution.is_ls);
+    }
+}
+
+#[test]
+fn test_pose_constructors() {
+    let mut rng: Pcg64 = Seeder::from("pose constructors").into_rng();
+    let random = |rng: &mut Pcg64| Vector3::from_fn(|_, _| rng.random_range(-1.0..1.0));
+
+    for _ in 0..100 {
+        let (origin, x_point, xy_point) = (random(&mut rng), random(&mut rng), random(&mut rng));
+        let pose = Pose::from_three_points(origin, x_point, xy_point).unwrap();
+        let r = pose.rotation;
+        assert!((r.transpose() * r - Matrix3::identity()).norm() < 1e-12);
+        assert!((r.determinant() - 1.0).abs() < 1e-12);
+        assert_eq!(pose.translation, origin);
+
+        // The x point lies on the x axis and the xy point in the xy plane on the side of +y
+        let local = |p: Vector3<f64>| r.transpose() * (p - origin);
+        let (x_local, xy_local) = (local(x_point), local(xy_point));
+        assert!(x_local.x > 0.0 && x_local.yz().norm() < 1e-12);
+        assert!(xy_local.y > 0.0 && xy_local.z.abs() < 1e-12);
+
+        let target = random(&mut rng);
+        let pose = Pose::look_at(origin, target, Vector3::x()).unwrap();
+        let z = pose.rotation.column(2);
+        assert!((z - (target - origin).normalize()).norm() < 1e-12);
+        assert!(pose.rotation.column(0).dot(&Vector3::x()) >= 0.0);
+        assert!((pose.rotation.determinant() - 1.0).abs() < 1e-12);
+    }
+
+    let origin = Vector3::zeros();
+    assert!(Pose::from_three_points(origin, Vector3::x(), Vector3::x() * 2.0).is_none());
+    assert!(Pose::look_at(origin, origin, Vector3::x()).is_none());
+    assert!(Pose::look_at(origin, Vector3::z(), Vector3::z()).is_none());
+}
+
+#[test]
+fn test_pose_parse_rotation_formats() {
+    use crate::pose::{RotationFormat, RpyConvention};
+    use nalgebra::{Rotation3, UnitQuaternion};
+
+    let mut rng: Pcg64 = Seeder::from("rotation formats").into_rng();
+    let join = |values: &[f64]| {
+        values.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(",")
+    };
+
+    for _ in 0..100 {
+        let axis = Vector3::from_fn(|_, _| rng.random_range(-1.0..1.0)).normalize();
+        let angle = rng.random_range(0.0..PI);
+        let r = rot(&axis, angle);
+        let t = Vector3::from_fn(|_, _| rng.random_range(-1.0..1.0));
+        let q = UnitQuaternion::from_matrix(&r);
+        let (roll, pitch, yaw) = Rotation3::from_matrix(&r).euler_angles();
+
+        let lines = [
+            (Some(RotationFormat::Matrix), join(&[r.transpose().as_slice(), t.as_slice()].concat())),
+            (None, join(&[r.transpose().as_slice(), t.as_slice()].concat())),
+            (Some(RotationFormat::QuaternionWxyz), join(&[&[q.w, q.i, q.j, q.k], t.as_slice()].concat())),
+            (Some(RotationFormat::QuaternionXyzw), join(&[&[q.i, q.j, q.k, q.w], t.as_slice()].concat())),
+            (None, join(&[&[roll, pitch, yaw], t.as_slice()].concat())),
+            (Some(RotationFormat::AxisAngle), join(&[axis.as_slice(), &[angle], t.as_slice()].concat())),
+        ];
+        for (format, line) in lines {
+            let pose = Pose::parse(&line, format).unwrap();
+            assert!((pose.rotation - r).norm() < 1e-9, "{format:?}: {line}");
+            assert!((pose.translation - t).norm() < 1e-12);
+        }
+    }
+
+    // Intrinsic angles apply the same rotations in the opposite order
+    let intrinsic = RotationFormat::Rpy(RpyConvention::Intrinsic).rotation(&[0.1, 0.2, 0.3]);
+    let expected = rot(&Vector3::x(), 0.1) * rot(&Vector3::y(), 0.2) * rot(&Vector3::z(), 0.3);
+    assert!((intrinsic - expected).norm() < 1e-12);
+
+    assert_eq!(
+        RotationFormat::from_header("# rotation: xyzw"),
+        Some(Ok(RotationFormat::QuaternionXyzw))
+    );
+    assert_eq!(RotationFormat::from_header("# a comment"), None);
+    assert!(matches!(
+        RotationFormat::from_header("# rotation: euler"),
+        Some(Err(IkGeoError::UnknownRotationFormat(_)))
+    ));
+    for name in RotationFormat::NAMES {
+        assert!(name.parse::<RotationFormat>().is_ok());
+    }
+
+    // Quaternions and axis-angle rotations both take 7 values
+    assert!(matches!(
+        Pose::parse("1,0,0,0,0.5,0,0", None),
+        Err(IkGeoError::UnknownRotationFormat(_))
+    ));
+    assert_eq!(
+        Pose::parse("1,0,0,0,0.5,0", Some(RotationFormat::QuaternionWxyz)),
+        Err(IkGeoError::DimensionMismatch { expected: 7, found: 6 })
+    );
+}
+
+#[test]
+fn test_rotation_policy() {
+    use crate::config::{RotationPolicy, SolverConfig};
+    use crate::pose::{project_to_rotation, rotation_defect, validate_pose};
+
+    let mut rng: Pcg64 = Seeder::from("rotation policy").into_rng();
+    let q: [f64; 6] = core::array::from_fn(|_| rng.random_range(-PI..PI));
+    let (r, t) = irb6640().fk(&q);
+    let noisy = r + Matrix3::from_fn(|_, _| rng.random_range(-1e-3..1e-3));
+    assert!(rotation_defect(&r) < 1e-12);
+    assert!(rotation_defect(&noisy) > 1e-6);
+
+    let projected = project_to_rotation(&noisy);
+    assert!(rotation_defect(&projected) < 1e-12);
+    assert!((projected.determinant() - 1.0).abs() < 1e-12);
+    assert!((projected - r).norm() < 1e-2);
+    let reflection = Matrix3::from_diagonal(&Vector3::new(1.0, 1.0, -1.0));
+    assert!((project_to_rotation(&reflection).determinant() - 1.0).abs() < 1e-12);
+
+    let with_policy = |rotation_policy| {
+        irb6640().with_config(SolverConfig { rotation_policy, ..SolverConfig::default() })
+    };
+
+    // Valid rotations are solved unchanged under every policy
+    for policy in [RotationPolicy::Ignore, RotationPolicy::Project, RotationPolicy::Error] {
+        let config = SolverConfig { rotation_policy: policy, ..SolverConfig::default() };
+        assert_eq!(validate_pose(&r, &config), Ok(r));
+        assert_eq!(with_policy(policy).ik(r, t).len(), irb6640().ik(r, t).len());
+    }
+
+    let ignored = with_policy(RotationPolicy::Ignore).ik(noisy, t);
+    assert!(ignored.iter().all(|solution| solution.error > 1e-4));
+
+    let projected_solutions = with_policy(RotationPolicy::Project).ik(noisy, t);
+    assert!(!projected_solutions.is_empty());
+    for solution in projected_solutions.iter().filter(|solution| !solution.is_ls) {
+        let (r_q, t_q) = irb6640().fk(&solution.q.into());
+        assert!((r_q - projected).norm() < 1e-9 && (t_q - t).norm() < 1e-9);
+    }
+
+    let strict = with_policy(RotationPolicy::Error);
+    assert!(strict.ik(noisy, t).is_empty());
+    assert!(matches!(strict.try_ik(noisy, t), Err(IkGeoError::InvalidRotation { .. })));
+    let mut solutions = crate::robot::SolutionBuffer::new();
+    assert!(matches!(
+        strict.solve_into(noisy, t, &mut solutions),
+        Err(IkGeoError::InvalidRotation { .. })
+    ));
+    assert!(matches!(
+        strict.try_ik(Matrix3::from_element(f64::NAN), t),
+        Err(IkGeoError::InvalidRotation { .. })
+    ));
+}
+
+#[test]
+fn test_validate_kinematics() {
+    use crate::inverse_kinematics::family::KinematicFamily;
+    use crate::model_check::{AxisRelation, ModelWarning};
+
+    for name in ["irb120", "irb1200", "irb2600", "irb4600", "irb6640", "motoman_gp8", "ur5"] {
+        let report = preset(name).unwrap().kinematics().validate();
+        assert!(report.is_ok(), "{name}: {report:?}");
+    }
+
+    let kin = Irb6640::get_kin();
+    let report = kin.validate();
+    let pairs = |relation| report.pairs(relation).map(|pair| pair.axes).collect::<Vec<_>>();
+    assert_eq!(pairs(AxisRelation::Parallel), vec![(1, 2)]);
+    assert_eq!(pairs(AxisRelation::Intersecting), vec![(3, 4), (4, 5)]);
+
+    // A slightly skewed parallel axis and an axis that is not a unit vector
+    let mut skewed = kin.clone();
+    skewed.h.set_column(2, &(rot(&Vector3::x(), 1e-4) * Vector3::y()));
+    skewed.h.set_column(5, &(Vector3::x() * 1.01));
+    let report = skewed.validate();
+    assert!(!report.is_ok());
+    assert_eq!(report.non_unit_axes.iter().map(|(i, _)| *i).collect::<Vec<_>>(), vec![5]);
+    let nearly = report.pairs(AxisRelation::NearlyParallel).next().unwrap();
+    assert_eq!(nearly.axes, (1, 2));
+    assert!((nearly.angle - 1e-4).abs() < 1e-9);
+
+    // A wrist center missed by a rounding error
+    let mut offset = kin.clone();
+    offset.p.set_column(5, &Vector3::new(0.0, 0.0, 1e-3));
+    let report = offset.validate();
+    assert_eq!(report.pairs(AxisRelation::NearlyIntersecting).next().unwrap().axes, (4, 5));
+
+    // The positions of the joints given in place of the offsets between them
+    let mut positions = kin.clone();
+    for i in 1..7 {
+        let previous = positions.p.column(i - 1).clone_owned();
+        positions.p.set_column(i, &(positions.p.column(i) + previous));
+    }
+    assert!(positions.validate().warnings.contains(&ModelWarning::AbsolutePositions {
+        given: KinematicFamily::TwoParallel,
+        family: KinematicFamily::SphericalTwoParallel,
+    }));
+
+    let mut millimeters = kin.clone();
+    millimeters.p *= 1000.0;
+    assert!(matches!(
+        millimeters.validate().warnings[..],
+        [ModelWarning::LargeOffsets { .. }]
+    ));
+}
+
+#[test]
+fn test_convention_round_trips() {
+    use crate::conventions::{ScrewFrame, ProductOfExponentials};
+    use crate::inverse_kinematics::{auxiliary::JointType, family::{classify, KinematicFamily}};
+    use nalgebra::{Isometry3, Translation3, UnitQuaternion};
+
+    let mut rng: Pcg64 = Seeder::from("conventions").into_rng();
+    let random_vector = |rng: &mut Pcg64| Vector3::from_fn(|_, _| rng.random_range(-1.0..1.0));
+    let random_isometry = |rng: &mut Pcg64| {
+        Isometry3::from_parts(
+            Translation3::from(random_vector(rng)),
+            UnitQuaternion::from_scaled_axis(random_vector(rng)),
+        )
+    };
+
+    let mut general = Kinematics::<6, 7>::new()
+        .with_joint_types([JointType::Revolute, JointType::Prismatic, JointType::Revolute,
+            JointType::Revolute, JointType::Revolute, JointType::Revolute])
+        .with_base(random_isometry(&mut rng))
+        .with_tool(random_isometry(&mut rng));
+    for i in 0..7 {
+        if i < 6 {
+            general.h.set_column(i, &random_vector(&mut rng).normalize());
+        }
+        general.p.set_column(i, &random_vector(&mut rng));
+    }
+    let irb6640 = Irb6640::get_kin().with_tool(random_isometry(&mut rng));
+
+    for kin in [general, irb6640.clone(), Ur5::get_kin()] {
+        let space = kin.to_poe(ScrewFrame::Space);
+        let body = kin.to_poe(ScrewFrame::Body);
+        let dh = kin.to_modified_dh();
+        let from_space = Kinematics::<6, 7>::from_poe(&space);
+        let from_body = Kinematics::<6, 7>::from_poe(&body);
+        let from_dh = Kinematics::<6, 7>::from_modified_dh(&dh);
+        assert_eq!(body.to_frame(ScrewFrame::Space).frame, ScrewFrame::Space);
+
+        for _ in 0..20 {
+            let q = Vector6::from_fn(|_, _| rng.random_range(-PI..PI));
+            let expected = Isometry3::from(Pose::from(kin.forward_kinematics(&q)));
+            let poses: [(&str, Isometry3<f64>); 6] = [
+                ("space", space.forward_kinematics(&q)),
+                ("body", body.forward_kinematics(&q)),
+                ("dh", dh.forward_kinematics(&q)),
+                ("from space", Pose::from(from_space.forward_kinematics(&q)).into()),
+                ("from body", Pose::from(from_body.forward_kinematics(&q)).into()),
+                ("from dh", Pose::from(from_dh.forward_kinematics(&q)).into()),
+            ];
+            for (name, pose) in poses {
+                let difference = expected.inverse() * pose;
+                assert!(
+                    difference.translation.vector.norm() < 1e-9 && difference.rotation.angle() < 1e-9,
+                    "{name}: {difference}"
+                );
+            }
+        }
+    }
+
+    // The origins land on the intersections, so the family of the chain survives the round trips
+    let poe: ProductOfExponentials<6> = irb6640.to_poe(ScrewFrame::Space);
+    assert_eq!(classify(&Kinematics::from_poe(&poe)), KinematicFamily::SphericalTwoParallel);
+    let dh = irb6640.to_modified_dh();
+    assert_eq!(classify(&Kinematics::from_modified_dh(&dh)), KinematicFamily::SphericalTwoParallel);
+
+    // The first and second axes of the IRB 6640 are perpendicular and offset by 0.32 m
+    assert!((dh.joints[1].a.abs() - 0.32).abs() < 1e-12);
+    assert!((dh.joints[1].alpha.abs() - PI / 2.0).abs() < 1e-12);
+
+    let mut scaled = irb6640.clone();
+    scaled.h *= 2.0;
+    assert!((scaled.with_normalized_axes().h - irb6640.h).norm() < 1e-12);
+}
+
+#[test]
+fn test_screw_utilities() {
+    use crate::conventions::ScrewFrame;
+    use crate::screw::{adjoint, exp, from_axis, ScrewMotion};
+
+    let mut rng: Pcg64 = Seeder::from("screws").into_rng();
+    let random_vector = |rng: &mut Pcg64| Vector3::from_fn(|_, _| rng.random_range(-1.0..1.0));
+    let close = |a: &Pose, b: &Pose| {
+        (a.rotation - b.rotation).norm() < 1e-9 && (a.translation - b.translation).norm() < 1e-9
+    };
+
+    // A quarter turn about the z axis through (1, 0, 0) takes the origin to (1, -1, 0)
+    let quarter = exp(&from_axis(&Vector3::x(), &Vector3::z(), 0.0), PI / 2.0);
+    assert!((quarter.translation - Vector3::new(1.0, -1.0, 0.0)).norm() < 1e-12);
+
+    for _ in 0..50 {
+        let point = random_vector(&mut rng);
+        let direction = random_vector(&mut rng).normalize();
+        let pitch = rng.random_range(-0.5..0.5);
+        let theta = rng.random_range(0.1..3.0);
+        let screw = from_axis(&point, &direction, pitch);
+        let from = Pose::new(rot(&random_vector(&mut rng).normalize(), 1.0), random_vector(&mut rng));
+
+        // The screw motion between two poses recovers the screw that moved one to the other
+        let to = Pose::from(Isometry3::from(exp(&screw, theta)) * Isometry3::from(from));
+        let motion = ScrewMotion::between(&from, &to, ScrewFrame::Space);
+        assert!((motion.theta - theta).abs() < 1e-9);
+        assert!((motion.screw - screw).norm() < 1e-9);
+        assert!((motion.pitch() - pitch).abs() < 1e-9);
+        let (closest, axis) = motion.axis().unwrap();
+        assert!((axis - direction).norm() < 1e-9);
+        assert!((closest - point).cross(&direction).norm() < 1e-9);
+
+        let body = ScrewMotion::between(&from, &to, ScrewFrame::Body);
+        let moved = Pose::from(Isometry3::from(from) * Isometry3::from(body.pose()));
+        assert!(close(&moved, &to));
+
+        // The adjoint maps a screw to the frame its pose is given in
+        let frame = Pose::from(Isometry3::from(from));
+        let mapped = exp(&(adjoint(&frame) * screw), theta);
+        let conjugated = Isometry3::from(frame)
+            * Isometry3::from(exp(&screw, theta))
+            * Isometry3::from(frame).inverse();
+        assert!(close(&mapped, &Pose::from(conjugated)));
+    }
+
+    let translation = ScrewMotion::between(
+        &Pose::identity(),
+        &Pose::new(Matrix3::identity(), Vector3::new(0.0, 2.0, 0.0)),
+        ScrewFrame::Space,
+    );
+    assert!(translation.is_translation() && translation.axis().is_none());
+    assert!((translation.theta - 2.0).abs() < 1e-12);
+
+    // The product of the exponentials of the joint screws is the forward kinematics
+    let kin = Irb6640::get_kin();
+    let screws = kin.to_screws();
+    let home = Isometry3::from(Pose::from(kin.forward_kinematics(&Vector6::zeros())));
+    for _ in 0..10 {
+        let q = Vector6::from_fn(|_, _| rng.random_range(-PI..PI));
+        let product = (0..6).fold(Isometry3::identity(), |t, i| {
+            t * Isometry3::from(exp(&screws[i], q[i]))
+        });
+        assert!(close(&Pose::from(product * home), &Pose::from(kin.forward_kinematics(&q))));
+    }
+}
+
+#[test]
+fn test_joint_mapping() {
+    use crate::joint_mapping::{JointMapping, MappedRobot};
+    use crate::inverse_kinematics::hardcoded::setups::FanucLrMate200id as FanucSetup;
+
+    let mut rng: Pcg64 = Seeder::from("joint mapping").into_rng();
+    let random_q = |rng: &mut Pcg64| Vector6::from_fn(|_, _| rng.random_range(-PI..PI));
+
+    let fanuc = JointMapping::fanuc_j2_j3();
+    let mapping = JointMapping::<6>::identity()
+        .with_offset(1, -PI / 2.0)
+        .with_sign_flip(3)
+        .with_coupling(5, 4, 0.25)
+        .unwrap();
+    for _ in 0..100 {
+        let m = random_q(&mut rng);
+        assert!((fanuc.to_joint(&m) - FanucSetup::from_joint_coordinates(&m)).norm() < 1e-12);
+        assert!((fanuc.to_motor(&m) - FanucSetup::to_joint_coordinates(&m)).norm() < 1e-12);
+
+        let q = mapping.to_joint(&m);
+        assert!((q[1] - (m[1] - PI / 2.0)).abs() < 1e-12);
+        assert!((q[3] + m[3]).abs() < 1e-12);
+        assert!((q[5] - (m[5] + 0.25 * m[4])).abs() < 1e-12);
+        assert!((mapping.to_motor(&q) - m).norm() < 1e-12);
+        assert!((mapping.velocity_to_motor(&mapping.velocity_to_joint(&m)) - m).norm() < 1e-12);
+    }
+
+    assert_eq!(
+        JointMapping::differential(4, 5, 0.0),
+        Err(IkGeoError::SingularCoupling)
+    );
+    assert!(JointMapping::<6>::identity()
+        .with_coupling(0, 0, -1.0)
+        .is_err());
+
+    // Forward kinematics take motor coordinates and inverse kinematics return them
+    let wrist = JointMapping::differential(4, 5, 2.0).unwrap().with_offset(0, 0.1);
+    let robot = MappedRobot::new(irb6640(), wrist);
+    for _ in 0..100 {
+        let m = random_q(&mut rng);
+        let (rot, translation) = robot.fk(&m.into());
+        let (rot_q, translation_q) = robot.robot().fk(&wrist.to_joint(&m).into());
+        assert!((rot - rot_q).norm() < 1e-12 && (translation - translation_q).norm() < 1e-12);
+
+        let solutions = robot.ik(rot, translation);
+        assert!(solutions.iter().filter(|s| !s.is_ls).all(|solution| {
+            let (rot_s, translation_s) = robot.fk(&solution.q.into());
+            (rot - rot_s).norm() < TOLERANCE && (translation - translation_s).norm() < TOLERANCE
+        }));
+        let near = robot.ik_near(rot, translation, &m);
+        assert!(near.iter().any(|solution| (solution.q - m).norm() < TOLERANCE));
+    }
+}
+
+#[test]
+fn test_lock_joint() {
+    use crate::inverse_kinematics::hardcoded::setups::PandaFixedQ4;
+    use crate::locked_joint::LockedRobot;
+    use nalgebra::{Translation3, UnitQuaternion};
+
+    let mut rng: Pcg64 = Seeder::from("lock joint").into_rng();
+    let mut kin = PandaFixedQ4::get_kin();
+    kin.base = Isometry3::from_parts(
+        Translation3::new(0.1, -0.2, 0.3),
+        UnitQuaternion::from_euler_angles(0.2, -0.1, 0.4),
+    );
+    kin.tool = Isometry3::from_parts(
+        Translation3::new(0.0, 0.0, 0.1),
+        UnitQuaternion::from_euler_angles(0.0, 0.3, 0.0),
+    );
+
+    // The remaining chain reaches the same poses as the full chain, whichever joint is locked
+    for joint in 0..7 {
+        let q_locked = rng.random_range(-PI..PI);
+        let locked = kin.lock_joint(joint, q_locked).unwrap();
+        for _ in 0..20 {
+            let q = Vector6::from_fn(|_, _| rng.random_range(-PI..PI));
+            let q_full = locked.expand(&q);
+            assert_eq!(q_full[joint], q_locked);
+            assert_eq!(locked.reduce(&q_full), q);
+
+            let (rot, translation) = locked.kinematics.forward_kinematics(&q);
+            let (rot_full, translation_full) = kin.forward_kinematics(&q_full);
+            assert!((rot - rot_full).norm() < 1e-12);
+            assert!((translation - translation_full).norm() < 1e-12);
+        }
+    }
+    assert!(matches!(
+        kin.lock_joint(7, 0.0),
+        Err(IkGeoError::InvalidJoint { joint: 7, .. })
+    ));
+
+    // Locking the fourth joint of the Panda leaves a chain solved in the general family
+    let robot = LockedRobot::new(kin.clone(), 3, -PI / 2.0).unwrap();
+    for _ in 0..5 {
+        let mut q = SVector::<f64, 7>::from_fn(|_, _| rng.random_range(-PI..PI));
+        q[3] = -PI / 2.0;
+        let (rot, translation) = robot.fk(&q);
+        let solutions = robot.ik(rot, translation);
+        assert!(solutions.iter().any(|solution| {
+            let (rot_s, translation_s) = robot.fk(&solution.q);
+            solution.q[3] == -PI / 2.0
+                && (rot - rot_s).norm() < TOLERANCE
+                && (translation - translation_s).norm() < TOLERANCE
+        }));
+    }
+}
+
+#[test]
+fn test_fixed_joint_full_solutions() {
+    use crate::inverse_kinematics::hardcoded::setups::{self, RrcFixedQ6 as RrcSetup};
+    use crate::locked_joint::{FixedJointSolver, FullOrReduced};
+    use crate::robot::{KinovaGen3FixedQ3, PandaFixedQ4, SawyerFixedQ3, YumiFixedQ3};
+
+    fn check<S: FixedJointSolver>(solver: &S, kin: &Kinematics<7, 8>, rng: &mut Pcg64) {
+        let (joint, value) = solver.fixed_joint();
+        let mut q = SVector::<f64, 7>::from_fn(|_, _| rng.random_range(-PI..PI));
+        q[joint] = value;
+        let (rot, translation) = kin.forward_kinematics(&q);
+
+        let full = solver.ik_full(rot, translation);
+        assert_eq!(full.len(), solver.ik(rot, translation).len());
+        // The residual of every full solution on the 7-DOF chain is the one the solver reported
+        assert!(!full.is_empty());
+        for solution in full.iter() {
+            assert_eq!(solution.q[joint], value);
+            let (rot_s, translation_s) = kin.forward_kinematics(&solution.q);
+            let residual = (rot - rot_s).norm() + (translation - translation_s).norm();
+            assert!((residual - solution.error).abs() < 1e-6);
+        }
+
+        assert!(matches!(
+            solver.ik_output(rot, translation, true),
+            FullOrReduced::Full(solutions) if solutions.len() == full.len()
+        ));
+        assert!(matches!(
+            solver.ik_output(rot, translation, false),
+            FullOrReduced::Reduced(solutions) if solutions.len() == full.len()
+        ));
+    }
+
+    let mut rng: Pcg64 = Seeder::from("fixed joint").into_rng();
+    for _ in 0..5 {
+        check(&crate::robot::KukaR800FixedQ3::new(), &KukaR800FixedQ3::get_kin(), &mut rng);
+        check(&crate::robot::RrcFixedQ6::new(), &RrcSetup::get_kin(), &mut rng);
+        check(&YumiFixedQ3::new(), &setups::YumiFixedQ3::get_kin(), &mut rng);
+        check(&PandaFixedQ4::new(), &setups::PandaFixedQ4::get_kin(), &mut rng);
+        check(&KinovaGen3FixedQ3::new(), &setups::KinovaGen3FixedQ3::get_kin(), &mut rng);
+        check(&SawyerFixedQ3::new(), &setups::SawyerFixedQ3::get_kin(), &mut rng);
+    }
+}
+
+#[test]
+fn test_solver_by_name() {
+    use crate::solver::{solver_by_name, solver_names, PoseSolver};
+
+    let mut rng: Pcg64 = Seeder::from("solver by name").into_rng();
+    assert!(solver_by_name("UR5").is_some());
+    assert!(solver_by_name("Ur10e").is_some());
+    assert!(solver_by_name("not a robot").is_none());
+
+    // Code written over `PoseSolver` works for every robot: the solutions are joint values of the
+    // kinematics it reports, and their residuals are those of the kinematics
+    fn residuals(solver: &dyn PoseSolver, q: &DVector<f64>) -> Vec<(f64, f64)> {
+        let kin = solver.kinematics();
+        let pose = Pose::from(kin.forward_kinematics(q).unwrap());
+        solver
+            .solve(&pose)
+            .iter()
+            .map(|solution| {
+                let q = DVector::from_column_slice(solution.q.as_slice());
+                let (rot, translation) = kin.forward_kinematics(&q).unwrap();
+                let residual = (rot - pose.rotation).norm() + (translation - pose.translation).norm();
+                (residual, solution.error)
+            })
+            .collect()
+    }
+
+    for name in solver_names() {
+        let solver = solver_by_name(name).unwrap();
+        assert_eq!(solver.dof(), 6, "{name}");
+        let q = DVector::from_fn(6, |_, _| rng.random_range(-PI..PI));
+        let residuals = residuals(solver.as_ref(), &q);
+        assert!(!residuals.is_empty(), "{name}");
+        for (residual, error) in residuals {
+            assert!((residual - error).abs() < 1e-6, "{name}: {residual} != {error}");
+        }
+    }
+
+    // FANUC solves in the joint angles of its kinematics, which map to the FANUC joint
+    // coordinates of `IKSolver::ik`
+    let fanuc = FanucLrMate200id::new();
+    let pose = PoseSolver::fk(&fanuc, &[0.3, 0.2, -0.1, 0.4, 0.5, 0.2]).unwrap();
+    let mapped = PoseSolver::solve(&fanuc, &pose).map_q(|q| fanuc.robot().mapping().to_motor(q));
+    let fanuc_coordinates = fanuc.ik(pose.rotation, pose.translation);
+    assert_eq!(mapped.len(), fanuc_coordinates.len());
+    for (a, b) in mapped.iter().zip(fanuc_coordinates.iter()) {
+        assert!((a.q - b.q).norm() < 1e-12);
+    }
+}
+
+#[test]
+fn test_robot_registry() {
+    use crate::{
+        registry::{register_robot, RobotDefinition, RobotRegistry},
+        robot::JointLimits,
+        solver::solver_by_name,
+    };
+
+    let limits = JointLimits::from_pairs(&[[-3.0, 3.0]; 6]);
+    let definition = RobotDefinition::new(Irb6640::get_kin()).with_joint_limits(limits);
+    let mut registry = RobotRegistry::new();
+    registry.register("My_Arm", definition.clone()).unwrap();
+    assert_eq!(registry.names().collect::<Vec<_>>(), ["my_arm"]);
+
+    // Names are matched ignoring case, and can be neither taken twice nor those of built-in robots
+    assert_eq!(
+        registry.register("MY_ARM", definition.clone()).err(),
+        Some(IkGeoError::DuplicateRobot(String::from("MY_ARM")))
+    );
+    assert_eq!(
+        registry.register("UR5", definition.clone()).err(),
+        Some(IkGeoError::DuplicateRobot(String::from("UR5")))
+    );
+
+    let robot = registry.robot("my_arm").unwrap();
+    assert_eq!(robot.joint_limits().copied(), Some(limits));
+    let q = [0.3, 0.2, -0.1, 0.4, 0.5, 0.2];
+    let pose = Pose::from(irb6640().fk(&q));
+    let solutions = registry.solver("my_arm").unwrap().solve(&pose);
+    assert!(solutions
+        .iter()
+        .any(|solution| (solution.q - Vector6::from(q)).map(wrap_to_pi).amax() < 1e-6));
+    assert!(registry.solver("ur5").is_some());
+
+    // The global registry is what `solver_by_name` looks robots up in
+    assert!(solver_by_name("registry test arm").is_none());
+    register_robot("Registry Test Arm", definition).unwrap();
+    assert_eq!(solver_by_name("registry test arm").unwrap().solve(&pose).len(), solutions.len());
+
+    #[cfg(feature = "yaml")]
+    {
+        let yaml = "
+name: yaml_arm
+h: [[0, 0, 1], [0, 1, 0], [0, 1, 0], [1, 0, 0], [0, 1, 0], [1, 0, 0]]
+p: [[0, 0, 0], [0.32, 0, 0.78], [0, 0, 1.075], [1.1425, 0, 0.2], [0, 0, 0], [0, 0, 0], [0.2, 0, 0]]
+";
+        assert_eq!(registry.register_yaml(yaml).unwrap(), "yaml_arm");
+        assert!(registry.robot("YAML_ARM").is_some());
+        assert_eq!(registry.len(), 2);
+    }
+}
+
+#[test]
+fn test_user_setup() {
+    use crate::{
+        diagnostics::{measure, ErrorHistogram, SetupIk, ERROR_BINS},
+        inverse_kinematics::hardcoded::setups::UrModel,
+    };
+
+    // A robot of your own joins the round trips of the crate by implementing `SetupIk`
+    struct Ur10eSetup {
+        robot: UniversalRobots,
+        r: Matrix3<f64>,
+        t: Vector3<f64>,
+        solutions: crate::robot::IkSolutionSet,
+    }
+
+    impl SetupIk for Ur10eSetup {
+        fn setup_with_rng(&mut self, rng: &mut dyn rand::RngCore) {
+            let q = Vector6::from_fn(|_, _| rng.random_range(-PI..PI));
+            (self.r, self.t) = self.robot.fk(&q.into());
+        }
+
+        fn setup_from_str(&mut self, raw: &str) -> Result<(), IkGeoError> {
+            (self.r, self.t) = hardcoded_setup_from_string(raw)?;
+            Ok(())
+        }
+
+        fn run(&mut self) {
+            self.solutions = self.robot.ik(self.r, self.t);
+        }
+
+        fn error(&self) -> f64 {
+            self.solutions
+                .iter()
+                .map(|solution| {
+                    let (r, t) = self.robot.fk(&solution.q.into());
+                    (r - self.r).norm() + (t - self.t).norm()
+                })
+                .reduce(f64::min)
+                .unwrap_or(f64::NAN)
+        }
+
+        fn ls_count(&self) -> usize {
+            self.solutions.iter().filter(|solution| solution.is_ls).count()
+        }
+
+        fn solution_count(&self) -> usize {
+            self.solutions.len()
+        }
+
+        fn name(&self) -> &'static str {
+            "ur10e"
+        }
+    }
+
+    let mut setup = Ur10eSetup {
+        robot: UniversalRobots::new(UrModel::Ur10e),
+        r: Matrix3::identity(),
+        t: Vector3::zeros(),
+        solutions: crate::robot::IkSolutionSet::new(),
+    };
+    let mut rng: Pcg64 = Seeder::from("user setup").into_rng();
+    let stats = measure(&mut setup, 100, &mut rng);
+    assert_eq!(stats.nan_percent, 0.0, "{stats}");
+    assert!(stats.solutions >= 100, "{stats}");
+    assert!(stats.ls_solutions < stats.solutions, "{stats}");
+    let ls_percent = stats.ls_solutions as f64 * 100.0 / stats.solutions as f64;
+    assert!((stats.ls_percent - ls_percent).abs() < 1e-12);
+    assert_eq!(stats.error_histogram.total(), 100);
+    assert_eq!(stats.error_histogram.count_below(ErrorHistogram::bin(1e-9)), 100);
+
+    assert_eq!(ErrorHistogram::bin(0.0), 0);
+    assert_eq!(ErrorHistogram::bin(1e-12), 4);
+    assert_eq!(ErrorHistogram::bin(2.0), ERROR_BINS - 1);
+    assert_eq!(ErrorHistogram::lower_edge(4), 1e-12);
+}
+
+#[test]
+fn test_accuracy_sweep() {
+    use crate::{
+        diagnostics::{accuracy_sweep, WORST_CASES},
+        solver::solver_by_name,
+    };
+
+    let mut rng: Pcg64 = Seeder::from("accuracy sweep").into_rng();
+    let solver = solver_by_name("irb6640").unwrap();
+    let report = accuracy_sweep(solver.as_ref(), 200, &mut rng);
+    assert_eq!(report.poses, 200);
+    assert!(report.p99 < 1e-9, "{report}");
+    assert!(report.min <= report.median && report.median <= report.max);
+    assert_eq!(report.exact_fraction, 1.0);
+    assert!(report.avg_branches >= 1.0 && report.avg_branches <= 8.0);
+
+    // The worst poses are kept, largest first, and solve again to the same residual
+    assert_eq!(report.worst.len(), WORST_CASES);
+    assert_eq!(report.worst[0].residual, report.max);
+    assert!(report.worst.windows(2).all(|w| w[0].residual >= w[1].residual));
+    let case = &report.worst[0];
+    let kin = solver.kinematics();
+    let reached = kin.forward_kinematics(&DVector::from_vec(case.q.clone())).unwrap();
+    assert!((reached.1 - case.pose.translation).norm() < 1e-12);
+    let residual = solver
+        .solve(&case.pose)
+        .iter()
+        .map(|solution| {
+            let q = DVector::from_column_slice(solution.q.as_slice());
+            let (r, t) = kin.forward_kinematics(&q).unwrap();
+            (r - case.pose.rotation).norm() + (t - case.pose.translation).norm()
+        })
+        .reduce(f64::min)
+        .unwrap();
+    assert_eq!(residual, case.residual);
+
+    let fixed = solver_by_name("kuka_r800_fixed_q3").unwrap();
+    let fixed = accuracy_sweep(fixed.as_ref(), 50, &mut rng);
+    assert!(fixed.median < 1e-9, "{fixed}");
+}
+
+#[test]
+fn test_replay() {
+    use crate::{
+        diagnostics::accuracy_sweep,
+        replay::{replay, ReplayCase, ReplayModel, ReplayRecorder},
+        solver::solver_by_name,
+    };
+
+    let mut rng: Pcg64 = Seeder::from("replay").into_rng();
+    let q = Vector6::from_fn(|_, _| rng.random_range(-PI..PI));
+
+    // A case parses back from its file to the same target, to the last bit
+    let mut kin = Irb6640::get_kin();
+    kin.tool = Isometry3::translation(0.0, 0.0, 0.1);
+    let robot = Robot::from_kinematics(kin);
+    let pose = Pose::from(robot.fk(&q.into()));
+    let case = ReplayCase::new(ReplayModel::from(&robot), pose, 1e-3);
+    let parsed: ReplayCase = case.to_string().parse().unwrap();
+    assert_eq!(parsed.pose, pose);
+    assert_eq!(parsed.residual, 1e-3);
+    let replayed = parsed.replay().unwrap();
+    assert!(replayed.residual < 1e-9);
+    assert_eq!(replayed.solutions.len(), robot.ik(pose.rotation, pose.translation).len());
+
+    // Only residuals above the threshold are recorded, and the files replay
+    let dir = std::env::temp_dir().join(format!("ik-geo-replay-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let recorder = ReplayRecorder::new(&dir, 1e-6);
+    let model = ReplayModel::Named(String::from("irb6640"));
+    let solutions = irb6640().ik(pose.rotation, pose.translation);
+    assert_eq!(recorder.check(&model, &pose, &solutions).unwrap(), None);
+
+    let sweep = accuracy_sweep(solver_by_name("irb6640").unwrap().as_ref(), 20, &mut rng);
+    assert!(ReplayRecorder::new(&dir, 1e-6)
+        .record_sweep(&model, &sweep)
+        .unwrap()
+        .is_empty());
+    let paths = ReplayRecorder::new(&dir, -1.0)
+        .record_sweep(&model, &sweep)
+        .unwrap();
+    assert_eq!(paths.len(), sweep.worst.len());
+    assert_ne!(paths[0], paths[1]);
+    let replayed = replay(&paths[0]).unwrap();
+    assert_eq!(replayed.residual, sweep.worst[0].residual);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(matches!(
+        "pose: 1,0,0,0,1,0,0,0,1,0,0,0".parse::<ReplayCase>(),
+        Err(IkGeoError::InvalidDescription(_))
+    ));
+}
+
+#[test]
+fn test_intersect_axes() {
+    use crate::robot::{RrcFixedQ6 as RrcRobot, SolverConfig};
+
+    let mut rng: Pcg64 = Seeder::from("intersect axes").into_rng();
+    let config = SolverConfig::default();
+
+    // Moving the offset onto the axes leaves the forward kinematics as they were
+    let (unreduced, _) = RrcFixedQ6::get_kin_unreduced_at(0.7);
+    let reduced = unreduced.intersect_axes(4, &config).unwrap();
+    assert_eq!(reduced.p.column(5), Vector3::zeros());
+    for _ in 0..10 {
+        let q = Vector6::from_fn(|_, _| rng.random_range(-PI..PI));
+        let (r_a, t_a) = unreduced.forward_kinematics(&q);
+        let (r_b, t_b) = reduced.forward_kinematics(&q);
+        assert!((r_a - r_b).norm() + (t_a - t_b).norm() < 1e-12);
+    }
+
+    // Near q6 = 0 the axes are close to parallel, and the reduction reports it
+    match RrcFixedQ6::get_kin_partial_at(1e-9, &config) {
+        Err(IkGeoError::IllConditioned { condition }) => {
+            assert!(condition > config.max_condition)
+        }
+        other => panic!("expected an ill-conditioned reduction, got {other:?}"),
+    }
+    let irb = Irb6640::get_kin();
+    assert!(matches!(
+        irb.intersect_axes(0, &config),
+        Err(IkGeoError::SkewAxes { axis: 0, .. })
+    ));
+    assert!(matches!(
+        irb.intersect_axes(5, &config),
+        Err(IkGeoError::InvalidJoint { joint: 6, .. })
+    ));
+
+    // The fixed-joint solver falls back to the sweep of the unreduced chain at the fixed q6, whose
+    // solutions reproduce the pose
+    let strict = SolverConfig {
+        max_condition: 1.0,
+        ..SolverConfig::default()
+    };
+    let fallback = RrcRobot::with_config(strict).unwrap();
+    assert!(!fallback.is_reduced());
+    assert!(RrcRobot::new().is_reduced());
+    let q = Vector6::from_fn(|_, _| rng.random_range(-PI..PI));
+    let (unreduced, r_6t) = RrcFixedQ6::get_kin_unreduced_at(RrcFixedQ6::Q6);
+    let (r, t) = unreduced.forward_kinematics(&q);
+    let r = r * r_6t;
+    let reproduces = |solution: &IkSolution| {
+        let (r_q, t_q) = unreduced.forward_kinematics(&solution.q);
+        (r_q * r_6t - r).norm() + (t_q - t).norm() < 1e-6
+    };
+    let solutions = fallback.ik(r, t);
+    assert!(solutions.iter().any(|solution| !solution.is_ls && reproduces(solution)));
+    let solutions = crate::inverse_kinematics::hardcoded::rrc_fixed_q6(&r, &t, &strict).unwrap();
+    assert!(solutions.iter().any(|solution| !solution.is_ls && reproduces(solution)));
+    for solution in &solutions {
+        let (r_q, t_q) = unreduced.forward_kinematics(&solution.q);
+        let residual = (r_q * r_6t - r).norm() + (t_q - t).norm();
+        assert!((residual - solution.error).abs() < 1e-9);
+    }
+
+    // So does the sweep, at every locked value where the reduction is ill-conditioned
+    let kin = RrcFixedQ6::get_kin();
+    let mut q7 = SVector::<f64, 7>::from_fn(|_, _| rng.random_range(-PI..PI));
+    q7[5] = 0.7;
+    let (r, t) = kin.forward_kinematics(&q7);
+    let sweep = SevenDofSweep::rrc(SweepStrategy::Grid(8)).with_config(strict);
+    let sample = sweep.solve_at(&r, &t, 0.7);
+    assert!(sample.solutions.iter().any(|solution| solution.error < 1e-6));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_solution_cache() {
+    use crate::cache::{CachedRobot, SolutionCache};
+
+    // The least recently used pose is evicted first
+    let mut cache = SolutionCache::new(2);
+    let r = Matrix3::identity();
+    let keys: Vec<_> = (0..3).map(|i| cache.key(&r, &Vector3::new(i as f64, 0.0, 0.0))).collect();
+    cache.insert(keys[0], Default::default());
+    cache.insert(keys[1], Default::default());
+    assert!(cache.get(&keys[0]).is_some());
+    cache.insert(keys[2], Default::default());
+    assert_eq!(cache.len(), 2);
+    assert!(cache.get(&keys[1]).is_none());
+    assert!(cache.get(&keys[0]).is_some() && cache.get(&keys[2]).is_some());
+    assert_eq!(cache.key(&r, &Vector3::new(0.0, 0.0, 2e-4)), keys[0]);
+
+    // Nearby poses are warm-started, keeping the branches and reaching the same solutions
+    let robot = Robot::gen_six_dof(TwoParallelBot::get_kin());
+    let cached = CachedRobot::new(Robot::gen_six_dof(TwoParallelBot::get_kin()), 16)
+        .with_resolution(1e-2, 1e-2);
+    let (r, t) = robot.fk(&[0.3, -0.5, 0.7, 1.1, -0.4, 0.9]);
+    for i in 0..5 {
+        let t = t + Vector3::new(1e-4, 0.0, 0.0) * i as f64;
+        let solutions = cached.ik(r, t);
+        let full = robot.ik(r, t);
+        assert!(!solutions.is_empty());
+        assert_eq!(solutions.len(), full.len());
+        for solution in &solutions {
+            assert!(solution.error < 1e-6);
+            assert!(full.iter().any(|other| (other.q - solution.q).norm() < 1e-6));
+        }
+    }
+    let stats = cached.stats();
+    assert_eq!(stats.misses, 1);
+    assert_eq!(stats.warm_starts + stats.hints, 4);
+    assert!(stats.warm_starts > 0);
+}
+
+#[test]
+fn test_pose_batch() {
+    use crate::pose::{PoseBatch, RotationFormat};
+    use nalgebra::{Rotation3, UnitQuaternion};
+
+    let robot = irb6640();
+    let mut rng: Pcg64 = Seeder::from("pose batch").into_rng();
+    let poses: Vec<_> = (0..50)
+        .map(|_| robot.fk(&rng.random::<[f64; 6]>().map(|x| x * 2.0 * PI - PI)))
+        .collect();
+
+    // The same poses in packed scalar-last arrays and in one column per component
+    let quaternions: Vec<UnitQuaternion<f64>> = poses
+        .iter()
+        .map(|(r, _)| {
+            UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix_unchecked(*r))
+        })
+        .collect();
+    let packed_q: Vec<f64> = quaternions.iter().flat_map(|q| q.coords.iter().copied()).collect();
+    let packed_t: Vec<f64> = poses.iter().flat_map(|(_, t)| t.iter().copied()).collect();
+    let w: Vec<f64> = quaternions.iter().map(|q| q.w).collect();
+    let x: Vec<f64> = quaternions.iter().map(|q| q.i).collect();
+    let y: Vec<f64> = quaternions.iter().map(|q| q.j).collect();
+    let z: Vec<f64> = quaternions.iter().map(|q| q.k).collect();
+    let t: [Vec<f64>; 3] = core::array::from_fn(|k| poses.iter().map(|(_, t)| t[k]).collect());
+
+    let packed =
+        PoseBatch::from_packed(&packed_q, &packed_t, RotationFormat::QuaternionXyzw).unwrap();
+    let columns = PoseBatch::from_columns([&w, &x, &y, &z], [&t[0], &t[1], &t[2]]).unwrap();
+    let expected = robot.solve_batch(&poses);
+    for batch in [packed, columns] {
+        assert_eq!(batch.len(), poses.len());
+        for (i, (r, t)) in poses.iter().enumerate() {
+            assert!((batch.rotation(i) - r).norm() < 1e-12);
+            assert_eq!(batch.translation(i), *t);
+        }
+        for (solutions, expected) in robot.solve_pose_batch(&batch).iter().zip(&expected) {
+            assert_eq!(solutions.len(), expected.len());
+            for (solution, expected) in solutions.iter().zip(expected.iter()) {
+                assert!((solution.q - expected.q).norm() < 1e-9);
+            }
+        }
+    }
+
+    assert_eq!(
+        PoseBatch::from_columns([&w, &x, &y, &z[1..]], [&t[0], &t[1], &t[2]]).unwrap_err(),
+        IkGeoError::DimensionMismatch { expected: 50, found: 49 }
+    );
+    assert!(matches!(
+        PoseBatch::from_packed(&packed_q, &packed_t, RotationFormat::Matrix),
+        Err(IkGeoError::UnknownRotationFormat(_))
+    ));
+
+    // The C interface reads the packed arrays
+    #[cfg(feature = "ffi")]
+    {
+        use crate::ffi::{
+            ik_geo_irb6640_batch, IK_GEO_INVALID_ARGUMENT, IK_GEO_MAX_SOLUTIONS, IK_GEO_OK,
+        };
+
+        let mut out_q = vec![0.0; 6 * IK_GEO_MAX_SOLUTIONS * poses.len()];
+        let mut out_is_ls = vec![0; IK_GEO_MAX_SOLUTIONS * poses.len()];
+        let mut out_error = vec![0.0; IK_GEO_MAX_SOLUTIONS * poses.len()];
+        let mut out_n = vec![0; poses.len()];
+        let status = unsafe {
+            ik_geo_irb6640_batch(
+                poses.len(),
+                packed_q.as_ptr(),
+                packed_t.as_ptr(),
+                out_q.as_mut_ptr(),
+                out_is_ls.as_mut_ptr(),
+                out_error.as_mut_ptr(),
+                out_n.as_mut_ptr(),
+            )
+        };
+        assert_eq!(status, IK_GEO_OK);
+        let out_q = out_q.chunks(6 * IK_GEO_MAX_SOLUTIONS);
+        let out_is_ls = out_is_ls.chunks(IK_GEO_MAX_SOLUTIONS);
+        for (((out_q, out_is_ls), &n), expected) in
+            out_q.zip(out_is_ls).zip(&out_n).zip(&expected)
+        {
+            assert_eq!(n as usize, expected.len().min(IK_GEO_MAX_SOLUTIONS));
+            for ((q, &is_ls), solution) in out_q.chunks(6).zip(out_is_ls).zip(expected.iter()) {
+                assert!((Vector6::from_column_slice(q) - solution.q).norm() < 1e-9);
+                assert_eq!(is_ls != 0, solution.is_ls);
+            }
+        }
+
+        // A number of poses whose arrays would not fit in memory is refused before reading them
+        let mut out_q = [0.0; 6 * IK_GEO_MAX_SOLUTIONS];
+        let mut out_is_ls = [0; IK_GEO_MAX_SOLUTIONS];
+        let mut out_error = [0.0; IK_GEO_MAX_SOLUTIONS];
+        let mut out_n = [0];
+        for n in [usize::MAX / 4 + 1, usize::MAX / (6 * IK_GEO_MAX_SOLUTIONS) + 1] {
+            let status = unsafe {
+                ik_geo_irb6640_batch(
+                    n,
+                    packed_q.as_ptr(),
+                    packed_t.as_ptr(),
+                    out_q.as_mut_ptr(),
+                    out_is_ls.as_mut_ptr(),
+                    out_error.as_mut_ptr(),
+                    out_n.as_mut_ptr(),
+                )
+            };
+            assert_eq!(status, IK_GEO_INVALID_ARGUMENT);
+        }
+    }
+}
+
+#[test]
+#[cfg(feature = "arrow")]
+fn test_arrow() {
+    use crate::{
+        arrow::{
+            pose_batch, pose_table, read_parquet, solution_table, solve_parquet, write_parquet,
+        },
+        pose::PoseBatch,
+    };
+    use arrow_array::{Array, Float64Array, UInt64Array};
+
+    let robot = irb6640();
+    let mut rng: Pcg64 = Seeder::from("arrow").into_rng();
+    let poses: Vec<_> = (0..20)
+        .map(|_| robot.fk(&rng.random::<[f64; 6]>().map(|x| x * 2.0 * PI - PI)))
+        .collect();
+    let packed_q: Vec<f64> = poses
+        .iter()
+        .flat_map(|(r, _)| {
+            let rotation = nalgebra::Rotation3::from_matrix_unchecked(*r);
+            let quaternion = nalgebra::UnitQuaternion::from_rotation_matrix(&rotation);
+            quaternion.coords.as_slice().to_vec()
+        })
+        .collect();
+    let packed_t: Vec<f64> = poses.iter().flat_map(|(_, t)| t.iter().copied()).collect();
+    let batch = PoseBatch::from_packed(
+        &packed_q,
+        &packed_t,
+        crate::pose::RotationFormat::QuaternionXyzw,
+    )
+    .unwrap();
+    let ids: Vec<u64> = (100..120).collect();
+
+    // A pose table reads back in place as the same poses
+    let table = pose_table(&batch, &ids).unwrap();
+    let read = pose_batch(&table).unwrap();
+    for (i, (r, t)) in poses.iter().enumerate() {
+        assert!((read.rotation(i) - r).norm() < 1e-12);
+        assert!((read.translation(i) - t).norm() < 1e-12);
+    }
+    assert!(pose_batch(&table.project(&[0, 1, 2]).unwrap()).is_err());
+
+    // The solutions of a Parquet pose table round trip through a Parquet solution table
+    let dir = std::env::temp_dir().join(format!("ik-geo-arrow-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    write_parquet(dir.join("poses.parquet"), &[table]).unwrap();
+    solve_parquet(&robot, dir.join("poses.parquet"), dir.join("solutions.parquet")).unwrap();
+    let solutions = read_parquet(dir.join("solutions.parquet")).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let expected = robot.solve_batch(&poses);
+    let expected = solution_table(&ids, &expected).unwrap();
+    assert_eq!(solutions.len(), 1);
+    let solutions = &solutions[0];
+    assert_eq!(solutions.schema().fields(), expected.schema().fields());
+    assert_eq!(solutions.num_rows(), expected.num_rows());
+    for name in ["pose_id", "branch", "is_ls"] {
+        assert_eq!(solutions.column_by_name(name), expected.column_by_name(name));
+    }
+    let floats = |table: &arrow_array::RecordBatch, name: &str| {
+        let column = table.column_by_name(name).unwrap();
+        column.as_any().downcast_ref::<Float64Array>().unwrap().values().to_vec()
+    };
+    for i in 0..6 {
+        let name = format!("q{i}");
+        let (q, q_expected) = (floats(solutions, &name), floats(&expected, &name));
+        assert!(q.iter().zip(&q_expected).all(|(a, b)| (a - b).abs() < 1e-9));
+    }
+    let pose_id = solutions.column_by_name("pose_id").unwrap();
+    let pose_id = pose_id.as_any().downcast_ref::<UInt64Array>().unwrap();
+    assert_eq!(pose_id.value(0), 100);
+    assert_eq!(pose_id.value(pose_id.len() - 1), 119);
+}
+
+#[cfg(feature = "hdf5")]
+#[test]
+fn test_hdf5() {
+    use crate::{
+        hdf5::{read_reachability, write_reachability, write_redundancy},
+        redundancy::RedundancyParameter,
+        workspace::WorkspaceGrid,
+    };
+
+    let robot = irb6640();
+    let (r, t) = robot.fk(&[0.3, 0.2, -0.1, 0.4, 0.9, 0.2]);
+    let grid = WorkspaceGrid::new(
+        t - Vector3::new(0.5, 0.5, 0.5),
+        t + Vector3::new(4.5, 1.5, 0.5),
+        [5, 2, 1],
+    );
+    let map = grid.map(&robot, &[r]);
+
+    let kin = KukaR800FixedQ3::get_kin();
+    let sweep = SevenDofSweep::kuka_r800(SweepStrategy::Grid(12));
+    let q = SVector::from([0.3, 0.5, -0.2, 1.1, 0.4, 0.3, 0.1]);
+    let (rot, translation) = kin.forward_kinematics(&q);
+    let parameter = RedundancyParameter::ArmAngle;
+    let cost = |solution: &IkSolution<7>| sweep.manipulability_cost(&solution.q);
+    let optimum = sweep.optimize_redundancy(rot, translation, parameter, cost);
+    assert!(optimum.is_some());
+    let optima = [optimum, None];
+
+    let path = std::env::temp_dir().join(format!("ik-geo-hdf5-{}.h5", std::process::id()));
+    let file = ::hdf5::File::create(&path).unwrap();
+    write_reachability(&file, "irb6640", &map).unwrap();
+    write_redundancy(&file, "kuka_r800", parameter, &optima).unwrap();
+    assert!(write_reachability(&file, "irb6640", &map).is_err());
+
+    // The map reads back unchanged, and the landscapes are shaped by pose and sample
+    assert_eq!(read_reachability(&file, "irb6640"), Ok(map));
+    let exact = file.dataset("irb6640/exact").unwrap();
+    assert_eq!(exact.shape(), vec![1, 2, 5]);
+    let cost = file.dataset("kuka_r800/cost").unwrap();
+    assert_eq!(cost.shape(), vec![2, 12]);
+    let cost = cost.read_raw::<f64>().unwrap();
+    assert!(cost[12..].iter().all(|c| c.is_infinite()));
+    let q = file.dataset("kuka_r800/optimum_q").unwrap().read_raw::<f64>().unwrap();
+    assert_eq!(&q[..7], optima[0].as_ref().unwrap().solution.q.as_slice());
+    assert!(q[7..].iter().all(|x| x.is_nan()));
+
+    drop(file);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_to_urdf() {
+    use crate::inverse_kinematics::auxiliary::JointType;
+    use nalgebra::{Translation3, UnitQuaternion};
+
+    let mut rng: Pcg64 = Seeder::from("urdf").into_rng();
+    let random_vector = |rng: &mut Pcg64| Vector3::from_fn(|_, _| rng.random_range(-1.0..1.0));
+    let random_isometry = |rng: &mut Pcg64| {
+        Isometry3::from_parts(
+            Translation3::from(random_vector(rng)),
+            UnitQuaternion::from_scaled_axis(random_vector(rng)),
+        )
+    };
+    let mut kin = Kinematics::<6, 7>::new()
+        .with_joint_types([JointType::Revolute, JointType::Prismatic, JointType::Revolute,
+            JointType::Revolute, JointType::Revolute, JointType::Revolute])
+        .with_base(random_isometry(&mut rng))
+        .with_tool(random_isometry(&mut rng));
+    for i in 0..7 {
+        if i < 6 {
+            kin.h.set_column(i, &random_vector(&mut rng).normalize());
+        }
+        kin.p.set_column(i, &random_vector(&mut rng));
+    }
+
+    let urdf = kin.to_urdf("general <6>");
+    assert!(urdf.contains("<robot name=\"general &lt;6&gt;\">"));
+    assert_eq!(urdf.matches("<link name=").count(), 10);
+    assert_eq!(urdf.matches("type=\"revolute\"").count(), 5);
+    assert_eq!(urdf.matches("type=\"prismatic\"").count(), 1);
+
+    // Composing the joints in the order they are written reaches the pose of the kinematics
+    let numbers = |element: &str, attribute: &str| -> Vector3<f64> {
+        let start = element.find(&format!("{attribute}=\"")).unwrap() + attribute.len() + 2;
+        let values = &element[start..start + element[start..].find('"').unwrap()];
+        Vector3::from_iterator(values.split(' ').map(|x| x.parse::<f64>().unwrap()))
+    };
+    let joints: Vec<&str> = urdf.split("<joint ").skip(1).collect();
+    assert_eq!(joints.len(), 9);
+    for _ in 0..10 {
+        let q = Vector6::from_fn(|_, _| rng.random_range(-PI..PI));
+        let mut pose = Isometry3::identity();
+        let mut i = 0;
+        for joint in &joints {
+            let xyz = numbers(joint, "xyz");
+            let rpy = numbers(joint, "rpy");
+            pose *= Isometry3::from_parts(
+                Translation3::from(xyz),
+                UnitQuaternion::from_euler_angles(rpy.x, rpy.y, rpy.z),
+            );
+            if !joint.contains("type=\"fixed\"") {
+                let axis = numbers(&joint[joint.find("<axis").unwrap()..], "xyz");
+                pose *= if joint.contains("type=\"prismatic\"") {
+                    Isometry3::translation(axis.x * q[i], axis.y * q[i], axis.z * q[i])
+                } else {
+                    Isometry3::rotation(axis * q[i])
+                };
+                i += 1;
+            }
+        }
+        let expected = Isometry3::from(Pose::from(kin.forward_kinematics(&q)));
+        assert!((pose.to_homogeneous() - expected.to_homogeneous()).norm() < 1e-9);
+    }
+}
+
+#[test]
+fn test_fk_probe() {
+    use crate::diagnostics::{fk_probe, PROBE_STEP};
+
+    // The first joint of an IRB 6640 turns the arm, reaching out along +x at zero, about +z
+    let probes = fk_probe(&Irb6640::get_kin());
+    assert_eq!(probes.len(), 6);
+    let base = probes[0];
+    assert!((base.rotation - Vector3::z() * PROBE_STEP).norm() < 1e-12);
+    assert!((base.direction().unwrap() - Vector3::y()).norm() < 1e-1);
+    assert!(base.to_string().contains("mostly +y"));
+    assert!(probes.iter().all(|probe| probe.rotation_axis().is_some()));
+
+    // Flipping an axis flips the directions of its probe
+    let mut flipped = Irb6640::get_kin();
+    flipped.h.set_column(1, &-flipped.h.column(1));
+    let flipped = fk_probe(&flipped);
+    assert!(flipped[1].direction().unwrap().dot(&probes[1].direction().unwrap()) < -0.99);
+    assert!((flipped[1].rotation + probes[1].rotation).norm() < 1e-12);
+
+    // A prismatic joint moves the tool without turning it
+    let mut kin = Kinematics::<3, 4>::new().with_joint_types([
+        crate::inverse_kinematics::auxiliary::JointType::Revolute,
+        crate::inverse_kinematics::auxiliary::JointType::Prismatic,
+        crate::inverse_kinematics::auxiliary::JointType::Revolute,
+    ]);
+    kin.h = Matrix3::from_columns(&[Vector3::z(), -Vector3::z(), Vector3::z()]);
+    kin.p.set_column(1, &Vector3::new(0.5, 0.0, 0.0));
+    let slide = fk_probe(&kin)[1];
+    assert!((slide.displacement + Vector3::z() * PROBE_STEP).norm() < 1e-12);
+    assert_eq!(slide.rotation_axis(), None);
+    assert!(slide.to_string().contains("does not turn"));
+}
@@ -1,61 +1,574 @@
 use {
-    crate::subproblems::auxiliary::rot, 
-    nalgebra::{ArrayStorage, Const, Matrix, Matrix3, Vector3, U1, U3, U7, U8}, nlopt::{Algorithm, Nlopt}, 
-    std::f64::consts::{PI, TAU},
+    crate::{config::SolverConfig, error::IkGeoError, subproblems::auxiliary::rot},
+    nalgebra::{convert, ArrayStorage, Const, DMatrix, DVector, Isometry3, Matrix, Matrix3, Matrix3x2, Matrix6xX, RealField, Rotation3, SVector, Translation3, UnitQuaternion, Vector3, Vector6, U1, U3, U6, U7, U8},
+    alloc::{vec, vec::Vec},
+    core::{cmp::Ordering, f64::consts::{PI, TAU}},
     
 };
 
+#[cfg(feature = "nlopt")]
+use nlopt::{Algorithm, Nlopt};
+
+use num_traits::Euclid;
+
+pub use super::search::{
+    search_1d, search_1d_with, search_2d_grid, search_2d_grid_with, SolverScratch,
+};
+
 pub type Matrix3x7<T> = Matrix<T, U3, U7, ArrayStorage<T, 3, 7>>;
 pub type Matrix3x8<T> = Matrix<T, U3, U8, ArrayStorage<T, 3, 8>>;
 
 pub type Vector<T, const N: usize> = Matrix<T, Const<N>, U1, ArrayStorage<f64, N, 1>>;
 
+/// How a joint moves the links after it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum JointType {
+    /// Rotates the links after it about its axis `h` by the joint angle
+    #[default]
+    Revolute,
+    /// Slides the links after it along its axis `h` by the joint value, a length
+    Prismatic,
+}
+
+/// The rotation and translation of the links after a joint of `joint_type` along or about `h`
+/// when its value is `q`
+pub(crate) fn joint_motion<T: RealField + Copy>(
+    h: &Vector3<T>,
+    joint_type: JointType,
+    q: T,
+) -> (Matrix3<T>, Vector3<T>) {
+    match joint_type {
+        JointType::Revolute => (rot(h, q), Vector3::zeros()),
+        JointType::Prismatic => (Matrix3::identity(), h * q),
+    }
+}
+
 #[derive(Debug, Clone)]
-pub struct Kinematics<const C1: usize, const C2: usize> {
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(
+        try_from = "crate::schema::KinematicsData",
+        into = "crate::schema::KinematicsData",
+        bound(
+            serialize = "Self: Clone + Into<crate::schema::KinematicsData>",
+            deserialize = "Self: TryFrom<crate::schema::KinematicsData>, \
+                <Self as TryFrom<crate::schema::KinematicsData>>::Error: core::fmt::Display"
+        )
+    )
+)]
+pub struct Kinematics<const C1: usize, const C2: usize, T = f64> {
     // TODO: somehow statically ensure that C2 - C1 = 1
-    pub h: Matrix<f64, U3, Const<C1>, ArrayStorage<f64, 3, C1>>,
-    pub p: Matrix<f64, U3, Const<C2>, ArrayStorage<f64, 3, C2>>,
+    pub h: Matrix<T, U3, Const<C1>, ArrayStorage<T, 3, C1>>,
+    pub p: Matrix<T, U3, Const<C2>, ArrayStorage<T, 3, C2>>,
+    /// Whether each joint turns about or slides along its column of `h`
+    pub joint_types: [JointType; C1],
+    /// Pose of the robot base in the user's world frame
+    pub base: Isometry3<T>,
+    /// Pose of the tool center point relative to the end of the chain
+    pub tool: Isometry3<T>,
 }
 
-impl<const C1: usize, const C2: usize> Kinematics<C1, C2> {
+/// Moves a pose of the end of the chain into the user's frames
+fn apply_frames<T: RealField + Copy>(
+    base: &Isometry3<T>,
+    tool: &Isometry3<T>,
+    r: &Matrix3<T>,
+    p: &Vector3<T>,
+) -> (Matrix3<T>, Vector3<T>) {
+    let r_base = base.rotation.to_rotation_matrix().into_inner();
+    let r_tool = tool.rotation.to_rotation_matrix().into_inner();
+
+    (
+        r_base * r * r_tool,
+        r_base * (p + r * tool.translation.vector) + base.translation.vector,
+    )
+}
+
+/// The mirror image of the pose `(r, p)` in the xz plane, with the frame mirrored as well so that
+/// it stays right-handed
+pub fn mirror_pose<T: RealField + Copy>(
+    r: &Matrix3<T>,
+    p: &Vector3<T>,
+) -> (Matrix3<T>, Vector3<T>) {
+    let mirror = mirror_matrix::<T>();
+    (mirror * r * mirror, mirror * p)
+}
+
+fn mirror_matrix<T: RealField + Copy>() -> Matrix3<T> {
+    Matrix3::from_diagonal(&Vector3::new(T::one(), -T::one(), T::one()))
+}
+
+fn mirror_isometry<T: RealField + Copy>(isometry: &Isometry3<T>) -> Isometry3<T> {
+    let (r, p) = mirror_pose(
+        isometry.rotation.to_rotation_matrix().matrix(),
+        &isometry.translation.vector,
+    );
+    Isometry3::from_parts(
+        Translation3::from(p),
+        UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix_unchecked(r)),
+    )
+}
+
+/// The inverse of `apply_frames`, giving the pose the end of the chain must reach
+fn remove_frames<T: RealField + Copy>(
+    base: &Isometry3<T>,
+    tool: &Isometry3<T>,
+    r: &Matrix3<T>,
+    p: &Vector3<T>,
+) -> (Matrix3<T>, Vector3<T>) {
+    let r_base = base.rotation.to_rotation_matrix().into_inner();
+    let r_tool = tool.rotation.to_rotation_matrix().into_inner();
+
+    let r_chain = r_base.transpose() * r * r_tool.transpose();
+    let p_chain =
+        r_base.transpose() * (p - base.translation.vector) - r_chain * tool.translation.vector;
+
+    (r_chain, p_chain)
+}
+
+impl<const C1: usize, const C2: usize, T: RealField + Copy> Kinematics<C1, C2, T> {
     pub fn new() -> Self {
         Self {
-            h: Matrix::<f64, U3, Const<C1>, ArrayStorage<f64, 3, C1>>::zeros(),
-            p: Matrix::<f64, U3, Const<C2>, ArrayStorage<f64, 3, C2>>::zeros(),
+            h: Matrix::<T, U3, Const<C1>, ArrayStorage<T, 3, C1>>::zeros(),
+            p: Matrix::<T, U3, Const<C2>, ArrayStorage<T, 3, C2>>::zeros(),
+            joint_types: [JointType::Revolute; C1],
+            base: Isometry3::identity(),
+            tool: Isometry3::identity(),
         }
     }
 
+    /// Make the joints turn or slide according to `joint_types`, instead of all being revolute
+    pub fn with_joint_types(mut self, joint_types: [JointType; C1]) -> Self {
+        self.joint_types = joint_types;
+        self
+    }
+
+    /// Whether every joint is revolute, as the 6R decompositions require
+    pub fn is_revolute(&self) -> bool {
+        self.joint_types
+            .iter()
+            .all(|&joint_type| joint_type == JointType::Revolute)
+    }
+
+    /// Report and accept poses of the tool center point `tool` instead of the end of the chain
+    pub fn with_tool(mut self, tool: Isometry3<T>) -> Self {
+        self.tool = tool;
+        self
+    }
+
+    /// Report and accept poses in a world frame in which the robot base is at `base`
+    pub fn with_base(mut self, base: Isometry3<T>) -> Self {
+        self.base = base;
+        self
+    }
+
+    /// The pose of the tool in the world frame, including the base and tool transforms
     pub fn forward_kinematics(
         &self,
-        theta: &Matrix<f64, Const<C1>, U1, ArrayStorage<f64, C1, 1>>,
-    ) -> (Matrix3<f64>, Vector3<f64>) {
-        let mut p: Vector3<f64> = self.p.column(0).into();
+        theta: &Matrix<T, Const<C1>, U1, ArrayStorage<T, C1, 1>>,
+    ) -> (Matrix3<T>, Vector3<T>) {
+        let (r, p) = self.chain_forward_kinematics(theta);
+        apply_frames(&self.base, &self.tool, &r, &p)
+    }
+
+    /// The pose of the end of the chain described by `h` and `p`, ignoring the base and tool
+    pub fn chain_forward_kinematics(
+        &self,
+        theta: &Matrix<T, Const<C1>, U1, ArrayStorage<T, C1, 1>>,
+    ) -> (Matrix3<T>, Vector3<T>) {
+        let mut p: Vector3<T> = self.p.column(0).into();
         let mut r = Matrix3::identity();
 
         for (i, &t) in theta.iter().enumerate() {
-            r = r * rot(&self.h.column(i).into(), t);
-            p = p + r * self.p.column(i + 1);
+            let (r_i, d_i) = joint_motion(&self.h.column(i).into(), self.joint_types[i], t);
+            p += r * d_i;
+            r *= r_i;
+            p += r * self.p.column(i + 1);
         }
 
         (r, p)
     }
+
+    /// The raw chain traversed from its end to its start, without the base and tool frames.
+    /// Joint `i` of the reversed chain is joint `C1 - 1 - i` of this one, and the reversed chain
+    /// reaches `(r^T, -r^T p)` wherever this one reaches `(r, p)`.
+    pub fn reversed(&self) -> Self {
+        let mut reversed = Self::new();
+        for i in 0..C1 {
+            reversed.h.set_column(i, &-self.h.column(C1 - 1 - i));
+            reversed.joint_types[i] = self.joint_types[C1 - 1 - i];
+        }
+        for i in 0..C2 {
+            reversed.p.set_column(i, &-self.p.column(C2 - 1 - i));
+        }
+        reversed
+    }
+
+    /// The mirror image of this robot in the xz plane of the world frame, with every revolute axis
+    /// turning the other way so that the mirror image reaches `mirror_pose(r, p)` wherever this one
+    /// reaches `(r, p)` at the same joint values. Prismatic axes are only mirrored.
+    pub fn mirrored(&self) -> Self {
+        let mirror = mirror_matrix::<T>();
+        let mut h = mirror * self.h;
+        for (i, &joint_type) in self.joint_types.iter().enumerate() {
+            if joint_type == JointType::Revolute {
+                h.set_column(i, &-h.column(i));
+            }
+        }
+
+        Self {
+            h,
+            p: mirror * self.p,
+            joint_types: self.joint_types,
+            base: mirror_isometry(&self.base),
+            tool: mirror_isometry(&self.tool),
+        }
+    }
+
+    /// Convert a tool pose in the world frame to the pose the raw chain described by `h` and
+    /// `p` has to reach, which is what the solvers work with
+    pub fn to_chain_frame(&self, r: &Matrix3<T>, t: &Vector3<T>) -> (Matrix3<T>, Vector3<T>) {
+        remove_frames(&self.base, &self.tool, r, t)
+    }
+
+    /// The position of each joint's origin, i.e. the point where `p` places it along the chain
+    pub fn joint_origins(
+        &self,
+        theta: &Matrix<T, Const<C1>, U1, ArrayStorage<T, C1, 1>>,
+    ) -> Matrix<T, U3, Const<C1>, ArrayStorage<T, 3, C1>> {
+        let mut origins = Matrix::<T, U3, Const<C1>, ArrayStorage<T, 3, C1>>::zeros();
+        let mut p: Vector3<T> = self.p.column(0).into();
+        let mut r = Matrix3::identity();
+
+        for (i, &t) in theta.iter().enumerate() {
+            origins.set_column(i, &p);
+            let (r_i, d_i) = joint_motion(&self.h.column(i).into(), self.joint_types[i], t);
+            p += r * d_i;
+            r *= r_i;
+            p += r * self.p.column(i + 1);
+        }
+
+        origins
+    }
+
+    /// The pose of every joint frame in the world frame, followed by the tool pose.
+    /// Frame `i` sits at the origin of joint `i`, moved along its axis if it is prismatic, and is
+    /// rotated by joints `0..=i`, so the last item is the same as `forward_kinematics`.
+    pub fn link_frames(
+        &self,
+        theta: &Matrix<T, Const<C1>, U1, ArrayStorage<T, C1, 1>>,
+    ) -> impl Iterator<Item = (Matrix3<T>, Vector3<T>)> + '_ {
+        let theta = *theta;
+        let r_base = self.base.rotation.to_rotation_matrix().into_inner();
+        let t_base = self.base.translation.vector;
+        let mut p: Vector3<T> = self.p.column(0).into();
+        let mut r = Matrix3::identity();
+
+        (0..=C1).map(move |i| {
+            if i == C1 {
+                return apply_frames(&self.base, &self.tool, &r, &p);
+            }
+
+            let (r_i, d_i) = joint_motion(&self.h.column(i).into(), self.joint_types[i], theta[i]);
+            p += r * d_i;
+            r *= r_i;
+            let frame = (r_base * r, r_base * p + t_base);
+            p += r * self.p.column(i + 1);
+            frame
+        })
+    }
+
+    /// The geometric Jacobian in the world frame, with the angular velocity in the first three
+    /// rows and the linear velocity of the tool center point in the last three. The column of a
+    /// prismatic joint only has a linear velocity, along its axis.
+    pub fn jacobian(
+        &self,
+        theta: &Matrix<T, Const<C1>, U1, ArrayStorage<T, C1, 1>>,
+    ) -> Matrix<T, U6, Const<C1>, ArrayStorage<T, 6, C1>> {
+        let (r_end, p_end) = self.chain_forward_kinematics(theta);
+        let p_tool = p_end + r_end * self.tool.translation.vector;
+        let r_base = self.base.rotation.to_rotation_matrix().into_inner();
+
+        let mut jacobian = Matrix::<T, U6, Const<C1>, ArrayStorage<T, 6, C1>>::zeros();
+        let mut p: Vector3<T> = self.p.column(0).into();
+        let mut r = Matrix3::identity();
+
+        for (i, &t) in theta.iter().enumerate() {
+            let h: Vector3<T> = self.h.column(i).into();
+            let axis = r * h;
+
+            match self.joint_types[i] {
+                JointType::Revolute => {
+                    jacobian
+                        .fixed_view_mut::<3, 1>(0, i)
+                        .copy_from(&(r_base * axis));
+                    jacobian
+                        .fixed_view_mut::<3, 1>(3, i)
+                        .copy_from(&(r_base * axis.cross(&(p_tool - p))));
+                }
+                JointType::Prismatic => {
+                    jacobian
+                        .fixed_view_mut::<3, 1>(3, i)
+                        .copy_from(&(r_base * axis));
+                }
+            }
+
+            let (r_i, d_i) = joint_motion(&h, self.joint_types[i], t);
+            p += r * d_i;
+            r *= r_i;
+            p += r * self.p.column(i + 1);
+        }
+
+        jacobian
+    }
+
+    /// The singular values of the Jacobian, largest first
+    fn singular_values(
+        &self,
+        theta: &Matrix<T, Const<C1>, U1, ArrayStorage<T, C1, 1>>,
+    ) -> DVector<T> {
+        let jacobian = self.jacobian(theta);
+        let mut singular_values =
+            DMatrix::from_column_slice(6, C1, jacobian.as_slice()).singular_values();
+        singular_values
+            .as_mut_slice()
+            .sort_unstable_by(|a, b| b.partial_cmp(a).unwrap_or(Ordering::Equal));
+        singular_values
+    }
+
+    /// Yoshikawa's manipulability measure, the product of the singular values of the Jacobian.
+    /// It is zero at a singularity.
+    pub fn manipulability(&self, theta: &Matrix<T, Const<C1>, U1, ArrayStorage<T, C1, 1>>) -> T {
+        self.singular_values(theta)
+            .iter()
+            .fold(T::one(), |product, &s| product * s)
+    }
+
+    /// The ratio of the largest to the smallest singular value of the Jacobian, which is infinite
+    /// at a singularity
+    pub fn condition_number(&self, theta: &Matrix<T, Const<C1>, U1, ArrayStorage<T, C1, 1>>) -> T {
+        let singular_values = self.singular_values(theta);
+        singular_values[0] / singular_values[singular_values.len() - 1]
+    }
+
+    /// Whether the smallest singular value of the Jacobian is below `tolerance`, i.e. some
+    /// direction of the tool can only be reached with joint velocities of at least `1 / tolerance`
+    pub fn is_near_singular(
+        &self,
+        theta: &Matrix<T, Const<C1>, U1, ArrayStorage<T, C1, 1>>,
+        tolerance: T,
+    ) -> bool {
+        let singular_values = self.singular_values(theta);
+        singular_values[singular_values.len() - 1] < tolerance
+    }
+
+    /// Joint velocities that move the tool with `twist`, given like the rows of `jacobian` as the
+    /// angular velocity followed by the linear velocity of the tool center point.
+    ///
+    /// This is the damped least squares solution, which limits the joint velocities near a
+    /// singularity at the cost of tracking the twist less closely. A `damping` of zero gives the
+    /// pseudoinverse solution.
+    pub fn solve_velocity(
+        &self,
+        theta: &Matrix<T, Const<C1>, U1, ArrayStorage<T, C1, 1>>,
+        twist: &Vector6<T>,
+        damping: T,
+    ) -> Matrix<T, Const<C1>, U1, ArrayStorage<T, C1, 1>> {
+        self.solve_velocity_with_nullspace(theta, twist, damping, &SVector::zeros())
+    }
+
+    /// Like `solve_velocity`, but also adds the part of the joint velocities `secondary` that
+    /// does not move the tool. For a redundant arm this pursues a secondary objective, e.g. the
+    /// gradient of a joint limit or manipulability cost, without disturbing the twist.
+    pub fn solve_velocity_with_nullspace(
+        &self,
+        theta: &Matrix<T, Const<C1>, U1, ArrayStorage<T, C1, 1>>,
+        twist: &Vector6<T>,
+        damping: T,
+        secondary: &Matrix<T, Const<C1>, U1, ArrayStorage<T, C1, 1>>,
+    ) -> Matrix<T, Const<C1>, U1, ArrayStorage<T, C1, 1>> {
+        let jacobian = self.jacobian(theta);
+        let svd = DMatrix::from_column_slice(6, C1, jacobian.as_slice()).svd(true, true);
+        let (u, v_t) = (svd.u.unwrap(), svd.v_t.unwrap());
+        let tolerance = svd.singular_values.max() * convert(1e-10);
+
+        let twist = DVector::from_column_slice(twist.as_slice());
+        let secondary = DVector::from_column_slice(secondary.as_slice());
+        let mut q_dot = secondary.clone();
+
+        for (i, &s) in svd.singular_values.iter().enumerate() {
+            if s <= tolerance {
+                continue;
+            }
+
+            // Remove the component of the secondary velocities along this row space direction
+            // and add the damped response to the twist instead
+            let v = v_t.row(i).transpose();
+            let response = u.column(i).dot(&twist) * s / (s * s + damping * damping);
+            q_dot += &v * (response - v.dot(&secondary));
+        }
+
+        SVector::from_column_slice(q_dot.as_slice())
+    }
+}
+
+impl<const C1: usize, const C2: usize> Kinematics<C1, C2> {
+    /// The same kinematics with another scalar type, e.g. `f32` or a dual number type
+    pub fn cast<T: RealField + Copy>(&self) -> Kinematics<C1, C2, T> {
+        Kinematics {
+            h: self.h.cast(),
+            p: self.p.cast(),
+            joint_types: self.joint_types,
+            base: self.base.cast(),
+            tool: self.tool.cast(),
+        }
+    }
+
+    /// Move the joint angles `theta` toward the pose `(r, t)` with up to `max_iterations`
+    /// Gauss-Newton steps on the Jacobian. Stops early once a step no longer reduces the twist
+    /// between the pose reached and the target, so the result is never worse than `theta`.
+    pub fn refine(
+        &self,
+        theta: &SVector<f64, C1>,
+        r: &Matrix3<f64>,
+        t: &Vector3<f64>,
+        max_iterations: usize,
+    ) -> SVector<f64, C1> {
+        let twist_to_target = |theta: &SVector<f64, C1>| {
+            let (r_q, t_q) = self.forward_kinematics(theta);
+            let rotation = Rotation3::from_matrix_unchecked(r * r_q.transpose());
+            let angular = UnitQuaternion::from_rotation_matrix(&rotation).scaled_axis();
+            Vector6::new(
+                angular.x,
+                angular.y,
+                angular.z,
+                t.x - t_q.x,
+                t.y - t_q.y,
+                t.z - t_q.z,
+            )
+        };
+
+        let mut theta = *theta;
+        let mut twist = twist_to_target(&theta);
+
+        for _ in 0..max_iterations {
+            let candidate = theta + self.solve_velocity(&theta, &twist, 0.0);
+            let candidate_twist = twist_to_target(&candidate);
+            if candidate_twist.norm() >= twist.norm() {
+                break;
+            }
+            theta = candidate;
+            twist = candidate_twist;
+        }
+
+        theta
+    }
+    /// The pairs `(i, j)` of revolute joints whose axes lie on the same line at `theta`, within
+    /// `tolerance`, with the sign of the dot product of their directions. Turning joint `i` by `s`
+    /// and joint `j` by `-sign * s` leaves the tool where it is, so such a configuration is one of
+    /// a continuum reaching the same pose, as at the wrist singularity of a spherical wrist.
+    pub fn coincident_axes(
+        &self,
+        theta: &SVector<f64, C1>,
+        tolerance: f64,
+    ) -> Vec<(usize, usize, f64)> {
+        self.iter_coincident_axes(theta, tolerance).collect()
+    }
+
+    /// `coincident_axes` without collecting them, so they can be found without allocating
+    pub fn iter_coincident_axes(
+        &self,
+        theta: &SVector<f64, C1>,
+        tolerance: f64,
+    ) -> impl Iterator<Item = (usize, usize, f64)> + '_ {
+        let mut frames = self.link_frames(theta);
+        let axes: [(Vector3<f64>, Vector3<f64>); C1] = core::array::from_fn(|i| {
+            let (r, p) = frames.next().unwrap();
+            (r * self.h.column(i), p)
+        });
+
+        (0..C1)
+            .flat_map(|i| (i + 1..C1).map(move |j| (i, j)))
+            .filter(|&(i, j)| {
+                self.joint_types[i] == JointType::Revolute
+                    && self.joint_types[j] == JointType::Revolute
+            })
+            .filter_map(move |(i, j)| {
+                let ((h_i, p_i), (h_j, p_j)) = (axes[i], axes[j]);
+                (h_i.cross(&h_j).norm() < tolerance && (p_j - p_i).cross(&h_i).norm() < tolerance)
+                    .then(|| (i, j, h_i.dot(&h_j).signum()))
+            })
+    }
+
+    /// The same chain with the offset between the axes of joints `i` and `i + 1` moved along the
+    /// two axes, so they intersect at the origin of joint `i + 1` as the decompositions with
+    /// intersecting axes require. The forward kinematics are unchanged.
+    ///
+    /// The offset is split along the axes by a least squares solve, which fails with
+    /// `IkGeoError::IllConditioned` when the axes are so close to parallel that its condition
+    /// number is above `config.max_condition`, and with `IkGeoError::SkewAxes` when the axes miss
+    /// each other by more than `config.ls_tolerance`.
+    pub fn intersect_axes(&self, i: usize, config: &SolverConfig) -> Result<Self, IkGeoError> {
+        if i + 1 >= C1 {
+            return Err(IkGeoError::InvalidJoint {
+                joint: i + 1,
+                num_joints: C1,
+            });
+        }
+
+        let axes = Matrix3x2::from_columns(&[self.h.column(i), self.h.column(i + 1)]);
+        let singular_values = axes.singular_values();
+        let condition = singular_values.max() / singular_values.min();
+        // A zero axis makes the condition number NaN
+        if condition.is_nan() || condition > config.max_condition {
+            return Err(IkGeoError::IllConditioned { condition });
+        }
+
+        let offset: Vector3<f64> = self.p.column(i + 1).into();
+        let alpha = axes
+            .pseudo_inverse(config.singular_tolerance)
+            .map_err(IkGeoError::PseudoInverse)?
+            * offset;
+        let distance = (offset - axes * alpha).norm();
+        if distance > config.ls_tolerance {
+            return Err(IkGeoError::SkewAxes { axis: i, distance });
+        }
+
+        let mut kin = self.clone();
+        kin.p
+            .set_column(i, &(self.p.column(i) + alpha[0] * self.h.column(i)));
+        kin.p.set_column(i + 1, &Vector3::zeros());
+        kin.p
+            .set_column(i + 2, &(self.p.column(i + 2) + alpha[1] * self.h.column(i + 1)));
+        Ok(kin)
+    }
 }
 
-impl Kinematics<7, 8> {
+impl<T: RealField + Copy> Kinematics<7, 8, T> {
+    /// Lock joint `n` at `q_n`. The partial kinematics describe the raw chain only, so targets
+    /// have to be converted with `to_chain_frame` before solving them.
     pub fn forward_kinematics_partial(
         &self,
-        q_n: f64,
+        q_n: T,
         n: usize,
-        r_6t: &Matrix3<f64>,
-    ) -> (Kinematics<6, 7>, Matrix3<f64>) {
-        let mut kin_new: Kinematics<6, 7> = Kinematics::new();
-        let r_n = rot(&self.h.column(n).into(), q_n);
+        r_6t: &Matrix3<T>,
+    ) -> (Kinematics<6, 7, T>, Matrix3<T>) {
+        let mut kin_new: Kinematics<6, 7, T> = Kinematics::new();
+        let (r_n, d_n) = joint_motion(&self.h.column(n).into(), self.joint_types[n], q_n);
 
         for i in 0..self.h.ncols() {
             if i > n {
                 kin_new.h.set_column(i - 1, &(r_n * self.h.column(i)));
-            } else {
+                kin_new.joint_types[i - 1] = self.joint_types[i];
+            } else if i < n {
                 kin_new.h.set_column(i, &self.h.column(i));
+                kin_new.joint_types[i] = self.joint_types[i];
             }
         }
 
@@ -63,7 +576,7 @@ impl Kinematics<7, 8> {
             if i == n {
                 kin_new
                     .p
-                    .set_column(i, &(self.p.column(i) + r_n * self.p.column(i + 1)));
+                    .set_column(i, &(self.p.column(i) + d_n + r_n * self.p.column(i + 1)));
             } else if i > n + 1 {
                 kin_new.p.set_column(i - 1, &(r_n * self.p.column(i)));
             } else {
@@ -75,95 +588,291 @@ impl Kinematics<7, 8> {
     }
 }
 
-pub fn wrap_to_pi(theta: f64) -> f64 {
-    (theta + PI).rem_euclid(TAU) - PI
+/// Recovers the full joint vector from a solution to the partial kinematics produced by
+/// `forward_kinematics_partial`, inserting the locked value `q_n` at index `n`
+pub fn expand_partial_solution(q: &Vector6<f64>, q_n: f64, n: usize) -> SVector<f64, 7> {
+    let mut q_full = SVector::<f64, 7>::zeros();
+
+    for i in 0..q_full.nrows() {
+        q_full[i] = match i.cmp(&n) {
+            Ordering::Less => q[i],
+            Ordering::Equal => q_n,
+            Ordering::Greater => q[i - 1],
+        };
+    }
+
+    q_full
 }
 
-pub fn search_1d<const N: usize, F: Fn(f64) -> Vector<f64, N> > (
-    f: F,
-    left: f64,
-    right: f64
-) -> Vec<(f64, usize)> {
+/// Kinematics with a joint count only known at runtime, e.g. when loaded from a file
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(
+        try_from = "crate::schema::KinematicsData",
+        into = "crate::schema::KinematicsData"
+    )
+)]
+pub struct KinematicsDyn {
+    h: DMatrix<f64>,
+    p: DMatrix<f64>,
+    joint_types: Vec<JointType>,
+    base: Isometry3<f64>,
+    tool: Isometry3<f64>,
+}
 
-    let mut results : Vec<(f64,usize)> = Vec::new();
-    // If on last iteration, we want all the results so we can return the best one
-    let mut last_results : Vec<(f64,usize,f64)> = Vec::new();
-    let max_evals = [50, 200, 1000, 2000];
-    let populations = [10, 30, 200, 400];
-    let epsilon = 1e-8;
+impl KinematicsDyn {
+    /// `h` must be 3 x N and `p` must be 3 x (N + 1)
+    pub fn new(h: DMatrix<f64>, p: DMatrix<f64>) -> Result<Self, IkGeoError> {
+        if h.nrows() != 3 || p.nrows() != 3 {
+            return Err(IkGeoError::DimensionMismatch {
+                expected: 3,
+                found: if h.nrows() != 3 { h.nrows() } else { p.nrows() },
+            });
+        }
 
-    // Do some iterative deepening
-    for (j, (max_eval, population)) in core::iter::zip(max_evals, populations).enumerate() { 
-        for i in 0..N {
-            // TODO: parallelize this, there are 4 branches that don't depend on each other
-            // Problems occur trying to multithread this due to the closure not being thread-safe.
+        if p.ncols() != h.ncols() + 1 {
+            return Err(IkGeoError::DimensionMismatch {
+                expected: h.ncols() + 1,
+                found: p.ncols(),
+            });
+        }
 
-            let cost_function = |x: &[f64], _gradient: Option<&mut [f64]>, _params: &mut ()| {
-                let err = f(x[0])[i];
-                err * err
-            };
+        Ok(Self {
+            joint_types: vec![JointType::Revolute; h.ncols()],
+            h,
+            p,
+            base: Isometry3::identity(),
+            tool: Isometry3::identity(),
+        })
+    }
 
-            
-            let mut optimizer = Nlopt::new(
-                Algorithm::GnMlslLds,
-                1,
-                cost_function,
-                nlopt::Target::Minimize, ());
+    /// See `Kinematics::with_joint_types`. There must be one type per joint.
+    pub fn with_joint_types(mut self, joint_types: &[JointType]) -> Result<Self, IkGeoError> {
+        if joint_types.len() != self.num_joints() {
+            return Err(IkGeoError::DimensionMismatch {
+                expected: self.num_joints(),
+                found: joint_types.len(),
+            });
+        }
 
+        self.joint_types = joint_types.to_vec();
+        Ok(self)
+    }
 
-            let mut x = [(left + right) / 2.0];
-            optimizer.set_upper_bounds(&[right]).unwrap();
-            optimizer.set_lower_bounds(&[left]).unwrap();
-            optimizer.set_xtol_abs(&[epsilon]).unwrap();
-            optimizer.set_stopval(epsilon).unwrap();
-            optimizer.set_maxeval(max_eval).unwrap();
-            optimizer.set_population(population).unwrap();
+    /// See `Kinematics::with_tool`
+    pub fn with_tool(mut self, tool: Isometry3<f64>) -> Self {
+        self.tool = tool;
+        self
+    }
 
-            let mut local_optimizer = Nlopt::new(
-                Algorithm::Neldermead,
-                1,
-                cost_function,
-                nlopt::Target::Minimize, ());
-            local_optimizer.set_maxeval(max_eval / 2).unwrap();
-            optimizer.set_local_optimizer(local_optimizer).unwrap();
-            
-            let result = optimizer.optimize(&mut x);
-            // Check for error
-            if let Err(e) = result {
-                // Didn't optimize, no zero on this branch
-                println!("Error: {:?}", e);
-                continue;
+    /// See `Kinematics::with_base`
+    pub fn with_base(mut self, base: Isometry3<f64>) -> Self {
+        self.base = base;
+        self
+    }
+
+    pub fn h(&self) -> &DMatrix<f64> {
+        &self.h
+    }
+
+    pub fn p(&self) -> &DMatrix<f64> {
+        &self.p
+    }
+
+    pub fn joint_types(&self) -> &[JointType] {
+        &self.joint_types
+    }
+
+    pub fn base(&self) -> &Isometry3<f64> {
+        &self.base
+    }
+
+    pub fn tool(&self) -> &Isometry3<f64> {
+        &self.tool
+    }
+
+    /// See `Kinematics::to_chain_frame`
+    pub fn to_chain_frame(
+        &self,
+        r: &Matrix3<f64>,
+        t: &Vector3<f64>,
+    ) -> (Matrix3<f64>, Vector3<f64>) {
+        remove_frames(&self.base, &self.tool, r, t)
+    }
+
+    pub fn num_joints(&self) -> usize {
+        self.h.ncols()
+    }
+
+    fn check_joints(&self, theta: &DVector<f64>) -> Result<(), IkGeoError> {
+        if theta.len() != self.num_joints() {
+            return Err(IkGeoError::DimensionMismatch {
+                expected: self.num_joints(),
+                found: theta.len(),
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn forward_kinematics(
+        &self,
+        theta: &DVector<f64>,
+    ) -> Result<(Matrix3<f64>, Vector3<f64>), IkGeoError> {
+        self.check_joints(theta)?;
+
+        let mut p: Vector3<f64> = self.p.fixed_view::<3, 1>(0, 0).into();
+        let mut r = Matrix3::identity();
+
+        for (i, &t) in theta.iter().enumerate() {
+            let (r_i, d_i) =
+                joint_motion(&self.h.fixed_view::<3, 1>(0, i).into(), self.joint_types[i], t);
+            p += r * d_i;
+            r *= r_i;
+            p += r * self.p.fixed_view::<3, 1>(0, i + 1);
+        }
+
+        Ok(apply_frames(&self.base, &self.tool, &r, &p))
+    }
+
+    /// Lock joint `n` at `q_n`, see `Kinematics::forward_kinematics_partial`
+    pub fn forward_kinematics_partial(
+        &self,
+        q_n: f64,
+        n: usize,
+        r_6t: &Matrix3<f64>,
+    ) -> Result<(KinematicsDyn, Matrix3<f64>), IkGeoError> {
+        if n >= self.num_joints() {
+            return Err(IkGeoError::InvalidJoint {
+                joint: n,
+                num_joints: self.num_joints(),
+            });
+        }
+
+        let (r_n, d_n) =
+            joint_motion(&self.h.fixed_view::<3, 1>(0, n).into(), self.joint_types[n], q_n);
+        let mut h = DMatrix::zeros(3, self.num_joints() - 1);
+        let mut p = DMatrix::zeros(3, self.num_joints());
+        let mut joint_types = self.joint_types.clone();
+        joint_types.remove(n);
+
+        for i in 0..self.h.ncols() {
+            if i > n {
+                h.set_column(i - 1, &(r_n * self.h.fixed_view::<3, 1>(0, i)));
+            } else if i < n {
+                h.set_column(i, &self.h.column(i));
             }
-            // Get the error
-            let error = result.unwrap().1;
+        }
 
-            // Only push close solutions, but all solutions if on last iteration
-            if error < epsilon {
-                results.push((x[0], i));
-            } else if j == max_evals.len() - 1 {
-                last_results.push((x[0], i, error))
+        for i in 0..self.p.ncols() {
+            if i == n {
+                p.set_column(
+                    i,
+                    &(self.p.fixed_view::<3, 1>(0, i)
+                        + d_n
+                        + r_n * self.p.fixed_view::<3, 1>(0, i + 1)),
+                );
+            } else if i > n + 1 {
+                p.set_column(i - 1, &(r_n * self.p.fixed_view::<3, 1>(0, i)));
+            } else if i < n {
+                p.set_column(i, &self.p.column(i));
             }
-            
         }
-        if results.len() > 0 {
-            break;
+
+        Ok((
+            KinematicsDyn::new(h, p)?.with_joint_types(&joint_types)?,
+            r_n * r_6t,
+        ))
+    }
+
+    /// See `Kinematics::jacobian`
+    pub fn jacobian(&self, theta: &DVector<f64>) -> Result<Matrix6xX<f64>, IkGeoError> {
+        self.check_joints(theta)?;
+
+        let mut axes = Vec::with_capacity(self.num_joints());
+        let mut p: Vector3<f64> = self.p.fixed_view::<3, 1>(0, 0).into();
+        let mut r = Matrix3::identity();
+
+        for (i, &t) in theta.iter().enumerate() {
+            let h: Vector3<f64> = self.h.fixed_view::<3, 1>(0, i).into();
+            axes.push((r * h, p));
+            let (r_i, d_i) = joint_motion(&h, self.joint_types[i], t);
+            p += r * d_i;
+            r *= r_i;
+            p += r * self.p.fixed_view::<3, 1>(0, i + 1);
         }
-        // If on last iteration, just use the lowest error solution
-        if j == max_evals.len() - 1 {
-            let best_result =  last_results.iter()
-                .min_by(|x, y| x.2.partial_cmp(&y.2).unwrap()).unwrap();
-            if best_result.2.is_finite() {
-                results.push((best_result.0, best_result.1));
+
+        let r_base = self.base.rotation.to_rotation_matrix().into_inner();
+        let p_tool = p + r * self.tool.translation.vector;
+
+        let mut jacobian = Matrix6xX::zeros(self.num_joints());
+        for (i, (axis, origin)) in axes.into_iter().enumerate() {
+            if self.joint_types[i] == JointType::Prismatic {
+                jacobian
+                    .fixed_view_mut::<3, 1>(3, i)
+                    .copy_from(&(r_base * axis));
+                continue;
             }
+
+            jacobian
+                .fixed_view_mut::<3, 1>(0, i)
+                .copy_from(&(r_base * axis));
+            jacobian
+                .fixed_view_mut::<3, 1>(3, i)
+                .copy_from(&(r_base * axis.cross(&(p_tool - origin))));
         }
+
+        Ok(jacobian)
     }
-    results
+}
+
+impl<const C1: usize, const C2: usize> From<&Kinematics<C1, C2>> for KinematicsDyn {
+    fn from(kin: &Kinematics<C1, C2>) -> Self {
+        KinematicsDyn {
+            h: DMatrix::from_column_slice(3, C1, kin.h.as_slice()),
+            p: DMatrix::from_column_slice(3, C2, kin.p.as_slice()),
+            joint_types: kin.joint_types.to_vec(),
+            base: kin.base,
+            tool: kin.tool,
+        }
+    }
+}
+
+impl<const C1: usize, const C2: usize> TryFrom<&KinematicsDyn> for Kinematics<C1, C2> {
+    type Error = IkGeoError;
 
+    fn try_from(kin: &KinematicsDyn) -> Result<Self, Self::Error> {
+        if kin.h.ncols() != C1 {
+            return Err(IkGeoError::DimensionMismatch {
+                expected: C1,
+                found: kin.h.ncols(),
+            });
+        }
+        if kin.p.ncols() != C2 {
+            return Err(IkGeoError::DimensionMismatch {
+                expected: C2,
+                found: kin.p.ncols(),
+            });
+        }
+
+        let mut kin_static = Kinematics::<C1, C2>::new();
+        kin_static.h.copy_from_slice(kin.h.as_slice());
+        kin_static.p.copy_from_slice(kin.p.as_slice());
+        kin_static.joint_types.copy_from_slice(&kin.joint_types);
+        kin_static.base = kin.base;
+        kin_static.tool = kin.tool;
+
+        Ok(kin_static)
+    }
 }
 
+pub fn wrap_to_pi(theta: f64) -> f64 {
+    Euclid::rem_euclid(&(theta + PI), &TAU) - PI
+}
 
-// Search 1d and 2d are very simliar, maybe consider collapsing
-// However, they could become very different down the line.
+#[cfg(feature = "nlopt")]
 pub fn search_2d<const N: usize, F: Fn(f64, f64) -> Vector<f64, N>>(
     f: F,
     min: (f64, f64),
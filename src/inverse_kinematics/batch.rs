@@ -0,0 +1,149 @@
+//! The spherical wrist solver with two parallel axes in `f32`, solving eight poses at once with
+//! the SIMD subproblems of `subproblems::batch`.
+//!
+//! Every pose gets all eight branches, in the order of the two choices of `q1`, then of `q3`,
+//! then of `q5`, each with a least squares flag. Branches where a subproblem has no exact
+//! solution repeat the least squares solution. Unlike the `f64` solvers there is no residual,
+//! deduplication or labeling, which is left to the caller as far as it needs them.
+
+use {
+    super::auxiliary::Kinematics,
+    crate::subproblems::batch::{
+        f32x8, mask_to_bools, subproblem1_x8, subproblem3_x8, subproblem4_x8, Vector3x8,
+    },
+    alloc::vec::Vec,
+    nalgebra::{Matrix3, Vector3, Vector6},
+};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// The eight branches of one pose with their least squares flags
+pub type BranchesF32 = [(Vector6<f32>, bool); 8];
+
+/// Axis `i` of `kin` in every lane
+fn axis(kin: &Kinematics<6, 7, f32>, i: usize) -> Vector3x8 {
+    Vector3x8::splat(&kin.h.column(i).into())
+}
+
+/// Offset `i` of `kin` in every lane
+fn offset(kin: &Kinematics<6, 7, f32>, i: usize) -> Vector3x8 {
+    Vector3x8::splat(&kin.p.column(i).into())
+}
+
+/// Solve eight poses, given in the world frame, of a robot with a spherical wrist and parallel
+/// second and third axes, i.e. one `Robot::spherical_two_parallel` solves
+pub fn spherical_two_parallel_x8(
+    poses: &[(Matrix3<f32>, Vector3<f32>); 8],
+    kin: &Kinematics<6, 7, f32>,
+    ls_tolerance: f32,
+) -> [BranchesF32; 8] {
+    let chain = poses.map(|(r, t)| kin.to_chain_frame(&r, &t));
+    let r_columns: [Vector3x8; 3] =
+        core::array::from_fn(|j| Vector3x8::from_vectors(&chain.map(|(r, _)| r.column(j).into())));
+    let p_0t = Vector3x8::from_vectors(&chain.map(|(_, t)| t));
+
+    // The transpose of the rotation of each lane applied to `v`
+    let r_transpose_mul = |v: &Vector3x8| {
+        Vector3x8::new(
+            r_columns[0].dot(v),
+            r_columns[1].dot(v),
+            r_columns[2].dot(v),
+        )
+    };
+    let r_mul = |v: &Vector3<f32>| {
+        r_columns[0]
+            .scale(f32x8::splat(v.x))
+            .add(&r_columns[1].scale(f32x8::splat(v.y)))
+            .add(&r_columns[2].scale(f32x8::splat(v.z)))
+    };
+
+    let h: [Vector3x8; 6] = core::array::from_fn(|i| axis(kin, i));
+    let p: [Vector3x8; 7] = core::array::from_fn(|i| offset(kin, i));
+    let p_6: Vector3<f32> = kin.p.column(6).into();
+    let h_6: Vector3<f32> = kin.h.column(5).into();
+
+    let v = p_0t.sub(&r_mul(&p_6)).sub(&p[0]);
+    let r_h6 = r_mul(&h_6);
+
+    let mut branches = [[(Vector6::zeros(), false); 8]; 8];
+    let mut branch = 0;
+
+    let d1 = f32x8::splat(
+        kin.h
+            .column(1)
+            .dot(&(kin.p.column(1) + kin.p.column(2) + kin.p.column(3))),
+    );
+    let (t1, q1_is_ls) = subproblem4_x8(&h[1], &v, &h[0].neg(), d1);
+
+    for q1 in t1 {
+        let w = v.neg().rotated(&h[0].neg(), q1).add(&p[1]);
+        let (t3, q3_is_ls) = subproblem3_x8(&p[3].neg(), &p[2], &h[2], w.norm());
+
+        for q3 in t3 {
+            let (q2, q2_is_ls) = subproblem1_x8(
+                &p[2].neg().sub(&p[3].rotated(&h[2], q3)),
+                &w,
+                &h[1],
+                ls_tolerance,
+            );
+
+            let r_36_h6 = r_h6
+                .rotated(&h[0].neg(), q1)
+                .rotated(&h[1].neg(), q2)
+                .rotated(&h[2].neg(), q3);
+            let r_36_t_h4 = r_transpose_mul(
+                &h[3]
+                    .rotated(&h[2], q3)
+                    .rotated(&h[1], q2)
+                    .rotated(&h[0], q1),
+            );
+
+            let (t5, q5_is_ls) = subproblem4_x8(&h[3], &h[5], &h[4], h[3].dot(&r_36_h6));
+
+            for q5 in t5 {
+                let (q4, q4_is_ls) =
+                    subproblem1_x8(&h[5].rotated(&h[4], q5), &r_36_h6, &h[3], ls_tolerance);
+                let (q6, q6_is_ls) = subproblem1_x8(
+                    &h[3].rotated(&h[4].neg(), q5),
+                    &r_36_t_h4,
+                    &h[5].neg(),
+                    ls_tolerance,
+                );
+
+                let is_ls =
+                    mask_to_bools(q1_is_ls | q2_is_ls | q3_is_ls | q4_is_ls | q5_is_ls | q6_is_ls);
+                let q = [q1, q2, q3, q4, q5, q6].map(|q| q.to_array());
+                for (lane, solutions) in branches.iter_mut().enumerate() {
+                    solutions[branch] = (Vector6::from_fn(|j, _| q[j][lane]), is_ls[lane]);
+                }
+                branch += 1;
+            }
+        }
+    }
+
+    branches
+}
+
+/// `spherical_two_parallel_x8` on any number of poses, eight at a time. With the `parallel`
+/// feature the groups of eight are distributed across the rayon thread pool.
+pub fn spherical_two_parallel_batch(
+    poses: &[(Matrix3<f32>, Vector3<f32>)],
+    kin: &Kinematics<6, 7, f32>,
+    ls_tolerance: f32,
+) -> Vec<BranchesF32> {
+    #[cfg(feature = "parallel")]
+    let chunks = poses.par_chunks(8);
+    #[cfg(not(feature = "parallel"))]
+    let chunks = poses.chunks(8);
+
+    let solved: Vec<[BranchesF32; 8]> = chunks
+        .map(|chunk| {
+            // Fill the lanes past the end with the last pose
+            let group = core::array::from_fn(|i| chunk[i.min(chunk.len() - 1)]);
+            spherical_two_parallel_x8(&group, kin, ls_tolerance)
+        })
+        .collect();
+
+    solved.into_iter().flatten().take(poses.len()).collect()
+}
@@ -0,0 +1,304 @@
+//! Solvers for chains of two to four joints.
+//!
+//! These are built from the same subproblems as the six joint solvers and are meant for
+//! mechanisms that are not full arms, or for sub-chains such as the shoulder or the wrist of an arm
+//! whose remaining joints are solved separately. Like the six joint solvers they work in the frame
+//! of the chain, ignoring the base and tool frames of the kinematics.
+//!
+//! `scara` and `palletizer` solve the common 4-DOF arms, which only position the tool and turn it
+//! about a vertical axis.
+
+use {
+    super::auxiliary::{JointType, Kinematics},
+    crate::{
+        config::SolverConfig,
+        solutionset::IkSolutionSet,
+        subproblems::{
+            auxiliary::rot, prismatic_subproblem4, subproblem1_with_tolerance, subproblem3,
+            subproblem4,
+        },
+    },
+    alloc::vec::Vec,
+    nalgebra::{Matrix3, Matrix6x4, SVector, Vector2, Vector3, Vector4},
+};
+
+/// The angles `(q1, q2)` of two parallel joints with `rot(h1, q1) * (p_12 + rot(h2, q2) * p_23) =
+/// p_1t`, and whether they are a least squares solution
+fn parallel_pair(
+    p_1t: &Vector3<f64>,
+    h1: &Vector3<f64>,
+    h2: &Vector3<f64>,
+    p_12: &Vector3<f64>,
+    p_23: &Vector3<f64>,
+    config: &SolverConfig,
+) -> Vec<(f64, f64, bool)> {
+    let (t2, q2_is_ls) = subproblem3(p_23, &-p_12, h2, p_1t.norm());
+
+    t2.get_all()
+        .map(|q2| {
+            let (q1, q1_is_ls) = subproblem1_with_tolerance(
+                &(p_12 + rot(h2, q2) * p_23),
+                p_1t,
+                h1,
+                config.ls_tolerance,
+            );
+            (q1, q2, q1_is_ls || q2_is_ls)
+        })
+        .collect()
+}
+
+/// Any unit vector not parallel to `h`
+fn transverse(h: &Vector3<f64>) -> Vector3<f64> {
+    if h.x.abs() < 0.9 {
+        Vector3::x()
+    } else {
+        Vector3::y()
+    }
+}
+
+/// The position `p_0t` of the end of a chain of two parallel joints. There are up to two
+/// solutions, the elbow up and elbow down configurations. The residual of each solution is its
+/// distance from `p_0t`.
+pub fn planar_2r(
+    p_0t: &Vector3<f64>,
+    kin: &Kinematics<2, 3>,
+    config: &SolverConfig,
+) -> IkSolutionSet<2> {
+    let mut solutions = IkSolutionSet::new();
+
+    for (q1, q2, is_ls) in parallel_pair(
+        &(p_0t - kin.p.column(0)),
+        &kin.h.column(0).into(),
+        &kin.h.column(1).into(),
+        &kin.p.column(1).into(),
+        &kin.p.column(2).into(),
+        config,
+    ) {
+        solutions.push(Vector2::new(q1, q2), is_ls);
+    }
+
+    solutions.set_errors(|q| (kin.chain_forward_kinematics(q).1 - p_0t).norm());
+    solutions.dedup(config.dedup_tolerance);
+    if config.annotate {
+        solutions.set_manipulability(|q| kin.manipulability(q));
+    }
+    solutions
+}
+
+/// The pose `(r_0t, p_0t)` of the end of a chain of three parallel joints. Only the rotation of
+/// `r_0t` about the joint axes can be reached, so a least squares solution is reported for any
+/// other orientation.
+pub fn planar_3r(
+    r_0t: &Matrix3<f64>,
+    p_0t: &Vector3<f64>,
+    kin: &Kinematics<3, 4>,
+    config: &SolverConfig,
+) -> IkSolutionSet<3> {
+    let mut solutions = IkSolutionSet::new();
+
+    let h3: Vector3<f64> = kin.h.column(2).into();
+    let v = transverse(&h3);
+
+    for (q1, q2, is_ls) in parallel_pair(
+        &(p_0t - kin.p.column(0) - r_0t * kin.p.column(3)),
+        &kin.h.column(0).into(),
+        &kin.h.column(1).into(),
+        &kin.p.column(1).into(),
+        &kin.p.column(2).into(),
+        config,
+    ) {
+        let r_23 = rot(&-kin.h.column(1), q2) * rot(&-kin.h.column(0), q1) * r_0t;
+        let (q3, q3_is_ls) = subproblem1_with_tolerance(&v, &(r_23 * v), &h3, config.ls_tolerance);
+
+        solutions.push(Vector3::new(q1, q2, q3), is_ls || q3_is_ls);
+    }
+
+    solutions.set_errors(|q| {
+        let (r_q, t_q) = kin.chain_forward_kinematics(q);
+        config.pose_error(&r_q, &t_q, r_0t, p_0t)
+    });
+    solutions.dedup(config.dedup_tolerance);
+    if config.annotate {
+        solutions.set_manipulability(|q| kin.manipulability(q));
+    }
+    solutions
+}
+
+/// The orientation `r_0t` of the end of a chain of three joints whose axes intersect, e.g. a
+/// spherical wrist. There are up to two solutions, which differ in the sign of the middle joint.
+/// The residual of each solution only measures the orientation.
+pub fn spherical_wrist_3r(
+    r_0t: &Matrix3<f64>,
+    kin: &Kinematics<3, 4>,
+    config: &SolverConfig,
+) -> IkSolutionSet<3> {
+    let mut solutions = IkSolutionSet::new();
+
+    let (t2, q2_is_ls) = subproblem4(
+        &kin.h.column(0).into(),
+        &kin.h.column(2).into(),
+        &kin.h.column(1).into(),
+        (kin.h.column(0).transpose() * r_0t * kin.h.column(2))[0],
+    );
+
+    for q2 in t2.get_all() {
+        let (q1, q1_is_ls) = subproblem1_with_tolerance(
+            &(rot(&kin.h.column(1).into(), q2) * kin.h.column(2)),
+            &(r_0t * kin.h.column(2)),
+            &kin.h.column(0).into(),
+            config.ls_tolerance,
+        );
+
+        let (q3, q3_is_ls) = subproblem1_with_tolerance(
+            &(rot(&-kin.h.column(1), q2) * kin.h.column(0)),
+            &(r_0t.transpose() * kin.h.column(0)),
+            &-kin.h.column(2),
+            config.ls_tolerance,
+        );
+
+        solutions.push(Vector3::new(q1, q2, q3), q1_is_ls || q2_is_ls || q3_is_ls);
+    }
+
+    solutions.set_errors(|q| {
+        let origin = Vector3::zeros();
+        config.pose_error(&kin.chain_forward_kinematics(q).0, &origin, r_0t, &origin)
+    });
+    solutions.dedup(config.dedup_tolerance);
+    if config.annotate {
+        solutions.set_manipulability(|q| kin.manipulability(q));
+    }
+    solutions
+}
+
+/// The pose `(r_0t, p_0t)` of the end of a SCARA arm: two revolute joints, a prismatic joint and a
+/// revolute joint, all with parallel axes, as set by `JointType::Prismatic` on the third joint.
+/// There are up to two solutions, the left and right arm configurations. Only the rotation of
+/// `r_0t` about the joint axes can be reached, so a least squares solution is reported for any
+/// other orientation.
+pub fn scara(
+    r_0t: &Matrix3<f64>,
+    p_0t: &Vector3<f64>,
+    kin: &Kinematics<4, 5>,
+    config: &SolverConfig,
+) -> IkSolutionSet<4> {
+    debug_assert_eq!(
+        kin.joint_types,
+        [
+            JointType::Revolute,
+            JointType::Revolute,
+            JointType::Prismatic,
+            JointType::Revolute
+        ]
+    );
+    let mut solutions = IkSolutionSet::new();
+
+    let h1: Vector3<f64> = kin.h.column(0).into();
+    let h4: Vector3<f64> = kin.h.column(3).into();
+    let p_03: Vector3<f64> = kin.p.column(1) + kin.p.column(2) + kin.p.column(3);
+    let p_1w = p_0t - r_0t * kin.p.column(4) - kin.p.column(0);
+
+    // Rotations about the parallel axes leave the height along them unchanged
+    let (d3, d3_is_ls) = prismatic_subproblem4(&h1, &p_03, &kin.h.column(2).into(), h1.dot(&p_1w));
+    let v = transverse(&h4);
+
+    for (q1, q2, is_ls) in parallel_pair(
+        &p_1w,
+        &h1,
+        &kin.h.column(1).into(),
+        &kin.p.column(1).into(),
+        &(kin.p.column(2) + kin.h.column(2) * d3 + kin.p.column(3)),
+        config,
+    ) {
+        let r_24 = rot(&-kin.h.column(1), q2) * rot(&-h1, q1) * r_0t;
+        let (q4, q4_is_ls) = subproblem1_with_tolerance(&v, &(r_24 * v), &h4, config.ls_tolerance);
+
+        solutions.push(Vector4::new(q1, q2, d3, q4), is_ls || d3_is_ls || q4_is_ls);
+    }
+
+    solutions.set_errors(|q| {
+        let (r_q, t_q) = kin.chain_forward_kinematics(q);
+        config.pose_error(&r_q, &t_q, r_0t, p_0t)
+    });
+    solutions.dedup(config.dedup_tolerance);
+    if config.annotate {
+        solutions.set_manipulability(|q| kin.manipulability(q));
+    }
+    solutions
+}
+
+/// The joint values of the serial chain a palletizer is modeled as, for its active joints `q`.
+/// The fourth joint is the passive joint of the parallelogram, which turns by `-(q2 + q3)` to keep
+/// the flange level.
+pub fn palletizer_joints(q: &Vector4<f64>) -> SVector<f64, 5> {
+    SVector::<f64, 5>::new(q[0], q[1], q[2], -(q[1] + q[2]), q[3])
+}
+
+/// The pose `(r_0t, p_0t)` of the end of a palletizer whose parallelogram keeps the flange level:
+/// a base joint, two parallel joints moving the arm in a vertical plane and a joint turning the
+/// flange about an axis parallel to the base joint.
+///
+/// `kin` is the serial equivalent of the arm, with the passive joint of the parallelogram as its
+/// fourth joint, parallel to the second and third, see `palletizer_joints`. The solutions hold
+/// the four active joints. There are up to four, reaching over the front or the back with the
+/// elbow up or down. Only the rotation of `r_0t` about the base axis can be reached, so a least
+/// squares solution is reported for any other orientation.
+pub fn palletizer(
+    r_0t: &Matrix3<f64>,
+    p_0t: &Vector3<f64>,
+    kin: &Kinematics<5, 6>,
+    config: &SolverConfig,
+) -> IkSolutionSet<4> {
+    let mut solutions = IkSolutionSet::new();
+
+    let h1: Vector3<f64> = kin.h.column(0).into();
+    let h2: Vector3<f64> = kin.h.column(1).into();
+    let h5: Vector3<f64> = kin.h.column(4).into();
+    let p_1w: Vector3<f64> = p_0t - r_0t * kin.p.column(5) - kin.p.column(0);
+    let p_14: Vector3<f64> = kin.p.column(1) + kin.p.column(4);
+
+    // The distance of the flange from the plane of the arm does not depend on the parallel joints
+    let (t1, q1_is_ls) = subproblem4(
+        &h2,
+        &p_1w,
+        &-h1,
+        h2.dot(&(p_14 + kin.p.column(2) + kin.p.column(3))),
+    );
+    let v = transverse(&h5);
+
+    for q1 in t1.get_all() {
+        for (q2, q3, is_ls) in parallel_pair(
+            &(rot(&-h1, q1) * p_1w - p_14),
+            &h2,
+            &kin.h.column(2).into(),
+            &kin.p.column(2).into(),
+            &kin.p.column(3).into(),
+            config,
+        ) {
+            let r_15 = rot(&-h1, q1) * r_0t;
+            let (q5, q5_is_ls) =
+                subproblem1_with_tolerance(&v, &(r_15 * v), &h5, config.ls_tolerance);
+
+            solutions.push(Vector4::new(q1, q2, q3, q5), is_ls || q1_is_ls || q5_is_ls);
+        }
+    }
+
+    solutions.set_errors(|q| {
+        let (r_q, t_q) = kin.chain_forward_kinematics(&palletizer_joints(q));
+        config.pose_error(&r_q, &t_q, r_0t, p_0t)
+    });
+    solutions.dedup(config.dedup_tolerance);
+    if config.annotate {
+        solutions.set_manipulability(|q| {
+            // The passive joint moves with the parallel joints, so its column joins theirs
+            let jacobian = kin.jacobian(&palletizer_joints(q));
+            let active = Matrix6x4::from_columns(&[
+                jacobian.column(0).into(),
+                jacobian.column(1) - jacobian.column(3),
+                jacobian.column(2) - jacobian.column(3),
+                jacobian.column(4).into(),
+            ]);
+            active.singular_values().iter().product()
+        });
+    }
+    solutions
+}
@@ -0,0 +1,246 @@
+//! Detecting which decomposition a 6R chain can be solved with.
+//!
+//! The solvers rely on joint axes being parallel or intersecting, which shows in the kinematics as
+//! parallel columns of `h` and zero columns of `p`, or for `ThreeParallelTwoIntersecting` an offset
+//! along the fifth axis. `KinematicFamily::classify` checks for these in order from the most to the
+//! least specialized decomposition. Every decomposition assumes revolute joints, which
+//! `Kinematics::is_revolute` checks.
+//!
+//! `Classification` reports the parallel and intersecting axes of a chain regardless of how its
+//! offsets are expressed, which shows what a design is missing to fall into a closed-form family.
+
+use {
+    super::{
+        auxiliary::Kinematics, gen_six_dof, gen_six_dof_with, spherical, spherical_into,
+        spherical_two_intersecting, spherical_two_intersecting_into, spherical_two_parallel,
+        spherical_two_parallel_into, three_parallel, three_parallel_into,
+        three_parallel_two_intersecting, three_parallel_two_intersecting_into, two_intersecting,
+        two_intersecting_at_base, two_intersecting_at_base_with, two_intersecting_with,
+        two_parallel, two_parallel_with,
+    },
+    crate::{
+        config::SolverConfig,
+        robot::{BufferedIKFunction, IKFunction, ScratchIKFunction},
+    },
+    alloc::vec::Vec,
+    nalgebra::Vector3,
+};
+
+/// The decompositions of 6R chains, each named after the axes it requires to be parallel or to
+/// intersect
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum KinematicFamily {
+    /// A spherical wrist, axes 2 and 3 parallel
+    SphericalTwoParallel,
+    /// A spherical wrist, axes 1 and 2 intersecting
+    SphericalTwoIntersecting,
+    /// A spherical wrist
+    Spherical,
+    /// Axes 2, 3 and 4 parallel, axes 5 and 6 intersecting
+    ThreeParallelTwoIntersecting,
+    /// Axes 2, 3 and 4 parallel
+    ThreeParallel,
+    /// Axes 2 and 3 parallel
+    TwoParallel,
+    /// Axes 5 and 6 intersecting
+    TwoIntersecting,
+    /// Axes 1 and 2 intersecting
+    TwoIntersectingAtBase,
+    /// No special structure
+    GenSixDof,
+}
+
+impl KinematicFamily {
+    pub const ALL: [KinematicFamily; 9] = [
+        KinematicFamily::SphericalTwoParallel,
+        KinematicFamily::SphericalTwoIntersecting,
+        KinematicFamily::Spherical,
+        KinematicFamily::ThreeParallelTwoIntersecting,
+        KinematicFamily::ThreeParallel,
+        KinematicFamily::TwoParallel,
+        KinematicFamily::TwoIntersecting,
+        KinematicFamily::TwoIntersectingAtBase,
+        KinematicFamily::GenSixDof,
+    ];
+
+    /// The most specialized family of `kin`. Axes count as parallel and offsets as zero when they
+    /// are within `tolerance` of it.
+    pub fn classify(kin: &Kinematics<6, 7>, tolerance: f64) -> Self {
+        let parallel = |i: usize, j: usize| {
+            kin.h.column(i).cross(&kin.h.column(j)).norm()
+                < tolerance * kin.h.column(i).norm() * kin.h.column(j).norm()
+        };
+        let zero = |i: usize| kin.p.column(i).norm() < tolerance;
+        let along = |i: usize, j: usize| {
+            kin.p.column(i).cross(&kin.h.column(j)).norm() < tolerance * kin.h.column(j).norm()
+        };
+
+        if zero(4) && zero(5) {
+            if zero(1) {
+                KinematicFamily::SphericalTwoIntersecting
+            } else if parallel(1, 2) {
+                KinematicFamily::SphericalTwoParallel
+            } else {
+                KinematicFamily::Spherical
+            }
+        } else if parallel(1, 2) && parallel(2, 3) {
+            if along(5, 4) {
+                KinematicFamily::ThreeParallelTwoIntersecting
+            } else {
+                KinematicFamily::ThreeParallel
+            }
+        } else if parallel(1, 2) {
+            KinematicFamily::TwoParallel
+        } else if zero(5) {
+            KinematicFamily::TwoIntersecting
+        } else if zero(1) {
+            KinematicFamily::TwoIntersectingAtBase
+        } else {
+            KinematicFamily::GenSixDof
+        }
+    }
+
+    /// The solver for chains of this family
+    pub fn solver(&self) -> IKFunction {
+        match self {
+            KinematicFamily::SphericalTwoParallel => spherical_two_parallel,
+            KinematicFamily::SphericalTwoIntersecting => spherical_two_intersecting,
+            KinematicFamily::Spherical => spherical,
+            KinematicFamily::ThreeParallelTwoIntersecting => three_parallel_two_intersecting,
+            KinematicFamily::ThreeParallel => three_parallel,
+            KinematicFamily::TwoParallel => two_parallel,
+            KinematicFamily::TwoIntersecting => two_intersecting,
+            KinematicFamily::TwoIntersectingAtBase => two_intersecting_at_base,
+            KinematicFamily::GenSixDof => gen_six_dof,
+        }
+    }
+
+    /// The solver for chains of this family that writes into a `SolutionBuffer` without
+    /// allocating, which only the closed-form families have
+    pub fn buffered_solver(&self) -> Option<BufferedIKFunction> {
+        match self {
+            KinematicFamily::SphericalTwoParallel => Some(spherical_two_parallel_into),
+            KinematicFamily::SphericalTwoIntersecting => Some(spherical_two_intersecting_into),
+            KinematicFamily::Spherical => Some(spherical_into),
+            KinematicFamily::ThreeParallelTwoIntersecting => {
+                Some(three_parallel_two_intersecting_into)
+            }
+            KinematicFamily::ThreeParallel => Some(three_parallel_into),
+            KinematicFamily::TwoParallel
+            | KinematicFamily::TwoIntersecting
+            | KinematicFamily::TwoIntersectingAtBase
+            | KinematicFamily::GenSixDof => None,
+        }
+    }
+
+    /// The solver for chains of this family that keeps the samples of its search in a
+    /// `SolverScratch`, which only the families solved by searching have
+    pub fn scratch_solver(&self) -> Option<ScratchIKFunction> {
+        match self {
+            KinematicFamily::TwoParallel => Some(two_parallel_with),
+            KinematicFamily::TwoIntersecting => Some(two_intersecting_with),
+            KinematicFamily::TwoIntersectingAtBase => Some(two_intersecting_at_base_with),
+            KinematicFamily::GenSixDof => Some(gen_six_dof_with),
+            KinematicFamily::SphericalTwoParallel
+            | KinematicFamily::SphericalTwoIntersecting
+            | KinematicFamily::Spherical
+            | KinematicFamily::ThreeParallelTwoIntersecting
+            | KinematicFamily::ThreeParallel => None,
+        }
+    }
+
+    /// The name of the solver, e.g. `"spherical_two_parallel"`
+    pub fn name(&self) -> &'static str {
+        match self {
+            KinematicFamily::SphericalTwoParallel => "spherical_two_parallel",
+            KinematicFamily::SphericalTwoIntersecting => "spherical_two_intersecting",
+            KinematicFamily::Spherical => "spherical",
+            KinematicFamily::ThreeParallelTwoIntersecting => "three_parallel_two_intersecting",
+            KinematicFamily::ThreeParallel => "three_parallel",
+            KinematicFamily::TwoParallel => "two_parallel",
+            KinematicFamily::TwoIntersecting => "two_intersecting",
+            KinematicFamily::TwoIntersectingAtBase => "two_intersecting_at_base",
+            KinematicFamily::GenSixDof => "gen_six_dof",
+        }
+    }
+
+    /// The family called `name`, as returned by `KinematicFamily::name`
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|family| family.name() == name)
+    }
+
+    /// How many joint angles the solver searches over, 0 for the closed-form solvers
+    pub fn search_dimensions(&self) -> usize {
+        match self {
+            KinematicFamily::TwoIntersecting | KinematicFamily::TwoIntersectingAtBase => 1,
+            KinematicFamily::GenSixDof => 2,
+            _ => 0,
+        }
+    }
+
+    /// Whether the solver finds every solution in closed form, rather than by a search that can
+    /// miss solutions or only return least squares ones
+    pub fn is_closed_form(&self) -> bool {
+        self.search_dimensions() == 0
+    }
+
+    /// Whether the last three axes of the family intersect in a wrist center
+    pub fn has_spherical_wrist(&self) -> bool {
+        matches!(
+            self,
+            KinematicFamily::SphericalTwoParallel
+                | KinematicFamily::SphericalTwoIntersecting
+                | KinematicFamily::Spherical
+        )
+    }
+}
+
+/// The most specialized family of `kin`, with the tolerance of the default `SolverConfig`
+pub fn classify(kin: &Kinematics<6, 7>) -> KinematicFamily {
+    KinematicFamily::classify(kin, SolverConfig::default().ls_tolerance)
+}
+
+/// The geometric conditions the joint axes of a 6R chain satisfy, and the family they put it in.
+///
+/// Only consecutive axes are compared, since the angle and distance between any others depend on
+/// the joints in between. Axes are given by their index in `h`. The solvers also need the offset between intersecting
+/// axes to be zero, so a chain can have intersecting axes and still be classified as a more general
+/// family until its offsets are moved along the axes onto the intersection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Classification {
+    pub family: KinematicFamily,
+    /// Pairs of consecutive axes `(i, i + 1)` that are parallel
+    pub parallel: Vec<(usize, usize)>,
+    /// Pairs of consecutive axes `(i, i + 1)` that cross at a point
+    pub intersecting: Vec<(usize, usize)>,
+}
+
+impl Classification {
+    /// Classify `kin`, counting axes as parallel or intersecting when they are within `tolerance`
+    /// of it
+    pub fn from_kinematics(kin: &Kinematics<6, 7>, tolerance: f64) -> Self {
+        let mut parallel = Vec::new();
+        let mut intersecting = Vec::new();
+        for i in 0..5 {
+            let normal: Vector3<f64> = kin.h.column(i).cross(&kin.h.column(i + 1));
+            if normal.norm() < tolerance * kin.h.column(i).norm() * kin.h.column(i + 1).norm() {
+                parallel.push((i, i + 1));
+            } else if kin.p.column(i + 1).dot(&normal).abs() < tolerance * normal.norm() {
+                // The offset between the axes lies in the plane they span
+                intersecting.push((i, i + 1));
+            }
+        }
+
+        Classification {
+            family: KinematicFamily::classify(kin, tolerance),
+            parallel,
+            intersecting,
+        }
+    }
+}
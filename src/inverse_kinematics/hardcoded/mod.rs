@@ -2,47 +2,149 @@ pub mod setups;
 
 use {
     self::setups::{
-        Irb6640, KukaR800FixedQ3, RrcFixedQ6, SphericalBot, ThreeParallelBot, TwoParallelBot, Ur5,
-        YumiFixedQ3,
+        EpsonLs6, FanucLrMate200id, Irb120, Irb1200, Irb2600, Irb4600, Irb6640, KinovaGen3FixedQ3,
+        KukaR800FixedQ3, MotomanGp8, PandaFixedQ4, RrcFixedQ6, SawyerFixedQ3, SphericalBot,
+        ThreeParallelBot, TwoParallelBot, Ur5, YumiFixedQ3,
     },
     super::{
-        gen_six_dof, spherical, spherical_two_intersecting, spherical_two_parallel, three_parallel,
-        three_parallel_two_intersecting, two_intersecting, two_parallel,
+        chains::scara, gen_six_dof, spherical, spherical_two_intersecting, spherical_two_parallel,
+        three_parallel, three_parallel_two_intersecting, two_intersecting, two_intersecting_at_base,
+        two_parallel,
     },
-    nalgebra::{Matrix3, Vector3, Vector6},
+    crate::{
+        config::SolverConfig,
+        error::IkGeoError,
+        robot::{SevenDofSweep, SweepStrategy},
+        solutionset::IkSolutionSet,
+    },
+    nalgebra::{Matrix3, Vector3},
 };
 
-pub fn irb6640(r: &Matrix3<f64>, t: &Vector3<f64>) -> (Vec<Vector6<f64>>, Vec<bool>) {
-    spherical_two_parallel(r, t, &Irb6640::get_kin())
+pub fn irb120(r: &Matrix3<f64>, t: &Vector3<f64>, config: &SolverConfig) -> IkSolutionSet {
+    spherical_two_parallel(r, t, &Irb120::get_kin(), config)
+}
+
+pub fn irb1200(r: &Matrix3<f64>, t: &Vector3<f64>, config: &SolverConfig) -> IkSolutionSet {
+    spherical_two_parallel(r, t, &Irb1200::get_kin(), config)
+}
+
+pub fn irb2600(r: &Matrix3<f64>, t: &Vector3<f64>, config: &SolverConfig) -> IkSolutionSet {
+    spherical_two_parallel(r, t, &Irb2600::get_kin(), config)
+}
+
+pub fn irb4600(r: &Matrix3<f64>, t: &Vector3<f64>, config: &SolverConfig) -> IkSolutionSet {
+    spherical_two_parallel(r, t, &Irb4600::get_kin(), config)
+}
+
+pub fn irb6640(r: &Matrix3<f64>, t: &Vector3<f64>, config: &SolverConfig) -> IkSolutionSet {
+    spherical_two_parallel(r, t, &Irb6640::get_kin(), config)
 }
 
-pub fn kuka_r800_fixed_q3(r: &Matrix3<f64>, t: &Vector3<f64>) -> (Vec<Vector6<f64>>, Vec<bool>) {
+/// Solutions are in the solver's joint angles, see `FanucLrMate200id::to_joint_coordinates`
+pub fn fanuc_lr_mate_200id(
+    r: &Matrix3<f64>,
+    t: &Vector3<f64>,
+    config: &SolverConfig,
+) -> IkSolutionSet {
+    spherical_two_parallel(r, t, &FanucLrMate200id::get_kin(), config)
+}
+
+pub fn epson_ls6(r: &Matrix3<f64>, t: &Vector3<f64>, config: &SolverConfig) -> IkSolutionSet<4> {
+    scara(r, t, &EpsonLs6::get_kin(), config)
+}
+
+pub fn motoman_gp8(r: &Matrix3<f64>, t: &Vector3<f64>, config: &SolverConfig) -> IkSolutionSet {
+    spherical_two_parallel(r, t, &MotomanGp8::get_kin(), config)
+}
+
+pub fn kuka_r800_fixed_q3(
+    r: &Matrix3<f64>,
+    t: &Vector3<f64>,
+    config: &SolverConfig,
+) -> IkSolutionSet {
     let (kin_partial, r_6t) = KukaR800FixedQ3::get_kin_partial();
-    spherical_two_intersecting(&(r * r_6t.transpose()), t, &kin_partial)
+    spherical_two_intersecting(&(r * r_6t.transpose()), t, &kin_partial, config)
 }
 
-pub fn rrc_fixed_q6(r: &Matrix3<f64>, t: &Vector3<f64>) -> (Vec<Vector6<f64>>, Vec<bool>) {
-    let (kin_partial, r_6t) = RrcFixedQ6::get_kin_partial();
-    two_intersecting(&(r * r_6t.transpose()), t, &kin_partial)
+/// Falls back to the `SevenDofSweep` of the RRC at the fixed q6 if reducing the partial chain is
+/// ill-conditioned, see `rrc_fixed_q6_unreduced`, and fails if the partial kinematics could not be
+/// constructed otherwise, e.g. with invalid tolerances in `config`
+pub fn rrc_fixed_q6(
+    r: &Matrix3<f64>,
+    t: &Vector3<f64>,
+    config: &SolverConfig,
+) -> Result<IkSolutionSet, IkGeoError> {
+    match RrcFixedQ6::get_kin_partial(config) {
+        Ok((kin_partial, r_6t)) => Ok(two_intersecting(
+            &(r * r_6t.transpose()),
+            t,
+            &kin_partial,
+            config,
+        )),
+        Err(IkGeoError::IllConditioned { .. }) => Ok(rrc_fixed_q6_unreduced(r, t, config)),
+        Err(e) => Err(e),
+    }
 }
 
-pub fn yumi_fixed_q3(r: &Matrix3<f64>, t: &Vector3<f64>) -> (Vec<Vector6<f64>>, Vec<bool>) {
+/// Solves the RRC with q6 fixed by `SevenDofSweep::solve_at` at `RrcFixedQ6::Q6`, which searches
+/// the unreduced partial chain with `gen_six_dof_grid`, and drops q6 from the solutions again.
+/// Their residuals are those of the full chain and their `is_ls` that of the grid search.
+pub(crate) fn rrc_fixed_q6_unreduced(
+    r: &Matrix3<f64>,
+    t: &Vector3<f64>,
+    config: &SolverConfig,
+) -> IkSolutionSet {
+    SevenDofSweep::rrc(SweepStrategy::Grid(1))
+        .with_config(*config)
+        .solve_at(r, t, RrcFixedQ6::Q6)
+        .solutions
+        .map_q(|q| q.remove_row(5))
+}
+
+pub fn yumi_fixed_q3(r: &Matrix3<f64>, t: &Vector3<f64>, config: &SolverConfig) -> IkSolutionSet {
     let (kin_partial, r_6t) = YumiFixedQ3::get_kin_partial();
-    gen_six_dof(&(r * r_6t.transpose()), t, &kin_partial)
+    gen_six_dof(&(r * r_6t.transpose()), t, &kin_partial, config)
+}
+
+pub fn panda_fixed_q4(r: &Matrix3<f64>, t: &Vector3<f64>, config: &SolverConfig) -> IkSolutionSet {
+    let (kin_partial, r_6t) = PandaFixedQ4::get_kin_partial();
+    two_intersecting_at_base(&(r * r_6t.transpose()), t, &kin_partial, config)
+}
+
+pub fn kinova_gen3_fixed_q3(
+    r: &Matrix3<f64>,
+    t: &Vector3<f64>,
+    config: &SolverConfig,
+) -> IkSolutionSet {
+    let (kin_partial, r_6t) = KinovaGen3FixedQ3::get_kin_partial();
+    two_intersecting(&(r * r_6t.transpose()), t, &kin_partial, config)
+}
+
+pub fn sawyer_fixed_q3(r: &Matrix3<f64>, t: &Vector3<f64>, config: &SolverConfig) -> IkSolutionSet {
+    let (kin_partial, r_6t) = SawyerFixedQ3::get_kin_partial();
+    two_intersecting(&(r * r_6t.transpose()), t, &kin_partial, config)
 }
 
-pub fn ur5(r: &Matrix3<f64>, t: &Vector3<f64>) -> (Vec<Vector6<f64>>, Vec<bool>) {
-    three_parallel_two_intersecting(r, t, &Ur5::get_kin())
+pub fn ur5(r: &Matrix3<f64>, t: &Vector3<f64>, config: &SolverConfig) -> IkSolutionSet {
+    three_parallel_two_intersecting(r, t, &Ur5::get_kin(), config)
 }
 
-pub fn three_parallel_bot(r: &Matrix3<f64>, t: &Vector3<f64>) -> (Vec<Vector6<f64>>, Vec<bool>) {
-    three_parallel(r, t, &ThreeParallelBot::get_kin())
+pub fn three_parallel_bot(
+    r: &Matrix3<f64>,
+    t: &Vector3<f64>,
+    config: &SolverConfig,
+) -> IkSolutionSet {
+    three_parallel(r, t, &ThreeParallelBot::get_kin(), config)
 }
 
-pub fn two_parallel_bot(r: &Matrix3<f64>, t: &Vector3<f64>) -> (Vec<Vector6<f64>>, Vec<bool>) {
-    two_parallel(r, t, &TwoParallelBot::get_kin())
+pub fn two_parallel_bot(
+    r: &Matrix3<f64>,
+    t: &Vector3<f64>,
+    config: &SolverConfig,
+) -> IkSolutionSet {
+    two_parallel(r, t, &TwoParallelBot::get_kin(), config)
 }
 
-pub fn spherical_bot(r: &Matrix3<f64>, t: &Vector3<f64>) -> (Vec<Vector6<f64>>, Vec<bool>) {
-    spherical(&r, &t, &SphericalBot::get_kin())
+pub fn spherical_bot(r: &Matrix3<f64>, t: &Vector3<f64>, config: &SolverConfig) -> IkSolutionSet {
+    spherical(r, t, &SphericalBot::get_kin(), config)
 }
@@ -1,78 +1,166 @@
 use {
-    super::{
-        irb6640, kuka_r800_fixed_q3, rrc_fixed_q6, spherical_bot, three_parallel_bot,
-        two_parallel_bot, ur5, yumi_fixed_q3,
-    },
     crate::{
-        inverse_kinematics::{
-            auxiliary::{Kinematics, Matrix3x7, Matrix3x8},
-            setups::{calculate_ik_error, ik_write_output, SetupIk},
-        },
-        subproblems::{auxiliary::random_angle, setups::SetupStatic, Vector7},
+        config::SolverConfig,
+        error::IkGeoError,
+        inverse_kinematics::auxiliary::{JointType, Kinematics, Matrix3x7, Matrix3x8},
+        solutionset::IkSolutionSet,
+    },
+    core::f64::consts::PI,
+    nalgebra::{
+        Isometry3, Matrix3, Matrix3x4, Matrix3x5, Matrix3x6, Rotation3, Translation3, Vector3,
+        Vector6,
     },
-    nalgebra::{Matrix3, Matrix3x6, Vector3, Vector6},
-    std::f64::{consts::PI, NAN},
 };
 
+#[cfg(all(not(feature = "std"), target_os = "none"))]
+use num_traits::Float;
+
+#[cfg(feature = "std")]
+mod harness;
+
+#[cfg(feature = "std")]
+pub use harness::hardcoded_setup_from_string;
+
 macro_rules! define_struct {
     ($name:ident, $num_joints:expr) => {
+        #[cfg_attr(not(feature = "std"), allow(dead_code))]
         pub struct $name {
             kin: Kinematics<$num_joints, { $num_joints + 1 }>,
             r: Matrix3<f64>,
             t: Vector3<f64>,
 
-            q: Vec<Vector6<f64>>,
-            is_ls: Vec<bool>,
+            solutions: IkSolutionSet,
         }
     };
 }
 
 // Define all structures
+define_struct!(Irb120, 6);
+define_struct!(Irb1200, 6);
+define_struct!(Irb2600, 6);
+define_struct!(Irb4600, 6);
 define_struct!(Irb6640, 6);
+define_struct!(FanucLrMate200id, 6);
+define_struct!(MotomanGp8, 6);
 define_struct!(KukaR800FixedQ3, 7);
 define_struct!(RrcFixedQ6, 7);
 define_struct!(YumiFixedQ3, 7);
+define_struct!(PandaFixedQ4, 7);
+define_struct!(KinovaGen3FixedQ3, 7);
+define_struct!(SawyerFixedQ3, 7);
 define_struct!(Ur5, 6);
 define_struct!(ThreeParallelBot, 6);
 define_struct!(TwoParallelBot, 6);
 define_struct!(SphericalBot, 6);
 
-pub fn hardcoded_setup_from_string(raw: &str, r: &mut Matrix3<f64>, t: &mut Vector3<f64>) {
-    let data: Vec<f64> = raw.split(',').map(|s| s.parse().unwrap()).collect();
+/// The Epson LS6-602 SCARA, solved by `chains::scara`. The base frame is at the height of the
+/// flange with the third joint retracted, which lowers the flange as it extends.
+pub struct EpsonLs6;
+
+/// Kinematics shared by most industrial arms: a shoulder offset, two parallel axes and a spherical
+/// wrist, given the base height, shoulder offset, upper arm length, elbow offset, forearm length
+/// and flange offset. The joint axes follow the ABB sign conventions.
+fn industrial_kin(d1: f64, a1: f64, a2: f64, a3: f64, d4: f64, d6: f64) -> Kinematics<6, 7> {
+    let mut kin = Kinematics::new();
+
+    let zv = Vector3::zeros();
+    let ex = Vector3::x();
+    let ey = Vector3::y();
+    let ez = Vector3::z();
+
+    kin.h = Matrix3x6::from_columns(&[ez, ey, ey, ex, ey, ex]);
+    kin.p = Matrix3x7::from_columns(&[
+        zv,
+        a1 * ex + d1 * ez,
+        a2 * ez,
+        d4 * ex + a3 * ez,
+        zv,
+        zv,
+        d6 * ex,
+    ]);
+
+    kin
+}
 
-    *r = Matrix3::new(
-        data[0], data[1], data[2], data[3], data[4], data[5], data[6], data[7], data[8],
-    );
+impl Irb120 {
+    /// Link parameters of the IRB 120-3/0.6
+    pub fn get_kin() -> Kinematics<6, 7> {
+        industrial_kin(0.29, 0.0, 0.27, 0.07, 0.302, 0.072)
+    }
+}
 
-    *t = Vector3::new(data[9], data[10], data[11]);
+impl Irb1200 {
+    /// Link parameters of the IRB 1200-5/0.9
+    pub fn get_kin() -> Kinematics<6, 7> {
+        industrial_kin(0.3991, 0.0, 0.448, 0.042, 0.451, 0.082)
+    }
+}
+
+impl Irb2600 {
+    /// Link parameters of the IRB 2600-12/1.65
+    pub fn get_kin() -> Kinematics<6, 7> {
+        industrial_kin(0.445, 0.15, 0.7, 0.115, 0.795, 0.085)
+    }
+}
+
+impl Irb4600 {
+    /// Link parameters of the IRB 4600-60/2.05
+    pub fn get_kin() -> Kinematics<6, 7> {
+        industrial_kin(0.495, 0.175, 0.9, 0.175, 0.96, 0.135)
+    }
 }
 
 impl Irb6640 {
     pub fn get_kin() -> Kinematics<6, 7> {
-        let mut kin = Kinematics::new();
+        industrial_kin(0.78, 0.32, 1.075, 0.2, 1.1425, 0.2)
+    }
+}
+
+impl FanucLrMate200id {
+    pub fn get_kin() -> Kinematics<6, 7> {
+        let mut kin = industrial_kin(0.33, 0.05, 0.33, 0.035, 0.335, 0.08);
 
-        let zv = Vector3::zeros();
         let ex = Vector3::x();
         let ey = Vector3::y();
         let ez = Vector3::z();
 
-        kin.h = Matrix3x6::from_columns(&[ez, ey, ey, ex, ey, ex]);
-        kin.p = Matrix3x7::from_columns(&[
-            zv,
-            0.32 * ex + 0.78 * ez,
-            1.075 * ez,
-            1.1425 * ex + 0.2 * ez,
-            zv,
-            zv,
-            0.2 * ex,
-        ]);
+        kin.h = Matrix3x6::from_columns(&[ez, ey, -ey, -ex, -ey, -ex]);
+
+        kin
+    }
+
+    /// Convert the solver's joint angles to FANUC joint coordinates, where J3 is measured from
+    /// the horizontal instead of from the upper arm, so moving J2 alone keeps the forearm level
+    pub fn to_joint_coordinates(q: &Vector6<f64>) -> Vector6<f64> {
+        let mut j = *q;
+        j[2] = q[2] - q[1];
+        j
+    }
+
+    /// Convert FANUC joint coordinates to the solver's joint angles
+    pub fn from_joint_coordinates(j: &Vector6<f64>) -> Vector6<f64> {
+        let mut q = *j;
+        q[2] = j[2] + j[1];
+        q
+    }
+}
+
+impl MotomanGp8 {
+    pub fn get_kin() -> Kinematics<6, 7> {
+        let mut kin = industrial_kin(0.33, 0.04, 0.345, 0.04, 0.34, 0.08);
+
+        let ex = Vector3::x();
+        let ey = Vector3::y();
+        let ez = Vector3::z();
+
+        kin.h = Matrix3x6::from_columns(&[ez, ey, -ey, -ex, -ey, -ex]);
 
         kin
     }
 }
 
 impl KukaR800FixedQ3 {
-    const Q3: f64 = PI / 6.0;
+    pub(crate) const Q3: f64 = PI / 6.0;
 
     pub fn get_kin() -> Kinematics<7, 8> {
         let mut kin = Kinematics::new();
@@ -97,13 +185,18 @@ impl KukaR800FixedQ3 {
     }
 
     pub fn get_kin_partial() -> (Kinematics<6, 7>, Matrix3<f64>) {
+        Self::get_kin_partial_at(Self::Q3)
+    }
+
+    /// Get the partial kinematics with q3 locked at the given value
+    pub fn get_kin_partial_at(q3: f64) -> (Kinematics<6, 7>, Matrix3<f64>) {
         let kin = Self::get_kin();
-        kin.forward_kinematics_partial(Self::Q3, 2, &Matrix3::identity())
+        kin.forward_kinematics_partial(q3, 2, &Matrix3::identity())
     }
 }
 
 impl RrcFixedQ6 {
-    const Q6: f64 = PI / 6.0;
+    pub(crate) const Q6: f64 = PI / 6.0;
 
     pub fn get_kin() -> Kinematics<7, 8> {
         let mut kin = Kinematics::new();
@@ -128,34 +221,34 @@ impl RrcFixedQ6 {
         kin
     }
 
-    pub fn get_kin_partial() -> (Kinematics<6, 7>, Matrix3<f64>) {
-        let kin = Self::get_kin();
-        let (mut kin_partial, r_6t) =
-            kin.forward_kinematics_partial(Self::Q6, 5, &Matrix3::identity());
+    pub fn get_kin_partial(
+        config: &SolverConfig,
+    ) -> Result<(Kinematics<6, 7>, Matrix3<f64>), IkGeoError> {
+        Self::get_kin_partial_at(Self::Q6, config)
+    }
 
-        let zv = Vector3::zeros();
-        let alpha = (kin_partial.h.fixed_columns::<2>(4))
-            .pseudo_inverse(1e-12)
-            .unwrap()
-            * kin_partial.p.column(5);
-        let delta_p_45 = alpha[0] * kin_partial.h.column(4);
-        let delta_p_6t = alpha[1] * kin_partial.h.column(5);
-
-        kin_partial
-            .p
-            .set_column(4, &(kin_partial.p.column(4) + delta_p_45));
-        kin_partial.p.set_column(5, &zv);
-        kin_partial
-            .p
-            .set_column(6, &(kin_partial.p.column(6) + delta_p_6t));
-
-        (kin_partial, r_6t)
+    /// Get the partial kinematics with q6 locked at the given value. The offset between the last
+    /// two axes is moved onto them so they intersect, see `Kinematics::intersect_axes`, which fails
+    /// with `IkGeoError::IllConditioned` as q6 approaches 0 or π and the axes become parallel.
+    pub fn get_kin_partial_at(
+        q6: f64,
+        config: &SolverConfig,
+    ) -> Result<(Kinematics<6, 7>, Matrix3<f64>), IkGeoError> {
+        let (kin_partial, r_6t) = Self::get_kin_unreduced_at(q6);
+        Ok((kin_partial.intersect_axes(4, config)?, r_6t))
+    }
+
+    /// Get the partial kinematics with q6 locked at the given value, without moving any offsets,
+    /// for the general solver
+    pub fn get_kin_unreduced_at(q6: f64) -> (Kinematics<6, 7>, Matrix3<f64>) {
+        Self::get_kin().forward_kinematics_partial(q6, 5, &Matrix3::identity())
     }
 }
 
 impl YumiFixedQ3 {
-    const Q3: f64 = PI / 6.0;
+    pub(crate) const Q3: f64 = PI / 6.0;
 
+    /// The left arm, in the frame of the robot body
     pub fn get_kin() -> Kinematics<7, 8> {
         let mut kin = Kinematics::new();
 
@@ -177,330 +270,298 @@ impl YumiFixedQ3 {
         kin
     }
 
+    /// The right arm, the mirror image of the left arm in the xz plane of the robot body. The
+    /// same joint angles put the arms in mirrored poses.
+    pub fn get_kin_right() -> Kinematics<7, 8> {
+        Self::get_kin().mirrored()
+    }
+
     pub fn get_kin_partial() -> (Kinematics<6, 7>, Matrix3<f64>) {
+        Self::get_kin_partial_at(Self::Q3)
+    }
+
+    /// Get the partial kinematics with q3 locked at the given value
+    pub fn get_kin_partial_at(q3: f64) -> (Kinematics<6, 7>, Matrix3<f64>) {
         let kin = Self::get_kin();
-        kin.forward_kinematics_partial(Self::Q3, 2, &Matrix3::identity())
+        kin.forward_kinematics_partial(q3, 2, &Matrix3::identity())
     }
 }
 
-impl Ur5 {
-    pub fn get_kin() -> Kinematics<6, 7> {
+impl PandaFixedQ4 {
+    pub(crate) const Q4: f64 = -PI / 2.0;
+
+    pub fn get_kin() -> Kinematics<7, 8> {
         let mut kin = Kinematics::new();
 
+        let zv = Vector3::zeros();
         let ex = Vector3::x();
         let ey = Vector3::y();
         let ez = Vector3::z();
 
-        kin.h = Matrix3x6::from_columns(&[ez, ey, ey, ey, -ez, ey]);
-        kin.p = Matrix3x7::from_columns(&[
-            0.089159 * ez,
-            0.1358 * ey,
-            -0.1197 * ey + 0.425 * ex,
-            0.3922 * ex,
-            0.093 * ey,
-            -0.0946 * ez,
-            0.0823 * ey,
+        kin.h = Matrix3x7::from_columns(&[ez, ey, ez, -ey, ez, -ey, -ez]);
+        kin.p = Matrix3x8::from_columns(&[
+            0.333 * ez,
+            zv,
+            0.316 * ez,
+            0.0825 * ex,
+            -0.0825 * ex + 0.384 * ez,
+            zv,
+            0.088 * ex,
+            -0.107 * ez,
         ]);
 
         kin
     }
-}
 
-impl ThreeParallelBot {
-    pub fn get_kin() -> Kinematics<6, 7> {
-        let mut kin = Kinematics::new();
+    pub fn get_kin_partial() -> (Kinematics<6, 7>, Matrix3<f64>) {
+        Self::get_kin_partial_at(Self::Q4)
+    }
 
-        let ex = Vector3::x();
-        let ey = Vector3::y();
-        let ez = Vector3::z();
+    /// Get the partial kinematics with q4 locked at the given value
+    pub fn get_kin_partial_at(q4: f64) -> (Kinematics<6, 7>, Matrix3<f64>) {
+        let kin = Self::get_kin();
+        kin.forward_kinematics_partial(q4, 3, &Matrix3::identity())
+    }
+}
 
-        kin.h = Matrix3x6::from_columns(&[ez, ex, ex, ex, ez, ex]);
-        kin.p = Matrix3x7::from_columns(&[ez, ey, ey, ey, ey, ey + ex, ex]);
+/// Models of the Universal Robots family, which share the same kinematic structure, from the
+/// official DH parameters. `UrModel::Ur5` is the DH model, while `Ur5` keeps its own geometry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UrModel {
+    Ur3,
+    Ur3e,
+    Ur5,
+    Ur5e,
+    Ur10,
+    Ur10e,
+    Ur16e,
+}
 
-        kin
+impl UrModel {
+    pub const ALL: [UrModel; 7] = [
+        UrModel::Ur3,
+        UrModel::Ur3e,
+        UrModel::Ur5,
+        UrModel::Ur5e,
+        UrModel::Ur10,
+        UrModel::Ur10e,
+        UrModel::Ur16e,
+    ];
+
+    /// The official DH parameters `[d1, a2, a3, d4, d5, d6]`
+    pub fn dh_parameters(self) -> [f64; 6] {
+        match self {
+            UrModel::Ur3 => [0.1519, -0.24365, -0.21325, 0.11235, 0.08535, 0.0819],
+            UrModel::Ur3e => [0.15185, -0.24355, -0.2132, 0.13105, 0.08535, 0.0921],
+            UrModel::Ur5 => [0.089159, -0.425, -0.39225, 0.10915, 0.09465, 0.0823],
+            UrModel::Ur5e => [0.1625, -0.425, -0.3922, 0.1333, 0.0997, 0.0996],
+            UrModel::Ur10 => [0.1273, -0.612, -0.5723, 0.163941, 0.1157, 0.0922],
+            UrModel::Ur10e => [0.1807, -0.6127, -0.57155, 0.17415, 0.11985, 0.11655],
+            UrModel::Ur16e => [0.1807, -0.4784, -0.36, 0.17415, 0.11985, 0.11655],
+        }
     }
-}
 
-impl TwoParallelBot {
-    pub fn get_kin() -> Kinematics<6, 7> {
+    pub fn get_kin(self) -> Kinematics<6, 7> {
         let mut kin = Kinematics::new();
 
+        let zv = Vector3::zeros();
         let ex = Vector3::x();
         let ey = Vector3::y();
         let ez = Vector3::z();
 
-        let es = (ex + ez).normalize();
+        let [d1, a2, a3, d4, d5, d6] = self.dh_parameters();
 
-        kin.h = Matrix3x6::from_columns(&[ez, ex, ex, ez, ex, es]);
-        kin.p = Matrix3x7::from_columns(&[ez, ey, ey, ey, ey, ey, ez]);
+        kin.h = Matrix3x6::from_columns(&[ez, ey, ey, ey, -ez, ey]);
+        kin.p =
+            Matrix3x7::from_columns(&[d1 * ez, zv, -a2 * ex, -a3 * ex, d4 * ey, -d5 * ez, d6 * ey]);
 
         kin
     }
 }
 
-impl SphericalBot {
-    pub fn get_kin() -> Kinematics<6, 7> {
+impl KinovaGen3FixedQ3 {
+    pub(crate) const Q3: f64 = PI / 6.0;
+
+    pub fn get_kin() -> Kinematics<7, 8> {
         let mut kin = Kinematics::new();
 
         let zv = Vector3::zeros();
-        let ex = Vector3::x();
         let ey = Vector3::y();
         let ez = Vector3::z();
 
-        kin.h = Matrix3x6::from_columns(&[ey, ez, ey, ex, ey, ex]);
-        kin.p = Matrix3x7::from_columns(&[zv, ez + ex, ez + ex, ez + ex, zv, zv, ex]);
+        kin.h = Matrix3x7::from_columns(&[-ez, ey, -ez, ey, -ez, ey, -ez]);
+        // The last two axes intersect at the origin of the last joint, so the partial kinematics are
+        // solved by `two_intersecting`
+        kin.p = Matrix3x8::from_columns(&[
+            0.15643 * ez,
+            -0.005375 * ey + 0.12838 * ez,
+            -0.006375 * ey + 0.21038 * ez,
+            -0.006375 * ey + 0.21038 * ez,
+            -0.006375 * ey + 0.20843 * ez,
+            -0.0003501 * ey + 0.10593 * ez,
+            zv,
+            0.167455 * ez,
+        ]);
 
-        kin
+        kin.with_tool(Isometry3::from_parts(
+            Translation3::identity(),
+            Rotation3::from_matrix_unchecked(Self::get_r_7t()).into(),
+        ))
     }
-}
-
-// Most of the implementations in SetupIk are the same, so we can use a macro to generate them.
-macro_rules! impl_setup_ik {
-    // Generate the function setup_from_str, write_output,ls_count, solution_count, name, and debug
-    ($name:ident) => {
-        fn setup_from_str(&mut self, raw: &str) {
-            hardcoded_setup_from_string(raw, &mut self.r, &mut self.t);
-        }
 
-        fn write_output(&self) -> String {
-            ik_write_output(&self.q)
-        }
-
-        fn ls_count(&self) -> usize {
-            self.is_ls.iter().filter(|b| **b).count()
-        }
-
-        fn solution_count(&self) -> usize {
-            self.is_ls.len()
-        }
-
-        fn name(&self) -> &'static str {
-            <$name as SetupStatic>::name()
-        }
-
-        fn debug(&self, i: usize) {
-            println!("{i}{}{}", self.r, self.t);
-        }
-    };
-}
-
-impl SetupIk for Irb6640 {
-    fn setup(&mut self) {
-        let q = Vector6::zeros().map(|_: f64| random_angle());
-        (self.r, self.t) = self.kin.forward_kinematics(&q);
+    /// Orientation of the tool flange relative to the last joint at the zero configuration,
+    /// which `get_kin` includes as the tool transform
+    pub fn get_r_7t() -> Matrix3<f64> {
+        Matrix3::identity()
     }
 
-    impl_setup_ik!(Irb6640);
-
-    fn run(&mut self) {
-        (self.q, self.is_ls) = irb6640(&self.r, &self.t)
+    pub fn get_kin_partial() -> (Kinematics<6, 7>, Matrix3<f64>) {
+        Self::get_kin_partial_at(Self::Q3)
     }
 
-    fn error(&self) -> f64 {
-        self.q
-            .iter()
-            .map(|q| calculate_ik_error(&self.kin, &self.r, &self.t, q))
-            .reduce(f64::min)
-            .unwrap_or(NAN)
+    /// Get the partial kinematics with q3 locked at the given value
+    pub fn get_kin_partial_at(q3: f64) -> (Kinematics<6, 7>, Matrix3<f64>) {
+        let kin = Self::get_kin();
+        kin.forward_kinematics_partial(q3, 2, &Self::get_r_7t())
     }
 }
 
-impl SetupIk for KukaR800FixedQ3 {
-    fn setup(&mut self) {
-        let mut q = Vector7::zeros().map(|_: f64| random_angle());
-        q[2] = Self::Q3;
-        (self.r, self.t) = self.kin.forward_kinematics(&q);
-    }
+impl SawyerFixedQ3 {
+    pub(crate) const Q3: f64 = PI / 6.0;
 
-    impl_setup_ik!(KukaR800FixedQ3);
+    pub fn get_kin() -> Kinematics<7, 8> {
+        let mut kin = Kinematics::new();
 
-    fn run(&mut self) {
-        (self.q, self.is_ls) = kuka_r800_fixed_q3(&self.r, &self.t);
-    }
+        let zv = Vector3::zeros();
+        let ex = Vector3::x();
+        let ey = Vector3::y();
+        let ez = Vector3::z();
 
-    fn error(&self) -> f64 {
-        self.q
-            .iter()
-            .map(|q| {
-                let q_e =
-                    Vector7::from_column_slice(&[q[0], q[1], Self::Q3, q[2], q[3], q[4], q[5]]);
+        kin.h = Matrix3x7::from_columns(&[ez, ey, ex, ey, ex, ey, ex]);
+        // The last two axes intersect at the origin of the last joint, so the partial kinematics are
+        // solved by `two_intersecting`
+        kin.p = Matrix3x8::from_columns(&[
+            0.08 * ez,
+            0.081 * ex + 0.05 * ey + 0.237 * ez,
+            0.14 * ex + 0.1425 * ey,
+            0.26 * ex - 0.042 * ey,
+            0.125 * ex - 0.1265 * ey,
+            0.275 * ex + 0.1363 * ey,
+            zv,
+            0.1345 * ex,
+        ]);
 
-                let (r_t, t_t) = self.kin.forward_kinematics(&q_e);
-                (r_t - self.r).norm() + (t_t - self.t).norm()
-            })
-            .reduce(f64::min)
-            .unwrap_or(NAN)
+        kin.with_tool(Isometry3::from_parts(
+            Translation3::identity(),
+            Rotation3::from_matrix_unchecked(Self::get_r_7t()).into(),
+        ))
     }
-}
 
-impl SetupIk for RrcFixedQ6 {
-    fn setup(&mut self) {
-        let mut q = Vector7::zeros().map(|_: f64| random_angle());
-        q[5] = Self::Q6;
-        (self.r, self.t) = self.kin.forward_kinematics(&q);
+    /// Orientation of the tool flange relative to the last joint at the zero configuration,
+    /// including the 10° tilt of the hand, which `get_kin` includes as the tool transform
+    pub fn get_r_7t() -> Matrix3<f64> {
+        let (s, c) = 10.0_f64.to_radians().sin_cos();
+        Matrix3::new(0.0, 0.0, 1.0, -c, -s, 0.0, s, -c, 0.0)
     }
 
-    impl_setup_ik!(RrcFixedQ6);
-
-    fn run(&mut self) {
-        (self.q, self.is_ls) = rrc_fixed_q6(&self.r, &self.t);
+    pub fn get_kin_partial() -> (Kinematics<6, 7>, Matrix3<f64>) {
+        Self::get_kin_partial_at(Self::Q3)
     }
 
-    fn error(&self) -> f64 {
-        self.q
-            .iter()
-            .map(|q| {
-                let q_e =
-                    Vector7::from_column_slice(&[q[0], q[1], q[2], q[3], q[4], Self::Q6, q[5]]);
-
-                let (r_t, t_t) = self.kin.forward_kinematics(&q_e);
-                (r_t - self.r).norm() + (t_t - self.t).norm()
-            })
-            .reduce(f64::min)
-            .unwrap_or(NAN)
+    /// Get the partial kinematics with q3 locked at the given value
+    pub fn get_kin_partial_at(q3: f64) -> (Kinematics<6, 7>, Matrix3<f64>) {
+        let kin = Self::get_kin();
+        kin.forward_kinematics_partial(q3, 2, &Self::get_r_7t())
     }
 }
 
-impl SetupIk for YumiFixedQ3 {
-    fn setup(&mut self) {
-        let mut q = Vector7::zeros().map(|_: f64| random_angle());
-        q[2] = Self::Q3;
-        (self.r, self.t) = self.kin.forward_kinematics(&q);
-    }
-
-    impl_setup_ik!(YumiFixedQ3);
+impl Ur5 {
+    pub fn get_kin() -> Kinematics<6, 7> {
+        let mut kin = Kinematics::new();
 
-    fn run(&mut self) {
-        (self.q, self.is_ls) = yumi_fixed_q3(&self.r, &self.t);
-    }
+        let ex = Vector3::x();
+        let ey = Vector3::y();
+        let ez = Vector3::z();
 
-    fn error(&self) -> f64 {
-        self.q
-            .iter()
-            .map(|q| {
-                let q_e =
-                    Vector7::from_column_slice(&[q[0], q[1], Self::Q3, q[2], q[3], q[4], q[5]]);
+        kin.h = Matrix3x6::from_columns(&[ez, ey, ey, ey, -ez, ey]);
+        kin.p = Matrix3x7::from_columns(&[
+            0.089159 * ez,
+            0.1358 * ey,
+            -0.1197 * ey + 0.425 * ex,
+            0.3922 * ex,
+            0.093 * ey,
+            -0.0946 * ez,
+            0.0823 * ey,
+        ]);
 
-                let (r_t, t_t) = self.kin.forward_kinematics(&q_e);
-                (r_t - self.r).norm() + (t_t - self.t).norm()
-            })
-            .reduce(f64::min)
-            .unwrap_or(NAN)
+        kin
     }
 }
 
-impl SetupIk for Ur5 {
-    fn setup(&mut self) {
-        let q = Vector6::zeros().map(|_: f64| random_angle());
-        (self.r, self.t) = self.kin.forward_kinematics(&q);
-    }
+impl ThreeParallelBot {
+    pub fn get_kin() -> Kinematics<6, 7> {
+        let mut kin = Kinematics::new();
 
-    impl_setup_ik!(Ur5);
+        let ex = Vector3::x();
+        let ey = Vector3::y();
+        let ez = Vector3::z();
 
-    fn run(&mut self) {
-        (self.q, self.is_ls) = ur5(&self.r, &self.t);
-    }
+        kin.h = Matrix3x6::from_columns(&[ez, ex, ex, ex, ez, ex]);
+        kin.p = Matrix3x7::from_columns(&[ez, ey, ey, ey, ey, ey + ex, ex]);
 
-    fn error(&self) -> f64 {
-        self.q
-            .iter()
-            .map(|q| calculate_ik_error(&self.kin, &self.r, &self.t, q))
-            .reduce(f64::min)
-            .unwrap_or(NAN)
+        kin
     }
 }
 
-impl SetupIk for ThreeParallelBot {
-    fn setup(&mut self) {
-        let q = Vector6::zeros().map(|_: f64| random_angle());
-        (self.r, self.t) = self.kin.forward_kinematics(&q);
-    }
+impl TwoParallelBot {
+    pub fn get_kin() -> Kinematics<6, 7> {
+        let mut kin = Kinematics::new();
 
-    impl_setup_ik!(ThreeParallelBot);
+        let ex = Vector3::x();
+        let ey = Vector3::y();
+        let ez = Vector3::z();
 
-    fn run(&mut self) {
-        (self.q, self.is_ls) = three_parallel_bot(&self.r, &self.t);
-    }
+        let es = (ex + ez).normalize();
 
-    fn error(&self) -> f64 {
-        self.q
-            .iter()
-            .map(|q| calculate_ik_error(&self.kin, &self.r, &self.t, q))
-            .reduce(f64::min)
-            .unwrap_or(NAN)
+        kin.h = Matrix3x6::from_columns(&[ez, ex, ex, ez, ex, es]);
+        kin.p = Matrix3x7::from_columns(&[ez, ey, ey, ey, ey, ey, ez]);
+
+        kin
     }
 }
 
-impl SetupIk for TwoParallelBot {
-    fn setup(&mut self) {
-        let q = Vector6::zeros().map(|_: f64| random_angle());
-        (self.r, self.t) = self.kin.forward_kinematics(&q);
-    }
+impl SphericalBot {
+    pub fn get_kin() -> Kinematics<6, 7> {
+        let mut kin = Kinematics::new();
 
-    impl_setup_ik!(TwoParallelBot);
+        let zv = Vector3::zeros();
+        let ex = Vector3::x();
+        let ey = Vector3::y();
+        let ez = Vector3::z();
 
-    fn run(&mut self) {
-        (self.q, self.is_ls) = two_parallel_bot(&self.r, &self.t);
-    }
+        kin.h = Matrix3x6::from_columns(&[ey, ez, ey, ex, ey, ex]);
+        kin.p = Matrix3x7::from_columns(&[zv, ez + ex, ez + ex, ez + ex, zv, zv, ex]);
 
-    fn error(&self) -> f64 {
-        self.q
-            .iter()
-            .map(|q| calculate_ik_error(&self.kin, &self.r, &self.t, q))
-            .reduce(f64::min)
-            .unwrap_or(NAN)
+        kin
     }
 }
 
-impl SetupIk for SphericalBot {
-    fn setup(&mut self) {
-        let q = Vector6::zeros().map(|_: f64| random_angle());
-        (self.r, self.t) = self.kin.forward_kinematics(&q);
-    }
+impl EpsonLs6 {
+    pub fn get_kin() -> Kinematics<4, 5> {
+        let zv = Vector3::zeros();
+        let ex = Vector3::x();
+        let ez = Vector3::z();
 
-    impl_setup_ik!(SphericalBot);
+        let mut kin = Kinematics::new().with_joint_types([
+            JointType::Revolute,
+            JointType::Revolute,
+            JointType::Prismatic,
+            JointType::Revolute,
+        ]);
 
-    fn run(&mut self) {
-        (self.q, self.is_ls) = spherical_bot(&self.r, &self.t);
-    }
+        kin.h = Matrix3x4::from_columns(&[ez, ez, -ez, ez]);
+        kin.p = Matrix3x5::from_columns(&[zv, 0.325 * ex, 0.275 * ex, zv, zv]);
 
-    fn error(&self) -> f64 {
-        self.q
-            .iter()
-            .map(|q| calculate_ik_error(&self.kin, &self.r, &self.t, q))
-            .reduce(f64::min)
-            .unwrap_or(NAN)
+        kin
     }
 }
-
-// Do setup static as a macro to avoid repition
-macro_rules! impl_setup_static {
-    ($name:ident, $long_name:expr) => {
-        impl SetupStatic for $name {
-            fn new() -> Self {
-                Self {
-                    kin: Self::get_kin(),
-                    r: Matrix3::zeros(),
-                    t: Vector3::zeros(),
-
-                    q: Vec::new(),
-                    is_ls: Vec::new(),
-                }
-            }
-
-            fn name() -> &'static str {
-                $long_name
-            }
-        }
-    };
-}
-
-// Implement static setup for all the robots
-impl_setup_static!(Irb6640, "IRB 6640");
-impl_setup_static!(KukaR800FixedQ3, "KUKA R800 Fixed Q3");
-impl_setup_static!(RrcFixedQ6, "RRC Fixed Q6");
-impl_setup_static!(YumiFixedQ3, "Yumi Fixed Q3");
-impl_setup_static!(Ur5, "UR5");
-impl_setup_static!(ThreeParallelBot, "Three Parallel Bot");
-impl_setup_static!(TwoParallelBot, "Two Parallel Bot");
-impl_setup_static!(SphericalBot, "Spherical Bot");
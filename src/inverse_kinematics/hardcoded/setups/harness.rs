@@ -0,0 +1,476 @@
+//! The `SetupIk` harness for the hardcoded robots, used by the tests and benchmarks
+
+use {
+    super::{
+        FanucLrMate200id, Irb120, Irb1200, Irb2600, Irb4600, Irb6640, KinovaGen3FixedQ3,
+        KukaR800FixedQ3, MotomanGp8, PandaFixedQ4, RrcFixedQ6, SawyerFixedQ3, SphericalBot,
+        ThreeParallelBot, TwoParallelBot, Ur5, YumiFixedQ3,
+    },
+    crate::{
+        config::SolverConfig,
+        error::{parse_values, IkGeoError},
+        inverse_kinematics::{
+            auxiliary::Kinematics,
+            hardcoded::{
+                fanuc_lr_mate_200id, irb120, irb1200, irb2600, irb4600, irb6640,
+                kinova_gen3_fixed_q3, kuka_r800_fixed_q3, motoman_gp8, panda_fixed_q4,
+                rrc_fixed_q6, sawyer_fixed_q3, spherical_bot, three_parallel_bot, two_parallel_bot,
+                ur5, yumi_fixed_q3,
+            },
+            setups::{ik_write_output, SetupIk, SetupStatic},
+        },
+        solutionset::{IkSolution, IkSolutionSet, OutputFormat},
+        subproblems::auxiliary::random_angle,
+    },
+    nalgebra::{Matrix3, SVector, Vector3, Vector6},
+    rand::RngCore,
+};
+
+type Vector7 = SVector<f64, 7>;
+
+/// The smallest pose error of `solutions` to the chain of `kin` with `joint` locked at `q`
+fn locked_joint_error(
+    kin: &Kinematics<7, 8>,
+    joint: usize,
+    q: f64,
+    solutions: &IkSolutionSet,
+    r: &Matrix3<f64>,
+    t: &Vector3<f64>,
+) -> f64 {
+    let Ok(locked) = kin.lock_joint(joint, q) else {
+        return f64::NAN;
+    };
+    solutions
+        .iter()
+        .map(|IkSolution { q, .. }| {
+            let (r_t, t_t) = kin.forward_kinematics(&locked.expand(q));
+            (r_t - r).norm() + (t_t - t).norm()
+        })
+        .reduce(f64::min)
+        .unwrap_or(f64::NAN)
+}
+
+/// Parses a pose from 12 comma separated values: the rotation matrix in row-major order
+/// followed by the translation
+pub fn hardcoded_setup_from_string(raw: &str) -> Result<(Matrix3<f64>, Vector3<f64>), IkGeoError> {
+    let data = parse_values(raw, 12)?;
+
+    let r = Matrix3::new(
+        data[0], data[1], data[2], data[3], data[4], data[5], data[6], data[7], data[8],
+    );
+
+    let t = Vector3::new(data[9], data[10], data[11]);
+
+    Ok((r, t))
+}
+
+// Most of the implementations in SetupIk are the same, so we can use a macro to generate them.
+macro_rules! impl_setup_ik {
+    // Generate the function setup_from_str, write_output,ls_count, solution_count, name, and debug
+    ($name:ident) => {
+        fn setup_from_str(&mut self, raw: &str) -> Result<(), IkGeoError> {
+            (self.r, self.t) = hardcoded_setup_from_string(raw)?;
+            Ok(())
+        }
+
+        fn write_output(&self, format: OutputFormat) -> Vec<u8> {
+            ik_write_output(&self.solutions, format)
+        }
+
+        fn ls_count(&self) -> usize {
+            self.solutions.iter().filter(|s| s.is_ls).count()
+        }
+
+        fn solution_count(&self) -> usize {
+            self.solutions.len()
+        }
+
+        fn name(&self) -> &'static str {
+            <$name as SetupStatic>::name()
+        }
+
+        fn debug(&self, i: usize) {
+            println!("{i}{}{}", self.r, self.t);
+        }
+    };
+}
+
+impl SetupIk for Irb120 {
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
+        let q = Vector6::zeros().map(|_: f64| random_angle(rng));
+        (self.r, self.t) = self.kin.forward_kinematics(&q);
+    }
+
+    impl_setup_ik!(Irb120);
+
+    fn run(&mut self) {
+        self.solutions = irb120(&self.r, &self.t, &SolverConfig::default())
+    }
+
+    fn error(&self) -> f64 {
+        self.solutions
+            .iter()
+            .map(|solution| solution.residual(&self.kin, &self.r, &self.t))
+            .reduce(f64::min)
+            .unwrap_or(f64::NAN)
+    }
+}
+
+impl SetupIk for Irb1200 {
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
+        let q = Vector6::zeros().map(|_: f64| random_angle(rng));
+        (self.r, self.t) = self.kin.forward_kinematics(&q);
+    }
+
+    impl_setup_ik!(Irb1200);
+
+    fn run(&mut self) {
+        self.solutions = irb1200(&self.r, &self.t, &SolverConfig::default())
+    }
+
+    fn error(&self) -> f64 {
+        self.solutions
+            .iter()
+            .map(|solution| solution.residual(&self.kin, &self.r, &self.t))
+            .reduce(f64::min)
+            .unwrap_or(f64::NAN)
+    }
+}
+
+impl SetupIk for Irb2600 {
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
+        let q = Vector6::zeros().map(|_: f64| random_angle(rng));
+        (self.r, self.t) = self.kin.forward_kinematics(&q);
+    }
+
+    impl_setup_ik!(Irb2600);
+
+    fn run(&mut self) {
+        self.solutions = irb2600(&self.r, &self.t, &SolverConfig::default())
+    }
+
+    fn error(&self) -> f64 {
+        self.solutions
+            .iter()
+            .map(|solution| solution.residual(&self.kin, &self.r, &self.t))
+            .reduce(f64::min)
+            .unwrap_or(f64::NAN)
+    }
+}
+
+impl SetupIk for Irb4600 {
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
+        let q = Vector6::zeros().map(|_: f64| random_angle(rng));
+        (self.r, self.t) = self.kin.forward_kinematics(&q);
+    }
+
+    impl_setup_ik!(Irb4600);
+
+    fn run(&mut self) {
+        self.solutions = irb4600(&self.r, &self.t, &SolverConfig::default())
+    }
+
+    fn error(&self) -> f64 {
+        self.solutions
+            .iter()
+            .map(|solution| solution.residual(&self.kin, &self.r, &self.t))
+            .reduce(f64::min)
+            .unwrap_or(f64::NAN)
+    }
+}
+
+impl SetupIk for Irb6640 {
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
+        let q = Vector6::zeros().map(|_: f64| random_angle(rng));
+        (self.r, self.t) = self.kin.forward_kinematics(&q);
+    }
+
+    impl_setup_ik!(Irb6640);
+
+    fn run(&mut self) {
+        self.solutions = irb6640(&self.r, &self.t, &SolverConfig::default())
+    }
+
+    fn error(&self) -> f64 {
+        self.solutions
+            .iter()
+            .map(|solution| solution.residual(&self.kin, &self.r, &self.t))
+            .reduce(f64::min)
+            .unwrap_or(f64::NAN)
+    }
+}
+
+impl SetupIk for FanucLrMate200id {
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
+        let q = Vector6::zeros().map(|_: f64| random_angle(rng));
+        (self.r, self.t) = self.kin.forward_kinematics(&q);
+    }
+
+    impl_setup_ik!(FanucLrMate200id);
+
+    fn run(&mut self) {
+        self.solutions = fanuc_lr_mate_200id(&self.r, &self.t, &SolverConfig::default())
+    }
+
+    fn error(&self) -> f64 {
+        self.solutions
+            .iter()
+            .map(|solution| solution.residual(&self.kin, &self.r, &self.t))
+            .reduce(f64::min)
+            .unwrap_or(f64::NAN)
+    }
+}
+
+impl SetupIk for MotomanGp8 {
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
+        let q = Vector6::zeros().map(|_: f64| random_angle(rng));
+        (self.r, self.t) = self.kin.forward_kinematics(&q);
+    }
+
+    impl_setup_ik!(MotomanGp8);
+
+    fn run(&mut self) {
+        self.solutions = motoman_gp8(&self.r, &self.t, &SolverConfig::default())
+    }
+
+    fn error(&self) -> f64 {
+        self.solutions
+            .iter()
+            .map(|solution| solution.residual(&self.kin, &self.r, &self.t))
+            .reduce(f64::min)
+            .unwrap_or(f64::NAN)
+    }
+}
+
+impl SetupIk for KukaR800FixedQ3 {
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
+        let mut q = Vector7::zeros().map(|_: f64| random_angle(rng));
+        q[2] = Self::Q3;
+        (self.r, self.t) = self.kin.forward_kinematics(&q);
+    }
+
+    impl_setup_ik!(KukaR800FixedQ3);
+
+    fn run(&mut self) {
+        self.solutions = kuka_r800_fixed_q3(&self.r, &self.t, &SolverConfig::default());
+    }
+
+    fn error(&self) -> f64 {
+        locked_joint_error(&self.kin, 2, Self::Q3, &self.solutions, &self.r, &self.t)
+    }
+}
+
+impl SetupIk for RrcFixedQ6 {
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
+        let mut q = Vector7::zeros().map(|_: f64| random_angle(rng));
+        q[5] = Self::Q6;
+        (self.r, self.t) = self.kin.forward_kinematics(&q);
+    }
+
+    impl_setup_ik!(RrcFixedQ6);
+
+    fn run(&mut self) {
+        self.solutions = rrc_fixed_q6(&self.r, &self.t, &SolverConfig::default())
+            .expect("the default tolerances are valid");
+    }
+
+    fn error(&self) -> f64 {
+        locked_joint_error(&self.kin, 5, Self::Q6, &self.solutions, &self.r, &self.t)
+    }
+}
+
+impl SetupIk for YumiFixedQ3 {
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
+        let mut q = Vector7::zeros().map(|_: f64| random_angle(rng));
+        q[2] = Self::Q3;
+        (self.r, self.t) = self.kin.forward_kinematics(&q);
+    }
+
+    impl_setup_ik!(YumiFixedQ3);
+
+    fn run(&mut self) {
+        self.solutions = yumi_fixed_q3(&self.r, &self.t, &SolverConfig::default());
+    }
+
+    fn error(&self) -> f64 {
+        locked_joint_error(&self.kin, 2, Self::Q3, &self.solutions, &self.r, &self.t)
+    }
+}
+
+impl SetupIk for PandaFixedQ4 {
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
+        let mut q = Vector7::zeros().map(|_: f64| random_angle(rng));
+        q[3] = Self::Q4;
+        (self.r, self.t) = self.kin.forward_kinematics(&q);
+    }
+
+    impl_setup_ik!(PandaFixedQ4);
+
+    fn run(&mut self) {
+        self.solutions = panda_fixed_q4(&self.r, &self.t, &SolverConfig::default());
+    }
+
+    fn error(&self) -> f64 {
+        locked_joint_error(&self.kin, 3, Self::Q4, &self.solutions, &self.r, &self.t)
+    }
+}
+
+impl SetupIk for KinovaGen3FixedQ3 {
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
+        let mut q = Vector7::zeros().map(|_: f64| random_angle(rng));
+        q[2] = Self::Q3;
+        (self.r, self.t) = self.kin.forward_kinematics(&q);
+    }
+
+    impl_setup_ik!(KinovaGen3FixedQ3);
+
+    fn run(&mut self) {
+        self.solutions = kinova_gen3_fixed_q3(&self.r, &self.t, &SolverConfig::default());
+    }
+
+    fn error(&self) -> f64 {
+        locked_joint_error(&self.kin, 2, Self::Q3, &self.solutions, &self.r, &self.t)
+    }
+}
+
+impl SetupIk for SawyerFixedQ3 {
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
+        let mut q = Vector7::zeros().map(|_: f64| random_angle(rng));
+        q[2] = Self::Q3;
+        (self.r, self.t) = self.kin.forward_kinematics(&q);
+    }
+
+    impl_setup_ik!(SawyerFixedQ3);
+
+    fn run(&mut self) {
+        self.solutions = sawyer_fixed_q3(&self.r, &self.t, &SolverConfig::default());
+    }
+
+    fn error(&self) -> f64 {
+        locked_joint_error(&self.kin, 2, Self::Q3, &self.solutions, &self.r, &self.t)
+    }
+}
+
+impl SetupIk for Ur5 {
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
+        let q = Vector6::zeros().map(|_: f64| random_angle(rng));
+        (self.r, self.t) = self.kin.forward_kinematics(&q);
+    }
+
+    impl_setup_ik!(Ur5);
+
+    fn run(&mut self) {
+        self.solutions = ur5(&self.r, &self.t, &SolverConfig::default());
+    }
+
+    fn error(&self) -> f64 {
+        self.solutions
+            .iter()
+            .map(|solution| solution.residual(&self.kin, &self.r, &self.t))
+            .reduce(f64::min)
+            .unwrap_or(f64::NAN)
+    }
+}
+
+impl SetupIk for ThreeParallelBot {
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
+        let q = Vector6::zeros().map(|_: f64| random_angle(rng));
+        (self.r, self.t) = self.kin.forward_kinematics(&q);
+    }
+
+    impl_setup_ik!(ThreeParallelBot);
+
+    fn run(&mut self) {
+        self.solutions = three_parallel_bot(&self.r, &self.t, &SolverConfig::default());
+    }
+
+    fn error(&self) -> f64 {
+        self.solutions
+            .iter()
+            .map(|solution| solution.residual(&self.kin, &self.r, &self.t))
+            .reduce(f64::min)
+            .unwrap_or(f64::NAN)
+    }
+}
+
+impl SetupIk for TwoParallelBot {
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
+        let q = Vector6::zeros().map(|_: f64| random_angle(rng));
+        (self.r, self.t) = self.kin.forward_kinematics(&q);
+    }
+
+    impl_setup_ik!(TwoParallelBot);
+
+    fn run(&mut self) {
+        self.solutions = two_parallel_bot(&self.r, &self.t, &SolverConfig::default());
+    }
+
+    fn error(&self) -> f64 {
+        self.solutions
+            .iter()
+            .map(|solution| solution.residual(&self.kin, &self.r, &self.t))
+            .reduce(f64::min)
+            .unwrap_or(f64::NAN)
+    }
+}
+
+impl SetupIk for SphericalBot {
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
+        let q = Vector6::zeros().map(|_: f64| random_angle(rng));
+        (self.r, self.t) = self.kin.forward_kinematics(&q);
+    }
+
+    impl_setup_ik!(SphericalBot);
+
+    fn run(&mut self) {
+        self.solutions = spherical_bot(&self.r, &self.t, &SolverConfig::default());
+    }
+
+    fn error(&self) -> f64 {
+        self.solutions
+            .iter()
+            .map(|solution| solution.residual(&self.kin, &self.r, &self.t))
+            .reduce(f64::min)
+            .unwrap_or(f64::NAN)
+    }
+}
+
+// Do setup static as a macro to avoid repition
+macro_rules! impl_setup_static {
+    ($name:ident, $long_name:expr) => {
+        impl SetupStatic for $name {
+            fn new() -> Self {
+                Self {
+                    kin: Self::get_kin(),
+                    r: Matrix3::zeros(),
+                    t: Vector3::zeros(),
+
+                    solutions: IkSolutionSet::new(),
+                }
+            }
+
+            fn name() -> &'static str {
+                $long_name
+            }
+        }
+    };
+}
+
+// Implement static setup for all the robots
+impl_setup_static!(Irb120, "IRB 120");
+impl_setup_static!(Irb1200, "IRB 1200");
+impl_setup_static!(Irb2600, "IRB 2600");
+impl_setup_static!(Irb4600, "IRB 4600");
+impl_setup_static!(Irb6640, "IRB 6640");
+impl_setup_static!(FanucLrMate200id, "FANUC LR Mate 200iD");
+impl_setup_static!(MotomanGp8, "Motoman GP8");
+impl_setup_static!(KukaR800FixedQ3, "KUKA R800 Fixed Q3");
+impl_setup_static!(RrcFixedQ6, "RRC Fixed Q6");
+impl_setup_static!(YumiFixedQ3, "Yumi Fixed Q3");
+impl_setup_static!(PandaFixedQ4, "Panda Fixed Q4");
+impl_setup_static!(KinovaGen3FixedQ3, "Kinova Gen3 Fixed Q3");
+impl_setup_static!(SawyerFixedQ3, "Sawyer Fixed Q3");
+impl_setup_static!(Ur5, "UR5");
+impl_setup_static!(ThreeParallelBot, "Three Parallel Bot");
+impl_setup_static!(TwoParallelBot, "Two Parallel Bot");
+impl_setup_static!(SphericalBot, "Spherical Bot");
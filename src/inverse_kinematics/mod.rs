@@ -1,27 +1,133 @@
 pub mod auxiliary;
+#[cfg(feature = "simd")]
+pub mod batch;
+pub mod chains;
+pub mod family;
 pub mod hardcoded;
+pub mod opw;
+#[cfg(feature = "std")]
 pub mod setups;
 
+mod search;
+
 use {
     crate::{
-        solutionset::SolutionSet4,
+        config::SolverConfig,
+        labels::BranchLabel,
+        solutionset::{merge_duplicates, IkSolution, IkSolutionSet, SolutionBuffer, SolutionSet4},
         subproblems::{
-            auxiliary::rot, subproblem1, subproblem2, subproblem3, subproblem4, subproblem5,
-            subproblem6,
+            auxiliary::rot, subproblem1_with_tolerance, subproblem2_with_tolerance, subproblem3,
+            subproblem4, subproblem5, subproblem6,
         },
     },
-    auxiliary::{search_1d, search_2d, wrap_to_pi, Kinematics},
+    auxiliary::{search_1d_with, search_2d_grid_with, wrap_to_pi, Kinematics, SolverScratch},
     nalgebra::{Matrix3, Matrix4, Vector3, Vector4, Vector6},
-    std::f64::{consts::PI, INFINITY, NAN},
+    core::f64::consts::PI,
 };
 
+#[cfg(feature = "nlopt")]
+use auxiliary::search_2d;
+
+/// Fill in the residual of each solution against the pose the chain was asked to reach, merge
+/// duplicates and fill in the manipulability and branch labels if `config.annotate` asks for them
+fn annotated(
+    mut solutions: IkSolutionSet,
+    kin: &Kinematics<6, 7>,
+    r: &Matrix3<f64>,
+    t: &Vector3<f64>,
+    config: &SolverConfig,
+) -> IkSolutionSet {
+    solutions.set_errors(|q| {
+        let (r_q, t_q) = kin.chain_forward_kinematics(q);
+        config.pose_error(&r_q, &t_q, r, t)
+    });
+    solutions.dedup(config.dedup_tolerance);
+    if config.annotate {
+        solutions.set_manipulability(|q| kin.manipulability(q));
+        solutions.set_labels(|q| BranchLabel::of(kin, q));
+    }
+    trace_event!(
+        DEBUG,
+        solutions = solutions.len(),
+        least_squares = solutions.iter().filter(|solution| solution.is_ls).count(),
+        "solved"
+    );
+    solutions
+}
+
+/// Add the next branch of a closed-form solver to `solutions`
+fn push_branch(solutions: &mut SolutionBuffer, q: Vector6<f64>, is_ls: bool) {
+    let branch = solutions.len();
+    solutions.push(IkSolution::from_branch(q, is_ls, branch));
+}
+
+/// `annotated` for the solutions of a closed-form solver, without allocating
+fn annotate_buffer(
+    solutions: &mut SolutionBuffer,
+    kin: &Kinematics<6, 7>,
+    r: &Matrix3<f64>,
+    t: &Vector3<f64>,
+    config: &SolverConfig,
+) {
+    for solution in solutions.iter_mut() {
+        let (r_q, t_q) = kin.chain_forward_kinematics(&solution.q);
+        solution.error = config.pose_error(&r_q, &t_q, r, t);
+    }
+    let kept = merge_duplicates(solutions, config.dedup_tolerance);
+    solutions.truncate(kept);
+    for solution in solutions.iter_mut().filter(|_| config.annotate) {
+        // The product of the singular values of the square Jacobian is the magnitude of its
+        // determinant, which unlike the SVD of `Kinematics::manipulability` needs no allocation
+        solution.manipulability = Some(kin.jacobian(&solution.q).determinant().abs());
+        solution.label = BranchLabel::of(kin, &solution.q);
+    }
+    trace_event!(
+        DEBUG,
+        solutions = solutions.len(),
+        least_squares = solutions.iter().filter(|solution| solution.is_ls).count(),
+        "solved"
+    );
+}
+
+/// The branches of a closed-form solver computed elsewhere, e.g. on a GPU, into `solutions`,
+/// which is cleared first, annotated as the solver would annotate its own
+#[cfg(feature = "gpu")]
+pub(crate) fn branches_into(
+    branches: impl IntoIterator<Item = (Vector6<f64>, bool)>,
+    r_0t: &Matrix3<f64>,
+    p_0t: &Vector3<f64>,
+    kin: &Kinematics<6, 7>,
+    config: &SolverConfig,
+    solutions: &mut SolutionBuffer,
+) {
+    solutions.clear();
+    for (q, is_ls) in branches {
+        push_branch(solutions, q, is_ls);
+    }
+    annotate_buffer(solutions, kin, r_0t, p_0t, config);
+}
+
 pub fn spherical_two_parallel(
     r_0t: &Matrix3<f64>,
     p_0t: &Vector3<f64>,
     kin: &Kinematics<6, 7>,
-) -> (Vec<Vector6<f64>>, Vec<bool>) {
-    let mut q = Vec::with_capacity(8);
-    let mut is_ls = Vec::with_capacity(8);
+    config: &SolverConfig,
+) -> IkSolutionSet {
+    let mut solutions = SolutionBuffer::new();
+    spherical_two_parallel_into(r_0t, p_0t, kin, config, &mut solutions);
+    solutions.into_iter().collect()
+}
+
+/// `spherical_two_parallel` into `solutions`, which is cleared first, without allocating
+pub fn spherical_two_parallel_into(
+    r_0t: &Matrix3<f64>,
+    p_0t: &Vector3<f64>,
+    kin: &Kinematics<6, 7>,
+    config: &SolverConfig,
+    solutions: &mut SolutionBuffer,
+) {
+    trace_span!(DEBUG, "spherical_two_parallel");
+    solutions.clear();
 
     let (t1, q1_is_ls) = subproblem4(
         &(kin.h.column(1).into()),
@@ -41,12 +147,13 @@ pub fn spherical_two_parallel(
         );
 
         for q3 in t3.get_all() {
-            let (q2, q2_is_ls) = subproblem1(
+            let (q2, q2_is_ls) = subproblem1_with_tolerance(
                 &(-kin.p.column(2) - rot(&kin.h.column(2).into(), q3) * kin.p.column(3)),
                 &(rot(&(-kin.h.column(0)), q1)
                     * (-p_0t + r_0t * kin.p.column(6) + kin.p.column(0))
                     + kin.p.column(1)),
                 &(kin.h.column(1).into()),
+                config.ls_tolerance,
             );
 
             let r_36 = rot(&(-kin.h.column(2)), q3)
@@ -62,34 +169,53 @@ pub fn spherical_two_parallel(
             );
 
             for q5 in t5.get_all() {
-                let (q4, q4_is_ls) = subproblem1(
+                let (q4, q4_is_ls) = subproblem1_with_tolerance(
                     &(rot(&(kin.h.column(4).into()), q5) * kin.h.column(5)),
                     &(r_36 * kin.h.column(5)),
                     &(kin.h.column(3).into()),
+                    config.ls_tolerance,
                 );
 
-                let (q6, q6_is_ls) = subproblem1(
+                let (q6, q6_is_ls) = subproblem1_with_tolerance(
                     &(rot(&(-kin.h.column(4)), q5) * kin.h.column(3)),
                     &(r_36.transpose() * kin.h.column(3)),
                     &(-kin.h.column(5)),
+                    config.ls_tolerance,
                 );
 
-                q.push(Vector6::new(q1, q2, q3, q4, q5, q6));
-                is_ls.push(q1_is_ls || q2_is_ls || q3_is_ls || q4_is_ls || q5_is_ls || q6_is_ls)
+                push_branch(
+                    solutions,
+                    Vector6::new(q1, q2, q3, q4, q5, q6),
+                    q1_is_ls || q2_is_ls || q3_is_ls || q4_is_ls || q5_is_ls || q6_is_ls,
+                );
             }
         }
     }
 
-    (q, is_ls)
+    annotate_buffer(solutions, kin, r_0t, p_0t, config);
 }
 
 pub fn spherical_two_intersecting(
     r_0t: &Matrix3<f64>,
     p_0t: &Vector3<f64>,
     kin: &Kinematics<6, 7>,
-) -> (Vec<Vector6<f64>>, Vec<bool>) {
-    let mut q = Vec::with_capacity(8);
-    let mut is_ls = Vec::with_capacity(8);
+    config: &SolverConfig,
+) -> IkSolutionSet {
+    let mut solutions = SolutionBuffer::new();
+    spherical_two_intersecting_into(r_0t, p_0t, kin, config, &mut solutions);
+    solutions.into_iter().collect()
+}
+
+/// `spherical_two_intersecting` into `solutions`, which is cleared first, without allocating
+pub fn spherical_two_intersecting_into(
+    r_0t: &Matrix3<f64>,
+    p_0t: &Vector3<f64>,
+    kin: &Kinematics<6, 7>,
+    config: &SolverConfig,
+    solutions: &mut SolutionBuffer,
+) {
+    trace_span!(DEBUG, "spherical_two_intersecting");
+    solutions.clear();
 
     let p_16 = p_0t - r_0t * kin.p.column(6) - kin.p.column(0);
 
@@ -101,11 +227,12 @@ pub fn spherical_two_intersecting(
     );
 
     for q3 in t3.get_all() {
-        let (t12, t12_is_ls) = subproblem2(
+        let (t12, t12_is_ls) = subproblem2_with_tolerance(
             &p_16,
             &(kin.p.column(2) + rot(&kin.h.column(2).into(), q3) * kin.p.column(3)),
             &-kin.h.column(0),
             &kin.h.column(1).into(),
+            config.subproblem2_ls_tolerance,
         );
 
         for (q1, q2) in t12.get_all() {
@@ -122,34 +249,53 @@ pub fn spherical_two_intersecting(
             );
 
             for q5 in t5.get_all() {
-                let (q4, q4_is_ls) = subproblem1(
+                let (q4, q4_is_ls) = subproblem1_with_tolerance(
                     &(rot(&kin.h.column(4).into(), q5) * kin.h.column(5)),
                     &(r_36 * kin.h.column(5)),
                     &kin.h.column(3).into(),
+                    config.ls_tolerance,
                 );
 
-                let (q6, q6_is_ls) = subproblem1(
+                let (q6, q6_is_ls) = subproblem1_with_tolerance(
                     &(rot(&-kin.h.column(4), q5) * kin.h.column(3)),
                     &(r_36.transpose() * kin.h.column(3)),
                     &-kin.h.column(5),
+                    config.ls_tolerance,
                 );
 
-                q.push(Vector6::new(q1, q2, q3, q4, q5, q6));
-                is_ls.push(t3_is_ls || t12_is_ls || q5_is_ls || q4_is_ls || q6_is_ls);
+                push_branch(
+                    solutions,
+                    Vector6::new(q1, q2, q3, q4, q5, q6),
+                    t3_is_ls || t12_is_ls || q5_is_ls || q4_is_ls || q6_is_ls,
+                );
             }
         }
     }
 
-    (q, is_ls)
+    annotate_buffer(solutions, kin, r_0t, p_0t, config);
 }
 
 pub fn spherical(
     r_06: &Matrix3<f64>,
     p_0t: &Vector3<f64>,
     kin: &Kinematics<6, 7>,
-) -> (Vec<Vector6<f64>>, Vec<bool>) {
-    let mut q = Vec::with_capacity(8);
-    let mut is_ls = Vec::with_capacity(8);
+    config: &SolverConfig,
+) -> IkSolutionSet {
+    let mut solutions = SolutionBuffer::new();
+    spherical_into(r_06, p_0t, kin, config, &mut solutions);
+    solutions.into_iter().collect()
+}
+
+/// `spherical` into `solutions`, which is cleared first, without allocating
+pub fn spherical_into(
+    r_06: &Matrix3<f64>,
+    p_0t: &Vector3<f64>,
+    kin: &Kinematics<6, 7>,
+    config: &SolverConfig,
+    solutions: &mut SolutionBuffer,
+) {
+    trace_span!(DEBUG, "spherical");
+    solutions.clear();
 
     let p_16 = p_0t - kin.p.column(0) - r_06 * kin.p.column(6);
 
@@ -177,33 +323,52 @@ pub fn spherical(
         );
 
         for q5 in t5.get_all() {
-            let (q4, q4_is_ls) = subproblem1(
+            let (q4, q4_is_ls) = subproblem1_with_tolerance(
                 &(rot(&kin.h.column(4).into(), q5) * kin.h.column(5)),
                 &(r_36 * kin.h.column(5)),
                 &kin.h.column(3).into(),
+                config.ls_tolerance,
             );
 
-            let (q6, q6_is_ls) = subproblem1(
+            let (q6, q6_is_ls) = subproblem1_with_tolerance(
                 &(rot(&-kin.h.column(4), q5) * kin.h.column(3)),
                 &(r_36.transpose() * kin.h.column(3)),
                 &-kin.h.column(5),
+                config.ls_tolerance,
             );
 
-            q.push(Vector6::new(q1, q2, q3, q4, q5, q6));
-            is_ls.push(q5_is_ls || q4_is_ls || q6_is_ls);
+            push_branch(
+                solutions,
+                Vector6::new(q1, q2, q3, q4, q5, q6),
+                q5_is_ls || q4_is_ls || q6_is_ls,
+            );
         }
     }
 
-    (q, is_ls)
+    annotate_buffer(solutions, kin, r_06, p_0t, config);
 }
 
 pub fn three_parallel_two_intersecting(
     r_06: &Matrix3<f64>,
     p_0t: &Vector3<f64>,
     kin: &Kinematics<6, 7>,
-) -> (Vec<Vector6<f64>>, Vec<bool>) {
-    let mut q = Vec::with_capacity(8);
-    let mut is_ls = Vec::with_capacity(8);
+    config: &SolverConfig,
+) -> IkSolutionSet {
+    let mut solutions = SolutionBuffer::new();
+    three_parallel_two_intersecting_into(r_06, p_0t, kin, config, &mut solutions);
+    solutions.into_iter().collect()
+}
+
+/// `three_parallel_two_intersecting` into `solutions`, which is cleared first, without allocating
+pub fn three_parallel_two_intersecting_into(
+    r_06: &Matrix3<f64>,
+    p_0t: &Vector3<f64>,
+    kin: &Kinematics<6, 7>,
+    config: &SolverConfig,
+    solutions: &mut SolutionBuffer,
+) {
+    trace_span!(DEBUG, "three_parallel_two_intersecting");
+    solutions.clear();
 
     let sum_p_2_5 = kin.p.fixed_columns::<4>(1).column_sum();
     let p_16 = p_0t - kin.p.column(0) - r_06 * kin.p.column(6);
@@ -226,10 +391,11 @@ pub fn three_parallel_two_intersecting(
         );
 
         for q5 in theta5.get_all() {
-            let (theta14, theta_14_is_ls) = subproblem1(
+            let (theta14, theta_14_is_ls) = subproblem1_with_tolerance(
                 &(rot(&kin.h.column(4).into(), q5) * kin.h.column(5)),
                 &(r_01.transpose() * r_06 * kin.h.column(5)),
                 &kin.h.column(1).into(),
+                config.ls_tolerance,
             );
 
             let r_01 = rot(&kin.h.column(0).into(), q1);
@@ -247,15 +413,16 @@ pub fn three_parallel_two_intersecting(
             let (theta_3, theta_3_is_ls) = subproblem3(&-p_34, p_23, &kin.h.column(1).into(), d);
 
             for q3 in theta_3.get_all() {
-                let (q2, q2_is_ls) = subproblem1(
+                let (q2, q2_is_ls) = subproblem1_with_tolerance(
                     &(p_23 + rot(&kin.h.column(1).into(), q3) * p_34),
                     &d_inner,
                     &kin.h.column(1).into(),
+                    config.ls_tolerance,
                 );
 
                 let q4 = wrap_to_pi(theta14 - q2 - q3);
 
-                let (q6, q6_is_ls) = subproblem1(
+                let (q6, q6_is_ls) = subproblem1_with_tolerance(
                     &kin.h.column(4).into(),
                     &(r_45.transpose()
                         * r_14.transpose()
@@ -263,10 +430,12 @@ pub fn three_parallel_two_intersecting(
                         * r_06
                         * kin.h.column(4)),
                     &kin.h.column(5).into(),
+                    config.ls_tolerance,
                 );
 
-                q.push(Vector6::new(q1, q2, q3, q4, q5, q6));
-                is_ls.push(
+                push_branch(
+                    solutions,
+                    Vector6::new(q1, q2, q3, q4, q5, q6),
                     theta1_is_ls
                         || theta5_is_ls
                         || theta_14_is_ls
@@ -278,16 +447,30 @@ pub fn three_parallel_two_intersecting(
         }
     }
 
-    (q, is_ls)
+    annotate_buffer(solutions, kin, r_06, p_0t, config);
 }
 
 pub fn three_parallel(
     r_06: &Matrix3<f64>,
     p_0t: &Vector3<f64>,
     kin: &Kinematics<6, 7>,
-) -> (Vec<Vector6<f64>>, Vec<bool>) {
-    let mut q = Vec::with_capacity(8);
-    let mut is_ls = Vec::with_capacity(8);
+    config: &SolverConfig,
+) -> IkSolutionSet {
+    let mut solutions = SolutionBuffer::new();
+    three_parallel_into(r_06, p_0t, kin, config, &mut solutions);
+    solutions.into_iter().collect()
+}
+
+/// `three_parallel` into `solutions`, which is cleared first, without allocating
+pub fn three_parallel_into(
+    r_06: &Matrix3<f64>,
+    p_0t: &Vector3<f64>,
+    kin: &Kinematics<6, 7>,
+    config: &SolverConfig,
+    solutions: &mut SolutionBuffer,
+) {
+    trace_span!(DEBUG, "three_parallel");
+    solutions.clear();
 
     let p_16 = p_0t - kin.p.column(0) - r_06 * kin.p.column(6);
 
@@ -319,15 +502,17 @@ pub fn three_parallel(
         let r_01 = rot(&kin.h.column(0).into(), q1);
         let r_45 = rot(&kin.h.column(4).into(), q5);
 
-        let (theta14, theta14_is_ls) = subproblem1(
+        let (theta14, theta14_is_ls) = subproblem1_with_tolerance(
             &(r_45 * kin.h.column(5)),
             &(r_01.transpose() * r_06 * kin.h.column(5)),
             &kin.h.column(1).into(),
+            config.ls_tolerance,
         );
-        let (q6, q6_is_ls) = subproblem1(
+        let (q6, q6_is_ls) = subproblem1_with_tolerance(
             &(r_45.transpose() * kin.h.column(1)),
             &(r_06.transpose() * r_01 * kin.h.column(1)),
             &-kin.h.column(5),
+            config.ls_tolerance,
         );
 
         let r_14 = rot(&kin.h.column(1).into(), theta14);
@@ -344,28 +529,44 @@ pub fn three_parallel(
         );
 
         for q3 in theta3.get_all() {
-            let (q2, q2_is_ls) = subproblem1(
+            let (q2, q2_is_ls) = subproblem1_with_tolerance(
                 &(kin.p.column(2) + rot(&kin.h.column(1).into(), q3) * kin.p.column(3)).into(),
                 &d_inner.into(),
                 &kin.h.column(1).into(),
+                config.ls_tolerance,
             );
             let q4 = wrap_to_pi(theta14 - q2 - q3);
 
-            q.push(Vector6::new(q1, q2, q3, q4, q5, q6));
-            is_ls.push(theta14_is_ls || theta3_is_ls || q2_is_ls || q6_is_ls);
+            push_branch(
+                solutions,
+                Vector6::new(q1, q2, q3, q4, q5, q6),
+                theta14_is_ls || theta3_is_ls || q2_is_ls || q6_is_ls,
+            );
         }
     }
 
-    (q, is_ls)
+    annotate_buffer(solutions, kin, r_06, p_0t, config);
 }
 
 pub fn two_parallel(
     r_06: &Matrix3<f64>,
     p_0t: &Vector3<f64>,
     kin: &Kinematics<6, 7>,
-) -> (Vec<Vector6<f64>>, Vec<bool>) {
-    let mut q = Vec::with_capacity(8);
-    let mut is_ls = Vec::with_capacity(8);
+    config: &SolverConfig,
+) -> IkSolutionSet {
+    two_parallel_with(r_06, p_0t, kin, config, &mut SolverScratch::new())
+}
+
+/// `two_parallel` keeping the samples of its search in `scratch`
+pub fn two_parallel_with(
+    r_06: &Matrix3<f64>,
+    p_0t: &Vector3<f64>,
+    kin: &Kinematics<6, 7>,
+    config: &SolverConfig,
+    scratch: &mut SolverScratch,
+) -> IkSolutionSet {
+    trace_span!(DEBUG, "two_parallel");
+    let mut solutions = IkSolutionSet::new();
 
     let p_16 = p_0t - kin.p.column(0) - r_06 * kin.p.column(6);
 
@@ -405,7 +606,7 @@ pub fn two_parallel(
     }
 
     let error_given_q1 = |q1: f64| {
-        let mut error = Vector4::from_element(INFINITY);
+        let mut error = Vector4::from_element(f64::INFINITY);
         let r_01 = rot(&kin.h.column(0).into(), q1);
 
         let h1: Vector3<f64> = (kin.h.column(1).transpose() * r_01.transpose() * r_06).transpose();
@@ -434,13 +635,14 @@ pub fn two_parallel(
 
         let t64 = subproblem6(&sp_h, &sp_k, &sp_p, d1, d2);
 
-        for (i, (t6, t4)) in t64.get_all().into_iter().enumerate() {
+        for (i, (t6, t4)) in t64.get_all().enumerate() {
             let r_34 = rot(&kin.h.column(3).into(), t4);
             let r_56 = rot(&kin.h.column(5).into(), t6);
-            let (t23, _) = subproblem1(
+            let (t23, _) = subproblem1_with_tolerance(
                 &(r_34 * kin.h.column(4)),
                 &(r_01.transpose() * r_06 * r_56.transpose() * kin.h.column(4)),
                 &kin.h.column(1).into(),
+                config.ls_tolerance,
             );
             let r_13 = rot(&kin.h.column(1).into(), t23);
 
@@ -456,24 +658,23 @@ pub fn two_parallel(
         error
     };
 
-    let q1_vec = search_1d(error_given_q1, -PI, PI);
-
-    for (q1, i) in q1_vec {
-        let t64 = t64_given_q1(r_06, kin, q1, &p_16).get_all();
+    for &(q1, i) in search_1d_with(error_given_q1, -PI, PI, scratch) {
+        let t64 = t64_given_q1(r_06, kin, q1, &p_16);
         let q6 = t64[i].0;
         let q4 = t64[i].1;
         let r_01 = rot(&kin.h.column(0).into(), q1);
         let r_34 = rot(&kin.h.column(3).into(), q4);
         let r_56 = rot(&kin.h.column(5).into(), q6);
 
-        let (t23, t23_is_ls) = subproblem1(
+        let (t23, t23_is_ls) = subproblem1_with_tolerance(
             &(r_34 * kin.h.column(4)),
             &(r_01.transpose() * r_06 * r_56.transpose() * kin.h.column(4)),
             &kin.h.column(1).into(),
+            config.ls_tolerance,
         );
         let r_13 = rot(&kin.h.column(1).into(), t23);
 
-        let (q2, q2_is_ls) = subproblem1(
+        let (q2, q2_is_ls) = subproblem1_with_tolerance(
             &kin.p.column(2).into(),
             &(r_01.transpose() * p_16
                 - kin.p.column(1)
@@ -481,35 +682,51 @@ pub fn two_parallel(
                 - r_13 * r_34 * kin.p.column(4)
                 - r_01.transpose() * r_06 * r_56.transpose() * kin.p.column(5)),
             &kin.h.column(1).into(),
+            config.ls_tolerance,
         );
 
-        let (q5, q5_is_ls) = subproblem1(
+        let (q5, q5_is_ls) = subproblem1_with_tolerance(
             &(r_34.transpose() * kin.h.column(1)),
             &(r_56 * r_06.transpose() * r_01 * kin.h.column(1)),
             &-kin.h.column(4),
+            config.ls_tolerance,
         );
 
         let q3 = wrap_to_pi(t23 - q2);
 
-        q.push(Vector6::new(q1, q2, q3, q4, q5, q6));
-        is_ls.push(t23_is_ls || q2_is_ls || q5_is_ls);
+        solutions.push(
+            Vector6::new(q1, q2, q3, q4, q5, q6),
+            t23_is_ls || q2_is_ls || q5_is_ls,
+        );
     }
 
-    (q, is_ls)
+    annotated(solutions, kin, r_06, p_0t, config)
 }
 
 pub fn two_intersecting(
     r_06: &Matrix3<f64>,
     p_0t: &Vector3<f64>,
     kin: &Kinematics<6, 7>,
-) -> (Vec<Vector6<f64>>, Vec<bool>) {
-    let mut q = Vec::with_capacity(8);
-    let mut is_ls = Vec::with_capacity(8);
+    config: &SolverConfig,
+) -> IkSolutionSet {
+    two_intersecting_with(r_06, p_0t, kin, config, &mut SolverScratch::new())
+}
+
+/// `two_intersecting` keeping the samples of its search in `scratch`
+pub fn two_intersecting_with(
+    r_06: &Matrix3<f64>,
+    p_0t: &Vector3<f64>,
+    kin: &Kinematics<6, 7>,
+    config: &SolverConfig,
+    scratch: &mut SolverScratch,
+) -> IkSolutionSet {
+    trace_span!(DEBUG, "two_intersecting");
+    let mut solutions = IkSolutionSet::new();
 
     let p_16 = p_0t - kin.p.column(0) - r_06 * kin.p.column(6);
 
     fn q_partial_given_q4(q4: f64, kin: &Kinematics<6, 7>, p_16: &Vector3<f64>) -> Matrix4<f64> {
-        let mut q_partial = Matrix4::from_element(NAN);
+        let mut q_partial = Matrix4::from_element(f64::NAN);
 
         let p_35_3 = kin.p.column(3) + rot(&kin.h.column(3).into(), q4) * kin.p.column(4);
 
@@ -523,7 +740,7 @@ pub fn two_intersecting(
             &kin.h.column(2).into(),
         );
 
-        for (i, (q1, q2, q3)) in t123.get_all().into_iter().enumerate() {
+        for (i, (q1, q2, q3)) in t123.get_all().enumerate() {
             q_partial.set_column(i, &Vector4::new(q1, q2, q3, q4));
         }
 
@@ -531,7 +748,7 @@ pub fn two_intersecting(
     }
 
     let alignment_error_given_q4 = |q4: f64| {
-        let mut error = Vector4::from_element(INFINITY);
+        let mut error = Vector4::from_element(f64::INFINITY);
 
         let p_35_3 = kin.p.column(3) + rot(&kin.h.column(3).into(), q4) * kin.p.column(4);
 
@@ -545,7 +762,7 @@ pub fn two_intersecting(
             &kin.h.column(2).into(),
         );
 
-        for (i, (q1, q2, q3)) in t123.get_all().into_iter().enumerate() {
+        for (i, (q1, q2, q3)) in t123.get_all().enumerate() {
             let r_04 = rot(&kin.h.column(0).into(), q1)
                 * rot(&kin.h.column(1).into(), q2)
                 * rot(&kin.h.column(2).into(), q3)
@@ -558,9 +775,7 @@ pub fn two_intersecting(
         error
     };
 
-    let q4_vec = search_1d(alignment_error_given_q4, -PI, PI);
-
-    for (q4, i) in q4_vec {
+    for &(q4, i) in search_1d_with(alignment_error_given_q4, -PI, PI, scratch) {
         let q_partial: Vector4<f64> = q_partial_given_q4(q4, kin, &p_16).column(i).into();
 
         let r_04 = rot(&kin.h.column(0).into(), q_partial[0])
@@ -568,37 +783,140 @@ pub fn two_intersecting(
             * rot(&kin.h.column(2).into(), q_partial[2])
             * rot(&kin.h.column(3).into(), q_partial[3]);
 
-        let (q5, q5_is_ls) = subproblem1(
+        let (q5, q5_is_ls) = subproblem1_with_tolerance(
             &kin.h.column(5).into(),
             &(r_04.transpose() * r_06 * kin.h.column(5)),
             &kin.h.column(4).into(),
+            config.ls_tolerance,
         );
-        let (q6, q6_is_ls) = subproblem1(
+        let (q6, q6_is_ls) = subproblem1_with_tolerance(
             &kin.h.column(4).into(),
             &(r_06.transpose() * r_04 * kin.h.column(4)),
             &-kin.h.column(5),
+            config.ls_tolerance,
         );
 
-        q.push(Vector6::new(
-            q_partial[0],
-            q_partial[1],
-            q_partial[2],
-            q_partial[3],
-            q5,
-            q6,
-        ));
-
-        is_ls.push(q5_is_ls || q6_is_ls);
+        solutions.push(
+            Vector6::new(
+                q_partial[0],
+                q_partial[1],
+                q_partial[2],
+                q_partial[3],
+                q5,
+                q6,
+            ),
+            q5_is_ls || q6_is_ls,
+        );
     }
 
-    (q, is_ls)
+    annotated(solutions, kin, r_06, p_0t, config)
+}
+
+/// `two_intersecting` for chains whose first two axes intersect rather than the last two,
+/// solved on the reversed chain
+pub fn two_intersecting_at_base(
+    r_06: &Matrix3<f64>,
+    p_0t: &Vector3<f64>,
+    kin: &Kinematics<6, 7>,
+    config: &SolverConfig,
+) -> IkSolutionSet {
+    two_intersecting_at_base_with(r_06, p_0t, kin, config, &mut SolverScratch::new())
+}
+
+/// `two_intersecting_at_base` keeping the samples of its search in `scratch`
+pub fn two_intersecting_at_base_with(
+    r_06: &Matrix3<f64>,
+    p_0t: &Vector3<f64>,
+    kin: &Kinematics<6, 7>,
+    config: &SolverConfig,
+    scratch: &mut SolverScratch,
+) -> IkSolutionSet {
+    trace_span!(DEBUG, "two_intersecting_at_base");
+    let r_60 = r_06.transpose();
+    let solutions =
+        two_intersecting_with(&r_60, &-(r_60 * p_0t), &kin.reversed(), config, scratch)
+            .map_q(|q| Vector6::new(q[5], q[4], q[3], q[2], q[1], q[0]));
+
+    annotated(solutions, kin, r_06, p_0t, config)
 }
 
 pub fn gen_six_dof(
     r_06: &Matrix3<f64>,
     p_0t: &Vector3<f64>,
     kin: &Kinematics<6, 7>,
-) -> (Vec<Vector6<f64>>, Vec<bool>) {
+    config: &SolverConfig,
+) -> IkSolutionSet {
+    gen_six_dof_with(r_06, p_0t, kin, config, &mut SolverScratch::new())
+}
+
+/// `gen_six_dof` keeping the samples of its search in `scratch`
+pub fn gen_six_dof_with(
+    r_06: &Matrix3<f64>,
+    p_0t: &Vector3<f64>,
+    kin: &Kinematics<6, 7>,
+    config: &SolverConfig,
+    scratch: &mut SolverScratch,
+) -> IkSolutionSet {
+    trace_span!(DEBUG, "gen_six_dof");
+
+    #[cfg(feature = "nlopt")]
+    {
+        // nlopt keeps its own buffers
+        let _ = scratch;
+        gen_six_dof_by(r_06, p_0t, kin, config, |error| {
+            search_2d(error, (-PI, -PI), (PI, PI))
+        })
+    }
+
+    #[cfg(not(feature = "nlopt"))]
+    {
+        gen_six_dof_grid_with(r_06, p_0t, kin, config, scratch)
+    }
+}
+
+/// `gen_six_dof` searching a grid of `config.search_resolution` samples per joint whether or not
+/// nlopt is available, so the cost and the coverage of the search are predictable
+pub fn gen_six_dof_grid(
+    r_06: &Matrix3<f64>,
+    p_0t: &Vector3<f64>,
+    kin: &Kinematics<6, 7>,
+    config: &SolverConfig,
+) -> IkSolutionSet {
+    gen_six_dof_grid_with(r_06, p_0t, kin, config, &mut SolverScratch::new())
+}
+
+/// `gen_six_dof_grid` keeping the samples of its search in `scratch`
+pub fn gen_six_dof_grid_with(
+    r_06: &Matrix3<f64>,
+    p_0t: &Vector3<f64>,
+    kin: &Kinematics<6, 7>,
+    config: &SolverConfig,
+    scratch: &mut SolverScratch,
+) -> IkSolutionSet {
+    trace_span!(DEBUG, "gen_six_dof_grid");
+    gen_six_dof_by(r_06, p_0t, kin, config, |error| {
+        search_2d_grid_with(
+            error,
+            (-PI, -PI),
+            (PI, PI),
+            config.search_resolution,
+            scratch,
+        )
+    })
+}
+
+/// The general 6R solver, finding the zeros of the alignment error over `q1` and `q2` with `search`
+fn gen_six_dof_by<S, Z>(
+    r_06: &Matrix3<f64>,
+    p_0t: &Vector3<f64>,
+    kin: &Kinematics<6, 7>,
+    config: &SolverConfig,
+    search: S,
+) -> IkSolutionSet
+where
+    S: FnOnce(&dyn Fn(f64, f64) -> Vector4<f64>) -> Z,
+    Z: AsRef<[(f64, f64, usize)]>,
+{
     fn q_given_q12_k(
         q1: f64,
         q2: f64,
@@ -606,6 +924,7 @@ pub fn gen_six_dof(
         p16: &Vector3<f64>,
         r_06: &Matrix3<f64>,
         kin: &Kinematics<6, 7>,
+        ls_tolerance: f64,
     ) -> (Vector6<f64>, bool) {
         let p63 = rot(&-kin.h.column(1), q2) * (rot(&-kin.h.column(0), q1) * p16 - kin.p.column(1))
             - kin.p.column(2);
@@ -619,8 +938,7 @@ pub fn gen_six_dof(
             &-kin.h.column(2),
             &kin.h.column(3).into(),
             &kin.h.column(4).into(),
-        )
-        .get_all();
+        );
 
         let (q3, q4, q5) = t345[k];
 
@@ -630,19 +948,22 @@ pub fn gen_six_dof(
             * rot(&kin.h.column(3).into(), q4)
             * rot(&kin.h.column(4).into(), q5);
 
-        let (q6, q6_is_ls) =
-            subproblem1(&p, &(r05.transpose() * r_06 * p), &kin.h.column(5).into());
+        let (q6, q6_is_ls) = subproblem1_with_tolerance(
+            &p,
+            &(r05.transpose() * r_06 * p),
+            &kin.h.column(5).into(),
+            ls_tolerance,
+        );
 
         (Vector6::new(q1, q2, q3, q4, q5, q6), q6_is_ls)
     }
 
-    let mut q = Vec::with_capacity(8);
-    let mut is_ls = Vec::with_capacity(8);
+    let mut solutions = IkSolutionSet::new();
 
     let p16 = p_0t - kin.p.column(0) - r_06 * kin.p.column(6);
 
     let alignment_error_given_q12 = |q1: f64, q2: f64| {
-        let mut error = Vector4::from_element(INFINITY);
+        let mut error = Vector4::from_element(f64::INFINITY);
 
         let p63 = rot(&-kin.h.column(1), q2) * (rot(&-kin.h.column(0), q1) * p16 - kin.p.column(1))
             - kin.p.column(2);
@@ -657,7 +978,7 @@ pub fn gen_six_dof(
             &kin.h.column(4).into(),
         );
 
-        for (i, (q3, q4, q5)) in t345.get_all().into_iter().enumerate() {
+        for (i, (q3, q4, q5)) in t345.get_all().enumerate() {
             let r05 = rot(&kin.h.column(0).into(), q1)
                 * rot(&kin.h.column(1).into(), q2)
                 * rot(&kin.h.column(2).into(), q3)
@@ -670,14 +991,13 @@ pub fn gen_six_dof(
         error
     };
 
-    let minima = search_2d(alignment_error_given_q12, (-PI, -PI), (PI, PI));
+    let minima = search(&alignment_error_given_q12);
 
-    for (x0, x1, k) in minima {
-        let (q_i, q_is_ls) = q_given_q12_k(x0, x1, k, &p16, r_06, kin);
+    for &(x0, x1, k) in minima.as_ref() {
+        let (q_i, q_is_ls) = q_given_q12_k(x0, x1, k, &p16, r_06, kin, config.ls_tolerance);
 
-        q.push(q_i);
-        is_ls.push(q_is_ls);
+        solutions.push(q_i, q_is_ls);
     }
 
-    (q, is_ls)
+    annotated(solutions, kin, r_06, p_0t, config)
 }
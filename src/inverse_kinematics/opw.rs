@@ -0,0 +1,116 @@
+//! The OPW parameters of ortho-parallel robots with a spherical wrist.
+//!
+//! Most six axis industrial robots share one geometry: a vertical first axis, parallel second and
+//! third axes perpendicular to it, and a spherical wrist. OPW parameters (Brandstötter, Angerer and
+//! Hofbaur, 2014) describe such a robot with seven lengths, plus a sign and an offset per joint to
+//! match the joint coordinates of the manufacturer. The parameter sets of ROS-Industrial's
+//! `opw_kinematics` can be used unchanged.
+//!
+//! In the zero configuration of the model the arm points straight up. The joint coordinates are
+//! `q = sign * (q_model + offset)`, so an offset of `-π/2` on the third joint puts the forearm
+//! level at zero as on most robots.
+
+use {
+    super::auxiliary::Kinematics,
+    crate::subproblems::auxiliary::rot,
+    nalgebra::{Isometry3, Matrix3, Rotation3, Translation3, Vector3},
+};
+
+/// The parameters of an OPW robot, in the conventions of `opw_kinematics`
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OpwParameters {
+    /// The offset of the second axis from the first, along the x axis
+    pub a1: f64,
+    /// The offset of the wrist center from the third axis, perpendicular to the forearm
+    pub a2: f64,
+    /// The offset of the second axis from the first, along the y axis
+    pub b: f64,
+    /// The height of the second axis above the base
+    pub c1: f64,
+    /// The length of the upper arm, from the second axis to the third
+    pub c2: f64,
+    /// The length of the forearm, from the third axis to the wrist center
+    pub c3: f64,
+    /// The distance from the wrist center to the flange
+    pub c4: f64,
+    /// The offset added to each model joint angle before its sign is applied
+    pub offsets: [f64; 6],
+    /// The direction of each joint, `1` or `-1`
+    pub sign_corrections: [i8; 6],
+}
+
+impl OpwParameters {
+    /// The parameters with no offsets and every joint turning in the direction of the model
+    pub fn new(a1: f64, a2: f64, b: f64, c1: f64, c2: f64, c3: f64, c4: f64) -> Self {
+        OpwParameters {
+            a1,
+            a2,
+            b,
+            c1,
+            c2,
+            c3,
+            c4,
+            offsets: [0.0; 6],
+            sign_corrections: [1; 6],
+        }
+    }
+
+    pub fn with_offsets(mut self, offsets: [f64; 6]) -> Self {
+        self.offsets = offsets;
+        self
+    }
+
+    pub fn with_sign_corrections(mut self, sign_corrections: [i8; 6]) -> Self {
+        self.sign_corrections = sign_corrections;
+        self
+    }
+}
+
+impl Kinematics<6, 7> {
+    /// The kinematics of an OPW robot in its joint coordinates, reporting the pose of the flange,
+    /// whose z axis points along the sixth axis away from the wrist
+    pub fn from_opw(params: OpwParameters) -> Self {
+        let OpwParameters {
+            a1,
+            a2,
+            b,
+            c1,
+            c2,
+            c3,
+            c4,
+            offsets,
+            sign_corrections,
+        } = params;
+        let (ex, ey, ez) = (Vector3::x(), Vector3::y(), Vector3::z());
+        let zv = Vector3::zeros();
+
+        let h = [ez, ey, ey, ez, ey, ez];
+        let p = [
+            zv,
+            a1 * ex + b * ey + c1 * ez,
+            c2 * ez,
+            a2 * ex + c3 * ez,
+            zv,
+            zv,
+            c4 * ez,
+        ];
+
+        // Move the zero configuration to where the model angles are minus the offsets, rotating
+        // every axis and offset by the joints before it
+        let mut kin = Kinematics::<6, 7>::new();
+        let mut r = Matrix3::identity();
+        kin.p.set_column(0, &p[0]);
+        for i in 0..6 {
+            kin.h
+                .set_column(i, &(r * h[i] * f64::from(sign_corrections[i])));
+            r *= rot(&h[i], -offsets[i]);
+            kin.p.set_column(i + 1, &(r * p[i + 1]));
+        }
+
+        kin.with_tool(Isometry3::from_parts(
+            Translation3::identity(),
+            Rotation3::from_matrix(&r).into(),
+        ))
+    }
+}
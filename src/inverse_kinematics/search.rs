@@ -0,0 +1,417 @@
+//! Searches for the zeros of the error functions of the solvers that cannot be solved in closed
+//! form. `search_1d` brackets zeros between samples like the reference MATLAB implementation.
+//! `search_2d_grid` refines the minima of a grid of samples, and replaces the nlopt based
+//! `search_2d` when the `nlopt` feature is disabled, e.g. for targets without a C toolchain such
+//! as `wasm32-unknown-unknown`. Both are used by all builds.
+//!
+//! Every branch is sampled on a grid and each candidate is refined, so all zeros found on the
+//! grid are returned rather than one per branch.
+//!
+//! The samples and the zeros found are kept in a `SolverScratch`, which `search_1d_with` and
+//! `search_2d_grid_with` reuse across calls so a solver called in a loop only allocates them once.
+
+use {super::auxiliary::Vector, alloc::vec::Vec};
+
+#[cfg(all(not(feature = "std"), target_os = "none"))]
+use num_traits::Float;
+
+const SAMPLES_1D: usize = 400;
+const MAX_ITERATIONS: usize = 500;
+/// Refined zeros of the same branch closer than this are considered the same zero
+const DUPLICATE_TOLERANCE: f64 = 1e-6;
+/// A minimum of the error this close to zero is taken to touch it. This is far tighter than the
+/// tolerance on the zeros, since near singular poses a branch can stay small without ever reaching
+/// zero.
+const TANGENT_TOLERANCE: f64 = 1e-12;
+/// A zero whose error is not this much smaller than the samples around it is a jump of the branch
+/// across zero rather than a zero
+const JUMP_RATIO: f64 = 1e-6;
+
+/// Buffers reused by the searches of the solvers across calls, see `Robot::ik_with_scratch`
+#[derive(Debug, Clone, Default)]
+pub struct SolverScratch {
+    /// The points sampled by `search_1d_with`
+    samples: Vec<f64>,
+    /// The error of every branch at every sample, the branches of each sample in a row
+    errors: Vec<f64>,
+    /// The zeros of the branch being searched, each with the sample it was found from
+    zeros: Vec<(f64, usize)>,
+    /// The zeros returned by `search_1d_with`
+    zeros_1d: Vec<(f64, usize)>,
+    /// The zeros returned by `search_2d_grid_with`
+    zeros_2d: Vec<(f64, f64, usize)>,
+}
+
+impl SolverScratch {
+    /// Empty buffers, which grow to the size of the searches on the first solve
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Squared error of one branch, with non-finite values treated as infinitely bad
+fn squared(error: f64) -> f64 {
+    let e = error * error;
+    if e.is_finite() {
+        e
+    } else {
+        f64::INFINITY
+    }
+}
+
+/// Minimize `cost` within `[a, b]`, returning the minimizer and its cost
+fn golden_section<F: Fn(f64) -> f64>(cost: F, mut a: f64, mut b: f64) -> (f64, f64) {
+    let ratio = (5.0_f64.sqrt() - 1.0) / 2.0;
+
+    let mut c = b - ratio * (b - a);
+    let mut d = a + ratio * (b - a);
+    let (mut cost_c, mut cost_d) = (cost(c), cost(d));
+
+    for _ in 0..MAX_ITERATIONS {
+        if b - a < 1e-14 {
+            break;
+        }
+
+        if cost_c < cost_d {
+            b = d;
+            d = c;
+            cost_d = cost_c;
+            c = b - ratio * (b - a);
+            cost_c = cost(c);
+        } else {
+            a = c;
+            c = d;
+            cost_c = cost_d;
+            d = a + ratio * (b - a);
+            cost_d = cost(d);
+        }
+    }
+
+    let x = (a + b) / 2.0;
+    (x, cost(x))
+}
+
+/// Minimize `cost` with the Nelder-Mead method starting from a simplex of size `step` at `x0`,
+/// keeping the iterate inside the box `[min, max]`
+fn nelder_mead<F: Fn(f64, f64) -> f64>(
+    cost: F,
+    x0: (f64, f64),
+    step: f64,
+    min: (f64, f64),
+    max: (f64, f64),
+) -> (f64, f64, f64) {
+    let clamp = |(x, y): (f64, f64)| (x.clamp(min.0, max.0), y.clamp(min.1, max.1));
+    let eval = |p: (f64, f64)| (p, cost(p.0, p.1));
+
+    let mut simplex = [
+        eval(clamp(x0)),
+        eval(clamp((x0.0 + step, x0.1))),
+        eval(clamp((x0.0, x0.1 + step))),
+    ];
+
+    for _ in 0..MAX_ITERATIONS {
+        simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+        let (best, worst) = (simplex[0], simplex[2]);
+
+        let size = (worst.0 .0 - best.0 .0).abs() + (worst.0 .1 - best.0 .1).abs();
+        if size < 1e-14 || best.1 == 0.0 {
+            break;
+        }
+
+        let centroid = (
+            (simplex[0].0 .0 + simplex[1].0 .0) / 2.0,
+            (simplex[0].0 .1 + simplex[1].0 .1) / 2.0,
+        );
+        let towards = |t: f64| {
+            clamp((
+                centroid.0 + t * (worst.0 .0 - centroid.0),
+                centroid.1 + t * (worst.0 .1 - centroid.1),
+            ))
+        };
+
+        let reflected = eval(towards(-1.0));
+        if reflected.1 < best.1 {
+            let expanded = eval(towards(-2.0));
+            simplex[2] = if expanded.1 < reflected.1 {
+                expanded
+            } else {
+                reflected
+            };
+        } else if reflected.1 < simplex[1].1 {
+            simplex[2] = reflected;
+        } else {
+            let contracted = eval(towards(0.5));
+            if contracted.1 < worst.1 {
+                simplex[2] = contracted;
+            } else {
+                for vertex in simplex.iter_mut().skip(1) {
+                    *vertex = eval((
+                        (vertex.0 .0 + best.0 .0) / 2.0,
+                        (vertex.0 .1 + best.0 .1) / 2.0,
+                    ));
+                }
+            }
+        }
+    }
+
+    simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+    (simplex[0].0 .0, simplex[0].0 .1, simplex[0].1)
+}
+
+/// Find `x` between `a` and `b` where `value` changes sign, given its value `value_a` at `a`.
+/// Where `value` has no value the branch is taken to have ended beyond the sign change.
+fn bisect<F: Fn(f64) -> f64>(value: F, mut a: f64, mut b: f64, mut value_a: f64) -> f64 {
+    for _ in 0..MAX_ITERATIONS {
+        let x = (a + b) / 2.0;
+        if (b - a).abs() < 1e-14 {
+            return x;
+        }
+
+        let value_x = value(x);
+        if value_x == 0.0 {
+            return x;
+        } else if value_x.is_finite()
+            && value_x.is_sign_positive() == value_a.is_sign_positive()
+        {
+            (a, value_a) = (x, value_x);
+        } else {
+            b = x;
+        }
+    }
+
+    (a + b) / 2.0
+}
+
+/// Find the zeros of each branch of `f` within `[left, right]`. Every branch is sampled on a
+/// grid, and each sign change between neighboring samples is refined by bisection. Where the
+/// error comes closest to zero without changing sign between samples it may still touch zero, or
+/// cross it twice in between, so it is minimized there and any zeros found are kept as well.
+/// If there are no zeros, the point with the lowest error is returned instead.
+pub fn search_1d<const N: usize, F: Fn(f64) -> Vector<f64, N>>(
+    f: F,
+    left: f64,
+    right: f64,
+) -> Vec<(f64, usize)> {
+    search_1d_with(f, left, right, &mut SolverScratch::new()).to_vec()
+}
+
+/// `search_1d` keeping the samples and zeros in `scratch`
+pub fn search_1d_with<const N: usize, F: Fn(f64) -> Vector<f64, N>>(
+    f: F,
+    left: f64,
+    right: f64,
+    scratch: &mut SolverScratch,
+) -> &[(f64, usize)] {
+    let epsilon = 1e-8;
+    let step = (right - left) / (SAMPLES_1D - 1) as f64;
+    let SolverScratch {
+        samples: xs,
+        errors,
+        zeros,
+        zeros_1d: results,
+        ..
+    } = scratch;
+    xs.clear();
+    xs.extend((0..SAMPLES_1D).map(|k| left + step * k as f64));
+    errors.clear();
+    for &x in xs.iter() {
+        errors.extend(f(x).iter());
+    }
+
+    results.clear();
+    let mut best: Option<(f64, usize, f64)> = None;
+
+    for i in 0..N {
+        let value = |x: f64| f(x)[i];
+        let sample = |k: usize| errors[k * N + i];
+        let crosses = |k: usize| {
+            k + 1 < SAMPLES_1D
+                && sample(k).is_finite()
+                && sample(k + 1).is_finite()
+                && sample(k).is_sign_positive() != sample(k + 1).is_sign_positive()
+        };
+
+        zeros.clear();
+
+        for k in 0..SAMPLES_1D {
+            if crosses(k) {
+                zeros.push((bisect(value, xs[k], xs[k + 1], sample(k)), k));
+                continue;
+            }
+
+            // A branch ends where it meets another one, and can cross zero just before
+            if sample(k).is_finite() {
+                for l in [k.wrapping_sub(1), k + 1] {
+                    if l < SAMPLES_1D && !sample(l).is_finite() {
+                        zeros.push((bisect(value, xs[k], xs[l], sample(k)), k));
+                    }
+                }
+            }
+
+            let magnitude = |k: usize| squared(sample(k));
+            let previous = if k > 0 {
+                magnitude(k - 1)
+            } else {
+                f64::INFINITY
+            };
+            let next = if k + 1 < SAMPLES_1D {
+                magnitude(k + 1)
+            } else {
+                f64::INFINITY
+            };
+            let is_minimum = magnitude(k).is_finite()
+                && magnitude(k) <= previous
+                && magnitude(k) <= next
+                && !(k > 0 && crosses(k - 1));
+            if !is_minimum {
+                continue;
+            }
+
+            // Minimize the error towards zero from the side of its sign at the sample
+            let (a, b) = (xs[k.saturating_sub(1)], xs[(k + 1).min(SAMPLES_1D - 1)]);
+            let sign = sample(k).signum();
+            let (x, closest) = golden_section(
+                |x| {
+                    let v = sign * value(x);
+                    if v.is_finite() {
+                        v
+                    } else {
+                        f64::INFINITY
+                    }
+                },
+                a,
+                b,
+            );
+
+            // The minimizer can end up where the branch has no value, next to the sample
+            let (x, closest) = if closest.is_finite() {
+                (x, closest)
+            } else {
+                (xs[k], sign * sample(k))
+            };
+
+            if closest < 0.0 {
+                // One zero is between the sample and the minimizer and the other beyond it, where
+                // the branch can end before the next sample
+                let beyond = if x < xs[k] { a } else { b };
+                zeros.push((bisect(value, xs[k], x, sample(k)), k));
+                zeros.push((bisect(value, x, beyond, -sample(k)), k));
+            } else if closest < TANGENT_TOLERANCE {
+                zeros.push((x, k));
+            } else if best.is_none_or(|(_, _, e)| closest * closest < e) {
+                best = Some((x, i, closest * closest));
+            }
+        }
+
+        // A sign change across a discontinuity of the branch is not a zero
+        let scale = |k: usize| {
+            (k.saturating_sub(1)..(k + 2).min(SAMPLES_1D))
+                .map(|j| sample(j).abs())
+                .filter(|v| v.is_finite())
+                .fold(0.0, f64::max)
+        };
+        for &(x, k) in zeros.iter() {
+            let duplicate = results
+                .iter()
+                .any(|&(other, j)| j == i && (other - x).abs() < DUPLICATE_TOLERANCE);
+            let e = squared(value(x));
+            if !duplicate && e < epsilon && e.sqrt() <= JUMP_RATIO * scale(k) {
+                results.push((x, i));
+            }
+        }
+    }
+
+    if results.is_empty() {
+        if let Some((x, i, _)) = best {
+            results.push((x, i));
+        }
+    }
+
+    results
+}
+
+/// Find the zeros of each branch of `f` within the box `[min, max]`, starting from the minima of
+/// a grid of `samples` by `samples` points. If there are none, the point with the lowest error is
+/// returned instead.
+pub fn search_2d_grid<const N: usize, F: Fn(f64, f64) -> Vector<f64, N>>(
+    f: F,
+    min: (f64, f64),
+    max: (f64, f64),
+    samples: usize,
+) -> Vec<(f64, f64, usize)> {
+    search_2d_grid_with(f, min, max, samples, &mut SolverScratch::new()).to_vec()
+}
+
+/// `search_2d_grid` keeping the samples and zeros in `scratch`
+pub fn search_2d_grid_with<const N: usize, F: Fn(f64, f64) -> Vector<f64, N>>(
+    f: F,
+    min: (f64, f64),
+    max: (f64, f64),
+    samples: usize,
+    scratch: &mut SolverScratch,
+) -> &[(f64, f64, usize)] {
+    let samples = samples.max(2);
+    let epsilon = 1e-6;
+    let step = (
+        (max.0 - min.0) / (samples - 1) as f64,
+        (max.1 - min.1) / (samples - 1) as f64,
+    );
+    let point = |k: usize, l: usize| (min.0 + step.0 * k as f64, min.1 + step.1 * l as f64);
+    let SolverScratch {
+        errors,
+        zeros_2d: results,
+        ..
+    } = scratch;
+    errors.clear();
+    for kl in 0..samples * samples {
+        let (x0, x1) = point(kl / samples, kl % samples);
+        errors.extend(f(x0, x1).iter());
+    }
+
+    results.clear();
+    let mut best: Option<(f64, f64, usize, f64)> = None;
+
+    for i in 0..N {
+        let cost = |k: usize, l: usize| squared(errors[(k * samples + l) * N + i]);
+
+        for k in 0..samples {
+            for l in 0..samples {
+                let c = cost(k, l);
+                let is_minimum = c.is_finite()
+                    && (k.saturating_sub(1)..(k + 2).min(samples)).all(|m| {
+                        (l.saturating_sub(1)..(l + 2).min(samples)).all(|n| c <= cost(m, n))
+                    });
+                if !is_minimum {
+                    continue;
+                }
+
+                let (x0, x1, error) = nelder_mead(
+                    |x0, x1| squared(f(x0, x1)[i]),
+                    point(k, l),
+                    step.0.min(step.1) / 2.0,
+                    min,
+                    max,
+                );
+
+                let duplicate = results.iter().any(|&(other0, other1, j)| {
+                    j == i && (other0 - x0).abs() + (other1 - x1).abs() < DUPLICATE_TOLERANCE
+                });
+                if error < epsilon {
+                    if !duplicate {
+                        results.push((x0, x1, i));
+                    }
+                } else if best.is_none_or(|(_, _, _, e)| error < e) {
+                    best = Some((x0, x1, i, error));
+                }
+            }
+        }
+    }
+
+    if results.is_empty() {
+        if let Some((x0, x1, i, _)) = best {
+            results.push((x0, x1, i));
+        }
+    }
+
+    results
+}
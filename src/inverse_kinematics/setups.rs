@@ -2,26 +2,64 @@ use {
     super::{
         auxiliary::{Kinematics, Matrix3x7},
         gen_six_dof, spherical, spherical_two_intersecting, spherical_two_parallel, three_parallel,
-        three_parallel_two_intersecting, two_intersecting, two_parallel,
+        three_parallel_two_intersecting, two_intersecting, two_intersecting_at_base, two_parallel,
     },
-    crate::subproblems::{
-        auxiliary::{random_angle, random_norm_vector3, random_vector3},
-        setups::SetupStatic,
+    crate::{
+        config::SolverConfig,
+        error::{parse_values, IkGeoError},
+        solutionset::{IkSolutionSet, OutputFormat},
+        subproblems::auxiliary::{random_angle, random_norm_vector3, random_vector3},
     },
     nalgebra::{Matrix3, Matrix3x6, Vector3, Vector6},
-    std::f64::NAN,
+    rand::RngCore,
 };
 
+/// A setup that can be built without arguments, such as a hardcoded robot or a decomposition on
+/// random chains of its family
+pub trait SetupStatic {
+    fn new() -> Self;
+    /// The name of every setup of the type, see `SetupIk::name`
+    fn name() -> &'static str;
+}
+
+/// A robot under test in the random forward-to-inverse kinematics round trips of the
+/// `diagnostics` module, the harness the crate measures its own solvers with.
+///
+/// A setup keeps the target of its current round trip and the solutions found for it:
+/// `setup_with_rng` draws joint angles and sets the target to the pose they reach, `run` solves
+/// the target, and `error`, `ls_count` and `solution_count` describe the solutions. Implement it
+/// for a robot of your own to collect its `Stats` with `diagnostics::measure` or a `Diagnostics`,
+/// or wrap a `Robot` in a `RobotSetup` instead.
 pub trait SetupIk {
-    fn setup(&mut self);
-    fn setup_from_str(&mut self, raw: &str);
-    fn write_output(&self) -> String;
+    /// Generate a random reachable pose, drawing the chain and joint angles from `rng` so a seeded
+    /// generator reproduces the same poses
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore);
+
+    /// Set the target from a line of comma separated values, as written by the correctness tests
+    fn setup_from_str(&mut self, raw: &str) -> Result<(), IkGeoError>;
+
+    /// The solutions of the last `run` in `format`, empty unless the setup writes its solutions
+    fn write_output(&self, _format: OutputFormat) -> Vec<u8> {
+        Vec::new()
+    }
+
+    /// Solve the current target, keeping the solutions. This is the only call that is timed.
     fn run(&mut self);
+
+    /// The smallest residual of the solutions of the last `run`, or NaN when there are none
     fn error(&self) -> f64;
+
+    /// The number of least squares solutions of the last `run`
     fn ls_count(&self) -> usize;
+
+    /// The number of solutions of the last `run`
     fn solution_count(&self) -> usize;
+
+    /// The name the statistics of the setup are reported under
     fn name(&self) -> &'static str;
-    fn debug(&self, i: usize);
+
+    /// Print the current target, prefixed with the iteration `i`, when investigating a failure
+    fn debug(&self, _i: usize) {}
 }
 
 macro_rules! define_struct {
@@ -31,8 +69,7 @@ macro_rules! define_struct {
             r: Matrix3<f64>,
             t: Vector3<f64>,
 
-            q: Vec<Vector6<f64>>,
-            is_ls: Vec<bool>,
+            solutions: IkSolutionSet,
         }
     };
 }
@@ -45,25 +82,16 @@ define_struct!(ThreeParallelTwoIntersectingSetup);
 define_struct!(ThreeParallelSetup);
 define_struct!(TwoParallelSetup);
 define_struct!(TwoIntersectingSetup);
+define_struct!(TwoIntersectingAtBaseSetup);
 define_struct!(GenSixDofSetup);
 
-pub fn calculate_ik_error(
-    kin: &Kinematics<6, 7>,
-    r: &Matrix3<f64>,
-    t: &Vector3<f64>,
-    q: &Vector6<f64>,
-) -> f64 {
-    let (r_t, t_t) = kin.forward_kinematics(q);
-    (r_t - r).norm() + (t_t - t).norm()
-}
-
 fn ik_setup_from_string(
     raw: &str,
     kin: &mut Kinematics<6, 7>,
     r: &mut Matrix3<f64>,
     t: &mut Vector3<f64>,
-) {
-    let data: Vec<f64> = raw.split(',').map(|s| s.parse().unwrap()).collect();
+) -> Result<(), IkGeoError> {
+    let data = parse_values(raw, 51)?;
 
     kin.h = Matrix3x6::from_columns(&[
         Vector3::new(data[0], data[1], data[2]),
@@ -89,18 +117,13 @@ fn ik_setup_from_string(
     );
 
     *t = Vector3::new(data[48], data[49], data[50]);
+
+    Ok(())
 }
 
-pub fn ik_write_output(q: &Vec<Vector6<f64>>) -> String {
-    q.iter()
-        .map(|q| {
-            q.iter()
-                .map(|x| x.to_string())
-                .collect::<Vec<String>>()
-                .join(",")
-        })
-        .collect::<Vec<String>>()
-        .join(",")
+/// Write the solutions of a setup in `format`
+pub fn ik_write_output(solutions: &IkSolutionSet, format: OutputFormat) -> Vec<u8> {
+    solutions.write(format)
 }
 
 // Do setup static as a macro to avoid repition
@@ -113,8 +136,7 @@ macro_rules! impl_setup_static {
                     r: Matrix3::zeros(),
                     t: Vector3::zeros(),
 
-                    q: Vec::new(),
-                    is_ls: Vec::new(),
+                    solutions: IkSolutionSet::new(),
                 }
             }
 
@@ -135,34 +157,35 @@ impl_setup_static!(
 impl_setup_static!(ThreeParallelSetup, "Three Parallel");
 impl_setup_static!(TwoParallelSetup, "Two Parallel");
 impl_setup_static!(TwoIntersectingSetup, "Two Intersecting");
+impl_setup_static!(TwoIntersectingAtBaseSetup, "Two Intersecting At Base");
 impl_setup_static!(GenSixDofSetup, "Gen Six DOF");
 
 // Most of the implementations in SetupIk are the same, so we can use a macro to generate them.
 macro_rules! impl_setup_ik {
     // Generate the function setup_from_str, write_output,ls_count, solution_count, name, and debug
     ($name:ident) => {
-        fn setup_from_str(&mut self, raw: &str) {
-            ik_setup_from_string(raw, &mut self.kin, &mut self.r, &mut self.t);
+        fn setup_from_str(&mut self, raw: &str) -> Result<(), IkGeoError> {
+            ik_setup_from_string(raw, &mut self.kin, &mut self.r, &mut self.t)
         }
 
-        fn write_output(&self) -> String {
-            ik_write_output(&self.q)
+        fn write_output(&self, format: OutputFormat) -> Vec<u8> {
+            ik_write_output(&self.solutions, format)
         }
 
         fn error(&self) -> f64 {
-            self.q
+            self.solutions
                 .iter()
-                .map(|q| calculate_ik_error(&self.kin, &self.r, &self.t, q))
+                .map(|solution| solution.residual(&self.kin, &self.r, &self.t))
                 .reduce(f64::min)
-                .unwrap_or(NAN)
+                .unwrap_or(f64::NAN)
         }
 
         fn ls_count(&self) -> usize {
-            self.is_ls.iter().filter(|b| **b).count()
+            self.solutions.iter().filter(|s| s.is_ls).count()
         }
 
         fn solution_count(&self) -> usize {
-            self.is_ls.len()
+            self.solutions.len()
         }
 
         fn name(&self) -> &'static str {
@@ -176,232 +199,264 @@ macro_rules! impl_setup_ik {
 }
 
 impl SetupIk for SphericalTwoParallelSetup {
-    fn setup(&mut self) {
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
         for i in 0..self.kin.h.ncols() {
-            self.kin.h.set_column(i, &random_norm_vector3());
+            self.kin.h.set_column(i, &random_norm_vector3(rng));
         }
 
-        let q = Vector6::zeros().map(|_: f64| random_angle());
+        let q = Vector6::zeros().map(|_: f64| random_angle(rng));
 
         let h_column_1: Vector3<f64> = self.kin.h.column(1).into();
         self.kin.h.set_column(2, &h_column_1);
 
         self.kin.p = Matrix3x7::from_columns(&[
-            random_vector3(),
-            random_vector3(),
-            random_vector3(),
-            random_vector3(),
+            random_vector3(rng),
+            random_vector3(rng),
+            random_vector3(rng),
+            random_vector3(rng),
             Vector3::zeros(),
             Vector3::zeros(),
-            random_vector3(),
+            random_vector3(rng),
         ]);
 
         (self.r, self.t) = self.kin.forward_kinematics(&q);
     }
 
     fn run(&mut self) {
-        (self.q, self.is_ls) = spherical_two_parallel(&self.r, &self.t, &self.kin);
+        self.solutions =
+            spherical_two_parallel(&self.r, &self.t, &self.kin, &SolverConfig::default());
     }
 
     impl_setup_ik!(SphericalTwoParallelSetup);
 }
 
 impl SetupIk for SphericalTwoIntersectingSetup {
-    fn setup(&mut self) {
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
         for i in 0..self.kin.h.ncols() {
-            self.kin.h.set_column(i, &random_norm_vector3());
+            self.kin.h.set_column(i, &random_norm_vector3(rng));
         }
 
-        let q = Vector6::zeros().map(|_: f64| random_angle());
+        let q = Vector6::zeros().map(|_: f64| random_angle(rng));
 
         self.kin.p = Matrix3x7::from_columns(&[
-            random_vector3(),
+            random_vector3(rng),
             Vector3::zeros(),
-            random_vector3(),
-            random_vector3(),
+            random_vector3(rng),
+            random_vector3(rng),
             Vector3::zeros(),
             Vector3::zeros(),
-            random_vector3(),
+            random_vector3(rng),
         ]);
 
         (self.r, self.t) = self.kin.forward_kinematics(&q);
     }
 
     fn run(&mut self) {
-        (self.q, self.is_ls) = spherical_two_intersecting(&self.r, &self.t, &self.kin);
+        self.solutions =
+            spherical_two_intersecting(&self.r, &self.t, &self.kin, &SolverConfig::default());
     }
 
     impl_setup_ik!(SphericalTwoParallelSetup);
 }
 
 impl SetupIk for SphericalSetup {
-    fn setup(&mut self) {
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
         for i in 0..self.kin.h.ncols() {
-            self.kin.h.set_column(i, &random_norm_vector3());
+            self.kin.h.set_column(i, &random_norm_vector3(rng));
         }
 
-        let q = Vector6::zeros().map(|_: f64| random_angle());
+        let q = Vector6::zeros().map(|_: f64| random_angle(rng));
 
         self.kin.p = Matrix3x7::from_columns(&[
-            random_vector3(),
-            random_vector3(),
-            random_vector3(),
-            random_vector3(),
+            random_vector3(rng),
+            random_vector3(rng),
+            random_vector3(rng),
+            random_vector3(rng),
             Vector3::zeros(),
             Vector3::zeros(),
-            random_vector3(),
+            random_vector3(rng),
         ]);
 
         (self.r, self.t) = self.kin.forward_kinematics(&q);
     }
 
     fn run(&mut self) {
-        (self.q, self.is_ls) = spherical(&self.r, &self.t, &self.kin);
+        self.solutions = spherical(&self.r, &self.t, &self.kin, &SolverConfig::default());
     }
 
     impl_setup_ik!(SphericalTwoParallelSetup);
 }
 
 impl SetupIk for ThreeParallelTwoIntersectingSetup {
-    fn setup(&mut self) {
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
         for i in 0..self.kin.h.ncols() {
-            self.kin.h.set_column(i, &random_norm_vector3());
+            self.kin.h.set_column(i, &random_norm_vector3(rng));
         }
 
-        let q = Vector6::zeros().map(|_: f64| random_angle());
+        let q = Vector6::zeros().map(|_: f64| random_angle(rng));
 
         let h_1: Vector3<f64> = self.kin.h.column(1).into();
         self.kin.h.set_column(2, &h_1);
         self.kin.h.set_column(3, &h_1);
 
         self.kin.p = Matrix3x7::from_columns(&[
-            random_vector3(),
-            random_vector3(),
-            random_vector3(),
-            random_vector3(),
-            random_vector3(),
+            random_vector3(rng),
+            random_vector3(rng),
+            random_vector3(rng),
+            random_vector3(rng),
+            random_vector3(rng),
             Vector3::zeros(),
-            random_vector3(),
+            random_vector3(rng),
         ]);
 
         (self.r, self.t) = self.kin.forward_kinematics(&q);
     }
 
     fn run(&mut self) {
-        (self.q, self.is_ls) = three_parallel_two_intersecting(&self.r, &self.t, &self.kin);
+        self.solutions =
+            three_parallel_two_intersecting(&self.r, &self.t, &self.kin, &SolverConfig::default());
     }
 
     impl_setup_ik!(SphericalTwoParallelSetup);
 }
 
 impl SetupIk for ThreeParallelSetup {
-    fn setup(&mut self) {
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
         for i in 0..self.kin.h.ncols() {
-            self.kin.h.set_column(i, &random_norm_vector3());
+            self.kin.h.set_column(i, &random_norm_vector3(rng));
         }
 
-        let q = Vector6::zeros().map(|_: f64| random_angle());
+        let q = Vector6::zeros().map(|_: f64| random_angle(rng));
 
         let h_1: Vector3<f64> = self.kin.h.column(1).into();
         self.kin.h.set_column(2, &h_1);
         self.kin.h.set_column(3, &h_1);
 
         self.kin.p = Matrix3x7::from_columns(&[
-            random_vector3(),
-            random_vector3(),
-            random_vector3(),
-            random_vector3(),
-            random_vector3(),
-            random_vector3(),
-            random_vector3(),
+            random_vector3(rng),
+            random_vector3(rng),
+            random_vector3(rng),
+            random_vector3(rng),
+            random_vector3(rng),
+            random_vector3(rng),
+            random_vector3(rng),
         ]);
 
         (self.r, self.t) = self.kin.forward_kinematics(&q);
     }
 
     fn run(&mut self) {
-        (self.q, self.is_ls) = three_parallel(&self.r, &self.t, &self.kin);
+        self.solutions = three_parallel(&self.r, &self.t, &self.kin, &SolverConfig::default());
     }
 
     impl_setup_ik!(SphericalTwoParallelSetup);
 }
 
 impl SetupIk for TwoParallelSetup {
-    fn setup(&mut self) {
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
         for i in 0..self.kin.h.ncols() {
-            self.kin.h.set_column(i, &random_norm_vector3());
+            self.kin.h.set_column(i, &random_norm_vector3(rng));
         }
 
-        let q = Vector6::zeros().map(|_: f64| random_angle());
+        let q = Vector6::zeros().map(|_: f64| random_angle(rng));
 
         let h_1: Vector3<f64> = self.kin.h.column(1).into();
         self.kin.h.set_column(2, &h_1);
 
         self.kin.p = Matrix3x7::from_columns(&[
-            random_vector3(),
-            random_vector3(),
-            random_vector3(),
-            random_vector3(),
-            random_vector3(),
-            random_vector3(),
-            random_vector3(),
+            random_vector3(rng),
+            random_vector3(rng),
+            random_vector3(rng),
+            random_vector3(rng),
+            random_vector3(rng),
+            random_vector3(rng),
+            random_vector3(rng),
         ]);
 
         (self.r, self.t) = self.kin.forward_kinematics(&q);
     }
 
     fn run(&mut self) {
-        (self.q, self.is_ls) = two_parallel(&self.r, &self.t, &self.kin);
+        self.solutions = two_parallel(&self.r, &self.t, &self.kin, &SolverConfig::default());
     }
 
     impl_setup_ik!(SphericalTwoParallelSetup);
 }
 
 impl SetupIk for TwoIntersectingSetup {
-    fn setup(&mut self) {
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
+        for i in 0..self.kin.h.ncols() {
+            self.kin.h.set_column(i, &random_norm_vector3(rng));
+        }
+
+        let q = Vector6::zeros().map(|_: f64| random_angle(rng));
+
+        self.kin.p = Matrix3x7::from_columns(&[
+            random_vector3(rng),
+            random_vector3(rng),
+            random_vector3(rng),
+            random_vector3(rng),
+            random_vector3(rng),
+            Vector3::zeros(),
+            random_vector3(rng),
+        ]);
+
+        (self.r, self.t) = self.kin.forward_kinematics(&q);
+    }
+
+    fn run(&mut self) {
+        self.solutions = two_intersecting(&self.r, &self.t, &self.kin, &SolverConfig::default());
+    }
+
+    impl_setup_ik!(SphericalTwoParallelSetup);
+}
+
+impl SetupIk for TwoIntersectingAtBaseSetup {
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
         for i in 0..self.kin.h.ncols() {
-            self.kin.h.set_column(i, &random_norm_vector3());
+            self.kin.h.set_column(i, &random_norm_vector3(rng));
         }
 
-        let q = Vector6::zeros().map(|_: f64| random_angle());
+        let q = Vector6::zeros().map(|_: f64| random_angle(rng));
 
         self.kin.p = Matrix3x7::from_columns(&[
-            random_vector3(),
-            random_vector3(),
-            random_vector3(),
-            random_vector3(),
-            random_vector3(),
+            random_vector3(rng),
             Vector3::zeros(),
-            random_vector3(),
+            random_vector3(rng),
+            random_vector3(rng),
+            random_vector3(rng),
+            random_vector3(rng),
+            random_vector3(rng),
         ]);
 
         (self.r, self.t) = self.kin.forward_kinematics(&q);
     }
 
     fn run(&mut self) {
-        (self.q, self.is_ls) = two_intersecting(&self.r, &self.t, &self.kin);
+        self.solutions =
+            two_intersecting_at_base(&self.r, &self.t, &self.kin, &SolverConfig::default());
     }
 
     impl_setup_ik!(SphericalTwoParallelSetup);
 }
 
 impl SetupIk for GenSixDofSetup {
-    fn setup(&mut self) {
-        let q = Vector6::zeros().map(|_: f64| random_angle());
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
+        let q = Vector6::zeros().map(|_: f64| random_angle(rng));
 
         for i in 0..self.kin.h.ncols() {
-            self.kin.h.set_column(i, &random_norm_vector3());
+            self.kin.h.set_column(i, &random_norm_vector3(rng));
         }
 
         for i in 0..self.kin.p.ncols() {
-            self.kin.p.set_column(i, &random_vector3());
+            self.kin.p.set_column(i, &random_vector3(rng));
         }
 
         (self.r, self.t) = self.kin.forward_kinematics(&q);
     }
 
     fn run(&mut self) {
-        (self.q, self.is_ls) = gen_six_dof(&self.r, &self.t, &self.kin);
+        self.solutions = gen_six_dof(&self.r, &self.t, &self.kin, &SolverConfig::default());
     }
 
     impl_setup_ik!(SphericalTwoParallelSetup);
@@ -0,0 +1,199 @@
+//! Transmissions between the coordinates of the motors and those of the joints.
+//!
+//! Controllers often report joint values that are not the angles of the kinematic chain: a zero
+//! position offset from the one of the model, a joint turning the other way, or a coupling between
+//! joints, as on FANUC arms where J3 is measured from the horizontal, or on differential wrists
+//! where two motors drive two joints together. A `JointMapping` is the affine map
+//! `q = coupling m + offset` from the motor coordinates `m` to the joint angles `q` of the
+//! kinematics.
+//!
+//! `MappedRobot` applies a mapping to a `Robot`, so its forward kinematics take motor coordinates
+//! and its inverse kinematics return them. Joint limits of the underlying robot are still applied
+//! to the joint angles of the kinematics.
+
+use {
+    crate::{
+        error::IkGeoError,
+        metrics::JointMetric,
+        robot::{IKSolver, IkSolutionSet, Robot, SolverConfig},
+    },
+    nalgebra::{Matrix3, SMatrix, SVector, Vector3, Vector6},
+};
+
+/// The affine map from the motor coordinates of `N` joints to the joint angles of the kinematics
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JointMapping<const N: usize> {
+    coupling: SMatrix<f64, N, N>,
+    /// The inverse of `coupling`, kept to map joint angles back without solving each time
+    inverse: SMatrix<f64, N, N>,
+    offset: SVector<f64, N>,
+}
+
+impl<const N: usize> Default for JointMapping<N> {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl<const N: usize> JointMapping<N> {
+    /// The mapping where motor coordinates are the joint angles
+    pub fn identity() -> Self {
+        JointMapping {
+            coupling: SMatrix::identity(),
+            inverse: SMatrix::identity(),
+            offset: SVector::zeros(),
+        }
+    }
+
+    /// The mapping `q = coupling m + offset`, failing with `IkGeoError::SingularCoupling` when
+    /// `coupling` cannot be inverted
+    pub fn new(coupling: SMatrix<f64, N, N>, offset: SVector<f64, N>) -> Result<Self, IkGeoError> {
+        let inverse = coupling.try_inverse().ok_or(IkGeoError::SingularCoupling)?;
+        Ok(JointMapping {
+            coupling,
+            inverse,
+            offset,
+        })
+    }
+
+    /// Add `offset` to the angle of `joint`, for a motor whose zero is not the zero of the model
+    pub fn with_offset(mut self, joint: usize, offset: f64) -> Self {
+        self.offset[joint] += offset;
+        self
+    }
+
+    /// Turn the motor of `joint` the other way
+    pub fn with_sign_flip(mut self, joint: usize) -> Self {
+        self.coupling.column_mut(joint).neg_mut();
+        self.inverse.row_mut(joint).neg_mut();
+        self
+    }
+
+    /// Add `ratio` times the motor coordinate of `driver` to the angle of `joint`
+    pub fn with_coupling(
+        self,
+        joint: usize,
+        driver: usize,
+        ratio: f64,
+    ) -> Result<Self, IkGeoError> {
+        let mut coupling = self.coupling;
+        coupling[(joint, driver)] += ratio;
+        Self::new(coupling, self.offset)
+    }
+
+    /// The matrix from motor coordinates to joint angles
+    pub fn coupling(&self) -> &SMatrix<f64, N, N> {
+        &self.coupling
+    }
+
+    /// The joint angles when every motor coordinate is zero
+    pub fn offset(&self) -> &SVector<f64, N> {
+        &self.offset
+    }
+
+    /// The joint angles of the kinematics at the motor coordinates `m`
+    pub fn to_joint(&self, m: &SVector<f64, N>) -> SVector<f64, N> {
+        self.coupling * m + self.offset
+    }
+
+    /// The motor coordinates at the joint angles `q` of the kinematics
+    pub fn to_motor(&self, q: &SVector<f64, N>) -> SVector<f64, N> {
+        self.inverse * (q - self.offset)
+    }
+
+    /// The joint velocities of the kinematics at the motor velocities `dm`
+    pub fn velocity_to_joint(&self, dm: &SVector<f64, N>) -> SVector<f64, N> {
+        self.coupling * dm
+    }
+
+    /// The motor velocities at the joint velocities `dq` of the kinematics
+    pub fn velocity_to_motor(&self, dq: &SVector<f64, N>) -> SVector<f64, N> {
+        self.inverse * dq
+    }
+}
+
+impl JointMapping<6> {
+    /// The mapping of FANUC arms, where J3 is measured from the horizontal rather than from the
+    /// upper arm, so the angle of the third joint is J2 + J3
+    pub fn fanuc_j2_j3() -> Self {
+        let mut mapping = Self::identity();
+        mapping.coupling[(2, 1)] = 1.0;
+        mapping.inverse[(2, 1)] = -1.0;
+        mapping
+    }
+
+    /// The mapping of a differential wrist, where the motors of `a` and `b` turn the joints
+    /// together: the angle of `a` is the mean of both motors, and the angle of `b` half their
+    /// difference, scaled by `ratio`
+    pub fn differential(a: usize, b: usize, ratio: f64) -> Result<Self, IkGeoError> {
+        let mut coupling = SMatrix::identity();
+        coupling[(a, a)] = 0.5;
+        coupling[(a, b)] = 0.5;
+        coupling[(b, a)] = 0.5 * ratio;
+        coupling[(b, b)] = -0.5 * ratio;
+        Self::new(coupling, SVector::zeros())
+    }
+}
+
+/// A `Robot` taking and returning motor coordinates, see the `joint_mapping` module
+pub struct MappedRobot {
+    robot: Robot,
+    mapping: JointMapping<6>,
+}
+
+impl MappedRobot {
+    pub fn new(robot: Robot, mapping: JointMapping<6>) -> Self {
+        MappedRobot { robot, mapping }
+    }
+
+    /// Replace the tolerances used by the solver
+    pub fn with_config(mut self, config: SolverConfig) -> Self {
+        self.robot = self.robot.with_config(config);
+        self
+    }
+
+    /// The robot solved in the joint angles of its kinematics
+    pub fn robot(&self) -> &Robot {
+        &self.robot
+    }
+
+    pub fn mapping(&self) -> &JointMapping<6> {
+        &self.mapping
+    }
+
+    /// The pose of the tool at the motor coordinates `m`
+    pub fn fk(&self, m: &[f64; 6]) -> (Matrix3<f64>, Vector3<f64>) {
+        let q = self.mapping.to_joint(&Vector6::from_row_slice(m));
+        self.robot.fk(&q.into())
+    }
+}
+
+impl IKSolver for MappedRobot {
+    fn ik(&self, rot: Matrix3<f64>, translation: Vector3<f64>) -> IkSolutionSet {
+        self.robot
+            .ik(rot, translation)
+            .map_q(|q| self.mapping.to_motor(q))
+    }
+
+    /// Solve IK with the joint angles moved by whole turns closest to the joint angles at the
+    /// motor coordinates `m_current`
+    fn ik_near(
+        &self,
+        rot: Matrix3<f64>,
+        translation: Vector3<f64>,
+        m_current: &Vector6<f64>,
+    ) -> IkSolutionSet {
+        self.robot
+            .ik_near(rot, translation, &self.mapping.to_joint(m_current))
+            .map_q(|q| self.mapping.to_motor(q))
+    }
+
+    fn joint_metric(&self) -> JointMetric {
+        self.robot.joint_metric()
+    }
+
+    /// The manipulability at the joint angles of the motor coordinates `m`
+    fn manipulability(&self, m: &Vector6<f64>) -> Option<f64> {
+        self.robot.manipulability(&self.mapping.to_joint(m))
+    }
+}
@@ -0,0 +1,158 @@
+//! Naming the configurations of industrial arms by shoulder, elbow and wrist.
+//!
+//! An arm with a spherical wrist and parallel second and third axes reaches a pose in up to eight
+//! configurations, which operators tell apart by the side of the base the wrist center is on,
+//! whether the elbow points up or down, and whether the wrist is flipped. `BranchLabel::of` reads
+//! these from the geometry of a configuration. The zero configuration is taken as the reference, with
+//! the wrist center in front of the base on the right and the elbow up, so the labels do not
+//! depend on the sign conventions of the axes.
+
+use {
+    crate::{
+        config::SolverConfig,
+        inverse_kinematics::{
+            auxiliary::{wrap_to_pi, Kinematics},
+            family::classify,
+        },
+        subproblems::auxiliary::rot,
+    },
+    core::fmt,
+    nalgebra::{Vector3, Vector6},
+};
+
+/// The side of the first axis the wrist center is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Shoulder {
+    /// The same side as in the zero configuration, with the arm reaching forward
+    Right,
+    /// The opposite side, with the arm reaching back over the base
+    Left,
+}
+
+/// The side of the line from the shoulder to the wrist center the elbow is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Elbow {
+    /// The same side as in the zero configuration
+    Up,
+    Down,
+}
+
+/// The sign of the fifth joint, which tells apart the two wrist solutions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Wrist {
+    /// The fifth joint is in `[0, π)`
+    NoFlip,
+    /// The fifth joint is in `[-π, 0)`
+    Flip,
+}
+
+/// The named configuration of a solution
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BranchLabel {
+    pub shoulder: Shoulder,
+    pub elbow: Elbow,
+    pub wrist: Wrist,
+}
+
+impl BranchLabel {
+    pub fn new(shoulder: Shoulder, elbow: Elbow, wrist: Wrist) -> Self {
+        BranchLabel {
+            shoulder,
+            elbow,
+            wrist,
+        }
+    }
+
+    /// The label of `q` on the chain `kin`, or `None` unless `kin` has a spherical wrist and
+    /// parallel second and third axes that the first axis is not parallel to
+    pub fn of(kin: &Kinematics<6, 7>, q: &Vector6<f64>) -> Option<Self> {
+        let tolerance = SolverConfig::default().ls_tolerance;
+        let h_1: Vector3<f64> = kin.h.column(0).into();
+        let h_2: Vector3<f64> = kin.h.column(1).into();
+        let parallel = |a: &Vector3<f64>, b: &Vector3<f64>| {
+            a.cross(b).norm() < tolerance * a.norm() * b.norm()
+        };
+        if !classify(kin).has_spherical_wrist()
+            || !parallel(&h_2, &kin.h.column(2).into())
+            || parallel(&h_1, &h_2)
+        {
+            return None;
+        }
+
+        let (forward, elbow) = Self::arm(kin, &Vector6::zeros());
+        let (reach, bend) = Self::arm(kin, q);
+
+        // Joint 1 carries the forward direction of the zero configuration along
+        let forward = rot(&h_1, q[0]) * forward;
+        let (shoulder, side) = if forward.dot(&reach) >= 0.0 {
+            (Shoulder::Right, 1.0)
+        } else {
+            (Shoulder::Left, -1.0)
+        };
+
+        Some(BranchLabel {
+            shoulder,
+            elbow: if side * bend * elbow >= 0.0 {
+                Elbow::Up
+            } else {
+                Elbow::Down
+            },
+            wrist: if wrap_to_pi(q[4]) >= 0.0 {
+                Wrist::NoFlip
+            } else {
+                Wrist::Flip
+            },
+        })
+    }
+
+    /// The reach of the wrist center away from the first axis, and the side of the line from the
+    /// shoulder to the wrist center the elbow is on, about the second axis
+    fn arm(kin: &Kinematics<6, 7>, q: &Vector6<f64>) -> (Vector3<f64>, f64) {
+        let origins = kin.joint_origins(q);
+        let h_1: Vector3<f64> = kin.h.column(0).normalize();
+        let h_2 = rot(&h_1, q[0]) * kin.h.column(1);
+        let (o_1, o_2, o_3, w) = (
+            origins.column(0),
+            origins.column(1),
+            origins.column(2),
+            origins.column(3),
+        );
+
+        let reach = (w - o_1) - h_1 * h_1.dot(&(w - o_1));
+        (reach, h_2.dot(&(o_3 - o_2).cross(&(w - o_2))))
+    }
+}
+
+impl fmt::Display for BranchLabel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let shoulder = match self.shoulder {
+            Shoulder::Right => "right",
+            Shoulder::Left => "left",
+        };
+        let elbow = match self.elbow {
+            Elbow::Up => "up",
+            Elbow::Down => "down",
+        };
+        let wrist = match self.wrist {
+            Wrist::NoFlip => "no_flip",
+            Wrist::Flip => "flip",
+        };
+        write!(f, "{shoulder}/{elbow}/{wrist}")
+    }
+}
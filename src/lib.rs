@@ -1,14 +1,89 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+// The cdylib needs the allocator and panic handler of std. Targets with an operating system have
+// std even when the crate does not use it, and bare metal targets drop the cdylib crate type.
+#[cfg(all(not(feature = "std"), not(target_os = "none")))]
+extern crate std as _;
+
 pub use nalgebra;
 
+#[macro_use]
+mod trace;
+
+#[cfg(feature = "std")]
+pub mod cache;
+pub mod calibration;
+pub mod config;
+pub mod continuation;
+pub mod conventions;
+pub mod coordinated;
+pub mod derivatives;
+#[cfg(feature = "std")]
+pub mod diagnostics;
+pub mod error;
+pub mod external_axis;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod joint_mapping;
+pub mod labels;
+pub mod limits;
+pub mod locked_joint;
+pub mod metrics;
+pub mod model_check;
+pub mod obstacles;
+pub mod parallel;
+pub mod partial_pose;
+pub mod pose;
+pub mod redundancy;
+pub mod registry;
+#[cfg(feature = "std")]
+pub mod replay;
+pub mod retiming;
+pub mod ros2;
+pub mod screw;
+pub mod se3;
 pub mod solutionset;
+pub mod solver;
+pub mod toleranced;
+pub mod trajectory;
+pub mod urdf;
+#[cfg(feature = "std")]
+pub mod validation;
+pub mod workspace;
+
+#[cfg(feature = "serde")]
+pub mod schema;
+
+#[cfg(feature = "proptest")]
+pub mod strategies;
+
+#[cfg(feature = "arrow")]
+pub mod arrow;
+
+#[cfg(feature = "gpu")]
+pub mod gpu;
+
+#[cfg(feature = "hdf5")]
+pub mod hdf5;
+
+#[cfg(feature = "service")]
+pub mod service;
+
+#[cfg(feature = "viz")]
+pub mod viz;
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 pub mod inverse_kinematics;
 pub mod subproblems;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod correctness;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod interface_tests;
 
 pub mod robot;
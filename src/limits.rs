@@ -0,0 +1,110 @@
+//! Joint limits, which the solvers of a `Robot` drop solutions outside of.
+//!
+//! The solvers return every joint angle in `(-π, π]`. Joints that turn more than once can reach
+//! the same solution a whole number of turns away, so `unwrap_near` and `JointLimits::unwrap_near`
+//! move each angle to the revolution closest to the current configuration of the robot instead.
+//! Limits wider than a turn admit several revolutions of the same solution, which
+//! `JointLimits::revolutions` enumerates.
+
+use {
+    crate::inverse_kinematics::auxiliary::wrap_to_pi, alloc::vec::Vec, core::f64::consts::TAU,
+    nalgebra::SVector,
+};
+
+#[cfg(all(not(feature = "std"), target_os = "none"))]
+use num_traits::Float;
+
+/// The joint angles of `q_solution`, each moved by whole turns to the revolution closest to
+/// `q_current`
+pub fn unwrap_near<const N: usize>(
+    q_solution: &SVector<f64, N>,
+    q_current: &SVector<f64, N>,
+) -> SVector<f64, N> {
+    q_current + (q_solution - q_current).map(wrap_to_pi)
+}
+
+/// The range `[lower, upper]` of each joint angle, in radians
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct JointLimits<const N: usize = 6> {
+    pub lower: SVector<f64, N>,
+    pub upper: SVector<f64, N>,
+}
+
+impl<const N: usize> JointLimits<N> {
+    pub fn new(lower: SVector<f64, N>, upper: SVector<f64, N>) -> Self {
+        JointLimits { lower, upper }
+    }
+
+    /// Build the limits from one `[lower, upper]` pair per joint
+    pub fn from_pairs(pairs: &[[f64; 2]; N]) -> Self {
+        JointLimits {
+            lower: SVector::from_fn(|i, _| pairs[i][0]),
+            upper: SVector::from_fn(|i, _| pairs[i][1]),
+        }
+    }
+
+    /// Whether every joint of `q` is within its range
+    pub fn contains(&self, q: &SVector<f64, N>) -> bool {
+        (0..N).all(|i| self.lower[i] <= q[i] && q[i] <= self.upper[i])
+    }
+
+    /// Every configuration within the limits that differs from `q` by whole turns of its joints,
+    /// in order of increasing joint angles with the last joint changing fastest. Joints with limits
+    /// wider than a turn contribute several revolutions each.
+    pub fn revolutions(&self, q: &SVector<f64, N>) -> Vec<SVector<f64, N>> {
+        self.iter_revolutions(q).collect()
+    }
+
+    /// `revolutions` without collecting them, so they can be enumerated without allocating.
+    /// Joints with an infinite or NaN bound admit endless revolutions, so they only contribute
+    /// their angle in `q`, if it is within the range.
+    pub fn iter_revolutions(&self, q: &SVector<f64, N>) -> impl Iterator<Item = SVector<f64, N>> {
+        let bounded = |i: usize| self.lower[i].is_finite() && self.upper[i].is_finite();
+        let first: SVector<f64, N> = SVector::from_fn(|i, _| {
+            if bounded(i) {
+                q[i] + TAU * ((self.lower[i] - q[i]) / TAU).ceil()
+            } else {
+                q[i]
+            }
+        });
+        let counts: [usize; N] = core::array::from_fn(|i| {
+            if bounded(i) {
+                (0..)
+                    .take_while(|&k| first[i] + TAU * k as f64 <= self.upper[i])
+                    .count()
+            } else {
+                usize::from(self.lower[i] <= q[i] && q[i] <= self.upper[i])
+            }
+        });
+        let total: usize = counts.iter().product();
+
+        // Count through the revolutions like an odometer, with the last joint as the lowest digit
+        (0..total).map(move |mut index| {
+            let mut copy = first;
+            for i in (0..N).rev() {
+                copy[i] += TAU * (index % counts[i]) as f64;
+                index /= counts[i];
+            }
+            copy
+        })
+    }
+
+    /// The joint angles of `q_solution`, each moved by whole turns to the revolution within its
+    /// range closest to `q_current`, or `None` if some joint has no revolution within its range
+    pub fn unwrap_near(
+        &self,
+        q_solution: &SVector<f64, N>,
+        q_current: &SVector<f64, N>,
+    ) -> Option<SVector<f64, N>> {
+        let mut q = unwrap_near(q_solution, q_current);
+        for i in 0..N {
+            if q[i] < self.lower[i] {
+                q[i] += TAU * ((self.lower[i] - q[i]) / TAU).ceil();
+            } else if q[i] > self.upper[i] {
+                q[i] -= TAU * ((q[i] - self.upper[i]) / TAU).ceil();
+            }
+        }
+        self.contains(&q).then_some(q)
+    }
+}
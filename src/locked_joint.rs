@@ -0,0 +1,200 @@
+//! Solving a 7-DOF chain with one joint locked, e.g. after the joint failed or its brake engaged.
+//!
+//! `Kinematics::lock_joint` folds the locked joint into its neighbouring links and returns the
+//! remaining 6-DOF chain with the bookkeeping to put the locked value back into the solutions.
+//! Unlike `Kinematics::forward_kinematics_partial`, the rotation of the locked joint is moved into
+//! the tool frame of the remaining chain, so its forward kinematics are those of the full chain and
+//! targets need no conversion. `LockedRobot` solves the remaining chain in closed form when its
+//! family allows, and returns solutions for every joint of the full chain.
+//!
+//! The hardcoded 7-DOF robots with a fixed joint, such as `KukaR800FixedQ3`, return only the joints
+//! they solve for from `IKSolver::ik`. As `FixedJointSolver`s they also return every joint with
+//! `ik_full`, or either with `ik_output`.
+
+use {
+    crate::{
+        error::IkGeoError,
+        inverse_kinematics::auxiliary::{expand_partial_solution, joint_motion, Kinematics},
+        robot::{IKSolver, IkSolutionSet, Robot, SolverConfig},
+    },
+    core::cmp::Ordering,
+    nalgebra::{
+        Isometry3, Matrix3, Rotation3, SVector, Translation3, UnitQuaternion, Vector3, Vector6,
+    },
+};
+
+/// The 6-DOF chain left when a joint of a 7-DOF chain is locked, see `Kinematics::lock_joint`
+#[derive(Debug, Clone)]
+pub struct LockedJoint {
+    /// The remaining joints, reaching the same poses as the full chain
+    pub kinematics: Kinematics<6, 7>,
+    /// The index of the locked joint in the full chain
+    pub joint: usize,
+    /// The value the joint is locked at
+    pub q: f64,
+}
+
+impl LockedJoint {
+    /// The joint values of the full chain at the values `q` of the remaining joints
+    pub fn expand(&self, q: &Vector6<f64>) -> SVector<f64, 7> {
+        expand_partial_solution(q, self.q, self.joint)
+    }
+
+    /// The values of the remaining joints in the joint values `q` of the full chain
+    pub fn reduce(&self, q: &SVector<f64, 7>) -> Vector6<f64> {
+        Vector6::from_iterator(
+            q.iter()
+                .enumerate()
+                .filter(|&(i, _)| i != self.joint)
+                .map(|(_, &q_i)| q_i),
+        )
+    }
+
+    /// Solutions of the remaining chain as solutions of the full chain
+    pub fn expand_solutions(&self, solutions: IkSolutionSet) -> IkSolutionSet<7> {
+        solutions.map_q(|q| self.expand(q))
+    }
+}
+
+impl Kinematics<7, 8> {
+    /// Lock `joint` at `q`, failing with `IkGeoError::InvalidJoint` when the chain has no such
+    /// joint, see the `locked_joint` module
+    pub fn lock_joint(&self, joint: usize, q: f64) -> Result<LockedJoint, IkGeoError> {
+        if joint >= 7 {
+            return Err(IkGeoError::InvalidJoint {
+                joint,
+                num_joints: 7,
+            });
+        }
+
+        // The joints after the locked one turn about axes rotated by it
+        let (r_n, d_n) = joint_motion(&self.h.column(joint).into(), self.joint_types[joint], q);
+        let mut kinematics = Kinematics::<6, 7>::new();
+        for i in 0..6 {
+            let (k, r) = if i < joint {
+                (i, Matrix3::identity())
+            } else {
+                (i + 1, r_n)
+            };
+            kinematics.h.set_column(i, &(r * self.h.column(k)));
+            kinematics.joint_types[i] = self.joint_types[k];
+        }
+        for i in 0..7 {
+            let p: Vector3<f64> = match i.cmp(&joint) {
+                Ordering::Less => self.p.column(i).into(),
+                Ordering::Equal => self.p.column(i) + d_n + r_n * self.p.column(i + 1),
+                Ordering::Greater => r_n * self.p.column(i + 1),
+            };
+            kinematics.p.set_column(i, &p);
+        }
+
+        // The remaining chain ends rotated back by the locked joint
+        let rotation = UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix_unchecked(r_n));
+        kinematics.base = self.base;
+        kinematics.tool = Isometry3::from_parts(Translation3::identity(), rotation) * self.tool;
+
+        Ok(LockedJoint {
+            kinematics,
+            joint,
+            q,
+        })
+    }
+}
+
+/// Solutions of a 7-DOF robot with a fixed joint, see `FixedJointSolver::ik_output`
+#[derive(Debug, Clone)]
+pub enum FullOrReduced {
+    /// Every joint, with the fixed joint at its fixed value
+    Full(IkSolutionSet<7>),
+    /// Only the joints solved for, in order without the fixed joint
+    Reduced(IkSolutionSet),
+}
+
+impl FullOrReduced {
+    pub fn len(&self) -> usize {
+        match self {
+            FullOrReduced::Full(solutions) => solutions.len(),
+            FullOrReduced::Reduced(solutions) => solutions.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A 7-DOF robot solved with one of its joints fixed
+pub trait FixedJointSolver: IKSolver {
+    /// The index of the fixed joint and its value
+    fn fixed_joint(&self) -> (usize, f64);
+
+    /// The joint values of the full chain at the values `q` of the joints solved for
+    fn expand(&self, q: &Vector6<f64>) -> SVector<f64, 7> {
+        let (joint, value) = self.fixed_joint();
+        expand_partial_solution(q, value, joint)
+    }
+
+    /// Solve IK like `ik`, returning every joint with the fixed joint inserted
+    fn ik_full(&self, rot: Matrix3<f64>, translation: Vector3<f64>) -> IkSolutionSet<7> {
+        self.ik(rot, translation).map_q(|q| self.expand(q))
+    }
+
+    /// Solve IK, returning every joint when `full` and only the joints solved for otherwise
+    fn ik_output(&self, rot: Matrix3<f64>, translation: Vector3<f64>, full: bool) -> FullOrReduced {
+        if full {
+            FullOrReduced::Full(self.ik_full(rot, translation))
+        } else {
+            FullOrReduced::Reduced(self.ik(rot, translation))
+        }
+    }
+}
+
+/// A 7-DOF robot with one joint locked, solving the remaining joints for any pose
+pub struct LockedRobot {
+    kinematics: Kinematics<7, 8>,
+    locked: LockedJoint,
+    robot: Robot,
+}
+
+impl LockedRobot {
+    /// Lock `joint` of `kinematics` at `q`, solving the remaining chain with the decomposition of
+    /// its kinematic family
+    pub fn new(kinematics: Kinematics<7, 8>, joint: usize, q: f64) -> Result<Self, IkGeoError> {
+        let locked = kinematics.lock_joint(joint, q)?;
+        let robot = Robot::from_kinematics(locked.kinematics.clone());
+        Ok(LockedRobot {
+            kinematics,
+            locked,
+            robot,
+        })
+    }
+
+    /// Replace the tolerances used by the solver
+    pub fn with_config(mut self, config: SolverConfig) -> Self {
+        self.robot = self.robot.with_config(config);
+        self
+    }
+
+    pub fn kinematics(&self) -> &Kinematics<7, 8> {
+        &self.kinematics
+    }
+
+    pub fn locked(&self) -> &LockedJoint {
+        &self.locked
+    }
+
+    /// The robot of the remaining joints
+    pub fn robot(&self) -> &Robot {
+        &self.robot
+    }
+
+    pub fn fk(&self, q: &SVector<f64, 7>) -> (Matrix3<f64>, Vector3<f64>) {
+        self.kinematics.forward_kinematics(q)
+    }
+
+    /// Solve for every joint of the full chain, with the locked joint at its locked value
+    pub fn ik(&self, rot: Matrix3<f64>, translation: Vector3<f64>) -> IkSolutionSet<7> {
+        self.locked
+            .expand_solutions(self.robot.ik(rot, translation))
+    }
+}
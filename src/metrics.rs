@@ -0,0 +1,117 @@
+//! Distances between joint configurations.
+//!
+//! A `JointMetric` combines one of the `Distance` measures with a weight per joint that scales the
+//! change of that joint before it is measured. `CartesianTracker` uses a metric to find the
+//! trajectory with the least joint motion, and `IkSolutionSet::nearest` to pick the solution
+//! closest to a seed configuration. The weights from `JointMetric::from_kinematics` make a change
+//! of a proximal joint, which swings more of the arm, count for more than the same change of a
+//! wrist joint.
+
+use {
+    crate::inverse_kinematics::auxiliary::{wrap_to_pi, Kinematics},
+    nalgebra::SVector,
+};
+
+/// How the weighted joint changes are combined into a distance
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Distance {
+    /// The Euclidean norm of the joint changes, taken as they are
+    WeightedL2,
+    /// The largest joint change, taken as it is
+    MaxAbs,
+    /// The Euclidean norm of the joint changes, each taking the shorter way around the circle
+    #[default]
+    WrappedAngular,
+}
+
+/// A distance between joint configurations with a weight per joint
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct JointMetric<const N: usize = 6> {
+    pub distance: Distance,
+    /// The factor the change of each joint is multiplied with
+    pub weights: SVector<f64, N>,
+}
+
+impl<const N: usize> Default for JointMetric<N> {
+    fn default() -> Self {
+        Self::new(Distance::default())
+    }
+}
+
+impl<const N: usize> JointMetric<N> {
+    /// `distance` with the same weight of 1 for every joint
+    pub fn new(distance: Distance) -> Self {
+        JointMetric {
+            distance,
+            weights: SVector::repeat(1.0),
+        }
+    }
+
+    pub fn with_weights(mut self, weights: SVector<f64, N>) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    /// `distance` weighted by the length of the chain each joint moves: the offsets after the
+    /// joint and the tool, relative to the longest. Chains without any length weigh every joint
+    /// the same.
+    pub fn from_kinematics<const C2: usize>(kin: &Kinematics<N, C2>, distance: Distance) -> Self {
+        Self::new(distance).with_weights(link_length_weights(kin))
+    }
+
+    /// The distance from `from` to `to`
+    pub fn distance(&self, from: &SVector<f64, N>, to: &SVector<f64, N>) -> f64 {
+        match self.distance {
+            Distance::WeightedL2 => weighted_l2(from, to, &self.weights),
+            Distance::MaxAbs => max_abs(from, to, &self.weights),
+            Distance::WrappedAngular => wrapped_angular(from, to, &self.weights),
+        }
+    }
+}
+
+/// The Euclidean norm of the joint changes from `from` to `to`, each multiplied by its weight
+pub fn weighted_l2<const N: usize>(
+    from: &SVector<f64, N>,
+    to: &SVector<f64, N>,
+    weights: &SVector<f64, N>,
+) -> f64 {
+    (to - from).component_mul(weights).norm()
+}
+
+/// The largest joint change from `from` to `to`, each multiplied by its weight
+pub fn max_abs<const N: usize>(
+    from: &SVector<f64, N>,
+    to: &SVector<f64, N>,
+    weights: &SVector<f64, N>,
+) -> f64 {
+    (to - from).component_mul(weights).amax()
+}
+
+/// The Euclidean norm of the joint changes from `from` to `to` modulo 2π, each multiplied by its
+/// weight
+pub fn wrapped_angular<const N: usize>(
+    from: &SVector<f64, N>,
+    to: &SVector<f64, N>,
+    weights: &SVector<f64, N>,
+) -> f64 {
+    (to - from).map(wrap_to_pi).component_mul(weights).norm()
+}
+
+/// The length of the chain each joint of `kin` moves, relative to the longest
+pub fn link_length_weights<const N: usize, const C2: usize>(
+    kin: &Kinematics<N, C2>,
+) -> SVector<f64, N> {
+    let tool = kin.tool.translation.vector.norm();
+    let lengths = SVector::<f64, N>::from_fn(|i, _| {
+        tool + (i + 1..C2).map(|j| kin.p.column(j).norm()).sum::<f64>()
+    });
+
+    let longest = lengths.max();
+    if longest > 0.0 {
+        lengths / longest
+    } else {
+        SVector::repeat(1.0)
+    }
+}
@@ -0,0 +1,213 @@
+//! Checking kinematic models for the mistakes that make the solvers return garbage.
+//!
+//! Most reports of wrong solutions come down to the model rather than the solver: axes that are
+//! not unit vectors, axes meant to be parallel or to intersect that miss by a rounding error, so
+//! the chain falls into a slower family or a badly conditioned one, and offsets written in another
+//! convention than the one of the crate, where `p[i]` is the offset from the origin of joint `i - 1`
+//! to the origin of joint `i` in the base frame at the zero configuration.
+//!
+//! `Kinematics::validate` reports these in a `ModelReport`. Like `Classification`, only
+//! consecutive axes are compared, since the angle and distance between any others depend on the
+//! joints in between.
+
+use {
+    crate::{
+        config::SolverConfig,
+        inverse_kinematics::{
+            auxiliary::{Kinematics, KinematicsDyn},
+            family::KinematicFamily,
+        },
+    },
+    alloc::vec::Vec,
+    nalgebra::Vector3,
+};
+
+#[cfg(all(not(feature = "std"), target_os = "none"))]
+use num_traits::Float;
+
+/// Axes within this angle, in radians, of being parallel are reported as nearly parallel by
+/// `Kinematics::validate`
+pub const NEAR_ANGLE: f64 = 1e-2;
+
+/// Axes within this fraction of the longest offset of being intersecting are reported as nearly
+/// intersecting by `Kinematics::validate`
+pub const NEAR_DISTANCE: f64 = 1e-2;
+
+/// Offsets longer than this are reported by `Kinematics::validate` as likely in millimeters
+pub const LARGE_OFFSET: f64 = 10.0;
+
+/// How two consecutive axes relate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum AxisRelation {
+    /// Parallel within the tolerance, as the solvers count them
+    Parallel,
+    /// Not parallel within the tolerance, but close enough to look like a modeling error
+    NearlyParallel,
+    /// Crossing at a point within the tolerance
+    Intersecting,
+    /// Not crossing within the tolerance, but close enough to look like a modeling error
+    NearlyIntersecting,
+}
+
+/// Two consecutive axes that are parallel or intersect, or nearly do
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AxisPair {
+    /// The indices of the axes in `h`
+    pub axes: (usize, usize),
+    pub relation: AxisRelation,
+    /// The angle between the axes, in `[0, π/2]`
+    pub angle: f64,
+    /// The distance between the lines of the axes
+    pub distance: f64,
+}
+
+impl AxisPair {
+    /// Whether the pair only nearly meets its condition, which is likely a modeling error
+    pub fn is_near(&self) -> bool {
+        matches!(
+            self.relation,
+            AxisRelation::NearlyParallel | AxisRelation::NearlyIntersecting
+        )
+    }
+}
+
+/// A sign that the offsets are not in the convention of the crate
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ModelWarning {
+    /// The columns of `p` of a 6R chain look like the positions of the joints rather than the
+    /// offsets between them: the differences of consecutive columns put the chain into a more
+    /// specialized `family` than the `given` one, in the order of `KinematicFamily::ALL`
+    AbsolutePositions {
+        given: KinematicFamily,
+        family: KinematicFamily,
+    },
+    /// The longest offset is longer than `LARGE_OFFSET`, as for kinematics in millimeters, which
+    /// needs `SolverConfig::ls_tolerance` scaled along
+    LargeOffsets { length: f64 },
+}
+
+/// The findings of `Kinematics::validate`
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModelReport {
+    /// The joints whose axis is not a unit vector, with the norm of the axis
+    pub non_unit_axes: Vec<(usize, f64)>,
+    /// The consecutive axes that are parallel or intersect, or nearly do
+    pub axis_pairs: Vec<AxisPair>,
+    pub warnings: Vec<ModelWarning>,
+}
+
+impl ModelReport {
+    /// Whether nothing looks wrong with the model: every axis is a unit vector, no pair of axes
+    /// nearly meets a condition and there are no warnings
+    pub fn is_ok(&self) -> bool {
+        self.non_unit_axes.is_empty()
+            && !self.axis_pairs.iter().any(AxisPair::is_near)
+            && self.warnings.is_empty()
+    }
+
+    /// The pairs of axes with `relation`
+    pub fn pairs(&self, relation: AxisRelation) -> impl Iterator<Item = &AxisPair> {
+        self.axis_pairs
+            .iter()
+            .filter(move |pair| pair.relation == relation)
+    }
+}
+
+/// The angle between axes `i` and `j`, and the distance between their lines when the origin of
+/// `j` is `offset` away from the origin of `i`
+fn axis_geometry(h_i: &Vector3<f64>, h_j: &Vector3<f64>, offset: &Vector3<f64>) -> (f64, f64) {
+    let (h_i, h_j) = (h_i.normalize(), h_j.normalize());
+    let normal = h_i.cross(&h_j);
+    let angle = normal.norm().atan2(h_i.dot(&h_j).abs());
+    let distance = if normal.norm() > 0.0 {
+        offset.dot(&normal).abs() / normal.norm()
+    } else {
+        offset.cross(&h_i).norm()
+    };
+    (angle, distance)
+}
+
+impl<const C1: usize, const C2: usize> Kinematics<C1, C2> {
+    /// Check the model for common mistakes, with the `ls_tolerance` of the default
+    /// `SolverConfig`, see the `model_check` module
+    pub fn validate(&self) -> ModelReport {
+        self.validate_with(SolverConfig::default().ls_tolerance)
+    }
+
+    /// `validate`, counting axes as unit, parallel or intersecting within `tolerance`
+    pub fn validate_with(&self, tolerance: f64) -> ModelReport {
+        let non_unit_axes = (0..C1)
+            .map(|i| (i, self.h.column(i).norm()))
+            .filter(|(_, norm)| (norm - 1.0).abs() > tolerance)
+            .collect();
+
+        let longest = self.p.column_iter().map(|p| p.norm()).fold(0.0, f64::max);
+        let near_distance = NEAR_DISTANCE * longest;
+
+        let mut axis_pairs = Vec::new();
+        let mut warnings = Vec::new();
+        for i in 0..C1.saturating_sub(1) {
+            let offset: Vector3<f64> = self.p.column(i + 1).into();
+            let (angle, distance) = axis_geometry(
+                &self.h.column(i).into(),
+                &self.h.column(i + 1).into(),
+                &offset,
+            );
+            let relation = if angle.sin() < tolerance {
+                AxisRelation::Parallel
+            } else if angle < NEAR_ANGLE {
+                AxisRelation::NearlyParallel
+            } else if distance < tolerance {
+                AxisRelation::Intersecting
+            } else if distance < near_distance {
+                AxisRelation::NearlyIntersecting
+            } else {
+                continue;
+            };
+            axis_pairs.push(AxisPair {
+                axes: (i, i + 1),
+                relation,
+                angle,
+                distance,
+            });
+        }
+
+        // Read as positions, the offset between joints i and i + 1 is p[i + 1] - p[i]
+        if let Ok(kin) = Kinematics::<6, 7>::try_from(&KinematicsDyn::from(self)) {
+            let mut differences = kin.clone();
+            for i in 1..7 {
+                differences
+                    .p
+                    .set_column(i, &(kin.p.column(i) - kin.p.column(i - 1)));
+            }
+            let given = KinematicFamily::classify(&kin, tolerance);
+            let family = KinematicFamily::classify(&differences, tolerance);
+            let rank = |family| KinematicFamily::ALL.iter().position(|f| *f == family);
+            if rank(family) < rank(given) {
+                warnings.push(ModelWarning::AbsolutePositions { given, family });
+            }
+        }
+
+        if longest > LARGE_OFFSET {
+            warnings.push(ModelWarning::LargeOffsets { length: longest });
+        }
+
+        ModelReport {
+            non_unit_axes,
+            axis_pairs,
+            warnings,
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! Placing the elbow of a 7-DOF arm away from obstacles in the workspace.
+//!
+//! Obstacles are spheres and capsules, the shapes that bound most fixtures and people well
+//! enough for choosing a configuration. The arm is taken as the segments from the shoulder to
+//! the elbow and from the elbow to the wrist, the origins of joints 2, 4 and 6 as in
+//! `SevenDofSweep::arm_angle_of`, whose positions come from `Kinematics::link_frames`. The
+//! clearance of a configuration is the least distance between these segments and the surfaces
+//! of the obstacles, negative where a segment passes through one. To account for the thickness
+//! of the links, inflate the obstacles by the radius of the links.
+
+use {
+    crate::{
+        redundancy::{RedundancyOptimum, RedundancyParameter},
+        robot::{IkSolution, SevenDofSweep},
+    },
+    alloc::vec::Vec,
+    nalgebra::{Matrix3, SVector, Vector3},
+};
+
+/// A sphere or capsule in the world frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Obstacle {
+    Sphere {
+        center: Vector3<f64>,
+        radius: f64,
+    },
+    /// The points within `radius` of the segment from `a` to `b`
+    Capsule {
+        a: Vector3<f64>,
+        b: Vector3<f64>,
+        radius: f64,
+    },
+}
+
+impl Obstacle {
+    /// The least distance between the segment from `p` to `q` and the surface of the obstacle,
+    /// negative if the segment passes through it
+    pub fn distance_to_segment(&self, p: &Vector3<f64>, q: &Vector3<f64>) -> f64 {
+        match self {
+            Obstacle::Sphere { center, radius } => {
+                (closest_on_segment(center, p, q) - center).norm() - radius
+            }
+            Obstacle::Capsule { a, b, radius } => segment_distance(p, q, a, b) - radius,
+        }
+    }
+}
+
+/// The point of the segment from `p` to `q` closest to `x`
+fn closest_on_segment(x: &Vector3<f64>, p: &Vector3<f64>, q: &Vector3<f64>) -> Vector3<f64> {
+    let d = q - p;
+    let length_squared = d.norm_squared();
+    if length_squared == 0.0 {
+        return *p;
+    }
+    p + d * ((x - p).dot(&d) / length_squared).clamp(0.0, 1.0)
+}
+
+/// The distance between the segments from `p1` to `q1` and from `p2` to `q2`
+fn segment_distance(
+    p1: &Vector3<f64>,
+    q1: &Vector3<f64>,
+    p2: &Vector3<f64>,
+    q2: &Vector3<f64>,
+) -> f64 {
+    let d1 = q1 - p1;
+    let d2 = q2 - p2;
+    let r = p1 - p2;
+    let (a, e, f) = (d1.norm_squared(), d2.norm_squared(), d2.dot(&r));
+
+    // The parameters of the closest points along each segment, clamped to the segments
+    let (s, t) = if a == 0.0 && e == 0.0 {
+        (0.0, 0.0)
+    } else if a == 0.0 {
+        (0.0, (f / e).clamp(0.0, 1.0))
+    } else {
+        let c = d1.dot(&r);
+        if e == 0.0 {
+            ((-c / a).clamp(0.0, 1.0), 0.0)
+        } else {
+            let b = d1.dot(&d2);
+            let denominator = a * e - b * b;
+            let s = if denominator > 0.0 {
+                ((b * f - c * e) / denominator).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let t = (b * s + f) / e;
+            if t < 0.0 {
+                ((-c / a).clamp(0.0, 1.0), 0.0)
+            } else if t > 1.0 {
+                (((b - c) / a).clamp(0.0, 1.0), 1.0)
+            } else {
+                (s, t)
+            }
+        }
+    };
+
+    ((p1 + d1 * s) - (p2 + d2 * t)).norm()
+}
+
+/// An exact solution of a sweep sample with its clearance
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClearanceCandidate {
+    pub q_free: f64,
+    pub solution: IkSolution<7>,
+    pub clearance: f64,
+}
+
+/// The candidates of a sweep with their clearances, and the configuration with the most
+#[derive(Debug, Clone)]
+pub struct ClearanceSweep {
+    /// The exact solutions of every sample of the free joint, in order of the free joint
+    pub candidates: Vec<ClearanceCandidate>,
+    /// The solution with the most clearance, refined between the samples, with the negated
+    /// clearance as its cost, or `None` if no sample has an exact solution
+    pub best: Option<RedundancyOptimum>,
+}
+
+impl SevenDofSweep {
+    /// The least distance between the upper arm and forearm of `q` and the `obstacles`, infinite
+    /// without obstacles
+    pub fn arm_clearance(&self, q: &SVector<f64, 7>, obstacles: &[Obstacle]) -> f64 {
+        let origins: Vec<Vector3<f64>> = self
+            .kinematics()
+            .link_frames(q)
+            .map(|(_, origin)| origin)
+            .collect();
+        let (shoulder, elbow, wrist) = (origins[1], origins[3], origins[5]);
+
+        obstacles
+            .iter()
+            .flat_map(|obstacle| {
+                [
+                    obstacle.distance_to_segment(&shoulder, &elbow),
+                    obstacle.distance_to_segment(&elbow, &wrist),
+                ]
+            })
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    /// Sweep the free joint, returning the clearance of every exact solution along with the
+    /// solution that maximizes it, see `optimize_redundancy`
+    pub fn solve_with_clearance(
+        &self,
+        rot: Matrix3<f64>,
+        translation: Vector3<f64>,
+        obstacles: &[Obstacle],
+    ) -> ClearanceSweep {
+        let candidates = self
+            .solve(rot, translation)
+            .into_iter()
+            .flat_map(|sample| {
+                sample
+                    .solutions
+                    .into_iter()
+                    .filter(|solution| !solution.is_ls)
+                    .map(move |solution| ClearanceCandidate {
+                        q_free: sample.q_free,
+                        clearance: self.arm_clearance(&solution.q, obstacles),
+                        solution,
+                    })
+            })
+            .collect();
+
+        let best = self.optimize_redundancy(
+            rot,
+            translation,
+            RedundancyParameter::LockedJoint,
+            |solution| -self.arm_clearance(&solution.q, obstacles),
+        );
+
+        ClearanceSweep { candidates, best }
+    }
+}
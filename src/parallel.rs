@@ -0,0 +1,255 @@
+//! Kinematics of parallel robots.
+//!
+//! Unlike serial arms, whose inverse kinematics is the hard direction, the legs of a parallel
+//! robot are solved one at a time from the pose of the platform, and it is the forward
+//! kinematics that couples them. The solutions follow the subproblem conventions: a value and
+//! whether it is a least squares approximation.
+
+use {
+    crate::{solutionset::SolutionSet2, subproblems::subproblem3},
+    core::f64::consts::{PI, TAU},
+    nalgebra::{Isometry3, Matrix6, Translation3, UnitQuaternion, Vector3, Vector6},
+};
+
+#[cfg(all(not(feature = "std"), target_os = "none"))]
+use num_traits::Float;
+
+/// The points at distances `radii` from each of the three `centers`, the intersection of three
+/// spheres. If the spheres do not meet, the point in the plane of the centers that is closest to
+/// doing so is returned as a least squares solution.
+pub fn sphere_intersection(
+    centers: &[Vector3<f64>; 3],
+    radii: &[f64; 3],
+) -> (SolutionSet2<Vector3<f64>>, bool) {
+    let c_12 = centers[1] - centers[0];
+    let c_13 = centers[2] - centers[0];
+
+    let d = c_12.norm();
+    let e_x = c_12 / d;
+    let i = e_x.dot(&c_13);
+    let e_y = (c_13 - e_x * i).normalize();
+    let j = e_y.dot(&c_13);
+    let e_z = e_x.cross(&e_y);
+
+    let x = (radii[0] * radii[0] - radii[1] * radii[1] + d * d) / (2.0 * d);
+    let y = (radii[0] * radii[0] - radii[2] * radii[2] + i * i + j * j) / (2.0 * j) - x * i / j;
+    let in_plane = centers[0] + e_x * x + e_y * y;
+
+    let z_sq = radii[0] * radii[0] - x * x - y * y;
+    if z_sq < 0.0 {
+        return (SolutionSet2::One(in_plane), true);
+    }
+
+    let z = z_sq.sqrt();
+    (
+        SolutionSet2::Two(in_plane + e_z * z, in_plane - e_z * z),
+        false,
+    )
+}
+
+/// A classic delta robot: three arms spaced evenly about the vertical axis, each a revolute
+/// shoulder on the base and a parallelogram forearm down to the effector, which only translates.
+///
+/// The base frame is at the center of the shoulders with `z` up, and the first arm points along
+/// `x`. A joint angle is zero with its upper arm horizontal and positive as the arm swings down.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Delta {
+    /// Distance from the base center to each shoulder axis
+    pub base_radius: f64,
+    /// Length from a shoulder to its elbow
+    pub upper_arm: f64,
+    /// Length of the parallelogram from an elbow to the effector
+    pub forearm: f64,
+    /// Distance from the effector center to the end of each forearm
+    pub effector_radius: f64,
+}
+
+impl Delta {
+    pub fn new(base_radius: f64, upper_arm: f64, forearm: f64, effector_radius: f64) -> Self {
+        Delta {
+            base_radius,
+            upper_arm,
+            forearm,
+            effector_radius,
+        }
+    }
+
+    /// The horizontal direction the arm `i` points in
+    fn arm_direction(i: usize) -> Vector3<f64> {
+        let angle = TAU * i as f64 / 3.0;
+        Vector3::new(angle.cos(), angle.sin(), 0.0)
+    }
+
+    /// The positions of the three elbows
+    pub fn elbows(&self, q: &Vector3<f64>) -> [Vector3<f64>; 3] {
+        core::array::from_fn(|i| {
+            let u = Self::arm_direction(i);
+            u * (self.base_radius + self.upper_arm * q[i].cos())
+                - Vector3::z() * (self.upper_arm * q[i].sin())
+        })
+    }
+
+    /// The position of the effector center for the joint angles `q`. Of the two intersections of
+    /// the forearm spheres, the one below the elbows is returned. If the forearms cannot meet,
+    /// the closest point is returned as a least squares solution.
+    pub fn forward_kinematics(&self, q: &Vector3<f64>) -> (Vector3<f64>, bool) {
+        let elbows = self.elbows(q);
+        // Moving each elbow in by the effector radius turns the legs into spheres about one point
+        let centers =
+            core::array::from_fn(|i| elbows[i] - Self::arm_direction(i) * self.effector_radius);
+
+        let (points, is_ls) = sphere_intersection(&centers, &[self.forearm; 3]);
+        let lowest = points
+            .get_all()
+            .reduce(|a, b| if b.z < a.z { b } else { a })
+            .expect("sphere intersection always returns a point");
+        (lowest, is_ls)
+    }
+
+    /// The joint angles that place the effector center at `p`, with every elbow pointing out. An
+    /// arm that cannot reach gets the angle that brings its forearm closest, and the solution is
+    /// reported as least squares.
+    pub fn inverse_kinematics(&self, p: &Vector3<f64>) -> (Vector3<f64>, bool) {
+        let mut q = Vector3::zeros();
+        let mut any_ls = false;
+
+        for i in 0..3 {
+            let u = Self::arm_direction(i);
+            let (t, is_ls) = subproblem3(
+                &(u * self.upper_arm),
+                &(p + u * (self.effector_radius - self.base_radius)),
+                &Vector3::z().cross(&u),
+                self.forearm,
+            );
+
+            q[i] = t
+                .get_all()
+                .reduce(|a, b| if b.cos() > a.cos() { b } else { a })
+                .expect("subproblem 3 always returns an angle");
+            any_ls |= is_ls;
+        }
+
+        (q, any_ls)
+    }
+}
+
+/// A Stewart platform: a moving platform carried by six legs of adjustable length, each between a
+/// spherical joint on the base and one on the platform.
+///
+/// The pose of the platform is the transform from its frame to the base frame, and the legs are
+/// described by the positions of their joints in those frames.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StewartPlatform {
+    /// The base joint of each leg, in the base frame
+    pub base_joints: [Vector3<f64>; 6],
+    /// The platform joint of each leg, in the platform frame
+    pub platform_joints: [Vector3<f64>; 6],
+}
+
+impl StewartPlatform {
+    pub fn new(base_joints: [Vector3<f64>; 6], platform_joints: [Vector3<f64>; 6]) -> Self {
+        StewartPlatform {
+            base_joints,
+            platform_joints,
+        }
+    }
+
+    /// The common layout with the joints in three pairs on circles about the `z` axes of the
+    /// base and the platform. Each base pair is `base_spread` radians apart and centered on
+    /// multiples of 120 degrees from `x`, and each platform pair is `platform_spread` apart and
+    /// centered halfway between them, so that neighboring legs cross over.
+    pub fn symmetric(
+        base_radius: f64,
+        platform_radius: f64,
+        base_spread: f64,
+        platform_spread: f64,
+    ) -> Self {
+        let on_circle =
+            |radius: f64, angle: f64| Vector3::new(angle.cos(), angle.sin(), 0.0) * radius;
+        let side = |i: usize| [-1.0, 1.0][i % 2];
+        let center = |i: usize| TAU * (i / 2) as f64 / 3.0;
+
+        StewartPlatform {
+            base_joints: core::array::from_fn(|i| {
+                on_circle(base_radius, center(i) + side(i) * base_spread / 2.0)
+            }),
+            platform_joints: core::array::from_fn(|i| {
+                on_circle(
+                    platform_radius,
+                    center(i) + side(i) * (PI / 3.0 - platform_spread / 2.0),
+                )
+            }),
+        }
+    }
+
+    /// The vectors from the base joint to the platform joint of each leg
+    pub fn legs(&self, pose: &Isometry3<f64>) -> [Vector3<f64>; 6] {
+        core::array::from_fn(|i| {
+            pose.rotation * self.platform_joints[i] + pose.translation.vector - self.base_joints[i]
+        })
+    }
+
+    /// The lengths of the legs that place the platform at `pose`
+    pub fn inverse_kinematics(&self, pose: &Isometry3<f64>) -> Vector6<f64> {
+        Vector6::from_iterator(self.legs(pose).iter().map(|leg| leg.norm()))
+    }
+
+    /// The Jacobian from the twist of the platform to the rates of the legs. The twist is in the
+    /// base frame, with the angular velocity in the first three columns and the linear velocity
+    /// of the platform origin in the last three, matching `Kinematics::jacobian`. It is the
+    /// inverse of the usual Jacobian of a serial arm, and is singular where the platform gains a
+    /// freedom.
+    pub fn jacobian(&self, pose: &Isometry3<f64>) -> Matrix6<f64> {
+        let legs = self.legs(pose);
+        let mut jacobian = Matrix6::zeros();
+
+        for (i, leg) in legs.iter().enumerate() {
+            let n = leg.normalize();
+            let arm = pose.rotation * self.platform_joints[i];
+            jacobian
+                .fixed_view_mut::<1, 3>(i, 0)
+                .copy_from(&arm.cross(&n).transpose());
+            jacobian
+                .fixed_view_mut::<1, 3>(i, 3)
+                .copy_from(&n.transpose());
+        }
+
+        jacobian
+    }
+
+    /// The pose of the platform for the leg `lengths`, found with Newton's method from `initial`.
+    /// The platform generally has several poses for the same lengths, and the one nearest to
+    /// `initial` is found. If the lengths are not within `tolerance` of `lengths` after
+    /// `max_iterations`, or the Jacobian becomes singular, the last pose is returned as a least
+    /// squares solution.
+    pub fn forward_kinematics(
+        &self,
+        lengths: &Vector6<f64>,
+        initial: &Isometry3<f64>,
+        tolerance: f64,
+        max_iterations: usize,
+    ) -> (Isometry3<f64>, bool) {
+        let mut pose = *initial;
+
+        for _ in 0..max_iterations {
+            let residual = self.inverse_kinematics(&pose) - lengths;
+            if residual.amax() < tolerance {
+                return (pose, false);
+            }
+
+            let Some(step) = self.jacobian(&pose).lu().solve(&-residual) else {
+                break;
+            };
+            let twist: Vector3<f64> = step.fixed_rows::<3>(0).into();
+            pose = Isometry3::from_parts(
+                Translation3::from(pose.translation.vector + step.fixed_rows::<3>(3)),
+                UnitQuaternion::from_scaled_axis(twist) * pose.rotation,
+            );
+        }
+
+        let is_ls = (self.inverse_kinematics(&pose) - lengths).amax() >= tolerance;
+        (pose, is_ls)
+    }
+}
@@ -0,0 +1,91 @@
+//! Solving targets that leave part of the tool orientation free.
+//!
+//! Many tasks only constrain part of the pose: drilling or spraying fixes the position and the
+//! direction of the tool z axis but not the roll about it, and some tasks only fix the position.
+//! Forcing an arbitrary full pose on such a task throws away every configuration that reaches the
+//! target with a different free rotation. `PartialPoseSolver` instead samples the free rotation and
+//! solves the full pose at every sample, returning the families of solutions that reach the
+//! partial pose.
+
+use {
+    crate::{
+        robot::{IKSolver, IkSolutionSet},
+        subproblems::auxiliary::rot,
+        workspace::{align_z, sphere_orientations},
+    },
+    alloc::vec::Vec,
+    core::f64::consts::{PI, TAU},
+    nalgebra::{Matrix3, Vector3},
+};
+
+/// The solutions found with the free rotation of a partial pose fixed to give `rotation`
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PartialPoseSample {
+    /// The full orientation of the tool that was solved for
+    pub rotation: Matrix3<f64>,
+    pub solutions: IkSolutionSet,
+}
+
+/// Solves position-only and axis-alignment targets by sampling the rotation they leave free
+pub struct PartialPoseSolver<S: IKSolver> {
+    solver: S,
+    rolls: usize,
+    directions: usize,
+}
+
+impl<S: IKSolver> PartialPoseSolver<S> {
+    /// A solver sampling 36 rolls about the tool axis, and 64 axis directions for position-only
+    /// targets
+    pub fn new(solver: S) -> Self {
+        PartialPoseSolver {
+            solver,
+            rolls: 36,
+            directions: 64,
+        }
+    }
+
+    /// Set the number of evenly spaced rolls about the tool z axis
+    pub fn with_rolls(mut self, rolls: usize) -> Self {
+        self.rolls = rolls;
+        self
+    }
+
+    /// Set the number of tool z axis directions of position-only targets, spread evenly over the
+    /// sphere
+    pub fn with_directions(mut self, directions: usize) -> Self {
+        self.directions = directions;
+        self
+    }
+
+    /// Solve for the tool z axis pointing along `axis` at `translation`, with the roll about the
+    /// axis free. The samples are ordered by roll over `[-π, π)`, so consecutive samples trace the
+    /// families of solutions.
+    pub fn solve_axis(
+        &self,
+        axis: &Vector3<f64>,
+        translation: &Vector3<f64>,
+    ) -> Vec<PartialPoseSample> {
+        let tilt = align_z(&axis.normalize());
+
+        (0..self.rolls)
+            .map(|i| tilt * rot(&Vector3::z(), -PI + TAU * i as f64 / self.rolls as f64))
+            .map(|rotation| self.solve_at(rotation, translation))
+            .collect()
+    }
+
+    /// Solve for the tool at `translation`, with its orientation free
+    pub fn solve_position(&self, translation: &Vector3<f64>) -> Vec<PartialPoseSample> {
+        sphere_orientations(self.directions, self.rolls)
+            .into_iter()
+            .map(|rotation| self.solve_at(rotation, translation))
+            .collect()
+    }
+
+    fn solve_at(&self, rotation: Matrix3<f64>, translation: &Vector3<f64>) -> PartialPoseSample {
+        PartialPoseSample {
+            rotation,
+            solutions: self.solver.ik(rotation, *translation),
+        }
+    }
+}
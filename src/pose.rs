@@ -0,0 +1,443 @@
+use {
+    crate::{
+        config::{RotationPolicy, SolverConfig},
+        error::IkGeoError,
+    },
+    alloc::{format, string::ToString},
+    core::str::FromStr,
+    nalgebra::{Isometry3, Matrix3, Quaternion, Rotation3, Translation3, UnitQuaternion, Vector3},
+};
+
+#[cfg(feature = "std")]
+use crate::error::parse_list;
+
+/// A rigid transform, given as the orientation and position of the tool in the base frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "crate::schema::PoseData", into = "crate::schema::PoseData")
+)]
+pub struct Pose {
+    pub rotation: Matrix3<f64>,
+    pub translation: Vector3<f64>,
+}
+
+impl Pose {
+    pub fn new(rotation: Matrix3<f64>, translation: Vector3<f64>) -> Self {
+        Self {
+            rotation,
+            translation,
+        }
+    }
+
+    pub fn identity() -> Self {
+        Self::new(Matrix3::identity(), Vector3::zeros())
+    }
+
+    /// The pose at `origin` with its z axis along `z_axis` and its x axis along the part of
+    /// `x_hint` perpendicular to it, completed to a right-handed frame. Returns `None` if either
+    /// vector is zero or they are parallel.
+    pub fn from_z_and_x(
+        origin: Vector3<f64>,
+        z_axis: Vector3<f64>,
+        x_hint: Vector3<f64>,
+    ) -> Option<Self> {
+        let z = z_axis.try_normalize(DEGENERATE_TOLERANCE)?;
+        let x = (x_hint - z * z.dot(&x_hint)).try_normalize(DEGENERATE_TOLERANCE)?;
+        Some(Self::new(
+            Matrix3::from_columns(&[x, z.cross(&x), z]),
+            origin,
+        ))
+    }
+
+    /// The pose at `origin` with its x axis along `x_axis` and its y axis along the part of
+    /// `y_hint` perpendicular to it, completed to a right-handed frame. Returns `None` if either
+    /// vector is zero or they are parallel.
+    pub fn from_x_and_y(
+        origin: Vector3<f64>,
+        x_axis: Vector3<f64>,
+        y_hint: Vector3<f64>,
+    ) -> Option<Self> {
+        let x = x_axis.try_normalize(DEGENERATE_TOLERANCE)?;
+        let y = (y_hint - x * x.dot(&y_hint)).try_normalize(DEGENERATE_TOLERANCE)?;
+        Some(Self::new(
+            Matrix3::from_columns(&[x, y, x.cross(&y)]),
+            origin,
+        ))
+    }
+
+    /// The pose at `origin` whose z axis points at `z_target`, as a tool approaching a point or
+    /// a camera looking at it, with the x axis towards `x_hint` as in `from_z_and_x`
+    pub fn look_at(
+        origin: Vector3<f64>,
+        z_target: Vector3<f64>,
+        x_hint: Vector3<f64>,
+    ) -> Option<Self> {
+        Self::from_z_and_x(origin, z_target - origin, x_hint)
+    }
+
+    /// The frame taught by three points, as with the user frames of robot controllers: the
+    /// origin, a point along the x axis and a point in the xy plane on the side of positive y.
+    /// Returns `None` if the points are collinear or coincide.
+    pub fn from_three_points(
+        origin: Vector3<f64>,
+        x_point: Vector3<f64>,
+        xy_point: Vector3<f64>,
+    ) -> Option<Self> {
+        Self::from_x_and_y(origin, x_point - origin, xy_point - origin)
+    }
+}
+
+/// The length below which a vector is taken as zero when building a frame from it
+const DEGENERATE_TOLERANCE: f64 = 1e-12;
+
+impl From<(Matrix3<f64>, Vector3<f64>)> for Pose {
+    fn from((rotation, translation): (Matrix3<f64>, Vector3<f64>)) -> Self {
+        Self::new(rotation, translation)
+    }
+}
+
+impl From<Pose> for (Matrix3<f64>, Vector3<f64>) {
+    fn from(pose: Pose) -> Self {
+        (pose.rotation, pose.translation)
+    }
+}
+
+impl From<Isometry3<f64>> for Pose {
+    fn from(isometry: Isometry3<f64>) -> Self {
+        Self::new(
+            isometry.rotation.to_rotation_matrix().into_inner(),
+            isometry.translation.vector,
+        )
+    }
+}
+
+impl From<Pose> for Isometry3<f64> {
+    fn from(pose: Pose) -> Self {
+        Isometry3::from_parts(
+            Translation3::from(pose.translation),
+            Rotation3::from_matrix(&pose.rotation).into(),
+        )
+    }
+}
+
+/// How far `rot` is from being a rotation matrix: the Frobenius norm of `R^T R - I`, or 2 if
+/// the determinant is negative, as for a reflection
+pub fn rotation_defect(rot: &Matrix3<f64>) -> f64 {
+    if rot.determinant() < 0.0 {
+        return 2.0;
+    }
+    (rot.transpose() * rot - Matrix3::identity()).norm()
+}
+
+/// The rotation matrix closest to `rot` in the Frobenius norm, `U V^T` from the SVD
+/// `rot = U S V^T`, with the sign of the smallest singular direction flipped if needed to keep
+/// the determinant at 1
+pub fn project_to_rotation(rot: &Matrix3<f64>) -> Matrix3<f64> {
+    let svd = rot.svd(true, true);
+    let (mut u, v_t) = (svd.u.unwrap(), svd.v_t.unwrap());
+    if (u * v_t).determinant() < 0.0 {
+        // The singular values are sorted, so the last one is the smallest
+        u.column_mut(2).neg_mut();
+    }
+    u * v_t
+}
+
+/// The rotation to solve for in place of the target rotation `rot`, following the
+/// `rotation_policy` of `config`: `rot` itself if it is within `rotation_tolerance` of a rotation
+/// matrix or the policy ignores it, the closest rotation matrix if the policy projects, and
+/// `IkGeoError::InvalidRotation` otherwise. Non-finite rotations are never valid.
+pub fn validate_pose(
+    rot: &Matrix3<f64>,
+    config: &SolverConfig,
+) -> Result<Matrix3<f64>, IkGeoError> {
+    if config.rotation_policy == RotationPolicy::Ignore {
+        return Ok(*rot);
+    }
+    let defect = rotation_defect(rot);
+    if defect <= config.rotation_tolerance {
+        return Ok(*rot);
+    }
+    match config.rotation_policy {
+        RotationPolicy::Project if rot.iter().all(|x| x.is_finite()) => {
+            Ok(project_to_rotation(rot))
+        }
+        _ => Err(IkGeoError::InvalidRotation {
+            defect,
+            determinant: rot.determinant(),
+        }),
+    }
+}
+
+/// The order of the rotations of roll, pitch and yaw angles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RpyConvention {
+    /// Roll, pitch and yaw about the fixed x, y and z axes in that order, `Rz * Ry * Rx`, as in
+    /// URDF and ROS
+    Extrinsic,
+    /// Roll, pitch and yaw about the moving x, y' and z'' axes in that order, `Rx * Ry * Rz`
+    Intrinsic,
+}
+
+/// How the orientation of a pose is written, ahead of the 3 values of the translation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RotationFormat {
+    /// The 9 values of the rotation matrix in row-major order, as `hardcoded_setup_from_string`
+    Matrix,
+    /// A quaternion with the scalar first, normalized when read
+    QuaternionWxyz,
+    /// A quaternion with the scalar last, as in ROS messages, normalized when read
+    QuaternionXyzw,
+    /// Roll, pitch and yaw in radians
+    Rpy(RpyConvention),
+    /// The 3 values of the axis, normalized when read, followed by the angle in radians
+    AxisAngle,
+}
+
+impl RotationFormat {
+    /// The names of the formats, as accepted by `from_str` and in file headers
+    pub const NAMES: [&'static str; 6] = [
+        "matrix",
+        "wxyz",
+        "xyzw",
+        "rpy",
+        "rpy_intrinsic",
+        "axis_angle",
+    ];
+
+    /// The number of values of the orientation
+    pub fn values(&self) -> usize {
+        match self {
+            RotationFormat::Matrix => 9,
+            RotationFormat::QuaternionWxyz
+            | RotationFormat::QuaternionXyzw
+            | RotationFormat::AxisAngle => 4,
+            RotationFormat::Rpy(_) => 3,
+        }
+    }
+
+    /// The format of a pose of `count` values including the translation, where the count alone
+    /// tells: 12 values are a rotation matrix and 6 are extrinsic roll, pitch and yaw. Quaternions
+    /// and axis-angle rotations both take 7 values, so they have to be named.
+    pub fn detect(count: usize) -> Option<Self> {
+        match count {
+            12 => Some(RotationFormat::Matrix),
+            6 => Some(RotationFormat::Rpy(RpyConvention::Extrinsic)),
+            _ => None,
+        }
+    }
+
+    /// The format named by a header line of the form `# rotation: <name>`, or `None` if the line
+    /// is not such a header
+    pub fn from_header(line: &str) -> Option<Result<Self, IkGeoError>> {
+        let (key, name) = line.trim().strip_prefix('#')?.split_once(':')?;
+        (key.trim() == "rotation").then(|| name.parse())
+    }
+
+    /// The rotation matrix of the orientation given by `values`, which must hold `self.values()`
+    /// numbers
+    pub fn rotation(&self, values: &[f64]) -> Matrix3<f64> {
+        let quaternion = |w: f64, x: f64, y: f64, z: f64| {
+            UnitQuaternion::from_quaternion(Quaternion::new(w, x, y, z))
+                .to_rotation_matrix()
+                .into_inner()
+        };
+        match *self {
+            RotationFormat::Matrix => Matrix3::from_row_slice(&values[..9]),
+            RotationFormat::QuaternionWxyz => {
+                quaternion(values[0], values[1], values[2], values[3])
+            }
+            RotationFormat::QuaternionXyzw => {
+                quaternion(values[3], values[0], values[1], values[2])
+            }
+            RotationFormat::Rpy(convention) => {
+                let (roll, pitch, yaw) = (values[0], values[1], values[2]);
+                let rotation = match convention {
+                    RpyConvention::Extrinsic => Rotation3::from_euler_angles(roll, pitch, yaw),
+                    RpyConvention::Intrinsic => {
+                        Rotation3::from_axis_angle(&Vector3::x_axis(), roll)
+                            * Rotation3::from_axis_angle(&Vector3::y_axis(), pitch)
+                            * Rotation3::from_axis_angle(&Vector3::z_axis(), yaw)
+                    }
+                };
+                rotation.into_inner()
+            }
+            RotationFormat::AxisAngle => {
+                let axis = Vector3::new(values[0], values[1], values[2]);
+                axis.try_normalize(0.0)
+                    .map_or_else(Matrix3::identity, |axis| {
+                        Rotation3::new(axis * values[3]).into_inner()
+                    })
+            }
+        }
+    }
+}
+
+impl FromStr for RotationFormat {
+    type Err = IkGeoError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.trim() {
+            "matrix" => Ok(RotationFormat::Matrix),
+            "wxyz" => Ok(RotationFormat::QuaternionWxyz),
+            "xyzw" => Ok(RotationFormat::QuaternionXyzw),
+            "rpy" => Ok(RotationFormat::Rpy(RpyConvention::Extrinsic)),
+            "rpy_intrinsic" => Ok(RotationFormat::Rpy(RpyConvention::Intrinsic)),
+            "axis_angle" => Ok(RotationFormat::AxisAngle),
+            _ => Err(IkGeoError::UnknownRotationFormat(name.trim().to_string())),
+        }
+    }
+}
+
+/// One value per pose, every `stride` values of `data`
+#[derive(Debug, Clone, Copy)]
+struct Column<'a> {
+    data: &'a [f64],
+    stride: usize,
+}
+
+impl Column<'_> {
+    fn get(&self, i: usize) -> f64 {
+        self.data[i * self.stride]
+    }
+}
+
+/// Poses given as quaternions and translations in structure-of-arrays layout, as the columns of
+/// an Arrow table or the rows of numpy arrays, read without copying them into a
+/// `Vec<(Matrix3<f64>, Vector3<f64>)>` first. Each rotation matrix is built from its quaternion,
+/// normalized, only when the pose is solved, see `IKSolver::solve_pose_batch`.
+#[derive(Debug, Clone, Copy)]
+pub struct PoseBatch<'a> {
+    /// The w, x, y and z components of the quaternions
+    quaternion: [Column<'a>; 4],
+    translation: [Column<'a>; 3],
+    len: usize,
+}
+
+impl<'a> PoseBatch<'a> {
+    /// The poses with the quaternion components `[w, x, y, z]` and translation components
+    /// `[x, y, z]` each in an array of its own, failing with `IkGeoError::DimensionMismatch` if
+    /// the arrays differ in length
+    pub fn from_columns(
+        quaternion: [&'a [f64]; 4],
+        translation: [&'a [f64]; 3],
+    ) -> Result<Self, IkGeoError> {
+        let len = translation[0].len();
+        if let Some(column) = quaternion
+            .iter()
+            .chain(&translation)
+            .find(|column| column.len() != len)
+        {
+            return Err(IkGeoError::DimensionMismatch {
+                expected: len,
+                found: column.len(),
+            });
+        }
+
+        let column = |data| Column { data, stride: 1 };
+        Ok(PoseBatch {
+            quaternion: quaternion.map(column),
+            translation: translation.map(column),
+            len,
+        })
+    }
+
+    /// The poses with 4 values per quaternion, in `format`, and 3 per translation, one pose after
+    /// the other, as in numpy arrays of shape `(n, 4)` and `(n, 3)`. Fails with
+    /// `IkGeoError::UnknownRotationFormat` if `format` is not a quaternion and with
+    /// `IkGeoError::DimensionMismatch` if the arrays do not hold the same number of poses.
+    pub fn from_packed(
+        quaternions: &'a [f64],
+        translations: &'a [f64],
+        format: RotationFormat,
+    ) -> Result<Self, IkGeoError> {
+        let order = match format {
+            RotationFormat::QuaternionWxyz => [0, 1, 2, 3],
+            RotationFormat::QuaternionXyzw => [3, 0, 1, 2],
+            format => return Err(IkGeoError::UnknownRotationFormat(format!("{format:?}"))),
+        };
+        let len = translations.len() / 3;
+        if 3 * len != translations.len() {
+            return Err(IkGeoError::DimensionMismatch {
+                expected: 3 * len,
+                found: translations.len(),
+            });
+        }
+        if quaternions.len() != 4 * len {
+            return Err(IkGeoError::DimensionMismatch {
+                expected: 4 * len,
+                found: quaternions.len(),
+            });
+        }
+
+        let column = |data: &'a [f64], offset: usize, stride| Column {
+            data: data.get(offset..).unwrap_or_default(),
+            stride,
+        };
+        Ok(PoseBatch {
+            quaternion: order.map(|offset| column(quaternions, offset, 4)),
+            translation: [0, 1, 2].map(|offset| column(translations, offset, 3)),
+            len,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The normalized quaternion of pose `i`
+    pub fn quaternion(&self, i: usize) -> UnitQuaternion<f64> {
+        let [w, x, y, z] = self.quaternion.map(|column| column.get(i));
+        UnitQuaternion::from_quaternion(Quaternion::new(w, x, y, z))
+    }
+
+    pub fn rotation(&self, i: usize) -> Matrix3<f64> {
+        self.quaternion(i).to_rotation_matrix().into_inner()
+    }
+
+    pub fn translation(&self, i: usize) -> Vector3<f64> {
+        Vector3::from(self.translation.map(|column| column.get(i)))
+    }
+
+    pub fn pose(&self, i: usize) -> Pose {
+        Pose::new(self.rotation(i), self.translation(i))
+    }
+
+    /// The rotation and translation of every pose, in order
+    pub fn iter(&self) -> impl Iterator<Item = (Matrix3<f64>, Vector3<f64>)> + '_ {
+        (0..self.len).map(|i| (self.rotation(i), self.translation(i)))
+    }
+}
+
+#[cfg(feature = "std")]
+impl Pose {
+    /// Parses a pose from comma separated values: the orientation in `format`, followed by the
+    /// translation. Without a format, it is detected from the number of values, see
+    /// `RotationFormat::detect`.
+    pub fn parse(raw: &str, format: Option<RotationFormat>) -> Result<Self, IkGeoError> {
+        let data = parse_list(raw)?;
+        let format = format
+            .or_else(|| RotationFormat::detect(data.len()))
+            .ok_or_else(|| IkGeoError::UnknownRotationFormat(raw.trim().to_string()))?;
+
+        let values = format.values();
+        if data.len() != values + 3 {
+            return Err(IkGeoError::DimensionMismatch {
+                expected: values + 3,
+                found: data.len(),
+            });
+        }
+
+        Ok(Pose::new(
+            format.rotation(&data[..values]),
+            Vector3::from_row_slice(&data[values..]),
+        ))
+    }
+}
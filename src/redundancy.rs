@@ -0,0 +1,143 @@
+//! Resolving the redundancy of 7-DOF arms by optimizing a cost over the self-motion.
+//!
+//! A pose of a 7-DOF arm is reached by a one-parameter family of configurations. Rather than
+//! returning all of them, `SevenDofSweep::optimize_redundancy` picks the one with the least cost,
+//! e.g. the farthest from the joint limits, the most manipulable or the one with the highest
+//! elbow. The free parameter, either the locked joint or the SEW arm angle, is sampled according
+//! to the `SweepStrategy` of the sweep with the analytic solver as the inner loop, and the best
+//! sample is refined with a golden-section search. The cost of every sample is returned as well,
+//! to inspect how the cost varies along the self-motion.
+
+use {
+    crate::{
+        limits::JointLimits,
+        robot::{
+            golden_section, IkSolution, IkSolutionSet, SevenDofSweep, SweepStrategy,
+            GRID_REFINE_TOLERANCE,
+        },
+    },
+    alloc::vec::Vec,
+    core::f64::consts::{PI, TAU},
+    nalgebra::{Matrix3, SVector, Vector3},
+};
+
+/// The free parameter of the self-motion that is optimized
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RedundancyParameter {
+    /// The joint the sweep locks, e.g. `q3` of the KUKA iiwa or `q6` of the RRC
+    LockedJoint,
+    /// The SEW arm angle, see `SevenDofSweep::arm_angle_of`
+    ArmAngle,
+}
+
+/// The least cost of the exact solutions at one value of the free parameter, infinite where
+/// there are none
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RedundancySample {
+    pub value: f64,
+    pub cost: f64,
+}
+
+/// The configuration with the least cost, with the cost landscape it was picked from
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RedundancyOptimum {
+    /// The value of the free parameter at the optimum
+    pub value: f64,
+    pub solution: IkSolution<7>,
+    pub cost: f64,
+    /// The samples of the free parameter, in order of their values
+    pub landscape: Vec<RedundancySample>,
+}
+
+/// How close `q` is to the limits: the sum over the joints of the squared distance from the
+/// middle of the range, relative to half the range. It is 0 in the middle of every range and 7
+/// at the limits of every joint.
+pub fn joint_limit_cost(limits: &JointLimits<7>, q: &SVector<f64, 7>) -> f64 {
+    (0..7)
+        .map(|i| {
+            let middle = (limits.lower[i] + limits.upper[i]) / 2.0;
+            let half_range = (limits.upper[i] - limits.lower[i]) / 2.0;
+            let relative = (q[i] - middle) / half_range;
+            relative * relative
+        })
+        .sum()
+}
+
+impl SevenDofSweep {
+    /// The negated manipulability of `q`, so that minimizing it keeps away from singularities
+    pub fn manipulability_cost(&self, q: &SVector<f64, 7>) -> f64 {
+        -self.kinematics().manipulability(q)
+    }
+
+    /// The negated height of the elbow of `q`, the origin of joint 4 as in `arm_angle_of`, so
+    /// that minimizing it raises the elbow
+    pub fn elbow_height_cost(&self, q: &SVector<f64, 7>) -> f64 {
+        -self.kinematics().joint_origins(q)[(2, 3)]
+    }
+
+    /// The exact solution with the least `cost` over the self-motion of the pose, found by
+    /// sampling `parameter` over `[-π, π)` and refining the best sample with a golden-section
+    /// search, down to the `GoldenSection` tolerance if one is set. Returns `None` if no sample
+    /// has an exact solution.
+    pub fn optimize_redundancy<F: Fn(&IkSolution<7>) -> f64>(
+        &self,
+        rot: Matrix3<f64>,
+        translation: Vector3<f64>,
+        parameter: RedundancyParameter,
+        cost: F,
+    ) -> Option<RedundancyOptimum> {
+        let (n, tolerance) = match self.strategy() {
+            SweepStrategy::Grid(n) => (n, GRID_REFINE_TOLERANCE),
+            SweepStrategy::GoldenSection(n, tolerance) => (n, tolerance),
+        };
+
+        let solve = |value: f64| -> IkSolutionSet<7> {
+            match parameter {
+                RedundancyParameter::LockedJoint => {
+                    self.solve_at(&rot, &translation, value).solutions
+                }
+                RedundancyParameter::ArmAngle => self.solve_with_arm_angle(rot, translation, value),
+            }
+        };
+        let best = |value: f64| {
+            solve(value)
+                .into_iter()
+                .filter(|solution| !solution.is_ls)
+                .map(|solution| (cost(&solution), solution))
+                .min_by(|a, b| a.0.total_cmp(&b.0))
+        };
+        let cost_at = |value: f64| best(value).map_or(f64::INFINITY, |(cost, _)| cost);
+
+        let step = TAU / n as f64;
+        let landscape: Vec<RedundancySample> = (0..n)
+            .map(|i| -PI + step * i as f64)
+            .map(|value| RedundancySample {
+                value,
+                cost: cost_at(value),
+            })
+            .collect();
+
+        let center = landscape
+            .iter()
+            .filter(|sample| sample.cost.is_finite())
+            .min_by(|a, b| a.cost.total_cmp(&b.cost))?
+            .value;
+        let refined = golden_section(cost_at, center - step, center + step, tolerance);
+
+        // The refinement can end up worse than the sample on a cost that is not unimodal
+        let (value, (cost, solution)) = [refined, center]
+            .into_iter()
+            .filter_map(|value| best(value).map(|best| (value, best)))
+            .min_by(|a, b| a.1 .0.total_cmp(&b.1 .0))?;
+
+        Some(RedundancyOptimum {
+            value,
+            solution,
+            cost,
+            landscape,
+        })
+    }
+}
@@ -0,0 +1,161 @@
+//! Robots of your own, looked up by name like the built-in ones.
+//!
+//! A `RobotDefinition` holds what it takes to build a `Robot`: its kinematics, the decomposition to
+//! solve it with and its joint limits. A `RobotRegistry` keeps definitions by name, matched
+//! ignoring case, and refuses names already taken by a built-in robot or an earlier definition.
+//!
+//! With the `std` feature, robots registered in the global registry, with `register_robot` or
+//! through `global()`, are found by `solver::solver_by_name` like the built-in robots, and so by the
+//! `ik-geo` command line solver. `Diagnostics::registered` measures the robots of a registry.
+
+use {
+    crate::{
+        error::IkGeoError,
+        inverse_kinematics::{auxiliary::Kinematics, family::KinematicFamily},
+        limits::JointLimits,
+        robot::Robot,
+        solver::{builtin_solver, PoseSolver},
+    },
+    alloc::{boxed::Box, collections::BTreeMap, string::String},
+};
+
+/// Everything needed to build a `Robot` of your own
+#[derive(Debug, Clone)]
+pub struct RobotDefinition {
+    pub kinematics: Kinematics<6, 7>,
+    /// The decomposition to solve with, detected from the kinematics when `None`
+    pub family: Option<KinematicFamily>,
+    pub joint_limits: Option<JointLimits>,
+}
+
+impl RobotDefinition {
+    pub fn new(kinematics: Kinematics<6, 7>) -> Self {
+        RobotDefinition {
+            kinematics,
+            family: None,
+            joint_limits: None,
+        }
+    }
+
+    /// Solve with the decomposition of `family` instead of the one detected from the kinematics
+    pub fn with_family(mut self, family: KinematicFamily) -> Self {
+        self.family = Some(family);
+        self
+    }
+
+    pub fn with_joint_limits(mut self, joint_limits: JointLimits) -> Self {
+        self.joint_limits = Some(joint_limits);
+        self
+    }
+
+    /// The robot solving these kinematics
+    pub fn robot(&self) -> Robot {
+        let kinematics = self.kinematics.clone();
+        let robot = match self.family {
+            Some(family) => Robot::from_family(family, kinematics),
+            None => Robot::from_kinematics(kinematics),
+        };
+        match self.joint_limits {
+            Some(joint_limits) => robot.with_joint_limits(joint_limits),
+            None => robot,
+        }
+    }
+}
+
+/// Robot definitions by name, see the `registry` module
+#[derive(Debug, Clone, Default)]
+pub struct RobotRegistry {
+    robots: BTreeMap<String, RobotDefinition>,
+}
+
+impl RobotRegistry {
+    pub const fn new() -> Self {
+        RobotRegistry {
+            robots: BTreeMap::new(),
+        }
+    }
+
+    /// Add `definition` under `name`, failing with `IkGeoError::DuplicateRobot` when a built-in
+    /// robot or another definition already has the name
+    pub fn register(&mut self, name: &str, definition: RobotDefinition) -> Result<(), IkGeoError> {
+        let key = name.to_ascii_lowercase();
+        if builtin_solver(&key).is_some() || self.robots.contains_key(&key) {
+            return Err(IkGeoError::DuplicateRobot(String::from(name)));
+        }
+        self.robots.insert(key, definition);
+        Ok(())
+    }
+
+    /// Add the robot described by `yaml`, with the layout of `schema::RobotData`, under the name of
+    /// the description, which is returned
+    #[cfg(feature = "yaml")]
+    pub fn register_yaml(&mut self, yaml: &str) -> Result<String, IkGeoError> {
+        let data: crate::schema::RobotData = serde_yaml::from_str(yaml)
+            .map_err(|e| IkGeoError::InvalidDescription(e.to_string()))?;
+        let name = data.name.clone().ok_or_else(|| {
+            IkGeoError::InvalidDescription(String::from("a registered robot needs a name"))
+        })?;
+        self.register(&name, RobotDefinition::try_from(data)?)?;
+        Ok(name)
+    }
+
+    /// Remove the definition called `name`, returning it
+    pub fn unregister(&mut self, name: &str) -> Option<RobotDefinition> {
+        self.robots.remove(&name.to_ascii_lowercase())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&RobotDefinition> {
+        self.robots.get(&name.to_ascii_lowercase())
+    }
+
+    /// The registered robot called `name`
+    pub fn robot(&self, name: &str) -> Option<Robot> {
+        self.get(name).map(RobotDefinition::robot)
+    }
+
+    /// The registered or built-in robot called `name`
+    pub fn solver(&self, name: &str) -> Option<Box<dyn PoseSolver + Send + Sync>> {
+        match self.robot(name) {
+            Some(robot) => Some(Box::new(robot)),
+            None => builtin_solver(name),
+        }
+    }
+
+    /// The names of the registered robots, in lower case
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.robots.keys().map(String::as_str)
+    }
+
+    /// The registered robots with their names
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &RobotDefinition)> {
+        self.robots
+            .iter()
+            .map(|(name, definition)| (name.as_str(), definition))
+    }
+
+    pub fn len(&self) -> usize {
+        self.robots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.robots.is_empty()
+    }
+}
+
+#[cfg(feature = "std")]
+static GLOBAL: std::sync::RwLock<RobotRegistry> = std::sync::RwLock::new(RobotRegistry::new());
+
+/// The registry `solver::solver_by_name` looks robots up in after the built-in ones
+#[cfg(feature = "std")]
+pub fn global() -> &'static std::sync::RwLock<RobotRegistry> {
+    &GLOBAL
+}
+
+/// Add `definition` to the global registry under `name`, see `RobotRegistry::register`
+#[cfg(feature = "std")]
+pub fn register_robot(name: &str, definition: RobotDefinition) -> Result<(), IkGeoError> {
+    GLOBAL
+        .write()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .register(name, definition)
+}
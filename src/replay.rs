@@ -0,0 +1,305 @@
+//! Capturing poses the solvers handle badly, and solving them again on their own.
+//!
+//! The diagnostics draw their poses at random, so a numerical failure they run into is gone with
+//! the next seed. A `ReplayCase` keeps the exact target of such a failure with the model it was
+//! solved with, and saves it as a small text file:
+//!
+//! ```text
+//! # ik-geo replay
+//! model: irb6640
+//! pose: 1,0,0,0,1,0,0,0,1,1.5,0,1.5
+//! residual: 0.00031
+//! ```
+//!
+//! The model is either the name of a robot found by `solver::solver_by_name`, or the kinematics of
+//! a `Robot` as `h` and `p` lines with the columns one after the other, along with optional `base`
+//! and `tool` lines. Poses are written as the 9 values of the rotation matrix in row-major order
+//! followed by the translation, with every value written exactly, so the replayed target is the
+//! captured one to the last bit.
+//!
+//! A `ReplayRecorder` writes a file for every solve or `accuracy_sweep` whose residual is above its
+//! threshold, and `replay` solves the target of a file again, e.g. under a debugger or with the
+//! `trace` feature.
+
+use {
+    crate::{
+        diagnostics::AccuracyReport,
+        error::{parse_values, IkGeoError},
+        inverse_kinematics::auxiliary::{Kinematics, KinematicsDyn},
+        pose::Pose,
+        robot::{IkSolutionSet, Robot},
+        solver::{solver_by_name, PoseSolver},
+    },
+    core::{fmt, str::FromStr},
+    nalgebra::{DVector, Isometry3, Matrix3, Vector3},
+    std::path::{Path, PathBuf},
+};
+
+/// The first line of a replay file
+const HEADER: &str = "# ik-geo replay";
+
+/// The robot a `ReplayCase` was solved with
+#[derive(Debug, Clone)]
+pub enum ReplayModel {
+    /// A robot found by `solver::solver_by_name`
+    Named(String),
+    /// A `Robot` of these kinematics, solved with the decomposition detected from them
+    Kinematics(Box<Kinematics<6, 7>>),
+}
+
+impl ReplayModel {
+    /// The solver of the model, failing with `IkGeoError::InvalidDescription` for an unknown name
+    pub fn solver(&self) -> Result<Box<dyn PoseSolver + Send + Sync>, IkGeoError> {
+        match self {
+            ReplayModel::Named(name) => solver_by_name(name).ok_or_else(|| {
+                IkGeoError::InvalidDescription(format!("there is no robot called \"{name}\""))
+            }),
+            ReplayModel::Kinematics(kin) => Ok(Box::new(Robot::from_kinematics(*kin.clone()))),
+        }
+    }
+}
+
+impl From<&Robot> for ReplayModel {
+    fn from(robot: &Robot) -> Self {
+        ReplayModel::Kinematics(Box::new(robot.kinematics().clone()))
+    }
+}
+
+/// A target solved badly, with the model it was solved with, see the `replay` module. Displays as
+/// the content of its replay file, and parses from it.
+#[derive(Debug, Clone)]
+pub struct ReplayCase {
+    pub model: ReplayModel,
+    pub pose: Pose,
+    /// The residual when the case was captured, infinite when there was no solution
+    pub residual: f64,
+}
+
+/// The result of solving a `ReplayCase` again
+#[derive(Debug, Clone)]
+pub struct Replay {
+    pub solutions: IkSolutionSet,
+    /// The smallest residual of the solutions, measured with the forward kinematics of the model,
+    /// infinite when there are none
+    pub residual: f64,
+}
+
+/// The smallest residual of `solutions` reaching `pose` with the forward kinematics of `kin`
+fn residual(kin: &KinematicsDyn, pose: &Pose, solutions: &IkSolutionSet) -> f64 {
+    solutions
+        .iter()
+        .filter_map(|solution| {
+            let q = DVector::from_column_slice(solution.q.as_slice());
+            let (r, t) = kin.forward_kinematics(&q).ok()?;
+            Some((r - pose.rotation).norm() + (t - pose.translation).norm())
+        })
+        .reduce(f64::min)
+        .unwrap_or(f64::INFINITY)
+}
+
+impl ReplayCase {
+    pub fn new(model: ReplayModel, pose: Pose, residual: f64) -> Self {
+        ReplayCase {
+            model,
+            pose,
+            residual,
+        }
+    }
+
+    /// Solve the target again with the model
+    pub fn replay(&self) -> Result<Replay, IkGeoError> {
+        trace_span!(INFO, "replay", captured_residual = self.residual);
+        let solver = self.model.solver()?;
+        let solutions = solver.solve(&self.pose);
+        let residual = residual(solver.kinematics(), &self.pose, &solutions);
+        trace_event!(DEBUG, residual, solutions = solutions.len(), "replayed");
+        Ok(Replay {
+            solutions,
+            residual,
+        })
+    }
+
+    /// Write the case to the file at `path`
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), IkGeoError> {
+        std::fs::write(path, self.to_string())
+            .map_err(|e| IkGeoError::InvalidDescription(e.to_string()))
+    }
+
+    /// Read the case saved in the file at `path`
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, IkGeoError> {
+        std::fs::read_to_string(path)
+            .map_err(|e| IkGeoError::InvalidDescription(e.to_string()))?
+            .parse()
+    }
+}
+
+/// The comma separated values of `values`, each written exactly
+fn join(values: impl IntoIterator<Item = f64>) -> String {
+    values
+        .into_iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// The row-major rotation and translation of `pose`
+fn pose_values(pose: &Pose) -> String {
+    join(
+        pose.rotation
+            .transpose()
+            .iter()
+            .chain(pose.translation.iter())
+            .copied(),
+    )
+}
+
+fn parse_pose(raw: &str) -> Result<Pose, IkGeoError> {
+    let data = parse_values(raw, 12)?;
+    Ok(Pose::new(
+        Matrix3::from_row_slice(&data[..9]),
+        Vector3::from_row_slice(&data[9..]),
+    ))
+}
+
+impl fmt::Display for ReplayCase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{HEADER}")?;
+        match &self.model {
+            ReplayModel::Named(name) => writeln!(f, "model: {name}")?,
+            ReplayModel::Kinematics(kin) => {
+                writeln!(f, "h: {}", join(kin.h.iter().copied()))?;
+                writeln!(f, "p: {}", join(kin.p.iter().copied()))?;
+                if kin.base != Isometry3::identity() {
+                    writeln!(f, "base: {}", pose_values(&kin.base.into()))?;
+                }
+                if kin.tool != Isometry3::identity() {
+                    writeln!(f, "tool: {}", pose_values(&kin.tool.into()))?;
+                }
+            }
+        }
+        writeln!(f, "pose: {}", pose_values(&self.pose))?;
+        writeln!(f, "residual: {}", self.residual)
+    }
+}
+
+impl FromStr for ReplayCase {
+    type Err = IkGeoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut name = None;
+        let mut kin = Kinematics::<6, 7>::new();
+        let mut has_kinematics = false;
+        let mut pose = None;
+        let mut residual = f64::INFINITY;
+
+        for line in s.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once(':').ok_or_else(|| {
+                IkGeoError::InvalidDescription(format!("expected a key and a value in \"{line}\""))
+            })?;
+            let value = value.trim();
+            match key.trim() {
+                "model" => name = Some(value.to_string()),
+                "h" => {
+                    kin.h.copy_from_slice(&parse_values(value, 18)?);
+                    has_kinematics = true;
+                }
+                "p" => kin.p.copy_from_slice(&parse_values(value, 21)?),
+                "base" => kin.base = parse_pose(value)?.into(),
+                "tool" => kin.tool = parse_pose(value)?.into(),
+                "pose" => pose = Some(parse_pose(value)?),
+                "residual" => {
+                    residual = value
+                        .parse()
+                        .map_err(|_| IkGeoError::MalformedInput(value.to_string()))?
+                }
+                key => {
+                    return Err(IkGeoError::InvalidDescription(format!(
+                        "unknown replay key \"{key}\""
+                    )))
+                }
+            }
+        }
+
+        let model = match (name, has_kinematics) {
+            (Some(name), false) => ReplayModel::Named(name),
+            (None, true) => ReplayModel::Kinematics(Box::new(kin)),
+            _ => {
+                return Err(IkGeoError::InvalidDescription(String::from(
+                    "a replay needs either a model name or kinematics",
+                )))
+            }
+        };
+        let pose = pose
+            .ok_or_else(|| IkGeoError::InvalidDescription(String::from("a replay needs a pose")))?;
+        Ok(ReplayCase::new(model, pose, residual))
+    }
+}
+
+/// Solve the target saved in the replay file at `path` again
+pub fn replay(path: impl AsRef<Path>) -> Result<Replay, IkGeoError> {
+    ReplayCase::load(path)?.replay()
+}
+
+/// Writes a replay file for every solve whose residual is above a threshold
+#[derive(Debug, Clone)]
+pub struct ReplayRecorder {
+    dir: PathBuf,
+    threshold: f64,
+}
+
+impl ReplayRecorder {
+    /// Write the replay files to the directory `dir`, which must exist, for residuals above
+    /// `threshold`
+    pub fn new(dir: impl Into<PathBuf>, threshold: f64) -> Self {
+        ReplayRecorder {
+            dir: dir.into(),
+            threshold,
+        }
+    }
+
+    /// Save `case` when its residual is above the threshold or NaN, returning the path of the
+    /// file, named `replay-<n>.txt` after the first number not taken in the directory
+    pub fn record(&self, case: &ReplayCase) -> Result<Option<PathBuf>, IkGeoError> {
+        if case.residual <= self.threshold {
+            return Ok(None);
+        }
+        let path = (0..)
+            .map(|n| self.dir.join(format!("replay-{n}.txt")))
+            .find(|path| !path.exists())
+            .expect("some file name is free");
+        case.save(&path)?;
+        Ok(Some(path))
+    }
+
+    /// Record solving `pose` with `model` to `solutions`, from the residuals the solutions report
+    pub fn check(
+        &self,
+        model: &ReplayModel,
+        pose: &Pose,
+        solutions: &IkSolutionSet,
+    ) -> Result<Option<PathBuf>, IkGeoError> {
+        let residual = solutions
+            .iter()
+            .map(|solution| solution.error)
+            .reduce(f64::min)
+            .unwrap_or(f64::INFINITY);
+        self.record(&ReplayCase::new(model.clone(), *pose, residual))
+    }
+
+    /// Record the worst poses of an `accuracy_sweep` of `model`, returning the paths of the files
+    pub fn record_sweep(
+        &self,
+        model: &ReplayModel,
+        report: &AccuracyReport,
+    ) -> Result<Vec<PathBuf>, IkGeoError> {
+        let mut paths = Vec::new();
+        for case in &report.worst {
+            let case = ReplayCase::new(model.clone(), case.pose, case.residual);
+            paths.extend(self.record(&case)?);
+        }
+        Ok(paths)
+    }
+}
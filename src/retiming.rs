@@ -0,0 +1,183 @@
+//! Timing a joint path within velocity and acceleration limits.
+//!
+//! `retime` moves along the piecewise linear path through the waypoints as fast as the limits
+//! allow, starting and ending at rest. Along each segment the speed follows a trapezoid:
+//! accelerating at the largest rate the joints allow, cruising at the largest speed they allow,
+//! and braking in time for the next waypoint. At a waypoint the path turns, and the speed there
+//! is limited so that the turn, spread over the neighboring half segments, stays within the
+//! acceleration limits. For the dense paths of `CartesianTracker`, this is close to the time
+//! optimal parametrization of the smooth path through the waypoints.
+
+use {
+    crate::trajectory::JointTrajectory,
+    alloc::vec::Vec,
+    nalgebra::{SVector, Vector6},
+};
+
+#[cfg(all(not(feature = "std"), target_os = "none"))]
+use num_traits::Float;
+
+/// Segments shorter than this are merged with the next one
+const MIN_SEGMENT_LENGTH: f64 = 1e-12;
+
+/// The largest speed and acceleration of each joint, in radians per second and per second squared
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DynamicLimits<const N: usize = 6> {
+    pub velocity: SVector<f64, N>,
+    pub acceleration: SVector<f64, N>,
+}
+
+impl<const N: usize> DynamicLimits<N> {
+    pub fn new(velocity: SVector<f64, N>, acceleration: SVector<f64, N>) -> Self {
+        DynamicLimits {
+            velocity,
+            acceleration,
+        }
+    }
+}
+
+/// A waypoint with the time it is passed and the joint velocity there
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimedWaypoint<const N: usize = 6> {
+    pub time: f64,
+    pub q: SVector<f64, N>,
+    pub velocity: SVector<f64, N>,
+}
+
+/// The waypoints of a path with the times they are passed, starting at zero
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimedTrajectory<const N: usize = 6> {
+    pub waypoints: Vec<TimedWaypoint<N>>,
+}
+
+impl<const N: usize> TimedTrajectory<N> {
+    /// The time the last waypoint is reached
+    pub fn duration(&self) -> f64 {
+        self.waypoints.last().map_or(0.0, |waypoint| waypoint.time)
+    }
+}
+
+/// The largest rate along the unit direction `u` for which no joint exceeds `limits`
+fn rate_along<const N: usize>(u: &SVector<f64, N>, limits: &SVector<f64, N>) -> f64 {
+    u.iter()
+        .zip(limits.iter())
+        .filter(|(u, _)| **u != 0.0)
+        .map(|(u, limit)| limit / u.abs())
+        .fold(f64::INFINITY, f64::min)
+}
+
+/// The time to cover `length` from speed `v0` to speed `v1`, accelerating and braking at
+/// `acceleration` and cruising at no more than `max_speed`
+fn segment_time(length: f64, v0: f64, v1: f64, max_speed: f64, acceleration: f64) -> f64 {
+    let peak = ((2.0 * acceleration * length + v0 * v0 + v1 * v1) / 2.0)
+        .sqrt()
+        .min(max_speed);
+    let ramps = (peak * peak - v0 * v0 + peak * peak - v1 * v1) / (2.0 * acceleration);
+    (2.0 * peak - v0 - v1) / acceleration + (length - ramps).max(0.0) / peak
+}
+
+/// Time the path through `q` as fast as `limits` allow, starting and ending at rest
+pub fn retime<const N: usize>(
+    q: &[SVector<f64, N>],
+    limits: &DynamicLimits<N>,
+) -> TimedTrajectory<N> {
+    let mut points: Vec<SVector<f64, N>> = Vec::with_capacity(q.len());
+    for point in q {
+        if points
+            .last()
+            .is_none_or(|last| (point - last).norm() >= MIN_SEGMENT_LENGTH)
+        {
+            points.push(*point);
+        }
+    }
+
+    if points.len() < 2 {
+        return TimedTrajectory {
+            waypoints: points
+                .into_iter()
+                .map(|q| TimedWaypoint {
+                    time: 0.0,
+                    q,
+                    velocity: SVector::zeros(),
+                })
+                .collect(),
+        };
+    }
+
+    let lengths: Vec<f64> = points.windows(2).map(|w| (w[1] - w[0]).norm()).collect();
+    let directions: Vec<SVector<f64, N>> = points
+        .windows(2)
+        .zip(&lengths)
+        .map(|(w, length)| (w[1] - w[0]) / *length)
+        .collect();
+    let max_speeds: Vec<f64> = directions
+        .iter()
+        .map(|u| rate_along(u, &limits.velocity))
+        .collect();
+    let accelerations: Vec<f64> = directions
+        .iter()
+        .map(|u| rate_along(u, &limits.acceleration))
+        .collect();
+
+    // The largest speed at each waypoint, first from the neighboring segments and the turn
+    let mut speeds = Vec::with_capacity(points.len());
+    speeds.push(0.0);
+    for i in 1..points.len() - 1 {
+        let turn = directions[i] - directions[i - 1];
+        let half_segments = (lengths[i - 1] + lengths[i]) / 2.0;
+        let turn_speed = rate_along(&turn, &(limits.acceleration * half_segments)).sqrt();
+        speeds.push(max_speeds[i - 1].min(max_speeds[i]).min(turn_speed));
+    }
+    speeds.push(0.0);
+
+    // Then from accelerating out of the previous waypoint and braking into the next one
+    for i in 0..lengths.len() {
+        let reachable = (speeds[i] * speeds[i] + 2.0 * accelerations[i] * lengths[i]).sqrt();
+        speeds[i + 1] = speeds[i + 1].min(reachable);
+    }
+    for i in (0..lengths.len()).rev() {
+        let stoppable =
+            (speeds[i + 1] * speeds[i + 1] + 2.0 * accelerations[i] * lengths[i]).sqrt();
+        speeds[i] = speeds[i].min(stoppable);
+    }
+
+    let mut waypoints = Vec::with_capacity(points.len());
+    let mut time = 0.0;
+    for (i, q) in points.iter().enumerate() {
+        if i > 0 {
+            let k = i - 1;
+            time += segment_time(
+                lengths[k],
+                speeds[k],
+                speeds[i],
+                max_speeds[k],
+                accelerations[k],
+            );
+        }
+
+        let direction = match (i.checked_sub(1), directions.get(i)) {
+            (Some(before), Some(after)) => (directions[before] + after) / 2.0,
+            (Some(before), None) => directions[before],
+            (None, Some(after)) => *after,
+            (None, None) => SVector::zeros(),
+        };
+        waypoints.push(TimedWaypoint {
+            time,
+            q: *q,
+            velocity: direction * speeds[i],
+        });
+    }
+
+    TimedTrajectory { waypoints }
+}
+
+impl JointTrajectory {
+    /// Time the reachable waypoints as fast as `limits` allow, see `retime`
+    pub fn retime(&self, limits: &DynamicLimits) -> TimedTrajectory {
+        let q: Vec<Vector6<f64>> = self.q().copied().collect();
+        retime(&q, limits)
+    }
+}
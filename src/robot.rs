@@ -1,34 +1,251 @@
+use crate::calibration::CalibrationSet;
+use crate::coordinated::{pair_solutions, relative_target, sort_pairs, ArmPair};
+use crate::error::IkGeoError;
 use crate::inverse_kinematics::{
-    auxiliary::{Kinematics, Matrix3x7}, hardcoded::*, setups::calculate_ik_error,
-    gen_six_dof as gen_six_dof_solver,
-    spherical as spherical_solver,
-    spherical_two_intersecting as spherical_two_intersecting_solver,
+    auxiliary::{expand_partial_solution, wrap_to_pi, Kinematics, KinematicsDyn, Matrix3x7},
+    gen_six_dof as gen_six_dof_solver, gen_six_dof_grid as gen_six_dof_grid_solver,
+    gen_six_dof_grid_with, gen_six_dof_with, two_intersecting_with,
+    hardcoded::*,
+    spherical as spherical_solver, spherical_two_intersecting as spherical_two_intersecting_solver,
     spherical_two_parallel as spherical_two_parallel_solver,
     three_parallel as three_parallel_solver,
     three_parallel_two_intersecting as three_parallel_two_intersecting_solver,
     two_intersecting as two_intersecting_solver,
+    two_intersecting_at_base as two_intersecting_at_base_solver,
     two_parallel as two_parallel_solver,
 };
+use crate::joint_mapping::{JointMapping, MappedRobot};
+use crate::locked_joint::FixedJointSolver;
+use crate::metrics::{Distance, JointMetric};
+use crate::pose::{validate_pose, PoseBatch};
+use crate::solutionset::{apply_singular_policy_into, expand_within_limits_into};
+use crate::subproblems::auxiliary::rot as rotation_about;
+pub use crate::{
+    inverse_kinematics::auxiliary::SolverScratch,
+    config::{ErrorMetric, RotationPolicy, SingularBranchPolicy, SolverConfig},
+    inverse_kinematics::family::{Classification, KinematicFamily},
+    inverse_kinematics::opw::OpwParameters,
+    limits::JointLimits,
+    solutionset::{IkSolution, IkSolutionSet, OutputFormat, SolutionBuffer, MAX_SOLUTIONS},
+};
+
+use alloc::{vec, vec::Vec};
+use core::cell::RefCell;
+use core::f64::consts::{PI, TAU};
+use nalgebra::{Matrix3, Matrix3x6, SVector, Vector3, Vector6};
+use setups::{
+    Irb120, Irb1200, Irb2600, Irb4600, Irb6640, MotomanGp8, SphericalBot, ThreeParallelBot,
+    TwoParallelBot, Ur5, UrModel,
+};
+
+#[cfg(all(not(feature = "std"), target_os = "none"))]
+use num_traits::Float;
 
-use nalgebra::{Matrix3, Matrix3x6, Vector3, Vector6};
-use setups::{Irb6640, SphericalBot, ThreeParallelBot, TwoParallelBot, Ur5};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 
 pub type IKFunction =
-    fn(&Matrix3<f64>, &Vector3<f64>, &Kinematics<6, 7>) -> (Vec<Vector6<f64>>, Vec<bool>);
+    fn(&Matrix3<f64>, &Vector3<f64>, &Kinematics<6, 7>, &SolverConfig) -> IkSolutionSet;
+
+/// A solver that keeps the samples of its search in a `SolverScratch`, see
+/// `Robot::ik_with_scratch`
+pub type ScratchIKFunction = fn(
+    &Matrix3<f64>,
+    &Vector3<f64>,
+    &Kinematics<6, 7>,
+    &SolverConfig,
+    &mut SolverScratch,
+) -> IkSolutionSet;
+
+/// A closed-form solver that writes its solutions into fixed capacity storage, see
+/// `Robot::solve_into`
+pub type BufferedIKFunction =
+    fn(&Matrix3<f64>, &Vector3<f64>, &Kinematics<6, 7>, &SolverConfig, &mut SolutionBuffer);
 
 pub struct Robot {
     sub_problem_solver: IKFunction,
+    /// The same solver writing into a `SolutionBuffer`, for the closed-form families
+    buffered_solver: Option<BufferedIKFunction>,
+    /// The same solver keeping its samples in a `SolverScratch`, for the families solved by
+    /// searching
+    scratch_solver: Option<ScratchIKFunction>,
     kinematics: Kinematics<6, 7>,
+    /// `kinematics` with the number of joints known at runtime, for `solver::PoseSolver`
+    kinematics_dyn: KinematicsDyn,
+    config: SolverConfig,
+    joint_limits: Option<JointLimits>,
+    /// The kinematics the decomposition solves when `kinematics` is calibrated
+    nominal: Option<Kinematics<6, 7>>,
 }
 
 pub trait IKSolver {
-    fn ik(&self, rot: Matrix3<f64>, translation: Vector3<f64>) -> Vec<(Vector6<f64>, bool)>;
+    fn ik(&self, rot: Matrix3<f64>, translation: Vector3<f64>) -> IkSolutionSet;
+
+    /// The metric the motion between solutions is measured with by default, e.g. by
+    /// `CartesianTracker`: weighted by link length for solvers that know their kinematics, and
+    /// with the same weight for every joint otherwise
+    fn joint_metric(&self) -> JointMetric {
+        JointMetric::default()
+    }
+
+    /// Yoshikawa's manipulability at `q`, for solvers that know their kinematics, e.g. for
+    /// `continuation::track` to find singularities without `SolverConfig::annotate`
+    fn manipulability(&self, _q: &Vector6<f64>) -> Option<f64> {
+        None
+    }
+
+    /// The limits the joint values of the solutions are kept within, e.g. for `CartesianTracker`
+    /// to unwrap the solutions only to revolutions within them
+    fn joint_limits(&self) -> Option<&JointLimits> {
+        None
+    }
+
+    /// Solve IK for every pose in `poses`, returning the solution sets in the same order.
+    /// With the `parallel` feature enabled the poses are distributed across the rayon thread pool.
+    fn solve_batch(&self, poses: &[(Matrix3<f64>, Vector3<f64>)]) -> Vec<IkSolutionSet>
+    where
+        Self: Sync,
+    {
+        #[cfg(feature = "parallel")]
+        let iter = poses.par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let iter = poses.iter();
+
+        iter.map(|(rot, translation)| self.ik(*rot, *translation))
+            .collect()
+    }
+
+    /// Solve IK for every pose of `batch` like `solve_batch`, building the rotation matrix of each
+    /// pose from its quaternion only as it is solved
+    fn solve_pose_batch(&self, batch: &PoseBatch) -> Vec<IkSolutionSet>
+    where
+        Self: Sync,
+    {
+        #[cfg(feature = "parallel")]
+        let iter = (0..batch.len()).into_par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let iter = 0..batch.len();
+
+        iter.map(|i| self.ik(batch.rotation(i), batch.translation(i)))
+            .collect()
+    }
+
+    /// The first solution that `accept` returns true for, e.g. the first free of collisions,
+    /// trying exact solutions before least squares approximations and each by lowest residual.
+    /// `accept` is called lazily in that order and not again once a solution is accepted, so an
+    /// expensive check runs on as few solutions as possible.
+    fn solve_filtered<F: FnMut(&Vector6<f64>) -> bool>(
+        &self,
+        rot: Matrix3<f64>,
+        translation: Vector3<f64>,
+        mut accept: F,
+    ) -> Option<IkSolution> {
+        let mut solutions = self.ik(rot, translation);
+        solutions.sort_by(|a, b| a.is_ls.cmp(&b.is_ls).then(a.error.total_cmp(&b.error)));
+        solutions.into_iter().find(|solution| accept(&solution.q))
+    }
+
+    /// Solve IK with every joint angle moved by whole turns to the revolution closest to
+    /// `q_current`, rather than into `(-π, π]`, so joints that turn more than once do not spin a
+    /// full turn to reach a solution
+    fn ik_near(
+        &self,
+        rot: Matrix3<f64>,
+        translation: Vector3<f64>,
+        q_current: &Vector6<f64>,
+    ) -> IkSolutionSet {
+        let mut solutions = self.ik(rot, translation);
+        solutions.unwrap_near(q_current, None);
+        solutions
+    }
+
+    /// Solve for the pose `(rot, translation)` with the rotation about `axis`, given in the tool
+    /// frame, left free, as for spray, weld and drill tools. The roll is sampled at
+    /// `TOOL_ROLL_SAMPLES` values, and the best solution of every branch over all samples is
+    /// returned. Branches without an exact solution have their roll refined by golden-section
+    /// search, and between exact solutions of a branch the one with the smallest roll is kept.
+    /// The residual of each solution is measured against the rolled pose it solves.
+    fn solve_with_free_tool_roll(
+        &self,
+        rot: Matrix3<f64>,
+        translation: Vector3<f64>,
+        axis: Vector3<f64>,
+    ) -> IkSolutionSet {
+        let solve = |roll: f64| self.ik(rot * rotation_about(&axis, roll), translation);
+        let better = |a: &(f64, IkSolution), b: &(f64, IkSolution)| {
+            if !a.1.is_ls && !b.1.is_ls {
+                a.0.abs() < b.0.abs()
+            } else {
+                a.1.error < b.1.error
+            }
+        };
+
+        let mut best: Vec<Option<(f64, IkSolution)>> = Vec::new();
+        let step = TAU / TOOL_ROLL_SAMPLES as f64;
+        for i in 0..TOOL_ROLL_SAMPLES {
+            let roll = -PI + step * i as f64;
+            for solution in solve(roll) {
+                if best.len() <= solution.branch {
+                    best.resize(solution.branch + 1, None);
+                }
+                let candidate = (roll, solution);
+                let slot = &mut best[candidate.1.branch];
+                if slot
+                    .as_ref()
+                    .is_none_or(|current| better(&candidate, current))
+                {
+                    *slot = Some(candidate);
+                }
+            }
+        }
+
+        for (branch, slot) in best.iter_mut().enumerate() {
+            let Some((roll, solution)) = slot else {
+                continue;
+            };
+            if !solution.is_ls {
+                continue;
+            }
+
+            let branch_error = |roll: f64| {
+                solve(roll)
+                    .iter()
+                    .find(|solution| solution.branch == branch)
+                    .map_or(f64::INFINITY, |solution| solution.error)
+            };
+            let refined = golden_section(
+                branch_error,
+                *roll - step,
+                *roll + step,
+                GRID_REFINE_TOLERANCE,
+            );
+            if let Some(candidate) = solve(refined)
+                .into_iter()
+                .find(|candidate| candidate.branch == branch)
+            {
+                if candidate.error < solution.error {
+                    *slot = Some((refined, candidate));
+                }
+            }
+        }
+
+        best.into_iter()
+            .flatten()
+            .map(|(_, solution)| solution)
+            .collect()
+    }
 }
 
 impl Robot {
     pub fn spherical_two_parallel(kinematics: Kinematics<6, 7>) -> Self {
         Robot {
             sub_problem_solver: spherical_two_parallel_solver,
+            buffered_solver: KinematicFamily::SphericalTwoParallel.buffered_solver(),
+            scratch_solver: KinematicFamily::SphericalTwoParallel.scratch_solver(),
+            config: SolverConfig::default(),
+            joint_limits: None,
+            nominal: None,
+            kinematics_dyn: KinematicsDyn::from(&kinematics),
             kinematics
         }
     }
@@ -36,6 +253,12 @@ impl Robot {
     pub fn spherical_two_intersecting(kinematics: Kinematics<6, 7>) -> Self {
         Robot {
             sub_problem_solver: spherical_two_intersecting_solver,
+            buffered_solver: KinematicFamily::SphericalTwoIntersecting.buffered_solver(),
+            scratch_solver: KinematicFamily::SphericalTwoIntersecting.scratch_solver(),
+            config: SolverConfig::default(),
+            joint_limits: None,
+            nominal: None,
+            kinematics_dyn: KinematicsDyn::from(&kinematics),
             kinematics
         }
     }
@@ -43,6 +266,12 @@ impl Robot {
     pub fn spherical(kinematics: Kinematics<6, 7>) -> Self {
         Robot {
             sub_problem_solver: spherical_solver,
+            buffered_solver: KinematicFamily::Spherical.buffered_solver(),
+            scratch_solver: KinematicFamily::Spherical.scratch_solver(),
+            config: SolverConfig::default(),
+            joint_limits: None,
+            nominal: None,
+            kinematics_dyn: KinematicsDyn::from(&kinematics),
             kinematics
         }
     }
@@ -50,6 +279,12 @@ impl Robot {
     pub fn three_parallel_two_intersecting(kinematics: Kinematics<6, 7>) -> Self {
         Robot {
             sub_problem_solver: three_parallel_two_intersecting_solver,
+            buffered_solver: KinematicFamily::ThreeParallelTwoIntersecting.buffered_solver(),
+            scratch_solver: KinematicFamily::ThreeParallelTwoIntersecting.scratch_solver(),
+            config: SolverConfig::default(),
+            joint_limits: None,
+            nominal: None,
+            kinematics_dyn: KinematicsDyn::from(&kinematics),
             kinematics
         }
     }
@@ -57,6 +292,12 @@ impl Robot {
     pub fn three_parallel(kinematics: Kinematics<6, 7>) -> Self {
         Robot {
             sub_problem_solver: three_parallel_solver,
+            buffered_solver: KinematicFamily::ThreeParallel.buffered_solver(),
+            scratch_solver: KinematicFamily::ThreeParallel.scratch_solver(),
+            config: SolverConfig::default(),
+            joint_limits: None,
+            nominal: None,
+            kinematics_dyn: KinematicsDyn::from(&kinematics),
             kinematics
         }
     }
@@ -64,6 +305,12 @@ impl Robot {
     pub fn two_parallel(kinematics: Kinematics<6, 7>) -> Self {
         Robot {
             sub_problem_solver: two_parallel_solver,
+            buffered_solver: KinematicFamily::TwoParallel.buffered_solver(),
+            scratch_solver: KinematicFamily::TwoParallel.scratch_solver(),
+            config: SolverConfig::default(),
+            joint_limits: None,
+            nominal: None,
+            kinematics_dyn: KinematicsDyn::from(&kinematics),
             kinematics
         }
     }
@@ -71,6 +318,25 @@ impl Robot {
     pub fn two_intersecting(kinematics: Kinematics<6, 7>) -> Self {
         Robot {
             sub_problem_solver: two_intersecting_solver,
+            buffered_solver: KinematicFamily::TwoIntersecting.buffered_solver(),
+            scratch_solver: KinematicFamily::TwoIntersecting.scratch_solver(),
+            config: SolverConfig::default(),
+            joint_limits: None,
+            nominal: None,
+            kinematics_dyn: KinematicsDyn::from(&kinematics),
+            kinematics
+        }
+    }
+
+    pub fn two_intersecting_at_base(kinematics: Kinematics<6, 7>) -> Self {
+        Robot {
+            sub_problem_solver: two_intersecting_at_base_solver,
+            buffered_solver: KinematicFamily::TwoIntersectingAtBase.buffered_solver(),
+            scratch_solver: KinematicFamily::TwoIntersectingAtBase.scratch_solver(),
+            config: SolverConfig::default(),
+            joint_limits: None,
+            nominal: None,
+            kinematics_dyn: KinematicsDyn::from(&kinematics),
             kinematics
         }
     }
@@ -78,66 +344,384 @@ impl Robot {
     pub fn gen_six_dof(kinematics: Kinematics<6, 7>) -> Self {
         Robot {
             sub_problem_solver: gen_six_dof_solver,
+            buffered_solver: KinematicFamily::GenSixDof.buffered_solver(),
+            scratch_solver: KinematicFamily::GenSixDof.scratch_solver(),
+            config: SolverConfig::default(),
+            joint_limits: None,
+            nominal: None,
+            kinematics_dyn: KinematicsDyn::from(&kinematics),
             kinematics
         }
     }
 
+    /// A robot solved with the decomposition of `family`, which must match the kinematics
+    pub fn from_family(family: KinematicFamily, kinematics: Kinematics<6, 7>) -> Self {
+        Robot {
+            sub_problem_solver: family.solver(),
+            buffered_solver: family.buffered_solver(),
+            scratch_solver: family.scratch_solver(),
+            config: SolverConfig::default(),
+            joint_limits: None,
+            nominal: None,
+            kinematics_dyn: KinematicsDyn::from(&kinematics),
+            kinematics,
+        }
+    }
+
+    /// A robot solved with the most specialized decomposition its kinematics allow, treating axes
+    /// and offsets within the default `ls_tolerance` of parallel or zero as such
+    pub fn from_kinematics(kinematics: Kinematics<6, 7>) -> Self {
+        let family = KinematicFamily::classify(&kinematics, SolverConfig::default().ls_tolerance);
+        Self::from_family(family, kinematics)
+    }
+
+    /// An ortho-parallel robot with a spherical wrist described by its OPW parameters, solved with
+    /// the `spherical_two_parallel` decomposition
+    pub fn from_opw(params: OpwParameters) -> Self {
+        Self::from_family(
+            KinematicFamily::SphericalTwoParallel,
+            Kinematics::from_opw(params),
+        )
+    }
+
+    /// Replace the tolerances used by the solver
+    pub fn with_config(mut self, config: SolverConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn config(&self) -> &SolverConfig {
+        &self.config
+    }
+
+    /// The kinematics the robot is solved with, including its calibration
+    pub fn kinematics(&self) -> &Kinematics<6, 7> {
+        &self.kinematics
+    }
+
+    pub(crate) fn kinematics_dyn(&self) -> &KinematicsDyn {
+        &self.kinematics_dyn
+    }
+
+    /// The kinematics before calibration, which the decomposition solves
+    pub fn nominal_kinematics(&self) -> &Kinematics<6, 7> {
+        self.nominal.as_ref().unwrap_or(&self.kinematics)
+    }
+
+    /// Solve the kinematics perturbed by `calibration`, replacing any previous calibration.
+    ///
+    /// The perturbed axes are no longer exactly parallel or intersecting, so the decomposition
+    /// still solves the nominal kinematics, and each of its solutions is refined on the calibrated
+    /// kinematics with `Kinematics::refine`. Solutions count as exact when their residual is then
+    /// within `ls_tolerance`. If none is, the calibrated kinematics are searched with
+    /// `gen_six_dof` instead.
+    pub fn with_calibration(mut self, calibration: &CalibrationSet<6, 7>) -> Self {
+        let nominal = self.nominal.take().unwrap_or(self.kinematics);
+        self.kinematics = nominal.clone();
+        self.kinematics.apply_calibration(calibration);
+        self.kinematics_dyn = KinematicsDyn::from(&self.kinematics);
+        self.nominal = Some(nominal);
+        self
+    }
+
+    /// Keep only the solutions within `joint_limits`, with a copy for each revolution of the
+    /// joints that fits within them
+    pub fn with_joint_limits(mut self, joint_limits: JointLimits) -> Self {
+        self.joint_limits = Some(joint_limits);
+        self
+    }
+
+    pub fn joint_limits(&self) -> Option<&JointLimits> {
+        self.joint_limits.as_ref()
+    }
+
+    /// A robot described in YAML, with the layout of `schema::RobotData`
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml_str(yaml: &str) -> Result<Self, IkGeoError> {
+        let data: crate::schema::RobotData = serde_yaml::from_str(yaml)
+            .map_err(|e| IkGeoError::InvalidDescription(e.to_string()))?;
+        Robot::try_from(data)
+    }
+
+    /// A robot described in the YAML file at `path`
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(path: impl AsRef<std::path::Path>) -> Result<Self, IkGeoError> {
+        let yaml = std::fs::read_to_string(path)
+            .map_err(|e| IkGeoError::InvalidDescription(e.to_string()))?;
+        Self::from_yaml_str(&yaml)
+    }
+
+    /// One of the robots whose descriptions ship with the crate, e.g. `"irb6640"`
+    #[cfg(feature = "yaml")]
+    pub fn named(name: &str) -> Option<Self> {
+        BUNDLED_ROBOTS
+            .iter()
+            .find(|(bundled, _)| *bundled == name)
+            .and_then(|(_, yaml)| Self::from_yaml_str(yaml).ok())
+    }
+
     // Get inverse kinematics and errors, sorted by error
     pub fn get_ik_sorted(
         &mut self,
         rot: Matrix3<f64>,
         translation: Vector3<f64>,
     ) -> Vec<([f64; 6], f64, bool)> {
-        // First, get all the solutions
-        let solutions = self.ik(rot, translation);
-
-        // Get the errors of each solution
-        let mut solutions_with_errors: Vec<([f64; 6], f64, bool)> =
-            Vec::<([f64; 6], f64, bool)>::new();
-
-        for (q, is_ls) in solutions {
-            // Calculate the error, assuming it's 0 if it's not a least squares solution
-            let mut error: f64 = 0.0;
-            if is_ls {
-                error = calculate_ik_error(&self.kinematics, &rot, &translation, &q);
-            }
-
-            // Convert q to a 6 element array
-            let mut q_vals = [0.0; 6];
-            for j in 0..6 {
-                q_vals[j] = q[j];
-            }
-            solutions_with_errors.push((q_vals, error, is_ls));
-        }
-
-        // Sort the solutions by error, asecending
-        solutions_with_errors.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        let mut solutions = self.ik(rot, translation);
+        solutions.sort_by_error();
 
-        solutions_with_errors
+        solutions
+            .into_iter()
+            .map(|solution| (solution.q.into(), solution.error, solution.is_ls))
+            .collect()
     }
 
     pub fn fk(&self, q: &[f64; 6]) -> (Matrix3<f64>, Vector3<f64>) {
         self.kinematics
             .forward_kinematics(&Vector6::from_row_slice(q))
     }
+
+    /// Solve IK without dropping the solutions outside of the joint limits
+    pub(crate) fn ik_unlimited(&self, rot: Matrix3<f64>, translation: Vector3<f64>) -> IkSolutionSet {
+        self.ik_unlimited_with(rot, translation, &mut SolverScratch::new())
+    }
+
+    /// Solve IK like `ik`, but keep the samples of the searches of the families that are not
+    /// solved in closed form in `scratch`. Reusing the same scratch across calls, e.g. along a
+    /// trajectory, saves allocating them on every call.
+    pub fn ik_with_scratch(
+        &self,
+        rot: Matrix3<f64>,
+        translation: Vector3<f64>,
+        scratch: &mut SolverScratch,
+    ) -> IkSolutionSet {
+        let solutions = self.ik_unlimited_with(rot, translation, scratch);
+        match &self.joint_limits {
+            Some(joint_limits) => solutions.expand_within_limits(joint_limits),
+            None => solutions,
+        }
+    }
+
+    fn ik_unlimited_with(
+        &self,
+        rot: Matrix3<f64>,
+        translation: Vector3<f64>,
+        scratch: &mut SolverScratch,
+    ) -> IkSolutionSet {
+        let Ok(rot) = validate_pose(&rot, &self.config) else {
+            return IkSolutionSet::new();
+        };
+        let mut solutions = self.ik_unrefined(rot, translation, scratch);
+        if self.config.refine {
+            solutions.refine(&self.kinematics, &rot, &translation, &self.config);
+        }
+        solutions.apply_singular_policy(&self.kinematics, &self.config);
+        solutions
+    }
+
+    /// Solve IK like `ik`, but fail with `IkGeoError::InvalidRotation` rather than returning no
+    /// solutions when `SolverConfig::rotation_policy` rejects the target rotation
+    pub fn try_ik(
+        &self,
+        rot: Matrix3<f64>,
+        translation: Vector3<f64>,
+    ) -> Result<IkSolutionSet, IkGeoError> {
+        validate_pose(&rot, &self.config)?;
+        Ok(self.ik(rot, translation))
+    }
+
+    /// Solve IK like `ik`, but into `solutions`, replacing what it held, without allocating, for
+    /// control loops that may not allocate. Only the closed-form families solve without
+    /// allocating, and neither calibrated kinematics nor `SolverConfig::refine` do, so these fail
+    /// with `IkGeoError::AllocationRequired`. Joint limits wider than a turn and singular policies
+    /// that add solutions can find more than `MAX_SOLUTIONS`, which fails with
+    /// `IkGeoError::CapacityExceeded`.
+    pub fn solve_into(
+        &self,
+        rot: Matrix3<f64>,
+        translation: Vector3<f64>,
+        solutions: &mut SolutionBuffer,
+    ) -> Result<(), IkGeoError> {
+        let solver = self.buffered()?;
+        let rot = validate_pose(&rot, &self.config)?;
+        let (rot_chain, translation_chain) = self.kinematics.to_chain_frame(&rot, &translation);
+        solver(
+            &rot_chain,
+            &translation_chain,
+            &self.kinematics,
+            &self.config,
+            solutions,
+        );
+        self.finish_into(solutions)
+    }
+
+    /// `solve_into` for branches of the chain solved elsewhere, e.g. on a GPU, for the pose
+    /// `(rot, translation)`
+    #[cfg(feature = "gpu")]
+    pub(crate) fn branches_into(
+        &self,
+        rot: Matrix3<f64>,
+        translation: Vector3<f64>,
+        branches: impl IntoIterator<Item = (Vector6<f64>, bool)>,
+        solutions: &mut SolutionBuffer,
+    ) -> Result<(), IkGeoError> {
+        self.buffered()?;
+        let (rot_chain, translation_chain) = self.kinematics.to_chain_frame(&rot, &translation);
+        crate::inverse_kinematics::branches_into(
+            branches,
+            &rot_chain,
+            &translation_chain,
+            &self.kinematics,
+            &self.config,
+            solutions,
+        );
+        self.finish_into(solutions)
+    }
+
+    /// The solver `solve_into` uses, or why this robot cannot be solved without allocating
+    pub(crate) fn buffered(&self) -> Result<BufferedIKFunction, IkGeoError> {
+        let Some(solver) = self.buffered_solver else {
+            return Err(IkGeoError::AllocationRequired(
+                "the kinematic family is solved by searching",
+            ));
+        };
+        if self.nominal.is_some() {
+            return Err(IkGeoError::AllocationRequired(
+                "calibrated kinematics are solved by refining nominal solutions",
+            ));
+        }
+        if self.config.refine {
+            return Err(IkGeoError::AllocationRequired(
+                "refining solutions takes the SVD of the Jacobian",
+            ));
+        }
+        Ok(solver)
+    }
+
+    /// Apply the singular policy and the joint limits to the solutions of the chain
+    fn finish_into(&self, solutions: &mut SolutionBuffer) -> Result<(), IkGeoError> {
+        apply_singular_policy_into(solutions, &self.kinematics, &self.config)?;
+        if let Some(joint_limits) = &self.joint_limits {
+            expand_within_limits_into(solutions, joint_limits)?;
+        }
+        Ok(())
+    }
+
+    /// Solve the chain of `kinematics` for the pose `(rot, translation)` in its chain frame
+    fn solve_chain(
+        &self,
+        rot: &Matrix3<f64>,
+        translation: &Vector3<f64>,
+        kinematics: &Kinematics<6, 7>,
+        scratch: &mut SolverScratch,
+    ) -> IkSolutionSet {
+        match self.scratch_solver {
+            Some(solver) => solver(rot, translation, kinematics, &self.config, scratch),
+            None => (self.sub_problem_solver)(rot, translation, kinematics, &self.config),
+        }
+    }
+
+    fn ik_unrefined(
+        &self,
+        rot: Matrix3<f64>,
+        translation: Vector3<f64>,
+        scratch: &mut SolverScratch,
+    ) -> IkSolutionSet {
+        let (rot_chain, translation_chain) = self.kinematics.to_chain_frame(&rot, &translation);
+        let Some(nominal) = &self.nominal else {
+            return self.solve_chain(&rot_chain, &translation_chain, &self.kinematics, scratch);
+        };
+
+        let mut solutions = self
+            .solve_chain(&rot_chain, &translation_chain, nominal, scratch)
+            .map_q(|q| {
+                self.kinematics
+                    .refine(q, &rot, &translation, CALIBRATION_REFINE_ITERATIONS)
+            });
+        solutions.compute_errors_with(&self.kinematics, &rot, &translation, &self.config);
+        solutions.set_ls_by_error(self.config.ls_tolerance);
+
+        if solutions.iter().all(|solution| solution.is_ls) {
+            trace_event!(
+                DEBUG,
+                "no exact solution on the calibrated kinematics, falling back to gen_six_dof"
+            );
+            solutions = gen_six_dof_with(
+                &rot_chain,
+                &translation_chain,
+                &self.kinematics,
+                &self.config,
+                scratch,
+            );
+        }
+
+        solutions.dedup(self.config.dedup_tolerance);
+        if self.config.annotate {
+            solutions.set_manipulability(|q| self.kinematics.manipulability(q));
+        }
+        solutions
+    }
 }
 
 impl IKSolver for Robot {
-    fn ik(&self, rot: Matrix3<f64>, translation: Vector3<f64>) -> Vec<(Vector6<f64>, bool)> {
-        let (solutions, lest_square_ness) =
-            (self.sub_problem_solver)(&rot, &translation, &self.kinematics);
+    fn ik(&self, rot: Matrix3<f64>, translation: Vector3<f64>) -> IkSolutionSet {
+        self.ik_with_scratch(rot, translation, &mut SolverScratch::new())
+    }
+
+    fn joint_metric(&self) -> JointMetric {
+        JointMetric::from_kinematics(&self.kinematics, Distance::default())
+    }
+
+    fn manipulability(&self, q: &Vector6<f64>) -> Option<f64> {
+        Some(self.kinematics.manipulability(q))
+    }
+
+    fn joint_limits(&self) -> Option<&JointLimits> {
+        self.joint_limits.as_ref()
+    }
+
+    /// Solve IK with every joint angle moved to the revolution closest to `q_current` within the
+    /// joint limits, so joints with limits spanning several turns can use all of them
+    fn ik_near(
+        &self,
+        rot: Matrix3<f64>,
+        translation: Vector3<f64>,
+        q_current: &Vector6<f64>,
+    ) -> IkSolutionSet {
+        let mut solutions = self.ik_unlimited(rot, translation);
+        solutions.unwrap_near(q_current, self.joint_limits.as_ref());
         solutions
-            .into_iter()
-            .zip(lest_square_ness.into_iter())
-            .collect()
     }
 }
 
+/// Names and YAML descriptions of the robots that ship with the crate
+#[cfg(feature = "yaml")]
+const BUNDLED_ROBOTS: [(&str, &str); 10] = [
+    ("irb120", include_str!("../robots/irb120.yaml")),
+    ("irb1200", include_str!("../robots/irb1200.yaml")),
+    ("irb2600", include_str!("../robots/irb2600.yaml")),
+    ("irb4600", include_str!("../robots/irb4600.yaml")),
+    ("irb6640", include_str!("../robots/irb6640.yaml")),
+    ("motoman_gp8", include_str!("../robots/motoman_gp8.yaml")),
+    ("ur5", include_str!("../robots/ur5.yaml")),
+    (
+        "three_parallel_bot",
+        include_str!("../robots/three_parallel_bot.yaml"),
+    ),
+    (
+        "two_parallel_bot",
+        include_str!("../robots/two_parallel_bot.yaml"),
+    ),
+    (
+        "spherical_bot",
+        include_str!("../robots/spherical_bot.yaml"),
+    ),
+];
+
 fn create_kinematics(h: Matrix3x6<f64>, p: Matrix3x7<f64>) -> Kinematics<6, 7> {
-    Kinematics {
-        h,
-        p,
-    }
+    let mut kin = Kinematics::new();
+    kin.h = h;
+    kin.p = p;
+    kin
 }
 
 // Create each bot from h, p
@@ -169,16 +753,40 @@ pub fn two_intersecting(h: Matrix3x6<f64>, p: Matrix3x7<f64>) -> Robot {
     Robot::two_intersecting(create_kinematics(h, p))
 }
 
+pub fn two_intersecting_at_base(h: Matrix3x6<f64>, p: Matrix3x7<f64>) -> Robot {
+    Robot::two_intersecting_at_base(create_kinematics(h, p))
+}
+
 pub fn gen_six_dof(h: Matrix3x6<f64>, p: Matrix3x7<f64>) -> Robot {
     Robot::gen_six_dof(create_kinematics(h, p))
 }
 
 // Hardcoded bots
 
+pub fn irb120() -> Robot {
+    Robot::spherical_two_parallel(Irb120::get_kin())
+}
+
+pub fn irb1200() -> Robot {
+    Robot::spherical_two_parallel(Irb1200::get_kin())
+}
+
+pub fn irb2600() -> Robot {
+    Robot::spherical_two_parallel(Irb2600::get_kin())
+}
+
+pub fn irb4600() -> Robot {
+    Robot::spherical_two_parallel(Irb4600::get_kin())
+}
+
 pub fn irb6640() -> Robot {
     Robot::spherical_two_parallel(Irb6640::get_kin())
 }
 
+pub fn motoman_gp8() -> Robot {
+    Robot::spherical_two_parallel(MotomanGp8::get_kin())
+}
+
 pub fn ur5() -> Robot {
     Robot::three_parallel_two_intersecting(Ur5::get_kin())
 }
@@ -195,9 +803,140 @@ pub fn spherical_bot() -> Robot {
     Robot::spherical(SphericalBot::get_kin())
 }
 
+/// The names of the hardcoded bots, as accepted by `preset`
+pub const PRESETS: [&str; 10] = [
+    "irb120",
+    "irb1200",
+    "irb2600",
+    "irb4600",
+    "irb6640",
+    "motoman_gp8",
+    "ur5",
+    "three_parallel_bot",
+    "two_parallel_bot",
+    "spherical_bot",
+];
+
+/// The hardcoded bot called `name`, e.g. `"ur5"` or `"irb6640"`
+pub fn preset(name: &str) -> Option<Robot> {
+    match name {
+        "irb120" => Some(irb120()),
+        "irb1200" => Some(irb1200()),
+        "irb2600" => Some(irb2600()),
+        "irb4600" => Some(irb4600()),
+        "irb6640" => Some(irb6640()),
+        "motoman_gp8" => Some(motoman_gp8()),
+        "ur5" => Some(ur5()),
+        "three_parallel_bot" => Some(three_parallel_bot()),
+        "two_parallel_bot" => Some(two_parallel_bot()),
+        "spherical_bot" => Some(spherical_bot()),
+        _ => None,
+    }
+}
+
+/// FANUC LR Mate 200iD, taking and returning joint angles in FANUC joint coordinates
+pub struct FanucLrMate200id {
+    robot: MappedRobot,
+}
+
+impl Default for FanucLrMate200id {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FanucLrMate200id {
+    pub fn new() -> Self {
+        FanucLrMate200id {
+            robot: MappedRobot::new(
+                Robot::spherical_two_parallel(setups::FanucLrMate200id::get_kin()),
+                JointMapping::fanuc_j2_j3(),
+            ),
+        }
+    }
+
+    /// Replace the tolerances used by the solver
+    pub fn with_config(mut self, config: SolverConfig) -> Self {
+        self.robot = self.robot.with_config(config);
+        self
+    }
+
+    pub fn fk(&self, j: &[f64; 6]) -> (Matrix3<f64>, Vector3<f64>) {
+        self.robot.fk(j)
+    }
+
+    /// The robot and the mapping between its joint angles and the FANUC joint coordinates
+    pub fn robot(&self) -> &MappedRobot {
+        &self.robot
+    }
+}
+
+impl IKSolver for FanucLrMate200id {
+    fn ik(&self, rot: Matrix3<f64>, translation: Vector3<f64>) -> IkSolutionSet {
+        self.robot.ik(rot, translation)
+    }
+
+    fn joint_metric(&self) -> JointMetric {
+        self.robot.joint_metric()
+    }
+
+    fn manipulability(&self, q: &Vector6<f64>) -> Option<f64> {
+        self.robot.manipulability(q)
+    }
+}
+
+/// Any robot of the Universal Robots family, built from its official DH parameters
+pub struct UniversalRobots {
+    robot: Robot,
+    model: UrModel,
+}
+
+impl UniversalRobots {
+    pub fn new(model: UrModel) -> Self {
+        UniversalRobots {
+            robot: Robot::three_parallel_two_intersecting(model.get_kin()),
+            model,
+        }
+    }
+
+    /// Replace the tolerances used by the solver
+    pub fn with_config(mut self, config: SolverConfig) -> Self {
+        self.robot = self.robot.with_config(config);
+        self
+    }
+
+    pub fn model(&self) -> UrModel {
+        self.model
+    }
+
+    /// The robot solved with the kinematics of the model
+    pub fn robot(&self) -> &Robot {
+        &self.robot
+    }
+
+    pub fn fk(&self, q: &[f64; 6]) -> (Matrix3<f64>, Vector3<f64>) {
+        self.robot.fk(q)
+    }
+}
+
+impl IKSolver for UniversalRobots {
+    fn ik(&self, rot: Matrix3<f64>, translation: Vector3<f64>) -> IkSolutionSet {
+        self.robot.ik(rot, translation)
+    }
+
+    fn joint_metric(&self) -> JointMetric {
+        self.robot.joint_metric()
+    }
+
+    fn manipulability(&self, q: &Vector6<f64>) -> Option<f64> {
+        self.robot.manipulability(q)
+    }
+}
+
 pub struct KukaR800FixedQ3 {
     robot: Robot,
     r_6t: Matrix3<f64>,
+    locked: KinematicsDyn,
 }
 
 impl KukaR800FixedQ3 {
@@ -206,54 +945,1059 @@ impl KukaR800FixedQ3 {
         KukaR800FixedQ3 {
             robot: Robot::spherical_two_intersecting(kinematics),
             r_6t: r_6t,
+            locked: locked_chain(
+                setups::KukaR800FixedQ3::get_kin(),
+                (2, setups::KukaR800FixedQ3::Q3),
+            ),
         }
     }
+
+    /// Replace the tolerances used by the solver
+    pub fn with_config(mut self, config: SolverConfig) -> Self {
+        self.robot = self.robot.with_config(config);
+        self
+    }
+
+    /// The kinematics of the joints solved for, those of `Kinematics::lock_joint` with the fixed
+    /// joint locked
+    pub fn locked_kinematics(&self) -> &KinematicsDyn {
+        &self.locked
+    }
 }
 
 impl IKSolver for KukaR800FixedQ3 {
-    fn ik(&self, rot: Matrix3<f64>, translation: Vector3<f64>) -> Vec<(Vector6<f64>, bool)> {
+    fn ik(&self, rot: Matrix3<f64>, translation: Vector3<f64>) -> IkSolutionSet {
         self.robot.ik(rot * self.r_6t.transpose(), translation)
     }
 }
 
+impl FixedJointSolver for KukaR800FixedQ3 {
+    fn fixed_joint(&self) -> (usize, f64) {
+        (2, setups::KukaR800FixedQ3::Q3)
+    }
+}
+
 pub struct RrcFixedQ6 {
-    robot: Robot,
-    r_6t: Matrix3<f64>,
+    /// The robot of the reduced partial chain and the rotation of its tool, or `None` if the
+    /// reduction is ill-conditioned and the unreduced chain is swept instead
+    reduced: Option<(Robot, Matrix3<f64>)>,
+    config: SolverConfig,
+    locked: KinematicsDyn,
+}
+
+impl Default for RrcFixedQ6 {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl RrcFixedQ6 {
     pub fn new() -> Self {
-        let (kinematics, r_6t) = setups::RrcFixedQ6::get_kin_partial();
-        RrcFixedQ6 {
-            robot: Robot::spherical_two_intersecting(kinematics),
-            r_6t: r_6t,
-        }
+        Self::try_new().expect("the default tolerances are valid")
+    }
+
+    /// The solver with the default tolerances, failing like `with_config` if the partial
+    /// kinematics cannot be constructed
+    pub fn try_new() -> Result<Self, IkGeoError> {
+        Self::with_config(SolverConfig::default())
+    }
+
+    /// Build the solver with the given tolerances, which also apply to constructing the partial
+    /// kinematics. If moving the offsets so the last two axes intersect is ill-conditioned, the
+    /// unreduced chain is solved by the `SevenDofSweep` of the RRC at the fixed q6 instead, see
+    /// `hardcoded::rrc_fixed_q6`.
+    pub fn with_config(config: SolverConfig) -> Result<Self, IkGeoError> {
+        let reduced = match setups::RrcFixedQ6::get_kin_partial(&config) {
+            Ok((kinematics, r_6t)) => {
+                Some((Robot::two_intersecting(kinematics).with_config(config), r_6t))
+            }
+            Err(IkGeoError::IllConditioned { condition }) => {
+                trace_event!(WARN, condition, "sweeping the unreduced chain");
+                None
+            }
+            Err(e) => return Err(e),
+        };
+        Ok(RrcFixedQ6 {
+            reduced,
+            config,
+            locked: locked_chain(
+                setups::RrcFixedQ6::get_kin(),
+                (5, setups::RrcFixedQ6::Q6),
+            ),
+        })
+    }
+
+    /// Whether the partial chain is solved with its last two axes made to intersect, rather than
+    /// unreduced by the sweep
+    pub fn is_reduced(&self) -> bool {
+        self.reduced.is_some()
+    }
+
+    /// The kinematics of the joints solved for, those of `Kinematics::lock_joint` with the fixed
+    /// joint locked
+    pub fn locked_kinematics(&self) -> &KinematicsDyn {
+        &self.locked
     }
 }
 
 impl IKSolver for RrcFixedQ6 {
-    fn ik(&self, rot: Matrix3<f64>, translation: Vector3<f64>) -> Vec<(Vector6<f64>, bool)> {
-        self.robot.ik(rot * self.r_6t.transpose(), translation)
+    fn ik(&self, rot: Matrix3<f64>, translation: Vector3<f64>) -> IkSolutionSet {
+        match &self.reduced {
+            Some((robot, r_6t)) => robot.ik(rot * r_6t.transpose(), translation),
+            None => rrc_fixed_q6_unreduced(&rot, &translation, &self.config),
+        }
+    }
+}
+
+impl FixedJointSolver for RrcFixedQ6 {
+    fn fixed_joint(&self) -> (usize, f64) {
+        (5, setups::RrcFixedQ6::Q6)
     }
 }
 
 pub struct YumiFixedQ3 {
     robot: Robot,
     r_6t: Matrix3<f64>,
+    locked: KinematicsDyn,
 }
 
 impl YumiFixedQ3 {
     pub fn new() -> Self {
         let (kinematics, r_6t) = setups::YumiFixedQ3::get_kin_partial();
         YumiFixedQ3 {
-            robot: Robot::spherical_two_intersecting(kinematics),
+            robot: Robot::gen_six_dof(kinematics),
             r_6t: r_6t,
+            locked: locked_chain(
+                setups::YumiFixedQ3::get_kin(),
+                (2, setups::YumiFixedQ3::Q3),
+            ),
         }
     }
+
+    /// Replace the tolerances used by the solver
+    pub fn with_config(mut self, config: SolverConfig) -> Self {
+        self.robot = self.robot.with_config(config);
+        self
+    }
+
+    /// The kinematics of the joints solved for, those of `Kinematics::lock_joint` with the fixed
+    /// joint locked
+    pub fn locked_kinematics(&self) -> &KinematicsDyn {
+        &self.locked
+    }
 }
 
 impl IKSolver for YumiFixedQ3 {
-    fn ik(&self, rot: Matrix3<f64>, translation: Vector3<f64>) -> Vec<(Vector6<f64>, bool)> {
+    fn ik(&self, rot: Matrix3<f64>, translation: Vector3<f64>) -> IkSolutionSet {
         self.robot.ik(rot * self.r_6t.transpose(), translation)
     }
 }
+
+impl FixedJointSolver for YumiFixedQ3 {
+    fn fixed_joint(&self) -> (usize, f64) {
+        (2, setups::YumiFixedQ3::Q3)
+    }
+}
+
+/// Both arms of a Yumi, in the frame of the robot body, each solved with its third joint locked
+/// by the grid search of `SevenDofSweep::general`, whose cost does not depend on nlopt
+pub struct YumiDualArm {
+    left: SevenDofSweep,
+    right: SevenDofSweep,
+    q3: f64,
+}
+
+/// The solutions of both arms of a dual-arm task
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DualArmSolutions {
+    pub left: IkSolutionSet<7>,
+    pub right: IkSolutionSet<7>,
+}
+
+impl Default for YumiDualArm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl YumiDualArm {
+    /// Both arms with the third joint locked at the same value as `YumiFixedQ3`
+    pub fn new() -> Self {
+        let arm = |kinematics| {
+            SevenDofSweep::new(kinematics, 2, gen_six_dof_grid_solver, SweepStrategy::Grid(1))
+        };
+        YumiDualArm {
+            left: arm(setups::YumiFixedQ3::get_kin()),
+            right: arm(setups::YumiFixedQ3::get_kin_right()),
+            q3: setups::YumiFixedQ3::Q3,
+        }
+    }
+
+    /// Lock the third joint of both arms at `q3`
+    pub fn with_q3(mut self, q3: f64) -> Self {
+        self.q3 = q3;
+        self
+    }
+
+    /// Replace the tolerances used by the solver
+    pub fn with_config(mut self, config: SolverConfig) -> Self {
+        self.left = self.left.with_config(config);
+        self.right = self.right.with_config(config);
+        self
+    }
+
+    pub fn left_kinematics(&self) -> &Kinematics<7, 8> {
+        self.left.kinematics()
+    }
+
+    /// The mirror image of the left arm, so solutions of the left arm for a pose are solutions of
+    /// the right arm for the mirrored pose, see `mirror_pose`
+    pub fn right_kinematics(&self) -> &Kinematics<7, 8> {
+        self.right.kinematics()
+    }
+
+    pub fn fk_left(&self, q: &SVector<f64, 7>) -> (Matrix3<f64>, Vector3<f64>) {
+        self.left_kinematics().forward_kinematics(q)
+    }
+
+    pub fn fk_right(&self, q: &SVector<f64, 7>) -> (Matrix3<f64>, Vector3<f64>) {
+        self.right_kinematics().forward_kinematics(q)
+    }
+
+    pub fn ik_left(&self, rot: Matrix3<f64>, translation: Vector3<f64>) -> IkSolutionSet<7> {
+        self.left.solve_at(&rot, &translation, self.q3).solutions
+    }
+
+    pub fn ik_right(&self, rot: Matrix3<f64>, translation: Vector3<f64>) -> IkSolutionSet<7> {
+        self.right.solve_at(&rot, &translation, self.q3).solutions
+    }
+
+    /// Solve both arms for the left tool at `(left_rot, left_translation)` and the right tool at
+    /// `(relative_rot, relative_translation)` in the frame of the left tool, as when both hands
+    /// hold the same object
+    pub fn ik_relative(
+        &self,
+        left_rot: Matrix3<f64>,
+        left_translation: Vector3<f64>,
+        relative_rot: Matrix3<f64>,
+        relative_translation: Vector3<f64>,
+    ) -> DualArmSolutions {
+        let (right_rot, right_translation) = relative_target(
+            &left_rot,
+            &left_translation,
+            &relative_rot,
+            &relative_translation,
+        );
+        DualArmSolutions {
+            left: self.ik_left(left_rot, left_translation),
+            right: self.ik_right(right_rot, right_translation),
+        }
+    }
+
+    /// The pairs of configurations solving the task of `ik_relative`, with the left arm as the
+    /// master searched over `samples` values of its third joint in `[-π, π)` and the right arm
+    /// solved at its locked third joint
+    pub fn ik_pairs(
+        &self,
+        left_rot: Matrix3<f64>,
+        left_translation: Vector3<f64>,
+        relative_rot: Matrix3<f64>,
+        relative_translation: Vector3<f64>,
+        samples: usize,
+    ) -> Vec<ArmPair<7, 7>> {
+        let (right_rot, right_translation) = relative_target(
+            &left_rot,
+            &left_translation,
+            &relative_rot,
+            &relative_translation,
+        );
+        let right = self.ik_right(right_rot, right_translation);
+
+        let mut pairs: Vec<ArmPair<7, 7>> = (0..samples)
+            .flat_map(|i| {
+                let q3 = -PI + TAU * i as f64 / samples as f64;
+                let left = self.left.solve_at(&left_rot, &left_translation, q3);
+                pair_solutions(&left.solutions, &right)
+            })
+            .collect();
+        sort_pairs(&mut pairs);
+        pairs
+    }
+}
+
+pub struct PandaFixedQ4 {
+    robot: Robot,
+    r_6t: Matrix3<f64>,
+    locked: KinematicsDyn,
+}
+
+impl Default for PandaFixedQ4 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PandaFixedQ4 {
+    pub fn new() -> Self {
+        let (kinematics, r_6t) = setups::PandaFixedQ4::get_kin_partial();
+        PandaFixedQ4 {
+            robot: Robot::two_intersecting_at_base(kinematics),
+            r_6t,
+            locked: locked_chain(
+                setups::PandaFixedQ4::get_kin(),
+                (3, setups::PandaFixedQ4::Q4),
+            ),
+        }
+    }
+
+    /// Replace the tolerances used by the solver
+    pub fn with_config(mut self, config: SolverConfig) -> Self {
+        self.robot = self.robot.with_config(config);
+        self
+    }
+
+    /// The kinematics of the joints solved for, those of `Kinematics::lock_joint` with the fixed
+    /// joint locked
+    pub fn locked_kinematics(&self) -> &KinematicsDyn {
+        &self.locked
+    }
+}
+
+impl IKSolver for PandaFixedQ4 {
+    fn ik(&self, rot: Matrix3<f64>, translation: Vector3<f64>) -> IkSolutionSet {
+        self.robot.ik(rot * self.r_6t.transpose(), translation)
+    }
+}
+
+impl FixedJointSolver for PandaFixedQ4 {
+    fn fixed_joint(&self) -> (usize, f64) {
+        (3, setups::PandaFixedQ4::Q4)
+    }
+}
+
+pub struct KinovaGen3FixedQ3 {
+    robot: Robot,
+    r_6t: Matrix3<f64>,
+    locked: KinematicsDyn,
+}
+
+impl Default for KinovaGen3FixedQ3 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KinovaGen3FixedQ3 {
+    pub fn new() -> Self {
+        let (kinematics, r_6t) = setups::KinovaGen3FixedQ3::get_kin_partial();
+        KinovaGen3FixedQ3 {
+            robot: Robot::two_intersecting(kinematics),
+            r_6t,
+            locked: locked_chain(
+                setups::KinovaGen3FixedQ3::get_kin(),
+                (2, setups::KinovaGen3FixedQ3::Q3),
+            ),
+        }
+    }
+
+    /// Replace the tolerances used by the solver
+    pub fn with_config(mut self, config: SolverConfig) -> Self {
+        self.robot = self.robot.with_config(config);
+        self
+    }
+
+    /// The kinematics of the joints solved for, those of `Kinematics::lock_joint` with the fixed
+    /// joint locked
+    pub fn locked_kinematics(&self) -> &KinematicsDyn {
+        &self.locked
+    }
+}
+
+impl IKSolver for KinovaGen3FixedQ3 {
+    fn ik(&self, rot: Matrix3<f64>, translation: Vector3<f64>) -> IkSolutionSet {
+        self.robot.ik(rot * self.r_6t.transpose(), translation)
+    }
+}
+
+impl FixedJointSolver for KinovaGen3FixedQ3 {
+    fn fixed_joint(&self) -> (usize, f64) {
+        (2, setups::KinovaGen3FixedQ3::Q3)
+    }
+}
+
+pub struct SawyerFixedQ3 {
+    robot: Robot,
+    r_6t: Matrix3<f64>,
+    locked: KinematicsDyn,
+}
+
+impl Default for SawyerFixedQ3 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SawyerFixedQ3 {
+    pub fn new() -> Self {
+        let (kinematics, r_6t) = setups::SawyerFixedQ3::get_kin_partial();
+        SawyerFixedQ3 {
+            robot: Robot::two_intersecting(kinematics),
+            r_6t,
+            locked: locked_chain(
+                setups::SawyerFixedQ3::get_kin(),
+                (2, setups::SawyerFixedQ3::Q3),
+            ),
+        }
+    }
+
+    /// Replace the tolerances used by the solver
+    pub fn with_config(mut self, config: SolverConfig) -> Self {
+        self.robot = self.robot.with_config(config);
+        self
+    }
+
+    /// The kinematics of the joints solved for, those of `Kinematics::lock_joint` with the fixed
+    /// joint locked
+    pub fn locked_kinematics(&self) -> &KinematicsDyn {
+        &self.locked
+    }
+}
+
+impl IKSolver for SawyerFixedQ3 {
+    fn ik(&self, rot: Matrix3<f64>, translation: Vector3<f64>) -> IkSolutionSet {
+        self.robot.ik(rot * self.r_6t.transpose(), translation)
+    }
+}
+
+impl FixedJointSolver for SawyerFixedQ3 {
+    fn fixed_joint(&self) -> (usize, f64) {
+        (2, setups::SawyerFixedQ3::Q3)
+    }
+}
+
+/// The kinematics of the joints of `full` solved for with the fixed `joint` locked at `value`
+fn locked_chain(full: Kinematics<7, 8>, (joint, value): (usize, f64)) -> KinematicsDyn {
+    let locked = full
+        .lock_joint(joint, value)
+        .expect("the fixed joint is a joint of the chain");
+    KinematicsDyn::from(&locked.kinematics)
+}
+
+/// Builds the 6-DOF partial kinematics of a 7-DOF robot with the joint at the given index locked
+/// at the given value, along with the rotation to apply to the target orientation
+pub type PartialKinFunction = fn(
+    &Kinematics<7, 8>,
+    usize,
+    f64,
+    &SolverConfig,
+) -> Result<(Kinematics<6, 7>, Matrix3<f64>), IkGeoError>;
+
+/// How the locked joint of a `SevenDofSweep` is sampled
+#[derive(Debug, Clone, Copy)]
+pub enum SweepStrategy {
+    /// Lock the joint at `n` evenly spaced values over `[-π, π)`
+    Grid(usize),
+    /// Sample a grid of `n` values, then golden-section refine the locked joint around the
+    /// sample with the lowest residual until the bracket is narrower than the tolerance
+    GoldenSection(usize, f64),
+}
+
+/// The solutions found with the free joint locked at `q_free`
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SweepSample {
+    pub q_free: f64,
+    pub solutions: IkSolutionSet<7>,
+}
+
+/// A connected piece of the self-motion manifold of a pose, i.e. configurations of a 7-DOF robot
+/// that all reach the same pose
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SelfMotionCurve {
+    /// Consecutive configurations along the curve
+    pub q: Vec<SVector<f64, 7>>,
+    /// Whether the last configuration connects back to the first
+    pub closed: bool,
+}
+
+/// Solves a 7-DOF robot by sweeping one joint and solving the remaining 6-DOF chain for each value
+pub struct SevenDofSweep {
+    kinematics: Kinematics<7, 8>,
+    locked_joint: usize,
+    partial_kinematics: PartialKinFunction,
+    sub_problem_solver: IKFunction,
+    /// The same solver keeping its samples in a `SolverScratch`, if it searches
+    scratch_solver: Option<ScratchIKFunction>,
+    strategy: SweepStrategy,
+    arm_angle_reference: Vector3<f64>,
+    config: SolverConfig,
+}
+
+fn lock_joint(
+    kin: &Kinematics<7, 8>,
+    n: usize,
+    q_n: f64,
+    _config: &SolverConfig,
+) -> Result<(Kinematics<6, 7>, Matrix3<f64>), IkGeoError> {
+    Ok(kin.forward_kinematics_partial(q_n, n, &Matrix3::identity()))
+}
+
+impl SevenDofSweep {
+    /// Sweep `locked_joint` of an arbitrary 7-DOF robot. `sub_problem_solver` must match the
+    /// kinematic family of the remaining 6-DOF chain.
+    pub fn new(
+        kinematics: Kinematics<7, 8>,
+        locked_joint: usize,
+        sub_problem_solver: IKFunction,
+        strategy: SweepStrategy,
+    ) -> Self {
+        SevenDofSweep {
+            kinematics,
+            locked_joint,
+            partial_kinematics: lock_joint,
+            sub_problem_solver,
+            scratch_solver: None,
+            strategy,
+            arm_angle_reference: Vector3::z(),
+            config: SolverConfig::default(),
+        }
+    }
+
+    /// Replace the tolerances used when building the partial kinematics and solving them
+    pub fn with_config(mut self, config: SolverConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn kinematics(&self) -> &Kinematics<7, 8> {
+        &self.kinematics
+    }
+
+    pub fn strategy(&self) -> SweepStrategy {
+        self.strategy
+    }
+
+    /// Solve the partial chains with `scratch_solver`, the variant of `sub_problem_solver` that
+    /// keeps the samples of its search in a `SolverScratch`, so a sweep reuses them across the
+    /// samples of the free joint
+    pub fn with_scratch_solver(mut self, scratch_solver: ScratchIKFunction) -> Self {
+        self.scratch_solver = Some(scratch_solver);
+        self
+    }
+
+    /// Replace how the partial kinematics are built, e.g. to move offsets so axes intersect
+    pub fn with_partial_kinematics(mut self, partial_kinematics: PartialKinFunction) -> Self {
+        self.partial_kinematics = partial_kinematics;
+        self
+    }
+
+    /// Sweep `locked_joint` of a 7-DOF robot without exploitable structure, solving each general
+    /// 6R chain with a 2D search over its first two joints. The resolution of the search is
+    /// `SolverConfig::search_resolution`, so a sweep of `n` samples evaluates the remaining joints
+    /// on `n` grids of that size.
+    pub fn general(
+        kinematics: Kinematics<7, 8>,
+        locked_joint: usize,
+        strategy: SweepStrategy,
+    ) -> Self {
+        Self::new(kinematics, locked_joint, gen_six_dof_grid_solver, strategy)
+            .with_scratch_solver(gen_six_dof_grid_with)
+    }
+
+    pub fn kuka_r800(strategy: SweepStrategy) -> Self {
+        Self::new(
+            setups::KukaR800FixedQ3::get_kin(),
+            2,
+            spherical_two_intersecting_solver,
+            strategy,
+        )
+    }
+
+    pub fn rrc(strategy: SweepStrategy) -> Self {
+        Self::new(
+            setups::RrcFixedQ6::get_kin(),
+            5,
+            two_intersecting_solver,
+            strategy,
+        )
+        .with_scratch_solver(two_intersecting_with)
+        .with_partial_kinematics(|_, _, q6, config| {
+            setups::RrcFixedQ6::get_kin_partial_at(q6, config)
+        })
+    }
+
+    pub fn yumi(strategy: SweepStrategy) -> Self {
+        Self::new(
+            setups::YumiFixedQ3::get_kin(),
+            2,
+            gen_six_dof_solver,
+            strategy,
+        )
+        .with_scratch_solver(gen_six_dof_with)
+    }
+
+    pub fn panda(strategy: SweepStrategy) -> Self {
+        Self::new(
+            setups::PandaFixedQ4::get_kin(),
+            3,
+            gen_six_dof_solver,
+            strategy,
+        )
+        .with_scratch_solver(gen_six_dof_with)
+    }
+
+    pub fn kinova_gen3(strategy: SweepStrategy) -> Self {
+        Self::new(
+            setups::KinovaGen3FixedQ3::get_kin(),
+            2,
+            gen_six_dof_solver,
+            strategy,
+        )
+        .with_scratch_solver(gen_six_dof_with)
+    }
+
+    pub fn sawyer(strategy: SweepStrategy) -> Self {
+        Self::new(
+            setups::SawyerFixedQ3::get_kin(),
+            2,
+            gen_six_dof_solver,
+            strategy,
+        )
+        .with_scratch_solver(gen_six_dof_with)
+    }
+
+    /// Solve for every sample of the free joint, ordered by the free joint value
+    pub fn solve(&self, rot: Matrix3<f64>, translation: Vector3<f64>) -> Vec<SweepSample> {
+        self.solve_with_scratch(rot, translation, &mut SolverScratch::new())
+    }
+
+    /// `solve` keeping the samples of the searches of the partial chains in `scratch`, which is
+    /// reused for every sample of the free joint and can be reused across calls
+    pub fn solve_with_scratch(
+        &self,
+        rot: Matrix3<f64>,
+        translation: Vector3<f64>,
+        scratch: &mut SolverScratch,
+    ) -> Vec<SweepSample> {
+        let n = match self.strategy {
+            SweepStrategy::Grid(n) | SweepStrategy::GoldenSection(n, _) => n,
+        };
+
+        let mut samples: Vec<SweepSample> = (0..n)
+            .map(|i| {
+                let q_free = -PI + TAU * i as f64 / n as f64;
+                self.solve_at_with(&rot, &translation, q_free, scratch)
+            })
+            .collect();
+
+        if let SweepStrategy::GoldenSection(_, tolerance) = self.strategy {
+            let best = samples
+                .iter()
+                .map(Self::residual)
+                .enumerate()
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(i, _)| i);
+
+            if let Some(i) = best {
+                let step = TAU / n as f64;
+                let q_free = self.refine(
+                    &rot,
+                    &translation,
+                    samples[i].q_free,
+                    step,
+                    tolerance,
+                    scratch,
+                );
+                samples.push(self.solve_at_with(&rot, &translation, q_free, scratch));
+                samples.sort_by(|a, b| a.q_free.total_cmp(&b.q_free));
+            }
+        }
+
+        samples
+    }
+
+    /// Solve the 6-DOF chain with the free joint locked at `q_free`
+    pub fn solve_at(
+        &self,
+        rot: &Matrix3<f64>,
+        translation: &Vector3<f64>,
+        q_free: f64,
+    ) -> SweepSample {
+        self.solve_at_with(rot, translation, q_free, &mut SolverScratch::new())
+    }
+
+    /// `solve_at` keeping the samples of the search of the partial chain in `scratch`
+    pub fn solve_at_with(
+        &self,
+        rot: &Matrix3<f64>,
+        translation: &Vector3<f64>,
+        q_free: f64,
+        scratch: &mut SolverScratch,
+    ) -> SweepSample {
+        // An ill-conditioned reduction falls back to the general solver on the unreduced chain
+        let reduction =
+            (self.partial_kinematics)(&self.kinematics, self.locked_joint, q_free, &self.config);
+        let partial = match reduction {
+            Err(IkGeoError::IllConditioned { condition }) => {
+                trace_event!(DEBUG, q_free, condition, "solving the unreduced partial chain");
+                lock_joint(&self.kinematics, self.locked_joint, q_free, &self.config)
+                    .map(|(kin_partial, r_6t)| (kin_partial, r_6t, false))
+            }
+            reduction => reduction.map(|(kin_partial, r_6t)| (kin_partial, r_6t, true)),
+        };
+        let mut solutions = match partial {
+            Ok((kin_partial, r_6t, reduced)) => {
+                let (rot, translation) = self.kinematics.to_chain_frame(rot, translation);
+                let rot = rot * r_6t.transpose();
+                let config = &self.config;
+                match self.scratch_solver {
+                    _ if !reduced => {
+                        gen_six_dof_grid_with(&rot, &translation, &kin_partial, config, scratch)
+                    }
+                    Some(solver) => solver(&rot, &translation, &kin_partial, config, scratch),
+                    None => (self.sub_problem_solver)(&rot, &translation, &kin_partial, config),
+                }
+                .map_q(|q| expand_partial_solution(q, q_free, self.locked_joint))
+            }
+            Err(_) => IkSolutionSet::new(),
+        };
+        solutions.compute_errors_with(&self.kinematics, rot, translation, &self.config);
+        if self.config.annotate {
+            solutions.set_manipulability(|q| self.kinematics.manipulability(q));
+        }
+
+        SweepSample { q_free, solutions }
+    }
+
+    fn residual(sample: &SweepSample) -> f64 {
+        sample
+            .solutions
+            .best()
+            .map_or(f64::INFINITY, |solution| solution.error)
+    }
+
+    /// Minimize the residual over the free joint within `center ± step`
+    fn refine(
+        &self,
+        rot: &Matrix3<f64>,
+        translation: &Vector3<f64>,
+        center: f64,
+        step: f64,
+        tolerance: f64,
+        scratch: &mut SolverScratch,
+    ) -> f64 {
+        let scratch = RefCell::new(scratch);
+        golden_section(
+            |q_free| {
+                let sample =
+                    self.solve_at_with(rot, translation, q_free, &mut scratch.borrow_mut());
+                Self::residual(&sample)
+            },
+            center - step,
+            center + step,
+            tolerance,
+        )
+    }
+
+    /// Use a different reference direction for the arm angle, which must not be parallel to the
+    /// shoulder-wrist line for the poses of interest
+    pub fn with_arm_angle_reference(mut self, reference: Vector3<f64>) -> Self {
+        self.arm_angle_reference = reference.normalize();
+        self
+    }
+
+    /// The SEW arm angle of `q`: the signed angle about the shoulder-wrist line from the plane
+    /// containing the reference direction to the plane containing the elbow.
+    /// The shoulder, elbow and wrist are taken as the origins of joints 2, 4 and 6.
+    pub fn arm_angle_of(&self, q: &SVector<f64, 7>) -> f64 {
+        let origins = self.kinematics.joint_origins(q);
+        let s: Vector3<f64> = origins.column(1).into();
+        let e: Vector3<f64> = origins.column(3).into();
+        let w: Vector3<f64> = origins.column(5).into();
+
+        let e_sw = (w - s).normalize();
+        let project = |v: Vector3<f64>| v - e_sw * e_sw.dot(&v);
+        let k_elbow = project(e - s);
+        let k_ref = project(self.arm_angle_reference);
+
+        e_sw.dot(&k_ref.cross(&k_elbow)).atan2(k_ref.dot(&k_elbow))
+    }
+
+    /// Solve for the configurations that reach the pose with the given arm angle. The free joint
+    /// is sampled according to the strategy and every local minimum of the arm angle error is
+    /// refined, using the `GoldenSection` tolerance if one is set.
+    pub fn solve_with_arm_angle(
+        &self,
+        rot: Matrix3<f64>,
+        translation: Vector3<f64>,
+        psi: f64,
+    ) -> IkSolutionSet<7> {
+        let (n, tolerance) = match self.strategy {
+            SweepStrategy::Grid(n) => (n, GRID_REFINE_TOLERANCE),
+            SweepStrategy::GoldenSection(n, tolerance) => (n, tolerance),
+        };
+
+        let arm_angle_error = |q: &SVector<f64, 7>| wrap_to_pi(self.arm_angle_of(q) - psi).abs();
+        let cost = |q_free: f64| {
+            self.solve_at(&rot, &translation, q_free)
+                .solutions
+                .iter()
+                .map(|solution| arm_angle_error(&solution.q))
+                .fold(f64::INFINITY, f64::min)
+        };
+
+        let step = TAU / n as f64;
+        let grid: Vec<(f64, f64)> = (0..n)
+            .map(|i| -PI + step * i as f64)
+            .map(|q_free| (q_free, cost(q_free)))
+            .collect();
+
+        let mut solutions: Vec<IkSolution<7>> = Vec::new();
+
+        for (i, &(q_free, c)) in grid.iter().enumerate() {
+            let previous = grid[(i + n - 1) % n].1;
+            let next = grid[(i + 1) % n].1;
+            if !c.is_finite() || c > previous || c > next {
+                continue;
+            }
+
+            let q_free = golden_section(cost, q_free - step, q_free + step, tolerance);
+
+            for solution in self.solve_at(&rot, &translation, q_free).solutions {
+                let duplicate = solutions
+                    .iter()
+                    .any(|other| (other.q - solution.q).norm() < ARM_ANGLE_TOLERANCE);
+                if arm_angle_error(&solution.q) < ARM_ANGLE_TOLERANCE && !duplicate {
+                    solutions.push(solution);
+                }
+            }
+        }
+
+        solutions.into_iter().collect()
+    }
+
+    /// Trace the self-motion manifold of the pose by continuation over the free joint.
+    ///
+    /// The free joint is sampled according to the strategy, and the interval between two samples
+    /// is bisected until their exact solutions can be paired up with small joint steps or the
+    /// interval is tiny, which happens where the free joint turns around. The solutions are then
+    /// linked into curves, and curves whose ends meet are joined.
+    pub fn trace_self_motion(
+        &self,
+        rot: Matrix3<f64>,
+        translation: Vector3<f64>,
+    ) -> Vec<SelfMotionCurve> {
+        let n = match self.strategy {
+            SweepStrategy::Grid(n) | SweepStrategy::GoldenSection(n, _) => n,
+        };
+        // Without samples there is nothing to bisect, like `solve` returns no samples
+        if n == 0 {
+            return Vec::new();
+        }
+        let distance = |a: &SVector<f64, 7>, b: &SVector<f64, 7>| (a - b).map(wrap_to_pi).amax();
+        let exact = |q_free: f64| -> Vec<SVector<f64, 7>> {
+            self.solve_at(&rot, &translation, q_free)
+                .solutions
+                .iter()
+                .filter(|solution| !solution.is_ls)
+                .map(|solution| solution.q)
+                .collect()
+        };
+        // Two samples are consistent when every solution of each has a close one in the other
+        let consistent = |a: &[SVector<f64, 7>], b: &[SVector<f64, 7>]| {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|p| b.iter().any(|q| distance(p, q) < SELF_MOTION_MAX_STEP))
+                && b.iter()
+                    .all(|q| a.iter().any(|p| distance(p, q) < SELF_MOTION_MAX_STEP))
+        };
+
+        // The last sample repeats the first one a full turn later, so curves can be closed
+        let mut samples: Vec<(f64, Vec<SVector<f64, 7>>)> = (0..=n)
+            .map(|i| -PI + TAU * i as f64 / n as f64)
+            .map(|q_free| (q_free, exact(q_free)))
+            .collect();
+
+        let min_spacing = TAU / n as f64 / SELF_MOTION_MAX_BISECTIONS as f64;
+        let mut i = 0;
+        while i + 1 < samples.len() {
+            let (a, b) = (&samples[i], &samples[i + 1]);
+            if b.0 - a.0 > min_spacing && !consistent(&a.1, &b.1) {
+                let q_free = (a.0 + b.0) / 2.0;
+                samples.insert(i + 1, (q_free, exact(q_free)));
+            } else {
+                i += 1;
+            }
+        }
+
+        // Curves still being extended are those whose last configuration is in the previous sample
+        let mut curves: Vec<Vec<SVector<f64, 7>>> = Vec::new();
+        let mut open: Vec<usize> = Vec::new();
+
+        for (_, sample) in &samples {
+            let mut pairs: Vec<(f64, usize, usize)> = open
+                .iter()
+                .flat_map(|&c| {
+                    let end = curves[c][curves[c].len() - 1];
+                    sample
+                        .iter()
+                        .enumerate()
+                        .map(move |(j, q)| (distance(&end, q), c, j))
+                })
+                .filter(|&(d, _, _)| d < SELF_MOTION_MAX_STEP)
+                .collect();
+            pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            let mut extended: Vec<usize> = Vec::new();
+            let mut claimed = vec![false; sample.len()];
+            for (_, c, j) in pairs {
+                if !extended.contains(&c) && !claimed[j] {
+                    curves[c].push(sample[j]);
+                    extended.push(c);
+                    claimed[j] = true;
+                }
+            }
+
+            for (j, q) in sample.iter().enumerate() {
+                if !claimed[j] {
+                    extended.push(curves.len());
+                    curves.push(vec![*q]);
+                }
+            }
+            open = extended;
+        }
+
+        // Join curves whose ends meet, where the free joint turns around or completes a turn.
+        // Ends that are the same configuration a full turn apart are merged into one.
+        let mut closed = vec![false; curves.len()];
+        loop {
+            let mut join = None;
+            'search: for a in 0..curves.len() {
+                if curves[a].is_empty() || closed[a] {
+                    continue;
+                }
+                let (first_a, last_a) = (curves[a][0], curves[a][curves[a].len() - 1]);
+
+                if curves[a].len() > 2 && distance(&last_a, &first_a) < SELF_MOTION_MAX_STEP {
+                    if distance(&last_a, &first_a) < SELF_MOTION_DUPLICATE {
+                        curves[a].pop();
+                    }
+                    closed[a] = true;
+                    continue;
+                }
+
+                for b in a + 1..curves.len() {
+                    if curves[b].is_empty() || closed[b] {
+                        continue;
+                    }
+                    let (first_b, last_b) = (curves[b][0], curves[b][curves[b].len() - 1]);
+
+                    for (reverse_a, end_a) in [(true, first_a), (false, last_a)] {
+                        for (reverse_b, start_b) in [(false, first_b), (true, last_b)] {
+                            if distance(&end_a, &start_b) < SELF_MOTION_MAX_STEP {
+                                join = Some((a, b, reverse_a, reverse_b));
+                                break 'search;
+                            }
+                        }
+                    }
+                }
+            }
+
+            let Some((a, b, reverse_a, reverse_b)) = join else {
+                break;
+            };
+            let mut tail = core::mem::take(&mut curves[b]);
+            if reverse_a {
+                curves[a].reverse();
+            }
+            if reverse_b {
+                tail.reverse();
+            }
+            if distance(&curves[a][curves[a].len() - 1], &tail[0]) < SELF_MOTION_DUPLICATE {
+                tail.remove(0);
+            }
+            curves[a].append(&mut tail);
+        }
+
+        curves
+            .into_iter()
+            .zip(closed)
+            .filter(|(q, _)| !q.is_empty())
+            .map(|(q, closed)| SelfMotionCurve { q, closed })
+            .collect()
+    }
+}
+
+/// The largest change of any joint between linked configurations of a self-motion curve
+const SELF_MOTION_MAX_STEP: f64 = 0.5;
+
+/// How finely the interval between two samples of the free joint may be divided when tracing
+const SELF_MOTION_MAX_BISECTIONS: usize = 1 << 12;
+
+/// Configurations of a self-motion curve closer than this are the same
+const SELF_MOTION_DUPLICATE: f64 = 1e-9;
+
+/// How close the arm angle of a solution must be to the requested one
+const ARM_ANGLE_TOLERANCE: f64 = 1e-6;
+
+/// How many rolls about the tool axis `IKSolver::solve_with_free_tool_roll` samples
+const TOOL_ROLL_SAMPLES: usize = 36;
+
+/// Gauss-Newton steps taken to move a solution of the nominal kinematics onto the calibrated ones
+const CALIBRATION_REFINE_ITERATIONS: usize = 10;
+
+/// Bracket width used when refining the arm angle under `SweepStrategy::Grid`
+pub(crate) const GRID_REFINE_TOLERANCE: f64 = 1e-9;
+
+/// The most steps of a golden section search, which narrow a bracket of 2π below 1e-15 long
+/// before this, so a search with a tolerance of zero or below stops
+const GOLDEN_SECTION_MAX_ITERATIONS: usize = 100;
+
+/// Minimize the periodic `cost` within `[a, b]` until the bracket is narrower than `tolerance`,
+/// returning the minimum wrapped to `[-π, π)`
+pub(crate) fn golden_section<F: Fn(f64) -> f64>(
+    cost: F,
+    a: f64,
+    b: f64,
+    tolerance: f64,
+) -> f64 {
+    wrap_to_pi(golden_section_interval(cost, a, b, tolerance))
+}
+
+/// Minimize `cost` within `[a, b]` until the bracket is narrower than `tolerance`, or for at most
+/// `GOLDEN_SECTION_MAX_ITERATIONS` steps
+pub(crate) fn golden_section_interval<F: Fn(f64) -> f64>(
+    cost: F,
+    mut a: f64,
+    mut b: f64,
+    tolerance: f64,
+) -> f64 {
+    let ratio = (5.0_f64.sqrt() - 1.0) / 2.0;
+
+    let mut c = b - ratio * (b - a);
+    let mut d = a + ratio * (b - a);
+    let (mut cost_c, mut cost_d) = (cost(c), cost(d));
+
+    for _ in 0..GOLDEN_SECTION_MAX_ITERATIONS {
+        if b - a <= tolerance {
+            break;
+        }
+        if cost_c < cost_d {
+            b = d;
+            d = c;
+            cost_d = cost_c;
+            c = b - ratio * (b - a);
+            cost_c = cost(c);
+        } else {
+            a = c;
+            c = d;
+            cost_c = cost_d;
+            d = a + ratio * (b - a);
+            cost_d = cost(d);
+        }
+    }
+
+    (a + b) / 2.0
+}
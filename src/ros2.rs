@@ -0,0 +1,162 @@
+//! Conversions between ROS 2 messages and the types of the crate, for embedding the solvers in
+//! ROS 2 nodes.
+//!
+//! With the `ros2` feature, a `geometry_msgs/Pose` of [r2r](https://github.com/sequenceplanner/r2r)
+//! converts to and from a `Pose`, normalizing its quaternion when read like the quaternion
+//! rotation formats. `JointNames` fills in `sensor_msgs/JointState` messages from joint values and
+//! reads joint values back from them by name, as joint state publishers do not keep the order of
+//! the joints. `ik_handler` wraps a solver into the callback of an IK service.
+//!
+//! r2r generates its message types from a sourced ROS 2 installation, so the conversions are built
+//! on `pose_from_message`, `message_from_pose` and `JointNames::select`, which take the fields of
+//! the messages as plain values and are available without the feature, e.g. for nodes using
+//! another client library.
+
+use {
+    crate::{error::IkGeoError, pose::Pose},
+    alloc::string::String,
+    nalgebra::{Quaternion, SVector, UnitQuaternion, Vector3},
+};
+
+#[cfg(feature = "ros2")]
+use {
+    crate::robot::IKSolver,
+    alloc::vec::Vec,
+    r2r::{
+        builtin_interfaces::msg::Time,
+        geometry_msgs::msg::{self as geometry_msgs, Point},
+        sensor_msgs::msg::JointState,
+        std_msgs::msg::Header,
+    },
+};
+
+/// The pose of a `geometry_msgs/Pose` with the `position` `[x, y, z]` and the `orientation`
+/// `[x, y, z, w]`, normalizing the quaternion
+pub fn pose_from_message(position: [f64; 3], orientation: [f64; 4]) -> Pose {
+    let [x, y, z, w] = orientation;
+    let rotation = UnitQuaternion::from_quaternion(Quaternion::new(w, x, y, z));
+    Pose::new(
+        rotation.to_rotation_matrix().into_inner(),
+        Vector3::from(position),
+    )
+}
+
+/// The `position` `[x, y, z]` and the `orientation` `[x, y, z, w]` of the `geometry_msgs/Pose` of
+/// `pose`
+pub fn message_from_pose(pose: &Pose) -> ([f64; 3], [f64; 4]) {
+    let rotation = UnitQuaternion::from_matrix(&pose.rotation);
+    (
+        pose.translation.into(),
+        [rotation.i, rotation.j, rotation.k, rotation.w],
+    )
+}
+
+#[cfg(feature = "ros2")]
+impl From<&geometry_msgs::Pose> for Pose {
+    fn from(pose: &geometry_msgs::Pose) -> Self {
+        let Point { x, y, z } = pose.position;
+        let geometry_msgs::Quaternion {
+            x: qx,
+            y: qy,
+            z: qz,
+            w: qw,
+        } = pose.orientation;
+        pose_from_message([x, y, z], [qx, qy, qz, qw])
+    }
+}
+
+#[cfg(feature = "ros2")]
+impl From<geometry_msgs::Pose> for Pose {
+    fn from(pose: geometry_msgs::Pose) -> Self {
+        Pose::from(&pose)
+    }
+}
+
+#[cfg(feature = "ros2")]
+impl From<&Pose> for geometry_msgs::Pose {
+    fn from(pose: &Pose) -> Self {
+        let ([x, y, z], [qx, qy, qz, qw]) = message_from_pose(pose);
+        geometry_msgs::Pose {
+            position: Point { x, y, z },
+            orientation: geometry_msgs::Quaternion {
+                x: qx,
+                y: qy,
+                z: qz,
+                w: qw,
+            },
+        }
+    }
+}
+
+#[cfg(feature = "ros2")]
+impl From<Pose> for geometry_msgs::Pose {
+    fn from(pose: Pose) -> Self {
+        geometry_msgs::Pose::from(&pose)
+    }
+}
+
+/// The names of the joints of a robot, in the order of its joint values
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JointNames<const N: usize>(pub [String; N]);
+
+impl<const N: usize> JointNames<N> {
+    pub fn new(names: [&str; N]) -> Self {
+        JointNames(names.map(String::from))
+    }
+
+    /// The values of the joints in the order of the names, given the `names` of a joint state and
+    /// its `values` in the same order, failing with `IkGeoError::MissingJoint` if there is no
+    /// value for one of them
+    pub fn select(&self, names: &[String], values: &[f64]) -> Result<SVector<f64, N>, IkGeoError> {
+        let mut q = SVector::<f64, N>::zeros();
+        for (value, name) in q.iter_mut().zip(&self.0) {
+            *value = names
+                .iter()
+                .position(|other| other == name)
+                .and_then(|i| values.get(i).copied())
+                .ok_or_else(|| IkGeoError::MissingJoint(name.clone()))?;
+        }
+        Ok(q)
+    }
+
+    /// The joint state with the positions `q`, without velocities or efforts and with an empty
+    /// header for the caller to stamp
+    #[cfg(feature = "ros2")]
+    pub fn joint_state(&self, q: &SVector<f64, N>) -> JointState {
+        JointState {
+            header: Header {
+                stamp: Time { sec: 0, nanosec: 0 },
+                frame_id: String::new(),
+            },
+            name: self.0.to_vec(),
+            position: q.iter().copied().collect(),
+            velocity: Vec::new(),
+            effort: Vec::new(),
+        }
+    }
+
+    /// The positions of the joints in `state`, in the order of the names, see `select`
+    #[cfg(feature = "ros2")]
+    pub fn positions(&self, state: &JointState) -> Result<SVector<f64, N>, IkGeoError> {
+        self.select(&state.name, &state.position)
+    }
+}
+
+/// The callback of an IK service: the joint states of the exact solutions of `solver` reaching a
+/// pose, in the order the solver returns them. The callback owns the solver, so it can be moved
+/// into the request handler of an r2r service.
+#[cfg(feature = "ros2")]
+pub fn ik_handler<S: IKSolver>(
+    solver: S,
+    names: JointNames<6>,
+) -> impl Fn(&geometry_msgs::Pose) -> Vec<JointState> {
+    move |pose| {
+        let pose = Pose::from(pose);
+        solver
+            .ik(pose.rotation, pose.translation)
+            .exact()
+            .iter()
+            .map(|solution| names.joint_state(&solution.q))
+            .collect()
+    }
+}
@@ -0,0 +1,201 @@
+//! Stable serialized forms of the crate's types, enabled with the `serde` feature.
+//!
+//! Kinematics are stored as lists of column vectors:
+//!
+//! ```json
+//! { "h": [[0, 0, 1], ...], "p": [[0, 0, 0.34], ...] }
+//! ```
+//!
+//! with one entry in `h` per joint and one more entry in `p` for the tool offset.
+//! The optional `base` and `tool` entries hold poses and are left out when they are the identity.
+//! The optional `joint_types` entry lists `"revolute"` or `"prismatic"` for each joint, and is
+//! left out when every joint is revolute.
+//! Poses store the rotation row by row:
+//!
+//! ```json
+//! { "rotation": [[1, 0, 0], [0, 1, 0], [0, 0, 1]], "translation": [0.5, 0, 0.2] }
+//! ```
+//!
+//! Robots are kinematics with an optional `name`, the `family` of solver to use, detected from the
+//! kinematics when left out, and `limits` holding one `[lower, upper]` pair per joint:
+//!
+//! ```json
+//! { "name": "irb6640", "family": "spherical_two_parallel", "h": [...], "p": [...], "limits": [[-2.96, 2.96], ...] }
+//! ```
+//!
+//! Joint vectors are plain arrays of angles, and an `IkSolutionSet` is a list of its solutions:
+//!
+//! ```json
+//! [{ "q": [q1, ..., q6], "is_ls": false, "error": 1e-15, "manipulability": 0.12, "branch": 0, "multiplicity": 1 }, ...]
+//! ```
+
+use {
+    crate::{
+        error::IkGeoError,
+        inverse_kinematics::{
+            auxiliary::{JointType, Kinematics, KinematicsDyn},
+            family::KinematicFamily,
+        },
+        limits::JointLimits,
+        pose::Pose,
+        registry::RobotDefinition,
+        robot::Robot,
+    },
+    nalgebra::{DMatrix, Isometry3, Matrix3, Vector3},
+    serde::{Deserialize, Serialize},
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KinematicsData {
+    pub h: Vec<[f64; 3]>,
+    pub p: Vec<[f64; 3]>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub joint_types: Vec<JointType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base: Option<PoseData>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool: Option<PoseData>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RobotData {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub family: Option<KinematicFamily>,
+    #[serde(flatten)]
+    pub kinematics: KinematicsData,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limits: Option<Vec<[f64; 2]>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoseData {
+    pub rotation: [[f64; 3]; 3],
+    pub translation: [f64; 3],
+}
+
+fn columns(m: &DMatrix<f64>) -> Vec<[f64; 3]> {
+    m.column_iter().map(|c| [c[0], c[1], c[2]]).collect()
+}
+
+fn from_columns(columns: &[[f64; 3]]) -> DMatrix<f64> {
+    DMatrix::from_iterator(3, columns.len(), columns.iter().flatten().copied())
+}
+
+fn frame(isometry: &Isometry3<f64>) -> Option<PoseData> {
+    (*isometry != Isometry3::identity()).then(|| Pose::from(*isometry).into())
+}
+
+fn isometry(data: Option<PoseData>) -> Isometry3<f64> {
+    data.map(|data| Pose::from(data).into())
+        .unwrap_or_else(Isometry3::identity)
+}
+
+impl From<KinematicsDyn> for KinematicsData {
+    fn from(kin: KinematicsDyn) -> Self {
+        let revolute = kin
+            .joint_types()
+            .iter()
+            .all(|&joint_type| joint_type == JointType::Revolute);
+        KinematicsData {
+            h: columns(kin.h()),
+            p: columns(kin.p()),
+            joint_types: if revolute {
+                Vec::new()
+            } else {
+                kin.joint_types().to_vec()
+            },
+            base: frame(kin.base()),
+            tool: frame(kin.tool()),
+        }
+    }
+}
+
+impl TryFrom<KinematicsData> for KinematicsDyn {
+    type Error = IkGeoError;
+
+    fn try_from(data: KinematicsData) -> Result<Self, Self::Error> {
+        let kin = KinematicsDyn::new(from_columns(&data.h), from_columns(&data.p))?
+            .with_base(isometry(data.base))
+            .with_tool(isometry(data.tool));
+
+        if data.joint_types.is_empty() {
+            Ok(kin)
+        } else {
+            kin.with_joint_types(&data.joint_types)
+        }
+    }
+}
+
+impl<const C1: usize, const C2: usize> From<Kinematics<C1, C2>> for KinematicsData {
+    fn from(kin: Kinematics<C1, C2>) -> Self {
+        KinematicsDyn::from(&kin).into()
+    }
+}
+
+impl<const C1: usize, const C2: usize> TryFrom<KinematicsData> for Kinematics<C1, C2> {
+    type Error = IkGeoError;
+
+    fn try_from(data: KinematicsData) -> Result<Self, Self::Error> {
+        Kinematics::try_from(&KinematicsDyn::try_from(data)?)
+    }
+}
+
+impl From<Pose> for PoseData {
+    fn from(pose: Pose) -> Self {
+        let r = pose.rotation;
+        PoseData {
+            rotation: [
+                [r[(0, 0)], r[(0, 1)], r[(0, 2)]],
+                [r[(1, 0)], r[(1, 1)], r[(1, 2)]],
+                [r[(2, 0)], r[(2, 1)], r[(2, 2)]],
+            ],
+            translation: pose.translation.into(),
+        }
+    }
+}
+
+impl From<PoseData> for Pose {
+    fn from(data: PoseData) -> Self {
+        Pose::new(
+            Matrix3::from_row_iterator(data.rotation.into_iter().flatten()),
+            Vector3::from(data.translation),
+        )
+    }
+}
+
+impl TryFrom<RobotData> for RobotDefinition {
+    type Error = IkGeoError;
+
+    fn try_from(data: RobotData) -> Result<Self, Self::Error> {
+        let kinematics = Kinematics::try_from(data.kinematics)?;
+        if !kinematics.is_revolute() {
+            return Err(IkGeoError::InvalidDescription(String::from(
+                "the 6R solvers require every joint to be revolute",
+            )));
+        }
+
+        let mut definition = RobotDefinition::new(kinematics);
+        definition.family = data.family;
+        if let Some(limits) = data.limits {
+            let limits: [[f64; 2]; 6] =
+                limits
+                    .try_into()
+                    .map_err(|limits: Vec<_>| IkGeoError::DimensionMismatch {
+                        expected: 6,
+                        found: limits.len(),
+                    })?;
+            definition.joint_limits = Some(JointLimits::from_pairs(&limits));
+        }
+        Ok(definition)
+    }
+}
+
+impl TryFrom<RobotData> for Robot {
+    type Error = IkGeoError;
+
+    fn try_from(data: RobotData) -> Result<Self, Self::Error> {
+        RobotDefinition::try_from(data).map(|definition| definition.robot())
+    }
+}
@@ -0,0 +1,124 @@
+//! Screw theory for working alongside the solvers: the exponentials of twists, adjoint transforms
+//! and the screw motion between two poses.
+//!
+//! Screws follow the convention of `Kinematics::jacobian` and `se3`: the angular part in the first
+//! three entries and the linear part in the last three. The screw of a joint turning about the
+//! unit axis `w` through the point `q` is `(w, -w × q)`, and that of a joint sliding along the
+//! unit axis `v` is `(0, v)`, so the forward kinematics of a chain is the product of the
+//! exponentials of `Kinematics::to_screws`, see the `conventions` module.
+
+use {
+    crate::{conventions::ScrewFrame, inverse_kinematics::auxiliary::Kinematics, pose::Pose, se3},
+    nalgebra::{Isometry3, Matrix6, Vector3, Vector6},
+};
+
+/// Screws whose angular part is shorter than this are pure translations
+const PURE_TRANSLATION: f64 = 1e-12;
+
+/// The screw turning about the line through `point` along the unit `direction` and sliding
+/// `pitch` along it per radian
+pub fn from_axis(point: &Vector3<f64>, direction: &Vector3<f64>, pitch: f64) -> Vector6<f64> {
+    let v = -direction.cross(point) + direction * pitch;
+    Vector6::new(direction.x, direction.y, direction.z, v.x, v.y, v.z)
+}
+
+/// The pose reached from the identity by moving along `screw` by `theta`, the angle for a screw
+/// with a unit angular part and the distance for a pure translation with a unit linear part
+pub fn exp(screw: &Vector6<f64>, theta: f64) -> Pose {
+    se3::exp(&(screw * theta))
+}
+
+/// The adjoint of `pose`, mapping screws given in the frame of `pose` to the frame `pose` is
+/// given in
+pub fn adjoint(pose: &Pose) -> Matrix6<f64> {
+    let mut adjoint = Matrix6::zeros();
+    adjoint
+        .fixed_view_mut::<3, 3>(0, 0)
+        .copy_from(&pose.rotation);
+    adjoint
+        .fixed_view_mut::<3, 3>(3, 3)
+        .copy_from(&pose.rotation);
+    adjoint
+        .fixed_view_mut::<3, 3>(3, 0)
+        .copy_from(&(pose.translation.cross_matrix() * pose.rotation));
+    adjoint
+}
+
+/// A motion along a screw, by an angle or, for a pure translation, a distance
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScrewMotion {
+    /// The screw, with a unit angular part, or a zero angular part and a unit linear part
+    pub screw: Vector6<f64>,
+    pub theta: f64,
+}
+
+impl ScrewMotion {
+    /// The screw motion that moves `from` to `to`, turning by at most π. With `ScrewFrame::Space`
+    /// the screw is in the frame the poses are given in and `to = exp(screw, theta) from`, with
+    /// `ScrewFrame::Body` it is in the frame of `from` and `to = from exp(screw, theta)`.
+    pub fn between(from: &Pose, to: &Pose, frame: ScrewFrame) -> Self {
+        let (from, to) = (Isometry3::from(*from), Isometry3::from(*to));
+        let relative = match frame {
+            ScrewFrame::Space => to * from.inverse(),
+            ScrewFrame::Body => from.inverse() * to,
+        };
+        let twist = se3::log(&relative.into());
+
+        let angle = twist.fixed_rows::<3>(0).norm();
+        let theta = if angle > PURE_TRANSLATION {
+            angle
+        } else {
+            twist.fixed_rows::<3>(3).norm()
+        };
+        if theta == 0.0 {
+            return ScrewMotion {
+                screw: Vector6::zeros(),
+                theta,
+            };
+        }
+        ScrewMotion {
+            screw: twist / theta,
+            theta,
+        }
+    }
+
+    /// The pose this motion moves the identity to
+    pub fn pose(&self) -> Pose {
+        exp(&self.screw, self.theta)
+    }
+
+    /// Whether the motion only slides, without turning
+    pub fn is_translation(&self) -> bool {
+        self.screw.fixed_rows::<3>(0).norm() <= PURE_TRANSLATION
+    }
+
+    /// The distance slid along the axis per radian turned, infinite for a pure translation
+    pub fn pitch(&self) -> f64 {
+        if self.is_translation() {
+            return f64::INFINITY;
+        }
+        self.screw
+            .fixed_rows::<3>(0)
+            .dot(&self.screw.fixed_rows::<3>(3))
+    }
+
+    /// The point of the axis closest to the origin and the direction of the axis, or `None` for a
+    /// pure translation, which has no axis
+    pub fn axis(&self) -> Option<(Vector3<f64>, Vector3<f64>)> {
+        if self.is_translation() {
+            return None;
+        }
+        let w: Vector3<f64> = self.screw.fixed_rows::<3>(0).into();
+        let v: Vector3<f64> = self.screw.fixed_rows::<3>(3).into();
+        Some((w.cross(&v), w))
+    }
+}
+
+impl<const C1: usize, const C2: usize> Kinematics<C1, C2> {
+    /// The screw of every joint at the zero configuration in the world frame, the space form of
+    /// `to_poe`
+    pub fn to_screws(&self) -> [Vector6<f64>; C1] {
+        self.to_poe(ScrewFrame::Space).screws
+    }
+}
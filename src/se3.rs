@@ -0,0 +1,143 @@
+//! Interpolating between poses, to generate the dense pose paths that `trajectory` and
+//! `continuation` follow.
+//!
+//! Twists follow the convention of `Kinematics::jacobian`: the rotation in the first three
+//! entries and the translation in the last three, both in the frame the poses are given in.
+//! `log` and `exp` convert between a pose and the twist that moves the identity to it in unit
+//! time, along a screw motion.
+//!
+//! There are two ways to interpolate. `interpolate` moves the position along the straight line
+//! between the poses and turns the orientation at a constant rate about a fixed axis, as a linear
+//! move of a robot controller does. `screw_interpolate` moves along the screw between the poses,
+//! so the whole body turns about one line in space, as when following a rotating fixture.
+
+use {
+    crate::pose::Pose,
+    alloc::vec::Vec,
+    nalgebra::{Matrix3, Rotation3, Vector3, Vector6},
+};
+
+#[cfg(all(not(feature = "std"), target_os = "none"))]
+use num_traits::Float;
+
+/// Below this angle the coefficients of `exp` and `log` are evaluated by their Taylor series
+const SMALL_ANGLE: f64 = 1e-6;
+
+fn hat(k: &Vector3<f64>) -> Matrix3<f64> {
+    Matrix3::new(0.0, -k.z, k.y, k.z, 0.0, -k.x, -k.y, k.x, 0.0)
+}
+
+/// The rotation vector of `rotation`, whose direction is the axis and whose length is the angle
+pub(crate) fn rotation_log(rotation: &Matrix3<f64>) -> Vector3<f64> {
+    let cos = (rotation.trace() - 1.0) / 2.0;
+    if cos <= 0.0 {
+        return Rotation3::from_matrix(rotation).scaled_axis();
+    }
+
+    // The skew part is the axis scaled by the sine, which keeps its precision at small angles
+    // where the angle from the cosine alone does not
+    let skew = Vector3::new(
+        rotation[(2, 1)] - rotation[(1, 2)],
+        rotation[(0, 2)] - rotation[(2, 0)],
+        rotation[(1, 0)] - rotation[(0, 1)],
+    ) / 2.0;
+    let sin = skew.norm();
+    let theta = sin.atan2(cos);
+    if theta < SMALL_ANGLE {
+        skew
+    } else {
+        skew * (theta / sin)
+    }
+}
+
+pub(crate) fn rotation_exp(omega: &Vector3<f64>) -> Matrix3<f64> {
+    Rotation3::from_scaled_axis(*omega).into_inner()
+}
+
+/// The pose `b` followed by `a`, i.e. `b` given in the frame of `a`
+fn compose(a: &Pose, b: &Pose) -> Pose {
+    Pose::new(
+        a.rotation * b.rotation,
+        a.rotation * b.translation + a.translation,
+    )
+}
+
+fn inverse(pose: &Pose) -> Pose {
+    let rotation = pose.rotation.transpose();
+    Pose::new(rotation, -(rotation * pose.translation))
+}
+
+/// The pose reached from the identity by moving with `twist` for unit time
+pub fn exp(twist: &Vector6<f64>) -> Pose {
+    let omega: Vector3<f64> = twist.fixed_rows::<3>(0).into();
+    let v: Vector3<f64> = twist.fixed_rows::<3>(3).into();
+
+    let theta = omega.norm();
+    let w = hat(&omega);
+    let (a, b) = if theta < SMALL_ANGLE {
+        (0.5 - theta * theta / 24.0, 1.0 / 6.0 - theta * theta / 120.0)
+    } else {
+        (
+            (1.0 - theta.cos()) / (theta * theta),
+            (theta - theta.sin()) / (theta * theta * theta),
+        )
+    };
+    let left_jacobian = Matrix3::identity() + w * a + w * w * b;
+
+    Pose::new(rotation_exp(&omega), left_jacobian * v)
+}
+
+/// The twist that moves the identity to `pose` in unit time, with a rotation of at most π
+pub fn log(pose: &Pose) -> Vector6<f64> {
+    let omega = rotation_log(&pose.rotation);
+
+    let theta = omega.norm();
+    let w = hat(&omega);
+    let c = if theta < SMALL_ANGLE {
+        1.0 / 12.0 + theta * theta / 720.0
+    } else {
+        (1.0 - theta * theta.sin() / (2.0 * (1.0 - theta.cos()))) / (theta * theta)
+    };
+    let inverse_left_jacobian = Matrix3::identity() - w * 0.5 + w * w * c;
+    let v = inverse_left_jacobian * pose.translation;
+
+    Vector6::new(omega.x, omega.y, omega.z, v.x, v.y, v.z)
+}
+
+/// The pose a fraction `s` of the way from `from` to `to`, with the position on the straight line
+/// between them and the orientation turned about a fixed axis by the shorter way, the
+/// interpolation of a SLERP
+pub fn interpolate(from: &Pose, to: &Pose, s: f64) -> Pose {
+    let relative = rotation_log(&(from.rotation.transpose() * to.rotation));
+    Pose::new(
+        from.rotation * rotation_exp(&(relative * s)),
+        from.translation.lerp(&to.translation, s),
+    )
+}
+
+/// The pose a fraction `s` of the way from `from` to `to` along the screw motion between them,
+/// with the twist taken in the frame of `from`
+pub fn screw_interpolate(from: &Pose, to: &Pose, s: f64) -> Pose {
+    let twist = log(&compose(&inverse(from), to));
+    compose(from, &exp(&(twist * s)))
+}
+
+/// `samples` poses evenly spaced from `from` to `to` by `interpolate`, including both ends
+pub fn linear_path(from: &Pose, to: &Pose, samples: usize) -> Vec<Pose> {
+    path(samples, |s| interpolate(from, to, s))
+}
+
+/// `samples` poses evenly spaced from `from` to `to` by `screw_interpolate`, including both ends
+pub fn screw_path(from: &Pose, to: &Pose, samples: usize) -> Vec<Pose> {
+    path(samples, |s| screw_interpolate(from, to, s))
+}
+
+fn path(samples: usize, pose_at: impl Fn(f64) -> Pose) -> Vec<Pose> {
+    match samples {
+        0 => Vec::new(),
+        1 => alloc::vec![pose_at(0.0)],
+        _ => (0..samples)
+            .map(|k| pose_at(k as f64 / (samples - 1) as f64))
+            .collect(),
+    }
+}
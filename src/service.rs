@@ -0,0 +1,172 @@
+//! The gRPC service of `proto/ik_geo.proto`, for clients in other languages that call the solvers
+//! over the network.
+//!
+//! `IkGeoService` answers `SolveIk`, `ForwardKinematics` and `ListRobots` for every robot
+//! `solver::solver_by_name` knows, or for the built-in robots and those of a `RobotRegistry` of its
+//! own. Requests naming an unknown robot fail with `NOT_FOUND`, and poses or joint values with the
+//! wrong number of values with `INVALID_ARGUMENT`. Solving runs on the blocking thread pool of
+//! tokio, so a slow search does not hold up the other requests. Serve it with tonic:
+//!
+//! ```ignore
+//! tonic::transport::Server::builder()
+//!     .add_service(IkGeoService::new().into_server())
+//!     .serve("0.0.0.0:50051".parse()?)
+//!     .await?;
+//! ```
+
+use {
+    crate::{
+        error::IkGeoError,
+        pose::Pose,
+        registry::{self, RobotRegistry},
+        solver::{solver_by_name, solver_names, PoseSolver},
+    },
+    nalgebra::{Matrix3, Vector3},
+    proto::{
+        ik_geo_server::{IkGeo, IkGeoServer},
+        ForwardKinematicsRequest, ForwardKinematicsResponse, ListRobotsRequest, ListRobotsResponse,
+        SolveIkRequest, SolveIkResponse,
+    },
+    tonic::{Request, Response, Status},
+};
+
+/// The messages, client and server generated from `proto/ik_geo.proto`
+pub mod proto {
+    tonic::include_proto!("ik_geo.v1");
+}
+
+/// The IK service, see the `service` module
+#[derive(Debug, Default)]
+pub struct IkGeoService {
+    /// The robots of your own, or those of `registry::global()` when `None`
+    registry: Option<RobotRegistry>,
+}
+
+impl IkGeoService {
+    /// The service of the built-in robots and those of `registry::global()`
+    pub fn new() -> Self {
+        IkGeoService { registry: None }
+    }
+
+    /// The service of the built-in robots and those of `registry`
+    pub fn with_registry(registry: RobotRegistry) -> Self {
+        IkGeoService {
+            registry: Some(registry),
+        }
+    }
+
+    /// The tonic service to add to a server
+    pub fn into_server(self) -> IkGeoServer<Self> {
+        IkGeoServer::new(self)
+    }
+
+    fn solver(&self, name: &str) -> Option<Box<dyn PoseSolver + Send + Sync>> {
+        match &self.registry {
+            Some(registry) => registry.solver(name),
+            None => solver_by_name(name),
+        }
+    }
+
+    /// The names of the built-in robots followed by those of the registry
+    fn names(&self) -> Vec<String> {
+        let builtin = solver_names().map(String::from);
+        match &self.registry {
+            Some(registry) => builtin.chain(registry.names().map(String::from)).collect(),
+            None => {
+                let global = registry::global()
+                    .read()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner);
+                builtin.chain(global.names().map(String::from)).collect()
+            }
+        }
+    }
+}
+
+fn not_found(name: &str) -> Status {
+    Status::not_found(format!("there is no robot called \"{name}\""))
+}
+
+fn invalid_argument(e: IkGeoError) -> Status {
+    Status::invalid_argument(e.to_string())
+}
+
+impl TryFrom<proto::Pose> for Pose {
+    type Error = IkGeoError;
+
+    fn try_from(pose: proto::Pose) -> Result<Self, Self::Error> {
+        for (values, expected) in [(&pose.rotation, 9), (&pose.translation, 3)] {
+            if values.len() != expected {
+                return Err(IkGeoError::DimensionMismatch {
+                    expected,
+                    found: values.len(),
+                });
+            }
+        }
+        Ok(Pose::new(
+            Matrix3::from_row_slice(&pose.rotation),
+            Vector3::from_column_slice(&pose.translation),
+        ))
+    }
+}
+
+impl From<Pose> for proto::Pose {
+    fn from(pose: Pose) -> Self {
+        proto::Pose {
+            rotation: pose.rotation.transpose().as_slice().to_vec(),
+            translation: pose.translation.as_slice().to_vec(),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl IkGeo for IkGeoService {
+    async fn solve_ik(
+        &self,
+        request: Request<SolveIkRequest>,
+    ) -> Result<Response<SolveIkResponse>, Status> {
+        let request = request.into_inner();
+        let solver = self
+            .solver(&request.robot)
+            .ok_or_else(|| not_found(&request.robot))?;
+        let pose = request
+            .pose
+            .ok_or_else(|| Status::invalid_argument("the request has no pose"))?;
+        let pose = Pose::try_from(pose).map_err(invalid_argument)?;
+
+        let solutions = tokio::task::spawn_blocking(move || solver.solve(&pose))
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        let solutions = solutions
+            .iter()
+            .map(|solution| proto::IkSolution {
+                q: solution.q.as_slice().to_vec(),
+                error: solution.error,
+                is_ls: solution.is_ls,
+            })
+            .collect();
+        Ok(Response::new(SolveIkResponse { solutions }))
+    }
+
+    async fn forward_kinematics(
+        &self,
+        request: Request<ForwardKinematicsRequest>,
+    ) -> Result<Response<ForwardKinematicsResponse>, Status> {
+        let request = request.into_inner();
+        let solver = self
+            .solver(&request.robot)
+            .ok_or_else(|| not_found(&request.robot))?;
+        let pose = solver.fk(&request.q).map_err(invalid_argument)?;
+        Ok(Response::new(ForwardKinematicsResponse {
+            pose: Some(pose.into()),
+        }))
+    }
+
+    async fn list_robots(
+        &self,
+        _request: Request<ListRobotsRequest>,
+    ) -> Result<Response<ListRobotsResponse>, Status> {
+        Ok(Response::new(ListRobotsResponse {
+            robots: self.names(),
+        }))
+    }
+}
@@ -1,8 +1,34 @@
 use {
-    crate::subproblems::setups::DELTA,
-    std::fmt::{Debug, Display, Formatter, Result},
+    crate::{
+        config::{SingularBranchPolicy, SolverConfig},
+        error::IkGeoError,
+        inverse_kinematics::auxiliary::{wrap_to_pi, Kinematics},
+        labels::BranchLabel,
+        limits::{unwrap_near, JointLimits},
+        metrics::JointMetric,
+    },
+    arrayvec::ArrayVec,
+    alloc::{format, string::String, vec, vec::Vec},
+    core::{
+        cmp::Ordering,
+        f64::consts::{PI, TAU},
+        fmt::{Debug, Display, Formatter, Result},
+        iter::Flatten,
+        ops::Index,
+    },
+    nalgebra::{Matrix3, SVector, Vector3},
 };
 
+pub const DELTA: f64 = 1e-12;
+
+/// The capacity of a `SolutionBuffer`, more than any closed-form solver returns for one pose
+pub const MAX_SOLUTIONS: usize = 16;
+
+/// Fixed capacity storage for the solutions of one pose, which `Robot::solve_into` fills without
+/// allocating
+pub type SolutionBuffer<const N: usize = 6> = ArrayVec<IkSolution<N>, MAX_SOLUTIONS>;
+
+/// Up to two solutions of a subproblem
 #[derive(Debug, Clone)]
 pub enum SolutionSet2<T> {
     Zero,
@@ -10,6 +36,7 @@ pub enum SolutionSet2<T> {
     Two(T, T),
 }
 
+/// Up to four solutions of a subproblem
 #[derive(Debug, Clone)]
 pub enum SolutionSet4<T> {
     Zero,
@@ -47,12 +74,15 @@ impl<T: Copy> SolutionSet2<T> {
         }
     }
 
-    pub fn get_all(&self) -> Vec<T> {
+    /// Iterates over the solutions without allocating
+    pub fn get_all(&self) -> Flatten<core::array::IntoIter<Option<T>, 2>> {
         match self {
-            Self::Zero => vec![],
-            Self::One(s) => vec![*s],
-            Self::Two(s1, s2) => vec![*s1, *s2],
+            Self::Zero => [None, None],
+            Self::One(s) => [Some(*s), None],
+            Self::Two(s1, s2) => [Some(*s1), Some(*s2)],
         }
+        .into_iter()
+        .flatten()
     }
 
     pub fn deltas() -> [(f64, f64); DELTAS_SIZE_2] {
@@ -80,19 +110,18 @@ impl<T: Copy + Debug> SolutionSet2<T> {
     pub fn as_csv(&self) -> String {
         let mut results = self
             .get_all()
-            .into_iter()
             .map(|v| format!("{v:?}"))
             .collect::<Vec<String>>();
         results.append(&mut vec![String::new(); 2 - results.len()]);
         results.join(",")
     }
 
-    pub fn from_vec(vec: &Vec<T>) -> Self {
-        match vec.len() {
+    pub fn from_slice(slice: &[T]) -> Self {
+        match slice.len() {
             0 => Self::Zero,
-            1 => Self::One(vec[0]),
-            2 => Self::Two(vec[0], vec[1]),
-            i => panic!("Vector {vec:?} contains too many solutions: {i}"),
+            1 => Self::One(slice[0]),
+            2 => Self::Two(slice[0], slice[1]),
+            i => panic!("Slice {slice:?} contains too many solutions: {i}"),
         }
     }
 }
@@ -136,14 +165,17 @@ impl<T: Copy> SolutionSet4<T> {
         }
     }
 
-    pub fn get_all(&self) -> Vec<T> {
+    /// Iterates over the solutions without allocating
+    pub fn get_all(&self) -> Flatten<core::array::IntoIter<Option<T>, 4>> {
         match self {
-            Self::Zero => vec![],
-            Self::One(s) => vec![*s],
-            Self::Two(s1, s2) => vec![*s1, *s2],
-            Self::Three(s1, s2, s3) => vec![*s1, *s2, *s3],
-            Self::Four(s1, s2, s3, s4) => vec![*s1, *s2, *s3, *s4],
+            Self::Zero => [None, None, None, None],
+            Self::One(s) => [Some(*s), None, None, None],
+            Self::Two(s1, s2) => [Some(*s1), Some(*s2), None, None],
+            Self::Three(s1, s2, s3) => [Some(*s1), Some(*s2), Some(*s3), None],
+            Self::Four(s1, s2, s3, s4) => [Some(*s1), Some(*s2), Some(*s3), Some(*s4)],
         }
+        .into_iter()
+        .flatten()
     }
 
     pub fn deltas() -> [(f64, f64, f64, f64); DELTAS_SIZE_4] {
@@ -164,21 +196,20 @@ impl<T: Copy + Debug> SolutionSet4<T> {
     pub fn as_csv(&self) -> String {
         let mut results = self
             .get_all()
-            .into_iter()
             .map(|v| format!("{v:?}"))
             .collect::<Vec<String>>();
         results.append(&mut vec![String::new(); 4 - results.len()]);
         results.join(",")
     }
 
-    pub fn from_vec(vec: &Vec<T>) -> Self {
-        match vec.len() {
+    pub fn from_slice(slice: &[T]) -> Self {
+        match slice.len() {
             0 => Self::Zero,
-            1 => Self::One(vec[0]),
-            2 => Self::Two(vec[0], vec[1]),
-            3 => Self::Three(vec[0], vec[1], vec[2]),
-            4 => Self::Four(vec[0], vec[1], vec[2], vec[3]),
-            i => panic!("Vector {vec:?} contains too many solutions: {i}"),
+            1 => Self::One(slice[0]),
+            2 => Self::Two(slice[0], slice[1]),
+            3 => Self::Three(slice[0], slice[1], slice[2]),
+            4 => Self::Four(slice[0], slice[1], slice[2], slice[3]),
+            i => panic!("Slice {slice:?} contains too many solutions: {i}"),
         }
     }
 }
@@ -204,3 +235,713 @@ impl<T: Display> Display for SolutionSet4<T> {
         }
     }
 }
+
+impl<T> FromIterator<T> for SolutionSet2<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        iter.into_iter().fold(Self::Zero, |set, s| match set {
+            Self::Zero => Self::One(s),
+            Self::One(s1) => Self::Two(s1, s),
+            Self::Two(..) => panic!("More than 2 solutions"),
+        })
+    }
+}
+
+impl<T> FromIterator<T> for SolutionSet4<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        iter.into_iter().fold(Self::Zero, |set, s| match set {
+            Self::Zero => Self::One(s),
+            Self::One(s1) => Self::Two(s1, s),
+            Self::Two(s1, s2) => Self::Three(s1, s2, s),
+            Self::Three(s1, s2, s3) => Self::Four(s1, s2, s3, s),
+            Self::Four(..) => panic!("More than 4 solutions"),
+        })
+    }
+}
+
+impl<T> Index<usize> for SolutionSet2<T> {
+    type Output = T;
+
+    fn index(&self, i: usize) -> &T {
+        match (self, i) {
+            (Self::One(s) | Self::Two(s, _), 0) => s,
+            (Self::Two(_, s), 1) => s,
+            _ => panic!("No solution {i}"),
+        }
+    }
+}
+
+impl<T> Index<usize> for SolutionSet4<T> {
+    type Output = T;
+
+    fn index(&self, i: usize) -> &T {
+        match (self, i) {
+            (Self::One(s) | Self::Two(s, _) | Self::Three(s, _, _) | Self::Four(s, _, _, _), 0) => {
+                s
+            }
+            (Self::Two(_, s) | Self::Three(_, s, _) | Self::Four(_, s, _, _), 1) => s,
+            (Self::Three(_, _, s) | Self::Four(_, _, s, _), 2) => s,
+            (Self::Four(_, _, _, s), 3) => s,
+            _ => panic!("No solution {i}"),
+        }
+    }
+}
+
+/// A single IK solution of an `N` joint robot
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IkSolution<const N: usize = 6> {
+    /// The joint angles
+    pub q: SVector<f64, N>,
+    /// Whether this is a least squares approximation rather than an exact solution
+    pub is_ls: bool,
+    /// The residual between the pose reached by the end of the chain and the target the solver
+    /// was given, measured with the `error_metric` and weights of its `SolverConfig`. With the
+    /// defaults this is `|R(q) - R| + |p(q) - p|`, see `residual` and `residual_with`.
+    pub error: f64,
+    /// Yoshikawa's manipulability of the robot at `q`, which approaches zero near a singularity.
+    /// Only filled in with `SolverConfig::annotate`, see `manipulability_on`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub manipulability: Option<f64>,
+    /// The position of this solution in the solver's enumeration of subproblem branches.
+    /// The same index picks the same combination of subproblem solutions for nearby poses, as long
+    /// as the number of solutions of each subproblem does not change.
+    pub branch: usize,
+    /// How many branches of the solver produced this solution, which is more than one at
+    /// degenerate poses where several branches coincide
+    #[cfg_attr(feature = "serde", serde(default = "one"))]
+    pub multiplicity: usize,
+    /// The shoulder, elbow and wrist configuration, for arms where these are meaningful. Only
+    /// filled in with `SolverConfig::annotate`, see `labels::BranchLabel::of`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub label: Option<BranchLabel>,
+    /// The solution as the solver returned it, if it was then polished by `IkSolutionSet::refine`
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub raw: Option<RawSolution<N>>,
+    /// Whether the solution is one of a continuum reaching the same pose, see
+    /// `SingularBranchPolicy`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub degenerate: bool,
+}
+
+/// A solution before `IkSolutionSet::refine` moved it, with its residual
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RawSolution<const N: usize = 6> {
+    pub q: SVector<f64, N>,
+    pub is_ls: bool,
+    pub error: f64,
+}
+
+#[cfg(feature = "serde")]
+fn one() -> usize {
+    1
+}
+
+impl<const N: usize> IkSolution<N> {
+    /// The solution of branch `branch` of a solver, with its residual and manipulability still to
+    /// be filled in
+    pub(crate) fn from_branch(q: SVector<f64, N>, is_ls: bool, branch: usize) -> Self {
+        IkSolution {
+            q,
+            is_ls,
+            error: 0.0,
+            manipulability: None,
+            branch,
+            multiplicity: 1,
+            label: None,
+            raw: None,
+            degenerate: false,
+        }
+    }
+
+    /// The residual `|R(q) - R| + |p(q) - p|` between the pose `kin` reaches at `q`, including its
+    /// base and tool frames, and the target pose `(r, t)`. This is the Frobenius residual only,
+    /// which matches `error` for solvers with the default `ErrorMetric` and weights.
+    pub fn residual<const C2: usize>(
+        &self,
+        kin: &Kinematics<N, C2>,
+        r: &Matrix3<f64>,
+        t: &Vector3<f64>,
+    ) -> f64 {
+        let (r_q, t_q) = kin.forward_kinematics(&self.q);
+        (r_q - r).norm() + (t_q - t).norm()
+    }
+
+    /// The residual like `residual`, measured with the error metric and weights of `config` as
+    /// `error` is by a solver with that config
+    pub fn residual_with<const C2: usize>(
+        &self,
+        kin: &Kinematics<N, C2>,
+        r: &Matrix3<f64>,
+        t: &Vector3<f64>,
+        config: &SolverConfig,
+    ) -> f64 {
+        let (r_q, t_q) = kin.forward_kinematics(&self.q);
+        config.pose_error(&r_q, &t_q, r, t)
+    }
+
+    /// Yoshikawa's manipulability of `kin` at the solution, as `SolverConfig::annotate` fills in
+    pub fn manipulability_on<const C2: usize>(&self, kin: &Kinematics<N, C2>) -> f64 {
+        kin.manipulability(&self.q)
+    }
+}
+
+/// The formats `IkSolutionSet::write` can write solutions in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// The joint angles of every solution, all separated by commas
+    #[default]
+    PlainText,
+    /// A header, then a line per solution with its joint angles followed by the other fields of
+    /// `IkSolution`
+    Csv,
+    /// An array with an object per solution, with the fields of `IkSolution`
+    Json,
+    /// The number of solutions as a little-endian `u64`, then the joint angles and residual of
+    /// each solution as little-endian `f64`s followed by `is_ls` as a byte. This is how bincode
+    /// encodes a `Vec<([f64; N], f64, bool)>` with its default options.
+    Bincode,
+    /// The output of the sample program of ikfast: a `Found N ik solutions:` line, then a
+    /// `solI (free=0): ` line per solution with its joint angles to 15 decimals, each followed by
+    /// a comma and a space. The solutions are those of `IkSolutionSet::to_ikfast`.
+    IkFast,
+}
+
+/// All IK solutions for a single pose
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct IkSolutionSet<const N: usize = 6> {
+    solutions: Vec<IkSolution<N>>,
+}
+
+impl<const N: usize> IkSolutionSet<N> {
+    pub fn new() -> Self {
+        Self {
+            solutions: Vec::new(),
+        }
+    }
+
+    /// Add the next branch of a solver, with its residual and manipulability still to be filled
+    /// in by `set_errors` and `set_manipulability`
+    pub(crate) fn push(&mut self, q: SVector<f64, N>, is_ls: bool) {
+        self.solutions
+            .push(IkSolution::from_branch(q, is_ls, self.solutions.len()));
+    }
+
+    /// Fill in the residual of every solution
+    pub(crate) fn set_errors<F: Fn(&SVector<f64, N>) -> f64>(&mut self, error: F) {
+        for solution in &mut self.solutions {
+            solution.error = error(&solution.q);
+        }
+    }
+
+    /// Recompute the residual of every solution against the target pose `(r, t)` of `kin`
+    pub fn compute_errors<const C2: usize>(
+        &mut self,
+        kin: &Kinematics<N, C2>,
+        r: &Matrix3<f64>,
+        t: &Vector3<f64>,
+    ) {
+        for solution in &mut self.solutions {
+            solution.error = solution.residual(kin, r, t);
+        }
+    }
+
+    /// Recompute the residual of every solution against the target pose `(r, t)` of `kin`, with
+    /// the error metric and weights of `config`
+    pub fn compute_errors_with<const C2: usize>(
+        &mut self,
+        kin: &Kinematics<N, C2>,
+        r: &Matrix3<f64>,
+        t: &Vector3<f64>,
+        config: &SolverConfig,
+    ) {
+        for solution in &mut self.solutions {
+            solution.error = solution.residual_with(kin, r, t, config);
+        }
+    }
+
+    /// Mark the solutions whose residual is above `tolerance` as least squares and the rest as
+    /// exact, after their joint angles were changed from what the solver returned
+    pub(crate) fn set_ls_by_error(&mut self, tolerance: f64) {
+        for solution in &mut self.solutions {
+            solution.is_ls = solution.error > tolerance;
+        }
+    }
+
+    /// Polish every solution toward the target pose `(r, t)` of `kin` with up to
+    /// `config.refine_iterations` Gauss-Newton steps, see `Kinematics::refine`, keeping what the
+    /// solver returned in `raw`. Both residuals are measured against `(r, t)` with `config`, and
+    /// the refined solution is only kept if its residual is no larger. A least squares solution
+    /// becomes exact if it is now within `config.ls_tolerance`.
+    pub fn refine<const C2: usize>(
+        &mut self,
+        kin: &Kinematics<N, C2>,
+        r: &Matrix3<f64>,
+        t: &Vector3<f64>,
+        config: &SolverConfig,
+    ) {
+        let error = |q: &SVector<f64, N>| {
+            let (r_q, t_q) = kin.forward_kinematics(q);
+            config.pose_error(&r_q, &t_q, r, t)
+        };
+
+        for solution in &mut self.solutions {
+            let raw = solution.raw.unwrap_or(RawSolution {
+                q: solution.q,
+                is_ls: solution.is_ls,
+                error: error(&solution.q),
+            });
+
+            // The steps shrink the twist to the target, which for least squares solutions need
+            // not shrink the residual in every metric
+            let refined = kin.refine(&solution.q, r, t, config.refine_iterations);
+            let refined_error = error(&refined);
+            if refined_error <= raw.error {
+                solution.q = refined;
+                solution.error = refined_error;
+            } else {
+                solution.q = raw.q;
+                solution.error = raw.error;
+            }
+            solution.is_ls = raw.is_ls && solution.error > config.ls_tolerance;
+            if config.annotate {
+                solution.manipulability = Some(kin.manipulability(&solution.q));
+            }
+            solution.raw = Some(raw);
+        }
+    }
+
+    /// Flag the solutions of `kin` that are one of a continuum reaching the same pose, and add
+    /// points of the continuum as `config.singular_policy` asks. Added solutions keep the branch,
+    /// residual and manipulability of the solution they continue.
+    pub fn apply_singular_policy<const C2: usize>(
+        &mut self,
+        kin: &Kinematics<N, C2>,
+        config: &SolverConfig,
+    ) {
+        let mut continued = Vec::new();
+        for solution in &mut self.solutions {
+            let Some(pair) = kin
+                .iter_coincident_axes(&solution.q, config.ls_tolerance)
+                .next()
+            else {
+                continue;
+            };
+            solution.degenerate = true;
+            continued.extend(continuum(solution, pair, config.singular_policy));
+        }
+        self.solutions.extend(continued);
+    }
+
+    /// Fill in the manipulability of every solution
+    pub(crate) fn set_manipulability<F: Fn(&SVector<f64, N>) -> f64>(&mut self, manipulability: F) {
+        for solution in &mut self.solutions {
+            solution.manipulability = Some(manipulability(&solution.q));
+        }
+    }
+
+    /// Fill in the branch label of every solution
+    pub(crate) fn set_labels<F: Fn(&SVector<f64, N>) -> Option<BranchLabel>>(&mut self, label: F) {
+        for solution in &mut self.solutions {
+            solution.label = label(&solution.q);
+        }
+    }
+
+    /// The solution with the given shoulder, elbow and wrist configuration, among solutions
+    /// labeled with `SolverConfig::annotate`
+    pub fn labeled(&self, label: BranchLabel) -> Option<&IkSolution<N>> {
+        self.solutions
+            .iter()
+            .find(|solution| solution.label == Some(label))
+    }
+
+    pub fn len(&self) -> usize {
+        self.solutions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.solutions.is_empty()
+    }
+
+    pub fn iter(&self) -> core::slice::Iter<'_, IkSolution<N>> {
+        self.solutions.iter()
+    }
+
+    /// The solution with the lowest residual
+    pub fn best(&self) -> Option<&IkSolution<N>> {
+        self.solutions
+            .iter()
+            .min_by(|a, b| a.error.total_cmp(&b.error))
+    }
+
+    /// The solution closest to `seed` under `metric`, e.g. to stay near the current configuration
+    /// of the arm
+    pub fn nearest(
+        &self,
+        seed: &SVector<f64, N>,
+        metric: &JointMetric<N>,
+    ) -> Option<&IkSolution<N>> {
+        self.solutions.iter().min_by(|a, b| {
+            metric
+                .distance(seed, &a.q)
+                .total_cmp(&metric.distance(seed, &b.q))
+        })
+    }
+
+    /// Sort the solutions by their distance from `seed` under `metric`, closest first
+    pub fn sort_by_distance(&mut self, seed: &SVector<f64, N>, metric: &JointMetric<N>) {
+        self.sort_by(|a, b| {
+            metric
+                .distance(seed, &a.q)
+                .total_cmp(&metric.distance(seed, &b.q))
+        });
+    }
+
+    /// Sort the solutions by residual, lowest first
+    pub fn sort_by_error(&mut self) {
+        self.sort_by(|a, b| a.error.total_cmp(&b.error));
+    }
+
+    pub fn sort_by<F: FnMut(&IkSolution<N>, &IkSolution<N>) -> Ordering>(&mut self, compare: F) {
+        self.solutions.sort_by(compare);
+    }
+
+    /// Keep only the solutions for which `keep` returns true
+    pub fn retain<F: FnMut(&IkSolution<N>) -> bool>(&mut self, keep: F) {
+        self.solutions.retain(keep);
+    }
+
+    /// Drop the least squares approximations
+    pub fn exact(mut self) -> Self {
+        self.retain(|solution| !solution.is_ls);
+        self
+    }
+
+    /// Merge the solutions whose joint angles all lie within `tolerance` of each other, modulo
+    /// 2π. The merged solution is the one with the lowest residual, and its multiplicity is the
+    /// total of the merged ones.
+    pub fn dedup(&mut self, tolerance: f64) {
+        let kept = merge_duplicates(&mut self.solutions, tolerance);
+        self.solutions.truncate(kept);
+    }
+
+    /// Drop the solutions whose residual is above `tolerance`
+    pub fn within(mut self, tolerance: f64) -> Self {
+        self.retain(|solution| solution.error <= tolerance);
+        self
+    }
+
+    /// Drop the solutions outside of `limits`
+    pub fn within_limits(mut self, limits: &JointLimits<N>) -> Self {
+        self.retain(|solution| limits.contains(&solution.q));
+        self
+    }
+
+    /// Move the joint angles of every solution to the revolution closest to `q_current`. With
+    /// `limits`, only revolutions within the limits are considered and the solutions without one
+    /// are dropped.
+    pub fn unwrap_near(&mut self, q_current: &SVector<f64, N>, limits: Option<&JointLimits<N>>) {
+        self.solutions.retain_mut(|solution| {
+            let q = match limits {
+                Some(limits) => limits.unwrap_near(&solution.q, q_current),
+                None => Some(unwrap_near(&solution.q, q_current)),
+            };
+            q.map(|q| solution.q = q).is_some()
+        });
+    }
+
+    /// Replace every solution by its revolutions within `limits`, so joints with limits wider than
+    /// a turn get a solution for each turn, and solutions with no revolution within the limits are
+    /// dropped
+    pub fn expand_within_limits(self, limits: &JointLimits<N>) -> Self {
+        self.solutions
+            .into_iter()
+            .flat_map(|solution| {
+                limits
+                    .revolutions(&solution.q)
+                    .into_iter()
+                    .map(move |q| IkSolution {
+                        q,
+                        ..solution.clone()
+                    })
+            })
+            .collect()
+    }
+
+    /// Transform the joint angles of every solution, keeping the rest of its data
+    pub fn map_q<const M: usize, F: Fn(&SVector<f64, N>) -> SVector<f64, M>>(
+        self,
+        f: F,
+    ) -> IkSolutionSet<M> {
+        self.solutions
+            .into_iter()
+            .map(|solution| IkSolution {
+                q: f(&solution.q),
+                is_ls: solution.is_ls,
+                error: solution.error,
+                manipulability: solution.manipulability,
+                branch: solution.branch,
+                multiplicity: solution.multiplicity,
+                label: solution.label,
+                raw: solution.raw.map(|raw| RawSolution {
+                    q: f(&raw.q),
+                    is_ls: raw.is_ls,
+                    error: raw.error,
+                }),
+                degenerate: solution.degenerate,
+            })
+            .collect()
+    }
+
+    /// The joint angles of the solutions in the order and form ikfast returns them: only exact
+    /// solutions, ordered by branch so the first joint solved varies slowest, with every angle in
+    /// `(-π, π]`. Every joint is solved for, so unlike ikfast no solution has free joints.
+    pub fn to_ikfast(&self) -> Vec<SVector<f64, N>> {
+        let mut exact: Vec<&IkSolution<N>> = self.iter().filter(|s| !s.is_ls).collect();
+        exact.sort_by_key(|solution| solution.branch);
+        exact
+            .into_iter()
+            .map(|solution| {
+                solution.q.map(|x| {
+                    let x = wrap_to_pi(x);
+                    if x <= -PI {
+                        x + TAU
+                    } else {
+                        x
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// Write the solutions in `format`. The text formats are UTF-8.
+    pub fn write(&self, format: OutputFormat) -> Vec<u8> {
+        let join = |q: &SVector<f64, N>| {
+            q.iter()
+                .map(|x| format!("{x}"))
+                .collect::<Vec<String>>()
+                .join(",")
+        };
+
+        match format {
+            OutputFormat::PlainText => self
+                .iter()
+                .map(|solution| join(&solution.q))
+                .collect::<Vec<String>>()
+                .join(",")
+                .into_bytes(),
+            OutputFormat::Csv => {
+                let mut csv = (1..=N).map(|i| format!("q{i},")).collect::<String>();
+                csv += "error,is_ls,manipulability,branch,multiplicity\n";
+                for solution in self {
+                    csv += &format!(
+                        "{},{},{},{},{},{}\n",
+                        join(&solution.q),
+                        solution.error,
+                        solution.is_ls,
+                        solution.manipulability.map_or(String::new(), |m| format!("{m}")),
+                        solution.branch,
+                        solution.multiplicity
+                    );
+                }
+                csv.into_bytes()
+            }
+            OutputFormat::Json => {
+                // JSON has no NaN or infinity, so they are written as null
+                let number = |x: f64| {
+                    if x.is_finite() {
+                        format!("{x}")
+                    } else {
+                        String::from("null")
+                    }
+                };
+                let objects = self
+                    .iter()
+                    .map(|solution| {
+                        let q = solution.q.iter().map(|&x| number(x));
+                        format!(
+                            concat!(
+                                "{{\"q\":[{}],\"is_ls\":{},\"error\":{},",
+                                "\"manipulability\":{},\"branch\":{},\"multiplicity\":{}}}"
+                            ),
+                            q.collect::<Vec<String>>().join(","),
+                            solution.is_ls,
+                            number(solution.error),
+                            solution.manipulability.map_or(String::from("null"), number),
+                            solution.branch,
+                            solution.multiplicity
+                        )
+                    })
+                    .collect::<Vec<String>>();
+                format!("[{}]", objects.join(",")).into_bytes()
+            }
+            OutputFormat::IkFast => {
+                let solutions = self.to_ikfast();
+                let mut text = format!("Found {} ik solutions:\n", solutions.len());
+                for (i, q) in solutions.iter().enumerate() {
+                    text += &format!("sol{i} (free=0): ");
+                    for x in q.iter() {
+                        text += &format!("{x:.15}, ");
+                    }
+                    text += "\n";
+                }
+                text.into_bytes()
+            }
+            OutputFormat::Bincode => {
+                let mut bytes = Vec::with_capacity(8 + self.len() * (8 * N + 9));
+                bytes.extend_from_slice(&(self.len() as u64).to_le_bytes());
+                for solution in self {
+                    for x in solution.q.iter().chain([&solution.error]) {
+                        bytes.extend_from_slice(&x.to_le_bytes());
+                    }
+                    bytes.push(solution.is_ls as u8);
+                }
+                bytes
+            }
+        }
+    }
+}
+
+impl<const N: usize> Index<usize> for IkSolutionSet<N> {
+    type Output = IkSolution<N>;
+
+    fn index(&self, i: usize) -> &IkSolution<N> {
+        &self.solutions[i]
+    }
+}
+
+/// The points of the continuum through the degenerate `solution` that `policy` adds, turning
+/// joint `i` by `s` and joint `j` by `-sign * s` for the coincident axes `(i, j, sign)`
+fn continuum<const N: usize>(
+    solution: &IkSolution<N>,
+    (i, j, sign): (usize, usize, f64),
+    policy: SingularBranchPolicy,
+) -> impl Iterator<Item = IkSolution<N>> + '_ {
+    let (samples, step) = match policy {
+        SingularBranchPolicy::FlagDegenerate => (1, 0.0),
+        SingularBranchPolicy::ReturnBoth => (2, PI),
+        SingularBranchPolicy::ReturnContinuum { samples } => (samples, TAU / samples as f64),
+    };
+    (1..samples).map(move |k| {
+        let s = step * k as f64;
+        let mut q = solution.q;
+        q[i] = wrap_to_pi(q[i] + s);
+        q[j] = wrap_to_pi(q[j] - sign * s);
+        IkSolution {
+            q,
+            ..solution.clone()
+        }
+    })
+}
+
+/// Merge the duplicates in `solutions` as `IkSolutionSet::dedup` does, moving the kept solutions
+/// to the front in their order, and return how many were kept
+pub(crate) fn merge_duplicates<const N: usize>(
+    solutions: &mut [IkSolution<N>],
+    tolerance: f64,
+) -> usize {
+    let mut kept = 0;
+    for i in 0..solutions.len() {
+        let duplicate = (0..kept)
+            .find(|&k| (solutions[k].q - solutions[i].q).map(wrap_to_pi).amax() < tolerance);
+
+        match duplicate {
+            Some(k) => {
+                let multiplicity = solutions[k].multiplicity + solutions[i].multiplicity;
+                if solutions[i].error < solutions[k].error {
+                    solutions.swap(i, k);
+                }
+                solutions[k].multiplicity = multiplicity;
+            }
+            None => {
+                solutions.swap(i, kept);
+                kept += 1;
+            }
+        }
+    }
+    kept
+}
+
+/// `IkSolutionSet::apply_singular_policy` on `solutions`, failing with
+/// `IkGeoError::CapacityExceeded` if the added solutions do not fit
+pub(crate) fn apply_singular_policy_into<const C2: usize>(
+    solutions: &mut SolutionBuffer,
+    kin: &Kinematics<6, C2>,
+    config: &SolverConfig,
+) -> core::result::Result<(), IkGeoError> {
+    for index in 0..solutions.len() {
+        let Some(pair) = kin
+            .iter_coincident_axes(&solutions[index].q, config.ls_tolerance)
+            .next()
+        else {
+            continue;
+        };
+        solutions[index].degenerate = true;
+
+        let solution = solutions[index].clone();
+        for continued in continuum(&solution, pair, config.singular_policy) {
+            solutions
+                .try_push(continued)
+                .map_err(|_| IkGeoError::CapacityExceeded {
+                    capacity: MAX_SOLUTIONS,
+                })?;
+        }
+    }
+    Ok(())
+}
+
+/// `IkSolutionSet::expand_within_limits` on `solutions`, failing with
+/// `IkGeoError::CapacityExceeded` if the revolutions do not fit
+pub(crate) fn expand_within_limits_into(
+    solutions: &mut SolutionBuffer,
+    limits: &JointLimits,
+) -> core::result::Result<(), IkGeoError> {
+    let unexpanded = core::mem::take(solutions);
+    for solution in &unexpanded {
+        for q in limits.iter_revolutions(&solution.q) {
+            solutions
+                .try_push(IkSolution {
+                    q,
+                    ..solution.clone()
+                })
+                .map_err(|_| IkGeoError::CapacityExceeded {
+                    capacity: MAX_SOLUTIONS,
+                })?;
+        }
+    }
+    Ok(())
+}
+
+impl<const N: usize> FromIterator<IkSolution<N>> for IkSolutionSet<N> {
+    fn from_iter<I: IntoIterator<Item = IkSolution<N>>>(iter: I) -> Self {
+        Self {
+            solutions: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<const N: usize> IntoIterator for IkSolutionSet<N> {
+    type Item = IkSolution<N>;
+    type IntoIter = vec::IntoIter<IkSolution<N>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.solutions.into_iter()
+    }
+}
+
+impl<'a, const N: usize> IntoIterator for &'a IkSolutionSet<N> {
+    type Item = &'a IkSolution<N>;
+    type IntoIter = core::slice::Iter<'a, IkSolution<N>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.solutions.iter()
+    }
+}
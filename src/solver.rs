@@ -0,0 +1,167 @@
+//! One interface over every robot, for tooling that should work with any of them.
+//!
+//! The hardcoded robots and `Robot` each come with their own constructor and their own `fk`, so
+//! code written for one of them does not work for another. `PoseSolver` gives them a common shape:
+//! the solutions for a `Pose`, the kinematics the solutions are joint values of, and the number of
+//! joints. `solver_by_name` looks up any of them by name, e.g. `solver_by_name("UR5")`,
+//! along with the robots of your own in the global `registry::RobotRegistry`.
+//!
+//! The hardcoded 7-DOF robots with a fixed joint solve for the six other joints, so their
+//! kinematics are those of `Kinematics::lock_joint`. `FanucLrMate200id` returns the joint angles
+//! of its kinematics rather than the FANUC joint coordinates of its `IKSolver::ik`, see
+//! `JointMapping::fanuc_j2_j3`.
+
+use {
+    crate::{
+        error::IkGeoError,
+        inverse_kinematics::{auxiliary::KinematicsDyn, hardcoded::setups},
+        pose::Pose,
+        robot::{
+            preset, FanucLrMate200id, IKSolver, IkSolutionSet, KinovaGen3FixedQ3, KukaR800FixedQ3,
+            PandaFixedQ4, Robot, RrcFixedQ6, SawyerFixedQ3, UniversalRobots, YumiFixedQ3, PRESETS,
+        },
+    },
+    alloc::boxed::Box,
+    nalgebra::DVector,
+};
+
+#[cfg(feature = "std")]
+use crate::registry;
+
+/// A robot solved for the pose of its tool
+pub trait PoseSolver {
+    /// The solutions reaching `pose`
+    fn solve(&self, pose: &Pose) -> IkSolutionSet;
+
+    /// The kinematics whose joint values the solutions are
+    fn kinematics(&self) -> &KinematicsDyn;
+
+    /// The number of joints of each solution
+    fn dof(&self) -> usize {
+        self.kinematics().num_joints()
+    }
+
+    /// The pose reached at the joint values `q` of a solution, failing with
+    /// `IkGeoError::DimensionMismatch` unless there are `dof()` of them
+    fn fk(&self, q: &[f64]) -> Result<Pose, IkGeoError> {
+        let (rotation, translation) = self
+            .kinematics()
+            .forward_kinematics(&DVector::from_column_slice(q))?;
+        Ok(Pose::new(rotation, translation))
+    }
+}
+
+impl PoseSolver for Robot {
+    fn solve(&self, pose: &Pose) -> IkSolutionSet {
+        self.ik(pose.rotation, pose.translation)
+    }
+
+    fn kinematics(&self) -> &KinematicsDyn {
+        self.kinematics_dyn()
+    }
+}
+
+impl PoseSolver for UniversalRobots {
+    fn solve(&self, pose: &Pose) -> IkSolutionSet {
+        self.ik(pose.rotation, pose.translation)
+    }
+
+    fn kinematics(&self) -> &KinematicsDyn {
+        PoseSolver::kinematics(self.robot())
+    }
+}
+
+/// Solved in the joint angles of its kinematics rather than in FANUC joint coordinates, which
+/// `JointMapping::to_motor` of `robot().mapping()` converts them to
+impl PoseSolver for FanucLrMate200id {
+    fn solve(&self, pose: &Pose) -> IkSolutionSet {
+        self.robot().robot().ik(pose.rotation, pose.translation)
+    }
+
+    fn kinematics(&self) -> &KinematicsDyn {
+        PoseSolver::kinematics(self.robot().robot())
+    }
+}
+
+macro_rules! impl_fixed_joint_solver {
+    ($name:ident) => {
+        impl PoseSolver for $name {
+            fn solve(&self, pose: &Pose) -> IkSolutionSet {
+                self.ik(pose.rotation, pose.translation)
+            }
+
+            fn kinematics(&self) -> &KinematicsDyn {
+                self.locked_kinematics()
+            }
+        }
+    };
+}
+
+impl_fixed_joint_solver!(KukaR800FixedQ3);
+impl_fixed_joint_solver!(RrcFixedQ6);
+impl_fixed_joint_solver!(YumiFixedQ3);
+impl_fixed_joint_solver!(PandaFixedQ4);
+impl_fixed_joint_solver!(KinovaGen3FixedQ3);
+impl_fixed_joint_solver!(SawyerFixedQ3);
+
+/// The names `solver_by_name` accepts besides the `PRESETS`
+pub const SOLVERS: [&str; 13] = [
+    "ur3",
+    "ur3e",
+    "ur5e",
+    "ur10",
+    "ur10e",
+    "ur16e",
+    "fanuc_lr_mate_200id",
+    "kuka_r800_fixed_q3",
+    "rrc_fixed_q6",
+    "yumi_fixed_q3",
+    "panda_fixed_q4",
+    "kinova_gen3_fixed_q3",
+    "sawyer_fixed_q3",
+];
+
+/// The robot called `name`, one of the `PRESETS` or `SOLVERS` or, with the `std` feature, a robot
+/// registered in `registry::global()`, ignoring case
+pub fn solver_by_name(name: &str) -> Option<Box<dyn PoseSolver + Send + Sync>> {
+    #[cfg(feature = "std")]
+    if let Some(robot) = registry::global()
+        .read()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .robot(name)
+    {
+        return Some(Box::new(robot));
+    }
+    builtin_solver(name)
+}
+
+/// The built-in robot called `name`, ignoring case
+pub(crate) fn builtin_solver(name: &str) -> Option<Box<dyn PoseSolver + Send + Sync>> {
+    let name = name.to_ascii_lowercase();
+    if let Some(robot) = preset(&name) {
+        return Some(Box::new(robot));
+    }
+    let model = setups::UrModel::ALL
+        .into_iter()
+        .find(|model| alloc::format!("{model:?}").eq_ignore_ascii_case(&name));
+    if let Some(model) = model {
+        return Some(Box::new(UniversalRobots::new(model)));
+    }
+
+    let solver: Box<dyn PoseSolver + Send + Sync> = match name.as_str() {
+        "fanuc_lr_mate_200id" => Box::new(FanucLrMate200id::new()),
+        "kuka_r800_fixed_q3" => Box::new(KukaR800FixedQ3::new()),
+        "rrc_fixed_q6" => Box::new(RrcFixedQ6::try_new().ok()?),
+        "yumi_fixed_q3" => Box::new(YumiFixedQ3::new()),
+        "panda_fixed_q4" => Box::new(PandaFixedQ4::new()),
+        "kinova_gen3_fixed_q3" => Box::new(KinovaGen3FixedQ3::new()),
+        "sawyer_fixed_q3" => Box::new(SawyerFixedQ3::new()),
+        _ => return None,
+    };
+    Some(solver)
+}
+
+/// Every name `solver_by_name` accepts
+pub fn solver_names() -> impl Iterator<Item = &'static str> {
+    PRESETS.into_iter().chain(SOLVERS)
+}
@@ -0,0 +1,112 @@
+//! `proptest` strategies for fuzzing code built on the solvers, enabled with the `proptest`
+//! feature.
+//!
+//! `reachable_pose` draws poses the solvers should solve exactly, from the forward kinematics of
+//! random joint angles. The other strategies draw the poses where solvers and the code around them
+//! tend to break: close to joint limits, close to singularities and just out of reach.
+//!
+//! ```ignore
+//! proptest! {
+//!     #[test]
+//!     fn solves_reachable_poses((q, pose) in reachable_pose(robot.kinematics().clone())) {
+//!         prop_assert!(!robot.ik(pose.rotation, pose.translation).is_empty());
+//!     }
+//! }
+//! ```
+
+use {
+    crate::{
+        inverse_kinematics::auxiliary::{wrap_to_pi, Kinematics},
+        limits::JointLimits,
+        pose::Pose,
+        subproblems::subproblem1,
+    },
+    core::{array, f64::consts::PI},
+    nalgebra::{Vector3, Vector6},
+    proptest::prelude::*,
+};
+
+/// Joint angles in `[-π, π]`
+pub fn joint_angles() -> impl Strategy<Value = Vector6<f64>> {
+    prop::array::uniform6(-PI..=PI).prop_map(Vector6::from)
+}
+
+/// Joint angles within `limits`
+pub fn joint_angles_within(limits: JointLimits) -> impl Strategy<Value = Vector6<f64>> {
+    array::from_fn::<_, 6, _>(|i| limits.lower[i]..=limits.upper[i]).prop_map(Vector6::from)
+}
+
+/// A pose `kin` reaches, with the joint angles that reach it
+pub fn reachable_pose(kin: Kinematics<6, 7>) -> impl Strategy<Value = (Vector6<f64>, Pose)> {
+    joint_angles().prop_map(move |q| (q, kin.forward_kinematics(&q).into()))
+}
+
+/// A pose `kin` reaches within `limits`, with each joint equally likely to be within `margin` of
+/// its lower limit, within `margin` of its upper limit, or anywhere in its range
+pub fn near_joint_limits(
+    kin: Kinematics<6, 7>,
+    limits: JointLimits,
+    margin: f64,
+) -> impl Strategy<Value = (Vector6<f64>, Pose)> {
+    let joints = array::from_fn::<_, 6, _>(|i| {
+        let (lower, upper) = (limits.lower[i], limits.upper[i]);
+        let margin = margin.min(upper - lower);
+        prop_oneof![
+            lower..=lower + margin,
+            upper - margin..=upper,
+            lower..=upper
+        ]
+    });
+
+    joints.prop_map(move |q| {
+        let q = Vector6::from(q);
+        (q, kin.forward_kinematics(&q).into())
+    })
+}
+
+/// A pose `kin` reaches with the axes of joints `i` and `i + 2` within `tolerance` radians of
+/// parallel, for a random `i`. This is the singularity of a spherical wrist, and of any other pair
+/// of axes that can be lined up by the joint between them.
+pub fn near_singularity(
+    kin: Kinematics<6, 7>,
+    tolerance: f64,
+) -> impl Strategy<Value = (Vector6<f64>, Pose)> {
+    (
+        joint_angles(),
+        0..4_usize,
+        any::<bool>(),
+        -tolerance..=tolerance,
+    )
+        .prop_map(move |(mut q, i, antiparallel, offset)| {
+            // Joint i does not move its own axis, so only joint i + 1 decides the angle between
+            // the axes
+            let h_i = if antiparallel {
+                -kin.h.column(i)
+            } else {
+                kin.h.column(i).into()
+            };
+            let (aligned, _) = subproblem1(
+                &kin.h.column(i + 2).into(),
+                &h_i,
+                &kin.h.column(i + 1).into(),
+            );
+            q[i + 1] = wrap_to_pi(aligned + offset);
+
+            (q, kin.forward_kinematics(&q).into())
+        })
+}
+
+/// A pose `margin` beyond the reach of `kin` in a random direction from its base, where the reach
+/// is the sum of the lengths of the links and the tool, with a random orientation
+pub fn outside_workspace(kin: Kinematics<6, 7>, margin: f64) -> impl Strategy<Value = Pose> {
+    let reach = kin.p.column_iter().map(|p| p.norm()).sum::<f64>()
+        + kin.tool.translation.vector.norm()
+        + margin;
+    let center = kin.base.translation.vector;
+
+    (reachable_pose(kin), -PI..=PI, -1.0..=1.0_f64).prop_map(move |((_, pose), azimuth, z)| {
+        let radius = (1.0 - z * z).sqrt();
+        let direction = Vector3::new(radius * azimuth.cos(), radius * azimuth.sin(), z);
+        Pose::new(pose.rotation, center + direction * reach)
+    })
+}
@@ -0,0 +1,215 @@
+//! Subproblems 1 to 4 in `f32` on eight instances at once, for generating large numbers of
+//! solutions where single precision is enough, e.g. datasets for learning.
+//!
+//! Each lane of a `Vector3x8` holds the vector of one instance, and every function solves all
+//! eight lanes with the same SIMD instructions. There is no branching on the lanes: subproblems 3
+//! and 4 always return two angles, which coincide on the lanes with a least squares solution,
+//! and the least squares flags are lane masks, true where all bits of the lane are set, as
+//! returned by the comparisons of `wide`.
+
+pub use wide::f32x8;
+
+use {
+    nalgebra::Vector3,
+    wide::{CmpGt, CmpLe},
+};
+
+/// Eight 3D vectors, one per lane
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vector3x8 {
+    pub x: f32x8,
+    pub y: f32x8,
+    pub z: f32x8,
+}
+
+impl Vector3x8 {
+    pub fn new(x: f32x8, y: f32x8, z: f32x8) -> Self {
+        Vector3x8 { x, y, z }
+    }
+
+    /// The same vector in every lane
+    pub fn splat(v: &Vector3<f32>) -> Self {
+        Self::new(f32x8::splat(v.x), f32x8::splat(v.y), f32x8::splat(v.z))
+    }
+
+    pub fn from_vectors(vectors: &[Vector3<f32>; 8]) -> Self {
+        Self::new(
+            f32x8::new(vectors.map(|v| v.x)),
+            f32x8::new(vectors.map(|v| v.y)),
+            f32x8::new(vectors.map(|v| v.z)),
+        )
+    }
+
+    /// The vector of each lane
+    pub fn to_vectors(&self) -> [Vector3<f32>; 8] {
+        let (x, y, z) = (self.x.to_array(), self.y.to_array(), self.z.to_array());
+        core::array::from_fn(|i| Vector3::new(x[i], y[i], z[i]))
+    }
+
+    pub fn dot(&self, other: &Self) -> f32x8 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn cross(&self, other: &Self) -> Self {
+        Self::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
+    pub fn norm_squared(&self) -> f32x8 {
+        self.dot(self)
+    }
+
+    pub fn norm(&self) -> f32x8 {
+        self.norm_squared().sqrt()
+    }
+
+    pub fn normalize(&self) -> Self {
+        self.scale(f32x8::ONE / self.norm())
+    }
+
+    pub fn scale(&self, s: f32x8) -> Self {
+        Self::new(self.x * s, self.y * s, self.z * s)
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        Self::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+
+    pub fn sub(&self, other: &Self) -> Self {
+        Self::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+
+    pub fn neg(&self) -> Self {
+        Self::new(-self.x, -self.y, -self.z)
+    }
+
+    /// Rotate the vector of each lane about the unit axis `k` of the lane by the angle `theta` of
+    /// the lane, as `rot(k, theta) * self`
+    pub fn rotated(&self, k: &Self, theta: f32x8) -> Self {
+        let (sin, cos) = theta.sin_cos();
+        self.scale(cos)
+            .add(&k.cross(self).scale(sin))
+            .add(&k.scale(k.dot(self) * (f32x8::ONE - cos)))
+    }
+}
+
+/// The lanes of a mask that are set
+pub fn mask_to_bools(mask: f32x8) -> [bool; 8] {
+    let bits = mask.move_mask();
+    core::array::from_fn(|i| bits & (1 << i) != 0)
+}
+
+/// The angles of `sc`, given as their sines and cosines
+fn angle(sin: f32x8, cos: f32x8) -> f32x8 {
+    sin.atan2(cos)
+}
+
+/// Subproblem 1 on every lane: `theta` where `rot(k, theta) * p1 = p2`, or the least squares
+/// solution where the lengths of `p1` and `p2` or their heights along `k` differ by more than
+/// `tolerance`
+pub fn subproblem1_x8(
+    p1: &Vector3x8,
+    p2: &Vector3x8,
+    k: &Vector3x8,
+    tolerance: f32,
+) -> (f32x8, f32x8) {
+    let kxp = k.cross(p1);
+    let theta = angle(kxp.dot(p2), k.cross(&kxp).neg().dot(p2));
+
+    let tolerance = f32x8::splat(tolerance);
+    let is_ls = (p1.norm() - p2.norm()).abs().cmp_gt(tolerance)
+        | (k.dot(p1) - k.dot(p2)).abs().cmp_gt(tolerance);
+
+    (theta, is_ls)
+}
+
+/// Subproblem 2 on every lane: the pairs `(theta1, theta2)` where
+/// `rot(k1, theta1) * p1 = rot(k2, theta2) * p2`. Lanes with a single solution return it twice.
+pub fn subproblem2_x8(
+    p1: &Vector3x8,
+    p2: &Vector3x8,
+    k1: &Vector3x8,
+    k2: &Vector3x8,
+    tolerance: f32,
+) -> ([(f32x8, f32x8); 2], f32x8) {
+    let p1_norm = p1.normalize();
+    let p2_norm = p2.normalize();
+
+    let (theta1, theta1_is_ls) = subproblem4_x8(k2, &p1_norm, k1, k2.dot(&p2_norm));
+    let (theta2, theta2_is_ls) = subproblem4_x8(k1, &p2_norm, k2, k1.dot(&p1_norm));
+
+    let is_ls = (p1.norm() - p2.norm())
+        .abs()
+        .cmp_gt(f32x8::splat(tolerance))
+        | theta1_is_ls
+        | theta2_is_ls;
+
+    ([(theta1[0], theta2[1]), (theta1[1], theta2[0])], is_ls)
+}
+
+/// Subproblem 3 on every lane: `theta` where `|| rot(k, theta) * p1 - p2 || = d`. Lanes without
+/// an exact solution return the least squares solution twice.
+pub fn subproblem3_x8(
+    p1: &Vector3x8,
+    p2: &Vector3x8,
+    k: &Vector3x8,
+    d: f32x8,
+) -> ([f32x8; 2], f32x8) {
+    let two = f32x8::splat(2.0);
+
+    let kxp = k.cross(p1);
+    let a_col = kxp.cross(k);
+    let a = (p2.dot(&kxp) * -two, p2.dot(&a_col) * -two);
+    let norm_a_sq = a.0 * a.0 + a.1 * a.1;
+
+    let b = d * d - p2.sub(&k.scale(k.dot(p1))).norm_squared() - kxp.norm_squared();
+
+    let x_scale = -two * b / norm_a_sq;
+    let x_ls = (p2.dot(&kxp) * x_scale, p2.dot(&a_col) * x_scale);
+
+    let is_ls = (x_ls.0 * x_ls.0 + x_ls.1 * x_ls.1).cmp_gt(f32x8::ONE);
+
+    let xi = (f32x8::ONE - b * b / norm_a_sq).max(f32x8::ZERO).sqrt() / norm_a_sq.sqrt();
+    let a_perp = (a.1 * xi, -a.0 * xi);
+
+    (
+        [
+            angle(x_ls.0 + a_perp.0, x_ls.1 + a_perp.1),
+            angle(x_ls.0 - a_perp.0, x_ls.1 - a_perp.1),
+        ],
+        is_ls,
+    )
+}
+
+/// Subproblem 4 on every lane: `theta` where `h' * rot(k, theta) * p = d`. Lanes without an
+/// exact solution return the least squares solution twice.
+pub fn subproblem4_x8(
+    h: &Vector3x8,
+    p: &Vector3x8,
+    k: &Vector3x8,
+    d: f32x8,
+) -> ([f32x8; 2], f32x8) {
+    let a_11 = k.cross(p);
+    let a_12 = a_11.cross(k);
+    let a = (h.dot(&a_11), h.dot(&a_12));
+
+    let b = d - h.dot(k) * k.dot(p);
+    let norm_a_2 = a.0 * a.0 + a.1 * a.1;
+
+    let x_ls = (a.0 * b, a.1 * b);
+    let is_ls = norm_a_2.cmp_le(b * b);
+
+    let xi = (norm_a_2 - b * b).max(f32x8::ZERO).sqrt();
+    let a_perp = (a.1 * xi, -a.0 * xi);
+
+    (
+        [
+            angle(x_ls.0 + a_perp.0, x_ls.1 + a_perp.1),
+            angle(x_ls.0 - a_perp.0, x_ls.1 - a_perp.1),
+        ],
+        is_ls,
+    )
+}
@@ -0,0 +1,429 @@
+//! Interval arithmetic versions of subproblems 1 to 4.
+//!
+//! Each is the same computation as its floating-point counterpart carried out on intervals whose
+//! bounds are rounded outward, so the returned intervals are guaranteed to enclose the exact
+//! solutions of the equation for the given inputs. Where the floating-point subproblems decide
+//! between exact and least-squares solutions by the sign of a rounded value, these report
+//! `Reachability::Undecided` when the interval of that value straddles zero, and return intervals
+//! enclosing the solutions of either case. This makes them suitable for certifying that a pose
+//! near the boundary of the workspace is or is not reachable.
+//!
+//! Rounding is emulated by moving each bound one ulp outward after every operation. This is exact
+//! for the arithmetic operations and the square root, which IEEE 754 rounds correctly, and the
+//! angles from `atan2` are widened by a few ulps more to cover the error of `libm`.
+//!
+//! The inputs are taken as exact. In particular the axes are used as given, so the enclosures do
+//! not account for the rounding of their normalization.
+
+use {
+    crate::solutionset::SolutionSet2,
+    core::{
+        f64::consts::PI,
+        ops::{Add, Div, Mul, Neg, Sub},
+    },
+    nalgebra::Vector3,
+};
+
+#[cfg(all(not(feature = "std"), target_os = "none"))]
+use num_traits::Float;
+
+/// Absolute slack added to both bounds of an angle from `atan2`
+const ANGLE_SLACK: f64 = 8.0 * f64::EPSILON;
+
+/// A closed interval `[lo, hi]` of real numbers
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Interval {
+    pub lo: f64,
+    pub hi: f64,
+}
+
+impl Interval {
+    pub fn new(lo: f64, hi: f64) -> Self {
+        debug_assert!(lo <= hi, "interval bounds out of order");
+        Interval { lo, hi }
+    }
+
+    /// The interval holding only `x`
+    pub fn point(x: f64) -> Self {
+        Interval { lo: x, hi: x }
+    }
+
+    /// Every real number
+    pub fn entire() -> Self {
+        Interval {
+            lo: f64::NEG_INFINITY,
+            hi: f64::INFINITY,
+        }
+    }
+
+    /// The interval with the rounded bounds `lo` and `hi` moved outward by one ulp
+    fn rounded(lo: f64, hi: f64) -> Self {
+        Interval {
+            lo: lo.next_down(),
+            hi: hi.next_up(),
+        }
+    }
+
+    pub fn contains(&self, x: f64) -> bool {
+        self.lo <= x && x <= self.hi
+    }
+
+    pub fn width(&self) -> f64 {
+        self.hi - self.lo
+    }
+
+    pub fn mid(&self) -> f64 {
+        self.lo / 2.0 + self.hi / 2.0
+    }
+
+    /// The smallest interval containing both intervals
+    pub fn hull(&self, other: &Interval) -> Self {
+        Interval {
+            lo: self.lo.min(other.lo),
+            hi: self.hi.max(other.hi),
+        }
+    }
+
+    /// Whether every number in the interval is positive
+    pub fn is_positive(&self) -> bool {
+        self.lo > 0.0
+    }
+
+    /// Whether every number in the interval is negative
+    pub fn is_negative(&self) -> bool {
+        self.hi < 0.0
+    }
+
+    /// The squares of the numbers in the interval, which unlike `self * self` are never negative
+    pub fn sqr(&self) -> Self {
+        let (a, b) = (self.lo * self.lo, self.hi * self.hi);
+        if self.lo <= 0.0 && self.hi >= 0.0 {
+            Interval::rounded(0.0, a.max(b)).max_zero()
+        } else {
+            Interval::rounded(a.min(b), a.max(b)).max_zero()
+        }
+    }
+
+    /// The square roots of the non-negative numbers in the interval
+    pub fn sqrt(&self) -> Self {
+        let lo = self.lo.max(0.0);
+        let hi = self.hi.max(0.0);
+        Interval::rounded(lo.sqrt(), hi.sqrt()).max_zero()
+    }
+
+    /// Clamp the interval to the non-negative numbers
+    fn max_zero(self) -> Self {
+        Interval {
+            lo: self.lo.max(0.0),
+            hi: self.hi.max(0.0),
+        }
+    }
+
+    /// The angles `atan2(y, x)` of the points in the box `y × x`. If the box holds the origin
+    /// the angle can be anything, and `[-π, π]` is returned. Otherwise the enclosure may extend
+    /// past `π` rather than wrapping around.
+    pub fn atan2(y: &Interval, x: &Interval) -> Self {
+        if y.contains(0.0) && x.contains(0.0) {
+            return Interval::new(-PI - ANGLE_SLACK, PI + ANGLE_SLACK);
+        }
+
+        // The box is seen from the origin within less than a half turn, and its extreme angles
+        // are at its corners
+        let reference = y.mid().atan2(x.mid());
+        let mut lo = f64::INFINITY;
+        let mut hi = f64::NEG_INFINITY;
+        for (y_c, x_c) in [(y.lo, x.lo), (y.lo, x.hi), (y.hi, x.lo), (y.hi, x.hi)] {
+            let mut relative = y_c.atan2(x_c) - reference;
+            if relative > PI {
+                relative -= 2.0 * PI;
+            } else if relative < -PI {
+                relative += 2.0 * PI;
+            }
+            lo = lo.min(relative);
+            hi = hi.max(relative);
+        }
+
+        Interval::new(reference + lo - ANGLE_SLACK, reference + hi + ANGLE_SLACK)
+    }
+}
+
+impl From<f64> for Interval {
+    fn from(x: f64) -> Self {
+        Interval::point(x)
+    }
+}
+
+impl Add for Interval {
+    type Output = Interval;
+
+    fn add(self, other: Interval) -> Interval {
+        Interval::rounded(self.lo + other.lo, self.hi + other.hi)
+    }
+}
+
+impl Sub for Interval {
+    type Output = Interval;
+
+    fn sub(self, other: Interval) -> Interval {
+        Interval::rounded(self.lo - other.hi, self.hi - other.lo)
+    }
+}
+
+impl Neg for Interval {
+    type Output = Interval;
+
+    fn neg(self) -> Interval {
+        Interval {
+            lo: -self.hi,
+            hi: -self.lo,
+        }
+    }
+}
+
+impl Mul for Interval {
+    type Output = Interval;
+
+    fn mul(self, other: Interval) -> Interval {
+        let products = [
+            self.lo * other.lo,
+            self.lo * other.hi,
+            self.hi * other.lo,
+            self.hi * other.hi,
+        ];
+        Interval::rounded(
+            products.iter().copied().fold(f64::INFINITY, f64::min),
+            products.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        )
+    }
+}
+
+impl Div for Interval {
+    type Output = Interval;
+
+    /// Division by an interval holding zero can give any number
+    fn div(self, other: Interval) -> Interval {
+        if other.contains(0.0) {
+            return Interval::entire();
+        }
+        let quotients = [
+            self.lo / other.lo,
+            self.lo / other.hi,
+            self.hi / other.lo,
+            self.hi / other.hi,
+        ];
+        Interval::rounded(
+            quotients.iter().copied().fold(f64::INFINITY, f64::min),
+            quotients.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        )
+    }
+}
+
+/// Whether an exact solution exists, as far as interval arithmetic can tell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reachability {
+    /// Exact solutions certainly exist, and each is enclosed by one of the returned intervals
+    Reachable,
+    /// There is certainly no exact solution, and the returned interval encloses the least
+    /// squares solution
+    Unreachable,
+    /// Rounding hides whether exact solutions exist. The returned intervals enclose the exact
+    /// solutions if there are any, and the least squares solution otherwise.
+    Undecided,
+}
+
+type IntervalVector = [Interval; 3];
+
+fn vector(v: &Vector3<f64>) -> IntervalVector {
+    [v.x.into(), v.y.into(), v.z.into()]
+}
+
+fn dot(a: &IntervalVector, b: &IntervalVector) -> Interval {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: &IntervalVector, b: &IntervalVector) -> IntervalVector {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn scale(a: &IntervalVector, s: Interval) -> IntervalVector {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+fn sub(a: &IntervalVector, b: &IntervalVector) -> IntervalVector {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn neg(a: &IntervalVector) -> IntervalVector {
+    [-a[0], -a[1], -a[2]]
+}
+
+fn norm_squared(a: &IntervalVector) -> Interval {
+    a[0].sqr() + a[1].sqr() + a[2].sqr()
+}
+
+/// The enclosure of `theta` where `rot(k, theta) * p1 = p2`, or of the least squares solution.
+/// Whether the solution is exact depends on equalities that interval arithmetic cannot certify.
+pub fn interval_subproblem1(p1: &Vector3<f64>, p2: &Vector3<f64>, k: &Vector3<f64>) -> Interval {
+    let (p1, p2, k) = (vector(p1), vector(p2), vector(k));
+
+    let kxp = cross(&k, &p1);
+    let kxkxp = neg(&cross(&k, &kxp));
+
+    Interval::atan2(&dot(&kxp, &p2), &dot(&kxkxp, &p2))
+}
+
+/// The enclosures of `(theta1, theta2)` where `rot(k1, theta1) * p1 = rot(k2, theta2) * p2`,
+/// paired as in `subproblem2`. `p1` and `p2` are normalized first, so only the directions are
+/// certified.
+pub fn interval_subproblem2(
+    p1: &Vector3<f64>,
+    p2: &Vector3<f64>,
+    k1: &Vector3<f64>,
+    k2: &Vector3<f64>,
+) -> (SolutionSet2<(Interval, Interval)>, Reachability) {
+    let normalize = |p: &Vector3<f64>| {
+        let p = vector(p);
+        scale(&p, Interval::point(1.0) / norm_squared(&p).sqrt())
+    };
+    let (p1_norm, p2_norm) = (normalize(p1), normalize(p2));
+    let (k1, k2) = (vector(k1), vector(k2));
+
+    let (theta1, theta1_reach) = subproblem4_intervals(&k2, &p1_norm, &k1, dot(&k2, &p2_norm));
+    let (theta2, theta2_reach) = subproblem4_intervals(&k1, &p2_norm, &k2, dot(&k1, &p1_norm));
+
+    let reachability = match (theta1_reach, theta2_reach) {
+        (Reachability::Reachable, Reachability::Reachable) => Reachability::Reachable,
+        (Reachability::Unreachable, _) | (_, Reachability::Unreachable) => {
+            Reachability::Unreachable
+        }
+        _ => Reachability::Undecided,
+    };
+
+    let solution = if theta1.size() > 1 || theta2.size() > 1 {
+        let (theta1_first, theta1_second) = theta1.duplicated().expect_two();
+        let (theta2_first, theta2_second) = theta2.duplicated().expect_two();
+
+        SolutionSet2::Two((theta1_first, theta2_second), (theta1_second, theta2_first))
+    } else {
+        SolutionSet2::One((theta1.expect_one(), theta2.expect_one()))
+    };
+
+    (solution, reachability)
+}
+
+/// The enclosures of `theta` where `|| rot(k, theta) * p1 - p2 || = d`, or of the least squares
+/// solution
+pub fn interval_subproblem3(
+    p1: &Vector3<f64>,
+    p2: &Vector3<f64>,
+    k: &Vector3<f64>,
+    d: f64,
+) -> (SolutionSet2<Interval>, Reachability) {
+    let (p1, p2, k, d) = (vector(p1), vector(p2), vector(k), Interval::point(d));
+    let two = Interval::point(2.0);
+
+    let kxp = cross(&k, &p1);
+    let a_1 = [kxp, neg(&cross(&k, &kxp))];
+    let a = [dot(&p2, &a_1[0]) * -two, dot(&p2, &a_1[1]) * -two];
+    let norm_a_sq = a[0].sqr() + a[1].sqr();
+
+    let b = d.sqr() - norm_squared(&sub(&p2, &scale(&k, dot(&k, &p1)))) - norm_squared(&kxp);
+    let factor = -two * b / norm_a_sq;
+    let x_ls = [dot(&a_1[0], &p2) * factor, dot(&a_1[1], &p2) * factor];
+
+    // Exact solutions exist where `1 - b^2 / |a|^2` is not negative
+    let xi_sq = Interval::point(1.0) - b.sqr() / norm_a_sq;
+    let reachability = classify(&xi_sq);
+    if reachability == Reachability::Unreachable {
+        return (
+            SolutionSet2::One(Interval::atan2(&x_ls[0], &x_ls[1])),
+            reachability,
+        );
+    }
+
+    let xi = xi_sq.sqrt();
+    let norm_a = norm_a_sq.sqrt();
+    let a_perp = [a[1] / norm_a, -a[0] / norm_a];
+
+    let sc_1 = [x_ls[0] + a_perp[0] * xi, x_ls[1] + a_perp[1] * xi];
+    let sc_2 = [x_ls[0] - a_perp[0] * xi, x_ls[1] - a_perp[1] * xi];
+
+    (
+        SolutionSet2::Two(
+            Interval::atan2(&sc_1[0], &sc_1[1]),
+            Interval::atan2(&sc_2[0], &sc_2[1]),
+        ),
+        reachability,
+    )
+}
+
+/// The enclosures of `theta` where `h' * rot(k, theta) * p = d`, or of the least squares
+/// solution
+pub fn interval_subproblem4(
+    h: &Vector3<f64>,
+    p: &Vector3<f64>,
+    k: &Vector3<f64>,
+    d: f64,
+) -> (SolutionSet2<Interval>, Reachability) {
+    subproblem4_intervals(&vector(h), &vector(p), &vector(k), Interval::point(d))
+}
+
+fn subproblem4_intervals(
+    h: &IntervalVector,
+    p: &IntervalVector,
+    k: &IntervalVector,
+    d: Interval,
+) -> (SolutionSet2<Interval>, Reachability) {
+    let a_11 = cross(k, p);
+    let a_12 = neg(&cross(k, &a_11));
+    let a = [dot(h, &a_11), dot(h, &a_12)];
+
+    let b = d - dot(h, k) * dot(k, p);
+    let norm_a_2 = a[0].sqr() + a[1].sqr();
+    let x_ls = [a[0] * b, a[1] * b];
+
+    let xi_sq = norm_a_2 - b.sqr();
+    let reachability = classify(&xi_sq);
+    if reachability == Reachability::Unreachable {
+        return (
+            SolutionSet2::One(Interval::atan2(&x_ls[0], &x_ls[1])),
+            reachability,
+        );
+    }
+
+    let xi = xi_sq.sqrt();
+    let a_perp_tilde = [a[1], -a[0]];
+
+    let sc_1 = [
+        x_ls[0] + a_perp_tilde[0] * xi,
+        x_ls[1] + a_perp_tilde[1] * xi,
+    ];
+    let sc_2 = [
+        x_ls[0] - a_perp_tilde[0] * xi,
+        x_ls[1] - a_perp_tilde[1] * xi,
+    ];
+
+    (
+        SolutionSet2::Two(
+            Interval::atan2(&sc_1[0], &sc_1[1]),
+            Interval::atan2(&sc_2[0], &sc_2[1]),
+        ),
+        reachability,
+    )
+}
+
+/// Whether exact solutions exist, given the interval of a quantity they need to be non-negative
+fn classify(xi_sq: &Interval) -> Reachability {
+    if xi_sq.is_positive() {
+        Reachability::Reachable
+    } else if xi_sq.is_negative() {
+        Reachability::Unreachable
+    } else {
+        Reachability::Undecided
+    }
+}
@@ -1,53 +1,119 @@
-use std::f64::INFINITY;
+//! The canonical geometric subproblems that the IK solvers are built from.
+//!
+//! Every subproblem is a plain function of 3D vectors, unit rotation axes `k` and distances `d`,
+//! where `rot(k, theta)` is the rotation by `theta` about `k`.
+//!
+//! Subproblems 1 to 4 also return a least-squares flag. When it is `false` the returned angles
+//! satisfy the equation exactly. When it is `true` no exact solution exists and the single
+//! returned solution minimizes the residual described in each function's documentation instead.
+//! Subproblems 5 and 6 only return the exact solutions, so an empty solution set means that the
+//! equation has no solution.
+//!
+//! The prismatic subproblems are the same equations with translations `d * k` along the axes of
+//! prismatic joints in place of rotations, and also return a least-squares flag.
+//!
+//! With the `interval` feature, `interval` has versions of subproblems 1 to 4 that return
+//! guaranteed enclosures of the solutions, and with the `simd` feature, `batch` has versions in
+//! `f32` that solve eight instances at once.
 
 pub(crate) mod auxiliary;
+#[cfg(feature = "simd")]
+pub mod batch;
+#[cfg(feature = "interval")]
+pub mod interval;
+#[cfg(all(test, feature = "std"))]
 pub(crate) mod setups;
 
+pub use auxiliary::rot;
+
 use {
     super::solutionset::{SolutionSet2, SolutionSet4},
     auxiliary::{
-        cone_polynomials, solve_lower_triangular_system_2x2, solve_quartic_roots,
-        solve_two_ellipse_numeric, vec_convolve_3, vec_self_convolve_2, vec_self_convolve_3,
+        cone_polynomials, solve_lower_triangular_system_2x2, solve_quadratic_roots,
+        solve_quartic_roots, solve_two_ellipse_numeric, vec_convolve_3, vec_self_convolve_2,
+        vec_self_convolve_3,
     },
     nalgebra::{
-        ArrayStorage, Complex, DVector, Matrix, Matrix2, Matrix2x4, Matrix3, Matrix3x2, Matrix3x4,
-        Matrix4, Matrix4x3, Vector2, Vector3, Vector4, Vector5, U1, U7,
+        convert, Complex, Matrix2, Matrix2x4, Matrix3, Matrix3x2, Matrix3x4, Matrix4, Matrix4x3,
+        RealField, Vector2, Vector3, Vector4, Vector5,
     },
 };
 
-pub(crate) type Vector7<T> = Matrix<T, U7, U1, ArrayStorage<T, 7, 1>>;
-
 /// Solves for `theta` where `rot(k, theta) * p1 = p2` if possible.
 /// If not, minimizes `|| rot(k, theta) * p1 - p2 ||`.
 /// Also returns a boolean of whether or not `theta` is a least-squares solution.
-pub fn subproblem1(p1: &Vector3<f64>, p2: &Vector3<f64>, k: &Vector3<f64>) -> (f64, bool) {
+pub fn subproblem1<T: RealField + Copy>(
+    p1: &Vector3<T>,
+    p2: &Vector3<T>,
+    k: &Vector3<T>,
+) -> (T, bool) {
+    subproblem1_with_tolerance(p1, p2, k, convert(1e-6))
+}
+
+/// `subproblem1`, reporting a least-squares solution when the residual may exceed `tolerance`
+pub fn subproblem1_with_tolerance<T: RealField + Copy>(
+    p1: &Vector3<T>,
+    p2: &Vector3<T>,
+    k: &Vector3<T>,
+    tolerance: T,
+) -> (T, bool) {
     let kxp = k.cross(p1);
     let a = Matrix3x2::from_columns(&[kxp, -k.cross(&kxp)]);
     let x = a.transpose() * p2;
 
     let theta = x[0].atan2(x[1]);
-    let is_ls = (p1.norm() - p2.norm()).abs() > 1e-6 || (k.dot(p1) - k.dot(p2)).abs() > 1e-6;
+    let is_ls =
+        (p1.norm() - p2.norm()).abs() > tolerance || (k.dot(p1) - k.dot(p2)).abs() > tolerance;
+    if is_ls {
+        trace_event!(
+            TRACE,
+            subproblem = 1,
+            length_difference = ?(p1.norm() - p2.norm()),
+            height_difference = ?(k.dot(p1) - k.dot(p2)),
+            "no exact solution"
+        );
+    }
 
     (theta, is_ls)
 }
 
-// Solves for `theta1` and `theta2` where `rot(k1, theta1) * p1 = rot(k2, theta2) * p2` if possible.
-// If not, minimizes `|| rot(k1, theta1) * p1 - rot(k2, theta2) * p2 ||`.
-// Also returns a boolean of whether or not `{ theta1, theta2 }` is a least-squares solution.
-// There may be 1 or 2 solutions for `theta1` and `theta2`.
-pub fn subproblem2(
-    p1: &Vector3<f64>,
-    p2: &Vector3<f64>,
-    k1: &Vector3<f64>,
-    k2: &Vector3<f64>,
-) -> (SolutionSet2<(f64, f64)>, bool) {
+/// Solves for `theta1` and `theta2` where `rot(k1, theta1) * p1 = rot(k2, theta2) * p2` if possible.
+/// If not, minimizes `|| rot(k1, theta1) * p1 - rot(k2, theta2) * p2 ||`.
+/// Also returns a boolean of whether or not `{ theta1, theta2 }` is a least-squares solution.
+/// There may be 1 or 2 solutions for `theta1` and `theta2`.
+pub fn subproblem2<T: RealField + Copy>(
+    p1: &Vector3<T>,
+    p2: &Vector3<T>,
+    k1: &Vector3<T>,
+    k2: &Vector3<T>,
+) -> (SolutionSet2<(T, T)>, bool) {
+    subproblem2_with_tolerance(p1, p2, k1, k2, convert(1e-8))
+}
+
+/// `subproblem2`, reporting a least-squares solution when `p1` and `p2` differ in length by more
+/// than `tolerance`
+pub fn subproblem2_with_tolerance<T: RealField + Copy>(
+    p1: &Vector3<T>,
+    p2: &Vector3<T>,
+    k1: &Vector3<T>,
+    k2: &Vector3<T>,
+    tolerance: T,
+) -> (SolutionSet2<(T, T)>, bool) {
     let p1_norm = p1.normalize();
     let p2_norm = p2.normalize();
 
     let (theta1, theta1_is_ls) = subproblem4(k2, &p1_norm, k1, k2.dot(&p2_norm));
     let (theta2, theta2_is_ls) = subproblem4(k1, &p2_norm, k2, k1.dot(&p1_norm));
 
-    let is_ls = (p1.norm() - p2.norm()).abs() > 1e-8 || theta1_is_ls || theta2_is_ls;
+    let is_ls = (p1.norm() - p2.norm()).abs() > tolerance || theta1_is_ls || theta2_is_ls;
+    if is_ls {
+        trace_event!(
+            TRACE,
+            subproblem = 2,
+            length_difference = ?(p1.norm() - p2.norm()),
+            "no exact solution"
+        );
+    }
 
     // Reverse theta2 and duplicate any angle with less solutions
     let solution = if theta1.size() > 1 || theta2.size() > 1 {
@@ -62,15 +128,17 @@ pub fn subproblem2(
     (solution, is_ls)
 }
 
-// Solves for `theta1` and `theta2` where `p0 + rot(k1, theta1) * p1 = rot(k2, theta2) * p2`.
-// Assumes only one solution. If there could be two, `subproblem2` should be used.
-pub fn subproblem2extended(
-    p0: &Vector3<f64>,
-    p1: &Vector3<f64>,
-    p2: &Vector3<f64>,
-    k1: &Vector3<f64>,
-    k2: &Vector3<f64>,
-) -> (f64, f64) {
+/// Solves for `theta1` and `theta2` where `p0 + rot(k1, theta1) * p1 = rot(k2, theta2) * p2`.
+/// Assumes only one solution. If there could be two, `subproblem2` should be used.
+pub fn subproblem2extended<T: RealField + Copy>(
+    p0: &Vector3<T>,
+    p1: &Vector3<T>,
+    p2: &Vector3<T>,
+    k1: &Vector3<T>,
+    k2: &Vector3<T>,
+) -> (T, T) {
+    let two: T = convert(2.0);
+
     let kxp1 = k1.cross(p1);
     let kxp2 = k2.cross(p2);
 
@@ -92,11 +160,11 @@ pub fn subproblem2extended(
 
     let alpha = radius1_sq / (radius1_sq + radius2_sq);
     let beta = radius2_sq / (radius1_sq + radius2_sq);
-    let m_inv = Matrix3::identity() + k1 * k1.transpose() * (alpha / (1.0 - alpha));
-    let aat_inv = 1.0 / (radius1_sq + radius2_sq)
-        * (m_inv
-            + m_inv * k2 * k2.transpose() * m_inv * beta
-                / (1.0 - (k2.transpose() * m_inv * k2 * beta)[0]));
+    let m_inv = Matrix3::identity() + k1 * k1.transpose() * (alpha / (T::one() - alpha));
+    let aat_inv = (m_inv
+        + m_inv * k2 * k2.transpose() * m_inv * beta
+            / (T::one() - (k2.transpose() * m_inv * k2 * beta)[0]))
+        / (radius1_sq + radius2_sq);
     let x_ls = a.transpose() * aat_inv * p;
 
     let n_sym = k1.cross(&k2);
@@ -109,11 +177,11 @@ pub fn subproblem2extended(
         pinv_a2.row(1),
     ]) * n_sym;
 
-    let num = (x_ls.fixed_rows::<2>(2).norm_squared() - 1.0)
+    let num = (x_ls.fixed_rows::<2>(2).norm_squared() - T::one())
         * a_perp_tilde.fixed_rows::<2>(0).norm_squared()
-        - (x_ls.fixed_rows::<2>(0).norm_squared() - 1.0)
+        - (x_ls.fixed_rows::<2>(0).norm_squared() - T::one())
             * a_perp_tilde.fixed_rows::<2>(2).norm_squared();
-    let den = 2.0
+    let den = two
         * (x_ls.fixed_rows::<2>(0).transpose()
             * a_perp_tilde.fixed_rows::<2>(0)
             * a_perp_tilde.fixed_rows::<2>(2).norm_squared()
@@ -123,41 +191,49 @@ pub fn subproblem2extended(
 
     let xi = num / den;
 
-    let sc = x_ls + xi * a_perp_tilde;
+    let sc = x_ls + a_perp_tilde * xi;
 
     (sc[0].atan2(sc[1]), sc[2].atan2(sc[3]))
 }
 
-// Solves for `theta` where `|| rot(k, theta) * p1 - p2 || = d` if possibble.
-// If not, minimizes `| || rot(k, theta)*p1 - p2 || - d |`.
-// Also returns a boolean of whether or not `theta` is a least-squares solution.
-pub fn subproblem3(
-    p1: &Vector3<f64>,
-    p2: &Vector3<f64>,
-    k: &Vector3<f64>,
-    d: f64,
-) -> (SolutionSet2<f64>, bool) {
+/// Solves for `theta` where `|| rot(k, theta) * p1 - p2 || = d` if possible.
+/// If not, minimizes `| || rot(k, theta)*p1 - p2 || - d |`.
+/// Also returns a boolean of whether or not `theta` is a least-squares solution.
+pub fn subproblem3<T: RealField + Copy>(
+    p1: &Vector3<T>,
+    p2: &Vector3<T>,
+    k: &Vector3<T>,
+    d: T,
+) -> (SolutionSet2<T>, bool) {
+    let two: T = convert(2.0);
+
     let kxp = k.cross(p1);
     let a_1 = Matrix3x2::from_columns(&[kxp, -k.cross(&kxp)]);
-    let a = -2.0 * p2.transpose() * a_1;
+    let a = p2.transpose() * a_1 * -two;
     let norm_a_sq = a.norm_squared();
     let norm_a = a.norm();
 
     let b = d * d - (p2 - k * k.transpose() * p1).norm_squared() - kxp.norm_squared();
 
-    let x_ls = a_1.transpose() * (-2.0 * p2 * b / norm_a_sq);
+    let x_ls = a_1.transpose() * (p2 * (-two * b / norm_a_sq));
 
-    if x_ls.norm_squared() > 1.0 {
+    if x_ls.norm_squared() > T::one() {
+        trace_event!(
+            TRACE,
+            subproblem = 3,
+            distance = ?d,
+            "no exact solution"
+        );
         return (SolutionSet2::One(x_ls[0].atan2(x_ls[1])), true);
     }
 
-    let xi = (1.0 - b * b / norm_a_sq).sqrt();
+    let xi = (T::one() - b * b / norm_a_sq).sqrt();
 
     let a_perp_tilde = Vector2::new(a[1], -a[0]);
     let a_perp = a_perp_tilde / norm_a;
 
-    let sc_1 = x_ls + xi * a_perp;
-    let sc_2 = x_ls - xi * a_perp;
+    let sc_1 = x_ls + a_perp * xi;
+    let sc_2 = x_ls - a_perp * xi;
 
     (
         SolutionSet2::Two(sc_1[0].atan2(sc_1[1]), sc_2[0].atan2(sc_2[1])),
@@ -168,12 +244,12 @@ pub fn subproblem3(
 /// Solves for `theta` where `h' * rot(k, theta) * p = d` if possible.
 /// If not minimizes `| h' * rot(k, theta) * p - d |`.
 /// Also returns a boolean of whether or not `theta` is a least-squares solution.
-pub fn subproblem4(
-    h: &Vector3<f64>,
-    p: &Vector3<f64>,
-    k: &Vector3<f64>,
-    d: f64,
-) -> (SolutionSet2<f64>, bool) {
+pub fn subproblem4<T: RealField + Copy>(
+    h: &Vector3<T>,
+    p: &Vector3<T>,
+    k: &Vector3<T>,
+    d: T,
+) -> (SolutionSet2<T>, bool) {
     let a_11 = k.cross(p);
     let a_1 = Matrix3x2::from_columns(&[a_11, -k.cross(&a_11)]);
     let a = h.transpose() * a_1;
@@ -188,48 +264,61 @@ pub fn subproblem4(
         let xi = (norm_a_2 - b * b).sqrt();
         let a_perp_tilde = Vector2::new(a[1], -a[0]);
 
-        let sc_1 = x_ls + xi * a_perp_tilde;
-        let sc_2 = x_ls - xi * a_perp_tilde;
+        let sc_1 = x_ls + a_perp_tilde * xi;
+        let sc_2 = x_ls - a_perp_tilde * xi;
 
         (
             SolutionSet2::Two(sc_1[0].atan2(sc_1[1]), sc_2[0].atan2(sc_2[1])),
             false,
         )
     } else {
+        trace_event!(
+            TRACE,
+            subproblem = 4,
+            excess = ?(b * b - norm_a_2),
+            "no exact solution"
+        );
         (SolutionSet2::One(x_ls[0].atan2(x_ls[1])), true)
     }
 }
 
 /// Solves for `theta1`, `theta2`, and `theta3` where `p0 + rot(k1, theta1) * p1 = rot(k2, theta2) * (p2 + rot(k3, theta3) * p3)` if possible.
 /// There can be up to 4 solutions.
-pub fn subproblem5(
-    p0: &Vector3<f64>,
-    p1: &Vector3<f64>,
-    p2: &Vector3<f64>,
-    p3: &Vector3<f64>,
-    k1: &Vector3<f64>,
-    k2: &Vector3<f64>,
-    k3: &Vector3<f64>,
-) -> SolutionSet4<(f64, f64, f64)> {
+pub fn subproblem5<T: RealField + Copy>(
+    p0: &Vector3<T>,
+    p1: &Vector3<T>,
+    p2: &Vector3<T>,
+    p3: &Vector3<T>,
+    k1: &Vector3<T>,
+    k2: &Vector3<T>,
+    k3: &Vector3<T>,
+) -> SolutionSet4<(T, T, T)> {
     /// Given n >= 4 solutions, return the top 4 most unique
-    fn reduced_solutionset(mut solutions: Vec<(f64, f64, f64)>) -> SolutionSet4<(f64, f64, f64)> {
+    fn reduced_solutionset<T: RealField + Copy>(
+        solutions: &mut [(T, T, T)],
+    ) -> SolutionSet4<(T, T, T)> {
         if solutions.len() <= 4 {
-            return SolutionSet4::from_vec(&solutions);
+            return SolutionSet4::from_slice(solutions);
         }
 
         solutions.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
 
-        let mut solutions_with_ordering = Vec::with_capacity(solutions.len());
-        let mut last = INFINITY;
+        let zero = T::zero();
+        let mut solutions_with_ordering = [(zero, (zero, zero, zero)); MAX_SOLUTIONS];
+        let mut last = None;
 
-        for solution in solutions {
-            let delta = solution.0 - last;
-            let ordering = 1.0 / (delta * delta);
+        for (with_ordering, &solution) in solutions_with_ordering.iter_mut().zip(solutions.iter()) {
+            // The first solution has no predecessor, as if it were infinitely far from it
+            let ordering = last.map_or(zero, |last| {
+                let delta = solution.0 - last;
+                T::one() / (delta * delta)
+            });
 
-            solutions_with_ordering.push((ordering, solution));
-            last = solution.0;
+            *with_ordering = (ordering, solution);
+            last = Some(solution.0);
         }
 
+        let solutions_with_ordering = &mut solutions_with_ordering[..solutions.len()];
         solutions_with_ordering.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
 
         SolutionSet4::Four(
@@ -240,9 +329,12 @@ pub fn subproblem5(
         )
     }
 
-    const EPSILON: f64 = 1e-6;
+    /// Each of the up to 4 real roots gives up to 4 sign combinations
+    const MAX_SOLUTIONS: usize = 16;
+    let epsilon = convert(1e-6);
 
-    let mut theta = Vec::with_capacity(8);
+    let mut theta = [(T::zero(), T::zero(), T::zero()); MAX_SOLUTIONS];
+    let mut n = 0;
 
     let p1_s = p0 + k1 * k1.transpose() * p1;
     let p3_s = p2 + k3 * k3.transpose() * p3;
@@ -256,22 +348,37 @@ pub fn subproblem5(
     let p_13 = p_1 - p_3;
     let p_13_sq = vec_self_convolve_2(&p_13);
 
-    let rhs = r_3 - r_1 - p_13_sq;
+    // With p0 or p2 in the plane of its axis and k2, as between intersecting axes, r_1 or r_3
+    // vanishes and the quartic is the square of p_13^2 - r_1 - r_3, whose double roots would be
+    // made complex by rounding, so the quadratic is solved instead
+    let in_plane = |p: &Vector3<T>, k: &Vector3<T>| {
+        p.dot(&k.cross(k2)).abs() <= convert::<f64, T>(1e-9) * p.norm()
+    };
+    let roots = if in_plane(p0, k1) || in_plane(p2, k3) {
+        solve_quadratic_roots(&(p_13_sq - r_1 - r_3).map(|c| Complex::new(c, T::zero())))
+    } else {
+        let rhs = r_3 - r_1 - p_13_sq;
+
+        let eqn_real =
+            vec_self_convolve_3(&rhs) - vec_convolve_3(&p_13_sq, &r_1) * convert::<f64, T>(4.0);
+        let mut eqn: Vector5<Complex<T>> = Vector5::zeros();
 
-    let eqn_real = vec_self_convolve_3(&rhs) - 4.0 * vec_convolve_3(&p_13_sq, &r_1);
-    let mut eqn: Vector5<Complex<f64>> = Vector5::zeros();
+        for (complex, real) in eqn.iter_mut().zip(eqn_real.into_iter()) {
+            complex.re = *real;
+        }
 
-    for (complex, real) in eqn.iter_mut().zip(eqn_real.into_iter()) {
-        complex.re = *real;
-    }
+        solve_quartic_roots(&eqn)
+    };
 
-    let all_roots = solve_quartic_roots(&eqn).transpose();
-    let h_vec = DVector::from_vec(
-        all_roots
-            .into_iter()
-            .filter(|c| c.im.abs() < EPSILON)
-            .map(|c| c.re)
-            .collect(),
+    let h_vec = roots
+        .get_all()
+        .filter(|c| c.im.abs() < epsilon)
+        .map(|c| c.re);
+    trace_event!(
+        TRACE,
+        subproblem = 5,
+        real_roots = h_vec.clone().count(),
+        "solved the quartic"
     );
 
     let kxp1 = k1.cross(p1);
@@ -279,12 +386,13 @@ pub fn subproblem5(
     let a_1 = Matrix3x2::from_columns(&[kxp1, -k1.cross(&kxp1)]);
     let a_3 = Matrix3x2::from_columns(&[kxp3, -k3.cross(&kxp3)]);
 
-    let signs_1 = [1.0, 1.0, -1.0, -1.0];
-    let signs_3 = [1.0, -1.0, 1.0, -1.0];
+    let (one, zero) = (T::one(), T::zero());
+    let signs_1 = [one, one, -one, -one];
+    let signs_3 = [one, -one, one, -one];
 
-    let j = Matrix2::new(0.0, 1.0, -1.0, 0.0);
+    let j = Matrix2::new(zero, one, -one, zero);
 
-    for &h in h_vec.into_iter() {
+    for h in h_vec {
         let const_1 = a_1.transpose() * k2 * (h - delta1);
         let const_3 = a_3.transpose() * k2 * (h - delta3);
 
@@ -292,12 +400,12 @@ pub fn subproblem5(
         let hd3 = h - delta3;
 
         let sq1 = (a_1.transpose() * k2).norm_squared() - hd1 * hd1;
-        if sq1 < 0.0 {
+        if sq1 < zero {
             continue;
         }
 
         let sq3 = (a_3.transpose() * k2).norm_squared() - hd3 * hd3;
-        if sq3 < 0.0 {
+        if sq3 < zero {
             continue;
         }
 
@@ -305,35 +413,36 @@ pub fn subproblem5(
         let pm_3 = j * a_3.transpose() * k2 * sq3.sqrt();
 
         for (&sign_1, &sign_3) in signs_1.iter().zip(signs_3.iter()) {
-            let sc1 = const_1 + sign_1 * pm_1;
+            let sc1 = const_1 + pm_1 * sign_1;
             let sc1 = sc1 / (a_1.transpose() * k2).norm_squared();
 
-            let sc3 = const_3 + sign_3 * pm_3;
+            let sc3 = const_3 + pm_3 * sign_3;
             let sc3 = sc3 / (a_3.transpose() * k2).norm_squared();
 
             let v1 = a_1 * sc1 + p1_s;
             let v3 = a_3 * sc3 + p3_s;
 
-            if ((v1 - h * k2).norm() - (v3 - h * k2).norm()).abs() < 1e-6 {
+            if ((v1 - k2 * h).norm() - (v3 - k2 * h).norm()).abs() < epsilon {
                 let (theta2_value, _) = subproblem1(&v3, &v1, &k2);
 
-                theta.push((sc1[0].atan2(sc1[1]), theta2_value, sc3[0].atan2(sc3[1])));
+                theta[n] = (sc1[0].atan2(sc1[1]), theta2_value, sc3[0].atan2(sc3[1]));
+                n += 1;
             }
         }
     }
 
-    reduced_solutionset(theta)
+    reduced_solutionset(&mut theta[..n])
 }
 
 /// Solves for `theta1` and `theta2` where `h1' * rot(k1, theta1) + h2' * rot(k2, theta2) = d1` and `h3' * rot(k3, theta1) + h4' * rot(k4, theta2) = d2`
 /// There can be up to 4 solutions
-pub fn subproblem6(
-    h: &[Vector3<f64>; 4],
-    k: &[Vector3<f64>; 4],
-    p: &[Vector3<f64>; 4],
-    d1: f64,
-    d2: f64,
-) -> SolutionSet4<(f64, f64)> {
+pub fn subproblem6<T: RealField + Copy>(
+    h: &[Vector3<T>; 4],
+    k: &[Vector3<T>; 4],
+    p: &[Vector3<T>; 4],
+    d1: T,
+    d2: T,
+) -> SolutionSet4<(T, T)> {
     let k1xp1 = k[0].cross(&p[0]);
     let k2xp2 = k[1].cross(&p[1]);
     let k3xp3 = k[2].cross(&p[2]);
@@ -367,10 +476,15 @@ pub fn subproblem6(
     qr.q_tr_mul(&mut q); // small hack to get entire q matrix
     q = q.transpose();
 
-    let (x_null_1, x_null_2): (Vector4<f64>, Vector4<f64>) =
-        (q.column(2).into(), q.column(3).into());
+    let (x_null_1, x_null_2): (Vector4<T>, Vector4<T>) = (q.column(2).into(), q.column(3).into());
     let q = q.fixed_columns::<2>(0);
     let r = qr.r().transpose();
+    trace_event!(
+        TRACE,
+        subproblem = 6,
+        condition = ?(r[(0, 0)] / r[(1, 1)]).abs(),
+        "factored the linear system"
+    );
     let x_min = q * solve_lower_triangular_system_2x2(&r, &b);
 
     let xi_i = solve_two_ellipse_numeric(
@@ -378,16 +492,107 @@ pub fn subproblem6(
         &Matrix2::new(x_null_1[0], x_null_2[0], x_null_1[1], x_null_2[1]),
         &x_min.fixed_rows::<2>(2).into(),
         &Matrix2::new(x_null_1[2], x_null_2[2], x_null_1[3], x_null_2[3]),
-    )
-    .get_all();
+    );
 
-    let mut theta = vec![(0.0, 0.0); xi_i.len()];
+    xi_i.get_all()
+        .map(|xi| {
+            let x = x_min + x_null_1 * xi.0 + x_null_2 * xi.1;
 
-    for (i, xi) in xi_i.iter().enumerate() {
-        let x = x_min + x_null_1 * xi.0 + x_null_2 * xi.1;
+            (x[0].atan2(x[1]), x[2].atan2(x[3]))
+        })
+        .collect()
+}
 
-        theta[i] = (x[0].atan2(x[1]), x[2].atan2(x[3]));
+/// Solves for `d` where `p1 + k * d = p2` if possible.
+/// If not, minimizes `|| p1 + k * d - p2 ||`.
+/// Also returns a boolean of whether or not `d` is a least-squares solution.
+pub fn prismatic_subproblem1<T: RealField + Copy>(
+    p1: &Vector3<T>,
+    p2: &Vector3<T>,
+    k: &Vector3<T>,
+) -> (T, bool) {
+    prismatic_subproblem1_with_tolerance(p1, p2, k, convert(1e-6))
+}
+
+/// `prismatic_subproblem1`, reporting a least-squares solution when the residual exceeds
+/// `tolerance`
+pub fn prismatic_subproblem1_with_tolerance<T: RealField + Copy>(
+    p1: &Vector3<T>,
+    p2: &Vector3<T>,
+    k: &Vector3<T>,
+    tolerance: T,
+) -> (T, bool) {
+    let p = p2 - p1;
+    let d = k.dot(&p);
+    let is_ls = (p - k * d).norm() > tolerance;
+
+    (d, is_ls)
+}
+
+/// Solves for `d1` and `d2` where `p1 + k1 * d1 = p2 + k2 * d2` if possible, i.e. the lines
+/// through `p1` along `k1` and through `p2` along `k2` intersect.
+/// If not, minimizes `|| p1 + k1 * d1 - p2 - k2 * d2 ||`, the distance between the lines.
+/// Also returns a boolean of whether or not `{ d1, d2 }` is a least-squares solution.
+/// Parallel lines have a continuum of solutions, of which the one with `d2 = 0` is returned.
+pub fn prismatic_subproblem2<T: RealField + Copy>(
+    p1: &Vector3<T>,
+    p2: &Vector3<T>,
+    k1: &Vector3<T>,
+    k2: &Vector3<T>,
+) -> ((T, T), bool) {
+    let tolerance = convert(1e-6);
+    let w = p1 - p2;
+    let b = k1.dot(k2);
+    let (d, e) = (k1.dot(&w), k2.dot(&w));
+    let den = T::one() - b * b;
+
+    let (d1, d2) = if den > convert(1e-12) {
+        ((b * e - d) / den, (e - b * d) / den)
+    } else {
+        (-d, T::zero())
+    };
+    let is_ls = (w + k1 * d1 - k2 * d2).norm() > tolerance;
+
+    ((d1, d2), is_ls)
+}
+
+/// Solves for `d` where `|| p1 + k * d - p2 || = dist` if possible.
+/// If not, minimizes `| || p1 + k * d - p2 || - dist |`.
+/// Also returns a boolean of whether or not `d` is a least-squares solution.
+pub fn prismatic_subproblem3<T: RealField + Copy>(
+    p1: &Vector3<T>,
+    p2: &Vector3<T>,
+    k: &Vector3<T>,
+    dist: T,
+) -> (SolutionSet2<T>, bool) {
+    let w = p1 - p2;
+    let kw = k.dot(&w);
+    let discriminant = kw * kw - w.norm_squared() + dist * dist;
+
+    if discriminant < T::zero() {
+        return (SolutionSet2::One(-kw), true);
     }
 
-    SolutionSet4::from_vec(&theta)
+    let root = discriminant.sqrt();
+    (SolutionSet2::Two(-kw + root, -kw - root), false)
+}
+
+/// Solves for `d` where `h' * (p + k * d) = dist` if possible.
+/// If not, which only happens when `h` and `k` are perpendicular, returns `d = 0`.
+/// Also returns a boolean of whether or not `d` is a least-squares solution.
+pub fn prismatic_subproblem4<T: RealField + Copy>(
+    h: &Vector3<T>,
+    p: &Vector3<T>,
+    k: &Vector3<T>,
+    dist: T,
+) -> (T, bool) {
+    let tolerance = convert(1e-6);
+    let hk = h.dot(k);
+    let b = dist - h.dot(p);
+
+    if hk.abs() > convert(1e-12) {
+        (b / hk, false)
+    } else {
+        (T::zero(), b.abs() > tolerance)
+    }
 }
@@ -1,27 +1,30 @@
 use {
     super::auxiliary::{random_angle, random_norm_vector3, random_vector3, rot},
     crate::{
-        solutionset::{SolutionSet2, SolutionSet4},
+        error::{parse_values, IkGeoError},
+        inverse_kinematics::setups::SetupStatic,
+        solutionset::{SolutionSet2, SolutionSet4, DELTA},
         subproblems::{
             subproblem1, subproblem2, subproblem2extended, subproblem3, subproblem4, subproblem5,
             subproblem6,
         },
     },
     nalgebra::{Vector2, Vector3},
+    rand::{Rng, RngCore},
 };
 
-pub const DELTA: f64 = 1e-12;
-
 /// An interface for setting up subproblem testing. Resposible for generating parameters, running
 /// the function, and calculating data such as the error.
 pub trait SetupDynamic {
-    /// Initialize parameters to test the case where theta is solved for exactly
-    fn setup(&mut self);
+    /// Initialize parameters to test the case where theta is solved for exactly, drawing them from
+    /// `rng`
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore);
 
-    /// Initialize parameters to test the case where theta is minimized using least squares
-    fn setup_ls(&mut self);
+    /// Initialize parameters to test the case where theta is minimized using least squares,
+    /// drawing them from `rng`
+    fn setup_ls_with_rng(&mut self, rng: &mut dyn RngCore);
 
-    fn setup_from_str(&mut self, raw: &str);
+    fn setup_from_str(&mut self, raw: &str) -> Result<(), IkGeoError>;
     fn write_output(&self) -> String;
 
     fn run(&mut self);
@@ -32,11 +35,6 @@ pub trait SetupDynamic {
     fn name(&self) -> &'static str;
 }
 
-pub trait SetupStatic {
-    fn new() -> Self;
-    fn name() -> &'static str;
-}
-
 pub struct Subproblem1Setup {
     p1: Vector3<f64>,
     p2: Vector3<f64>,
@@ -265,27 +263,29 @@ impl SetupStatic for Subproblem6Setup {
 }
 
 impl SetupDynamic for Subproblem1Setup {
-    fn setup(&mut self) {
-        self.p1 = random_vector3();
-        self.k = random_norm_vector3();
-        self.theta = random_angle();
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
+        self.p1 = random_vector3(rng);
+        self.k = random_norm_vector3(rng);
+        self.theta = random_angle(rng);
 
         self.p2 = rot(&self.k, self.theta) * self.p1;
     }
 
-    fn setup_ls(&mut self) {
-        self.p1 = random_vector3();
-        self.p2 = random_vector3();
-        self.k = random_norm_vector3();
-        self.theta = random_angle();
+    fn setup_ls_with_rng(&mut self, rng: &mut dyn RngCore) {
+        self.p1 = random_vector3(rng);
+        self.p2 = random_vector3(rng);
+        self.k = random_norm_vector3(rng);
+        self.theta = random_angle(rng);
     }
 
-    fn setup_from_str(&mut self, raw: &str) {
-        let data: Vec<f64> = raw.split(',').map(|s| s.parse().unwrap()).collect();
+    fn setup_from_str(&mut self, raw: &str) -> Result<(), IkGeoError> {
+        let data = parse_values(raw, 9)?;
 
         self.p1 = Vector3::new(data[0], data[1], data[2]);
         self.p2 = Vector3::new(data[6], data[7], data[8]);
         self.k = Vector3::new(data[3], data[4], data[5]);
+
+        Ok(())
     }
 
     fn write_output(&self) -> String {
@@ -320,34 +320,36 @@ impl SetupDynamic for Subproblem1Setup {
 }
 
 impl SetupDynamic for Subproblem2Setup {
-    fn setup(&mut self) {
-        let theta1 = random_angle();
-        let theta2 = random_angle();
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
+        let theta1 = random_angle(rng);
+        let theta2 = random_angle(rng);
 
-        self.p1 = random_vector3();
-        self.k1 = random_norm_vector3();
-        self.k2 = random_norm_vector3();
+        self.p1 = random_vector3(rng);
+        self.k1 = random_norm_vector3(rng);
+        self.k2 = random_norm_vector3(rng);
         self.theta = SolutionSet2::One((theta1, theta2));
 
         self.p2 = rot(&self.k2, -theta2) * rot(&self.k1, theta1) * self.p1;
     }
 
-    fn setup_ls(&mut self) {
-        self.p1 = random_vector3();
-        self.p2 = random_vector3();
-        self.k1 = random_norm_vector3();
-        self.k2 = random_norm_vector3();
-        self.k2 = random_norm_vector3();
-        self.theta = SolutionSet2::One((random_angle(), random_angle()));
+    fn setup_ls_with_rng(&mut self, rng: &mut dyn RngCore) {
+        self.p1 = random_vector3(rng);
+        self.p2 = random_vector3(rng);
+        self.k1 = random_norm_vector3(rng);
+        self.k2 = random_norm_vector3(rng);
+        self.k2 = random_norm_vector3(rng);
+        self.theta = SolutionSet2::One((random_angle(rng), random_angle(rng)));
     }
 
-    fn setup_from_str(&mut self, raw: &str) {
-        let data: Vec<f64> = raw.split(',').map(|s| s.parse().unwrap()).collect();
+    fn setup_from_str(&mut self, raw: &str) -> Result<(), IkGeoError> {
+        let data = parse_values(raw, 12)?;
 
         self.p1 = Vector3::new(data[0], data[1], data[2]);
         self.k1 = Vector3::new(data[3], data[4], data[5]);
         self.k2 = Vector3::new(data[6], data[7], data[8]);
         self.p2 = Vector3::new(data[9], data[10], data[11]);
+
+        Ok(())
     }
 
     fn write_output(&self) -> String {
@@ -365,7 +367,7 @@ impl SetupDynamic for Subproblem2Setup {
     }
 
     fn error(&self) -> f64 {
-        let theta = self.theta.get_all();
+        let theta = self.theta.get_all().collect::<Vec<_>>();
         let len = theta.len();
         theta
             .into_iter()
@@ -395,31 +397,33 @@ impl SetupDynamic for Subproblem2Setup {
 }
 
 impl SetupDynamic for Subproblem2ExtendedSetup {
-    fn setup(&mut self) {
-        self.p0 = random_vector3();
-        self.p1 = random_vector3();
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
+        self.p0 = random_vector3(rng);
+        self.p1 = random_vector3(rng);
 
-        self.k1 = random_norm_vector3();
-        self.k2 = random_norm_vector3();
+        self.k1 = random_norm_vector3(rng);
+        self.k2 = random_norm_vector3(rng);
 
-        self.theta1 = random_angle();
-        self.theta2 = random_angle();
+        self.theta1 = random_angle(rng);
+        self.theta2 = random_angle(rng);
 
         self.p2 = rot(&self.k2, -self.theta2) * (self.p0 + rot(&self.k1, self.theta1) * self.p1);
     }
 
-    fn setup_ls(&mut self) {
+    fn setup_ls_with_rng(&mut self, _rng: &mut dyn RngCore) {
         unimplemented!();
     }
 
-    fn setup_from_str(&mut self, raw: &str) {
-        let data: Vec<f64> = raw.split(',').map(|s| s.parse().unwrap()).collect();
+    fn setup_from_str(&mut self, raw: &str) -> Result<(), IkGeoError> {
+        let data = parse_values(raw, 15)?;
 
         self.p0 = Vector3::new(data[0], data[1], data[2]);
         self.p1 = Vector3::new(data[3], data[4], data[5]);
         self.k1 = Vector3::new(data[6], data[7], data[8]);
         self.k2 = Vector3::new(data[9], data[10], data[11]);
         self.p2 = Vector3::new(data[12], data[13], data[14]);
+
+        Ok(())
     }
 
     fn write_output(&self) -> String {
@@ -452,32 +456,34 @@ impl SetupDynamic for Subproblem2ExtendedSetup {
 }
 
 impl SetupDynamic for Subproblem3Setup {
-    fn setup(&mut self) {
-        let theta = random_angle();
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
+        let theta = random_angle(rng);
 
-        self.p1 = random_vector3();
-        self.p2 = random_vector3();
-        self.k = random_norm_vector3();
+        self.p1 = random_vector3(rng);
+        self.p2 = random_vector3(rng);
+        self.k = random_norm_vector3(rng);
         self.theta = SolutionSet2::One(theta);
 
         self.d = (self.p2 - rot(&self.k, theta) * self.p1).norm();
     }
 
-    fn setup_ls(&mut self) {
-        self.p1 = random_vector3();
-        self.p2 = random_vector3();
-        self.k = random_norm_vector3();
-        self.d = fastrand::f64();
-        self.theta = SolutionSet2::One(random_angle());
+    fn setup_ls_with_rng(&mut self, rng: &mut dyn RngCore) {
+        self.p1 = random_vector3(rng);
+        self.p2 = random_vector3(rng);
+        self.k = random_norm_vector3(rng);
+        self.d = rng.random::<f64>();
+        self.theta = SolutionSet2::One(random_angle(rng));
     }
 
-    fn setup_from_str(&mut self, raw: &str) {
-        let data: Vec<f64> = raw.split(',').map(|s| s.parse().unwrap()).collect();
+    fn setup_from_str(&mut self, raw: &str) -> Result<(), IkGeoError> {
+        let data = parse_values(raw, 10)?;
 
         self.p1 = Vector3::new(data[0], data[1], data[2]);
         self.p2 = Vector3::new(data[3], data[4], data[5]);
         self.k = Vector3::new(data[6], data[7], data[8]);
         self.d = data[9];
+
+        Ok(())
     }
 
     fn write_output(&self) -> String {
@@ -489,7 +495,7 @@ impl SetupDynamic for Subproblem3Setup {
     }
 
     fn error(&self) -> f64 {
-        self.calculate_error(&self.theta.get_all())
+        self.calculate_error(&self.theta.get_all().collect::<Vec<_>>())
     }
 
     fn run_report_info(&mut self) -> bool {
@@ -502,7 +508,7 @@ impl SetupDynamic for Subproblem3Setup {
         let error = self.error();
         let error_check = error - DELTA;
 
-        let mut solution = self.theta.get_all();
+        let mut solution = self.theta.get_all().collect::<Vec<_>>();
 
         for i in 0..solution.len() {
             for sign in [-1.0, 1.0] {
@@ -526,32 +532,34 @@ impl SetupDynamic for Subproblem3Setup {
 }
 
 impl SetupDynamic for Subproblem4Setup {
-    fn setup(&mut self) {
-        let theta = random_angle();
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
+        let theta = random_angle(rng);
 
-        self.h = random_norm_vector3();
-        self.p = random_vector3();
-        self.k = random_norm_vector3();
+        self.h = random_norm_vector3(rng);
+        self.p = random_vector3(rng);
+        self.k = random_norm_vector3(rng);
         self.theta = SolutionSet2::One(theta);
 
         self.d = (self.h.transpose() * rot(&self.k, theta) * self.p)[0];
     }
 
-    fn setup_ls(&mut self) {
-        self.h = random_norm_vector3();
-        self.p = random_vector3();
-        self.k = random_norm_vector3();
-        self.d = fastrand::f64();
-        self.theta = SolutionSet2::One(random_angle());
+    fn setup_ls_with_rng(&mut self, rng: &mut dyn RngCore) {
+        self.h = random_norm_vector3(rng);
+        self.p = random_vector3(rng);
+        self.k = random_norm_vector3(rng);
+        self.d = rng.random::<f64>();
+        self.theta = SolutionSet2::One(random_angle(rng));
     }
 
-    fn setup_from_str(&mut self, raw: &str) {
-        let data: Vec<f64> = raw.split(',').map(|s| s.parse().unwrap()).collect();
+    fn setup_from_str(&mut self, raw: &str) -> Result<(), IkGeoError> {
+        let data = parse_values(raw, 10)?;
 
         self.p = Vector3::new(data[0], data[1], data[2]);
         self.k = Vector3::new(data[3], data[4], data[5]);
         self.h = Vector3::new(data[6], data[7], data[8]);
         self.d = data[9];
+
+        Ok(())
     }
 
     fn write_output(&self) -> String {
@@ -569,14 +577,14 @@ impl SetupDynamic for Subproblem4Setup {
     }
 
     fn error(&self) -> f64 {
-        self.calculate_error(&self.theta.get_all())
+        self.calculate_error(&self.theta.get_all().collect::<Vec<_>>())
     }
 
     fn is_at_local_min(&self) -> bool {
         let error = self.error();
         let error_check = error - DELTA;
 
-        let mut solution = self.theta.get_all();
+        let mut solution = self.theta.get_all().collect::<Vec<_>>();
 
         for i in 0..solution.len() {
             for sign in [-1.0, 1.0] {
@@ -600,18 +608,18 @@ impl SetupDynamic for Subproblem4Setup {
 }
 
 impl SetupDynamic for Subproblem5Setup {
-    fn setup(&mut self) {
-        let theta1 = random_angle();
-        let theta2 = random_angle();
-        let theta3 = random_angle();
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
+        let theta1 = random_angle(rng);
+        let theta2 = random_angle(rng);
+        let theta3 = random_angle(rng);
 
-        self.p1 = random_vector3();
-        self.p2 = random_vector3();
-        self.p3 = random_vector3();
+        self.p1 = random_vector3(rng);
+        self.p2 = random_vector3(rng);
+        self.p3 = random_vector3(rng);
 
-        self.k1 = random_norm_vector3();
-        self.k2 = random_norm_vector3();
-        self.k3 = random_norm_vector3();
+        self.k1 = random_norm_vector3(rng);
+        self.k2 = random_norm_vector3(rng);
+        self.k3 = random_norm_vector3(rng);
 
         self.theta = SolutionSet4::One((theta1, theta2, theta3));
 
@@ -619,12 +627,12 @@ impl SetupDynamic for Subproblem5Setup {
             - rot(&self.k2, theta2) * (self.p2 + rot(&self.k3, theta3) * self.p3));
     }
 
-    fn setup_ls(&mut self) {
+    fn setup_ls_with_rng(&mut self, _rng: &mut dyn RngCore) {
         unimplemented!()
     }
 
-    fn setup_from_str(&mut self, raw: &str) {
-        let data: Vec<f64> = raw.split(',').map(|s| s.parse().unwrap()).collect();
+    fn setup_from_str(&mut self, raw: &str) -> Result<(), IkGeoError> {
+        let data = parse_values(raw, 21)?;
 
         self.p1 = Vector3::new(data[00], data[01], data[02]);
         self.p2 = Vector3::new(data[03], data[04], data[05]);
@@ -633,6 +641,8 @@ impl SetupDynamic for Subproblem5Setup {
         self.k2 = Vector3::new(data[12], data[13], data[14]);
         self.k3 = Vector3::new(data[15], data[16], data[17]);
         self.p0 = Vector3::new(data[18], data[19], data[20]);
+
+        Ok(())
     }
 
     fn write_output(&self) -> String {
@@ -653,7 +663,7 @@ impl SetupDynamic for Subproblem5Setup {
     }
 
     fn error(&self) -> f64 {
-        let theta = self.theta.get_all();
+        let theta = self.theta.get_all().collect::<Vec<_>>();
         let len = theta.len();
 
         if len == 0 {
@@ -681,9 +691,9 @@ impl SetupDynamic for Subproblem5Setup {
 }
 
 impl SetupDynamic for Subproblem6Setup {
-    fn setup(&mut self) {
-        let theta1 = random_angle();
-        let theta2 = random_angle();
+    fn setup_with_rng(&mut self, rng: &mut dyn RngCore) {
+        let theta1 = random_angle(rng);
+        let theta2 = random_angle(rng);
 
         self.h = [Vector3::zeros(); 4];
         self.k = [Vector3::zeros(); 4];
@@ -694,9 +704,9 @@ impl SetupDynamic for Subproblem6Setup {
             .iter_mut()
             .zip(self.k.iter_mut().zip(self.p.iter_mut()))
         {
-            *h = random_norm_vector3();
-            *k = random_norm_vector3();
-            *p = random_vector3();
+            *h = random_norm_vector3(rng);
+            *k = random_norm_vector3(rng);
+            *p = random_vector3(rng);
         }
 
         self.d1 = (self.h[0].transpose() * rot(&self.k[0], theta1) * self.p[0]
@@ -708,12 +718,12 @@ impl SetupDynamic for Subproblem6Setup {
         self.theta = SolutionSet4::One((theta1, theta2));
     }
 
-    fn setup_ls(&mut self) {
+    fn setup_ls_with_rng(&mut self, _rng: &mut dyn RngCore) {
         unimplemented!()
     }
 
-    fn setup_from_str(&mut self, raw: &str) {
-        let data: Vec<f64> = raw.split(',').map(|s| s.parse().unwrap()).collect();
+    fn setup_from_str(&mut self, raw: &str) -> Result<(), IkGeoError> {
+        let data = parse_values(raw, 38)?;
         let mut i = 0;
 
         for v in self.h.iter_mut() {
@@ -733,6 +743,8 @@ impl SetupDynamic for Subproblem6Setup {
 
         self.d1 = data[i];
         self.d2 = data[i + 1];
+
+        Ok(())
     }
 
     fn write_output(&self) -> String {
@@ -749,7 +761,7 @@ impl SetupDynamic for Subproblem6Setup {
     }
 
     fn error(&self) -> f64 {
-        let theta = self.theta.get_all();
+        let theta = self.theta.get_all().collect::<Vec<_>>();
         let len = theta.len();
 
         theta
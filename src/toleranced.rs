@@ -0,0 +1,169 @@
+//! Solving for a region of acceptable poses rather than an exact pose.
+//!
+//! Process tolerances often allow the tool to be a little off the nominal pose, e.g. a drill may
+//! tilt by a degree, and a pose just out of reach is then still reachable within the tolerance.
+//! A `Tolerance` bounds the deviation from the target in the frame of the target tool: a box of
+//! half-widths `pos` for the position and, for the orientation, the largest rotation `rot` about
+//! each axis, given as the components of the rotation vector between the target and the reached
+//! orientation.
+//!
+//! `Robot::solve_toleranced` keeps the exact solutions of the target, and any least squares
+//! solution whose pose falls within the region. Only when the target has no exact solution, the
+//! region is sampled at its center, faces, edges and corners, and for each branch of the solver
+//! the solution closest to the target is kept.
+
+use {
+    crate::{
+        robot::{IKSolver, IkSolution, Robot},
+        se3::{rotation_exp, rotation_log},
+    },
+    alloc::{collections::BTreeMap, vec::Vec},
+    nalgebra::{Matrix3, Vector3, Vector6},
+};
+
+/// The largest deviations from a target pose that are acceptable, in the frame of the target
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Tolerance {
+    /// The half-widths of the box of acceptable positions along each axis
+    pub pos: Vector3<f64>,
+    /// The largest rotation about each axis, in radians
+    pub rot: Vector3<f64>,
+}
+
+impl Tolerance {
+    pub fn new(pos: Vector3<f64>, rot: Vector3<f64>) -> Self {
+        Tolerance { pos, rot }
+    }
+
+    /// The largest ratio of a component of `deviation` to its tolerance, at most 1 within the
+    /// region. Components with a zero tolerance count only if they deviate.
+    pub fn normalized(&self, deviation: &Vector6<f64>) -> f64 {
+        let limits = self.rot.iter().chain(self.pos.iter());
+        deviation
+            .iter()
+            .zip(limits)
+            .map(|(d, &limit)| {
+                if d.abs() <= limit {
+                    if limit > 0.0 {
+                        d.abs() / limit
+                    } else {
+                        0.0
+                    }
+                } else {
+                    f64::INFINITY
+                }
+            })
+            .fold(0.0, f64::max)
+    }
+
+    /// The tolerances of the rotation vector and the position, in the order of a deviation
+    fn half_widths(&self) -> Vector6<f64> {
+        Vector6::new(
+            self.rot.x, self.rot.y, self.rot.z, self.pos.x, self.pos.y, self.pos.z,
+        )
+    }
+
+    /// The pose `offset` away from `(rot, translation)`, in the frame of the target, with the
+    /// rotation vector in the first three entries and the position in the last three
+    fn offset(
+        &self,
+        rot: &Matrix3<f64>,
+        translation: &Vector3<f64>,
+        offset: &Vector6<f64>,
+    ) -> (Matrix3<f64>, Vector3<f64>) {
+        (
+            rot * rotation_exp(&offset.fixed_rows::<3>(0).into()),
+            translation + rot * offset.fixed_rows::<3>(3),
+        )
+    }
+}
+
+/// A solution reaching a pose within the tolerance of the target
+#[derive(Debug, Clone, PartialEq)]
+pub struct TolerancedSolution {
+    pub solution: IkSolution,
+    /// The deviation of the pose reached from the target, in the frame of the target, with the
+    /// rotation vector in the first three entries and the position in the last three
+    pub deviation: Vector6<f64>,
+}
+
+impl Robot {
+    /// The deviation of the pose reached at `q` from the target, see `TolerancedSolution`
+    fn deviation(
+        &self,
+        q: &Vector6<f64>,
+        rot: &Matrix3<f64>,
+        translation: &Vector3<f64>,
+    ) -> Vector6<f64> {
+        let (rot_q, translation_q) = self.fk(&(*q).into());
+        let rotation = rotation_log(&(rot.transpose() * rot_q));
+        let position = rot.transpose() * (translation_q - translation);
+        Vector6::new(
+            rotation.x, rotation.y, rotation.z, position.x, position.y, position.z,
+        )
+    }
+
+    /// Solve for any pose within `tolerance` of `(rot, translation)`, see the `toleranced`
+    /// module. The solutions are ordered by their deviation relative to the tolerance, so the
+    /// exact solutions of the target come first. The residual of each solution is measured
+    /// against the target.
+    pub fn solve_toleranced(
+        &self,
+        rot: Matrix3<f64>,
+        translation: Vector3<f64>,
+        tolerance: Tolerance,
+    ) -> Vec<TolerancedSolution> {
+        let within = |solution: IkSolution| {
+            let deviation = self.deviation(&solution.q, &rot, &translation);
+            tolerance
+                .normalized(&deviation)
+                .is_finite()
+                .then_some(TolerancedSolution {
+                    solution,
+                    deviation,
+                })
+        };
+        // Exact solutions of their sample first, then the least deviation
+        let key = |s: &TolerancedSolution| (s.solution.is_ls, tolerance.normalized(&s.deviation));
+        let precedes = |a: &TolerancedSolution, b: &TolerancedSolution| {
+            let (a, b) = (key(a), key(b));
+            a.0.cmp(&b.0).then(a.1.total_cmp(&b.1))
+        };
+
+        let target = self.ik(rot, translation);
+        let mut solutions: Vec<TolerancedSolution> = if target.iter().any(|s| !s.is_ls) {
+            target.into_iter().filter_map(within).collect()
+        } else {
+            // The solution of each branch closest to the target over the samples of the region
+            let mut branches: BTreeMap<usize, TolerancedSolution> = BTreeMap::new();
+            let levels = [0.0, -1.0, 1.0];
+            for k in 0..levels.len().pow(6) {
+                let unit = Vector6::from_fn(|i, _| levels[(k / 3usize.pow(i as u32)) % 3]);
+                let offset = unit.component_mul(&tolerance.half_widths());
+                let (rot_k, translation_k) = tolerance.offset(&rot, &translation, &offset);
+
+                for mut solution in self.ik(rot_k, translation_k) {
+                    let (rot_q, translation_q) = self.fk(&solution.q.into());
+                    solution.error =
+                        self.config()
+                            .pose_error(&rot_q, &translation_q, &rot, &translation);
+                    let Some(candidate) = within(solution) else {
+                        continue;
+                    };
+                    let branch = candidate.solution.branch;
+                    let better = branches
+                        .get(&branch)
+                        .is_none_or(|best| precedes(&candidate, best).is_lt());
+                    if better {
+                        branches.insert(branch, candidate);
+                    }
+                }
+            }
+            branches.into_values().collect()
+        };
+
+        solutions.sort_by(precedes);
+        solutions
+    }
+}
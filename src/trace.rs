@@ -0,0 +1,35 @@
+//! Instrumentation with `tracing`, compiled in with the `trace` feature and to nothing without it.
+//!
+//! Each solver enters a `debug` span named after its decomposition and reports how many of its
+//! solutions are least squares, `Robot` reports falling back to the general solver, and the
+//! subproblems emit `trace` events when they fall back to a least squares solution, along with
+//! the conditioning of the linear systems they solve.
+
+/// Enter a span at the `tracing::Level` `$level` until the end of the enclosing block
+macro_rules! trace_span {
+    ($level:ident, $($args:tt)+) => {
+        #[cfg(feature = "trace")]
+        let _span = tracing::span!(tracing::Level::$level, $($args)+).entered();
+    };
+}
+
+/// Emit an event at the `tracing::Level` `$level`. Without the `trace` feature, the variables
+/// recorded as fields by name are still borrowed, so they do not warn as unused.
+macro_rules! trace_event {
+    ($level:ident, $($args:tt)+) => {
+        #[cfg(feature = "trace")]
+        tracing::event!(tracing::Level::$level, $($args)+);
+        #[cfg(not(feature = "trace"))]
+        trace_fields!($($args)+);
+    };
+}
+
+/// Borrow the leading fields of a `trace_event!` that are variables recorded by name
+#[cfg(not(feature = "trace"))]
+macro_rules! trace_fields {
+    ($field:ident, $($rest:tt)+) => {
+        let _ = &$field;
+        trace_fields!($($rest)+);
+    };
+    ($($rest:tt)*) => {};
+}
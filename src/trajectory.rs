@@ -0,0 +1,228 @@
+//! Following a Cartesian path with the closed-form solvers.
+//!
+//! `CartesianTracker` solves IK at every waypoint and picks one solution per waypoint so that the
+//! total joint motion along the path is as small as possible. Since every solution of every
+//! waypoint is considered, a large joint step that remains in the result cannot be avoided by
+//! picking other branches and is reported as a `TrackingEvent::BranchChange`. The joint motion is
+//! measured with a `JointMetric`, by default the wrapped angular distance with equal weights.
+//! Solutions can be excluded with a filter, e.g. a collision check, before the path is chosen.
+
+use {
+    crate::{
+        inverse_kinematics::auxiliary::wrap_to_pi,
+        metrics::JointMetric,
+        pose::Pose,
+        robot::{IKSolver, IkSolution},
+    },
+    alloc::{boxed::Box, vec::Vec},
+    nalgebra::Vector6,
+};
+
+/// Something the tracker could not avoid along the path
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TrackingEvent {
+    /// No solution of the waypoint has a residual within the tolerance and passes the filter
+    Unreachable { waypoint: usize },
+    /// Some joint moves by more than the allowed step between the previous reachable waypoint and
+    /// this one, i.e. the arm has to switch between solution branches
+    BranchChange {
+        waypoint: usize,
+        from_branch: usize,
+        to_branch: usize,
+        max_joint_step: f64,
+    },
+}
+
+/// The joint trajectory found for a path, with one entry per waypoint
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct JointTrajectory {
+    /// The selected solution of each waypoint, or `None` if it is unreachable. The joint angles
+    /// are unwrapped so that consecutive waypoints never differ by more than π in any joint, unless
+    /// the joint limits of the solver leave only the revolution the long way around.
+    pub waypoints: Vec<Option<IkSolution>>,
+    /// Unreachable waypoints and branch changes, in the order of the waypoints
+    pub events: Vec<TrackingEvent>,
+}
+
+impl JointTrajectory {
+    /// Whether every waypoint was reached without switching branches
+    pub fn is_continuous(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// The joint angles of the reachable waypoints
+    pub fn q(&self) -> impl Iterator<Item = &Vector6<f64>> {
+        self.waypoints.iter().flatten().map(|solution| &solution.q)
+    }
+}
+
+/// Decides whether the tracker may use a solution
+type SolutionFilter = Box<dyn Fn(&Vector6<f64>) -> bool>;
+
+/// Solves a sequence of poses into a continuous joint trajectory
+pub struct CartesianTracker<S: IKSolver> {
+    solver: S,
+    tolerance: f64,
+    max_joint_step: f64,
+    metric: JointMetric,
+    filter: Option<SolutionFilter>,
+}
+
+/// The change of every joint when moving from `from` to `to`, taking the shorter way around
+fn joint_step(from: &Vector6<f64>, to: &Vector6<f64>) -> Vector6<f64> {
+    (to - from).map(wrap_to_pi)
+}
+
+impl<S: IKSolver> CartesianTracker<S> {
+    /// A tracker accepting solutions with a residual up to `1e-6`, reporting joint steps above
+    /// `0.5` rad as branch changes and measuring joint motion with the `IKSolver::joint_metric` of
+    /// `solver`, weighted by link length for a `Robot`
+    pub fn new(solver: S) -> Self {
+        CartesianTracker {
+            metric: solver.joint_metric(),
+            solver,
+            tolerance: 1e-6,
+            max_joint_step: 0.5,
+            filter: None,
+        }
+    }
+
+    /// Set the largest residual a solution may have for its waypoint to count as reachable
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Set the largest change of any joint between waypoints before it is reported as a branch
+    /// change
+    pub fn with_max_joint_step(mut self, max_joint_step: f64) -> Self {
+        self.max_joint_step = max_joint_step;
+        self
+    }
+
+    /// Set the metric the joint motion between waypoints is measured with
+    pub fn with_metric(mut self, metric: JointMetric) -> Self {
+        self.metric = metric;
+        self
+    }
+
+    /// Only consider the solutions that `filter` returns true for, e.g. those free of
+    /// collisions. A waypoint none of whose solutions pass is reported as unreachable.
+    pub fn with_filter(mut self, filter: impl Fn(&Vector6<f64>) -> bool + 'static) -> Self {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    /// Find the joint trajectory through `poses` with the least total joint motion. If `start` is
+    /// given, the motion from it to the first reachable waypoint is included, so the trajectory
+    /// continues from the current configuration of the arm.
+    pub fn track(&self, poses: &[Pose], start: Option<&Vector6<f64>>) -> JointTrajectory {
+        let candidates: Vec<Vec<IkSolution>> = poses
+            .iter()
+            .map(|pose| {
+                let mut solutions = self.solver.ik(pose.rotation, pose.translation);
+                solutions.retain(|solution| {
+                    solution.error <= self.tolerance
+                        && self.filter.as_ref().is_none_or(|filter| filter(&solution.q))
+                });
+                solutions.into_iter().collect()
+            })
+            .collect();
+
+        // The least total motion to reach each candidate, and the candidate of the previous
+        // reachable waypoint it is reached from
+        let mut costs: Vec<Vec<(f64, Option<usize>)>> = Vec::with_capacity(candidates.len());
+        let mut previous: Option<usize> = None;
+
+        for (k, solutions) in candidates.iter().enumerate() {
+            let layer = solutions
+                .iter()
+                .map(|solution| match previous {
+                    Some(p) => candidates[p]
+                        .iter()
+                        .zip(&costs[p])
+                        .enumerate()
+                        .map(|(i, (from, &(cost, _)))| {
+                            (cost + self.metric.distance(&from.q, &solution.q), Some(i))
+                        })
+                        .min_by(|a, b| a.0.total_cmp(&b.0))
+                        .unwrap_or((0.0, None)),
+                    None => (
+                        start.map_or(0.0, |q| self.metric.distance(q, &solution.q)),
+                        None,
+                    ),
+                })
+                .collect();
+            costs.push(layer);
+
+            if !solutions.is_empty() {
+                previous = Some(k);
+            }
+        }
+
+        // Walk back from the cheapest candidate of the last reachable waypoint
+        let mut selected: Vec<Option<usize>> = Vec::with_capacity(candidates.len());
+        selected.resize(candidates.len(), None);
+        let mut current = previous.and_then(|k| {
+            costs[k]
+                .iter()
+                .enumerate()
+                .min_by(|a, b| a.1 .0.total_cmp(&b.1 .0))
+                .map(|(i, _)| (k, i))
+        });
+
+        while let Some((k, i)) = current {
+            selected[k] = Some(i);
+            current = costs[k][i].1.and_then(|j| {
+                (0..k)
+                    .rev()
+                    .find(|&p| !candidates[p].is_empty())
+                    .map(|p| (p, j))
+            });
+        }
+
+        let mut trajectory = JointTrajectory::default();
+        let mut last: Option<IkSolution> = None;
+
+        for (k, choice) in selected.into_iter().enumerate() {
+            let Some(i) = choice else {
+                trajectory
+                    .events
+                    .push(TrackingEvent::Unreachable { waypoint: k });
+                trajectory.waypoints.push(None);
+                continue;
+            };
+
+            let mut solution = candidates[k][i].clone();
+            let reference = last.as_ref().map(|last| last.q).or(start.copied());
+
+            if let Some(reference) = reference {
+                solution.q = match self.solver.joint_limits() {
+                    Some(limits) => limits
+                        .unwrap_near(&solution.q, &reference)
+                        .unwrap_or(solution.q),
+                    None => reference + joint_step(&reference, &solution.q),
+                };
+
+                let max_joint_step = (solution.q - reference).amax();
+                if max_joint_step > self.max_joint_step {
+                    if let Some(last) = &last {
+                        trajectory.events.push(TrackingEvent::BranchChange {
+                            waypoint: k,
+                            from_branch: last.branch,
+                            to_branch: solution.branch,
+                            max_joint_step,
+                        });
+                    }
+                }
+            }
+
+            last = Some(solution.clone());
+            trajectory.waypoints.push(Some(solution));
+        }
+
+        trajectory
+    }
+}
@@ -0,0 +1,130 @@
+//! Exporting kinematics to URDF, to check a hand-built model in RViz or another URDF viewer.
+//!
+//! `Kinematics::to_urdf` writes a chain of links `base_link` and `link_1` to `link_n`, connected
+//! by a joint `joint_i` per joint of the chain, with its origin at `p[i - 1]` in the frame of the
+//! previous link and its axis along `h[i - 1]`, and a `flange` at `p[n]` from `link_n`. Since
+//! every frame of the chain is unrotated at the zero configuration, the joints have no rotation
+//! of their own. The `base` and `tool` of the kinematics become fixed joints from a `world` link
+//! and to a `tool0` link, so the pose of `tool0` in `world` is the one of `forward_kinematics`.
+//!
+//! Every link of the chain has a thin cylinder as its visual, from its origin to the next joint
+//! origin or the flange, and no collision or inertial elements. Revolute joints are limited to
+//! `[-π, π]` and prismatic ones to the reach of the chain, as URDF needs limits for both.
+
+use {
+    crate::inverse_kinematics::auxiliary::{JointType, Kinematics},
+    alloc::{format, string::String},
+    core::f64::consts::PI,
+    nalgebra::{Isometry3, Translation3, UnitQuaternion, Vector3},
+};
+
+/// The radius of the link cylinders as a fraction of the reach of the chain
+const LINK_RADIUS: f64 = 0.02;
+
+/// `text` with the characters XML reserves in attribute values escaped
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped += "&amp;",
+            '<' => escaped += "&lt;",
+            '>' => escaped += "&gt;",
+            '"' => escaped += "&quot;",
+            '\'' => escaped += "&apos;",
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The `origin` element of `pose`
+fn origin(pose: &Isometry3<f64>) -> String {
+    let t = pose.translation.vector;
+    let (roll, pitch, yaw) = pose.rotation.euler_angles();
+    format!(
+        "<origin xyz=\"{} {} {}\" rpy=\"{} {} {}\"/>",
+        t.x, t.y, t.z, roll, pitch, yaw
+    )
+}
+
+/// A link with a cylinder of `radius` from its origin to `offset`, or no visual if `offset` is
+/// zero
+fn link(name: &str, offset: &Vector3<f64>, radius: f64) -> String {
+    let length = offset.norm();
+    if length == 0.0 {
+        return format!("  <link name=\"{name}\"/>\n");
+    }
+    // Cylinders lie along their z axis, centered on their origin
+    let rotation = UnitQuaternion::rotation_between(&Vector3::z(), offset)
+        .unwrap_or_else(|| UnitQuaternion::from_axis_angle(&Vector3::x_axis(), PI));
+    let pose = Isometry3::from_parts(Translation3::from(offset / 2.0), rotation);
+    format!(
+        "  <link name=\"{name}\">\n    <visual>\n      {}\n      <geometry>\
+         <cylinder radius=\"{radius}\" length=\"{length}\"/></geometry>\n    </visual>\n  \
+         </link>\n",
+        origin(&pose)
+    )
+}
+
+/// A fixed joint from `parent` to `child` at `pose`
+fn fixed_joint(name: &str, parent: &str, child: &str, pose: &Isometry3<f64>) -> String {
+    format!(
+        "  <joint name=\"{name}\" type=\"fixed\">\n    <parent link=\"{parent}\"/>\n    \
+         <child link=\"{child}\"/>\n    {}\n  </joint>\n",
+        origin(pose)
+    )
+}
+
+impl<const C1: usize, const C2: usize> Kinematics<C1, C2> {
+    /// The chain as a URDF robot called `name`, see the `urdf` module
+    pub fn to_urdf(&self, name: &str) -> String {
+        let reach: f64 = self.p.column_iter().map(|p| p.norm()).sum();
+        let radius = LINK_RADIUS * reach;
+        let links: [String; C2] = core::array::from_fn(|i| match i {
+            0 => String::from("base_link"),
+            i => format!("link_{i}"),
+        });
+        let offset = |i: usize| {
+            Isometry3::from_parts(
+                Translation3::from(Vector3::from(self.p.column(i))),
+                UnitQuaternion::identity(),
+            )
+        };
+
+        let mut urdf = format!(
+            "<?xml version=\"1.0\"?>\n<robot name=\"{}\">\n  <link name=\"world\"/>\n",
+            escape(name)
+        );
+        urdf += &fixed_joint("base", "world", &links[0], &self.base);
+        for (i, name) in links.iter().enumerate() {
+            urdf += &link(name, &self.p.column(i).into(), radius);
+        }
+        for i in 0..C1 {
+            let (kind, limit) = match self.joint_types[i] {
+                JointType::Revolute => ("revolute", PI),
+                JointType::Prismatic => ("prismatic", reach),
+            };
+            let h = self.h.column(i).normalize();
+            urdf += &format!(
+                "  <joint name=\"joint_{}\" type=\"{kind}\">\n    <parent link=\"{}\"/>\n    \
+                 <child link=\"{}\"/>\n    {}\n    <axis xyz=\"{} {} {}\"/>\n    \
+                 <limit lower=\"{}\" upper=\"{limit}\" effort=\"0\" velocity=\"0\"/>\n  \
+                 </joint>\n",
+                i + 1,
+                links[i],
+                links[i + 1],
+                origin(&offset(i)),
+                h.x,
+                h.y,
+                h.z,
+                -limit
+            );
+        }
+        urdf += "  <link name=\"flange\"/>\n";
+        urdf += &fixed_joint("flange_joint", &links[C1], "flange", &offset(C1));
+        urdf += "  <link name=\"tool0\"/>\n";
+        urdf += &fixed_joint("tool", "flange", "tool0", &self.tool);
+        urdf += "</robot>\n";
+        urdf
+    }
+}
@@ -0,0 +1,255 @@
+//! Comparing the solvers against the test vectors of the MATLAB and Julia IK-Geo implementations.
+//!
+//! A corpus is a CSV file with one case per line: the inputs of the solver, followed by the joint
+//! angles of every branch the reference implementation found, 6 values per branch. The inputs are
+//! either a pose, as the 12 values `hardcoded_setup_from_string` accepts, or the 51 values of a
+//! general setup: the 6 axes `h` and the 7 offsets `p` one column at a time, the rotation in
+//! row-major order and the translation. Branches containing NaN, which the reference writes to pad
+//! its output, are skipped, as are blank lines, lines starting with `#` and a header line.
+//!
+//! Each expected branch is matched to the closest solution of the solver, and reported as a
+//! `BranchMismatch` when no solution is within the joint tolerance. Solutions that match no branch
+//! are reported as extra.
+
+use {
+    crate::{
+        config::SolverConfig,
+        error::{parse_list, IkGeoError},
+        inverse_kinematics::auxiliary::{wrap_to_pi, Kinematics, Matrix3x7},
+        robot::{IKSolver, IkSolutionSet, KinematicFamily},
+    },
+    core::fmt,
+    nalgebra::{Matrix3, Matrix3x6, Vector3, Vector6},
+};
+
+/// The inputs that precede the expected branches on each line of a corpus
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// The 12 values of a pose, for the hardcoded robots
+    Pose,
+    /// The 51 values of the kinematics and a pose, for the solvers of each family
+    KinematicsAndPose,
+}
+
+impl Layout {
+    /// The number of values before the expected branches
+    pub fn inputs(&self) -> usize {
+        match self {
+            Layout::Pose => 12,
+            Layout::KinematicsAndPose => 51,
+        }
+    }
+}
+
+/// One line of a corpus
+#[derive(Debug, Clone)]
+pub struct ReferenceCase {
+    /// The kinematics of the case, only given with `Layout::KinematicsAndPose`
+    pub kinematics: Option<Kinematics<6, 7>>,
+    pub rotation: Matrix3<f64>,
+    pub translation: Vector3<f64>,
+    /// The joint angles of every branch the reference implementation found
+    pub expected: Vec<Vector6<f64>>,
+}
+
+impl ReferenceCase {
+    /// Parse one line of a corpus
+    pub fn parse(raw: &str, layout: Layout) -> Result<Self, IkGeoError> {
+        let data = parse_list(raw)?;
+        let inputs = layout.inputs();
+        if data.len() < inputs || !(data.len() - inputs).is_multiple_of(6) {
+            return Err(IkGeoError::DimensionMismatch {
+                expected: inputs + data.len().saturating_sub(inputs).div_ceil(6) * 6,
+                found: data.len(),
+            });
+        }
+
+        let (kinematics, pose) = match layout {
+            Layout::Pose => (None, &data[..12]),
+            Layout::KinematicsAndPose => {
+                let mut kin = Kinematics::new();
+                kin.h = Matrix3x6::from_column_slice(&data[..18]);
+                kin.p = Matrix3x7::from_column_slice(&data[18..39]);
+                (Some(kin), &data[39..51])
+            }
+        };
+
+        Ok(ReferenceCase {
+            kinematics,
+            rotation: Matrix3::from_row_slice(&pose[..9]),
+            translation: Vector3::from_row_slice(&pose[9..]),
+            expected: data[inputs..]
+                .chunks(6)
+                .map(Vector6::from_row_slice)
+                .filter(|q| !q.iter().any(|x| x.is_nan()))
+                .collect(),
+        })
+    }
+}
+
+/// Parse every case of a corpus
+pub fn parse_corpus(raw: &str, layout: Layout) -> Result<Vec<ReferenceCase>, IkGeoError> {
+    raw.lines()
+        .map(str::trim)
+        .enumerate()
+        .filter(|(i, line)| {
+            let is_header = *i == 0 && line.starts_with(|c: char| c.is_ascii_alphabetic());
+            !line.is_empty() && !line.starts_with('#') && !is_header
+        })
+        .map(|(_, line)| ReferenceCase::parse(line, layout))
+        .collect()
+}
+
+/// How close the solver has to come to the reference
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerances {
+    /// The largest difference of any joint angle, in radians, for a solution to match a branch
+    pub joint: f64,
+    /// Solutions with a larger residual do not count as extra when they match no branch, since
+    /// the reference only writes the solutions it found exactly
+    pub residual: f64,
+}
+
+impl Default for Tolerances {
+    fn default() -> Self {
+        Tolerances {
+            joint: 1e-6,
+            residual: 1e-6,
+        }
+    }
+}
+
+/// An expected branch without a solution within the joint tolerance
+#[derive(Debug, Clone, PartialEq)]
+pub struct BranchMismatch {
+    /// The index of the case in the corpus
+    pub case: usize,
+    /// The index of the branch among the expected branches of the case
+    pub branch: usize,
+    pub expected: Vector6<f64>,
+    /// The closest solution, if there was any
+    pub closest: Option<Vector6<f64>>,
+    /// The largest joint difference to the closest solution, infinite without one
+    pub distance: f64,
+}
+
+/// The outcome of comparing a solver to a corpus
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValidationReport {
+    pub cases: usize,
+    pub branches: usize,
+    pub mismatches: Vec<BranchMismatch>,
+    /// Solutions that match no expected branch, with the index of their case
+    pub extra: Vec<(usize, Vector6<f64>)>,
+}
+
+impl ValidationReport {
+    /// Whether every branch was matched and there were no extra solutions
+    pub fn is_parity(&self) -> bool {
+        self.mismatches.is_empty() && self.extra.is_empty()
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} cases, {} branches, {} mismatched, {} extra",
+            self.cases,
+            self.branches,
+            self.mismatches.len(),
+            self.extra.len()
+        )?;
+        for mismatch in &self.mismatches {
+            writeln!(
+                f,
+                "case {} branch {}: off by {:.2e}",
+                mismatch.case, mismatch.branch, mismatch.distance
+            )?;
+        }
+        for (case, q) in &self.extra {
+            writeln!(f, "case {case}: extra solution {}", q.transpose())?;
+        }
+        Ok(())
+    }
+}
+
+/// The largest difference between the joint angles of `a` and `b`, modulo 2π
+fn joint_distance(a: &Vector6<f64>, b: &Vector6<f64>) -> f64 {
+    a.iter()
+        .zip(b.iter())
+        .map(|(a, b)| wrap_to_pi(a - b).abs())
+        .fold(0.0, f64::max)
+}
+
+/// Compare `solve` to the corpus `cases`, branch by branch
+pub fn validate<F: Fn(&ReferenceCase) -> IkSolutionSet>(
+    cases: &[ReferenceCase],
+    tolerances: &Tolerances,
+    solve: F,
+) -> ValidationReport {
+    let mut report = ValidationReport {
+        cases: cases.len(),
+        ..Default::default()
+    };
+
+    for (i, case) in cases.iter().enumerate() {
+        let solutions = solve(case);
+        let mut matched = vec![false; solutions.len()];
+        report.branches += case.expected.len();
+
+        for (branch, expected) in case.expected.iter().enumerate() {
+            let closest = solutions
+                .iter()
+                .enumerate()
+                .map(|(j, solution)| (j, joint_distance(&solution.q, expected)))
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+
+            match closest {
+                Some((j, distance)) if distance <= tolerances.joint => matched[j] = true,
+                _ => report.mismatches.push(BranchMismatch {
+                    case: i,
+                    branch,
+                    expected: *expected,
+                    closest: closest.map(|(j, _)| solutions[j].q),
+                    distance: closest.map_or(f64::INFINITY, |(_, distance)| distance),
+                }),
+            }
+        }
+
+        report.extra.extend(
+            solutions
+                .iter()
+                .zip(matched)
+                .filter(|(solution, matched)| !matched && solution.error <= tolerances.residual)
+                .map(|(solution, _)| (i, solution.q)),
+        );
+    }
+
+    report
+}
+
+/// Compare `robot` to a corpus of poses
+pub fn validate_robot(
+    robot: &impl IKSolver,
+    cases: &[ReferenceCase],
+    tolerances: &Tolerances,
+) -> ValidationReport {
+    validate(cases, tolerances, |case| {
+        robot.ik(case.rotation, case.translation)
+    })
+}
+
+/// Compare the solver of `family` to a corpus of kinematics and poses. Cases without kinematics
+/// have no solutions.
+pub fn validate_family(
+    family: KinematicFamily,
+    cases: &[ReferenceCase],
+    tolerances: &Tolerances,
+    config: &SolverConfig,
+) -> ValidationReport {
+    validate(cases, tolerances, |case| match &case.kinematics {
+        Some(kin) => (family.solver())(&case.rotation, &case.translation, kin, config),
+        None => IkSolutionSet::new(),
+    })
+}
@@ -0,0 +1,333 @@
+//! Streaming link frames and candidate solutions to a meshcat viewer, to see why a branch was
+//! rejected.
+//!
+//! A `VizSink` receives named frames. `MeshcatServer` is a websocket server for meshcat viewers,
+//! which connect to it from the browser with `viewer.connect("ws://127.0.0.1:7001")`, and sends
+//! them the msgpack encoded commands of the meshcat protocol: `set_object` adds a triad the first
+//! time a frame is shown and `set_transform` moves it. Viewers that connect later are sent the
+//! frames shown so far. `MeshcatCommands` writes the same commands one after the other to any
+//! `io::Write`, to record them or forward them over another transport. Other viewers, such as
+//! rerun.io, only need `VizSink` implemented on their recording stream, which keeps their SDKs out
+//! of the dependencies of this crate.
+//!
+//! Frames are named like paths: `log_configuration` puts the frames of joint `i` at
+//! `{prefix}/link_{i}` and the tool at `{prefix}/tool`, and `log_candidates` puts each solution
+//! under `{prefix}/accepted/branch_{b}` or `{prefix}/rejected/branch_{b}`, so the two groups can
+//! be shown and hidden separately. In meshcat they are under the `meshcat` root, as the objects of
+//! meshcat-python are.
+
+use {
+    crate::{inverse_kinematics::auxiliary::Kinematics, robot::IkSolution},
+    nalgebra::{Matrix3, Matrix4, SVector, Vector3},
+    rmp::encode,
+    std::{
+        collections::{btree_map::Entry, BTreeMap, BTreeSet},
+        io,
+        net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+        sync::{Arc, Mutex, MutexGuard, PoisonError, Weak},
+        thread,
+    },
+    tungstenite::{Message, WebSocket},
+};
+
+/// The length of the axes of the triad shown for each frame, in meters
+pub const TRIAD_SCALE: f32 = 0.1;
+
+/// The msgpack extension type meshcat reads as a `Float32Array`
+const FLOAT32_ARRAY: i8 = 0x17;
+
+/// A viewer that shows frames by name
+pub trait VizSink {
+    /// Show the frame at `path` with the orientation `rotation` and origin `translation`
+    fn set_transform(
+        &mut self,
+        path: &str,
+        rotation: &Matrix3<f64>,
+        translation: &Vector3<f64>,
+    ) -> io::Result<()>;
+}
+
+fn meshcat_path(path: &str) -> String {
+    format!("/meshcat/{}", path.trim_start_matches('/'))
+}
+
+fn write_entry(out: &mut Vec<u8>, key: &str, value: &str) -> io::Result<()> {
+    encode::write_str(out, key)?;
+    encode::write_str(out, value)?;
+    Ok(())
+}
+
+/// A buffer attribute of 3-vectors, as meshcat-python packs numpy arrays
+fn write_attribute(out: &mut Vec<u8>, key: &str, vectors: &[[f32; 3]]) -> io::Result<()> {
+    encode::write_str(out, key)?;
+    encode::write_map_len(out, 4)?;
+    encode::write_str(out, "itemSize")?;
+    encode::write_uint(out, 3)?;
+    write_entry(out, "type", "Float32Array")?;
+    encode::write_str(out, "array")?;
+    encode::write_ext_meta(out, 12 * vectors.len() as u32, FLOAT32_ARRAY)?;
+    for x in vectors.iter().flatten() {
+        out.extend_from_slice(&x.to_le_bytes());
+    }
+    encode::write_str(out, "normalized")?;
+    encode::write_bool(out, false)?;
+    Ok(())
+}
+
+/// The `set_object` command adding a triad at `path`, with red, green and blue axes along x, y and
+/// z, like the `triad` of meshcat-python
+fn set_triad_command(path: &str) -> io::Result<Vec<u8>> {
+    let path = meshcat_path(path);
+    let (geometry, material) = (format!("{path}/geometry"), format!("{path}/material"));
+    let s = TRIAD_SCALE;
+    let position = [[0.0; 3], [s, 0.0, 0.0], [0.0; 3], [0.0, s, 0.0], [0.0; 3], [0.0, 0.0, s]];
+    let color = [
+        [1.0, 0.0, 0.0],
+        [1.0, 0.6, 0.0],
+        [0.0, 1.0, 0.0],
+        [0.6, 1.0, 0.0],
+        [0.0, 0.0, 1.0],
+        [0.0, 0.6, 1.0],
+    ];
+
+    let mut out = Vec::new();
+    encode::write_map_len(&mut out, 3)?;
+    write_entry(&mut out, "type", "set_object")?;
+    write_entry(&mut out, "path", &path)?;
+
+    encode::write_str(&mut out, "object")?;
+    encode::write_map_len(&mut out, 4)?;
+    encode::write_str(&mut out, "metadata")?;
+    encode::write_map_len(&mut out, 2)?;
+    encode::write_str(&mut out, "version")?;
+    encode::write_f64(&mut out, 4.5)?;
+    write_entry(&mut out, "type", "Object")?;
+
+    encode::write_str(&mut out, "geometries")?;
+    encode::write_array_len(&mut out, 1)?;
+    encode::write_map_len(&mut out, 3)?;
+    write_entry(&mut out, "uuid", &geometry)?;
+    write_entry(&mut out, "type", "BufferGeometry")?;
+    encode::write_str(&mut out, "data")?;
+    encode::write_map_len(&mut out, 1)?;
+    encode::write_str(&mut out, "attributes")?;
+    encode::write_map_len(&mut out, 2)?;
+    write_attribute(&mut out, "position", &position)?;
+    write_attribute(&mut out, "color", &color)?;
+
+    encode::write_str(&mut out, "materials")?;
+    encode::write_array_len(&mut out, 1)?;
+    encode::write_map_len(&mut out, 3)?;
+    write_entry(&mut out, "uuid", &material)?;
+    write_entry(&mut out, "type", "LineBasicMaterial")?;
+    encode::write_str(&mut out, "vertexColors")?;
+    encode::write_uint(&mut out, 2)?;
+
+    encode::write_str(&mut out, "object")?;
+    encode::write_map_len(&mut out, 5)?;
+    write_entry(&mut out, "uuid", &path)?;
+    write_entry(&mut out, "type", "LineSegments")?;
+    write_entry(&mut out, "geometry", &geometry)?;
+    write_entry(&mut out, "material", &material)?;
+    encode::write_str(&mut out, "matrix")?;
+    encode::write_array_len(&mut out, 16)?;
+    for x in Matrix4::<f64>::identity().iter() {
+        encode::write_f64(&mut out, *x)?;
+    }
+
+    Ok(out)
+}
+
+/// The `set_transform` command moving the object at `path`
+fn set_transform_command(
+    path: &str,
+    rotation: &Matrix3<f64>,
+    translation: &Vector3<f64>,
+) -> io::Result<Vec<u8>> {
+    let mut matrix = Matrix4::identity();
+    matrix.fixed_view_mut::<3, 3>(0, 0).copy_from(rotation);
+    matrix.fixed_view_mut::<3, 1>(0, 3).copy_from(translation);
+
+    let mut out = Vec::new();
+    encode::write_map_len(&mut out, 3)?;
+    write_entry(&mut out, "type", "set_transform")?;
+    write_entry(&mut out, "path", &meshcat_path(path))?;
+    // meshcat takes the matrix in column major order, as nalgebra stores it
+    encode::write_str(&mut out, "matrix")?;
+    encode::write_array_len(&mut out, 16)?;
+    for x in matrix.iter() {
+        encode::write_f64(&mut out, *x)?;
+    }
+
+    Ok(out)
+}
+
+/// Writes frames as msgpack encoded meshcat commands, one after the other
+pub struct MeshcatCommands<W: io::Write> {
+    out: W,
+    /// The paths a triad has been added at
+    shown: BTreeSet<String>,
+}
+
+impl<W: io::Write> MeshcatCommands<W> {
+    pub fn new(out: W) -> Self {
+        MeshcatCommands {
+            out,
+            shown: BTreeSet::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl<W: io::Write> VizSink for MeshcatCommands<W> {
+    fn set_transform(
+        &mut self,
+        path: &str,
+        rotation: &Matrix3<f64>,
+        translation: &Vector3<f64>,
+    ) -> io::Result<()> {
+        if !self.shown.contains(path) {
+            self.out.write_all(&set_triad_command(path)?)?;
+            self.shown.insert(path.to_owned());
+        }
+        self.out
+            .write_all(&set_transform_command(path, rotation, translation)?)
+    }
+}
+
+/// The viewers connected to a `MeshcatServer` and the frames shown so far
+#[derive(Default)]
+struct Scene {
+    viewers: Vec<WebSocket<TcpStream>>,
+    /// The `set_object` and latest `set_transform` commands of every path
+    frames: BTreeMap<String, (Vec<u8>, Vec<u8>)>,
+}
+
+/// Send `command` to every viewer, dropping those that have disconnected
+fn broadcast(viewers: &mut Vec<WebSocket<TcpStream>>, command: &[u8]) {
+    viewers.retain_mut(|viewer| viewer.send(Message::Binary(command.to_vec())).is_ok());
+}
+
+/// A websocket server that meshcat viewers connect to, sending them the frames it is given
+pub struct MeshcatServer {
+    address: SocketAddr,
+    scene: Arc<Mutex<Scene>>,
+}
+
+impl MeshcatServer {
+    /// Listen for viewers at `address`, e.g. `"127.0.0.1:7001"`, accepting them on a thread of
+    /// its own
+    pub fn bind(address: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let scene = Arc::new(Mutex::new(Scene::default()));
+        let weak = Arc::downgrade(&scene);
+        thread::spawn(move || accept_viewers(listener, weak));
+        Ok(MeshcatServer { address, scene })
+    }
+
+    /// The address the server listens at, e.g. to find the port after binding port 0
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// The number of viewers connected
+    pub fn viewers(&self) -> usize {
+        self.lock().viewers.len()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Scene> {
+        self.scene.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Accept viewers until the server is dropped, sending each the frames shown so far
+fn accept_viewers(listener: TcpListener, scene: Weak<Mutex<Scene>>) {
+    for stream in listener.incoming() {
+        let Some(scene) = scene.upgrade() else {
+            return;
+        };
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let Ok(mut viewer) = tungstenite::accept(stream) else {
+            continue;
+        };
+
+        let mut scene = scene.lock().unwrap_or_else(PoisonError::into_inner);
+        let replayed = scene
+            .frames
+            .values()
+            .flat_map(|(object, transform)| [object, transform])
+            .all(|command| viewer.send(Message::Binary(command.clone())).is_ok());
+        if replayed {
+            scene.viewers.push(viewer);
+        }
+    }
+}
+
+impl VizSink for MeshcatServer {
+    fn set_transform(
+        &mut self,
+        path: &str,
+        rotation: &Matrix3<f64>,
+        translation: &Vector3<f64>,
+    ) -> io::Result<()> {
+        let transform = set_transform_command(path, rotation, translation)?;
+        let mut scene = self.lock();
+        let Scene { viewers, frames } = &mut *scene;
+        let frame = match frames.entry(path.to_owned()) {
+            Entry::Occupied(frame) => frame.into_mut(),
+            Entry::Vacant(frame) => {
+                let object = set_triad_command(path)?;
+                broadcast(viewers, &object);
+                frame.insert((object, Vec::new()))
+            }
+        };
+        broadcast(viewers, &transform);
+        frame.1 = transform;
+        Ok(())
+    }
+}
+
+/// Show the frame of every joint and the tool of `kin` at the joint angles `q` under `prefix`
+pub fn log_configuration<S: VizSink, const C1: usize, const C2: usize>(
+    sink: &mut S,
+    prefix: &str,
+    kin: &Kinematics<C1, C2>,
+    q: &SVector<f64, C1>,
+) -> io::Result<()> {
+    for (i, (rotation, translation)) in kin.link_frames(q).enumerate() {
+        let path = if i == C1 {
+            format!("{prefix}/tool")
+        } else {
+            format!("{prefix}/link_{i}")
+        };
+        sink.set_transform(&path, &rotation, &translation)?;
+    }
+    Ok(())
+}
+
+/// Show every solution in `candidates` under `prefix`, grouped by whether `accept` returns true
+/// for it, e.g. the collision check of `solve_filtered`
+pub fn log_candidates<'a, S: VizSink, const C2: usize>(
+    sink: &mut S,
+    prefix: &str,
+    kin: &Kinematics<6, C2>,
+    candidates: impl IntoIterator<Item = &'a IkSolution>,
+    mut accept: impl FnMut(&IkSolution) -> bool,
+) -> io::Result<()> {
+    for solution in candidates {
+        let group = if accept(solution) {
+            "accepted"
+        } else {
+            "rejected"
+        };
+        let path = format!("{prefix}/{group}/branch_{}", solution.branch);
+        log_configuration(sink, &path, kin, &solution.q)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,141 @@
+//! `wasm-bindgen` wrapper, enabled with the `wasm` feature, so the solvers can run in the browser.
+//!
+//! Build for `wasm32-unknown-unknown` with `--no-default-features --features wasm`, which
+//! replaces the nlopt based searches with the pure Rust ones. Rotations are passed as
+//! `Float64Array`s of 9 values in row-major order and translations as 3 values.
+
+use {
+    crate::{
+        inverse_kinematics::auxiliary::{Kinematics, Matrix3x7},
+        robot::{preset, IKSolver, IkSolutionSet, KinematicFamily, Robot},
+    },
+    nalgebra::{Matrix3, Matrix3x6, Vector3},
+    wasm_bindgen::prelude::*,
+};
+
+fn robot_by_family(family: &str, kin: Kinematics<6, 7>) -> Option<Robot> {
+    match family {
+        "auto" => Some(Robot::from_kinematics(kin)),
+        _ => KinematicFamily::from_name(family).map(|family| Robot::from_family(family, kin)),
+    }
+}
+
+fn check_length(name: &str, values: &[f64], expected: usize) -> Result<(), JsError> {
+    if values.len() == expected {
+        Ok(())
+    } else {
+        Err(JsError::new(&format!(
+            "{name} must have {expected} values but has {}",
+            values.len()
+        )))
+    }
+}
+
+/// A 6-DOF robot that can be solved from JavaScript
+#[wasm_bindgen]
+pub struct WasmRobot {
+    robot: Robot,
+}
+
+#[wasm_bindgen]
+impl WasmRobot {
+    /// One of the hardcoded robots, e.g. `"ur5"` or `"irb6640"`
+    #[wasm_bindgen(constructor)]
+    pub fn new(name: &str) -> Result<WasmRobot, JsError> {
+        preset(name)
+            .map(|robot| WasmRobot { robot })
+            .ok_or_else(|| JsError::new(&format!("unknown robot \"{name}\"")))
+    }
+
+    /// A robot given as a product of exponentials, with `h` holding the 6 joint axes and `p` the
+    /// 7 offsets as consecutive 3-vectors, solved with the decomposition named by `family`,
+    /// e.g. `"spherical_two_parallel"`, or with the one detected from the kinematics for `"auto"`
+    #[wasm_bindgen(js_name = fromKinematics)]
+    pub fn from_kinematics(family: &str, h: &[f64], p: &[f64]) -> Result<WasmRobot, JsError> {
+        check_length("h", h, 18)?;
+        check_length("p", p, 21)?;
+
+        let mut kin = Kinematics::new();
+        kin.h = Matrix3x6::from_column_slice(h);
+        kin.p = Matrix3x7::from_column_slice(p);
+
+        robot_by_family(family, kin)
+            .map(|robot| WasmRobot { robot })
+            .ok_or_else(|| JsError::new(&format!("unknown kinematic family \"{family}\"")))
+    }
+
+    /// Forward kinematics, returning the rotation in row-major order followed by the translation
+    pub fn fk(&self, q: &[f64]) -> Result<Vec<f64>, JsError> {
+        check_length("q", q, 6)?;
+
+        let (r, t) = self.robot.fk(&[q[0], q[1], q[2], q[3], q[4], q[5]]);
+        Ok(r.transpose().iter().chain(t.iter()).copied().collect())
+    }
+
+    /// Inverse kinematics for the pose given by `rotation` and `translation`
+    pub fn ik(&self, rotation: &[f64], translation: &[f64]) -> Result<WasmSolutions, JsError> {
+        check_length("rotation", rotation, 9)?;
+        check_length("translation", translation, 3)?;
+
+        let solutions = self.robot.ik(
+            Matrix3::from_row_slice(rotation),
+            Vector3::from_column_slice(translation),
+        );
+        Ok(WasmSolutions {
+            solutions,
+            kinematics: self.robot.kinematics().clone(),
+        })
+    }
+}
+
+/// The IK solutions for one pose
+#[wasm_bindgen]
+pub struct WasmSolutions {
+    solutions: IkSolutionSet,
+    /// The kinematics the solutions are for, to compute their manipulability
+    kinematics: Kinematics<6, 7>,
+}
+
+#[wasm_bindgen]
+impl WasmSolutions {
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> usize {
+        self.solutions.len()
+    }
+
+    /// The joint angles of every solution, 6 values each
+    #[wasm_bindgen(getter)]
+    pub fn q(&self) -> Vec<f64> {
+        self.solutions
+            .iter()
+            .flat_map(|solution| solution.q.iter().copied())
+            .collect()
+    }
+
+    /// Whether each solution is a least squares approximation
+    #[wasm_bindgen(getter, js_name = isLs)]
+    pub fn is_ls(&self) -> Vec<u8> {
+        self.solutions
+            .iter()
+            .map(|solution| solution.is_ls as u8)
+            .collect()
+    }
+
+    /// The residual error of each solution
+    #[wasm_bindgen(getter)]
+    pub fn error(&self) -> Vec<f64> {
+        self.solutions
+            .iter()
+            .map(|solution| solution.error)
+            .collect()
+    }
+
+    /// The manipulability of each solution, which approaches zero near a singularity
+    #[wasm_bindgen(getter)]
+    pub fn manipulability(&self) -> Vec<f64> {
+        self.solutions
+            .iter()
+            .map(|solution| solution.manipulability_on(&self.kinematics))
+            .collect()
+    }
+}
@@ -0,0 +1,403 @@
+//! Reachability maps of a robot over a box of positions.
+//!
+//! `WorkspaceGrid` divides a box into voxels and solves IK at the center of every voxel for each
+//! of a set of tool orientations. The resulting `ReachabilityMap` counts the exact and least
+//! squares solutions per voxel and can be exported as CSV or as a compact binary file.
+//!
+//! `ReachEnvelope` bounds how far a chain reaches from its first joint, which is enough to reject
+//! targets that are clearly out of reach without solving them.
+
+use {
+    crate::{
+        error::IkGeoError,
+        inverse_kinematics::{auxiliary::Kinematics, family::classify},
+        robot::IKSolver,
+        subproblems::auxiliary::rot,
+    },
+    alloc::{format, string::String, vec::Vec},
+    core::f64::consts::{PI, TAU},
+    nalgebra::{Matrix3, Point3, Vector3, Vector6},
+};
+
+#[cfg(all(not(feature = "std"), target_os = "none"))]
+use num_traits::Float;
+
+/// The first bytes of the binary format written by `ReachabilityMap::to_bytes`
+const MAGIC: &[u8; 4] = b"IKWS";
+const HEADER_SIZE: usize = 4 + 3 * 4 + 6 * 8;
+const VOXEL_SIZE: usize = 3 * 4;
+
+/// Slack given to the radii of a `ReachEnvelope` for rounding
+const REACH_TOLERANCE: f64 = 1e-9;
+
+/// The joint samples per joint `ReachEnvelope::new` uses for chains without a spherical wrist
+const REACH_SAMPLES: usize = 8;
+
+/// Tool orientations whose approach (z) axes are spread evenly over the sphere along a Fibonacci
+/// spiral, each rotated about the approach axis by `rolls` evenly spaced angles
+pub fn sphere_orientations(directions: usize, rolls: usize) -> Vec<Matrix3<f64>> {
+    let golden_angle = PI * (3.0 - 5.0_f64.sqrt());
+
+    (0..directions)
+        .flat_map(|i| {
+            let z = 1.0 - 2.0 * (i as f64 + 0.5) / directions as f64;
+            let radius = (1.0 - z * z).sqrt();
+            let (sin, cos) = (golden_angle * i as f64).sin_cos();
+            let tilt = align_z(&Vector3::new(radius * cos, radius * sin, z));
+
+            (0..rolls).map(move |j| tilt * rot(&Vector3::z(), TAU * j as f64 / rolls as f64))
+        })
+        .collect()
+}
+
+/// A rotation taking the z axis to the unit vector `approach`
+pub fn align_z(approach: &Vector3<f64>) -> Matrix3<f64> {
+    let axis = Vector3::z().cross(approach);
+    if axis.norm() < 1e-12 {
+        if approach.z > 0.0 {
+            Matrix3::identity()
+        } else {
+            rot(&Vector3::x(), PI)
+        }
+    } else {
+        rot(&axis.normalize(), approach.z.clamp(-1.0, 1.0).acos())
+    }
+}
+
+/// The solutions found in one voxel, summed over all orientations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VoxelCount {
+    /// The number of exact solutions
+    pub exact: u32,
+    /// The number of least squares solutions
+    pub ls: u32,
+    /// The number of orientations with at least one exact solution
+    pub reachable_orientations: u32,
+}
+
+/// A box of positions divided into `resolution` voxels along each axis
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WorkspaceGrid {
+    pub min: Vector3<f64>,
+    pub max: Vector3<f64>,
+    pub resolution: [usize; 3],
+}
+
+impl WorkspaceGrid {
+    pub fn new(min: Vector3<f64>, max: Vector3<f64>, resolution: [usize; 3]) -> Self {
+        WorkspaceGrid {
+            min,
+            max,
+            resolution,
+        }
+    }
+
+    /// The number of voxels in the grid
+    pub fn len(&self) -> usize {
+        self.resolution.iter().product()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The grid coordinates of voxel `index`, with x varying fastest
+    pub fn coordinates(&self, index: usize) -> [usize; 3] {
+        let [nx, ny, _] = self.resolution;
+        [index % nx, (index / nx) % ny, index / (nx * ny)]
+    }
+
+    /// The center of voxel `index`
+    pub fn center(&self, index: usize) -> Vector3<f64> {
+        let coordinates = self.coordinates(index);
+        Vector3::from_fn(|i, _| {
+            let size = (self.max[i] - self.min[i]) / self.resolution[i] as f64;
+            self.min[i] + size * (coordinates[i] as f64 + 0.5)
+        })
+    }
+
+    /// Solve every orientation at the center of every voxel with `solver`. All poses are handed to
+    /// `solve_batch` at once, so they are solved in parallel with the `parallel` feature.
+    pub fn map<S: IKSolver + Sync>(
+        &self,
+        solver: &S,
+        orientations: &[Matrix3<f64>],
+    ) -> ReachabilityMap {
+        let poses: Vec<(Matrix3<f64>, Vector3<f64>)> = (0..self.len())
+            .flat_map(|index| {
+                let center = self.center(index);
+                orientations.iter().map(move |&rotation| (rotation, center))
+            })
+            .collect();
+
+        let voxels = solver
+            .solve_batch(&poses)
+            .chunks(orientations.len().max(1))
+            .map(|solution_sets| {
+                let mut count = VoxelCount::default();
+                for solutions in solution_sets {
+                    let exact = solutions.iter().filter(|solution| !solution.is_ls).count() as u32;
+                    count.exact += exact;
+                    count.ls += solutions.len() as u32 - exact;
+                    count.reachable_orientations += (exact > 0) as u32;
+                }
+                count
+            })
+            .collect();
+
+        ReachabilityMap {
+            grid: self.clone(),
+            voxels,
+        }
+    }
+}
+
+/// The solution counts of every voxel of a `WorkspaceGrid`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReachabilityMap {
+    pub grid: WorkspaceGrid,
+    /// The counts in the order of the voxel indices of the grid
+    pub voxels: Vec<VoxelCount>,
+}
+
+impl ReachabilityMap {
+    /// One line per voxel with its center and counts, after a header line
+    pub fn as_csv(&self) -> String {
+        let mut csv = String::from("x,y,z,exact,ls,reachable_orientations\n");
+        for (index, count) in self.voxels.iter().enumerate() {
+            let center = self.grid.center(index);
+            csv += &format!(
+                "{},{},{},{},{},{}\n",
+                center.x, center.y, center.z, count.exact, count.ls, count.reachable_orientations
+            );
+        }
+        csv
+    }
+
+    /// The little-endian binary format: the magic bytes `IKWS`, the resolution as three `u32`s,
+    /// the corners of the box as six `f64`s and then `exact`, `ls` and `reachable_orientations` of
+    /// every voxel as `u32`s
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + VOXEL_SIZE * self.voxels.len());
+        bytes.extend_from_slice(MAGIC);
+        for n in self.grid.resolution {
+            bytes.extend_from_slice(&(n as u32).to_le_bytes());
+        }
+        for x in self.grid.min.iter().chain(self.grid.max.iter()) {
+            bytes.extend_from_slice(&x.to_le_bytes());
+        }
+        for count in &self.voxels {
+            for n in [count.exact, count.ls, count.reachable_orientations] {
+                bytes.extend_from_slice(&n.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    /// Read a map written by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, IkGeoError> {
+        if bytes.len() < HEADER_SIZE || &bytes[..4] != MAGIC {
+            return Err(IkGeoError::MalformedInput(String::from(
+                "missing reachability map header",
+            )));
+        }
+
+        let u32_at = |offset: usize| {
+            u32::from_le_bytes([
+                bytes[offset],
+                bytes[offset + 1],
+                bytes[offset + 2],
+                bytes[offset + 3],
+            ])
+        };
+        let f64_at = |offset: usize| {
+            let mut value = [0; 8];
+            value.copy_from_slice(&bytes[offset..offset + 8]);
+            f64::from_le_bytes(value)
+        };
+
+        let resolution = [u32_at(4) as usize, u32_at(8) as usize, u32_at(12) as usize];
+        let grid = WorkspaceGrid::new(
+            Vector3::new(f64_at(16), f64_at(24), f64_at(32)),
+            Vector3::new(f64_at(40), f64_at(48), f64_at(56)),
+            resolution,
+        );
+
+        // The resolution is untrusted, so the size it implies may not fit in a usize
+        let size = resolution
+            .iter()
+            .try_fold(1_usize, |len, &n| len.checked_mul(n))
+            .and_then(|len| len.checked_mul(VOXEL_SIZE))
+            .and_then(|size| size.checked_add(HEADER_SIZE))
+            .ok_or_else(|| {
+                IkGeoError::MalformedInput(format!(
+                    "reachability map resolution {resolution:?} is too large"
+                ))
+            })?;
+        if bytes.len() != size {
+            return Err(IkGeoError::DimensionMismatch {
+                expected: grid.len(),
+                found: (bytes.len() - HEADER_SIZE) / VOXEL_SIZE,
+            });
+        }
+
+        let voxels = (0..grid.len())
+            .map(|index| {
+                let offset = HEADER_SIZE + VOXEL_SIZE * index;
+                VoxelCount {
+                    exact: u32_at(offset),
+                    ls: u32_at(offset + 4),
+                    reachable_orientations: u32_at(offset + 8),
+                }
+            })
+            .collect();
+
+        Ok(ReachabilityMap { grid, voxels })
+    }
+
+    /// The share of voxels in which at least one orientation has an exact solution
+    pub fn reachable_fraction(&self) -> f64 {
+        if self.voxels.is_empty() {
+            return 0.0;
+        }
+        let reachable = self
+            .voxels
+            .iter()
+            .filter(|count| count.reachable_orientations > 0)
+            .count();
+        reachable as f64 / self.voxels.len() as f64
+    }
+}
+
+/// Bounds on the distance from the origin of the first joint to a point fixed in the tool frame.
+///
+/// The first joint turns about an axis through its origin, so the distance only depends on the
+/// other joints. Every bound is conservative: a pose whose point lies outside of the envelope
+/// cannot be reached, but one inside may still be out of reach.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReachEnvelope {
+    /// The origin of the first joint in the world frame
+    pub center: Vector3<f64>,
+    /// The bounded point in the tool frame, the wrist center for spherical wrists and the tool
+    /// center point otherwise
+    pub point: Vector3<f64>,
+    pub min_radius: f64,
+    pub max_radius: f64,
+}
+
+impl ReachEnvelope {
+    /// The envelope of the wrist center for spherical wrists, and a sampled envelope of the tool
+    /// center point otherwise
+    pub fn new(kin: &Kinematics<6, 7>) -> Self {
+        Self::spherical_wrist(kin).unwrap_or_else(|| Self::sampled(kin, REACH_SAMPLES))
+    }
+
+    /// The envelope of the tool center point from the lengths of the links alone
+    pub fn link_lengths(kin: &Kinematics<6, 7>) -> Self {
+        let reach = (1..7).map(|i| kin.p.column(i).norm()).sum::<f64>()
+            + kin.tool.translation.vector.norm();
+
+        ReachEnvelope {
+            center: first_joint(kin),
+            point: Vector3::zeros(),
+            min_radius: 0.0,
+            max_radius: reach,
+        }
+    }
+
+    /// The envelope of the wrist center, computed analytically, or `None` if the last three axes
+    /// of `kin` do not intersect.
+    ///
+    /// The distance from the second joint to the wrist center only depends on the third joint,
+    /// between extremes found in closed form, and the offset between the first two joints is
+    /// added to and subtracted from them.
+    pub fn spherical_wrist(kin: &Kinematics<6, 7>) -> Option<Self> {
+        if !classify(kin).has_spherical_wrist() {
+            return None;
+        }
+
+        let h: Vector3<f64> = kin.h.column(2).normalize();
+        let (p_12, p_23, p_34) = (kin.p.column(1), kin.p.column(2), kin.p.column(3));
+        let p_34_along = h * h.dot(&p_34);
+        let p_34_across = p_34 - p_34_along;
+
+        // |p_23 + rot(h, q3) p_34|² = c + a cos q3 + b sin q3
+        let c = p_23.norm_squared() + p_34.norm_squared() + 2.0 * p_23.dot(&p_34_along);
+        let a = 2.0 * p_23.dot(&p_34_across);
+        let b = 2.0 * p_23.dot(&h.cross(&p_34_across));
+        let amplitude = a.hypot(b);
+        let inner = (c - amplitude).max(0.0).sqrt();
+        let outer = (c + amplitude).sqrt();
+        let offset = p_12.norm();
+
+        let r_tool = kin.tool.rotation.to_rotation_matrix().into_inner();
+        Some(ReachEnvelope {
+            center: first_joint(kin),
+            point: -r_tool.transpose() * (kin.p.column(6) + kin.tool.translation.vector),
+            min_radius: (inner - offset).max(offset - outer).max(0.0),
+            max_radius: outer + offset,
+        })
+    }
+
+    /// The envelope of the tool center point over a grid of `samples` values of each joint after
+    /// the first. The extremes of the grid are widened by how far the point can move between grid
+    /// points, and clamped to the link length bounds.
+    pub fn sampled(kin: &Kinematics<6, 7>, samples: usize) -> Self {
+        let bounds = Self::link_lengths(kin);
+        let samples = samples.max(1);
+        let step = TAU / samples as f64;
+
+        let (mut min_radius, mut max_radius) = (f64::INFINITY, 0.0_f64);
+        for index in 0..samples.pow(5) {
+            let q = Vector6::from_fn(|i, _| match i {
+                0 => 0.0,
+                _ => -PI + step * ((index / samples.pow(i as u32 - 1)) % samples) as f64,
+            });
+            let radius = (kin.forward_kinematics(&q).1 - bounds.center).norm();
+            min_radius = min_radius.min(radius);
+            max_radius = max_radius.max(radius);
+        }
+
+        // Joint i moves the point by at most its distance from the axis times the angle
+        let tool = kin.tool.translation.vector.norm();
+        let levers = (1..6)
+            .map(|i| tool + (i + 1..7).map(|j| kin.p.column(j).norm()).sum::<f64>())
+            .sum::<f64>();
+        let margin = step / 2.0 * levers;
+
+        ReachEnvelope {
+            min_radius: (min_radius - margin).max(bounds.min_radius),
+            max_radius: (max_radius + margin).min(bounds.max_radius),
+            ..bounds
+        }
+    }
+
+    /// Whether the tool pose `(rot, translation)` puts the bounded point inside the envelope
+    pub fn contains(&self, rot: &Matrix3<f64>, translation: &Vector3<f64>) -> bool {
+        let radius = (translation + rot * self.point - self.center).norm();
+        self.min_radius - REACH_TOLERANCE <= radius && radius <= self.max_radius + REACH_TOLERANCE
+    }
+}
+
+/// A quick check that the tool pose `(rot, translation)` may be reachable by `kin`, to skip the
+/// solve of clearly infeasible targets. A pose it rejects is out of reach, but one it accepts may
+/// still be. Spherical wrists are checked against the envelope of their wrist center, other chains
+/// against the lengths of their links.
+pub fn is_pose_reachable_conservative(
+    kin: &Kinematics<6, 7>,
+    rot: &Matrix3<f64>,
+    translation: &Vector3<f64>,
+) -> bool {
+    ReachEnvelope::spherical_wrist(kin)
+        .unwrap_or_else(|| ReachEnvelope::link_lengths(kin))
+        .contains(rot, translation)
+}
+
+/// The origin of the first joint of `kin` in the world frame
+fn first_joint(kin: &Kinematics<6, 7>) -> Vector3<f64> {
+    (kin.base * Point3::from(kin.p.column(0).into_owned())).coords
+}